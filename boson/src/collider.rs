@@ -0,0 +1,389 @@
+use std::sync::Arc;
+
+use cgmath::{InnerSpace, Vector3, Zero};
+
+use crate::TriMesh;
+
+/// Describes how the collider of a body is created.
+///
+/// The primitive builders create unit sized shapes centered on the body, and
+/// the hull and mesh builders shapes around the body's origin. The model
+/// builders wait until the body is bound to a model and derive the shape and
+/// center of mass offset from the model's geometry instead.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColliderBuilder {
+    /// A sphere with a radius of 1
+    Sphere,
+    /// An infinite plane through the body facing +Y
+    Plane,
+    /// A cube with an edge length of 2
+    Cube,
+    /// A box fit to the axis aligned bounds of the model
+    FromModelAabb,
+    /// A convex hull around the vertices of the model
+    FromModelConvexHull,
+    /// The triangles of the model, for static level geometry
+    FromModelTriMesh,
+    /// A convex hull around points in the body's local space
+    ConvexHull(Vec<[f32; 3]>),
+    /// Triangles in the body's local space, for static level geometry
+    TriMesh(Arc<TriMesh>),
+}
+
+impl ColliderBuilder {
+    /// Returns whether this builder needs the geometry of a model
+    pub fn needs_model_geometry(&self) -> bool {
+        matches!(
+            self,
+            Self::FromModelAabb | Self::FromModelConvexHull | Self::FromModelTriMesh
+        )
+    }
+
+    /// Returns whether this builder needs the triangles of a model rather
+    /// than the points around it
+    pub fn needs_model_triangles(&self) -> bool {
+        matches!(self, Self::FromModelTriMesh)
+    }
+
+    /// Builds the collider for shapes that do not depend on a model.
+    ///
+    /// # Returns
+    /// The collider, or `Collider::Empty` for the model builders
+    pub fn build(&self) -> Collider {
+        match self {
+            Self::Sphere => Collider::Sphere { radius: 1.0 },
+            Self::Plane => Collider::Plane {
+                normal: Vector3::unit_y(),
+                distance: 0.0,
+            },
+            Self::Cube => Collider::Box {
+                half_extents: Vector3::new(1.0, 1.0, 1.0),
+            },
+            Self::ConvexHull(points) => Collider::ConvexHull {
+                points: points
+                    .iter()
+                    .map(|point| Vector3::new(point[0] as f64, point[1] as f64, point[2] as f64))
+                    .collect(),
+            },
+            Self::TriMesh(mesh) => Collider::TriMesh { mesh: mesh.clone() },
+            Self::FromModelAabb | Self::FromModelConvexHull | Self::FromModelTriMesh => {
+                Collider::Empty
+            }
+        }
+    }
+
+    /// Builds the collider from points of a model in its local space.
+    ///
+    /// The model's vertices are never modified, instead the center of the
+    /// shape is returned so the body can use it as its center of mass.
+    ///
+    /// # Arguments
+    /// * `points` - Points on the surface of the model, only the convex hull
+    ///   of the points matters
+    ///
+    /// # Returns
+    /// The collider centered on the origin and the offset of its center from
+    /// the model's origin
+    pub fn build_from_points(&self, points: &[[f32; 3]]) -> (Collider, Vector3<f64>) {
+        if points.is_empty() {
+            return (self.build(), Vector3::zero());
+        }
+
+        let mut min = Vector3::new(f64::MAX, f64::MAX, f64::MAX);
+        let mut max = Vector3::new(f64::MIN, f64::MIN, f64::MIN);
+
+        for point in points {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(point[axis] as f64);
+                max[axis] = max[axis].max(point[axis] as f64);
+            }
+        }
+
+        let center = (min + max) * 0.5;
+
+        match self {
+            Self::FromModelAabb => (
+                Collider::Box {
+                    half_extents: (max - min) * 0.5,
+                },
+                center,
+            ),
+            Self::FromModelConvexHull => (
+                Collider::ConvexHull {
+                    points: points
+                        .iter()
+                        .map(|point| {
+                            Vector3::new(point[0] as f64, point[1] as f64, point[2] as f64) - center
+                        })
+                        .collect(),
+                },
+                center,
+            ),
+            _ => (self.build(), Vector3::zero()),
+        }
+    }
+
+    /// Builds the collider from the triangles of a model in its local space.
+    ///
+    /// Only `FromModelTriMesh` uses the triangles, the other builders are
+    /// built from the positions like `build_from_points`.
+    ///
+    /// # Arguments
+    /// * `positions` - The vertices of the model
+    /// * `indices` - Three indices into `positions` per triangle
+    ///
+    /// # Returns
+    /// The collider centered on the origin and the offset of its center from
+    /// the model's origin
+    pub fn build_from_mesh(
+        &self,
+        positions: &[[f32; 3]],
+        indices: &[u32],
+    ) -> (Collider, Vector3<f64>) {
+        if !self.needs_model_triangles() {
+            return self.build_from_points(positions);
+        }
+
+        // The mesh is centered on its bounds the same as the other model builders
+        let (_, center) = Self::FromModelAabb.build_from_points(positions);
+        let mesh = TriMesh::new(positions, indices).translated(-center);
+
+        (
+            Collider::TriMesh {
+                mesh: Arc::new(mesh),
+            },
+            center,
+        )
+    }
+}
+
+/// The shape of a body in its local space, centered on its center of mass.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Collider {
+    /// No shape yet, the body does not collide
+    Empty,
+    Sphere {
+        radius: f64,
+    },
+    Plane {
+        normal: Vector3<f64>,
+        distance: f64,
+    },
+    Box {
+        half_extents: Vector3<f64>,
+    },
+    /// The convex hull of a set of points
+    ConvexHull {
+        points: Vec<Vector3<f64>>,
+    },
+    /// Triangles with no inside, for static level geometry
+    TriMesh {
+        mesh: Arc<TriMesh>,
+    },
+}
+
+impl Collider {
+    /// Returns the point of the shape furthest along `direction` in local space.
+    ///
+    /// Planes are unbounded and return the point of the plane closest to the origin.
+    pub fn support(&self, direction: Vector3<f64>) -> Vector3<f64> {
+        match self {
+            Self::Empty => Vector3::zero(),
+            Self::Sphere { radius } => {
+                if direction.magnitude2() == 0.0 {
+                    Vector3::zero()
+                } else {
+                    direction.normalize_to(*radius)
+                }
+            }
+            Self::Plane { normal, distance } => normal.normalize() * *distance,
+            Self::Box { half_extents } => Vector3::new(
+                half_extents.x.copysign(direction.x),
+                half_extents.y.copysign(direction.y),
+                half_extents.z.copysign(direction.z),
+            ),
+            Self::ConvexHull { points } => points
+                .iter()
+                .copied()
+                .max_by(|a, b| a.dot(direction).total_cmp(&b.dot(direction)))
+                .unwrap_or_else(Vector3::zero),
+            Self::TriMesh { mesh } => mesh
+                .vertices()
+                .iter()
+                .copied()
+                .max_by(|a, b| a.dot(direction).total_cmp(&b.dot(direction)))
+                .unwrap_or_else(Vector3::zero),
+        }
+    }
+
+    /// Finds where a ray in local space first enters the shape.
+    ///
+    /// Convex hulls are approximated by the box around their points, triangle
+    /// meshes are hit from either side of each triangle.
+    ///
+    /// # Arguments
+    /// * `origin` - Start of the ray in local space
+    /// * `direction` - Direction of the ray, the result is in multiples of its length
+    ///
+    /// # Returns
+    /// How far along the ray the shape is entered, 0 if the ray starts inside
+    /// of it, or `None` if the ray misses
+    pub fn ray_distance(&self, origin: Vector3<f64>, direction: Vector3<f64>) -> Option<f64> {
+        self.ray_hit(origin, direction).map(|(t, _normal)| t)
+    }
+
+    /// Finds where a ray in local space first enters the shape and the
+    /// surface normal there.
+    ///
+    /// Convex hulls are approximated by the box around their points, triangle
+    /// meshes are hit from either side of each triangle.
+    ///
+    /// # Arguments
+    /// * `origin` - Start of the ray in local space
+    /// * `direction` - Direction of the ray, the result is in multiples of its length
+    ///
+    /// # Returns
+    /// How far along the ray the shape is entered and the unit normal of the
+    /// surface in local space, or `None` if the ray misses. Rays that start
+    /// inside the shape hit it at 0 with the normal facing back along the ray.
+    pub fn ray_hit(
+        &self,
+        origin: Vector3<f64>,
+        direction: Vector3<f64>,
+    ) -> Option<(f64, Vector3<f64>)> {
+        let inside = || {
+            let normal = if direction.magnitude2() == 0.0 {
+                Vector3::zero()
+            } else {
+                -direction.normalize()
+            };
+
+            Some((0.0, normal))
+        };
+
+        match self {
+            Self::Empty => None,
+            Self::Sphere { radius } => {
+                let a = direction.magnitude2();
+                let b = origin.dot(direction);
+                let c = origin.magnitude2() - radius * radius;
+                if c <= 0.0 {
+                    return inside();
+                }
+
+                let discriminant = b * b - a * c;
+                if a == 0.0 || discriminant < 0.0 {
+                    return None;
+                }
+
+                let t = (-b - discriminant.sqrt()) / a;
+                (t >= 0.0).then(|| (t, (origin + direction * t).normalize()))
+            }
+            Self::Plane { normal, distance } => {
+                let normal = normal.normalize();
+                let height = origin.dot(normal) - distance;
+                if height <= 0.0 {
+                    return inside();
+                }
+
+                let speed = direction.dot(normal);
+                (speed < 0.0).then(|| (-height / speed, normal))
+            }
+            Self::Box { half_extents } => ray_box_hit(origin, direction, *half_extents),
+            Self::ConvexHull { .. } => {
+                let min = Vector3::new(
+                    self.support(-Vector3::unit_x()).x,
+                    self.support(-Vector3::unit_y()).y,
+                    self.support(-Vector3::unit_z()).z,
+                );
+                let max = Vector3::new(
+                    self.support(Vector3::unit_x()).x,
+                    self.support(Vector3::unit_y()).y,
+                    self.support(Vector3::unit_z()).z,
+                );
+                let center = (min + max) * 0.5;
+
+                ray_box_hit(origin - center, direction, (max - min) * 0.5)
+            }
+            Self::TriMesh { mesh } => mesh.ray_hit(origin, direction),
+        }
+    }
+
+    /// Returns the diagonal of the inertia tensor of the shape for a given mass.
+    ///
+    /// Convex hulls and triangle meshes are approximated by the box around
+    /// their points.
+    pub fn inertia(&self, mass: f64) -> Vector3<f64> {
+        match self {
+            Self::Empty | Self::Plane { .. } => Vector3::new(mass, mass, mass),
+            Self::Sphere { radius } => {
+                let inertia = 2.0 / 5.0 * mass * radius * radius;
+                Vector3::new(inertia, inertia, inertia)
+            }
+            Self::Box { half_extents } => box_inertia(*half_extents, mass),
+            Self::ConvexHull { .. } | Self::TriMesh { .. } => {
+                let half_extents = Vector3::new(
+                    self.support(Vector3::unit_x()).x - self.support(-Vector3::unit_x()).x,
+                    self.support(Vector3::unit_y()).y - self.support(-Vector3::unit_y()).y,
+                    self.support(Vector3::unit_z()).z - self.support(-Vector3::unit_z()).z,
+                ) * 0.5;
+
+                box_inertia(half_extents, mass)
+            }
+        }
+    }
+}
+
+/// Slab test of a ray against a box centered on the origin, the normal is
+/// the face of the slab the ray enters last
+fn ray_box_hit(
+    origin: Vector3<f64>,
+    direction: Vector3<f64>,
+    half_extents: Vector3<f64>,
+) -> Option<(f64, Vector3<f64>)> {
+    let mut t_enter: f64 = 0.0;
+    let mut t_exit = f64::INFINITY;
+    let mut normal = None;
+
+    for axis in 0..3 {
+        if direction[axis] == 0.0 {
+            if origin[axis].abs() > half_extents[axis] {
+                return None;
+            }
+            continue;
+        }
+
+        let t_a = (-half_extents[axis] - origin[axis]) / direction[axis];
+        let t_b = (half_extents[axis] - origin[axis]) / direction[axis];
+        if t_a.min(t_b) > t_enter {
+            t_enter = t_a.min(t_b);
+
+            let mut face = Vector3::zero();
+            face[axis] = -direction[axis].signum();
+            normal = Some(face);
+        }
+        t_exit = t_exit.min(t_a.max(t_b));
+
+        if t_enter > t_exit {
+            return None;
+        }
+    }
+
+    // Rays that start inside the box never cross a face on the way in
+    let normal = normal.unwrap_or_else(|| {
+        if direction.magnitude2() == 0.0 {
+            Vector3::zero()
+        } else {
+            -direction.normalize()
+        }
+    });
+
+    Some((t_enter, normal))
+}
+
+fn box_inertia(half_extents: Vector3<f64>, mass: f64) -> Vector3<f64> {
+    let size = half_extents * 2.0;
+    let (x2, y2, z2) = (size.x * size.x, size.y * size.y, size.z * size.z);
+
+    Vector3::new(y2 + z2, x2 + z2, x2 + y2) * (mass / 12.0)
+}
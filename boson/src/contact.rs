@@ -0,0 +1,791 @@
+use std::sync::Arc;
+
+use cgmath::{InnerSpace, Matrix, Matrix3, Vector3};
+
+use crate::{
+    Collider, RigidBody, TriMesh,
+    gjk::{Core, Gjk, gjk, penetration},
+};
+
+/// How far below the furthest point of a shape its other corners can be and
+/// still count as part of the face touching another shape
+const FEATURE_TOLERANCE: f64 = 0.01;
+
+/// A point where two bodies touch, found by the narrowphase.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ContactPoint {
+    /// Direction from the first body to the second in world space
+    pub(crate) normal: Vector3<f64>,
+    /// Middle of the overlap between the bodies in world space
+    pub(crate) point: Vector3<f64>,
+    /// How far the bodies overlap along the normal
+    pub(crate) depth: f64,
+    /// Identifies the feature that made the contact so it can be matched across steps
+    pub(crate) feature: u32,
+}
+
+/// A collider placed in the world.
+pub(crate) enum WorldShape {
+    Sphere {
+        center: Vector3<f64>,
+        radius: f64,
+    },
+    Plane {
+        normal: Vector3<f64>,
+        distance: f64,
+    },
+    Box {
+        center: Vector3<f64>,
+        axes: Matrix3<f64>,
+        half_extents: Vector3<f64>,
+    },
+    Hull {
+        center: Vector3<f64>,
+        /// The points of the hull in world space
+        points: Vec<Vector3<f64>>,
+        radius: f64,
+    },
+    TriMesh {
+        position: Vector3<f64>,
+        axes: Matrix3<f64>,
+        mesh: Arc<TriMesh>,
+    },
+}
+
+impl WorldShape {
+    pub(crate) fn from_body(body: &RigidBody) -> Option<Self> {
+        let rotation = Matrix3::from(body.orientation);
+
+        match body.collider() {
+            Collider::Empty => None,
+            Collider::Sphere { radius } => Some(Self::Sphere {
+                center: body.position,
+                radius: *radius,
+            }),
+            Collider::Plane { normal, distance } => {
+                let normal = rotation * normal.normalize();
+
+                Some(Self::Plane {
+                    normal,
+                    distance: normal.dot(body.position) + distance,
+                })
+            }
+            Collider::Box { half_extents } => Some(Self::Box {
+                center: body.position,
+                axes: rotation,
+                half_extents: *half_extents,
+            }),
+            Collider::ConvexHull { points } => Some(Self::Hull {
+                center: body.position,
+                points: points
+                    .iter()
+                    .map(|point| body.position + rotation * point)
+                    .collect(),
+                radius: points
+                    .iter()
+                    .map(|point| point.magnitude())
+                    .fold(0.0, f64::max),
+            }),
+            Collider::TriMesh { mesh } => Some(Self::TriMesh {
+                position: body.position,
+                axes: rotation,
+                mesh: mesh.clone(),
+            }),
+        }
+    }
+
+    /// Radius of a sphere around the shape, infinite for planes
+    pub(crate) fn bounding_radius(&self) -> f64 {
+        match self {
+            Self::Sphere { radius, .. } => *radius,
+            Self::Plane { .. } => f64::INFINITY,
+            Self::Box { half_extents, .. } => half_extents.magnitude(),
+            Self::Hull { radius, .. } => *radius,
+            Self::TriMesh { mesh, .. } => mesh.bounding_radius(),
+        }
+    }
+
+    pub(crate) fn center(&self) -> Vector3<f64> {
+        match self {
+            Self::Sphere { center, .. } | Self::Box { center, .. } | Self::Hull { center, .. } => {
+                *center
+            }
+            Self::Plane { normal, distance } => normal * *distance,
+            Self::TriMesh { position, .. } => *position,
+        }
+    }
+
+    /// Returns the shape as a GJK core and the radius rounding it off, or
+    /// `None` for planes and triangle meshes, which aren't convex shapes
+    pub(crate) fn core(&self) -> Option<(Core, f64)> {
+        match self {
+            Self::Sphere { center, radius } => Some((Core::Point(*center), *radius)),
+            Self::Box {
+                center,
+                axes,
+                half_extents,
+            } => Some((
+                Core::Box {
+                    center: *center,
+                    axes: *axes,
+                    half_extents: *half_extents,
+                },
+                0.0,
+            )),
+            Self::Hull { points, .. } => Some((Core::Hull(points.clone()), 0.0)),
+            Self::Plane { .. } | Self::TriMesh { .. } => None,
+        }
+    }
+}
+
+/// Returns whether the bounding spheres of two bodies overlap.
+///
+/// Used as the broadphase, pairs that fail this test are never passed to the narrowphase.
+pub(crate) fn bounds_overlap(a: &RigidBody, b: &RigidBody) -> bool {
+    let (Some(shape_a), Some(shape_b)) = (WorldShape::from_body(a), WorldShape::from_body(b))
+    else {
+        return false;
+    };
+
+    let radius = shape_a.bounding_radius() + shape_b.bounding_radius();
+
+    radius.is_infinite() || (shape_b.center() - shape_a.center()).magnitude2() <= radius * radius
+}
+
+/// Finds the points where two bodies touch.
+///
+/// # Arguments
+/// * `a` - The first body, contact normals point away from it
+/// * `b` - The second body
+/// * `contacts` - Receives the contact points
+pub(crate) fn collide(a: &RigidBody, b: &RigidBody, contacts: &mut Vec<ContactPoint>) {
+    let (Some(shape_a), Some(shape_b)) = (WorldShape::from_body(a), WorldShape::from_body(b))
+    else {
+        return;
+    };
+
+    collide_shapes(&shape_a, &shape_b, contacts);
+}
+
+fn collide_shapes(a: &WorldShape, b: &WorldShape, contacts: &mut Vec<ContactPoint>) {
+    let start = contacts.len();
+
+    match (a, b) {
+        (
+            WorldShape::Sphere {
+                center: center_a,
+                radius: radius_a,
+            },
+            WorldShape::Sphere {
+                center: center_b,
+                radius: radius_b,
+            },
+        ) => {
+            let offset = center_b - center_a;
+            let distance = offset.magnitude();
+
+            if distance < radius_a + radius_b {
+                let normal = if distance > 0.0 {
+                    offset / distance
+                } else {
+                    Vector3::unit_y()
+                };
+
+                contacts.push(ContactPoint {
+                    normal,
+                    point: center_a + normal * (radius_a - (radius_a + radius_b - distance) * 0.5),
+                    depth: radius_a + radius_b - distance,
+                    feature: 0,
+                });
+            }
+        }
+        (WorldShape::Plane { normal, distance }, WorldShape::Sphere { center, radius }) => {
+            let height = normal.dot(*center) - distance;
+
+            if height < *radius {
+                contacts.push(ContactPoint {
+                    normal: *normal,
+                    point: center - normal * ((radius + height) * 0.5),
+                    depth: radius - height,
+                    feature: 0,
+                });
+            }
+        }
+        (
+            WorldShape::Plane { normal, distance },
+            WorldShape::Box {
+                center,
+                axes,
+                half_extents,
+            },
+        ) => {
+            for (index, corner) in box_corners(*center, axes, *half_extents)
+                .into_iter()
+                .enumerate()
+            {
+                let height = normal.dot(corner) - distance;
+
+                if height < 0.0 {
+                    contacts.push(ContactPoint {
+                        normal: *normal,
+                        point: corner - normal * (height * 0.5),
+                        depth: -height,
+                        feature: index as u32,
+                    });
+                }
+            }
+        }
+        (
+            WorldShape::Sphere { center, radius },
+            WorldShape::Box {
+                center: box_center,
+                axes,
+                half_extents,
+            },
+        ) => {
+            let local = to_local(*center - box_center, axes);
+            let closest = Vector3::new(
+                local.x.clamp(-half_extents.x, half_extents.x),
+                local.y.clamp(-half_extents.y, half_extents.y),
+                local.z.clamp(-half_extents.z, half_extents.z),
+            );
+
+            if closest == local {
+                // The center is inside the box, push out through the nearest face
+                let (axis, depth, sign) = nearest_face(local, *half_extents);
+                let normal = -(axes[axis] * sign);
+                let face = center - normal * depth;
+
+                contacts.push(ContactPoint {
+                    normal,
+                    point: (face + center - normal * *radius) * 0.5,
+                    depth: depth + radius,
+                    feature: 0,
+                });
+            } else {
+                let offset = closest - local;
+                let distance = offset.magnitude();
+
+                if distance < *radius {
+                    let normal = axes * (offset / distance);
+
+                    contacts.push(ContactPoint {
+                        normal,
+                        point: box_center + axes * closest + normal * ((radius - distance) * 0.5),
+                        depth: radius - distance,
+                        feature: 0,
+                    });
+                }
+            }
+        }
+        (
+            WorldShape::Box {
+                center: center_a,
+                axes: axes_a,
+                half_extents: half_a,
+            },
+            WorldShape::Box {
+                center: center_b,
+                axes: axes_b,
+                half_extents: half_b,
+            },
+        ) => {
+            collide_boxes(
+                (*center_a, axes_a, *half_a),
+                (*center_b, axes_b, *half_b),
+                contacts,
+            );
+        }
+        (WorldShape::Plane { normal, distance }, WorldShape::Hull { points, .. }) => {
+            for (index, point) in points.iter().enumerate() {
+                let height = normal.dot(*point) - distance;
+
+                if height < 0.0 {
+                    contacts.push(ContactPoint {
+                        normal: *normal,
+                        point: point - normal * (height * 0.5),
+                        depth: -height,
+                        feature: index as u32,
+                    });
+                }
+            }
+        }
+        (WorldShape::Plane { .. }, WorldShape::Plane { .. }) => {}
+        (WorldShape::TriMesh { .. }, WorldShape::TriMesh { .. } | WorldShape::Plane { .. }) => {}
+        (
+            WorldShape::TriMesh {
+                position,
+                axes,
+                mesh,
+            },
+            other,
+        ) => collide_trimesh((*position, axes, mesh), other, contacts),
+        (
+            WorldShape::Hull { .. },
+            WorldShape::Sphere { .. } | WorldShape::Box { .. } | WorldShape::Hull { .. },
+        )
+        | (WorldShape::Sphere { .. } | WorldShape::Box { .. }, WorldShape::Hull { .. }) => {
+            if let (Some((core_a, radius_a)), Some((core_b, radius_b))) = (a.core(), b.core()) {
+                collide_convex((&core_a, radius_a), (&core_b, radius_b), 0, contacts);
+            }
+        }
+        // Every other pair is one of the above with the bodies swapped
+        _ => {
+            collide_shapes(b, a, contacts);
+
+            for contact in contacts[start..].iter_mut() {
+                contact.normal = -contact.normal;
+            }
+        }
+    }
+}
+
+/// Finds the contacts between two convex shapes with GJK, or EPA once they
+/// overlap.
+///
+/// Rounded shapes touch at a single point. Shapes with corners touch at the
+/// corners of the smaller of the two touching faces, kept within the larger
+/// one, so resting faces get a contact at every corner.
+///
+/// # Arguments
+/// * `a` - The first shape and its radius, contact normals point away from it
+/// * `b` - The second shape and its radius
+/// * `feature` - Identifies the pair of shapes among the contacts of two bodies
+/// * `contacts` - Receives the contact points
+fn collide_convex(
+    (a, radius_a): (&Core, f64),
+    (b, radius_b): (&Core, f64),
+    feature: u32,
+    contacts: &mut Vec<ContactPoint>,
+) {
+    let radius = radius_a + radius_b;
+    let feature = feature << 16;
+
+    let penetration = match gjk(a, b) {
+        Gjk::Separated(point_a, point_b) => {
+            let offset = point_b - point_a;
+            let distance = offset.magnitude();
+
+            if distance > 0.0 && distance < radius {
+                let normal = offset / distance;
+
+                contacts.push(ContactPoint {
+                    normal,
+                    point: (point_a + normal * radius_a + point_b - normal * radius_b) * 0.5,
+                    depth: radius - distance,
+                    feature,
+                });
+            }
+
+            return;
+        }
+        Gjk::Overlapping(simplex) => match penetration(a, b, simplex) {
+            Some(penetration) => penetration,
+            None => return,
+        },
+    };
+
+    let normal = penetration.normal;
+    let single = ContactPoint {
+        normal,
+        point: (penetration.point_a + normal * radius_a + penetration.point_b - normal * radius_b)
+            * 0.5,
+        depth: penetration.depth + radius,
+        feature,
+    };
+
+    if radius > 0.0 {
+        contacts.push(single);
+        return;
+    }
+
+    // The corners of each shape in its face towards the other
+    let face = |core: &Core, direction: Vector3<f64>| {
+        let extent = core.support(direction).dot(direction);
+
+        core.vertices()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, vertex)| vertex.dot(direction) >= extent - FEATURE_TOLERANCE)
+            .collect::<Vec<_>>()
+    };
+    let face_a = face(a, normal);
+    let face_b = face(b, -normal);
+
+    // The corners of the smaller face poke into the larger one
+    let (incident, reference, incident_is_b) = if face_b.len() <= face_a.len() {
+        (face_b, face_a, true)
+    } else {
+        (face_a, face_b, false)
+    };
+
+    // Corners and crossing edges touch at the single deepest point
+    if incident.len() < 2 || reference.len() < 3 {
+        contacts.push(single);
+        return;
+    }
+
+    let tangent = if normal.x.abs() < 0.57 {
+        normal.cross(Vector3::unit_x()).normalize()
+    } else {
+        normal.cross(Vector3::unit_y()).normalize()
+    };
+    let bitangent = normal.cross(tangent);
+    let extent = |axis: Vector3<f64>| {
+        reference
+            .iter()
+            .map(|(_, vertex)| vertex.dot(axis))
+            .fold((f64::MAX, f64::MIN), |(min, max), along| {
+                (min.min(along), max.max(along))
+            })
+    };
+    let (tangent_range, bitangent_range) = (extent(tangent), extent(bitangent));
+
+    let reference_plane = if incident_is_b {
+        a.support(normal).dot(normal)
+    } else {
+        b.support(-normal).dot(normal)
+    };
+
+    let start = contacts.len();
+    for (index, vertex) in incident {
+        // Keep the corner within the reference face
+        let along_tangent = vertex.dot(tangent);
+        let along_bitangent = vertex.dot(bitangent);
+        let vertex = vertex
+            + tangent * (along_tangent.clamp(tangent_range.0, tangent_range.1) - along_tangent)
+            + bitangent
+                * (along_bitangent.clamp(bitangent_range.0, bitangent_range.1) - along_bitangent);
+
+        let depth = if incident_is_b {
+            reference_plane - normal.dot(vertex)
+        } else {
+            normal.dot(vertex) - reference_plane
+        };
+
+        let duplicate = contacts[start..]
+            .iter()
+            .any(|contact| (contact.point - vertex).magnitude2() <= 1e-12);
+        if depth < 0.0 || duplicate {
+            continue;
+        }
+
+        let point = if incident_is_b {
+            vertex + normal * (depth * 0.5)
+        } else {
+            vertex - normal * (depth * 0.5)
+        };
+
+        contacts.push(ContactPoint {
+            normal,
+            point,
+            depth,
+            feature: feature | ((incident_is_b as u32) << 15) | (index as u32 & 0x7fff),
+        });
+    }
+
+    if contacts.len() == start {
+        contacts.push(single);
+    }
+}
+
+/// Finds the contacts between a triangle mesh and a convex shape, colliding
+/// the shape with every triangle near it
+fn collide_trimesh(
+    (position, axes, mesh): (Vector3<f64>, &Matrix3<f64>, &TriMesh),
+    other: &WorldShape,
+    contacts: &mut Vec<ContactPoint>,
+) {
+    let Some((core, radius)) = other.core() else {
+        return;
+    };
+
+    // Compare the triangles to the other shape in the local space of the mesh
+    let local_center = axes.transpose() * (other.center() - position);
+    let reach = other.bounding_radius();
+
+    for (index, triangle) in mesh.iter_triangles().enumerate() {
+        let (min, max) = triangle.iter().fold(
+            (
+                Vector3::new(f64::MAX, f64::MAX, f64::MAX),
+                Vector3::new(f64::MIN, f64::MIN, f64::MIN),
+            ),
+            |(min, max), corner| {
+                (
+                    Vector3::new(
+                        min.x.min(corner.x),
+                        min.y.min(corner.y),
+                        min.z.min(corner.z),
+                    ),
+                    Vector3::new(
+                        max.x.max(corner.x),
+                        max.y.max(corner.y),
+                        max.z.max(corner.z),
+                    ),
+                )
+            },
+        );
+        let closest = Vector3::new(
+            local_center.x.clamp(min.x, max.x),
+            local_center.y.clamp(min.y, max.y),
+            local_center.z.clamp(min.z, max.z),
+        );
+        if (closest - local_center).magnitude2() > reach * reach {
+            continue;
+        }
+
+        let triangle = Core::Triangle(triangle.map(|corner| position + axes * corner));
+        collide_convex((&triangle, 0.0), (&core, radius), index as u32, contacts);
+    }
+}
+
+fn to_local(offset: Vector3<f64>, axes: &Matrix3<f64>) -> Vector3<f64> {
+    Vector3::new(axes.x.dot(offset), axes.y.dot(offset), axes.z.dot(offset))
+}
+
+fn box_corners(
+    center: Vector3<f64>,
+    axes: &Matrix3<f64>,
+    half_extents: Vector3<f64>,
+) -> [Vector3<f64>; 8] {
+    std::array::from_fn(|index| {
+        let sign = |bit: usize| if index & bit > 0 { 1.0 } else { -1.0 };
+
+        center
+            + axes.x * (half_extents.x * sign(0x01))
+            + axes.y * (half_extents.y * sign(0x02))
+            + axes.z * (half_extents.z * sign(0x04))
+    })
+}
+
+/// Returns the axis, depth and side of the face of a box nearest to a point inside it
+fn nearest_face(local: Vector3<f64>, half_extents: Vector3<f64>) -> (usize, f64, f64) {
+    (0..3)
+        .map(|axis| {
+            let sign = if local[axis] < 0.0 { -1.0 } else { 1.0 };
+            (axis, half_extents[axis] - local[axis].abs(), sign)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or((1, 0.0, 1.0))
+}
+
+/// A box as its center, axes and half extents
+type OrientedBox<'a> = (Vector3<f64>, &'a Matrix3<f64>, Vector3<f64>);
+
+/// Radius of a box projected onto an axis
+fn projected_radius(axes: &Matrix3<f64>, half_extents: Vector3<f64>, axis: Vector3<f64>) -> f64 {
+    (0..3)
+        .map(|index| half_extents[index] * axes[index].dot(axis).abs())
+        .sum()
+}
+
+/// Finds the contacts between two boxes with the separating axis test.
+///
+/// The axis of least overlap picks the contact normal. Face axes clip the
+/// face of the other box most facing the normal against the reference face,
+/// giving up to eight points for resting contacts. Edge axes give a single
+/// point between the closest points of the two edges.
+fn collide_boxes(a: OrientedBox, b: OrientedBox, contacts: &mut Vec<ContactPoint>) {
+    let (center_a, axes_a, half_a) = a;
+    let (center_b, axes_b, half_b) = b;
+    let offset = center_b - center_a;
+
+    // Face axes are preferred unless an edge axis is clearly better, which
+    // keeps resting contacts from flickering between the two
+    const EDGE_BIAS: f64 = 0.95;
+    const EDGE_TOLERANCE: f64 = 0.001;
+
+    let mut best: Option<(f64, Vector3<f64>, u32)> = None;
+
+    let face_axes = (0..3)
+        .map(|index| axes_a[index])
+        .chain((0..3).map(|index| axes_b[index]));
+    let edge_axes = (0..3).flat_map(|i| (0..3).map(move |j| (i, j)));
+
+    for (index, axis) in face_axes.enumerate() {
+        let overlap = projected_radius(axes_a, half_a, axis)
+            + projected_radius(axes_b, half_b, axis)
+            - offset.dot(axis).abs();
+
+        if overlap < 0.0 {
+            return;
+        }
+
+        if best.is_none_or(|(best_overlap, _, _)| overlap < best_overlap) {
+            best = Some((overlap, axis, index as u32));
+        }
+    }
+
+    for (i, j) in edge_axes {
+        let axis = axes_a[i].cross(axes_b[j]);
+        let length = axis.magnitude();
+
+        // Parallel edges are already covered by the face axes
+        if length < 1e-6 {
+            continue;
+        }
+
+        let axis = axis / length;
+        let overlap = projected_radius(axes_a, half_a, axis)
+            + projected_radius(axes_b, half_b, axis)
+            - offset.dot(axis).abs();
+
+        if overlap < 0.0 {
+            return;
+        }
+
+        if best
+            .is_none_or(|(best_overlap, _, _)| overlap < best_overlap * EDGE_BIAS - EDGE_TOLERANCE)
+        {
+            best = Some((overlap, axis, 6 + (i * 3 + j) as u32));
+        }
+    }
+
+    let Some((depth, axis, index)) = best else {
+        return;
+    };
+
+    // Point the normal from a to b
+    let normal = if offset.dot(axis) < 0.0 { -axis } else { axis };
+
+    if index >= 6 {
+        // Edge to edge, find the edge of each box furthest along the normal
+        let (i, j) = ((index as usize - 6) / 3, (index as usize - 6) % 3);
+        let edge_center = |center: Vector3<f64>,
+                           axes: &Matrix3<f64>,
+                           half: Vector3<f64>,
+                           edge: usize,
+                           direction: Vector3<f64>| {
+            (0..3).filter(|&k| k != edge).fold(center, |point, k| {
+                point + axes[k] * half[k].copysign(axes[k].dot(direction))
+            })
+        };
+
+        let point_a = edge_center(center_a, axes_a, half_a, i, normal);
+        let point_b = edge_center(center_b, axes_b, half_b, j, -normal);
+        let (closest_a, closest_b) =
+            closest_points_on_lines(point_a, axes_a[i], half_a[i], point_b, axes_b[j], half_b[j]);
+
+        contacts.push(ContactPoint {
+            normal,
+            point: (closest_a + closest_b) * 0.5,
+            depth,
+            feature: 64 + index,
+        });
+
+        return;
+    }
+
+    // The reference face belongs to the box whose axis was picked
+    let (reference, incident, reference_normal, flip) = if index < 3 {
+        (a, b, normal, false)
+    } else {
+        (b, a, -normal, true)
+    };
+    let (reference_center, reference_axes, reference_half) = reference;
+    let (incident_center, incident_axes, incident_half) = incident;
+    let reference_axis = index as usize % 3;
+
+    // The incident face is the face of the other box most facing the reference face
+    let incident_axis = (0..3)
+        .max_by(|&x, &y| {
+            incident_axes[x]
+                .dot(reference_normal)
+                .abs()
+                .total_cmp(&incident_axes[y].dot(reference_normal).abs())
+        })
+        .unwrap_or(0);
+    let incident_normal =
+        incident_axes[incident_axis] * -incident_axes[incident_axis].dot(reference_normal).signum();
+    let incident_face_center = incident_center + incident_normal * incident_half[incident_axis];
+    let (u, v) = ((incident_axis + 1) % 3, (incident_axis + 2) % 3);
+    let mut polygon = [(1.0, 1.0), (-1.0, 1.0), (-1.0, -1.0), (1.0, -1.0)]
+        .map(|(x, y): (f64, f64)| {
+            incident_face_center
+                + incident_axes[u] * (incident_half[u] * x)
+                + incident_axes[v] * (incident_half[v] * y)
+        })
+        .to_vec();
+
+    // Clip the incident face against the sides of the reference face
+    for side in (0..3).filter(|&side| side != reference_axis) {
+        for sign in [1.0, -1.0] {
+            let plane_normal = reference_axes[side] * sign;
+            let plane_distance = plane_normal.dot(reference_center) + reference_half[side];
+
+            polygon = clip_polygon(&polygon, plane_normal, plane_distance);
+        }
+    }
+
+    let face_distance = reference_normal.dot(reference_center) + reference_half[reference_axis];
+
+    for (point_index, point) in polygon.into_iter().enumerate() {
+        let depth = face_distance - reference_normal.dot(point);
+
+        if depth >= 0.0 {
+            contacts.push(ContactPoint {
+                normal: if flip {
+                    -reference_normal
+                } else {
+                    reference_normal
+                },
+                point: point + reference_normal * (depth * 0.5),
+                depth,
+                feature: (index << 4) | point_index as u32,
+            });
+        }
+    }
+}
+
+/// Keeps the part of a polygon behind a plane
+fn clip_polygon(
+    polygon: &[Vector3<f64>],
+    normal: Vector3<f64>,
+    distance: f64,
+) -> Vec<Vector3<f64>> {
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+
+    for (index, start) in polygon.iter().enumerate() {
+        let end = polygon[(index + 1) % polygon.len()];
+        let start_distance = normal.dot(*start) - distance;
+        let end_distance = normal.dot(end) - distance;
+
+        if start_distance <= 0.0 {
+            clipped.push(*start);
+        }
+
+        // The edge crosses the plane
+        if (start_distance <= 0.0) != (end_distance <= 0.0) {
+            let t = start_distance / (start_distance - end_distance);
+            clipped.push(start + (end - start) * t);
+        }
+    }
+
+    clipped
+}
+
+/// Returns the closest points between two segments given as centers,
+/// directions and half lengths
+fn closest_points_on_lines(
+    center_a: Vector3<f64>,
+    direction_a: Vector3<f64>,
+    half_a: f64,
+    center_b: Vector3<f64>,
+    direction_b: Vector3<f64>,
+    half_b: f64,
+) -> (Vector3<f64>, Vector3<f64>) {
+    let offset = center_b - center_a;
+    let alignment = direction_a.dot(direction_b);
+    let denominator = 1.0 - alignment * alignment;
+
+    let t_a = if denominator.abs() < 1e-9 {
+        0.0
+    } else {
+        ((direction_a.dot(offset) - alignment * direction_b.dot(offset)) / denominator)
+            .clamp(-half_a, half_a)
+    };
+    let point_a = center_a + direction_a * t_a;
+    let t_b = direction_b.dot(point_a - center_b).clamp(-half_b, half_b);
+
+    (point_a, center_b + direction_b * t_b)
+}
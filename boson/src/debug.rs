@@ -0,0 +1,175 @@
+use cgmath::{InnerSpace, Quaternion, Rotation, Vector3};
+
+use crate::{BosonBody, Collider};
+
+// Number of line segments used for each circle of a sphere
+const SPHERE_SEGMENTS: usize = 24;
+
+// Half the size of the square drawn for a plane
+const PLANE_HALF_SIZE: f64 = 10.0;
+
+// Half the size of the cross drawn for a point mass
+const POINT_HALF_SIZE: f64 = 0.1;
+
+const COLLIDER_COLOR: [f32; 4] = [0.0, 1.0, 1.0, 1.0];
+const VELOCITY_COLOR: [f32; 4] = [1.0, 1.0, 0.0, 1.0];
+
+/// Receives the debug geometry of the physics world.
+///
+/// Boson has no renderer of its own, whatever draws the lines (a GPU
+/// renderer, a test recording them, a headless logger) implements this trait
+/// and is passed to `Boson::debug_draw`.
+pub trait BosonDebugger {
+    /// Draws a line between two points in world space
+    fn draw_line(&mut self, start: Vector3<f64>, end: Vector3<f64>, color: [f32; 4]);
+}
+
+impl BosonBody {
+    /// Draws the collider and velocity of the body
+    pub fn debug_draw(&self, debugger: &mut dyn BosonDebugger) {
+        match self {
+            BosonBody::PointMass(point_mass) => {
+                for axis in [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()] {
+                    debugger.draw_line(
+                        point_mass.position - axis * POINT_HALF_SIZE,
+                        point_mass.position + axis * POINT_HALF_SIZE,
+                        COLLIDER_COLOR,
+                    );
+                }
+
+                debugger.draw_line(
+                    point_mass.position,
+                    point_mass.position + point_mass.velocity,
+                    VELOCITY_COLOR,
+                );
+            }
+            BosonBody::RigidBody(rigid_body) => {
+                draw_collider(
+                    rigid_body.collider(),
+                    rigid_body.position,
+                    rigid_body.orientation,
+                    debugger,
+                );
+
+                debugger.draw_line(
+                    rigid_body.position,
+                    rigid_body.position + rigid_body.velocity,
+                    VELOCITY_COLOR,
+                );
+            }
+            BosonBody::StaticCollider(_) => {}
+        }
+    }
+}
+
+fn draw_collider(
+    collider: &Collider,
+    position: Vector3<f64>,
+    orientation: Quaternion<f64>,
+    debugger: &mut dyn BosonDebugger,
+) {
+    let to_world = |point: Vector3<f64>| orientation.rotate_vector(point) + position;
+
+    match collider {
+        Collider::Empty => {}
+        Collider::Sphere { radius } => {
+            let axes = [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()];
+
+            for (u, v) in [(0, 1), (1, 2), (0, 2)] {
+                let point = |segment: usize| {
+                    let angle = segment as f64 / SPHERE_SEGMENTS as f64 * std::f64::consts::TAU;
+                    to_world((axes[u] * angle.cos() + axes[v] * angle.sin()) * *radius)
+                };
+
+                for segment in 0..SPHERE_SEGMENTS {
+                    debugger.draw_line(point(segment), point(segment + 1), COLLIDER_COLOR);
+                }
+            }
+        }
+        Collider::Plane { normal, distance } => {
+            let normal = normal.normalize();
+            let center = normal * *distance;
+
+            // Any vector not parallel to the normal gives the plane's tangents
+            let reference = if normal.x.abs() < 0.9 {
+                Vector3::unit_x()
+            } else {
+                Vector3::unit_y()
+            };
+            let tangent = normal.cross(reference).normalize() * PLANE_HALF_SIZE;
+            let bitangent = normal.cross(tangent);
+
+            let corners = [
+                center + tangent + bitangent,
+                center - tangent + bitangent,
+                center - tangent - bitangent,
+                center + tangent - bitangent,
+            ]
+            .map(to_world);
+
+            for index in 0..4 {
+                debugger.draw_line(corners[index], corners[(index + 1) % 4], COLLIDER_COLOR);
+            }
+            debugger.draw_line(to_world(center), to_world(center + normal), COLLIDER_COLOR);
+        }
+        Collider::Box { half_extents } => draw_box(
+            *half_extents,
+            Vector3::new(0.0, 0.0, 0.0),
+            to_world,
+            debugger,
+        ),
+        Collider::ConvexHull { .. } => {
+            // Hull edges are not stored, draw the box the hull fits in
+            let max = Vector3::new(
+                collider.support(Vector3::unit_x()).x,
+                collider.support(Vector3::unit_y()).y,
+                collider.support(Vector3::unit_z()).z,
+            );
+            let min = Vector3::new(
+                collider.support(-Vector3::unit_x()).x,
+                collider.support(-Vector3::unit_y()).y,
+                collider.support(-Vector3::unit_z()).z,
+            );
+
+            draw_box((max - min) * 0.5, (max + min) * 0.5, to_world, debugger);
+        }
+        Collider::TriMesh { mesh } => {
+            for [a, b, c] in mesh.iter_triangles() {
+                debugger.draw_line(to_world(a), to_world(b), COLLIDER_COLOR);
+                debugger.draw_line(to_world(b), to_world(c), COLLIDER_COLOR);
+                debugger.draw_line(to_world(c), to_world(a), COLLIDER_COLOR);
+            }
+        }
+    }
+}
+
+fn draw_box<T>(
+    half_extents: Vector3<f64>,
+    center: Vector3<f64>,
+    to_world: T,
+    debugger: &mut dyn BosonDebugger,
+) where
+    T: Fn(Vector3<f64>) -> Vector3<f64>,
+{
+    let corner = |index: usize| {
+        let sign = |bit: usize| if index & bit > 0 { 1.0 } else { -1.0 };
+
+        to_world(
+            center
+                + Vector3::new(
+                    half_extents.x * sign(0x01),
+                    half_extents.y * sign(0x02),
+                    half_extents.z * sign(0x04),
+                ),
+        )
+    };
+
+    // Every pair of corners that differ in exactly one axis is an edge
+    for start in 0..8 {
+        for bit in [0x01, 0x02, 0x04] {
+            if start & bit == 0 {
+                debugger.draw_line(corner(start), corner(start | bit), COLLIDER_COLOR);
+            }
+        }
+    }
+}
@@ -0,0 +1,492 @@
+use cgmath::{InnerSpace, Matrix3, Vector3, Zero};
+
+/// Most vertices GJK adds to its simplex before it stops refining
+const MAX_GJK_ITERATIONS: usize = 32;
+
+/// Most vertices EPA adds to the difference of two shapes before it stops
+const MAX_EPA_ITERATIONS: usize = 64;
+
+/// How close to the surface of the difference EPA has to get to stop early
+const EPA_TOLERANCE: f64 = 1e-6;
+
+/// A convex shape without a radius, which GJK finds the closest points of
+pub(crate) enum Core {
+    Point(Vector3<f64>),
+    Segment(Vector3<f64>, Vector3<f64>),
+    Box {
+        center: Vector3<f64>,
+        axes: Matrix3<f64>,
+        half_extents: Vector3<f64>,
+    },
+    /// The convex hull of a set of points
+    Hull(Vec<Vector3<f64>>),
+    Triangle([Vector3<f64>; 3]),
+}
+
+impl Core {
+    /// Returns the point of the shape furthest along `direction`
+    pub(crate) fn support(&self, direction: Vector3<f64>) -> Vector3<f64> {
+        match self {
+            Self::Point(point) => *point,
+            Self::Segment(start, end) => {
+                if direction.dot(end - start) > 0.0 {
+                    *end
+                } else {
+                    *start
+                }
+            }
+            Self::Box {
+                center,
+                axes,
+                half_extents,
+            } => {
+                let sign = |axis: Vector3<f64>| {
+                    if axis.dot(direction) >= 0.0 {
+                        1.0
+                    } else {
+                        -1.0
+                    }
+                };
+
+                center
+                    + axes.x * (half_extents.x * sign(axes.x))
+                    + axes.y * (half_extents.y * sign(axes.y))
+                    + axes.z * (half_extents.z * sign(axes.z))
+            }
+            Self::Hull(points) => furthest(points, direction),
+            Self::Triangle(corners) => furthest(corners, direction),
+        }
+    }
+
+    /// Returns the corners of the shape, where it touches other shapes
+    pub(crate) fn vertices(&self) -> Vec<Vector3<f64>> {
+        match self {
+            Self::Point(point) => vec![*point],
+            Self::Segment(start, end) => vec![*start, *end],
+            Self::Box {
+                center,
+                axes,
+                half_extents,
+            } => (0..8)
+                .map(|index| {
+                    let sign = |bit: usize| if index & bit > 0 { 1.0 } else { -1.0 };
+
+                    center
+                        + axes.x * (half_extents.x * sign(0x01))
+                        + axes.y * (half_extents.y * sign(0x02))
+                        + axes.z * (half_extents.z * sign(0x04))
+                })
+                .collect(),
+            Self::Hull(points) => points.clone(),
+            Self::Triangle(corners) => corners.to_vec(),
+        }
+    }
+
+    pub(crate) fn translated(&self, offset: Vector3<f64>) -> Self {
+        match self {
+            Self::Point(point) => Self::Point(point + offset),
+            Self::Segment(start, end) => Self::Segment(start + offset, end + offset),
+            Self::Box {
+                center,
+                axes,
+                half_extents,
+            } => Self::Box {
+                center: center + offset,
+                axes: *axes,
+                half_extents: *half_extents,
+            },
+            Self::Hull(points) => Self::Hull(points.iter().map(|point| point + offset).collect()),
+            Self::Triangle(corners) => Self::Triangle(corners.map(|corner| corner + offset)),
+        }
+    }
+}
+
+fn furthest(points: &[Vector3<f64>], direction: Vector3<f64>) -> Vector3<f64> {
+    points
+        .iter()
+        .copied()
+        .max_by(|a, b| a.dot(direction).total_cmp(&b.dot(direction)))
+        .unwrap_or_else(Vector3::zero)
+}
+
+/// A vertex of the simplex GJK builds in the difference of two shapes
+#[derive(Clone, Copy)]
+pub(crate) struct SimplexVertex {
+    a: Vector3<f64>,
+    b: Vector3<f64>,
+    /// `a - b`
+    w: Vector3<f64>,
+}
+
+/// What GJK found out about two shapes
+pub(crate) enum Gjk {
+    /// The closest point on each shape
+    Separated(Vector3<f64>, Vector3<f64>),
+    /// The shapes overlap, with the simplex GJK stopped at around the origin
+    Overlapping(Vec<SimplexVertex>),
+}
+
+/// Finds the closest points of two shapes with GJK.
+///
+/// # Returns
+/// The closest point on `a` and the closest point on `b`, or `None` if the
+/// shapes overlap
+pub(crate) fn closest_points(a: &Core, b: &Core) -> Option<(Vector3<f64>, Vector3<f64>)> {
+    match gjk(a, b) {
+        Gjk::Separated(point_a, point_b) => Some((point_a, point_b)),
+        Gjk::Overlapping(_) => None,
+    }
+}
+
+/// Runs GJK on two shapes, see `Gjk`
+pub(crate) fn gjk(a: &Core, b: &Core) -> Gjk {
+    let vertex = |direction: Vector3<f64>| support_vertex(a, b, direction);
+
+    let mut simplex = vec![(vertex(Vector3::unit_x()), 1.0)];
+    let mut closest = simplex[0].0.w;
+    if closest.magnitude2() <= 1e-20 {
+        return Gjk::Overlapping(vec![simplex[0].0]);
+    }
+
+    for _ in 0..MAX_GJK_ITERATIONS {
+        let next = vertex(-closest);
+
+        // No vertex gets closer to the origin than the current closest point
+        if closest.magnitude2() - closest.dot(next.w) <= 1e-10 * closest.magnitude2().max(1.0)
+            || simplex
+                .iter()
+                .any(|(vertex, _)| (vertex.w - next.w).magnitude2() <= 1e-20)
+        {
+            break;
+        }
+
+        let mut vertices = simplex
+            .iter()
+            .map(|(vertex, _)| *vertex)
+            .collect::<Vec<_>>();
+        vertices.push(next);
+
+        let Some(closest_simplex) = closest_on_simplex(&vertices) else {
+            return Gjk::Overlapping(vertices);
+        };
+        simplex = closest_simplex;
+        closest = simplex
+            .iter()
+            .fold(Vector3::new(0.0, 0.0, 0.0), |sum, (vertex, weight)| {
+                sum + vertex.w * *weight
+            });
+
+        if closest.magnitude2() <= 1e-20 {
+            return Gjk::Overlapping(simplex.into_iter().map(|(vertex, _)| vertex).collect());
+        }
+    }
+
+    let (point_a, point_b) = simplex.iter().fold(
+        (Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0)),
+        |(a, b), (vertex, weight)| (a + vertex.a * *weight, b + vertex.b * *weight),
+    );
+    Gjk::Separated(point_a, point_b)
+}
+
+fn support_vertex(a: &Core, b: &Core, direction: Vector3<f64>) -> SimplexVertex {
+    let (a, b) = (a.support(direction), b.support(-direction));
+    SimplexVertex { a, b, w: a - b }
+}
+
+/// How deep two overlapping shapes are in each other, found with EPA
+pub(crate) struct Penetration {
+    /// Unit direction from `a` to `b`, moving `b` along it by `depth` separates them
+    pub(crate) normal: Vector3<f64>,
+    pub(crate) depth: f64,
+    /// The point of `a` deepest in `b`
+    pub(crate) point_a: Vector3<f64>,
+    /// The point of `b` deepest in `a`
+    pub(crate) point_b: Vector3<f64>,
+}
+
+/// Finds how deep two overlapping shapes are with EPA, growing the simplex
+/// GJK stopped at towards the surface of the difference of the shapes.
+///
+/// # Returns
+/// The penetration, or `None` if the shapes only just touch and have no depth
+pub(crate) fn penetration(a: &Core, b: &Core, simplex: Vec<SimplexVertex>) -> Option<Penetration> {
+    let vertex = |direction: Vector3<f64>| support_vertex(a, b, direction);
+    let mut vertices = simplex;
+
+    // Grow the simplex into a tetrahedron along directions it doesn't span yet
+    let axes = [
+        Vector3::unit_x(),
+        -Vector3::unit_x(),
+        Vector3::unit_y(),
+        -Vector3::unit_y(),
+        Vector3::unit_z(),
+        -Vector3::unit_z(),
+    ];
+    if vertices.len() == 1 {
+        let first = vertices[0].w;
+        if let Some(next) = axes
+            .iter()
+            .map(|axis| vertex(*axis))
+            .find(|next| (next.w - first).magnitude2() > 1e-12)
+        {
+            vertices.push(next);
+        }
+    }
+    if vertices.len() == 2 {
+        let line = vertices[1].w - vertices[0].w;
+        let start = vertices[0].w;
+        let away_from_line = |point: Vector3<f64>| {
+            let offset = point - start;
+            (offset - line * (offset.dot(line) / line.magnitude2())).magnitude2() > 1e-12
+        };
+
+        if let Some(next) = axes
+            .iter()
+            .map(|axis| line.cross(*axis))
+            .filter(|direction| direction.magnitude2() > 1e-12)
+            .flat_map(|direction| [vertex(direction), vertex(-direction)])
+            .find(|next| away_from_line(next.w))
+        {
+            vertices.push(next);
+        }
+    }
+    if vertices.len() == 3 {
+        let normal = (vertices[1].w - vertices[0].w).cross(vertices[2].w - vertices[0].w);
+        let start = vertices[0].w;
+
+        if let Some(next) = [vertex(normal), vertex(-normal)]
+            .into_iter()
+            .find(|next| normal.dot(next.w - start).abs() > 1e-12)
+        {
+            vertices.push(next);
+        }
+    }
+    if vertices.len() != 4 {
+        return None;
+    }
+
+    // Faces wind so their normals face away from the inside of the tetrahedron
+    let centroid = vertices
+        .iter()
+        .fold(Vector3::zero(), |sum, vertex| sum + vertex.w)
+        / 4.0;
+    let mut faces = [[0, 1, 2], [0, 3, 1], [0, 2, 3], [1, 3, 2]]
+        .into_iter()
+        .map(|[i, j, k]| {
+            let normal = (vertices[j].w - vertices[i].w).cross(vertices[k].w - vertices[i].w);
+            if normal.dot(vertices[i].w - centroid) < 0.0 {
+                [i, k, j]
+            } else {
+                [i, j, k]
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let plane = |vertices: &[SimplexVertex], [i, j, k]: [usize; 3]| {
+        let normal = (vertices[j].w - vertices[i].w).cross(vertices[k].w - vertices[i].w);
+        let length = normal.magnitude();
+        (length > 1e-12).then(|| {
+            let normal = normal / length;
+            (normal, normal.dot(vertices[i].w))
+        })
+    };
+
+    let mut closest = None;
+    for _ in 0..MAX_EPA_ITERATIONS {
+        let Some((face, normal, distance)) = faces
+            .iter()
+            .filter_map(|face| {
+                plane(&vertices, *face).map(|(normal, distance)| (*face, normal, distance))
+            })
+            .min_by(|(_, _, a), (_, _, b)| a.total_cmp(b))
+        else {
+            break;
+        };
+        closest = Some((face, normal, distance));
+
+        let next = vertex(normal);
+        if next.w.dot(normal) - distance <= EPA_TOLERANCE {
+            break;
+        }
+
+        // Replace the faces the new vertex sees with faces from their outline to it
+        let new_index = vertices.len();
+        vertices.push(next);
+
+        let mut outline: Vec<(usize, usize)> = Vec::new();
+        faces.retain(|face| {
+            let Some((normal, distance)) = plane(&vertices, *face) else {
+                return true;
+            };
+            if normal.dot(next.w) - distance <= 0.0 {
+                return true;
+            }
+
+            for edge in [(face[0], face[1]), (face[1], face[2]), (face[2], face[0])] {
+                match outline.iter().position(|other| *other == (edge.1, edge.0)) {
+                    Some(shared) => {
+                        outline.swap_remove(shared);
+                    }
+                    None => outline.push(edge),
+                }
+            }
+            false
+        });
+
+        faces.extend(outline.into_iter().map(|(i, j)| [i, j, new_index]));
+    }
+
+    let ([i, j, k], normal, depth) = closest?;
+
+    // Where the origin lands on the closest face, as weights of its corners
+    let weights = barycentric(
+        normal * depth,
+        [vertices[i].w, vertices[j].w, vertices[k].w],
+    );
+    let corners = [vertices[i], vertices[j], vertices[k]];
+    let (point_a, point_b) = corners.iter().zip(weights).fold(
+        (Vector3::zero(), Vector3::zero()),
+        |(a, b), (corner, weight)| (a + corner.a * weight, b + corner.b * weight),
+    );
+
+    Some(Penetration {
+        normal,
+        depth: depth.max(0.0),
+        point_a,
+        point_b,
+    })
+}
+
+/// Weights of the corners of a triangle that make up a point on its plane
+fn barycentric(point: Vector3<f64>, [a, b, c]: [Vector3<f64>; 3]) -> [f64; 3] {
+    let (ab, ac, ap) = (b - a, c - a, point - a);
+    let (d00, d01, d11) = (ab.dot(ab), ab.dot(ac), ac.dot(ac));
+    let (d20, d21) = (ap.dot(ab), ap.dot(ac));
+    let denominator = d00 * d11 - d01 * d01;
+    if denominator.abs() <= 1e-20 {
+        return [1.0, 0.0, 0.0];
+    }
+
+    let v = ((d11 * d20 - d01 * d21) / denominator).clamp(0.0, 1.0);
+    let w = ((d00 * d21 - d01 * d20) / denominator).clamp(0.0, 1.0 - v);
+    [1.0 - v - w, v, w]
+}
+
+/// Finds the point of a simplex closest to the origin.
+///
+/// # Returns
+/// The smallest part of the simplex that holds the point, with the weight of
+/// each vertex, or `None` if the simplex is a tetrahedron around the origin
+fn closest_on_simplex(vertices: &[SimplexVertex]) -> Option<Vec<(SimplexVertex, f64)>> {
+    match vertices {
+        [a] => Some(vec![(*a, 1.0)]),
+        [a, b] => Some(closest_on_segment(*a, *b)),
+        [a, b, c] => Some(closest_on_triangle(*a, *b, *c)),
+        [a, b, c, d] => {
+            let faces = [(a, b, c, d), (a, c, d, b), (a, d, b, c), (b, d, c, a)];
+
+            faces
+                .into_iter()
+                .filter(|(a, b, c, opposite)| {
+                    let normal = (b.w - a.w).cross(c.w - a.w);
+                    let origin_side = normal.dot(-a.w);
+                    let opposite_side = normal.dot(opposite.w - a.w);
+
+                    // Flat tetrahedrons have every face facing the origin
+                    opposite_side.abs() <= 1e-12 || origin_side * opposite_side < 0.0
+                })
+                .map(|(a, b, c, _)| closest_on_triangle(*a, *b, *c))
+                .min_by(|a, b| weighted_length(a).total_cmp(&weighted_length(b)))
+        }
+        _ => None,
+    }
+}
+
+fn weighted_length(simplex: &[(SimplexVertex, f64)]) -> f64 {
+    simplex
+        .iter()
+        .fold(Vector3::new(0.0, 0.0, 0.0), |sum, (vertex, weight)| {
+            sum + vertex.w * *weight
+        })
+        .magnitude2()
+}
+
+fn closest_on_segment(a: SimplexVertex, b: SimplexVertex) -> Vec<(SimplexVertex, f64)> {
+    let ab = b.w - a.w;
+    let length2 = ab.magnitude2();
+    if length2 <= 1e-20 {
+        return vec![(a, 1.0)];
+    }
+
+    let t = -a.w.dot(ab) / length2;
+    if t <= 0.0 {
+        vec![(a, 1.0)]
+    } else if t >= 1.0 {
+        vec![(b, 1.0)]
+    } else {
+        vec![(a, 1.0 - t), (b, t)]
+    }
+}
+
+/// Closest point of a triangle to the origin by its voronoi regions
+fn closest_on_triangle(
+    a: SimplexVertex,
+    b: SimplexVertex,
+    c: SimplexVertex,
+) -> Vec<(SimplexVertex, f64)> {
+    let ab = b.w - a.w;
+    let ac = c.w - a.w;
+
+    let d1 = ab.dot(-a.w);
+    let d2 = ac.dot(-a.w);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return vec![(a, 1.0)];
+    }
+
+    let d3 = ab.dot(-b.w);
+    let d4 = ac.dot(-b.w);
+    if d3 >= 0.0 && d4 <= d3 {
+        return vec![(b, 1.0)];
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        let t = d1 / (d1 - d3);
+        return vec![(a, 1.0 - t), (b, t)];
+    }
+
+    let d5 = ab.dot(-c.w);
+    let d6 = ac.dot(-c.w);
+    if d6 >= 0.0 && d5 <= d6 {
+        return vec![(c, 1.0)];
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        let t = d2 / (d2 - d6);
+        return vec![(a, 1.0 - t), (c, t)];
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        let t = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+        return vec![(b, 1.0 - t), (c, t)];
+    }
+
+    let area = va + vb + vc;
+    if area <= 1e-20 {
+        // A flat triangle, the closest point is on one of its edges
+        return [
+            closest_on_segment(a, b),
+            closest_on_segment(b, c),
+            closest_on_segment(a, c),
+        ]
+        .into_iter()
+        .min_by(|a, b| weighted_length(a).total_cmp(&weighted_length(b)))
+        .unwrap_or_else(|| vec![(a, 1.0)]);
+    }
+
+    let v = vb / area;
+    let w = vc / area;
+    vec![(a, 1.0 - v - w), (b, v), (c, w)]
+}
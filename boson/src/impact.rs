@@ -0,0 +1,82 @@
+use std::collections::{HashMap, HashSet};
+
+use cgmath::{InnerSpace, Vector3};
+
+use crate::{BosonObject, RigidBody, solver::Contact};
+
+/// Slowest closing speed that counts as an impact, slower touches are bodies
+/// settling against each other
+pub const IMPACT_SPEED_THRESHOLD: f64 = 0.1;
+
+/// Most impacts kept until they are drained, the oldest are dropped first
+pub(crate) const MAX_QUEUED_IMPACTS: usize = 1024;
+
+/// Two bodies that started touching, read with `Boson::drain_impacts`.
+#[derive(Clone)]
+pub struct Impact {
+    pub a: BosonObject,
+    pub b: BosonObject,
+    /// Where the bodies touched in world space
+    pub point: Vector3<f64>,
+    /// Direction from `a` to `b` in world space
+    pub normal: Vector3<f64>,
+    /// How fast the bodies closed in on each other along the normal
+    pub speed: f64,
+}
+
+/// Finds the pairs of bodies that start touching from one step to the next.
+#[derive(Default)]
+pub(crate) struct ImpactTracker {
+    // Pairs of bodies that touched in the previous step
+    touching: HashSet<(usize, usize)>,
+    pub(crate) impacts: Vec<Impact>,
+}
+
+impl ImpactTracker {
+    /// Queues an impact for every pair of bodies that touches this step but
+    /// didn't in the last one, at the point they close in the fastest.
+    ///
+    /// # Arguments
+    /// * `contacts` - The contacts of the step, before the velocities are solved
+    /// * `bodies` - The bodies the contacts index into
+    /// * `object` - Returns the object of a body by its index
+    pub(crate) fn record<'a>(
+        &mut self,
+        contacts: &[Contact],
+        bodies: &[&mut RigidBody],
+        object: impl Fn(usize) -> &'a BosonObject,
+    ) {
+        let mut fastest: HashMap<(usize, usize), (&Contact, f64)> = HashMap::new();
+
+        for contact in contacts {
+            let (a, b) = (&bodies[contact.a], &bodies[contact.b]);
+            let point = contact.point.point;
+
+            let velocity_a = a.velocity + a.angular_velocity.cross(point - a.position);
+            let velocity_b = b.velocity + b.angular_velocity.cross(point - b.position);
+            let speed = (velocity_a - velocity_b).dot(contact.point.normal);
+
+            let pair = (object(contact.a).id(), object(contact.b).id());
+            match fastest.get(&pair) {
+                Some((_, fastest_speed)) if *fastest_speed >= speed => {}
+                _ => _ = fastest.insert(pair, (contact, speed)),
+            }
+        }
+
+        for (pair, (contact, speed)) in fastest.iter() {
+            if self.touching.contains(pair) || *speed < IMPACT_SPEED_THRESHOLD {
+                continue;
+            }
+
+            self.impacts.push(Impact {
+                a: object(contact.a).clone(),
+                b: object(contact.b).clone(),
+                point: contact.point.point,
+                normal: contact.point.normal,
+                speed: *speed,
+            });
+        }
+
+        self.touching = fastest.into_keys().collect();
+    }
+}
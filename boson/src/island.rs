@@ -0,0 +1,278 @@
+use std::{collections::HashMap, panic};
+
+use crate::{
+    joint::Joint,
+    rigid_body::RigidBody,
+    solver::{Contact, ContactConstraint, ImpulseSolver},
+};
+
+// Islands are handed to the job pool in batches of at least this many
+// contacts, so small islands don't cost more to queue than to solve
+const MIN_CONTACTS_PER_JOB: usize = 32;
+
+/// The groups of movable bodies connected by contacts or joints.
+///
+/// Contacts of one island never touch the bodies of another, so every island
+/// is solved on its own, in parallel with the others on the `gluon` pool.
+/// Immovable bodies don't carry motion from one body to the next and never
+/// join islands, every island that touches one solves against its own copy.
+pub(crate) struct Islands {
+    // The bodies of every island by their index in the step
+    bodies: Vec<Vec<usize>>,
+    // The contacts of every island in the order they were found, indexing
+    // into the bodies of the island
+    contacts: Vec<Vec<Contact>>,
+    // The joints of every island as the bodies of the island they connect
+    // and their index in the step
+    joints: Vec<Vec<(usize, usize, usize)>>,
+    // Movable bodies without contacts count as islands of their own
+    count: usize,
+}
+
+impl Islands {
+    /// Groups the contacts and joints of a step by the island they belong to.
+    ///
+    /// # Arguments
+    /// * `bodies` - Every rigid body of the step
+    /// * `contacts` - The contacts between the bodies
+    /// * `joints` - The bodies every joint of the step connects
+    pub(crate) fn new(
+        bodies: &[&mut RigidBody],
+        contacts: &[Contact],
+        joints: &[(usize, usize)],
+    ) -> Self {
+        let mut parents = (0..bodies.len()).collect::<Vec<_>>();
+
+        fn root(parents: &mut [usize], mut index: usize) -> usize {
+            while parents[index] != index {
+                parents[index] = parents[parents[index]];
+                index = parents[index];
+            }
+
+            index
+        }
+
+        let movable = |index: usize| bodies[index].inv_mass != 0.0;
+
+        let connections = contacts
+            .iter()
+            .map(|contact| (contact.a, contact.b))
+            .chain(joints.iter().copied());
+        for (a, b) in connections {
+            if !movable(a) || !movable(b) {
+                continue;
+            }
+
+            let (a, b) = (root(&mut parents, a), root(&mut parents, b));
+            parents[a] = b;
+        }
+
+        let count = (0..bodies.len())
+            .filter(|&index| movable(index) && root(&mut parents, index) == index)
+            .count();
+
+        // Islands are numbered in the order their first contact or joint was found
+        let mut islands = HashMap::new();
+        let mut locals = Vec::<HashMap<usize, usize>>::new();
+        let mut island_bodies = Vec::<Vec<usize>>::new();
+        let mut island_contacts = Vec::<Vec<Contact>>::new();
+        let mut island_joints = Vec::<Vec<(usize, usize, usize)>>::new();
+
+        // Returns the island of a connection and the indices of its bodies in the island
+        let mut island_of = |a: usize, b: usize| {
+            // Broadphase never pairs two immovable bodies, nor are joints between them solved
+            let body = if movable(a) { a } else { b };
+
+            let island = *islands.entry(root(&mut parents, body)).or_insert_with(|| {
+                locals.push(HashMap::new());
+                island_bodies.push(Vec::new());
+                island_contacts.push(Vec::new());
+                island_joints.push(Vec::new());
+                island_contacts.len() - 1
+            });
+
+            let mut local = |index: usize| {
+                *locals[island].entry(index).or_insert_with(|| {
+                    island_bodies[island].push(index);
+                    island_bodies[island].len() - 1
+                })
+            };
+
+            let (a, b) = (local(a), local(b));
+            (island, a, b)
+        };
+
+        let mut grouped_contacts = Vec::with_capacity(contacts.len());
+        for contact in contacts {
+            let (island, a, b) = island_of(contact.a, contact.b);
+            grouped_contacts.push((island, Contact { a, b, ..*contact }));
+        }
+
+        let mut grouped_joints = Vec::with_capacity(joints.len());
+        for (index, &(a, b)) in joints.iter().enumerate() {
+            let (island, a, b) = island_of(a, b);
+            grouped_joints.push((island, (a, b, index)));
+        }
+
+        for (island, contact) in grouped_contacts {
+            island_contacts[island].push(contact);
+        }
+
+        for (island, joint) in grouped_joints {
+            island_joints[island].push(joint);
+        }
+
+        Self {
+            bodies: island_bodies,
+            contacts: island_contacts,
+            joints: island_joints,
+            count,
+        }
+    }
+
+    /// Returns the number of islands, including movable bodies touching nothing
+    pub(crate) fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Solves the velocities of every island, see `ImpulseSolver::solve_velocities`
+    ///
+    /// # Arguments
+    /// * `solver` - The solver
+    /// * `bodies` - Every rigid body of the step
+    /// * `joints` - Every joint of the step, in the order given to `new`
+    /// * `timestep` - Duration of the step in seconds
+    ///
+    /// # Returns
+    /// The constraints of every island for `solve_positions`
+    pub(crate) fn solve_velocities(
+        &self,
+        solver: &mut ImpulseSolver,
+        bodies: &mut [&mut RigidBody],
+        joints: &[&Joint],
+        timestep: f64,
+    ) -> Vec<Vec<ContactConstraint>> {
+        let constraints = {
+            let solver = &*solver;
+            self.solve(bodies, |island, island_bodies| {
+                let island_joints = self.joints[island]
+                    .iter()
+                    .map(|&(a, b, index)| (a, b, joints[index]))
+                    .collect::<Vec<_>>();
+
+                solver.solve_velocities(
+                    island_bodies,
+                    &self.contacts[island],
+                    &island_joints,
+                    timestep,
+                )
+            })
+        };
+
+        solver.cache_impulses(constraints.iter().flatten());
+        constraints
+    }
+
+    /// Pushes the overlapping bodies of every island apart, see `ImpulseSolver::solve_positions`
+    ///
+    /// # Returns
+    /// The most iterations any island took
+    pub(crate) fn solve_positions(
+        &self,
+        solver: &ImpulseSolver,
+        bodies: &mut [&mut RigidBody],
+        constraints: &[Vec<ContactConstraint>],
+    ) -> u32 {
+        self.solve(bodies, |island, island_bodies| {
+            solver.solve_positions(island_bodies, &constraints[island])
+        })
+        .into_iter()
+        .max()
+        .unwrap_or(0)
+    }
+
+    /// Runs a solve on the bodies of every island, in parallel when there
+    /// are enough contacts to go around
+    ///
+    /// # Returns
+    /// The result of every island in the order of the islands
+    fn solve<F, R>(&self, bodies: &mut [&mut RigidBody], solve: F) -> Vec<R>
+    where
+        F: Fn(usize, &mut [&mut RigidBody]) -> R + Sync,
+        R: Send,
+    {
+        let mut copies = self
+            .bodies
+            .iter()
+            .map(|island| {
+                island
+                    .iter()
+                    .filter(|index| bodies[**index].inv_mass == 0.0)
+                    .map(|index| (*bodies[*index]).clone())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        // Every movable body belongs to a single island
+        let mut slots = bodies
+            .iter_mut()
+            .map(|body| Some(&mut **body))
+            .collect::<Vec<_>>();
+        let islands = self
+            .bodies
+            .iter()
+            .zip(copies.iter_mut())
+            .map(|(island, copies)| {
+                let mut copies = copies.iter_mut();
+                island
+                    .iter()
+                    .filter_map(|index| match slots[*index].as_ref()?.inv_mass == 0.0 {
+                        true => copies.next(),
+                        false => slots[*index].take(),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        // Batch consecutive islands until each batch is worth a job
+        let mut batches = vec![Vec::new()];
+        let mut batch_contacts = 0;
+        for (index, island) in islands.into_iter().enumerate() {
+            if batch_contacts >= MIN_CONTACTS_PER_JOB {
+                batches.push(Vec::new());
+                batch_contacts = 0;
+            }
+
+            batch_contacts += self.contacts[index].len() + self.joints[index].len();
+            if let Some(batch) = batches.last_mut() {
+                batch.push((index, island));
+            }
+        }
+
+        let solve = &solve;
+        let run = move |batch: Vec<(usize, Vec<&mut RigidBody>)>| {
+            batch
+                .into_iter()
+                .map(|(index, mut island)| solve(index, &mut island))
+                .collect::<Vec<_>>()
+        };
+
+        if batches.len() == 1 {
+            return batches.into_iter().flat_map(run).collect();
+        }
+
+        gluon::scope(|scope| {
+            let jobs = batches
+                .into_iter()
+                .map(|batch| scope.spawn(move || run(batch)))
+                .collect::<Vec<_>>();
+
+            jobs.into_iter()
+                .flat_map(|job| {
+                    job.join()
+                        .unwrap_or_else(|panic| panic::resume_unwind(panic))
+                })
+                .collect()
+        })
+    }
+}
@@ -0,0 +1,448 @@
+use cgmath::{ElementWise, InnerSpace, One, Quaternion, Rotation, Vector3, Zero};
+
+use crate::{
+    BosonBody, BosonObject, RigidBody,
+    solver::{SolverSettings, effective_mass_inverse, inverse_or_zero, tangents},
+};
+
+/// Identifies a joint added with `Boson::add_joint`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct JointId(pub(crate) u64);
+
+/// How a joint lets its bodies move relative to each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JointKind {
+    /// Rotates about the axis, such as a door, a turret or a wheel
+    Hinge,
+    /// Slides along the axis without rotating, such as a piston or an elevator
+    Slider,
+}
+
+/// The range a joint can move in, in radians for hinges and meters for sliders.
+///
+/// Positions are measured from where the bodies were when the joint was created.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JointLimits {
+    pub lower: f64,
+    pub upper: f64,
+}
+
+/// Drives a joint along its axis with a force it can't exceed, a torque for hinges.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JointMotor {
+    /// Spins or slides at a speed, in radians or meters per second, such as a wheel
+    Velocity { speed: f64, max_force: f64 },
+    /// Moves to a position and holds it, in radians or meters, such as a
+    /// turret turning to aim or a door swinging shut
+    Position { target: f64, max_force: f64 },
+}
+
+/// Connects two rigid bodies so they only move relative to each other along
+/// or about one axis, optionally within limits and driven by a motor.
+///
+/// Bodies joined by a joint never collide with each other. Joints only
+/// connect rigid bodies, joints to any other body are ignored. To attach a
+/// body to the world, join it to an immovable body such as a door frame.
+///
+/// # Example
+/// ```ignore
+/// // A door that swings up to 90 degrees and closes itself
+/// let hinge = Joint::hinge(&frame, &door, hinge_position, Vector3::unit_y())
+///     .with_limits(0.0, std::f64::consts::FRAC_PI_2)
+///     .with_motor(JointMotor::Position { target: 0.0, max_force: 20.0 });
+/// let hinge = boson.add_joint(hinge);
+///
+/// // Open it later
+/// boson.modify_joint(hinge, |joint| {
+///     joint.set_motor(Some(JointMotor::Position { target: 1.5, max_force: 20.0 }))
+/// });
+/// ```
+#[derive(Clone)]
+pub struct Joint {
+    pub(crate) a: BosonObject,
+    pub(crate) b: BosonObject,
+    kind: JointKind,
+    // Where the joint is on each body, relative to its center of mass in its local space
+    anchor_a: Vector3<f64>,
+    anchor_b: Vector3<f64>,
+    // The axis of the joint in the local space of each body
+    axis_a: Vector3<f64>,
+    axis_b: Vector3<f64>,
+    // Perpendicular to the axis, the angle of a hinge is measured between them
+    reference_a: Vector3<f64>,
+    reference_b: Vector3<f64>,
+    // Rotation of b relative to a when the joint was created, which sliders keep
+    rest_rotation: Quaternion<f64>,
+    limits: Option<JointLimits>,
+    motor: Option<JointMotor>,
+}
+
+impl Joint {
+    /// Creates a hinge that lets the bodies rotate about an axis through a point.
+    ///
+    /// # Arguments
+    /// * `a` - The first body, usually the one that holds the other such as a frame
+    /// * `b` - The second body, positive angles turn it counterclockwise about the axis
+    /// * `anchor` - Where the hinge is in world space, with the bodies where they are now
+    /// * `axis` - The axis the bodies rotate about in world space
+    pub fn hinge(
+        a: &BosonObject,
+        b: &BosonObject,
+        anchor: Vector3<f64>,
+        axis: Vector3<f64>,
+    ) -> Self {
+        Self::new(JointKind::Hinge, a, b, anchor, axis)
+    }
+
+    /// Creates a slider that lets the bodies move along an axis without rotating.
+    ///
+    /// # Arguments
+    /// * `a` - The first body, usually the one that holds the other such as a shaft
+    /// * `b` - The second body, positive positions move it along the axis
+    /// * `anchor` - A point on the axis in world space, with the bodies where they are now
+    /// * `axis` - The axis the bodies slide along in world space
+    pub fn slider(
+        a: &BosonObject,
+        b: &BosonObject,
+        anchor: Vector3<f64>,
+        axis: Vector3<f64>,
+    ) -> Self {
+        Self::new(JointKind::Slider, a, b, anchor, axis)
+    }
+
+    fn new(
+        kind: JointKind,
+        a: &BosonObject,
+        b: &BosonObject,
+        anchor: Vector3<f64>,
+        axis: Vector3<f64>,
+    ) -> Self {
+        let axis = if axis.magnitude2() == 0.0 {
+            Vector3::unit_y()
+        } else {
+            axis.normalize()
+        };
+        let [reference, _] = tangents(axis);
+
+        let (position_a, orientation_a) = body_transform(a);
+        let (position_b, orientation_b) = body_transform(b);
+        let (inverse_a, inverse_b) = (orientation_a.invert(), orientation_b.invert());
+
+        Self {
+            a: a.clone(),
+            b: b.clone(),
+            kind,
+            anchor_a: inverse_a.rotate_vector(anchor - position_a),
+            anchor_b: inverse_b.rotate_vector(anchor - position_b),
+            axis_a: inverse_a.rotate_vector(axis),
+            axis_b: inverse_b.rotate_vector(axis),
+            reference_a: inverse_a.rotate_vector(reference),
+            reference_b: inverse_b.rotate_vector(reference),
+            rest_rotation: inverse_a * orientation_b,
+            limits: None,
+            motor: None,
+        }
+    }
+
+    /// Keeps the joint between two positions, see `JointLimits`
+    pub fn with_limits(mut self, lower: f64, upper: f64) -> Self {
+        self.set_limits(Some(JointLimits { lower, upper }));
+        self
+    }
+
+    /// Drives the joint with a motor
+    pub fn with_motor(mut self, motor: JointMotor) -> Self {
+        self.motor = Some(motor);
+        self
+    }
+
+    pub fn kind(&self) -> JointKind {
+        self.kind
+    }
+
+    pub fn limits(&self) -> Option<JointLimits> {
+        self.limits
+    }
+
+    /// Changes the limits of the joint, `None` lets it move freely
+    pub fn set_limits(&mut self, limits: Option<JointLimits>) {
+        self.limits = limits.map(|limits| JointLimits {
+            lower: limits.lower.min(limits.upper),
+            upper: limits.lower.max(limits.upper),
+        });
+    }
+
+    pub fn motor(&self) -> Option<JointMotor> {
+        self.motor
+    }
+
+    /// Changes the motor of the joint, such as its target, `None` turns it off
+    pub fn set_motor(&mut self, motor: Option<JointMotor>) {
+        self.motor = motor;
+    }
+
+    /// Returns the angle of a hinge or how far a slider moved since the
+    /// joint was created, as of the last step
+    pub fn position(&self) -> f64 {
+        self.position_between(body_transform(&self.a), body_transform(&self.b))
+    }
+
+    fn position_between(
+        &self,
+        (position_a, orientation_a): (Vector3<f64>, Quaternion<f64>),
+        (position_b, orientation_b): (Vector3<f64>, Quaternion<f64>),
+    ) -> f64 {
+        let axis = orientation_a.rotate_vector(self.axis_a);
+
+        match self.kind {
+            JointKind::Hinge => {
+                let reference_a = orientation_a.rotate_vector(self.reference_a);
+                let reference_b = orientation_b.rotate_vector(self.reference_b);
+                // Only the rotation about the axis counts
+                let reference_b = reference_b - axis * reference_b.dot(axis);
+
+                reference_a
+                    .cross(reference_b)
+                    .dot(axis)
+                    .atan2(reference_a.dot(reference_b))
+            }
+            JointKind::Slider => {
+                let anchor_a = position_a + orientation_a.rotate_vector(self.anchor_a);
+                let anchor_b = position_b + orientation_b.rotate_vector(self.anchor_b);
+
+                (anchor_b - anchor_a).dot(axis)
+            }
+        }
+    }
+}
+
+// Reads the transform of a body without holding its lock, so reading both
+// bodies of a joint never waits on one while holding the other
+fn body_transform(object: &BosonObject) -> (Vector3<f64>, Quaternion<f64>) {
+    object.read_body(|body| match body {
+        BosonBody::RigidBody(rigid_body) => (rigid_body.position, rigid_body.orientation),
+        _ => (Vector3::zero(), Quaternion::one()),
+    })
+}
+
+/// One direction a joint constrains, solved like the normal of a contact
+struct JointRow {
+    // Constrains the velocity of the anchors, or the angular velocity of the bodies
+    linear: bool,
+    direction: Vector3<f64>,
+    mass: f64,
+    // The relative velocity the row drives the bodies to
+    target: f64,
+    // The accumulated impulse and the range it is clamped to
+    impulse: f64,
+    min_impulse: f64,
+    max_impulse: f64,
+}
+
+/// A joint prepared for solving
+pub(crate) struct JointConstraint {
+    a: usize,
+    b: usize,
+    // Anchor relative to the center of mass of each body
+    r_a: Vector3<f64>,
+    r_b: Vector3<f64>,
+    rows: Vec<JointRow>,
+}
+
+impl JointConstraint {
+    /// Prepares a joint between two bodies of a step.
+    ///
+    /// Drift away from the joint is removed through the velocities, by the
+    /// same fraction per step as overlaps are.
+    ///
+    /// # Arguments
+    /// * `joint` - The joint
+    /// * `a` - Index of the first body of the joint in `bodies`
+    /// * `b` - Index of the second body of the joint in `bodies`
+    /// * `bodies` - The bodies of the step
+    /// * `settings` - The settings of the solver
+    /// * `timestep` - Duration of the step in seconds
+    pub(crate) fn prepare(
+        joint: &Joint,
+        (a, b): (usize, usize),
+        bodies: &[&mut RigidBody],
+        settings: &SolverSettings,
+        timestep: f64,
+    ) -> Self {
+        let (body_a, body_b) = (&bodies[a], &bodies[b]);
+        let stiffness = if timestep > 0.0 {
+            settings.baumgarte / timestep
+        } else {
+            0.0
+        };
+
+        let r_a = body_a.orientation.rotate_vector(joint.anchor_a);
+        let r_b = body_b.orientation.rotate_vector(joint.anchor_b);
+        let drift = (body_b.position + r_b) - (body_a.position + r_a);
+        let axis = body_a.orientation.rotate_vector(joint.axis_a);
+        let world_axes = [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()];
+
+        let linear = |direction: Vector3<f64>, target: f64| {
+            JointRow::new(
+                true,
+                direction,
+                effective_mass_inverse(body_a, body_b, r_a, r_b, direction),
+                target,
+            )
+        };
+        let angular = |direction: Vector3<f64>, target: f64| {
+            let inverse_mass = direction.dot(body_a.world_inverse_inertia() * direction)
+                + direction.dot(body_b.world_inverse_inertia() * direction);
+
+            JointRow::new(false, direction, inverse_mass, target)
+        };
+        let along_axis = |target: f64| match joint.kind {
+            JointKind::Hinge => angular(axis, target),
+            JointKind::Slider => linear(axis, target),
+        };
+
+        let mut rows = Vec::new();
+        match joint.kind {
+            JointKind::Hinge => {
+                // The anchors stay together
+                for direction in world_axes {
+                    rows.push(linear(direction, -stiffness * drift.dot(direction)));
+                }
+
+                // The axes stay aligned, only the rotation about them is free
+                let axis_b = body_b.orientation.rotate_vector(joint.axis_b);
+                let misalignment = axis.cross(axis_b);
+                for direction in tangents(axis) {
+                    rows.push(angular(direction, -stiffness * misalignment.dot(direction)));
+                }
+            }
+            JointKind::Slider => {
+                // The anchors stay on the axis
+                for direction in tangents(axis) {
+                    rows.push(linear(direction, -stiffness * drift.dot(direction)));
+                }
+
+                // The bodies don't rotate relative to each other
+                let rotation =
+                    body_b.orientation * (body_a.orientation * joint.rest_rotation).invert();
+                let rotation_error = rotation.v * (2.0 * rotation.s.signum());
+                for direction in world_axes {
+                    rows.push(angular(
+                        direction,
+                        -stiffness * rotation_error.dot(direction),
+                    ));
+                }
+            }
+        }
+
+        let position = joint.position_between(
+            (body_a.position, body_a.orientation),
+            (body_b.position, body_b.orientation),
+        );
+
+        // A limit only pushes back, and only once it is reached
+        if let Some(limits) = joint.limits {
+            if position <= limits.lower {
+                let mut row = along_axis(stiffness * (limits.lower - position));
+                row.min_impulse = 0.0;
+                rows.push(row);
+            } else if position >= limits.upper {
+                let mut row = along_axis(stiffness * (limits.upper - position));
+                row.max_impulse = 0.0;
+                rows.push(row);
+            }
+        }
+
+        if let Some(motor) = joint.motor {
+            let (speed, max_force) = match motor {
+                JointMotor::Velocity { speed, max_force } => (speed, max_force),
+                JointMotor::Position { target, max_force } => {
+                    (stiffness * (target - position), max_force)
+                }
+            };
+
+            let max_impulse = max_force.max(0.0) * timestep;
+            let mut row = along_axis(speed);
+            row.min_impulse = -max_impulse;
+            row.max_impulse = max_impulse;
+            rows.push(row);
+        }
+
+        Self {
+            a,
+            b,
+            r_a,
+            r_b,
+            rows,
+        }
+    }
+
+    /// Runs one iteration over every row of the joint
+    pub(crate) fn solve(&mut self, bodies: &mut [&mut RigidBody]) {
+        for index in 0..self.rows.len() {
+            let row = &self.rows[index];
+            let speed = self.relative_velocity(bodies, row);
+
+            let previous = row.impulse;
+            let impulse = (previous + (row.target - speed) * row.mass)
+                .clamp(row.min_impulse, row.max_impulse);
+            self.rows[index].impulse = impulse;
+
+            let row = &self.rows[index];
+            self.apply_impulse(bodies, row, row.direction * (impulse - previous));
+        }
+    }
+
+    // Velocity of b relative to a along a row
+    fn relative_velocity(&self, bodies: &[&mut RigidBody], row: &JointRow) -> f64 {
+        let (a, b) = (&bodies[self.a], &bodies[self.b]);
+
+        let relative = if row.linear {
+            (b.velocity + b.angular_velocity.cross(self.r_b))
+                - (a.velocity + a.angular_velocity.cross(self.r_a))
+        } else {
+            b.angular_velocity - a.angular_velocity
+        };
+
+        relative.dot(row.direction)
+    }
+
+    // Applies an impulse to b and the opposite impulse to a
+    fn apply_impulse(&self, bodies: &mut [&mut RigidBody], row: &JointRow, impulse: Vector3<f64>) {
+        if impulse == Vector3::zero() {
+            return;
+        }
+
+        let (angular_a, angular_b) = if row.linear {
+            (self.r_a.cross(impulse), self.r_b.cross(impulse))
+        } else {
+            (impulse, impulse)
+        };
+
+        let a = &mut bodies[self.a];
+        if row.linear {
+            a.velocity -= a.effective_inverse_mass().mul_element_wise(impulse);
+        }
+        a.angular_velocity -= a.world_inverse_inertia() * angular_a;
+
+        let b = &mut bodies[self.b];
+        if row.linear {
+            b.velocity += b.effective_inverse_mass().mul_element_wise(impulse);
+        }
+        b.angular_velocity += b.world_inverse_inertia() * angular_b;
+    }
+}
+
+impl JointRow {
+    fn new(linear: bool, direction: Vector3<f64>, inverse_mass: f64, target: f64) -> Self {
+        Self {
+            linear,
+            direction,
+            mass: inverse_or_zero(inverse_mass),
+            target,
+            impulse: 0.0,
+            min_impulse: f64::NEG_INFINITY,
+            max_impulse: f64::INFINITY,
+        }
+    }
+}
@@ -1,55 +1,176 @@
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use std::{
-    sync::{Arc, atomic::AtomicU32},
+    collections::{HashMap, HashSet},
+    sync::{
+        Arc,
+        atomic::{AtomicU32, AtomicU64, Ordering},
+    },
     thread::JoinHandle,
     time::{Duration, Instant},
 };
 
-use cgmath::Vector3;
-use gpu_controller::GpuController;
-use log::info;
+use cgmath::{InnerSpace, Quaternion, Rotation, Vector3};
+pub use collider::{Collider, ColliderBuilder};
+pub use debug::BosonDebugger;
+use gluon::ThreadSignal;
+pub use impact::{IMPACT_SPEED_THRESHOLD, Impact};
+use impact::{ImpactTracker, MAX_QUEUED_IMPACTS};
+use island::Islands;
+pub use joint::{Joint, JointId, JointKind, JointLimits, JointMotor};
+use log::{error, info};
 pub use point_mass::PointMass;
 use properties::gravity::{Gravitational, Gravity};
+pub use properties::locks::{Axis, AxisLocks};
+pub use raycast::RayHit;
 pub use rigid_body::RigidBody;
+pub use shape_cast::{CastShape, SHAPE_CAST_TOLERANCE, ShapeHit};
+pub use solver::SolverSettings;
+use solver::{Contact, ImpulseSolver};
+use state::BodyState;
+pub use state::{BodyTransform, BodyVelocities};
 pub use static_collider::StaticCollider;
+pub use stats::PhysicsStats;
+use trigger::{MAX_QUEUED_TRIGGERS, TriggerTracker};
+pub use trigger::{TriggerEvent, TriggerKind};
+pub use trimesh::TriMesh;
 
+mod collider;
+mod contact;
+mod debug;
+mod gjk;
+mod impact;
+mod island;
+mod joint;
 mod point_mass;
 mod properties;
+mod raycast;
 mod rigid_body;
+mod shape_cast;
+mod solver;
+mod state;
 mod static_collider;
+mod stats;
+mod trigger;
+mod trimesh;
 
-const DEFAULT_TICKRATE: Duration = Duration::from_micros(50);
+pub const BOSON_DEFAULT_TICKRATE: Duration = Duration::from_micros(50);
 
-pub struct BosonObject(Arc<RwLock<BosonBody>>);
-
-unsafe impl Send for BosonObject {}
-unsafe impl Sync for BosonObject {}
+/// A shared handle to a body simulated by Boson.
+///
+/// The body is behind a `RwLock` so the boson thread and the ECS sync systems
+/// can hand it back and forth, which makes the handle `Send` and `Sync` as
+/// long as every body type is. The state of the last two steps is kept behind
+/// its own lock so it can be read without waiting on the body.
+pub struct BosonObject {
+    body: Arc<RwLock<BosonBody>>,
+    state: Arc<Mutex<BodyState>>,
+}
 
 impl Clone for BosonObject {
     fn clone(&self) -> Self {
-        Self(self.0.clone())
+        Self {
+            body: self.body.clone(),
+            state: self.state.clone(),
+        }
     }
 }
 
 impl BosonObject {
     pub fn new(boson_body: BosonBody) -> Self {
-        Self(Arc::new(RwLock::new(boson_body)))
+        Self {
+            state: Arc::new(Mutex::new(BodyState::from_body(&boson_body))),
+            body: Arc::new(RwLock::new(boson_body)),
+        }
     }
 
     pub fn resolve_collisions(&self, other: &BosonObject, timestep: f32) {}
 
+    /// Returns a number that identifies the body, shared by every clone of
+    /// the object and unique while it exists
+    pub fn id(&self) -> usize {
+        Arc::as_ptr(&self.body) as usize
+    }
+
     pub fn modify_body<F, R>(&self, callback: F) -> R
     where
         F: FnOnce(&mut BosonBody) -> R,
     {
-        callback(&mut self.0.write())
+        callback(&mut self.body.write())
     }
 
     pub fn read_body<F, R>(&self, callback: F) -> R
     where
         F: FnOnce(&BosonBody) -> R,
     {
-        callback(&self.0.read())
+        callback(&self.body.read())
+    }
+
+    /// Returns the state of the body captured at the end of the last two steps.
+    ///
+    /// Both steps and the velocities are recorded together, so they always
+    /// belong to consecutive steps. Only a short lock on the copied state is
+    /// taken, never the lock on the body itself.
+    ///
+    /// # Returns
+    /// The transform after the previous step, the transform after the
+    /// current step and the velocities after the current step
+    ///
+    /// # Example
+    /// ```ignore
+    /// let (previous, current, _velocities) = boson_object.read_state();
+    /// let position = previous.position.lerp(current.position, alpha);
+    /// ```
+    pub fn read_state(&self) -> (BodyTransform, BodyTransform, BodyVelocities) {
+        let state = self.state.lock();
+
+        (state.previous, state.current, state.velocities)
+    }
+
+    /// Makes both recorded steps match the body as it is now.
+    ///
+    /// Call this after moving a body outside of the simulation so the move is
+    /// not interpolated.
+    pub fn reset_state(&self) {
+        let state = BodyState::from_body(&self.body.read());
+        *self.state.lock() = state;
+    }
+
+    /// Casts a line against the collider of the body as if it were at `transform`,
+    /// such as where it was a few steps ago for lag compensation.
+    ///
+    /// # Arguments
+    /// * `transform` - Where the origin of the body's model is, as recorded by `read_state`
+    /// * `from` - Start of the line in world space
+    /// * `to` - End of the line in world space
+    ///
+    /// # Returns
+    /// How far along the line the collider is entered from 0 to 1, or `None`
+    /// if the line misses it
+    pub fn ray_distance_at(
+        &self,
+        transform: &BodyTransform,
+        from: Vector3<f64>,
+        to: Vector3<f64>,
+    ) -> Option<f64> {
+        self.read_body(|body| match body {
+            BosonBody::RigidBody(rigid_body) => {
+                let center = transform.position
+                    + transform
+                        .orientation
+                        .rotate_vector(rigid_body.center_of_mass());
+
+                // Move the line into the local space of the collider
+                let inverse = transform.orientation.invert();
+                let origin = inverse.rotate_vector(from - center);
+                let direction = inverse.rotate_vector(to - from);
+
+                rigid_body
+                    .collider()
+                    .ray_distance(origin, direction)
+                    .filter(|t| *t <= 1.0)
+            }
+            BosonBody::PointMass(_) | BosonBody::StaticCollider(_) => None,
+        })
     }
 }
 
@@ -62,63 +183,136 @@ pub enum BosonBody {
 pub struct Boson {
     objects_count: AtomicU32,
     objects: Arc<RwLock<Vec<BosonObject>>>,
-    gpu_controller: Arc<GpuController>,
-    tickrate: Duration,
+    joints_count: AtomicU64,
+    joints: Arc<RwLock<Vec<(JointId, Joint)>>>,
+    solver_settings: Arc<RwLock<SolverSettings>>,
+    stats: Arc<Mutex<PhysicsStats>>,
+    impacts: Arc<Mutex<Vec<Impact>>>,
+    triggers: Arc<Mutex<Vec<TriggerEvent>>>,
+    tickrate: Arc<RwLock<Duration>>,
+    time_scale: Arc<RwLock<f64>>,
 
     // Multi Threading
-    boson_thread: (Arc<RwLock<bool>>, JoinHandle<()>),
+    boson_thread: (Arc<ThreadSignal>, Option<JoinHandle<()>>),
 }
 
-unsafe impl Send for Boson {}
-unsafe impl Sync for Boson {}
+impl Drop for Boson {
+    fn drop(&mut self) {
+        // Stop the boson thread so it does not outlive the objects it steps
+        self.boson_thread.0.stop();
+
+        if let Some(handle) = self.boson_thread.1.take()
+            && handle.join().is_err()
+        {
+            error!("Boson thread panicked");
+        }
+    }
+}
+
+impl Default for Boson {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Boson {
-    pub fn new(gpu_controller: Arc<GpuController>) -> Self {
+    pub fn new() -> Self {
         info!("Initializing Boson");
         let objects: Arc<RwLock<Vec<BosonObject>>> = Arc::new(RwLock::new(Vec::new()));
         let thread_objects = objects.clone();
-        let tickrate = DEFAULT_TICKRATE;
-        let tr_clone = tickrate.clone();
+        let joints: Arc<RwLock<Vec<(JointId, Joint)>>> = Arc::new(RwLock::new(Vec::new()));
+        let thread_joints = joints.clone();
+        let solver_settings = Arc::new(RwLock::new(SolverSettings::default()));
+        let thread_solver_settings = solver_settings.clone();
+        let stats = Arc::new(Mutex::new(PhysicsStats::default()));
+        let thread_stats = stats.clone();
+        let impacts = Arc::new(Mutex::new(Vec::new()));
+        let thread_impacts = impacts.clone();
+        let triggers = Arc::new(Mutex::new(Vec::new()));
+        let thread_triggers = triggers.clone();
+        let tickrate = Arc::new(RwLock::new(BOSON_DEFAULT_TICKRATE));
+        let thread_tickrate = tickrate.clone();
+        let time_scale = Arc::new(RwLock::new(1.0_f64));
+        let thread_time_scale = time_scale.clone();
+        let signal = Arc::new(ThreadSignal::new());
+        let thread_signal = signal.clone();
         let boson_thread_function = std::thread::spawn(move || {
             info!("Starting Boson Thread");
             let mut last_frame_time = Instant::now();
 
             let gravity = Gravity::World(Vector3::unit_y() * -9.81);
+            let mut solver = ImpulseSolver::default();
+            let mut impact_tracker = ImpactTracker::default();
+            let mut trigger_tracker = TriggerTracker::default();
+
+            // Don't simulate while paused, and don't count the time spent paused
+            while let Some(paused_for) = thread_signal.wait_while_paused() {
+                last_frame_time += paused_for;
 
-            loop {
                 let now = Instant::now();
-                let dt = now.duration_since(last_frame_time).as_secs_f64();
+                let dt = now.duration_since(last_frame_time).as_secs_f64()
+                    * thread_time_scale.read().max(0.0);
                 last_frame_time = now;
 
-                let objects = thread_objects.read();
-                for object in objects.iter() {
-                    let mut object = object.0.write();
+                solver.settings = *thread_solver_settings.read();
 
-                    match *object {
-                        BosonBody::PointMass(ref mut point_mass) => {
-                            point_mass.apply_gravity(&gravity, dt);
-                        }
-                        _ => {}
-                    }
+                // Copy the joints so their lock is never held while waiting on a body
+                let joints = thread_joints
+                    .read()
+                    .iter()
+                    .map(|(_, joint)| joint.clone())
+                    .collect::<Vec<_>>();
+
+                // Scope the read so the lock is not held while sleeping
+                let step_stats = step(
+                    &thread_objects.read(),
+                    &joints,
+                    &gravity,
+                    &mut solver,
+                    &mut impact_tracker,
+                    &mut trigger_tracker,
+                    dt,
+                );
+                *thread_stats.lock() = step_stats;
+
+                if !impact_tracker.impacts.is_empty() {
+                    let mut impacts = thread_impacts.lock();
+                    impacts.append(&mut impact_tracker.impacts);
+
+                    let overflow = impacts.len().saturating_sub(MAX_QUEUED_IMPACTS);
+                    impacts.drain(..overflow);
+                }
+
+                if !trigger_tracker.events.is_empty() {
+                    let mut triggers = thread_triggers.lock();
+                    triggers.append(&mut trigger_tracker.events);
+
+                    let overflow = triggers.len().saturating_sub(MAX_QUEUED_TRIGGERS);
+                    triggers.drain(..overflow);
                 }
 
-                std::thread::sleep(tr_clone);
+                thread_signal.sleep(*thread_tickrate.read());
             }
         });
+        signal.start();
 
         Self {
             objects_count: AtomicU32::new(0),
             objects,
-            gpu_controller,
+            joints_count: AtomicU64::new(0),
+            joints,
+            solver_settings,
+            stats,
+            impacts,
+            triggers,
             tickrate,
-            boson_thread: (Arc::new(RwLock::new(true)), boson_thread_function),
+            time_scale,
+            boson_thread: (signal, Some(boson_thread_function)),
         }
     }
 
     pub fn add_object(&mut self, object: &BosonObject) -> u32 {
-        let object_id = self
-            .objects_count
-            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        let object_id = self.objects_count.fetch_add(1, Ordering::SeqCst);
 
         // if let Ok(mut objects) = self.objects.write() {
         //     objects.push(object.clone());
@@ -127,4 +321,1454 @@ impl Boson {
 
         object_id
     }
+
+    /// Removes an object along with every joint attached to it
+    pub fn remove_object(&mut self, object: &BosonObject) {
+        self.objects
+            .write()
+            .retain(|other| !Arc::ptr_eq(&other.body, &object.body));
+
+        self.joints.write().retain(|(_, joint)| {
+            !Arc::ptr_eq(&joint.a.body, &object.body) && !Arc::ptr_eq(&joint.b.body, &object.body)
+        });
+    }
+
+    /// Adds a joint between two objects, simulated from the next step on.
+    ///
+    /// The joint only takes effect while both objects are added to Boson.
+    ///
+    /// # Arguments
+    /// * `joint` - The joint, see `Joint`
+    ///
+    /// # Returns
+    /// The id of the joint, used to change or remove it
+    pub fn add_joint(&self, joint: Joint) -> JointId {
+        let id = JointId(self.joints_count.fetch_add(1, Ordering::SeqCst));
+        self.joints.write().push((id, joint));
+
+        id
+    }
+
+    /// Removes a joint, letting its bodies move freely
+    ///
+    /// # Returns
+    /// The joint, or `None` if it was already removed
+    pub fn remove_joint(&self, id: JointId) -> Option<Joint> {
+        let mut joints = self.joints.write();
+        let index = joints.iter().position(|(other, _)| *other == id)?;
+
+        Some(joints.remove(index).1)
+    }
+
+    /// Changes a joint, such as the target of its motor, used from the next step on.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the joint
+    /// * `callback` - Changes the joint
+    ///
+    /// # Returns
+    /// The result of the callback, or `None` if the joint was removed
+    ///
+    /// # Example
+    /// ```ignore
+    /// boson.modify_joint(wheel, |joint| {
+    ///     joint.set_motor(Some(JointMotor::Velocity { speed: 10.0, max_force: 50.0 }))
+    /// });
+    /// ```
+    pub fn modify_joint<F, R>(&self, id: JointId, callback: F) -> Option<R>
+    where
+        F: FnOnce(&mut Joint) -> R,
+    {
+        self.joints
+            .write()
+            .iter_mut()
+            .find(|(other, _)| *other == id)
+            .map(|(_, joint)| callback(joint))
+    }
+
+    /// Reads a joint, such as how far it turned with `Joint::position`
+    ///
+    /// # Returns
+    /// The result of the callback, or `None` if the joint was removed
+    pub fn read_joint<F, R>(&self, id: JointId, callback: F) -> Option<R>
+    where
+        F: FnOnce(&Joint) -> R,
+    {
+        self.joints
+            .read()
+            .iter()
+            .find(|(other, _)| *other == id)
+            .map(|(_, joint)| callback(joint))
+    }
+
+    /// Returns the settings of the contact solver
+    pub fn solver_settings(&self) -> SolverSettings {
+        *self.solver_settings.read()
+    }
+
+    /// Changes the settings of the contact solver, used from the next step on
+    pub fn set_solver_settings(&self, settings: SolverSettings) {
+        *self.solver_settings.write() = settings;
+    }
+
+    /// Returns the time the boson thread waits between steps
+    pub fn tickrate(&self) -> Duration {
+        *self.tickrate.read()
+    }
+
+    /// Changes the time the boson thread waits between steps.
+    ///
+    /// Steps always cover the time since the previous step, so a longer
+    /// tickrate simulates at the same speed with fewer, larger steps.
+    pub fn set_tickrate(&self, tickrate: Duration) {
+        *self.tickrate.write() = tickrate;
+    }
+
+    /// Returns how fast time passes for every body
+    pub fn time_scale(&self) -> f64 {
+        *self.time_scale.read()
+    }
+
+    /// Changes how fast time passes for every body, such as for slow motion.
+    ///
+    /// Bodies are scaled by their own `time_scale` on top of this, so a body
+    /// at half speed in a world at half speed moves at a quarter speed.
+    ///
+    /// # Arguments
+    /// * `time_scale` - 1 is normal speed, 0 stops time without pausing the simulation
+    pub fn set_time_scale(&self, time_scale: f64) {
+        *self.time_scale.write() = time_scale;
+    }
+
+    /// Returns whether the simulation is paused
+    pub fn paused(&self) -> bool {
+        self.boson_thread.0.is_paused()
+    }
+
+    /// Pauses or resumes the simulation, time does not pass while paused
+    pub fn set_paused(&self, paused: bool) {
+        self.boson_thread.0.set_paused(paused);
+    }
+
+    /// Returns the counters and timings of the last step
+    pub fn stats(&self) -> PhysicsStats {
+        *self.stats.lock()
+    }
+
+    /// Takes the impacts of every step since the last time they were drained.
+    ///
+    /// An impact is reported once when two bodies start touching faster than
+    /// `IMPACT_SPEED_THRESHOLD`, not for every step they stay in contact.
+    pub fn drain_impacts(&self) -> Vec<Impact> {
+        std::mem::take(&mut *self.impacts.lock())
+    }
+
+    /// Takes the bodies that went into or out of a sensor in every step since
+    /// the last time they were drained.
+    ///
+    /// Sensors are rigid bodies with `sensor` set, they only report overlaps
+    /// with movable bodies and never push them. A body that is removed while
+    /// inside a sensor exits it on the next step.
+    ///
+    /// # Example
+    /// ```ignore
+    /// for event in boson.drain_triggers() {
+    ///     if event.kind == TriggerKind::Enter && event.sensor.id() == checkpoint.id() {
+    ///         save_progress();
+    ///     }
+    /// }
+    /// ```
+    pub fn drain_triggers(&self) -> Vec<TriggerEvent> {
+        std::mem::take(&mut *self.triggers.lock())
+    }
+
+    /// Checks if any collider blocks the straight line between two points,
+    /// such as for line of sight.
+    ///
+    /// # Arguments
+    /// * `from` - Start of the line in world space
+    /// * `to` - End of the line in world space
+    /// * `ignore` - Objects that never block the line, such as the bodies of
+    ///   whoever is looking and what they are looking at
+    ///
+    /// # Returns
+    /// Whether the line is blocked
+    pub fn is_occluded(
+        &self,
+        from: Vector3<f64>,
+        to: Vector3<f64>,
+        ignore: &[&BosonObject],
+    ) -> bool {
+        let direction = to - from;
+
+        self.objects.read().iter().any(|object| {
+            if ignore
+                .iter()
+                .any(|ignored| Arc::ptr_eq(&ignored.body, &object.body))
+            {
+                return false;
+            }
+
+            object.read_body(|body| match body {
+                BosonBody::RigidBody(rigid_body) if rigid_body.sensor => false,
+                BosonBody::RigidBody(rigid_body) => {
+                    // Move the line into the local space of the collider
+                    let inverse = rigid_body.orientation.invert();
+                    let origin = inverse.rotate_vector(from - rigid_body.position);
+                    let direction = inverse.rotate_vector(direction);
+
+                    rigid_body
+                        .collider()
+                        .ray_distance(origin, direction)
+                        .is_some_and(|t| t <= 1.0)
+                }
+                BosonBody::PointMass(_) | BosonBody::StaticCollider(_) => false,
+            })
+        })
+    }
+
+    /// Casts a ray against the collider of every body and returns the
+    /// closest hit, such as for shooting, ground checks or mouse picking.
+    ///
+    /// # Arguments
+    /// * `origin` - Start of the ray in world space
+    /// * `direction` - Direction of the ray in world space, doesn't need to be normalized
+    /// * `max_distance` - How far from the origin colliders are hit
+    /// * `filter` - Returns whether a body can be hit, such as to skip the
+    ///   body of whoever is shooting
+    ///
+    /// # Returns
+    /// The closest hit, or `None` if the ray hits nothing within `max_distance`
+    ///
+    /// # Example
+    /// ```ignore
+    /// if let Some(hit) = boson.raycast(muzzle, aim, 100.0, |object| object.id() != shooter.id()) {
+    ///     spawn_decal(hit.point, hit.normal);
+    /// }
+    /// ```
+    pub fn raycast(
+        &self,
+        origin: Vector3<f64>,
+        direction: Vector3<f64>,
+        max_distance: f64,
+        filter: impl Fn(&BosonObject) -> bool,
+    ) -> Option<RayHit> {
+        if direction.magnitude2() == 0.0 {
+            return None;
+        }
+        let direction = direction.normalize();
+
+        let mut closest: Option<RayHit> = None;
+        for object in self.objects.read().iter() {
+            if !filter(object) {
+                continue;
+            }
+
+            let hit = object.read_body(|body| match body {
+                BosonBody::RigidBody(rigid_body) if rigid_body.sensor => None,
+                BosonBody::RigidBody(rigid_body) => {
+                    // Move the ray into the local space of the collider
+                    let inverse = rigid_body.orientation.invert();
+                    let local_origin = inverse.rotate_vector(origin - rigid_body.position);
+                    let local_direction = inverse.rotate_vector(direction);
+
+                    rigid_body
+                        .collider()
+                        .ray_hit(local_origin, local_direction)
+                        .map(|(t, normal)| (t, rigid_body.orientation.rotate_vector(normal)))
+                }
+                BosonBody::PointMass(_) | BosonBody::StaticCollider(_) => None,
+            });
+
+            let Some((t, normal)) = hit else {
+                continue;
+            };
+
+            if t > max_distance || closest.as_ref().is_some_and(|closest| closest.t <= t) {
+                continue;
+            }
+
+            closest = Some(RayHit {
+                object: object.clone(),
+                point: origin + direction * t,
+                normal,
+                t,
+            });
+        }
+
+        closest
+    }
+
+    /// Sweeps a shape through the world and returns the first body it
+    /// touches, such as to move a character or a fast projectile without it
+    /// passing through thin walls between steps.
+    ///
+    /// # Arguments
+    /// * `shape` - The shape to sweep, centered on `origin`
+    /// * `origin` - Where the center of the shape starts in world space
+    /// * `orientation` - Rotation of the shape, which stays the same along the sweep
+    /// * `direction` - Direction the shape moves in world space, doesn't need to be normalized
+    /// * `max_distance` - How far the shape moves
+    /// * `filter` - Returns whether a body can be hit, such as to skip the
+    ///   body of the character that is moving
+    ///
+    /// # Returns
+    /// The first hit along the sweep, or `None` if the shape moves
+    /// `max_distance` without touching anything. Shapes that start touching
+    /// a body hit it at 0.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let capsule = CastShape::Capsule { radius: 0.4, half_height: 0.5 };
+    /// let distance = match boson.shape_cast(&capsule, position, Quaternion::one(), velocity, speed * dt, |object| object.id() != player.id()) {
+    ///     // Stop just short of the wall and slide along it next step
+    ///     Some(hit) => hit.t,
+    ///     None => speed * dt,
+    /// };
+    /// ```
+    pub fn shape_cast(
+        &self,
+        shape: &CastShape,
+        origin: Vector3<f64>,
+        orientation: Quaternion<f64>,
+        direction: Vector3<f64>,
+        max_distance: f64,
+        filter: impl Fn(&BosonObject) -> bool,
+    ) -> Option<ShapeHit> {
+        if direction.magnitude2() == 0.0 {
+            return None;
+        }
+        let direction = direction.normalize();
+
+        let mut closest: Option<ShapeHit> = None;
+        for object in self.objects.read().iter() {
+            if !filter(object) {
+                continue;
+            }
+
+            let max_distance = closest.as_ref().map_or(max_distance, |closest| closest.t);
+            let hit = object.read_body(|body| match body {
+                BosonBody::RigidBody(rigid_body) if rigid_body.sensor => None,
+                BosonBody::RigidBody(rigid_body) => contact::WorldShape::from_body(rigid_body)
+                    .and_then(|target| {
+                        shape_cast::cast_against(
+                            (shape, origin, orientation),
+                            direction,
+                            max_distance,
+                            &target,
+                        )
+                    }),
+                BosonBody::PointMass(_) | BosonBody::StaticCollider(_) => None,
+            });
+
+            let Some((t, point, normal)) = hit else {
+                continue;
+            };
+
+            if closest.as_ref().is_some_and(|closest| closest.t <= t) {
+                continue;
+            }
+
+            closest = Some(ShapeHit {
+                object: object.clone(),
+                point,
+                normal,
+                t,
+            });
+        }
+
+        closest
+    }
+
+    /// Draws the colliders and velocities of every object.
+    ///
+    /// # Arguments
+    /// * `debugger` - Receives the lines to draw
+    pub fn debug_draw(&self, debugger: &mut dyn BosonDebugger) {
+        for object in self.objects.read().iter() {
+            object.read_body(|body| body.debug_draw(debugger));
+        }
+    }
+}
+
+/// Advances every object by one step.
+///
+/// Every body is locked for the whole step so the solver sees the world at a
+/// single point in time. Bodies are always locked in the order of `objects`,
+/// the same order every other multi-body access uses. Bodies that start
+/// touching are queued on the impact tracker, bodies going into or out of a
+/// sensor on the trigger tracker. Joints to objects that aren't rigid bodies
+/// of the step are skipped.
+///
+/// # Returns
+/// The counters and timings of the step
+fn step(
+    objects: &[BosonObject],
+    joints: &[Joint],
+    gravity: &Gravity,
+    solver: &mut ImpulseSolver,
+    impact_tracker: &mut ImpactTracker,
+    trigger_tracker: &mut TriggerTracker,
+    timestep: f64,
+) -> PhysicsStats {
+    let step_start = Instant::now();
+    let mut stats = PhysicsStats {
+        bodies: objects.len(),
+        timestep: Duration::from_secs_f64(timestep.max(0.0)),
+        ..Default::default()
+    };
+
+    let mut bodies = objects
+        .iter()
+        .map(|object| object.body.write())
+        .collect::<Vec<_>>();
+
+    // Apply forces to every body before solving contacts, each body
+    // advances by its own share of the step
+    let start = Instant::now();
+    for body in bodies.iter_mut() {
+        match &mut **body {
+            BosonBody::PointMass(point_mass) => {
+                let timestep = timestep * point_mass.time_scale.max(0.0);
+                point_mass.apply_gravity(gravity, timestep);
+            }
+            BosonBody::RigidBody(rigid_body) => {
+                rigid_body.accelerate(gravity);
+                rigid_body.integrate_velocity(timestep * rigid_body.time_scale.max(0.0));
+            }
+            _ => {}
+        }
+    }
+    stats.integration_time += start.elapsed();
+
+    {
+        let (indices, mut rigid_bodies): (Vec<usize>, Vec<&mut RigidBody>) = bodies
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, body)| match &mut **body {
+                BosonBody::RigidBody(rigid_body) => Some((index, rigid_body)),
+                _ => None,
+            })
+            .unzip();
+
+        // The address of a body identifies it across steps for warm starting
+        let id = |index: usize| Arc::as_ptr(&objects[indices[index]].body) as usize;
+
+        let rigid_indices = (0..rigid_bodies.len())
+            .map(|index| (id(index), index))
+            .collect::<HashMap<_, _>>();
+        let (joint_pairs, joints): (Vec<(usize, usize)>, Vec<&Joint>) = joints
+            .iter()
+            .filter_map(|joint| {
+                let a = *rigid_indices.get(&joint.a.id())?;
+                let b = *rigid_indices.get(&joint.b.id())?;
+                let movable = rigid_bodies[a].inv_mass != 0.0 || rigid_bodies[b].inv_mass != 0.0;
+
+                (a != b && movable).then_some(((a, b), joint))
+            })
+            .unzip();
+        stats.joints = joints.len();
+
+        // Joined bodies never collide with each other
+        let joined = joint_pairs
+            .iter()
+            .map(|&(a, b)| (a.min(b), a.max(b)))
+            .collect::<HashSet<_>>();
+
+        let start = Instant::now();
+        let mut pairs = Vec::new();
+        for a in 0..rigid_bodies.len() {
+            for b in (a + 1)..rigid_bodies.len() {
+                let (body_a, body_b) = (&rigid_bodies[a], &rigid_bodies[b]);
+
+                if (body_a.inv_mass == 0.0 && body_b.inv_mass == 0.0) || joined.contains(&(a, b)) {
+                    continue;
+                }
+
+                stats.broadphase_pairs += 1;
+
+                if contact::bounds_overlap(body_a, body_b) {
+                    pairs.push((a, b));
+                }
+            }
+        }
+        stats.broadphase_time = start.elapsed();
+
+        let start = Instant::now();
+        let mut contacts = Vec::new();
+        let mut overlaps = Vec::new();
+        let mut points = Vec::new();
+        stats.narrowphase_tests = pairs.len();
+        for (a, b) in pairs {
+            points.clear();
+            contact::collide(rigid_bodies[a], rigid_bodies[b], &mut points);
+
+            // Sensors only report the overlap, the solver never sees it
+            match (rigid_bodies[a].sensor, rigid_bodies[b].sensor) {
+                (false, false) => {}
+                (true, true) => continue,
+                (sensor_a, _) => {
+                    let (sensor, other) = if sensor_a { (a, b) } else { (b, a) };
+
+                    if !points.is_empty() {
+                        overlaps.push((
+                            objects[indices[sensor]].clone(),
+                            objects[indices[other]].clone(),
+                        ));
+                    }
+                    continue;
+                }
+            }
+
+            contacts.extend(points.iter().map(|point| Contact {
+                a,
+                b,
+                key: (id(a), id(b), point.feature),
+                point: *point,
+            }));
+        }
+        stats.contacts = contacts.len();
+        stats.narrowphase_time = start.elapsed();
+
+        // Impacts are measured before the solver takes the closing speed away
+        impact_tracker.record(&contacts, &rigid_bodies, |index| &objects[indices[index]]);
+        trigger_tracker.record(overlaps);
+
+        let islands = Islands::new(&rigid_bodies, &contacts, &joint_pairs);
+        stats.active_islands = islands.count();
+
+        let start = Instant::now();
+        let constraints = islands.solve_velocities(solver, &mut rigid_bodies, &joints, timestep);
+        stats.velocity_iterations = solver.settings.velocity_iterations;
+        stats.velocity_solver_time = start.elapsed();
+
+        let start = Instant::now();
+        for rigid_body in rigid_bodies.iter_mut() {
+            let timestep = timestep * rigid_body.time_scale.max(0.0);
+            rigid_body.integrate_position(timestep);
+        }
+        stats.integration_time += start.elapsed();
+
+        let start = Instant::now();
+        stats.position_iterations =
+            islands.solve_positions(solver, &mut rigid_bodies, &constraints);
+        stats.position_solver_time = start.elapsed();
+    }
+
+    // Record the step while the bodies are still locked so the state always
+    // matches a whole step
+    for (object, body) in objects.iter().zip(bodies.iter()) {
+        object.state.lock().record(body);
+    }
+
+    stats.step_time = step_start.elapsed();
+    stats
+}
+
+#[cfg(test)]
+mod test {
+    use std::{sync::Barrier, thread};
+
+    use cgmath::{InnerSpace, One, Zero};
+
+    use super::*;
+
+    const THREADS: usize = 4;
+    const ITERATIONS: usize = 1000;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    /// Tests that the public types are thread safe without any unsafe impls.
+    #[test]
+    fn test_send_sync() {
+        assert_send_sync::<BosonObject>();
+        assert_send_sync::<BosonBody>();
+        assert_send_sync::<Boson>();
+    }
+
+    /// Tests that bodies written by several threads while the boson thread
+    /// steps them never lose a write and stay readable.
+    #[test]
+    fn test_concurrent_body_access() {
+        let mut boson = Boson::new();
+        let object = PointMass::new(1.0);
+        boson.add_object(&object);
+
+        let barrier = Arc::new(Barrier::new(THREADS));
+        let handles = (0..THREADS)
+            .map(|_| {
+                let object = object.clone();
+                let barrier = barrier.clone();
+
+                thread::spawn(move || {
+                    barrier.wait();
+
+                    for _ in 0..ITERATIONS {
+                        object.modify_body(|body| {
+                            if let BosonBody::PointMass(point_mass) = body {
+                                point_mass.mass += 1.0;
+                            }
+                        });
+
+                        object.read_body(|body| {
+                            if let BosonBody::PointMass(point_mass) = body {
+                                assert!(point_mass.position.x.is_finite());
+                            }
+                        });
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        object.read_body(|body| match body {
+            BosonBody::PointMass(point_mass) => {
+                assert_eq!(point_mass.mass, 1.0 + (THREADS * ITERATIONS) as f64)
+            }
+            _ => panic!("Body changed type"),
+        });
+    }
+
+    /// Tests that the recorded state shifts by one step per record and that a
+    /// reset collapses both steps onto the body.
+    #[test]
+    fn test_read_state() {
+        let object = RigidBody::new(1.0, ColliderBuilder::Sphere);
+        let (previous, current, _) = object.read_state();
+        assert_eq!(previous, current);
+
+        object.modify_body(|body| {
+            if let BosonBody::RigidBody(rigid_body) = body {
+                rigid_body.position = Vector3::new(1.0, 2.0, 3.0);
+                rigid_body.velocity = Vector3::unit_x();
+            }
+        });
+
+        // Reading the state never sees changes made outside of a step
+        assert_eq!(object.read_state().1, current);
+
+        object.state.lock().record(&object.body.read());
+        let (previous, current, velocities) = object.read_state();
+        assert_eq!(previous.position, Vector3::zero());
+        assert_eq!(current.position, Vector3::new(1.0, 2.0, 3.0));
+        assert_eq!(velocities.linear, Vector3::unit_x());
+
+        object.reset_state();
+        let (previous, current, _) = object.read_state();
+        assert_eq!(previous, current);
+        assert_eq!(previous.position, Vector3::new(1.0, 2.0, 3.0));
+    }
+
+    /// Tests that locked axes keep their position and rotation while free axes integrate.
+    #[test]
+    fn test_axis_locks() {
+        let object = RigidBody::new(1.0, ColliderBuilder::Cube);
+
+        object.modify_body(|body| {
+            let BosonBody::RigidBody(rigid_body) = body else {
+                panic!("Expected a rigid body");
+            };
+
+            rigid_body.constrain_to_plane(Axis::Z);
+            rigid_body.velocity = Vector3::new(1.0, 0.0, 1.0);
+            rigid_body.apply_torque(Vector3::new(1.0, 1.0, 1.0), 1.0);
+
+            let gravity = Gravity::World(Vector3::new(0.0, -9.81, -9.81));
+            for _ in 0..10 {
+                rigid_body.apply_gravity(&gravity, 0.1);
+            }
+
+            assert!(rigid_body.position.x > 0.0);
+            assert!(rigid_body.position.y < 0.0);
+            assert_eq!(rigid_body.position.z, 0.0);
+            assert_eq!(rigid_body.angular_velocity.x, 0.0);
+            assert_eq!(rigid_body.angular_velocity.y, 0.0);
+            assert!(rigid_body.angular_velocity.z > 0.0);
+
+            rigid_body.freeze_rotation();
+            let orientation = rigid_body.orientation;
+            rigid_body.apply_torque(Vector3::new(1.0, 1.0, 1.0), 1.0);
+            rigid_body.apply_gravity(&gravity, 0.1);
+            assert_eq!(rigid_body.orientation, orientation);
+        });
+    }
+
+    /// Tests that a body advances by its share of the step, falling a quarter
+    /// as far at half speed, and that a frozen body keeps still.
+    #[test]
+    fn test_time_scale() {
+        let objects = [0.5, 1.0, 0.0].map(|time_scale| {
+            let object = RigidBody::new(1.0, ColliderBuilder::Sphere);
+            object.modify_body(|body| {
+                if let BosonBody::RigidBody(rigid_body) = body {
+                    rigid_body.time_scale = time_scale;
+                }
+            });
+
+            object
+        });
+
+        let gravity = Gravity::World(Vector3::unit_y() * -9.81);
+        let mut solver = ImpulseSolver::default();
+        let mut impact_tracker = ImpactTracker::default();
+        let mut trigger_tracker = TriggerTracker::default();
+        for _ in 0..10 {
+            step(
+                &objects[..1],
+                &[],
+                &gravity,
+                &mut solver,
+                &mut impact_tracker,
+                &mut trigger_tracker,
+                0.1,
+            );
+            step(
+                &objects[1..2],
+                &[],
+                &gravity,
+                &mut solver,
+                &mut impact_tracker,
+                &mut trigger_tracker,
+                0.1,
+            );
+            step(
+                &objects[2..],
+                &[],
+                &gravity,
+                &mut solver,
+                &mut impact_tracker,
+                &mut trigger_tracker,
+                0.1,
+            );
+        }
+
+        let (slow, slow_velocity) = rigid_body(&objects[0]);
+        let (normal, normal_velocity) = rigid_body(&objects[1]);
+        let (frozen, _) = rigid_body(&objects[2]);
+
+        assert!(normal.y < 0.0);
+        assert!((slow.y - normal.y * 0.25).abs() < 1e-9);
+        assert!((slow_velocity.y - normal_velocity.y * 0.5).abs() < 1e-9);
+        assert_eq!(frozen, Vector3::zero());
+    }
+
+    fn rigid_body(object: &BosonObject) -> (Vector3<f64>, Vector3<f64>) {
+        object.read_body(|body| match body {
+            BosonBody::RigidBody(rigid_body) => (rigid_body.position, rigid_body.velocity),
+            _ => panic!("Expected a rigid body"),
+        })
+    }
+
+    /// Tests that a stack of boxes comes to rest on a static plane without
+    /// sinking into it or sliding off.
+    #[test]
+    fn test_impulse_solver_stacking() {
+        let ground = RigidBody::new(0.0, ColliderBuilder::Plane);
+        let boxes = (0..3)
+            .map(|index| {
+                let object = RigidBody::new(1.0, ColliderBuilder::Cube);
+                object.modify_body(|body| {
+                    if let BosonBody::RigidBody(rigid_body) = body {
+                        rigid_body.position = Vector3::new(0.0, 1.0 + index as f64 * 2.0, 0.0);
+                    }
+                });
+
+                object
+            })
+            .collect::<Vec<_>>();
+
+        let objects = std::iter::once(ground)
+            .chain(boxes.iter().cloned())
+            .collect::<Vec<_>>();
+        let gravity = Gravity::World(Vector3::unit_y() * -9.81);
+        let mut solver = ImpulseSolver::default();
+        let mut impact_tracker = ImpactTracker::default();
+        let mut trigger_tracker = TriggerTracker::default();
+
+        let mut stats = PhysicsStats::default();
+        for _ in 0..600 {
+            stats = step(
+                &objects,
+                &[],
+                &gravity,
+                &mut solver,
+                &mut impact_tracker,
+                &mut trigger_tracker,
+                1.0 / 120.0,
+            );
+        }
+
+        // The ground does not join islands, so the stack is a single island
+        assert_eq!(stats.bodies, 4);
+        assert_eq!(stats.active_islands, 1);
+        assert_eq!(stats.broadphase_pairs, 6);
+        assert!(stats.contacts >= 12);
+        assert_eq!(
+            stats.velocity_iterations,
+            solver.settings.velocity_iterations
+        );
+        assert!(stats.position_iterations <= solver.settings.position_iterations);
+
+        for (index, object) in boxes.iter().enumerate() {
+            let (position, velocity) = rigid_body(object);
+
+            assert!((position.y - (1.0 + index as f64 * 2.0)).abs() < 0.05);
+            assert!(position.x.abs() < 0.01 && position.z.abs() < 0.01);
+            assert!(velocity.magnitude() < 0.05);
+        }
+    }
+
+    /// Tests that stacks far apart are solved as islands of their own, in
+    /// parallel against copies of the ground, and all come to rest on it.
+    #[test]
+    fn test_parallel_islands() {
+        const STACKS: usize = 8;
+
+        let ground = RigidBody::new(0.0, ColliderBuilder::Plane);
+        let stacks = (0..STACKS)
+            .map(|stack| {
+                (0..2)
+                    .map(|index| {
+                        let object = RigidBody::new(1.0, ColliderBuilder::Cube);
+                        object.modify_body(|body| {
+                            if let BosonBody::RigidBody(rigid_body) = body {
+                                rigid_body.position = Vector3::new(
+                                    stack as f64 * 10.0,
+                                    1.0 + index as f64 * 2.0,
+                                    0.0,
+                                );
+                            }
+                        });
+
+                        object
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let objects = std::iter::once(ground)
+            .chain(stacks.iter().flatten().cloned())
+            .collect::<Vec<_>>();
+        let gravity = Gravity::World(Vector3::unit_y() * -9.81);
+        let mut solver = ImpulseSolver::default();
+        let mut impact_tracker = ImpactTracker::default();
+        let mut trigger_tracker = TriggerTracker::default();
+
+        let mut stats = PhysicsStats::default();
+        for _ in 0..600 {
+            stats = step(
+                &objects,
+                &[],
+                &gravity,
+                &mut solver,
+                &mut impact_tracker,
+                &mut trigger_tracker,
+                1.0 / 120.0,
+            );
+        }
+
+        assert_eq!(stats.active_islands, STACKS);
+
+        for stack in stacks.iter() {
+            for (index, object) in stack.iter().enumerate() {
+                let (position, velocity) = rigid_body(object);
+
+                assert!((position.y - (1.0 + index as f64 * 2.0)).abs() < 0.05);
+                assert!(velocity.magnitude() < 0.05);
+            }
+        }
+    }
+
+    /// Tests that hinges and sliders hold their bodies, stop at their limits
+    /// and drive them with motors.
+    #[test]
+    fn test_joints() {
+        let rigid_body_at = |mass: f64, position: Vector3<f64>| {
+            let object = RigidBody::new(mass, ColliderBuilder::Cube);
+            object.modify_body(|body| {
+                if let BosonBody::RigidBody(rigid_body) = body {
+                    rigid_body.position = position;
+                }
+            });
+
+            object
+        };
+        let run = |objects: &[BosonObject], joints: &[Joint], gravity: Gravity, steps: usize| {
+            let mut solver = ImpulseSolver::default();
+            let mut impact_tracker = ImpactTracker::default();
+            let mut trigger_tracker = TriggerTracker::default();
+
+            let mut stats = PhysicsStats::default();
+            for _ in 0..steps {
+                stats = step(
+                    objects,
+                    joints,
+                    &gravity,
+                    &mut solver,
+                    &mut impact_tracker,
+                    &mut trigger_tracker,
+                    1.0 / 120.0,
+                );
+            }
+
+            stats
+        };
+
+        // A wheel spun by a velocity motor turns in place, and never collides
+        // with the axle it overlaps
+        let axle = rigid_body_at(0.0, Vector3::zero());
+        let wheel = rigid_body_at(1.0, Vector3::zero());
+        let hinge = Joint::hinge(&axle, &wheel, Vector3::zero(), Vector3::unit_z()).with_motor(
+            JointMotor::Velocity {
+                speed: 2.0,
+                max_force: 100.0,
+            },
+        );
+        let stats = run(&[axle, wheel.clone()], &[hinge], Gravity::None, 120);
+
+        assert_eq!(stats.joints, 1);
+        assert_eq!(stats.contacts, 0);
+        wheel.read_body(|body| {
+            if let BosonBody::RigidBody(rigid_body) = body {
+                assert!((rigid_body.angular_velocity.z - 2.0).abs() < 0.01);
+                assert!(rigid_body.angular_velocity.truncate().magnitude() < 0.01);
+                assert!(rigid_body.position.magnitude() < 0.01);
+            }
+        });
+
+        // A door falling around its hinge stops at its lower limit
+        let frame = rigid_body_at(0.0, Vector3::zero());
+        let door = rigid_body_at(1.0, Vector3::new(3.0, 0.0, 0.0));
+        let hinge =
+            Joint::hinge(&frame, &door, Vector3::zero(), Vector3::unit_z()).with_limits(-0.5, 0.5);
+        run(
+            &[frame, door.clone()],
+            std::slice::from_ref(&hinge),
+            Gravity::World(Vector3::unit_y() * -9.81),
+            240,
+        );
+
+        let (position, velocity) = rigid_body(&door);
+        assert!((hinge.position() + 0.5).abs() < 0.05);
+        assert!((position.magnitude() - 3.0).abs() < 0.05);
+        assert!(velocity.magnitude() < 0.1);
+
+        // A piston driven by a position motor slides to its target without turning
+        let shaft = rigid_body_at(0.0, Vector3::zero());
+        let piston = rigid_body_at(1.0, Vector3::new(0.0, 0.0, 4.0));
+        let slider = Joint::slider(&shaft, &piston, Vector3::zero(), Vector3::unit_x()).with_motor(
+            JointMotor::Position {
+                target: 3.0,
+                max_force: 50.0,
+            },
+        );
+        run(
+            &[shaft, piston.clone()],
+            std::slice::from_ref(&slider),
+            Gravity::World(Vector3::unit_y() * -9.81),
+            360,
+        );
+
+        let (position, velocity) = rigid_body(&piston);
+        assert!((slider.position() - 3.0).abs() < 0.05);
+        assert!((position - Vector3::new(3.0, 0.0, 4.0)).magnitude() < 0.05);
+        assert!(velocity.magnitude() < 0.05);
+        piston.read_body(|body| {
+            if let BosonBody::RigidBody(rigid_body) = body {
+                assert!((rigid_body.orientation.s.abs() - 1.0).abs() < 0.01);
+            }
+        });
+    }
+
+    /// Tests that a ball dropped on the ground reports one impact when it lands
+    /// and none while it rests there.
+    #[test]
+    fn test_impacts() {
+        let ground = RigidBody::new(0.0, ColliderBuilder::Plane);
+        let ball = RigidBody::new(1.0, ColliderBuilder::Sphere);
+        ball.modify_body(|body| {
+            if let BosonBody::RigidBody(rigid_body) = body {
+                rigid_body.position = Vector3::new(0.0, 2.0, 0.0);
+                rigid_body.restitution = 0.0;
+            }
+        });
+
+        let objects = [ground.clone(), ball.clone()];
+        let gravity = Gravity::World(Vector3::unit_y() * -9.81);
+        let mut solver = ImpulseSolver::default();
+        let mut impact_tracker = ImpactTracker::default();
+        let mut trigger_tracker = TriggerTracker::default();
+        for _ in 0..240 {
+            step(
+                &objects,
+                &[],
+                &gravity,
+                &mut solver,
+                &mut impact_tracker,
+                &mut trigger_tracker,
+                1.0 / 120.0,
+            );
+        }
+
+        assert_eq!(impact_tracker.impacts.len(), 1);
+        let impact = &impact_tracker.impacts[0];
+        assert_eq!(impact.a.id(), ground.id());
+        assert_eq!(impact.b.id(), ball.id());
+        assert!(impact.point.y.abs() < 0.1);
+        assert!(impact.normal.y > 0.9);
+
+        // Falling a meter lands at about 4.4 meters per second
+        assert!((impact.speed - (2.0 * 9.81_f64).sqrt()).abs() < 0.3);
+    }
+
+    /// Tests that a ball falls through a sensor untouched, entering and
+    /// exiting it once, and that rays pass through the sensor.
+    #[test]
+    fn test_triggers() {
+        let sensor = RigidBody::new_sensor(ColliderBuilder::Cube);
+        let ball = RigidBody::new(1.0, ColliderBuilder::Sphere);
+        ball.modify_body(|body| {
+            if let BosonBody::RigidBody(rigid_body) = body {
+                rigid_body.position = Vector3::new(0.0, 3.0, 0.0);
+            }
+        });
+
+        let objects = [sensor.clone(), ball.clone()];
+        let gravity = Gravity::World(Vector3::unit_y() * -9.81);
+        let mut solver = ImpulseSolver::default();
+        let mut impact_tracker = ImpactTracker::default();
+        let mut trigger_tracker = TriggerTracker::default();
+        for _ in 0..240 {
+            step(
+                &objects,
+                &[],
+                &gravity,
+                &mut solver,
+                &mut impact_tracker,
+                &mut trigger_tracker,
+                1.0 / 120.0,
+            );
+        }
+
+        // Falling for two seconds takes the ball well below the sensor at free fall speed
+        let (position, velocity) = rigid_body(&ball);
+        assert!(position.y < -10.0);
+        assert!((velocity.y + 2.0 * 9.81).abs() < 0.1);
+        assert!(impact_tracker.impacts.is_empty());
+
+        let kinds = trigger_tracker
+            .events
+            .iter()
+            .map(|event| {
+                assert_eq!(event.sensor.id(), sensor.id());
+                assert_eq!(event.other.id(), ball.id());
+                event.kind
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(kinds, [TriggerKind::Enter, TriggerKind::Exit]);
+
+        let mut boson = Boson::new();
+        boson.set_paused(true);
+        boson.add_object(&sensor);
+        assert!(
+            boson
+                .raycast(
+                    Vector3::new(0.0, 5.0, 0.0),
+                    -Vector3::unit_y(),
+                    10.0,
+                    |_| true
+                )
+                .is_none()
+        );
+    }
+
+    /// Tests that fast contacts bounce by their restitution and slow ones do not.
+    #[test]
+    fn test_impulse_solver_restitution() {
+        let ground = RigidBody::new(0.0, ColliderBuilder::Plane);
+        let ball = RigidBody::new(1.0, ColliderBuilder::Sphere);
+        ball.modify_body(|body| {
+            if let BosonBody::RigidBody(rigid_body) = body {
+                rigid_body.position = Vector3::new(0.0, 0.99, 0.0);
+                rigid_body.velocity = Vector3::new(0.0, -10.0, 0.0);
+                rigid_body.restitution = 0.5;
+            }
+        });
+
+        let objects = [ground, ball.clone()];
+        let mut solver = ImpulseSolver::default();
+        let mut impact_tracker = ImpactTracker::default();
+        let mut trigger_tracker = TriggerTracker::default();
+        step(
+            &objects,
+            &[],
+            &Gravity::None,
+            &mut solver,
+            &mut impact_tracker,
+            &mut trigger_tracker,
+            1.0 / 120.0,
+        );
+
+        let (_, velocity) = rigid_body(&ball);
+        assert!((velocity.y - 5.0).abs() < 0.01);
+
+        ball.modify_body(|body| {
+            if let BosonBody::RigidBody(rigid_body) = body {
+                rigid_body.position = Vector3::new(0.0, 0.99, 0.0);
+                rigid_body.velocity = Vector3::new(0.0, -0.1, 0.0);
+            }
+        });
+        step(
+            &objects,
+            &[],
+            &Gravity::None,
+            &mut solver,
+            &mut impact_tracker,
+            &mut trigger_tracker,
+            1.0 / 120.0,
+        );
+
+        let (_, velocity) = rigid_body(&ball);
+        assert!(velocity.y.abs() < 0.01);
+    }
+
+    /// Tests adding and removing objects from other threads while the boson
+    /// thread is running, then shutting it down cleanly.
+    #[test]
+    fn test_concurrent_add_remove() {
+        let boson = Arc::new(RwLock::new(Boson::new()));
+
+        let handles = (0..THREADS)
+            .map(|_| {
+                let boson = boson.clone();
+
+                thread::spawn(move || {
+                    for _ in 0..ITERATIONS / 10 {
+                        let object = RigidBody::new(1.0, ColliderBuilder::Sphere);
+                        boson.write().add_object(&object);
+                        boson.write().remove_object(&object);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let boson = Arc::into_inner(boson).unwrap().into_inner();
+        assert!(boson.objects.read().is_empty());
+        assert_eq!(
+            boson.objects_count.load(Ordering::SeqCst),
+            (THREADS * ITERATIONS / 10) as u32
+        );
+
+        // Dropping joins the boson thread
+        drop(boson);
+    }
+
+    /// Tests that rigid bodies block lines through them unless they are
+    /// ignored, and that lines ending before a body are not blocked.
+    #[test]
+    fn test_is_occluded() {
+        let mut boson = Boson::new();
+        boson.set_paused(true);
+
+        let wall = RigidBody::new(0.0, ColliderBuilder::Cube);
+        wall.modify_body(|body| {
+            if let BosonBody::RigidBody(rigid_body) = body {
+                rigid_body.position = Vector3::new(5.0, 0.0, 0.0);
+            }
+        });
+        boson.add_object(&wall);
+
+        let from = Vector3::zero();
+        assert!(boson.is_occluded(from, Vector3::new(10.0, 0.0, 0.0), &[]));
+        assert!(!boson.is_occluded(from, Vector3::new(10.0, 0.0, 0.0), &[&wall]));
+        assert!(!boson.is_occluded(from, Vector3::new(3.0, 0.0, 0.0), &[]));
+        assert!(!boson.is_occluded(from, Vector3::new(10.0, 5.0, 0.0), &[]));
+    }
+
+    /// Tests that rays hit the closest body they can within range, on the
+    /// face they enter through.
+    #[test]
+    fn test_raycast() {
+        let mut boson = Boson::new();
+        boson.set_paused(true);
+
+        let near = RigidBody::new(0.0, ColliderBuilder::Cube);
+        let far = RigidBody::new(0.0, ColliderBuilder::Sphere);
+        for (object, x) in [(&near, 5.0), (&far, 10.0)] {
+            object.modify_body(|body| {
+                if let BosonBody::RigidBody(rigid_body) = body {
+                    rigid_body.position = Vector3::new(x, 0.0, 0.0);
+                }
+            });
+            boson.add_object(object);
+        }
+
+        let origin = Vector3::zero();
+        let direction = Vector3::new(2.0, 0.0, 0.0);
+
+        let hit = boson.raycast(origin, direction, 100.0, |_| true).unwrap();
+        assert_eq!(hit.object.id(), near.id());
+        assert!((hit.t - 4.0).abs() < 1e-9);
+        assert!((hit.point - Vector3::new(4.0, 0.0, 0.0)).magnitude() < 1e-9);
+        assert!((hit.normal - Vector3::new(-1.0, 0.0, 0.0)).magnitude() < 1e-9);
+
+        let hit = boson
+            .raycast(origin, direction, 100.0, |object| object.id() != near.id())
+            .unwrap();
+        assert_eq!(hit.object.id(), far.id());
+        assert!((hit.t - 9.0).abs() < 1e-9);
+        assert!((hit.normal - Vector3::new(-1.0, 0.0, 0.0)).magnitude() < 1e-9);
+
+        assert!(boson.raycast(origin, direction, 3.0, |_| true).is_none());
+        assert!(
+            boson
+                .raycast(origin, Vector3::new(0.0, 1.0, 0.0), 100.0, |_| true)
+                .is_none()
+        );
+    }
+
+    /// Tests that swept shapes stop where they first touch a body, including
+    /// bodies thinner than a single step of the shape.
+    #[test]
+    fn test_shape_cast() {
+        let mut boson = Boson::new();
+        boson.set_paused(true);
+
+        let wall = RigidBody::new(0.0, ColliderBuilder::Cube);
+        let ground = RigidBody::new(0.0, ColliderBuilder::Plane);
+        let pane = RigidBody::new(0.0, ColliderBuilder::FromModelAabb);
+        wall.modify_body(|body| {
+            if let BosonBody::RigidBody(rigid_body) = body {
+                rigid_body.position = Vector3::new(5.0, 0.0, 0.0);
+            }
+        });
+        ground.modify_body(|body| {
+            if let BosonBody::RigidBody(rigid_body) = body {
+                rigid_body.position = Vector3::new(0.0, -10.0, 0.0);
+            }
+        });
+        pane.modify_body(|body| {
+            if let BosonBody::RigidBody(rigid_body) = body {
+                rigid_body.position = Vector3::new(0.0, 0.0, 20.0);
+                rigid_body.build_collider_from_points(&[[-1.0, -1.0, -0.001], [1.0, 1.0, 0.001]]);
+            }
+        });
+        for object in [&wall, &ground, &pane] {
+            boson.add_object(object);
+        }
+
+        let identity = Quaternion::one();
+        let all = |_: &BosonObject| true;
+
+        let sphere = CastShape::Sphere { radius: 0.5 };
+        let hit = boson
+            .shape_cast(
+                &sphere,
+                Vector3::zero(),
+                identity,
+                Vector3::unit_x(),
+                100.0,
+                all,
+            )
+            .unwrap();
+        assert_eq!(hit.object.id(), wall.id());
+        assert!((hit.t - 3.5).abs() < 1e-3);
+        assert!((hit.point - Vector3::new(4.0, 0.0, 0.0)).magnitude() < 1e-3);
+        assert!((hit.normal - Vector3::new(-1.0, 0.0, 0.0)).magnitude() < 1e-3);
+
+        // Grazes the top edge of the wall with its side
+        let hit = boson
+            .shape_cast(
+                &sphere,
+                Vector3::new(0.0, 1.4, 0.0),
+                identity,
+                Vector3::unit_x(),
+                100.0,
+                all,
+            )
+            .unwrap();
+        assert_eq!(hit.object.id(), wall.id());
+        assert!(hit.t > 3.5 && hit.t < 4.0);
+
+        let capsule = CastShape::Capsule {
+            radius: 0.5,
+            half_height: 1.0,
+        };
+        let hit = boson
+            .shape_cast(
+                &capsule,
+                Vector3::new(-5.0, 0.0, 0.0),
+                identity,
+                -Vector3::unit_y(),
+                100.0,
+                all,
+            )
+            .unwrap();
+        assert_eq!(hit.object.id(), ground.id());
+        assert!((hit.t - 8.5).abs() < 1e-3);
+        assert!((hit.normal - Vector3::unit_y()).magnitude() < 1e-3);
+
+        let cube = CastShape::Box {
+            half_extents: Vector3::new(0.25, 0.25, 0.25),
+        };
+        let hit = boson
+            .shape_cast(
+                &cube,
+                Vector3::zero(),
+                identity,
+                Vector3::unit_z(),
+                100.0,
+                all,
+            )
+            .unwrap();
+        assert_eq!(hit.object.id(), pane.id());
+        assert!((hit.t - 19.749).abs() < 1e-3);
+
+        assert!(
+            boson
+                .shape_cast(
+                    &cube,
+                    Vector3::zero(),
+                    identity,
+                    Vector3::unit_z(),
+                    10.0,
+                    all
+                )
+                .is_none()
+        );
+        assert!(
+            boson
+                .shape_cast(
+                    &sphere,
+                    Vector3::zero(),
+                    identity,
+                    Vector3::unit_x(),
+                    100.0,
+                    |object| object.id() != wall.id()
+                )
+                .is_none()
+        );
+    }
+
+    /// Tests that convex hulls and spheres come to rest on a triangle mesh
+    /// floor, that hulls stack on each other and that rays and shape casts
+    /// hit the floor's triangles.
+    #[test]
+    fn test_convex_hull_and_trimesh() {
+        let floor = TriMesh::new(
+            &[
+                [-10.0, 0.0, -10.0],
+                [10.0, 0.0, -10.0],
+                [10.0, 0.0, 10.0],
+                [-10.0, 0.0, 10.0],
+            ],
+            &[0, 2, 1, 0, 3, 2],
+        );
+        assert_eq!(floor.triangles().len(), 2);
+
+        let cube = (0..8)
+            .map(|index| {
+                let sign = |bit: usize| if index & bit > 0 { 0.5 } else { -0.5 };
+                [sign(0x01), sign(0x02), sign(0x04)]
+            })
+            .collect::<Vec<_>>();
+
+        let ground = RigidBody::new(0.0, ColliderBuilder::TriMesh(Arc::new(floor)));
+        let bottom = RigidBody::new(1.0, ColliderBuilder::ConvexHull(cube.clone()));
+        let top = RigidBody::new(1.0, ColliderBuilder::ConvexHull(cube));
+        let ball = RigidBody::new(1.0, ColliderBuilder::Sphere);
+        for (object, position) in [
+            (&bottom, Vector3::new(0.0, 0.6, 0.0)),
+            (&top, Vector3::new(0.0, 1.7, 0.0)),
+            (&ball, Vector3::new(5.0, 2.0, 0.0)),
+        ] {
+            object.modify_body(|body| {
+                if let BosonBody::RigidBody(rigid_body) = body {
+                    rigid_body.position = position;
+                }
+            });
+        }
+
+        let objects = [ground.clone(), bottom.clone(), top.clone(), ball.clone()];
+        let gravity = Gravity::World(Vector3::unit_y() * -9.81);
+        let mut solver = ImpulseSolver::default();
+        let mut impact_tracker = ImpactTracker::default();
+        let mut trigger_tracker = TriggerTracker::default();
+        for _ in 0..600 {
+            step(
+                &objects,
+                &[],
+                &gravity,
+                &mut solver,
+                &mut impact_tracker,
+                &mut trigger_tracker,
+                1.0 / 120.0,
+            );
+        }
+
+        for (object, height) in [(&bottom, 0.5), (&top, 1.5), (&ball, 1.0)] {
+            let (position, velocity) = rigid_body(object);
+
+            assert!((position.y - height).abs() < 0.05);
+            assert!(velocity.magnitude() < 0.05);
+        }
+
+        let mut boson = Boson::new();
+        boson.set_paused(true);
+        boson.add_object(&ground);
+
+        let hit = boson
+            .raycast(
+                Vector3::new(2.0, 3.0, 2.0),
+                -Vector3::unit_y(),
+                100.0,
+                |_| true,
+            )
+            .unwrap();
+        assert!((hit.t - 3.0).abs() < 1e-9);
+        assert!((hit.normal - Vector3::unit_y()).magnitude() < 1e-9);
+
+        let hit = boson
+            .shape_cast(
+                &CastShape::Sphere { radius: 0.5 },
+                Vector3::new(-3.0, 4.0, 1.0),
+                Quaternion::one(),
+                -Vector3::unit_y(),
+                100.0,
+                |_| true,
+            )
+            .unwrap();
+        assert!((hit.t - 3.5).abs() < 1e-3);
+        assert!(hit.normal.y > 0.99);
+    }
+
+    /// Tests casting against a body posed somewhere other than where it is.
+    #[test]
+    fn test_ray_distance_at() {
+        let target = RigidBody::new(1.0, ColliderBuilder::Cube);
+        let (_, current, _) = target.read_state();
+        let rewound = BodyTransform {
+            position: Vector3::new(5.0, 0.0, 0.0),
+            ..current
+        };
+
+        let from = Vector3::zero();
+        let to = Vector3::new(10.0, 0.0, 0.0);
+        let t = target.ray_distance_at(&rewound, from, to).unwrap();
+        assert!((t - 0.4).abs() < 1e-9);
+
+        // The body itself is still at the origin, which the line starts in
+        assert_eq!(target.ray_distance_at(&current, from, to), Some(0.0));
+        assert_eq!(
+            target.ray_distance_at(&rewound, from, Vector3::new(3.0, 0.0, 0.0)),
+            None
+        );
+    }
 }
@@ -13,6 +13,9 @@ pub struct PointMass {
 
     pub mass: f64,
     pub inv_mass: f64,
+
+    /// How fast time passes for the body, 1 is normal speed and 0 freezes it
+    pub time_scale: f64,
 }
 
 impl Gravitational for PointMass {
@@ -46,6 +49,7 @@ impl PointMass {
 
             mass,
             inv_mass: if mass == 0.0 { 0.0 } else { 1.0 / mass },
+            time_scale: 1.0,
         }))
     }
 
@@ -0,0 +1,77 @@
+use cgmath::Vector3;
+
+/// A world space axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+/// The world space axes a body may not move along or rotate about.
+///
+/// Locked axes are removed from the body's velocity every step and are given
+/// an infinite mass by the solvers, so nothing can push the body along them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AxisLocks {
+    pub x: bool,
+    pub y: bool,
+    pub z: bool,
+}
+
+impl AxisLocks {
+    /// No axis is locked
+    pub const NONE: Self = Self {
+        x: false,
+        y: false,
+        z: false,
+    };
+
+    /// Every axis is locked
+    pub const ALL: Self = Self {
+        x: true,
+        y: true,
+        z: true,
+    };
+
+    /// Locks a single axis
+    pub fn only(axis: Axis) -> Self {
+        Self {
+            x: axis == Axis::X,
+            y: axis == Axis::Y,
+            z: axis == Axis::Z,
+        }
+    }
+
+    /// Locks every axis except one
+    pub fn all_but(axis: Axis) -> Self {
+        let only = Self::only(axis);
+
+        Self {
+            x: !only.x,
+            y: !only.y,
+            z: !only.z,
+        }
+    }
+
+    /// Returns whether any axis is locked
+    pub fn any(&self) -> bool {
+        self.x || self.y || self.z
+    }
+
+    /// Returns 0 for every locked axis and 1 for every free one
+    pub fn mask(&self) -> Vector3<f64> {
+        let free = |locked: bool| if locked { 0.0 } else { 1.0 };
+
+        Vector3::new(free(self.x), free(self.y), free(self.z))
+    }
+
+    /// Removes the locked axes from a vector
+    pub fn apply(&self, vector: Vector3<f64>) -> Vector3<f64> {
+        Vector3::new(
+            if self.x { 0.0 } else { vector.x },
+            if self.y { 0.0 } else { vector.y },
+            if self.z { 0.0 } else { vector.z },
+        )
+    }
+}
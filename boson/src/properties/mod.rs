@@ -1 +1,2 @@
 pub mod gravity;
+pub mod locks;
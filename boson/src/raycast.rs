@@ -0,0 +1,16 @@
+use cgmath::Vector3;
+
+use crate::BosonObject;
+
+/// Where a ray cast with `Boson::raycast` first hit a collider.
+#[derive(Clone)]
+pub struct RayHit {
+    /// The body that was hit
+    pub object: BosonObject,
+    /// Where the ray entered the collider in world space
+    pub point: Vector3<f64>,
+    /// Unit normal of the collider's surface at `point` in world space
+    pub normal: Vector3<f64>,
+    /// Distance from the origin of the ray to `point`
+    pub t: f64,
+}
@@ -1 +1,306 @@
-pub struct RigidBody {}
+use cgmath::{
+    InnerSpace, Matrix, Matrix3, MetricSpace, One, Quaternion, Rad, Rotation, Rotation3, Vector3,
+    Zero,
+};
+
+use crate::{
+    BosonBody, BosonObject, Collider, ColliderBuilder,
+    properties::{
+        gravity::{GRAVITATIONAL_CONSTANT, Gravitational, Gravity},
+        locks::{Axis, AxisLocks},
+    },
+};
+
+// Rotations smaller than this are skipped to avoid dividing by a zero length axis
+const ANGULAR_VELOCITY_THRESHOLD: f64 = 1e-12;
+
+#[derive(Clone)]
+pub struct RigidBody {
+    /// World position of the center of mass
+    pub position: Vector3<f64>,
+    pub velocity: Vector3<f64>,
+    pub acceleration: Vector3<f64>,
+
+    pub orientation: Quaternion<f64>,
+    pub angular_velocity: Vector3<f64>,
+
+    pub mass: f64,
+    pub inv_mass: f64,
+    pub inverse_inertia: Vector3<f64>,
+
+    pub restitution: f64,
+    pub static_friction: f64,
+    pub dynamic_friction: f64,
+
+    /// World axes the body may not move along
+    pub linear_locks: AxisLocks,
+    /// World axes the body may not rotate about
+    pub angular_locks: AxisLocks,
+
+    /// How fast time passes for the body, 1 is normal speed and 0 freezes it
+    pub time_scale: f64,
+
+    /// Detects the movable bodies overlapping it without pushing them or
+    /// being pushed, see `Boson::drain_triggers`. Rays and shape casts pass
+    /// through sensors.
+    pub sensor: bool,
+
+    pub(crate) collider: Collider,
+    pub(crate) collider_builder: ColliderBuilder,
+    // Offset of the center of mass from the origin of the bound model
+    pub(crate) center_of_mass: Vector3<f64>,
+}
+
+impl Gravitational for RigidBody {
+    fn apply_gravity(&mut self, gravity: &Gravity, timestep: f64) {
+        self.accelerate(gravity);
+        self.integrate(timestep);
+    }
+}
+
+impl RigidBody {
+    /// Creates a new rigid body wrapped in a `BosonObject` ready to be added to Boson.
+    ///
+    /// Bodies using a model collider builder have no collider until they are
+    /// bound to a model with `build_collider_from_points`.
+    ///
+    /// # Arguments
+    /// * `mass` - The mass of the body, a mass of 0 makes it immovable
+    /// * `collider_builder` - How the collider of the body is created
+    pub fn new(mass: f64, collider_builder: ColliderBuilder) -> BosonObject {
+        let collider = collider_builder.build();
+        let inv_mass = if mass == 0.0 { 0.0 } else { 1.0 / mass };
+
+        BosonObject::new(BosonBody::RigidBody(Self {
+            position: Vector3::zero(),
+            velocity: Vector3::zero(),
+            acceleration: Vector3::zero(),
+            orientation: Quaternion::one(),
+            angular_velocity: Vector3::zero(),
+            mass,
+            inv_mass,
+            inverse_inertia: inverse_inertia(&collider, mass),
+            restitution: 0.01,
+            static_friction: 0.1,
+            dynamic_friction: 0.05,
+            linear_locks: AxisLocks::NONE,
+            angular_locks: AxisLocks::NONE,
+            time_scale: 1.0,
+            sensor: false,
+            collider,
+            collider_builder,
+            center_of_mass: Vector3::zero(),
+        }))
+    }
+
+    /// Creates an immovable sensor, a trigger volume for pickups, checkpoints
+    /// and kill zones.
+    ///
+    /// # Arguments
+    /// * `collider_builder` - The shape of the volume
+    ///
+    /// # Example
+    /// ```ignore
+    /// let kill_zone = RigidBody::new_sensor(ColliderBuilder::Cube);
+    /// boson.add_object(&kill_zone);
+    /// ```
+    pub fn new_sensor(collider_builder: ColliderBuilder) -> BosonObject {
+        let object = Self::new(0.0, collider_builder);
+        object.modify_body(|body| {
+            if let BosonBody::RigidBody(rigid_body) = body {
+                rigid_body.sensor = true;
+            }
+        });
+        object
+    }
+
+    /// Returns the collider of the body in its local space
+    pub fn collider(&self) -> &Collider {
+        &self.collider
+    }
+
+    /// Returns how the collider of the body is created
+    pub fn collider_builder(&self) -> &ColliderBuilder {
+        &self.collider_builder
+    }
+
+    /// Returns the offset of the center of mass from the origin of the bound
+    /// model in the body's local space
+    pub fn center_of_mass(&self) -> Vector3<f64> {
+        self.center_of_mass
+    }
+
+    /// Returns whether the body is still waiting for model geometry to build its collider
+    pub fn needs_model_geometry(&self) -> bool {
+        self.collider_builder.needs_model_geometry() && self.collider == Collider::Empty
+    }
+
+    /// Builds the collider of the body from points of its model.
+    ///
+    /// The center of the resulting shape becomes the body's center of mass,
+    /// the model's vertices are left untouched.
+    ///
+    /// # Arguments
+    /// * `points` - Points on the surface of the model in its local space
+    pub fn build_collider_from_points(&mut self, points: &[[f32; 3]]) {
+        let (collider, center_of_mass) = self.collider_builder.build_from_points(points);
+        self.set_model_collider(collider, center_of_mass);
+    }
+
+    /// Builds the collider of the body from the triangles of its model, for
+    /// bodies whose builder needs them.
+    ///
+    /// # Arguments
+    /// * `positions` - The vertices of the model in its local space
+    /// * `indices` - Three indices into `positions` per triangle
+    pub fn build_collider_from_mesh(&mut self, positions: &[[f32; 3]], indices: &[u32]) {
+        let (collider, center_of_mass) = self.collider_builder.build_from_mesh(positions, indices);
+        self.set_model_collider(collider, center_of_mass);
+    }
+
+    fn set_model_collider(&mut self, collider: Collider, center_of_mass: Vector3<f64>) {
+        // Keep the model where it is by moving the body onto the new center of mass
+        let origin = self.position - self.orientation.rotate_vector(self.center_of_mass);
+        self.position = origin + self.orientation.rotate_vector(center_of_mass);
+
+        self.inverse_inertia = inverse_inertia(&collider, self.mass);
+        self.collider = collider;
+        self.center_of_mass = center_of_mass;
+    }
+
+    /// Returns the world position of the origin of the bound model
+    pub fn origin(&self) -> Vector3<f64> {
+        self.position - self.orientation.rotate_vector(self.center_of_mass)
+    }
+
+    /// Moves the body so the origin of the bound model is at `origin`
+    pub fn set_origin(&mut self, origin: Vector3<f64>) {
+        self.position = origin + self.orientation.rotate_vector(self.center_of_mass);
+    }
+
+    /// Stops the body from rotating at all, for example to keep characters upright
+    pub fn freeze_rotation(&mut self) {
+        self.angular_locks = AxisLocks::ALL;
+        self.angular_velocity = Vector3::zero();
+    }
+
+    /// Keeps the body on a plane, as in 2.5D games.
+    ///
+    /// The body can no longer move along `normal` and can only rotate about it.
+    ///
+    /// # Arguments
+    /// * `normal` - The world axis the plane faces
+    pub fn constrain_to_plane(&mut self, normal: Axis) {
+        self.linear_locks = AxisLocks::only(normal);
+        self.angular_locks = AxisLocks::all_but(normal);
+
+        self.velocity = self.linear_locks.apply(self.velocity);
+        self.angular_velocity = self.angular_locks.apply(self.angular_velocity);
+    }
+
+    /// Returns the inverse mass along each world axis, 0 on locked axes
+    pub(crate) fn effective_inverse_mass(&self) -> Vector3<f64> {
+        self.linear_locks.mask() * self.inv_mass
+    }
+
+    /// Returns the inverse inertia tensor in world space, without the locked axes
+    pub(crate) fn world_inverse_inertia(&self) -> Matrix3<f64> {
+        let rotation = Matrix3::from(self.orientation);
+        let mask = diagonal(self.angular_locks.mask());
+
+        mask * rotation * diagonal(self.inverse_inertia) * rotation.transpose() * mask
+    }
+
+    /// Sets the acceleration of the body from gravity
+    pub(crate) fn accelerate(&mut self, gravity: &Gravity) {
+        match gravity {
+            Gravity::None => self.acceleration = Vector3::zero(),
+            Gravity::World(gravity_vector) => self.acceleration = *gravity_vector,
+            Gravity::Point(location, mass) => {
+                let distance = self.position.distance(*location);
+                let force = GRAVITATIONAL_CONSTANT * self.mass * mass / distance.powi(2);
+                self.acceleration = (self.position - location).normalize_to(force) * self.inv_mass;
+            }
+            Gravity::WorldPoint(gravity_vector, location, mass) => {
+                let distance = self.position.distance(*location);
+                let force = GRAVITATIONAL_CONSTANT * self.mass * mass / distance.powi(2);
+                self.acceleration = (self.position - location).normalize_to(force) * self.inv_mass
+                    + *gravity_vector;
+            }
+        }
+    }
+
+    pub fn apply_force(&mut self, force: Vector3<f64>, timestep: f64) {
+        if self.mass == 0.0 {
+            return;
+        }
+
+        // a = F / m
+        self.acceleration = force * self.inv_mass;
+
+        self.integrate(timestep);
+    }
+
+    pub fn apply_torque(&mut self, torque: Vector3<f64>, timestep: f64) {
+        if self.mass == 0.0 {
+            return;
+        }
+
+        self.angular_velocity += self.world_inverse_inertia() * torque * timestep;
+    }
+
+    #[inline]
+    fn integrate(&mut self, timestep: f64) {
+        self.integrate_velocity(timestep);
+        self.integrate_position(timestep);
+    }
+
+    /// Adds the acceleration of the step to the velocity
+    pub(crate) fn integrate_velocity(&mut self, timestep: f64) {
+        if self.mass == 0.0 {
+            return;
+        }
+
+        // v = v_0 + a * t
+        self.velocity += self.acceleration * timestep;
+
+        // Locked axes never gain velocity, whatever was applied to them
+        self.velocity = self.linear_locks.apply(self.velocity);
+        self.angular_velocity = self.angular_locks.apply(self.angular_velocity);
+    }
+
+    /// Moves and rotates the body by its velocities
+    pub(crate) fn integrate_position(&mut self, timestep: f64) {
+        if self.mass == 0.0 {
+            return;
+        }
+
+        // x = x_0 + v * t
+        self.position += self.velocity * timestep;
+        self.rotate_by(self.angular_velocity * timestep);
+    }
+
+    /// Rotates the body about the direction of `rotation` by its length in radians
+    pub(crate) fn rotate_by(&mut self, rotation: Vector3<f64>) {
+        let angle = rotation.magnitude();
+
+        if angle > ANGULAR_VELOCITY_THRESHOLD {
+            let rotation = Quaternion::from_axis_angle(rotation / angle, Rad(angle));
+            self.orientation = (rotation * self.orientation).normalize();
+        }
+    }
+}
+
+fn diagonal(vector: Vector3<f64>) -> Matrix3<f64> {
+    Matrix3::new(vector.x, 0.0, 0.0, 0.0, vector.y, 0.0, 0.0, 0.0, vector.z)
+}
+
+fn inverse_inertia(collider: &Collider, mass: f64) -> Vector3<f64> {
+    if mass == 0.0 {
+        return Vector3::zero();
+    }
+
+    collider
+        .inertia(mass)
+        .map(|inertia| if inertia == 0.0 { 0.0 } else { 1.0 / inertia })
+}
@@ -0,0 +1,205 @@
+use cgmath::{InnerSpace, Matrix3, Quaternion, Rotation, Vector3};
+
+use crate::{
+    BosonObject,
+    contact::WorldShape,
+    gjk::{Core, closest_points},
+};
+
+/// Most steps a shape takes towards a body before the cast gives up and
+/// reports the hit where it stopped
+const MAX_CAST_STEPS: usize = 32;
+
+/// Gap between the shape and a body that counts as touching
+pub const SHAPE_CAST_TOLERANCE: f64 = 1e-4;
+
+/// A shape swept through the world with `Boson::shape_cast`, centered on the
+/// origin of the cast.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CastShape {
+    Sphere {
+        radius: f64,
+    },
+    /// A cylinder along the Y axis capped with half spheres, such as the body
+    /// of a character
+    Capsule {
+        radius: f64,
+        /// Half the height of the cylinder, without the caps
+        half_height: f64,
+    },
+    Box {
+        half_extents: Vector3<f64>,
+    },
+}
+
+impl CastShape {
+    /// Places the shape in the world as the shape swept by GJK and the
+    /// radius rounding it off
+    fn core(&self, position: Vector3<f64>, orientation: Quaternion<f64>) -> (Core, f64) {
+        match self {
+            Self::Sphere { radius } => (Core::Point(position), *radius),
+            Self::Capsule {
+                radius,
+                half_height,
+            } => {
+                let up = orientation.rotate_vector(Vector3::unit_y()) * *half_height;
+                (Core::Segment(position - up, position + up), *radius)
+            }
+            Self::Box { half_extents } => (
+                Core::Box {
+                    center: position,
+                    axes: Matrix3::from(orientation),
+                    half_extents: *half_extents,
+                },
+                0.0,
+            ),
+        }
+    }
+
+    /// Radius of a sphere around the shape
+    fn bounding_radius(&self) -> f64 {
+        match self {
+            Self::Sphere { radius } => *radius,
+            Self::Capsule {
+                radius,
+                half_height,
+            } => radius + half_height,
+            Self::Box { half_extents } => half_extents.magnitude(),
+        }
+    }
+}
+
+/// Where a shape cast with `Boson::shape_cast` first touched a collider.
+#[derive(Clone)]
+pub struct ShapeHit {
+    /// The body that was hit
+    pub object: BosonObject,
+    /// Where the shape touched the collider in world space
+    pub point: Vector3<f64>,
+    /// Unit normal of the collider's surface at `point` in world space,
+    /// facing the shape
+    pub normal: Vector3<f64>,
+    /// How far the shape moved from the origin of the cast before it
+    /// touched, the time of impact for a unit speed
+    pub t: f64,
+}
+
+/// Sweeps a shape against the collider of a body.
+///
+/// The shape advances in steps as far as the gap to the body allows along
+/// the direction, so it can't step over a body however thin it is.
+///
+/// # Arguments
+/// * `shape` - The shape and how it is placed at the start of the cast
+/// * `direction` - Unit direction the shape moves in
+/// * `max_distance` - How far the shape moves
+/// * `target` - The collider of the body in world space
+///
+/// # Returns
+/// How far the shape moved, the point it touched and the normal of the
+/// body there, or `None` if it moves past the body
+pub(crate) fn cast_against(
+    (shape, origin, orientation): (&CastShape, Vector3<f64>, Quaternion<f64>),
+    direction: Vector3<f64>,
+    max_distance: f64,
+    target: &WorldShape,
+) -> Option<(f64, Vector3<f64>, Vector3<f64>)> {
+    let (core, radius) = shape.core(origin, orientation);
+
+    // Skip bodies the bounds of the sweep never reach
+    let center = target.center();
+    let along = (center - origin).dot(direction).clamp(0.0, max_distance);
+    let reach = target.bounding_radius() + shape.bounding_radius();
+    if reach.is_finite() && (origin + direction * along - center).magnitude2() > reach * reach {
+        return None;
+    }
+
+    let (target_core, target_radius) = match target {
+        WorldShape::Plane { normal, distance } => {
+            let lowest = core.support(-*normal) - normal * radius;
+            let height = normal.dot(lowest) - distance;
+            if height <= SHAPE_CAST_TOLERANCE {
+                return Some((0.0, lowest - normal * height, *normal));
+            }
+
+            let speed = direction.dot(*normal);
+            if speed >= 0.0 {
+                return None;
+            }
+
+            let t = height / -speed;
+            return (t <= max_distance).then(|| (t, lowest + direction * t, *normal));
+        }
+        // The first triangle of the mesh the shape touches
+        WorldShape::TriMesh {
+            position,
+            axes,
+            mesh,
+        } => {
+            return mesh
+                .iter_triangles()
+                .filter_map(|triangle| {
+                    let triangle = Core::Triangle(triangle.map(|corner| position + axes * corner));
+                    sweep((&core, radius), direction, max_distance, (&triangle, 0.0))
+                })
+                .min_by(|(a, _, _), (b, _, _)| a.total_cmp(b));
+        }
+        _ => target.core()?,
+    };
+
+    sweep(
+        (&core, radius),
+        direction,
+        max_distance,
+        (&target_core, target_radius),
+    )
+}
+
+/// Advances a core towards another as far as the gap between them allows,
+/// until they touch
+fn sweep(
+    (core, radius): (&Core, f64),
+    direction: Vector3<f64>,
+    max_distance: f64,
+    (target_core, target_radius): (&Core, f64),
+) -> Option<(f64, Vector3<f64>, Vector3<f64>)> {
+    let mut t = 0.0;
+    for _ in 0..MAX_CAST_STEPS {
+        let moved = core.translated(direction * t);
+
+        // The cores overlap, the shape started inside of the body
+        let Some((point, target_point)) = closest_points(&moved, target_core) else {
+            let normal = -direction;
+            return Some((t, moved.support(direction), normal));
+        };
+
+        let offset = point - target_point;
+        let distance = offset.magnitude();
+        let normal = if distance > 0.0 {
+            offset / distance
+        } else {
+            -direction
+        };
+        let gap = distance - radius - target_radius;
+
+        if gap <= SHAPE_CAST_TOLERANCE {
+            return Some((t, target_point + normal * target_radius, normal));
+        }
+
+        let closing = -direction.dot(normal);
+        if closing <= 0.0 {
+            return None;
+        }
+
+        t += gap / closing;
+        if t > max_distance {
+            return None;
+        }
+    }
+
+    // Still closing in, report where it got to rather than letting it tunnel
+    let moved = core.translated(direction * t);
+    let (point, target_point) = closest_points(&moved, target_core)?;
+    let normal = (point - target_point).normalize();
+    Some((t, target_point + normal * target_radius, normal))
+}
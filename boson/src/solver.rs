@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+
+use cgmath::{ElementWise, InnerSpace, Matrix, Matrix3, Vector3, Zero};
+
+use crate::{
+    RigidBody,
+    contact::ContactPoint,
+    joint::{Joint, JointConstraint},
+};
+
+/// Settings of the sequential impulse solver.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolverSettings {
+    /// Passes over every contact when solving velocities
+    pub velocity_iterations: u32,
+    /// Passes over every contact when pushing overlapping bodies apart, 0 moves
+    /// the correction into the velocity solve instead
+    pub position_iterations: u32,
+    /// Starts each step from the impulses of the previous one
+    pub warm_starting: bool,
+    /// Closing speed below which contacts do not bounce, so resting bodies stay at rest
+    pub restitution_slop: f64,
+    /// Overlap allowed before bodies are pushed apart, keeps resting contacts alive
+    pub penetration_slop: f64,
+    /// Fraction of the overlap removed each step
+    pub baumgarte: f64,
+    /// Largest distance a single position iteration may move a contact
+    pub max_correction: f64,
+}
+
+impl Default for SolverSettings {
+    fn default() -> Self {
+        Self {
+            velocity_iterations: 8,
+            position_iterations: 3,
+            warm_starting: true,
+            restitution_slop: 0.5,
+            penetration_slop: 0.005,
+            baumgarte: 0.2,
+            max_correction: 0.2,
+        }
+    }
+}
+
+/// A contact between two bodies of a step.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Contact {
+    /// Index of the first body
+    pub(crate) a: usize,
+    /// Index of the second body
+    pub(crate) b: usize,
+    /// Identifies the contact across steps
+    pub(crate) key: ContactKey,
+    pub(crate) point: ContactPoint,
+}
+
+/// Stable identity of a contact, made of the identities of both bodies and
+/// the feature that touched.
+pub(crate) type ContactKey = (usize, usize, u32);
+
+/// Impulses carried from one step to the next
+#[derive(Debug, Clone, Copy)]
+struct CachedImpulse {
+    normal: f64,
+    tangent: [f64; 2],
+}
+
+/// A contact prepared for solving
+pub(crate) struct ContactConstraint {
+    a: usize,
+    b: usize,
+    key: ContactKey,
+    normal: Vector3<f64>,
+    tangents: [Vector3<f64>; 2],
+    // Contact point relative to the center of mass of each body
+    r_a: Vector3<f64>,
+    r_b: Vector3<f64>,
+    // Contact point in the local space of each body for the position solve
+    local_a: Vector3<f64>,
+    local_b: Vector3<f64>,
+    depth: f64,
+    normal_mass: f64,
+    tangent_mass: [f64; 2],
+    velocity_bias: f64,
+    friction: f64,
+    normal_impulse: f64,
+    tangent_impulse: [f64; 2],
+}
+
+/// Sequential impulse solver.
+///
+/// Contacts are solved one at a time for a number of iterations, clamping the
+/// impulse accumulated over the step rather than each individual impulse, so
+/// impulses that overshoot can be taken back by later iterations. The
+/// accumulated impulses are cached by contact and applied up front on the
+/// next step, which lets stacks settle in a few steps instead of jittering.
+#[derive(Debug, Default)]
+pub(crate) struct ImpulseSolver {
+    pub(crate) settings: SolverSettings,
+    cache: HashMap<ContactKey, CachedImpulse>,
+}
+
+impl ImpulseSolver {
+    /// Solves the velocities of the bodies so touching bodies stop moving
+    /// into each other and joined bodies only move the way their joints let them.
+    ///
+    /// # Arguments
+    /// * `bodies` - Every body of the step
+    /// * `contacts` - The contacts between the bodies found this step
+    /// * `joints` - The joints between the bodies with the indices of the bodies they connect
+    /// * `timestep` - Duration of the step in seconds
+    ///
+    /// # Returns
+    /// The contacts prepared for `solve_positions`
+    pub(crate) fn solve_velocities(
+        &self,
+        bodies: &mut [&mut RigidBody],
+        contacts: &[Contact],
+        joints: &[(usize, usize, &Joint)],
+        timestep: f64,
+    ) -> Vec<ContactConstraint> {
+        let settings = self.settings;
+        let mut constraints = contacts
+            .iter()
+            .map(|contact| self.prepare(bodies, contact, timestep))
+            .collect::<Vec<_>>();
+        let mut joint_constraints = joints
+            .iter()
+            .map(|&(a, b, joint)| {
+                JointConstraint::prepare(joint, (a, b), bodies, &settings, timestep)
+            })
+            .collect::<Vec<_>>();
+
+        if settings.warm_starting {
+            for constraint in constraints.iter() {
+                let impulse = constraint.normal * constraint.normal_impulse
+                    + constraint.tangents[0] * constraint.tangent_impulse[0]
+                    + constraint.tangents[1] * constraint.tangent_impulse[1];
+
+                apply_impulse(bodies, constraint, impulse);
+            }
+        }
+
+        for _ in 0..settings.velocity_iterations {
+            // Joints first so contacts get the last word on bodies pushed into each other
+            for joint in joint_constraints.iter_mut() {
+                joint.solve(bodies);
+            }
+
+            for constraint in constraints.iter_mut() {
+                // Friction first so the normal impulse, which matters most, is solved last
+                for axis in 0..2 {
+                    let tangent = constraint.tangents[axis];
+                    let speed = relative_velocity(bodies, constraint).dot(tangent);
+                    let max_friction = constraint.friction * constraint.normal_impulse;
+
+                    let previous = constraint.tangent_impulse[axis];
+                    constraint.tangent_impulse[axis] = (previous
+                        - speed * constraint.tangent_mass[axis])
+                        .clamp(-max_friction, max_friction);
+
+                    let delta = constraint.tangent_impulse[axis] - previous;
+                    apply_impulse(bodies, constraint, tangent * delta);
+                }
+
+                let speed = relative_velocity(bodies, constraint).dot(constraint.normal);
+
+                let previous = constraint.normal_impulse;
+                constraint.normal_impulse = (previous
+                    + (constraint.velocity_bias - speed) * constraint.normal_mass)
+                    .max(0.0);
+
+                let delta = constraint.normal_impulse - previous;
+                apply_impulse(bodies, constraint, constraint.normal * delta);
+            }
+        }
+
+        constraints
+    }
+
+    /// Keeps the impulses of the solved contacts for the next step, only
+    /// contacts that still exist are kept.
+    ///
+    /// # Arguments
+    /// * `constraints` - Every constraint solved this step
+    pub(crate) fn cache_impulses<'a>(
+        &mut self,
+        constraints: impl Iterator<Item = &'a ContactConstraint>,
+    ) {
+        self.cache = constraints
+            .map(|constraint| {
+                (
+                    constraint.key,
+                    CachedImpulse {
+                        normal: constraint.normal_impulse,
+                        tangent: constraint.tangent_impulse,
+                    },
+                )
+            })
+            .collect();
+    }
+
+    /// Pushes overlapping bodies apart by moving them directly.
+    ///
+    /// Must be called after the positions of the bodies are integrated.
+    ///
+    /// # Returns
+    /// The number of iterations run, fewer than configured once every overlap
+    /// is within the slop
+    pub(crate) fn solve_positions(
+        &self,
+        bodies: &mut [&mut RigidBody],
+        constraints: &[ContactConstraint],
+    ) -> u32 {
+        let settings = self.settings;
+        let mut iterations = 0;
+
+        for _ in 0..settings.position_iterations {
+            iterations += 1;
+            let mut max_depth: f64 = 0.0;
+
+            for constraint in constraints {
+                let (a, b) = (&bodies[constraint.a], &bodies[constraint.b]);
+
+                // Track how far the bodies moved since the contact was found
+                let point_a = a.position + Matrix3::from(a.orientation) * constraint.local_a;
+                let point_b = b.position + Matrix3::from(b.orientation) * constraint.local_b;
+                let depth = constraint.depth - (point_b - point_a).dot(constraint.normal);
+                max_depth = max_depth.max(depth);
+
+                let correction = (settings.baumgarte * (depth - settings.penetration_slop))
+                    .clamp(0.0, settings.max_correction);
+
+                if correction == 0.0 {
+                    continue;
+                }
+
+                let r_a = point_a - a.position;
+                let r_b = point_b - b.position;
+                let mass = effective_mass(a, b, r_a, r_b, constraint.normal);
+
+                if mass == 0.0 {
+                    continue;
+                }
+
+                let impulse = constraint.normal * (correction * mass);
+
+                let a = &mut bodies[constraint.a];
+                a.position -= a.effective_inverse_mass().mul_element_wise(impulse);
+                a.rotate_by(a.world_inverse_inertia() * r_a.cross(-impulse));
+
+                let b = &mut bodies[constraint.b];
+                b.position += b.effective_inverse_mass().mul_element_wise(impulse);
+                b.rotate_by(b.world_inverse_inertia() * r_b.cross(impulse));
+            }
+
+            if max_depth <= settings.penetration_slop * 3.0 {
+                break;
+            }
+        }
+
+        iterations
+    }
+
+    fn prepare(
+        &self,
+        bodies: &[&mut RigidBody],
+        contact: &Contact,
+        timestep: f64,
+    ) -> ContactConstraint {
+        let settings = self.settings;
+        let (a, b) = (&bodies[contact.a], &bodies[contact.b]);
+        let normal = contact.point.normal;
+        let tangents = tangents(normal);
+
+        let point = contact.point.point;
+        let r_a = point - a.position;
+        let r_b = point - b.position;
+
+        // Rotation matrices are orthonormal so their inverse is their transpose
+        let inverse_a = Matrix3::from(a.orientation).transpose();
+        let inverse_b = Matrix3::from(b.orientation).transpose();
+
+        let mut constraint = ContactConstraint {
+            a: contact.a,
+            b: contact.b,
+            key: contact.key,
+            normal,
+            tangents,
+            r_a,
+            r_b,
+            local_a: inverse_a * r_a,
+            local_b: inverse_b * r_b,
+            depth: contact.point.depth,
+            normal_mass: inverse_or_zero(effective_mass_inverse(a, b, r_a, r_b, normal)),
+            tangent_mass: tangents
+                .map(|tangent| inverse_or_zero(effective_mass_inverse(a, b, r_a, r_b, tangent))),
+            velocity_bias: 0.0,
+            friction: (a.dynamic_friction * b.dynamic_friction).sqrt(),
+            normal_impulse: 0.0,
+            tangent_impulse: [0.0; 2],
+        };
+
+        // Bounce only when the bodies close fast enough
+        let closing_speed = relative_velocity(bodies, &constraint).dot(normal);
+        if closing_speed < -settings.restitution_slop {
+            constraint.velocity_bias = -a.restitution.max(b.restitution) * closing_speed;
+        }
+
+        // Without position iterations the overlap is removed through the velocities
+        if settings.position_iterations == 0 && timestep > 0.0 {
+            constraint.velocity_bias += settings.baumgarte / timestep
+                * (contact.point.depth - settings.penetration_slop).max(0.0);
+        }
+
+        if settings.warm_starting
+            && let Some(cached) = self.cache.get(&contact.key)
+        {
+            constraint.normal_impulse = cached.normal;
+            constraint.tangent_impulse = cached.tangent;
+        }
+
+        constraint
+    }
+}
+
+/// Returns two directions perpendicular to the normal and each other
+pub(crate) fn tangents(normal: Vector3<f64>) -> [Vector3<f64>; 2] {
+    let reference = if normal.x.abs() < 0.9 {
+        Vector3::unit_x()
+    } else {
+        Vector3::unit_y()
+    };
+
+    let first = normal.cross(reference).normalize();
+    [first, normal.cross(first)]
+}
+
+pub(crate) fn inverse_or_zero(value: f64) -> f64 {
+    if value == 0.0 { 0.0 } else { 1.0 / value }
+}
+
+/// Returns how much the relative velocity along `direction` changes for a unit impulse
+pub(crate) fn effective_mass_inverse(
+    a: &RigidBody,
+    b: &RigidBody,
+    r_a: Vector3<f64>,
+    r_b: Vector3<f64>,
+    direction: Vector3<f64>,
+) -> f64 {
+    let linear = direction.dot(a.effective_inverse_mass().mul_element_wise(direction))
+        + direction.dot(b.effective_inverse_mass().mul_element_wise(direction));
+
+    let angular_a = (a.world_inverse_inertia() * r_a.cross(direction))
+        .cross(r_a)
+        .dot(direction);
+    let angular_b = (b.world_inverse_inertia() * r_b.cross(direction))
+        .cross(r_b)
+        .dot(direction);
+
+    linear + angular_a + angular_b
+}
+
+fn effective_mass(
+    a: &RigidBody,
+    b: &RigidBody,
+    r_a: Vector3<f64>,
+    r_b: Vector3<f64>,
+    direction: Vector3<f64>,
+) -> f64 {
+    inverse_or_zero(effective_mass_inverse(a, b, r_a, r_b, direction))
+}
+
+/// Velocity of the contact point on b relative to the contact point on a
+fn relative_velocity(bodies: &[&mut RigidBody], constraint: &ContactConstraint) -> Vector3<f64> {
+    let (a, b) = (&bodies[constraint.a], &bodies[constraint.b]);
+
+    (b.velocity + b.angular_velocity.cross(constraint.r_b))
+        - (a.velocity + a.angular_velocity.cross(constraint.r_a))
+}
+
+/// Applies an impulse to b and the opposite impulse to a
+fn apply_impulse(
+    bodies: &mut [&mut RigidBody],
+    constraint: &ContactConstraint,
+    impulse: Vector3<f64>,
+) {
+    if impulse == Vector3::zero() {
+        return;
+    }
+
+    let a = &mut bodies[constraint.a];
+    a.velocity -= a.effective_inverse_mass().mul_element_wise(impulse);
+    a.angular_velocity -= a.world_inverse_inertia() * constraint.r_a.cross(impulse);
+
+    let b = &mut bodies[constraint.b];
+    b.velocity += b.effective_inverse_mass().mul_element_wise(impulse);
+    b.angular_velocity += b.world_inverse_inertia() * constraint.r_b.cross(impulse);
+}
@@ -0,0 +1,97 @@
+use cgmath::{One, Quaternion, Vector3, Zero};
+
+use crate::BosonBody;
+
+/// Position and orientation of a body at the end of a step.
+///
+/// Rigid bodies report the origin of their bound model rather than their
+/// center of mass so the transform can be applied to the model directly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BodyTransform {
+    pub position: Vector3<f64>,
+    pub orientation: Quaternion<f64>,
+}
+
+impl Default for BodyTransform {
+    fn default() -> Self {
+        Self {
+            position: Vector3::zero(),
+            orientation: Quaternion::one(),
+        }
+    }
+}
+
+/// Linear and angular velocity of a body at the end of a step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BodyVelocities {
+    pub linear: Vector3<f64>,
+    pub angular: Vector3<f64>,
+}
+
+impl Default for BodyVelocities {
+    fn default() -> Self {
+        Self {
+            linear: Vector3::zero(),
+            angular: Vector3::zero(),
+        }
+    }
+}
+
+/// The last two steps of a body, kept apart from the body so readers never
+/// wait on the simulation.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct BodyState {
+    pub(crate) previous: BodyTransform,
+    pub(crate) current: BodyTransform,
+    pub(crate) velocities: BodyVelocities,
+}
+
+impl BodyState {
+    /// Creates a state where both steps match the body as it is now
+    pub(crate) fn from_body(body: &BosonBody) -> Self {
+        let (transform, velocities) = body.snapshot();
+
+        Self {
+            previous: transform,
+            current: transform,
+            velocities,
+        }
+    }
+
+    /// Shifts the current step into the previous one and records the body as the current step
+    pub(crate) fn record(&mut self, body: &BosonBody) {
+        let (transform, velocities) = body.snapshot();
+
+        self.previous = self.current;
+        self.current = transform;
+        self.velocities = velocities;
+    }
+}
+
+impl BosonBody {
+    fn snapshot(&self) -> (BodyTransform, BodyVelocities) {
+        match self {
+            BosonBody::PointMass(point_mass) => (
+                BodyTransform {
+                    position: point_mass.position,
+                    ..Default::default()
+                },
+                BodyVelocities {
+                    linear: point_mass.velocity,
+                    ..Default::default()
+                },
+            ),
+            BosonBody::RigidBody(rigid_body) => (
+                BodyTransform {
+                    position: rigid_body.origin(),
+                    orientation: rigid_body.orientation,
+                },
+                BodyVelocities {
+                    linear: rigid_body.velocity,
+                    angular: rigid_body.angular_velocity,
+                },
+            ),
+            BosonBody::StaticCollider(_) => Default::default(),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use std::time::Duration;
+
+/// Counters and timings of the last physics step.
+///
+/// Written by the boson thread at the end of every step, read with
+/// `Boson::stats` by debug overlays and profilers.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PhysicsStats {
+    /// Every object in the world
+    pub bodies: usize,
+    /// Groups of touching bodies that can move, bodies resting on static
+    /// bodies only join through each other
+    pub active_islands: usize,
+    /// Pairs of bodies checked by the broadphase
+    pub broadphase_pairs: usize,
+    /// Pairs whose bounds overlapped and were passed to the narrowphase
+    pub narrowphase_tests: usize,
+    /// Contact points found by the narrowphase
+    pub contacts: usize,
+    /// Joints solved, joints to bodies that aren't simulated are left out
+    pub joints: usize,
+    /// Velocity iterations run by the solver
+    pub velocity_iterations: u32,
+    /// Position iterations run by the solver, stops early once bodies are apart
+    pub position_iterations: u32,
+
+    /// Length of the simulated step
+    pub timestep: Duration,
+    /// Time spent applying forces and moving bodies
+    pub integration_time: Duration,
+    /// Time spent finding the pairs of bodies that may touch
+    pub broadphase_time: Duration,
+    /// Time spent finding contact points
+    pub narrowphase_time: Duration,
+    /// Time spent in the velocity solve
+    pub velocity_solver_time: Duration,
+    /// Time spent in the position solve
+    pub position_solver_time: Duration,
+    /// Time spent on the whole step, including locking the bodies
+    pub step_time: Duration,
+}
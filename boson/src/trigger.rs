@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+
+use crate::BosonObject;
+
+/// Most trigger events kept until they are drained, the oldest are dropped first
+pub(crate) const MAX_QUEUED_TRIGGERS: usize = 1024;
+
+/// Whether a body went into or out of a sensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerKind {
+    Enter,
+    Exit,
+}
+
+/// A body that started or stopped overlapping a sensor, read with
+/// `Boson::drain_triggers`.
+#[derive(Clone)]
+pub struct TriggerEvent {
+    pub kind: TriggerKind,
+    /// The body marked as a sensor
+    pub sensor: BosonObject,
+    /// The body that went into or out of the sensor
+    pub other: BosonObject,
+}
+
+/// Finds the bodies that start or stop overlapping a sensor from one step
+/// to the next.
+#[derive(Default)]
+pub(crate) struct TriggerTracker {
+    // Sensor and body pairs that overlapped in the previous step
+    overlapping: HashMap<(usize, usize), (BosonObject, BosonObject)>,
+    pub(crate) events: Vec<TriggerEvent>,
+}
+
+impl TriggerTracker {
+    /// Queues an enter for every pair that overlaps this step but didn't in
+    /// the last one, and an exit for every pair that stopped overlapping or
+    /// whose bodies were removed.
+    ///
+    /// # Arguments
+    /// * `overlaps` - The sensor and the other body of every overlap of the step
+    pub(crate) fn record(&mut self, overlaps: Vec<(BosonObject, BosonObject)>) {
+        let mut overlapping = HashMap::with_capacity(overlaps.len());
+
+        for (sensor, other) in overlaps {
+            let pair = (sensor.id(), other.id());
+
+            if self.overlapping.remove(&pair).is_none() {
+                self.events.push(TriggerEvent {
+                    kind: TriggerKind::Enter,
+                    sensor: sensor.clone(),
+                    other: other.clone(),
+                });
+            }
+
+            overlapping.insert(pair, (sensor, other));
+        }
+
+        // Whatever is left stopped overlapping
+        for (_, (sensor, other)) in std::mem::replace(&mut self.overlapping, overlapping) {
+            self.events.push(TriggerEvent {
+                kind: TriggerKind::Exit,
+                sensor,
+                other,
+            });
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use cgmath::{InnerSpace, Vector3};
+
+/// Triangles that make up the shape of a collider, such as the floors and
+/// walls of a level.
+///
+/// Triangles have no inside, bodies are pushed out of whichever side of a
+/// triangle they are closest to. Triangle meshes are meant for static bodies,
+/// moving ones collide the same way but spin like the box around them.
+///
+/// # Example
+/// ```ignore
+/// // A quad on the ground made of two triangles
+/// let floor = TriMesh::new(
+///     &[[-5.0, 0.0, -5.0], [5.0, 0.0, -5.0], [5.0, 0.0, 5.0], [-5.0, 0.0, 5.0]],
+///     &[0, 2, 1, 0, 3, 2],
+/// );
+/// let ground = RigidBody::new(0.0, ColliderBuilder::TriMesh(Arc::new(floor)));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TriMesh {
+    vertices: Vec<Vector3<f64>>,
+    triangles: Vec<[u32; 3]>,
+    // Distance from the origin to the furthest vertex
+    bounding_radius: f64,
+}
+
+impl TriMesh {
+    /// Creates a triangle mesh from the positions and indices of a mesh.
+    ///
+    /// Triangles with an index past the positions or with no area are left
+    /// out, as are the indices after the last full triangle.
+    ///
+    /// # Arguments
+    /// * `positions` - The vertices in the body's local space
+    /// * `indices` - Three indices into `positions` per triangle
+    pub fn new(positions: &[[f32; 3]], indices: &[u32]) -> Self {
+        let vertices = positions
+            .iter()
+            .map(|position| {
+                Vector3::new(position[0] as f64, position[1] as f64, position[2] as f64)
+            })
+            .collect::<Vec<_>>();
+
+        let triangles = indices
+            .chunks_exact(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+            .filter(|triangle| {
+                if triangle
+                    .iter()
+                    .any(|index| *index as usize >= vertices.len())
+                {
+                    return false;
+                }
+
+                let [a, b, c] = triangle.map(|index| vertices[index as usize]);
+                (b - a).cross(c - a).magnitude2() > 1e-20
+            })
+            .collect();
+
+        let bounding_radius = vertices
+            .iter()
+            .map(|vertex| vertex.magnitude())
+            .fold(0.0, f64::max);
+
+        Self {
+            vertices,
+            triangles,
+            bounding_radius,
+        }
+    }
+
+    /// Returns the vertices of the mesh in the body's local space
+    pub fn vertices(&self) -> &[Vector3<f64>] {
+        &self.vertices
+    }
+
+    /// Returns the indices of the vertices of every triangle
+    pub fn triangles(&self) -> &[[u32; 3]] {
+        &self.triangles
+    }
+
+    /// Returns the corners of every triangle in the body's local space
+    pub fn iter_triangles(&self) -> impl Iterator<Item = [Vector3<f64>; 3]> + '_ {
+        self.triangles
+            .iter()
+            .map(|triangle| triangle.map(|index| self.vertices[index as usize]))
+    }
+
+    /// Returns the distance from the origin to the furthest vertex
+    pub fn bounding_radius(&self) -> f64 {
+        self.bounding_radius
+    }
+
+    /// Returns the mesh with every vertex moved by an offset
+    pub(crate) fn translated(&self, offset: Vector3<f64>) -> Self {
+        let vertices = self
+            .vertices
+            .iter()
+            .map(|vertex| vertex + offset)
+            .collect::<Vec<_>>();
+        let bounding_radius = vertices
+            .iter()
+            .map(|vertex| vertex.magnitude())
+            .fold(0.0, f64::max);
+
+        Self {
+            vertices,
+            triangles: self.triangles.clone(),
+            bounding_radius,
+        }
+    }
+
+    /// Finds the first triangle a ray in local space crosses.
+    ///
+    /// # Returns
+    /// How far along the ray the triangle is crossed and the unit normal of
+    /// the triangle facing back along the ray, or `None` if the ray misses
+    pub(crate) fn ray_hit(
+        &self,
+        origin: Vector3<f64>,
+        direction: Vector3<f64>,
+    ) -> Option<(f64, Vector3<f64>)> {
+        self.iter_triangles()
+            .filter_map(|triangle| ray_triangle_hit(origin, direction, triangle))
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+    }
+}
+
+/// Möller–Trumbore intersection of a ray with a triangle from either side
+fn ray_triangle_hit(
+    origin: Vector3<f64>,
+    direction: Vector3<f64>,
+    [a, b, c]: [Vector3<f64>; 3],
+) -> Option<(f64, Vector3<f64>)> {
+    let edge_ab = b - a;
+    let edge_ac = c - a;
+    let p = direction.cross(edge_ac);
+    let determinant = edge_ab.dot(p);
+
+    // The ray runs along the plane of the triangle
+    if determinant.abs() <= 1e-12 {
+        return None;
+    }
+
+    let offset = origin - a;
+    let u = offset.dot(p) / determinant;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = offset.cross(edge_ab);
+    let v = direction.dot(q) / determinant;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = edge_ac.dot(q) / determinant;
+    if t < 0.0 {
+        return None;
+    }
+
+    let normal = edge_ab.cross(edge_ac).normalize();
+    let normal = if normal.dot(direction) > 0.0 {
+        -normal
+    } else {
+        normal
+    };
+
+    Some((t, normal))
+}
@@ -0,0 +1,71 @@
+//! Change ticks that tell which molecules were added or changed since a
+//! system last ran.
+//!
+//! Every system run by a `Schedule` starts a new tick and remembers the tick
+//! of its last run. Molecules added or written while it runs are stamped with
+//! its tick, so the `Added` and `Changed` filters of the next run of any
+//! system see them, but the system doesn't see its own changes again.
+//! Changes made outside of a system are stamped with the tick of the next
+//! system to start.
+//!
+//! Unlike the modified flag, which the first `*_mod` iterator to see clears
+//! for everyone, ticks are never consumed, so any number of systems can react
+//! to the same change.
+
+use std::{
+    cell::Cell,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// The tick of the last system that started running
+static CHANGE_TICK: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// The ticks of the last and the current run of the system running on this thread
+    static SYSTEM_TICKS: Cell<Option<(u64, u64)>> = const { Cell::new(None) };
+}
+
+/// Returns the tick that changes made now are stamped with
+pub(crate) fn tick() -> u64 {
+    match SYSTEM_TICKS.get() {
+        Some((_, this_run)) => this_run,
+        None => CHANGE_TICK.load(Ordering::Acquire) + 1,
+    }
+}
+
+/// Returns the tick of the last run of the system running on this thread,
+/// changes stamped after it are new to the system
+pub(crate) fn last_run() -> Option<u64> {
+    SYSTEM_TICKS.get().map(|(last_run, _)| last_run)
+}
+
+/// Starts a new tick, changes made after it are stamped later
+pub(crate) fn advance() -> u64 {
+    CHANGE_TICK.fetch_add(1, Ordering::AcqRel) + 1
+}
+
+/// Runs a system with its own tick, queries inside it only see what was
+/// added or changed since its last run.
+///
+/// # Arguments
+/// - `last_run`: The tick of the last run of the system, updated to this run
+/// - `run`: Runs the system
+pub(crate) fn run_system<R>(last_run: &mut u64, run: impl FnOnce() -> R) -> R {
+    // Puts back the ticks of the system this one runs inside of, even if it panics
+    struct Restore(Option<(u64, u64)>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SYSTEM_TICKS.set(self.0);
+        }
+    }
+
+    let this_run = advance();
+    let restore = Restore(SYSTEM_TICKS.replace(Some((*last_run, this_run))));
+
+    let result = run();
+    drop(restore);
+
+    *last_run = this_run;
+    result
+}
@@ -0,0 +1,95 @@
+use crate::{Compound, Entity, MoleculeBundle};
+
+/// Handle to a single entity of a `Compound` that chains changes to it.
+///
+/// Every call acts on the compound right away, just like the method of the
+/// compound it stands in for, so the handle can be shared between threads
+/// the same way the compound is.
+///
+/// # Example
+/// ```ignore
+/// let player = compound
+///     .entity(compound.create_entity())
+///     .insert_bundle((Position { x: 0.0, y: 0.0 }, Velocity { x: 0.0, y: 0.0 }))
+///     .insert(Health { current: 100, max: 100 })
+///     .remove::<Spawning>()
+///     .id();
+///
+/// compound.entity(enemy).despawn();
+/// ```
+#[derive(Clone, Copy)]
+pub struct EntityMut<'a> {
+    compound: &'a Compound,
+    entity: Entity,
+}
+
+impl<'a> EntityMut<'a> {
+    /// Returns the entity the handle changes
+    pub fn id(&self) -> Entity {
+        self.entity
+    }
+
+    /// Returns the compound the entity is in
+    pub fn compound(&self) -> &'a Compound {
+        self.compound
+    }
+
+    /// Adds a molecule to the entity, replacing the one of the same type it already has
+    pub fn insert<T: Send + Sync + 'static>(self, molecule: T) -> Self {
+        self.compound.add_molecule(self.entity, molecule);
+        self
+    }
+
+    /// Adds a bundle of molecules to the entity, replacing any of the same
+    /// types it already has
+    pub fn insert_bundle(self, bundle: impl MoleculeBundle) -> Self {
+        bundle.add_to_entity(self.compound, self.entity);
+        self
+    }
+
+    /// Removes a molecule from the entity, if it has one
+    pub fn remove<T: Send + Sync + 'static>(self) -> Self {
+        self.compound.remove_molecule::<T>(self.entity);
+        self
+    }
+
+    /// Removes a molecule from the entity and returns it, ending the chain
+    pub fn take<T: Send + Sync + 'static>(self) -> Option<T> {
+        self.compound.remove_molecule::<T>(self.entity)
+    }
+
+    /// Checks if the entity has a molecule of a type
+    pub fn has<T: Send + Sync + 'static>(&self) -> bool {
+        self.compound.has_molecule::<T>(self.entity)
+    }
+
+    /// Removes the entity and all of its molecules, see `Compound::despawn`
+    ///
+    /// # Returns
+    /// Whether the entity had any molecules
+    pub fn despawn(self) -> bool {
+        self.compound.despawn(self.entity)
+    }
+}
+
+impl Compound {
+    /// Returns a handle that chains changes to an entity without naming it
+    /// again on every line.
+    ///
+    /// # Arguments
+    /// - `entity`: The entity to change, it doesn't need any molecules yet
+    ///
+    /// # Example
+    /// ```ignore
+    /// compound
+    ///     .entity(door)
+    ///     .remove::<Locked>()
+    ///     .insert_bundle((Open, Animation::new("door_open")));
+    /// ```
+    pub fn entity(&self, entity: Entity) -> EntityMut<'_> {
+        EntityMut {
+            compound: self,
+            entity,
+        }
+    }
+}
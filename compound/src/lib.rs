@@ -8,26 +8,62 @@
 //! - **Entity**: A unique identifier for a game object or data container
 //! - **Molecule**: A component that can be attached to an entity (analogous to components in traditional ECS)
 //! - **Compound**: The main ECS world that manages all entities and their molecules
-//! - **MoleculeBundle**: A collection of molecules that can be added to an entity together
+//! - **MoleculeBundle**: A collection of molecules that can be added to an entity together,
+//!   implemented for tuples and derived with `#[derive(MoleculeBundle)]` for named bundles
 //!
 //! ## Change Detection
 //! Compound includes built-in change detection through automatic modified flag management:
 //! - **Standard iterators** (`iter_mut_*`) automatically mark entities as modified
 //! - **Modified-only iterators** (`*_mod`) only process entities that have been marked as modified
 //! - **Unmodified iterators** (`*_unmod`) provide mutable access without affecting the modified flag
+//! - **Change filters** (`Added<T>`, `Changed<T>`) let queries visit molecules that were added or
+//!   written since the system last ran, tracked per system so they never consume each other's changes
 //!
 //! ## Iterator Variants
 //! All query patterns support multiple variants:
-//! - Read-only access: `iter_mol`, `iter_duo`, `iter_trio`
-//! - Mutable access: `iter_mut_mol`, `iter_mut_duo`, `iter_mut_trio`
+//! - Read-only access: `iter_mol`, `iter_duo`, `iter_trio`, `iter_quad`, `iter_quint`
+//! - Mutable access: `iter_mut_mol`, `iter_mut_duo`, `iter_mut_trio`, `iter_mut_quad`, `iter_mut_quint`
 //! - Modified-only: `*_mod` variants for reactive systems
 //! - Unmodified: `*_unmod` variants for internal maintenance
 //! - Exclusion filters: `*_without_*` variants to filter out specific components
 //!
+//! ## Queries
+//! `Compound::query` covers any combination the iterators don't, such as six or
+//! more components, mixed access or multiple exclusions:
+//! `compound.query::<(&Position, &mut Velocity), (Without<Frozen>, Without<Dead>)>()`
+//!
+//! ## Systems
+//! A `Schedule` runs `System`s that declare the molecules they read and write,
+//! running the ones that don't conflict in parallel and the rest in an order
+//! that honors their `before` and `after` constraints.
+//!
+//! ## Names
+//! Entities with a `Name` molecule can be looked up with `find_by_name`, so
+//! well known entities don't have to be tracked by ID.
+//!
+//! ## Resources
+//! World level data that doesn't belong to an entity (gravity, score, settings) is
+//! stored as resources, one per type: `insert_resource`, `resource` and `resource_mut`.
+//!
+//! ## Tags
+//! Zero sized molecules such as markers are only stored as a set of the entities
+//! that have them, without a lock per entity, so worlds with many tags stay small.
+//!
+//! ## Snapshots
+//! Molecules and resources registered with a `TypeRegistry` can be saved with
+//! `to_ron` or `to_bincode` and loaded back into a new compound with `from_ron`
+//! or `from_bincode`, for save games and scene files. `state_hash` hashes the
+//! same state to compare simulations for determinism.
+//!
 //! ## Thread Safety
 //! All operations in this ECS are thread-safe through the use of `RwLock`s and atomic operations.
 //! Multiple threads can read/write different component types simultaneously without blocking each other.
 //!
+//! Iterators can be nested inside the closures of other iterators. Reading a
+//! molecule the outer iterator already holds is allowed, while writing it or
+//! adding and removing molecules of a type being iterated panics instead of
+//! deadlocking, so collect those changes and apply them after the iteration.
+//!
 //! ## Example
 //! ```ignore
 //! use compound::Compound;
@@ -88,15 +124,38 @@
 //! }
 //! ```
 
+// Lets the code generated by the derive macros name this crate from inside it
+extern crate self as compound;
+
+mod change;
+mod entity_mut;
+mod lock;
+#[cfg(feature = "lock_diagnostics")]
+pub mod lock_diagnostics;
+mod name;
+mod query;
+mod schedule;
+mod snapshot;
+mod sparse_set;
+
+pub use compound_derive::MoleculeBundle;
+pub use entity_mut::EntityMut;
+pub use name::Name;
+pub use query::{Added, Changed, Query, QueryData, QueryFilter, QueryParam, With, Without};
+pub use schedule::{Schedule, System};
+pub use snapshot::TypeRegistry;
+
 const MAX_LOCK_TIMEOUT: Duration = Duration::from_millis(50);
 const SECOND_ATTEMPT_MAX_LOCK_TIMEOUT: Duration = Duration::from_secs(1);
 const MAX_WRITE_ATTEMPTS: u32 = 5;
 
 use std::{
-    any::{Any, TypeId},
+    any::{Any, TypeId, type_name},
     cmp::min,
     collections::HashMap,
+    fmt::Debug,
     ops::{Deref, DerefMut},
+    ptr::NonNull,
     sync::{
         Arc,
         atomic::{AtomicU64, Ordering},
@@ -104,7 +163,8 @@ use std::{
     time::Duration,
 };
 
-use parking_lot::RwLock;
+use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use sparse_set::SparseSet;
 
 use log::{debug, error, warn};
 
@@ -126,14 +186,6 @@ impl Default for Modified {
 }
 
 impl Modified {
-    /// Returns whether this entity has been modified since the last check.
-    ///
-    /// # Returns
-    /// `true` if the entity has been modified, `false` otherwise
-    fn is_modified(&self) -> bool {
-        self.0
-    }
-
     /// Marks this entity as modified.
     ///
     /// This is automatically called by mutable iterator methods to indicate
@@ -142,13 +194,14 @@ impl Modified {
         self.0 = true;
     }
 
-    /// Clears the modified flag for this entity.
+    /// Clears the modified flag for this entity, returning whether it was set.
     ///
-    /// This is automatically called by `*_mod` iterator methods after processing
+    /// This is automatically called by `*_mod` iterator methods before processing
     /// a modified entity, preventing it from being processed again until it's
-    /// modified by another system.
-    fn clear_modified(&mut self) {
-        self.0 = false;
+    /// modified by another system. The flag isn't locked while the entity is
+    /// processed, so changes made meanwhile mark it modified again.
+    fn take_modified(&mut self) -> bool {
+        std::mem::replace(&mut self.0, false)
     }
 }
 
@@ -178,6 +231,8 @@ pub type Entity = u64;
 /// Lock poisoning is handled automatically with recovery and logging.
 pub struct MoleculeCell<T: Send + Sync + 'static> {
     data: RwLock<T>,
+    /// The change tick the molecule was last written at
+    changed: AtomicU64,
 }
 
 impl<T: Send + Sync + 'static> MoleculeCell<T> {
@@ -196,9 +251,15 @@ impl<T: Send + Sync + 'static> MoleculeCell<T> {
     fn new(data: T) -> Self {
         MoleculeCell {
             data: RwLock::new(data),
+            changed: AtomicU64::new(change::tick()),
         }
     }
 
+    /// Returns the change tick the molecule was last written at
+    fn changed(&self) -> u64 {
+        self.changed.load(Ordering::Relaxed)
+    }
+
     /// Acquires a read lock on the component data.
     ///
     /// This method allows multiple concurrent readers but blocks writers.
@@ -211,23 +272,35 @@ impl<T: Send + Sync + 'static> MoleculeCell<T> {
     /// # Panics
     /// This method should not panic under normal circumstances as it handles
     /// poisoned locks gracefully.
-    fn read(&self) -> impl Deref<Target = T> + '_ {
-        match self.data.try_read_for(MAX_LOCK_TIMEOUT) {
-            Some(data) => data,
-            None => {
-                warn!("Read lock timeout - potential deadlock avoided");
-                self.data
-                    .try_read_for(SECOND_ATTEMPT_MAX_LOCK_TIMEOUT)
-                    .expect("Failed to acquire read lock after extended timeout")
-            }
+    fn read(&self) -> MoleculeRef<'_, T> {
+        // Tags hold no data, so there is nothing to guard
+        if size_of::<T>() == 0 {
+            return MoleculeRef::Tag(unsafe { &*self.data.data_ptr() });
         }
+
+        let address = self.data.data_ptr() as usize;
+        let data = match lock::cell_lock(address) {
+            Some(true) => panic!(
+                "{} is already being written by this thread and can't be read",
+                type_name::<T>()
+            ),
+            // This thread already reads it so no writer can hold it, even one queued up
+            Some(false) => self.data.read_recursive(),
+            None => self.lock_read(lock::holds_cells()),
+        };
+
+        lock::lock_cell(address, false);
+        #[cfg(feature = "lock_diagnostics")]
+        lock_diagnostics::acquired(address, lock_diagnostics::molecule_class::<T>(), false);
+        MoleculeRef::Locked(data)
     }
 
     /// Acquires a write lock on the component data.
     ///
     /// This method provides exclusive access to the component data, blocking
-    /// all other readers and writers. If the lock is poisoned, it recovers
-    /// the data and logs a warning.
+    /// all other readers and writers, and stamps the molecule as changed for
+    /// the `Changed` filter. If the lock is poisoned, it recovers the data and
+    /// logs a warning.
     ///
     /// # Returns
     /// A RAII guard that dereferences to `&mut T`
@@ -235,12 +308,89 @@ impl<T: Send + Sync + 'static> MoleculeCell<T> {
     /// # Panics
     /// This method should not panic under normal circumstances as it handles
     /// poisoned locks gracefully.
-    fn write(&self) -> impl DerefMut<Target = T> + '_ {
+    fn write(&self) -> MoleculeMut<'_, T> {
+        let data = self.write_untracked();
+        self.changed.store(change::tick(), Ordering::Relaxed);
+
+        data
+    }
+
+    /// Acquires a write lock without stamping the molecule as changed, for the
+    /// `*_unmod` iterators that don't take part in change detection.
+    fn write_untracked(&self) -> MoleculeMut<'_, T> {
+        if size_of::<T>() == 0 {
+            return MoleculeMut::Tag(unsafe { &mut *self.data.data_ptr() });
+        }
+
+        let address = self.data.data_ptr() as usize;
+        if lock::cell_lock(address).is_some() {
+            panic!(
+                "{} is already locked by this thread and can't be written",
+                type_name::<T>()
+            );
+        }
+
+        let data = self.lock_write();
+        lock::lock_cell(address, true);
+        #[cfg(feature = "lock_diagnostics")]
+        lock_diagnostics::acquired(address, lock_diagnostics::molecule_class::<T>(), true);
+        MoleculeMut::Locked(data)
+    }
+
+    // A thread that already holds a cell reads recursively so it never waits on a queued writer
+    fn lock_read(&self, recursive: bool) -> RwLockReadGuard<'_, T> {
+        #[cfg(feature = "lock_diagnostics")]
+        lock_diagnostics::acquiring(
+            self.data.data_ptr() as usize,
+            lock_diagnostics::molecule_class::<T>(),
+            false,
+            recursive,
+        );
+
+        let try_read = |timeout| {
+            if recursive {
+                self.data.try_read_recursive_for(timeout)
+            } else {
+                self.data.try_read_for(timeout)
+            }
+        };
+
+        match try_read(MAX_LOCK_TIMEOUT) {
+            Some(data) => data,
+            None => {
+                warn!("Read lock timeout - potential deadlock avoided");
+                #[cfg(feature = "lock_diagnostics")]
+                lock_diagnostics::contended(
+                    self.data.data_ptr() as usize,
+                    lock_diagnostics::molecule_class::<T>(),
+                    false,
+                );
+                try_read(SECOND_ATTEMPT_MAX_LOCK_TIMEOUT)
+                    .expect("Failed to acquire read lock after extended timeout")
+            }
+        }
+    }
+
+    fn lock_write(&self) -> RwLockWriteGuard<'_, T> {
+        #[cfg(feature = "lock_diagnostics")]
+        lock_diagnostics::acquiring(
+            self.data.data_ptr() as usize,
+            lock_diagnostics::molecule_class::<T>(),
+            true,
+            false,
+        );
+
         if let Some(data) = self.data.try_write_for(MAX_LOCK_TIMEOUT) {
             return data;
         }
 
         warn!("Write lock contention detected, retrying...");
+        #[cfg(feature = "lock_diagnostics")]
+        lock_diagnostics::contended(
+            self.data.data_ptr() as usize,
+            lock_diagnostics::molecule_class::<T>(),
+            true,
+        );
 
         for attempt in 1..=MAX_WRITE_ATTEMPTS {
             let timeout = Duration::from_millis(100 * attempt as u64);
@@ -259,6 +409,69 @@ impl<T: Send + Sync + 'static> MoleculeCell<T> {
     }
 }
 
+/// Read access to the data of a `MoleculeCell`, dereferences to `&T`.
+///
+/// Zero sized molecules are handed out without taking the lock.
+pub enum MoleculeRef<'a, T> {
+    Locked(RwLockReadGuard<'a, T>),
+    Tag(&'a T),
+}
+
+impl<T> Drop for MoleculeRef<'_, T> {
+    fn drop(&mut self) {
+        if let MoleculeRef::Locked(guard) = self {
+            lock::unlock_cell(&**guard as *const T as usize);
+        }
+    }
+}
+
+impl<T> Deref for MoleculeRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match self {
+            MoleculeRef::Locked(guard) => guard,
+            MoleculeRef::Tag(tag) => tag,
+        }
+    }
+}
+
+/// Write access to the data of a `MoleculeCell`, dereferences to `&mut T`.
+///
+/// Zero sized molecules are handed out without taking the lock.
+pub enum MoleculeMut<'a, T> {
+    Locked(RwLockWriteGuard<'a, T>),
+    Tag(&'a mut T),
+}
+
+impl<T> Drop for MoleculeMut<'_, T> {
+    fn drop(&mut self) {
+        if let MoleculeMut::Locked(guard) = self {
+            lock::unlock_cell(&**guard as *const T as usize);
+        }
+    }
+}
+
+impl<T> Deref for MoleculeMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match self {
+            MoleculeMut::Locked(guard) => guard,
+            MoleculeMut::Tag(tag) => tag,
+        }
+    }
+}
+
+impl<T> DerefMut for MoleculeMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        match self {
+            MoleculeMut::Locked(guard) => guard,
+            MoleculeMut::Tag(tag) => tag,
+        }
+    }
+}
+
 /// Storage container for all components of a specific type.
 ///
 /// `MoleculeStorage` maintains a mapping from entity IDs to their corresponding
@@ -269,9 +482,13 @@ impl<T: Send + Sync + 'static> MoleculeCell<T> {
 /// - `T`: The component type stored in this container
 ///
 /// # Internal Structure
-/// Uses a `HashMap` for O(1) average-case lookup by entity ID.
+/// Uses a `SparseSet`, the cells are packed in a dense vector so iterating
+/// is a linear scan, and looking up the cell of an entity while iterating
+/// another storage is an array index instead of a hash. Zero sized molecules
+/// (tags) only store the set of entities that have them, with no cell or
+/// lock per entity.
 pub struct MoleculeStorage<T: Send + Sync + 'static> {
-    compounds: HashMap<Entity, MoleculeCell<T>>,
+    compounds: SparseSet<MoleculeCell<T>>,
 }
 
 impl<T: Send + Sync + 'static> MoleculeStorage<T> {
@@ -285,12 +502,83 @@ impl<T: Send + Sync + 'static> MoleculeStorage<T> {
     /// let storage: MoleculeStorage<Position> = MoleculeStorage::new();
     /// ```
     fn new() -> Self {
+        if size_of::<T>() == 0 {
+            return Self {
+                // A zero sized value is read out of a dangling pointer,
+                // there are no bytes to read
+                compounds: SparseSet::tags(|| {
+                    MoleculeCell::new(unsafe { NonNull::<T>::dangling().read() })
+                }),
+            };
+        }
+
         Self {
-            compounds: HashMap::new(),
+            compounds: SparseSet::new(),
         }
     }
 }
 
+/// Moves a molecule that was taken out of one `Compound` into an entity of another.
+type MoleculeTransfer = Box<dyn FnOnce(&Compound, Entity) + Send>;
+
+/// Type-erased access to the storage of a component type.
+///
+/// Every storage in a `Compound` is kept behind this trait, which lets the
+/// compound act on all molecules of an entity without knowing their types.
+trait ErasedStorage: Send + Sync {
+    /// Returns the storage as `Any` so it can be downcast to its concrete type
+    fn as_any(&self) -> &dyn Any;
+
+    /// Removes the molecule of an entity from the storage.
+    ///
+    /// # Arguments
+    /// - `entity`: The entity to take the molecule from
+    ///
+    /// # Returns
+    /// A transfer that adds the molecule to an entity of another `Compound`,
+    /// or `None` if the entity has no molecule of this type
+    fn take_molecule(&self, entity: Entity) -> Option<MoleculeTransfer>;
+
+    /// Returns whether an entity has a molecule in the storage
+    fn contains(&self, entity: Entity) -> bool;
+
+    /// Returns every entity with a molecule in the storage
+    fn entities(&self) -> Vec<Entity>;
+}
+
+impl<T: Send + Sync + 'static> ErasedStorage for Arc<RwLock<MoleculeStorage<T>>> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn take_molecule(&self, entity: Entity) -> Option<MoleculeTransfer> {
+        let cell = lock::write_storage(self).compounds.remove(&entity)?;
+        let molecule = cell.data.into_inner();
+
+        Some(Box::new(move |compound: &Compound, entity: Entity| {
+            compound.add_molecule(entity, molecule);
+        }))
+    }
+
+    fn contains(&self, entity: Entity) -> bool {
+        lock::read_storage(self).compounds.get(&entity).is_some()
+    }
+
+    fn entities(&self) -> Vec<Entity> {
+        lock::read_storage(self)
+            .compounds
+            .iter()
+            .map(|(entity, _)| *entity)
+            .collect()
+    }
+}
+
+impl Debug for dyn ErasedStorage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ErasedStorage")
+    }
+}
+
 /// The main ECS world that manages all entities and their components.
 ///
 /// `Compound` is the central data structure of the ECS. It maintains:
@@ -320,7 +608,11 @@ pub struct Compound {
     /// Atomic counter for generating unique entity IDs
     num_entities: EntityId,
     /// Type-erased storage for all component types, indexed by TypeId
-    storages: RwLock<HashMap<TypeId, Box<dyn Any + Send + Sync>>>,
+    storages: RwLock<HashMap<TypeId, Box<dyn ErasedStorage>>>,
+    /// World level data that isn't attached to an entity, indexed by TypeId
+    resources: RwLock<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>,
+    /// Entities with each `Name`, oldest first
+    names: RwLock<HashMap<String, Vec<Entity>>>,
 }
 
 impl Compound {
@@ -338,6 +630,8 @@ impl Compound {
         Self {
             num_entities: AtomicU64::new(0),
             storages: RwLock::new(HashMap::new()),
+            resources: RwLock::new(HashMap::new()),
+            names: RwLock::new(HashMap::new()),
         }
     }
 
@@ -399,6 +693,7 @@ impl Compound {
             storages
                 .get(&type_id)
                 .unwrap_unchecked()
+                .as_any()
                 .downcast_ref::<Arc<RwLock<MoleculeStorage<T>>>>()
                 .unwrap_unchecked()
         };
@@ -434,6 +729,7 @@ impl Compound {
             storages
                 .get(&type_id)
                 .unwrap_unchecked()
+                .as_any()
                 .downcast_ref::<Arc<RwLock<MoleculeStorage<T>>>>()
                 .unwrap_unchecked()
         };
@@ -463,13 +759,64 @@ impl Compound {
     /// compound.add_molecule(entity, Name("Player".to_string()));
     /// ```
     pub fn add_molecule<T: Send + Sync + 'static>(&self, entity: Entity, molecule: T) {
+        let storage = self.get_or_create_storage::<T>();
+        let mut storage = lock::write_storage(&storage);
         // unsafe {
-        self.get_or_create_storage::<T>()
-            .write()
+        let replaced = storage
             // .unwrap_unchecked()
             .compounds
             .insert(entity, MoleculeCell::new(molecule));
         // };
+
+        if TypeId::of::<T>() == TypeId::of::<Name>() {
+            let added = storage.compounds.get(&entity).map(|cell| cell.read());
+            self.index_name(
+                entity,
+                replaced.as_ref().map(|cell| cell.read()).as_deref(),
+                added.as_deref(),
+            );
+        }
+    }
+
+    /// Removes a component (molecule) from an entity and returns it.
+    ///
+    /// The rest of the entity is left as it is and counts as modified.
+    ///
+    /// # Arguments
+    /// - `entity`: The entity to remove the component from
+    ///
+    /// # Type Parameters
+    /// - `T`: The type of component to remove
+    ///
+    /// # Returns
+    /// The removed component, or `None` if the entity does not have one of this type
+    ///
+    /// # Example
+    /// ```ignore
+    /// let entity = compound.spawn((Health { current: 100, max: 100 },));
+    /// let health = compound.remove_molecule::<Health>(entity);
+    /// ```
+    pub fn remove_molecule<T: Send + Sync + 'static>(&self, entity: Entity) -> Option<T> {
+        // The storages are only read so removing never creates a storage
+        let storage = self
+            .storages
+            .read()
+            .get(&TypeId::of::<T>())?
+            .as_any()
+            .downcast_ref::<Arc<RwLock<MoleculeStorage<T>>>>()?
+            .clone();
+
+        let cell = lock::write_storage(&storage).compounds.remove(&entity)?;
+        self.index_name(entity, Some(&*cell.read()), None);
+
+        if let Some(modified_flag) = lock::read_storage(&self.get_or_create_storage::<Modified>())
+            .compounds
+            .get(&entity)
+        {
+            modified_flag.write().set_modified();
+        }
+
+        Some(cell.data.into_inner())
     }
 
     /// Adds multiple components to an entity using a bundle.
@@ -522,6 +869,265 @@ impl Compound {
         entity
     }
 
+    /// Moves an entity and all of its molecules to another compound.
+    ///
+    /// The molecules are removed from this compound and added to a new entity
+    /// of `other`, which counts as modified there. Entity IDs are only unique
+    /// within a single compound, so the entity gets a new ID in `other`.
+    ///
+    /// # Arguments
+    /// - `entity`: The entity to move
+    /// - `other`: The compound to move the entity to
+    ///
+    /// # Returns
+    /// The ID of the entity in `other`, callers that keep references to the
+    /// entity should map the old ID to this one
+    ///
+    /// # Example
+    /// ```ignore
+    /// let player = level_one.spawn((Position { x: 0.0, y: 0.0 },));
+    /// let player = level_one.move_entity(player, &level_two);
+    ///
+    /// level_two.iter_mol::<Position, _>(|entity, pos| {
+    ///     assert_eq!(entity, player);
+    /// });
+    /// ```
+    pub fn move_entity(&self, entity: Entity, other: &Compound) -> Entity {
+        if std::ptr::eq(self, other) {
+            return entity;
+        }
+
+        self.unindex_name(entity);
+
+        // Take every molecule before adding any so the storages of both
+        // compounds are never locked at the same time
+        let transfers: Vec<MoleculeTransfer> = self
+            .storages
+            .read()
+            .values()
+            .filter_map(|storage| storage.take_molecule(entity))
+            .collect();
+
+        let moved = other.create_entity();
+        for transfer in transfers {
+            transfer(other, moved);
+        }
+        other.add_molecule(moved, Modified::default());
+
+        debug!("Moved entity {} to entity {}", entity, moved);
+        moved
+    }
+
+    /// Moves every entity of this compound to another one, such as a level
+    /// that was loaded into a world of its own in the background.
+    ///
+    /// Entities are moved oldest first, so they keep their order, and this
+    /// compound is left empty apart from its resources.
+    ///
+    /// # Arguments
+    /// - `other`: The compound to move the entities to
+    ///
+    /// # Returns
+    /// The ID of every moved entity in `other` by its ID in this compound,
+    /// molecules that refer to other entities should be remapped with it
+    ///
+    /// # Example
+    /// ```ignore
+    /// let moved = loading.move_all_entities(&main);
+    /// main.iter_mut_mol(|_entity, parent: &mut Parent| parent.0 = moved[&parent.0]);
+    /// ```
+    pub fn move_all_entities(&self, other: &Compound) -> HashMap<Entity, Entity> {
+        let mut entities = self
+            .storages
+            .read()
+            .values()
+            .flat_map(|storage| storage.entities())
+            .collect::<Vec<_>>();
+        entities.sort_unstable();
+        entities.dedup();
+
+        let moved = entities
+            .into_iter()
+            .map(|entity| (entity, self.move_entity(entity, other)))
+            .collect::<HashMap<_, _>>();
+
+        debug!("Moved {} entities", moved.len());
+        moved
+    }
+
+    /// Removes an entity and all of its molecules.
+    ///
+    /// Entity IDs are never reused, so an ID held after the entity is
+    /// despawned never names another entity.
+    ///
+    /// # Arguments
+    /// - `entity`: The entity to remove
+    ///
+    /// # Returns
+    /// Whether the entity had any molecules to remove
+    ///
+    /// # Example
+    /// ```ignore
+    /// compound.iter_mol(|entity, health: &Health| {
+    ///     if health.current == 0 {
+    ///         dead.push(entity);
+    ///     }
+    /// });
+    ///
+    /// for entity in dead {
+    ///     compound.despawn(entity);
+    /// }
+    /// ```
+    pub fn despawn(&self, entity: Entity) -> bool {
+        self.unindex_name(entity);
+
+        // Dropped after the lock is released so molecules are never dropped while the compound is locked
+        let molecules: Vec<MoleculeTransfer> = self
+            .storages
+            .read()
+            .values()
+            .filter_map(|storage| storage.take_molecule(entity))
+            .collect();
+
+        let despawned = !molecules.is_empty();
+        drop(molecules);
+
+        if despawned {
+            debug!("Despawned entity {}", entity);
+        }
+
+        despawned
+    }
+
+    /// Checks if an entity has a molecule of a type, without iterating.
+    ///
+    /// # Type Parameters
+    /// - `T`: The type of component to look for
+    ///
+    /// # Example
+    /// ```ignore
+    /// if compound.has_molecule::<Frozen>(enemy) {
+    ///     return;
+    /// }
+    /// ```
+    pub fn has_molecule<T: Send + Sync + 'static>(&self, entity: Entity) -> bool {
+        self.storages
+            .read()
+            .get(&TypeId::of::<T>())
+            .is_some_and(|storage| storage.contains(entity))
+    }
+
+    /// Checks if an entity exists, which is as long as it has any molecule.
+    ///
+    /// Spawned entities always exist until their molecules are removed or the
+    /// compound is cleared.
+    ///
+    /// # Example
+    /// ```ignore
+    /// if !compound.entity_exists(target) {
+    ///     ai.target = None;
+    /// }
+    /// ```
+    pub fn entity_exists(&self, entity: Entity) -> bool {
+        self.storages
+            .read()
+            .values()
+            .any(|storage| storage.contains(entity))
+    }
+
+    /// Starts a new change tick, for code outside of a `Schedule` that checks
+    /// for added or changed molecules on its own.
+    ///
+    /// Ticks are shared by every compound, systems run by a schedule track
+    /// their own without calling this.
+    ///
+    /// # Returns
+    /// The tick to pass to `Query::since` the next time the code checks, so
+    /// it sees what changed from now on
+    ///
+    /// # Example
+    /// ```ignore
+    /// let now = compound.advance_change_tick();
+    /// compound
+    ///     .query::<(&Health,), Changed<Health>>()
+    ///     .since(self.last_check)
+    ///     .for_each(|entity, (health,)| self.update_bar(entity, health));
+    /// self.last_check = now;
+    /// ```
+    pub fn advance_change_tick(&self) -> u64 {
+        change::advance()
+    }
+
+    /// Removes every entity and all of their molecules, such as when leaving a level.
+    ///
+    /// Resources are kept, and entity IDs keep counting up from where they
+    /// were so an ID held from before the clear never names a new entity.
+    ///
+    /// # Example
+    /// ```ignore
+    /// compound.clear();
+    /// load_level(&compound, "game://levels/two.lvl")?;
+    /// ```
+    pub fn clear(&self) {
+        // Dropped after the locks are released so molecules are never dropped while the compound is locked
+        let storages = std::mem::take(&mut *self.storages.write());
+        self.names.write().clear();
+
+        debug!("Cleared {} molecule storages", storages.len());
+        drop(storages);
+    }
+
+    /// Removes every molecule of a type from every entity.
+    ///
+    /// The rest of the entities that had one is left as it is and counts as modified.
+    ///
+    /// # Type Parameters
+    /// - `T`: The type of component to remove
+    ///
+    /// # Returns
+    /// How many molecules were removed
+    ///
+    /// # Example
+    /// ```ignore
+    /// // Every enemy of the last level forgets who it was chasing
+    /// compound.clear_molecules::<Target>();
+    /// ```
+    pub fn clear_molecules<T: Send + Sync + 'static>(&self) -> usize {
+        let Some(storage) = self.storages.write().remove(&TypeId::of::<T>()) else {
+            return 0;
+        };
+
+        if TypeId::of::<T>() == TypeId::of::<Name>() {
+            self.names.write().clear();
+        }
+
+        let entities: Vec<Entity> = storage
+            .as_any()
+            .downcast_ref::<Arc<RwLock<MoleculeStorage<T>>>>()
+            .map(|storage| {
+                lock::read_storage(storage)
+                    .compounds
+                    .iter()
+                    .map(|(entity, _)| *entity)
+                    .collect()
+            })
+            .unwrap_or_default();
+        drop(storage);
+
+        if TypeId::of::<T>() != TypeId::of::<Modified>() {
+            let modified = self.get_or_create_storage::<Modified>();
+            let modified = lock::read_storage(&modified);
+
+            for entity in entities.iter() {
+                if let Some(modified_flag) = modified.compounds.get(entity) {
+                    modified_flag.write().set_modified();
+                }
+            }
+        }
+
+        entities.len()
+    }
+
     // Singular Molecule accessors ====================================
 
     /// Iterates over all entities that have a specific component type.
@@ -559,7 +1165,7 @@ impl Compound {
         //         poisoned.into_inner()
         //     }
         // };
-        let storage_guard = storage.read();
+        let storage_guard = lock::read_storage(&storage);
 
         for (entity, cell) in &storage_guard.compounds {
             let data = cell.read();
@@ -617,7 +1223,7 @@ impl Compound {
         //         poisoned.into_inner()
         //     }
         // };
-        let storage_guard = storage.read();
+        let storage_guard = lock::read_storage(&storage);
 
         let modified_storage = self.get_or_create_storage::<Modified>();
         // let modified_storage_guard = match modified_storage.read() {
@@ -627,17 +1233,14 @@ impl Compound {
         //         poisoned.into_inner()
         //     }
         // };
-        let modified_storage_guard = modified_storage.read();
+        let modified_storage_guard = lock::read_storage(&modified_storage);
 
         for (entity, cell) in &storage_guard.compounds {
-            if let Some(modified_flag) = modified_storage_guard.compounds.get(entity) {
-                let mut modified_flag = modified_flag.write();
-
-                if modified_flag.is_modified() {
-                    let data = cell.read();
-                    f(*entity, &*data);
-                    modified_flag.clear_modified();
-                }
+            if let Some(modified_flag) = modified_storage_guard.compounds.get(entity)
+                && modified_flag.write().take_modified()
+            {
+                let data = cell.read();
+                f(*entity, &*data);
             }
         }
     }
@@ -669,10 +1272,10 @@ impl Compound {
         F: FnMut(Entity, &T) + Send + Sync,
     {
         let t_storage = self.get_or_create_storage::<T>();
-        let t_storage_guard = t_storage.read();
+        let t_storage_guard = lock::read_storage(&t_storage);
 
         let w_storage = self.get_or_create_storage::<W>();
-        let w_storage_guard = w_storage.read();
+        let w_storage_guard = lock::read_storage(&w_storage);
 
         for (entity, t_cell) in &t_storage_guard.compounds {
             if let Some(_) = w_storage_guard.compounds.get(entity) {
@@ -719,25 +1322,22 @@ impl Compound {
         F: FnMut(Entity, &T) + Send + Sync,
     {
         let t_storage = self.get_or_create_storage::<T>();
-        let t_storage_guard = t_storage.read();
+        let t_storage_guard = lock::read_storage(&t_storage);
 
         let w_storage = self.get_or_create_storage::<W>();
-        let w_storage_guard = w_storage.read();
+        let w_storage_guard = lock::read_storage(&w_storage);
 
         let modified_storage = self.get_or_create_storage::<Modified>();
-        let modified_storage_guard = modified_storage.read();
+        let modified_storage_guard = lock::read_storage(&modified_storage);
 
         for (entity, t_cell) in &t_storage_guard.compounds {
             if let Some(_) = w_storage_guard.compounds.get(entity) {
             } else {
-                if let Some(modified_flag) = modified_storage_guard.compounds.get(entity) {
-                    let mut modified_flag = modified_flag.write();
-
-                    if modified_flag.is_modified() {
-                        let t_data = t_cell.read();
-                        f(*entity, &*t_data);
-                        modified_flag.clear_modified();
-                    }
+                if let Some(modified_flag) = modified_storage_guard.compounds.get(entity)
+                    && modified_flag.write().take_modified()
+                {
+                    let t_data = t_cell.read();
+                    f(*entity, &*t_data);
                 }
             }
         }
@@ -768,10 +1368,10 @@ impl Compound {
         F: FnMut(Entity, &mut T) + Send + Sync,
     {
         let storage = self.get_or_create_storage::<T>();
-        let storage_guard = storage.read();
+        let storage_guard = lock::read_storage(&storage);
 
         let modified_storage = self.get_or_create_storage::<Modified>();
-        let modified_storage_guard = modified_storage.read();
+        let modified_storage_guard = lock::read_storage(&modified_storage);
 
         for (entity, cell) in &storage_guard.compounds {
             // Set the modified flag for the entity
@@ -821,10 +1421,10 @@ impl Compound {
         F: FnMut(Entity, &mut T) + Send + Sync,
     {
         let storage = self.get_or_create_storage::<T>();
-        let storage_guard = storage.read();
+        let storage_guard = lock::read_storage(&storage);
 
         for (entity, cell) in &storage_guard.compounds {
-            let mut data = cell.write();
+            let mut data = cell.write_untracked();
             f(*entity, &mut *data);
         }
     }
@@ -868,20 +1468,17 @@ impl Compound {
         F: FnMut(Entity, &mut T) + Send + Sync,
     {
         let storage = self.get_or_create_storage::<T>();
-        let storage_guard = storage.read();
+        let storage_guard = lock::read_storage(&storage);
 
         let modified_storage = self.get_or_create_storage::<Modified>();
-        let modified_storage_guard = modified_storage.read();
+        let modified_storage_guard = lock::read_storage(&modified_storage);
 
         for (entity, cell) in &storage_guard.compounds {
-            if let Some(modified_flag) = modified_storage_guard.compounds.get(entity) {
-                let mut modified_flag = modified_flag.write();
-
-                if modified_flag.is_modified() {
-                    let mut data = cell.write();
-                    f(*entity, &mut *data);
-                    modified_flag.clear_modified();
-                }
+            if let Some(modified_flag) = modified_storage_guard.compounds.get(entity)
+                && modified_flag.write().take_modified()
+            {
+                let mut data = cell.write();
+                f(*entity, &mut *data);
             }
         }
     }
@@ -912,13 +1509,13 @@ impl Compound {
         F: FnMut(Entity, &mut T) + Send + Sync,
     {
         let t_storage = self.get_or_create_storage::<T>();
-        let t_storage_guard = t_storage.read();
+        let t_storage_guard = lock::read_storage(&t_storage);
 
         let w_storage = self.get_or_create_storage::<W>();
-        let w_storage_guard = w_storage.read();
+        let w_storage_guard = lock::read_storage(&w_storage);
 
         let modified_storage = self.get_or_create_storage::<Modified>();
-        let modified_storage_guard = modified_storage.read();
+        let modified_storage_guard = lock::read_storage(&modified_storage);
 
         for (entity, t_cell) in &t_storage_guard.compounds {
             if let Some(_) = w_storage_guard.compounds.get(entity) {
@@ -974,15 +1571,15 @@ impl Compound {
         F: FnMut(Entity, &mut T) + Send + Sync,
     {
         let t_storage = self.get_or_create_storage::<T>();
-        let t_storage_guard = t_storage.read();
+        let t_storage_guard = lock::read_storage(&t_storage);
 
         let w_storage = self.get_or_create_storage::<W>();
-        let w_storage_guard = w_storage.read();
+        let w_storage_guard = lock::read_storage(&w_storage);
 
         for (entity, t_cell) in &t_storage_guard.compounds {
             if let Some(_) = w_storage_guard.compounds.get(entity) {
             } else {
-                let mut t_data = t_cell.write();
+                let mut t_data = t_cell.write_untracked();
                 f(*entity, &mut *t_data);
             }
         }
@@ -1021,25 +1618,22 @@ impl Compound {
         F: FnMut(Entity, &mut T) + Send + Sync,
     {
         let t_storage = self.get_or_create_storage::<T>();
-        let t_storage_guard = t_storage.read();
+        let t_storage_guard = lock::read_storage(&t_storage);
 
         let w_storage = self.get_or_create_storage::<W>();
-        let w_storage_guard = w_storage.read();
+        let w_storage_guard = lock::read_storage(&w_storage);
 
         let modified_storage = self.get_or_create_storage::<Modified>();
-        let modified_storage_guard = modified_storage.read();
+        let modified_storage_guard = lock::read_storage(&modified_storage);
 
         for (entity, t_cell) in &t_storage_guard.compounds {
             if let Some(_) = w_storage_guard.compounds.get(entity) {
             } else {
-                if let Some(modified_flag) = modified_storage_guard.compounds.get(entity) {
-                    let mut modified_flag = modified_flag.write();
-
-                    if modified_flag.is_modified() {
-                        let mut t_data = t_cell.write();
-                        f(*entity, &mut *t_data);
-                        modified_flag.clear_modified();
-                    }
+                if let Some(modified_flag) = modified_storage_guard.compounds.get(entity)
+                    && modified_flag.write().take_modified()
+                {
+                    let mut t_data = t_cell.write();
+                    f(*entity, &mut *t_data);
                 }
             }
         }
@@ -1076,9 +1670,9 @@ impl Compound {
         let t1_storage = self.get_or_create_storage::<T1>();
         let t2_storage = self.get_or_create_storage::<T2>();
 
-        let t1_storage_guard = t1_storage.read();
+        let t1_storage_guard = lock::read_storage(&t1_storage);
 
-        let t2_storage_guard = t2_storage.read();
+        let t2_storage_guard = lock::read_storage(&t2_storage);
 
         for (entity, t1_cell) in &t1_storage_guard.compounds {
             if let Some(t2_cell) = t2_storage_guard.compounds.get(entity) {
@@ -1123,25 +1717,21 @@ impl Compound {
         let t1_storage = self.get_or_create_storage::<T1>();
         let t2_storage = self.get_or_create_storage::<T2>();
 
-        let t1_storage_guard = t1_storage.read();
+        let t1_storage_guard = lock::read_storage(&t1_storage);
 
-        let t2_storage_guard = t2_storage.read();
+        let t2_storage_guard = lock::read_storage(&t2_storage);
 
         let modified_storage = self.get_or_create_storage::<Modified>();
-        let modified_storage_guard = modified_storage.read();
+        let modified_storage_guard = lock::read_storage(&modified_storage);
 
         for (entity, t1_cell) in &t1_storage_guard.compounds {
-            if let Some(t2_cell) = t2_storage_guard.compounds.get(entity) {
-                if let Some(modified_flag) = modified_storage_guard.compounds.get(entity) {
-                    let mut modified_flag = modified_flag.write();
-
-                    if modified_flag.is_modified() {
-                        let t1_data = t1_cell.read();
-                        let t2_data = t2_cell.read();
-                        f(*entity, &*t1_data, &*t2_data);
-                        modified_flag.clear_modified();
-                    }
-                }
+            if let Some(t2_cell) = t2_storage_guard.compounds.get(entity)
+                && let Some(modified_flag) = modified_storage_guard.compounds.get(entity)
+                && modified_flag.write().take_modified()
+            {
+                let t1_data = t1_cell.read();
+                let t2_data = t2_cell.read();
+                f(*entity, &*t1_data, &*t2_data);
             }
         }
     }
@@ -1179,12 +1769,12 @@ impl Compound {
         let t1_storage = self.get_or_create_storage::<T1>();
         let t2_storage = self.get_or_create_storage::<T2>();
 
-        let t1_storage_guard = t1_storage.read();
+        let t1_storage_guard = lock::read_storage(&t1_storage);
 
-        let t2_storage_guard = t2_storage.read();
+        let t2_storage_guard = lock::read_storage(&t2_storage);
 
         let w_storage = self.get_or_create_storage::<W>();
-        let w_storage_guard = w_storage.read();
+        let w_storage_guard = lock::read_storage(&w_storage);
 
         for (entity, t1_cell) in &t1_storage_guard.compounds {
             if let Some(t2_cell) = t2_storage_guard.compounds.get(entity) {
@@ -1236,29 +1826,26 @@ impl Compound {
         let t1_storage = self.get_or_create_storage::<T1>();
         let t2_storage = self.get_or_create_storage::<T2>();
 
-        let t1_storage_guard = t1_storage.read();
+        let t1_storage_guard = lock::read_storage(&t1_storage);
 
-        let t2_storage_guard = t2_storage.read();
+        let t2_storage_guard = lock::read_storage(&t2_storage);
 
         let w_storage = self.get_or_create_storage::<W>();
-        let w_storage_guard = w_storage.read();
+        let w_storage_guard = lock::read_storage(&w_storage);
 
         let modified_storage = self.get_or_create_storage::<Modified>();
-        let modified_storage_guard = modified_storage.read();
+        let modified_storage_guard = lock::read_storage(&modified_storage);
 
         for (entity, t1_cell) in &t1_storage_guard.compounds {
             if let Some(t2_cell) = t2_storage_guard.compounds.get(entity) {
                 if let Some(_) = w_storage_guard.compounds.get(entity) {
                 } else {
-                    if let Some(modified_flag) = modified_storage_guard.compounds.get(entity) {
-                        let mut modified_flag = modified_flag.write();
-
-                        if modified_flag.is_modified() {
-                            let t1_data = t1_cell.read();
-                            let t2_data = t2_cell.read();
-                            f(*entity, &*t1_data, &*t2_data);
-                            modified_flag.clear_modified();
-                        }
+                    if let Some(modified_flag) = modified_storage_guard.compounds.get(entity)
+                        && modified_flag.write().take_modified()
+                    {
+                        let t1_data = t1_cell.read();
+                        let t2_data = t2_cell.read();
+                        f(*entity, &*t1_data, &*t2_data);
                     }
                 }
             }
@@ -1294,12 +1881,12 @@ impl Compound {
         let t1_storage = self.get_or_create_storage::<T1>();
         let t2_storage = self.get_or_create_storage::<T2>();
 
-        let t1_storage_guard = t1_storage.read();
+        let t1_storage_guard = lock::read_storage(&t1_storage);
 
-        let t2_storage_guard = t2_storage.read();
+        let t2_storage_guard = lock::read_storage(&t2_storage);
 
         let modified_storage = self.get_or_create_storage::<Modified>();
-        let modified_storage_guard = modified_storage.read();
+        let modified_storage_guard = lock::read_storage(&modified_storage);
 
         for (entity, t1_cell) in &t1_storage_guard.compounds {
             if let Some(t2_cell) = t2_storage_guard.compounds.get(entity) {
@@ -1374,9 +1961,9 @@ impl Compound {
         let t1_storage = self.get_or_create_storage::<T1>();
         let t2_storage = self.get_or_create_storage::<T2>();
 
-        let t1_storage_guard = t1_storage.read();
+        let t1_storage_guard = lock::read_storage(&t1_storage);
 
-        let t2_storage_guard = t2_storage.read();
+        let t2_storage_guard = lock::read_storage(&t2_storage);
 
         for (entity, t1_cell) in &t1_storage_guard.compounds {
             if let Some(t2_cell) = t2_storage_guard.compounds.get(entity) {
@@ -1386,13 +1973,13 @@ impl Compound {
                     let t2_id = TypeId::of::<T2>();
 
                     if t1_id <= t2_id {
-                        let t1_data = t1_cell.write();
-                        let t2_data = t2_cell.write();
+                        let t1_data = t1_cell.write_untracked();
+                        let t2_data = t2_cell.write_untracked();
 
                         (t1_data, t2_data)
                     } else {
-                        let t2_data = t2_cell.write();
-                        let t1_data = t1_cell.write();
+                        let t2_data = t2_cell.write_untracked();
+                        let t1_data = t1_cell.write_untracked();
 
                         (t1_data, t2_data)
                     }
@@ -1440,41 +2027,38 @@ impl Compound {
         let t1_storage = self.get_or_create_storage::<T1>();
         let t2_storage = self.get_or_create_storage::<T2>();
 
-        let t1_storage_guard = t1_storage.read();
+        let t1_storage_guard = lock::read_storage(&t1_storage);
 
-        let t2_storage_guard = t2_storage.read();
+        let t2_storage_guard = lock::read_storage(&t2_storage);
 
         let modified_storage = self.get_or_create_storage::<Modified>();
-        let modified_storage_guard = modified_storage.read();
+        let modified_storage_guard = lock::read_storage(&modified_storage);
 
         for (entity, t1_cell) in &t1_storage_guard.compounds {
             if let Some(t2_cell) = t2_storage_guard.compounds.get(entity) {
                 // Set the modified flag for the entity
-                if let Some(modified_flag) = modified_storage_guard.compounds.get(entity) {
-                    let mut modified_flag = modified_flag.write();
-
-                    if modified_flag.is_modified() {
-                        // Lock aquisition ordering to prevent deadlocks
-                        let (mut t1_data, mut t2_data) = {
-                            let t1_id = TypeId::of::<T1>();
-                            let t2_id = TypeId::of::<T2>();
+                if let Some(modified_flag) = modified_storage_guard.compounds.get(entity)
+                    && modified_flag.write().take_modified()
+                {
+                    // Lock aquisition ordering to prevent deadlocks
+                    let (mut t1_data, mut t2_data) = {
+                        let t1_id = TypeId::of::<T1>();
+                        let t2_id = TypeId::of::<T2>();
 
-                            if t1_id <= t2_id {
-                                let t1_data = t1_cell.write();
-                                let t2_data = t2_cell.write();
+                        if t1_id <= t2_id {
+                            let t1_data = t1_cell.write();
+                            let t2_data = t2_cell.write();
 
-                                (t1_data, t2_data)
-                            } else {
-                                let t2_data = t2_cell.write();
-                                let t1_data = t1_cell.write();
+                            (t1_data, t2_data)
+                        } else {
+                            let t2_data = t2_cell.write();
+                            let t1_data = t1_cell.write();
 
-                                (t1_data, t2_data)
-                            }
-                        };
+                            (t1_data, t2_data)
+                        }
+                    };
 
-                        f(*entity, &mut *t1_data, &mut *t2_data);
-                        modified_flag.clear_modified();
-                    }
+                    f(*entity, &mut *t1_data, &mut *t2_data);
                 }
             }
         }
@@ -1527,12 +2111,12 @@ impl Compound {
         let t1_storage = self.get_or_create_storage::<T1>();
         let t2_storage = self.get_or_create_storage::<T2>();
 
-        let t1_storage_guard = t1_storage.read();
+        let t1_storage_guard = lock::read_storage(&t1_storage);
 
-        let t2_storage_guard = t2_storage.read();
+        let t2_storage_guard = lock::read_storage(&t2_storage);
 
         let w_storage = self.get_or_create_storage::<W>();
-        let w_storage_guard = w_storage.read();
+        let w_storage_guard = lock::read_storage(&w_storage);
 
         for (entity, t1_cell) in &t1_storage_guard.compounds {
             if let Some(t2_cell) = t2_storage_guard.compounds.get(entity) {
@@ -1544,13 +2128,13 @@ impl Compound {
                         let t2_id = TypeId::of::<T2>();
 
                         if t1_id <= t2_id {
-                            let t1_data = t1_cell.write();
-                            let t2_data = t2_cell.write();
+                            let t1_data = t1_cell.write_untracked();
+                            let t2_data = t2_cell.write_untracked();
 
                             (t1_data, t2_data)
                         } else {
-                            let t2_data = t2_cell.write();
-                            let t1_data = t1_cell.write();
+                            let t2_data = t2_cell.write_untracked();
+                            let t1_data = t1_cell.write_untracked();
 
                             (t1_data, t2_data)
                         }
@@ -1597,15 +2181,15 @@ impl Compound {
         let t1_storage = self.get_or_create_storage::<T1>();
         let t2_storage = self.get_or_create_storage::<T2>();
 
-        let t1_storage_guard = t1_storage.read();
+        let t1_storage_guard = lock::read_storage(&t1_storage);
 
-        let t2_storage_guard = t2_storage.read();
+        let t2_storage_guard = lock::read_storage(&t2_storage);
 
         let w_storage = self.get_or_create_storage::<W>();
-        let w_storage_guard = w_storage.read();
+        let w_storage_guard = lock::read_storage(&w_storage);
 
         let modified_storage = self.get_or_create_storage::<Modified>();
-        let modified_storage_guard = modified_storage.read();
+        let modified_storage_guard = lock::read_storage(&modified_storage);
 
         for (entity, t1_cell) in &t1_storage_guard.compounds {
             if let Some(t2_cell) = t2_storage_guard.compounds.get(entity) {
@@ -1681,21 +2265,21 @@ impl Compound {
         let t2_storage = self.get_or_create_storage::<T2>();
         let t3_storage = self.get_or_create_storage::<T3>();
 
-        let t1_storage_guard = t1_storage.read();
+        let t1_storage_guard = lock::read_storage(&t1_storage);
 
-        let t2_storage_guard = t2_storage.read();
+        let t2_storage_guard = lock::read_storage(&t2_storage);
 
-        let t3_storage_guard = t3_storage.read();
+        let t3_storage_guard = lock::read_storage(&t3_storage);
 
         for (entity, t1_cell) in &t1_storage_guard.compounds {
-            if let Some(t2_cell) = t2_storage_guard.compounds.get(entity) {
-                if let Some(t3_cell) = t3_storage_guard.compounds.get(entity) {
-                    let t1_data = t1_cell.read();
-                    let t2_data = t2_cell.read();
-                    let t3_data = t3_cell.read();
+            if let Some(t2_cell) = t2_storage_guard.compounds.get(entity)
+                && let Some(t3_cell) = t3_storage_guard.compounds.get(entity)
+            {
+                let t1_data = t1_cell.read();
+                let t2_data = t2_cell.read();
+                let t3_data = t3_cell.read();
 
-                    f(*entity, &*t1_data, &*t2_data, &*t3_data);
-                }
+                f(*entity, &*t1_data, &*t2_data, &*t3_data);
             }
         }
     }
@@ -1741,31 +2325,26 @@ impl Compound {
         let t2_storage = self.get_or_create_storage::<T2>();
         let t3_storage = self.get_or_create_storage::<T3>();
 
-        let t1_storage_guard = t1_storage.read();
+        let t1_storage_guard = lock::read_storage(&t1_storage);
 
-        let t2_storage_guard = t2_storage.read();
+        let t2_storage_guard = lock::read_storage(&t2_storage);
 
-        let t3_storage_guard = t3_storage.read();
+        let t3_storage_guard = lock::read_storage(&t3_storage);
 
         let modified_storage = self.get_or_create_storage::<Modified>();
-        let modified_storage_guard = modified_storage.read();
+        let modified_storage_guard = lock::read_storage(&modified_storage);
 
         for (entity, t1_cell) in &t1_storage_guard.compounds {
-            if let Some(t2_cell) = t2_storage_guard.compounds.get(entity) {
-                if let Some(t3_cell) = t3_storage_guard.compounds.get(entity) {
-                    if let Some(modified_flag) = modified_storage_guard.compounds.get(entity) {
-                        let mut modified_flag = modified_flag.write();
-
-                        if modified_flag.is_modified() {
-                            let t1_data = t1_cell.read();
-                            let t2_data = t2_cell.read();
-                            let t3_data = t3_cell.read();
+            if let Some(t2_cell) = t2_storage_guard.compounds.get(entity)
+                && let Some(t3_cell) = t3_storage_guard.compounds.get(entity)
+                && let Some(modified_flag) = modified_storage_guard.compounds.get(entity)
+                && modified_flag.write().take_modified()
+            {
+                let t1_data = t1_cell.read();
+                let t2_data = t2_cell.read();
+                let t3_data = t3_cell.read();
 
-                            f(*entity, &*t1_data, &*t2_data, &*t3_data);
-                            modified_flag.clear_modified();
-                        }
-                    }
-                }
+                f(*entity, &*t1_data, &*t2_data, &*t3_data);
             }
         }
     }
@@ -1813,24 +2392,24 @@ impl Compound {
         let t2_storage = self.get_or_create_storage::<T2>();
         let t3_storage = self.get_or_create_storage::<T3>();
 
-        let t1_storage_guard = t1_storage.read();
-        let t2_storage_guard = t2_storage.read();
-        let t3_storage_guard = t3_storage.read();
+        let t1_storage_guard = lock::read_storage(&t1_storage);
+        let t2_storage_guard = lock::read_storage(&t2_storage);
+        let t3_storage_guard = lock::read_storage(&t3_storage);
 
         let w_storage = self.get_or_create_storage::<W>();
-        let w_storage_guard = w_storage.read();
+        let w_storage_guard = lock::read_storage(&w_storage);
 
         for (entity, t1_cell) in &t1_storage_guard.compounds {
-            if let Some(t2_cell) = t2_storage_guard.compounds.get(entity) {
-                if let Some(t3_cell) = t3_storage_guard.compounds.get(entity) {
-                    if let Some(_) = w_storage_guard.compounds.get(entity) {
-                    } else {
-                        let t1_data = t1_cell.read();
-                        let t2_data = t2_cell.read();
-                        let t3_data = t3_cell.read();
+            if let Some(t2_cell) = t2_storage_guard.compounds.get(entity)
+                && let Some(t3_cell) = t3_storage_guard.compounds.get(entity)
+            {
+                if let Some(_) = w_storage_guard.compounds.get(entity) {
+                } else {
+                    let t1_data = t1_cell.read();
+                    let t2_data = t2_cell.read();
+                    let t3_data = t3_cell.read();
 
-                        f(*entity, &*t1_data, &*t2_data, &*t3_data);
-                    }
+                    f(*entity, &*t1_data, &*t2_data, &*t3_data);
                 }
             }
         }
@@ -1879,35 +2458,32 @@ impl Compound {
         let t2_storage = self.get_or_create_storage::<T2>();
         let t3_storage = self.get_or_create_storage::<T3>();
 
-        let t1_storage_guard = t1_storage.read();
+        let t1_storage_guard = lock::read_storage(&t1_storage);
 
-        let t2_storage_guard = t2_storage.read();
+        let t2_storage_guard = lock::read_storage(&t2_storage);
 
-        let t3_storage_guard = t3_storage.read();
+        let t3_storage_guard = lock::read_storage(&t3_storage);
 
         let w_storage = self.get_or_create_storage::<W>();
-        let w_storage_guard = w_storage.read();
+        let w_storage_guard = lock::read_storage(&w_storage);
 
         let modified_storage = self.get_or_create_storage::<Modified>();
-        let modified_storage_guard = modified_storage.read();
+        let modified_storage_guard = lock::read_storage(&modified_storage);
 
         for (entity, t1_cell) in &t1_storage_guard.compounds {
-            if let Some(t2_cell) = t2_storage_guard.compounds.get(entity) {
-                if let Some(t3_cell) = t3_storage_guard.compounds.get(entity) {
-                    if let Some(_) = w_storage_guard.compounds.get(entity) {
-                    } else {
-                        if let Some(modified_flag) = modified_storage_guard.compounds.get(entity) {
-                            let mut modified_flag = modified_flag.write();
-
-                            if modified_flag.is_modified() {
-                                let t1_data = t1_cell.read();
-                                let t2_data = t2_cell.read();
-                                let t3_data = t3_cell.read();
+            if let Some(t2_cell) = t2_storage_guard.compounds.get(entity)
+                && let Some(t3_cell) = t3_storage_guard.compounds.get(entity)
+            {
+                if let Some(_) = w_storage_guard.compounds.get(entity) {
+                } else {
+                    if let Some(modified_flag) = modified_storage_guard.compounds.get(entity)
+                        && modified_flag.write().take_modified()
+                    {
+                        let t1_data = t1_cell.read();
+                        let t2_data = t2_cell.read();
+                        let t3_data = t3_cell.read();
 
-                                f(*entity, &*t1_data, &*t2_data, &*t3_data);
-                                modified_flag.clear_modified();
-                            }
-                        }
+                        f(*entity, &*t1_data, &*t2_data, &*t3_data);
                     }
                 }
             }
@@ -1965,68 +2541,68 @@ impl Compound {
         let t2_storage = self.get_or_create_storage::<T2>();
         let t3_storage = self.get_or_create_storage::<T3>();
 
-        let t1_storage_guard = t1_storage.read();
+        let t1_storage_guard = lock::read_storage(&t1_storage);
 
-        let t2_storage_guard = t2_storage.read();
+        let t2_storage_guard = lock::read_storage(&t2_storage);
 
-        let t3_storage_guard = t3_storage.read();
+        let t3_storage_guard = lock::read_storage(&t3_storage);
 
         let modified_storage = self.get_or_create_storage::<Modified>();
-        let modified_storage_guard = modified_storage.read();
+        let modified_storage_guard = lock::read_storage(&modified_storage);
 
         for (entity, t1_cell) in &t1_storage_guard.compounds {
-            if let Some(t2_cell) = t2_storage_guard.compounds.get(entity) {
-                if let Some(t3_cell) = t3_storage_guard.compounds.get(entity) {
-                    // Set the modified flag for the entity
-                    if let Some(modified_flag) = modified_storage_guard.compounds.get(entity) {
-                        modified_flag.write().set_modified();
-                    }
+            if let Some(t2_cell) = t2_storage_guard.compounds.get(entity)
+                && let Some(t3_cell) = t3_storage_guard.compounds.get(entity)
+            {
+                // Set the modified flag for the entity
+                if let Some(modified_flag) = modified_storage_guard.compounds.get(entity) {
+                    modified_flag.write().set_modified();
+                }
 
-                    // Lock aquisition ordering to prevent deadlocks
-                    let (mut t1_data, mut t2_data, mut t3_data) = {
-                        let t1_id = TypeId::of::<T1>();
-                        let t2_id = TypeId::of::<T2>();
-                        let t3_id = TypeId::of::<T3>();
+                // Lock aquisition ordering to prevent deadlocks
+                let (mut t1_data, mut t2_data, mut t3_data) = {
+                    let t1_id = TypeId::of::<T1>();
+                    let t2_id = TypeId::of::<T2>();
+                    let t3_id = TypeId::of::<T3>();
 
-                        let total_min = min(t1_id, min(t2_id, t3_id));
-                        if t1_id == total_min {
-                            let t1_data = t1_cell.write();
-                            if t2_id == min(t2_id, t3_id) {
-                                let t2_data = t2_cell.write();
-                                let t3_data = t3_cell.write();
-                                (t1_data, t2_data, t3_data)
-                            } else {
-                                let t3_data = t3_cell.write();
-                                let t2_data = t2_cell.write();
-                                (t1_data, t2_data, t3_data)
-                            }
-                        } else if t2_id == total_min {
+                    let total_min = min(t1_id, min(t2_id, t3_id));
+                    if t1_id == total_min {
+                        let t1_data = t1_cell.write();
+                        if t2_id == min(t2_id, t3_id) {
                             let t2_data = t2_cell.write();
-                            if t3_id == min(t1_id, t3_id) {
-                                let t3_data = t3_cell.write();
-                                let t1_data = t1_cell.write();
-                                (t1_data, t2_data, t3_data)
-                            } else {
-                                let t1_data = t1_cell.write();
-                                let t3_data = t3_cell.write();
-                                (t1_data, t2_data, t3_data)
-                            }
+                            let t3_data = t3_cell.write();
+                            (t1_data, t2_data, t3_data)
                         } else {
                             let t3_data = t3_cell.write();
-                            if t1_id == min(t1_id, t2_id) {
-                                let t1_data = t1_cell.write();
-                                let t2_data = t2_cell.write();
-                                (t1_data, t2_data, t3_data)
-                            } else {
-                                let t2_data = t2_cell.write();
-                                let t1_data = t1_cell.write();
-                                (t1_data, t2_data, t3_data)
-                            }
+                            let t2_data = t2_cell.write();
+                            (t1_data, t2_data, t3_data)
                         }
-                    };
+                    } else if t2_id == total_min {
+                        let t2_data = t2_cell.write();
+                        if t3_id == min(t1_id, t3_id) {
+                            let t3_data = t3_cell.write();
+                            let t1_data = t1_cell.write();
+                            (t1_data, t2_data, t3_data)
+                        } else {
+                            let t1_data = t1_cell.write();
+                            let t3_data = t3_cell.write();
+                            (t1_data, t2_data, t3_data)
+                        }
+                    } else {
+                        let t3_data = t3_cell.write();
+                        if t1_id == min(t1_id, t2_id) {
+                            let t1_data = t1_cell.write();
+                            let t2_data = t2_cell.write();
+                            (t1_data, t2_data, t3_data)
+                        } else {
+                            let t2_data = t2_cell.write();
+                            let t1_data = t1_cell.write();
+                            (t1_data, t2_data, t3_data)
+                        }
+                    }
+                };
 
-                    f(*entity, &mut *t1_data, &mut *t2_data, &mut *t3_data);
-                }
+                f(*entity, &mut *t1_data, &mut *t2_data, &mut *t3_data);
             }
         }
     }
@@ -2081,60 +2657,60 @@ impl Compound {
         let t2_storage = self.get_or_create_storage::<T2>();
         let t3_storage = self.get_or_create_storage::<T3>();
 
-        let t1_storage_guard = t1_storage.read();
+        let t1_storage_guard = lock::read_storage(&t1_storage);
 
-        let t2_storage_guard = t2_storage.read();
+        let t2_storage_guard = lock::read_storage(&t2_storage);
 
-        let t3_storage_guard = t3_storage.read();
+        let t3_storage_guard = lock::read_storage(&t3_storage);
 
         for (entity, t1_cell) in &t1_storage_guard.compounds {
-            if let Some(t2_cell) = t2_storage_guard.compounds.get(entity) {
-                if let Some(t3_cell) = t3_storage_guard.compounds.get(entity) {
-                    // Lock aquisition ordering to prevent deadlocks
-                    let (mut t1_data, mut t2_data, mut t3_data) = {
-                        let t1_id = TypeId::of::<T1>();
-                        let t2_id = TypeId::of::<T2>();
-                        let t3_id = TypeId::of::<T3>();
-
-                        let total_min = min(t1_id, min(t2_id, t3_id));
-                        if t1_id == total_min {
-                            let t1_data = t1_cell.write();
-                            if t2_id == min(t2_id, t3_id) {
-                                let t2_data = t2_cell.write();
-                                let t3_data = t3_cell.write();
-                                (t1_data, t2_data, t3_data)
-                            } else {
-                                let t3_data = t3_cell.write();
-                                let t2_data = t2_cell.write();
-                                (t1_data, t2_data, t3_data)
-                            }
-                        } else if t2_id == total_min {
-                            let t2_data = t2_cell.write();
-                            if t3_id == min(t1_id, t3_id) {
-                                let t3_data = t3_cell.write();
-                                let t1_data = t1_cell.write();
-                                (t1_data, t2_data, t3_data)
-                            } else {
-                                let t1_data = t1_cell.write();
-                                let t3_data = t3_cell.write();
-                                (t1_data, t2_data, t3_data)
-                            }
+            if let Some(t2_cell) = t2_storage_guard.compounds.get(entity)
+                && let Some(t3_cell) = t3_storage_guard.compounds.get(entity)
+            {
+                // Lock aquisition ordering to prevent deadlocks
+                let (mut t1_data, mut t2_data, mut t3_data) = {
+                    let t1_id = TypeId::of::<T1>();
+                    let t2_id = TypeId::of::<T2>();
+                    let t3_id = TypeId::of::<T3>();
+
+                    let total_min = min(t1_id, min(t2_id, t3_id));
+                    if t1_id == total_min {
+                        let t1_data = t1_cell.write_untracked();
+                        if t2_id == min(t2_id, t3_id) {
+                            let t2_data = t2_cell.write_untracked();
+                            let t3_data = t3_cell.write_untracked();
+                            (t1_data, t2_data, t3_data)
                         } else {
-                            let t3_data = t3_cell.write();
-                            if t1_id == min(t1_id, t2_id) {
-                                let t1_data = t1_cell.write();
-                                let t2_data = t2_cell.write();
-                                (t1_data, t2_data, t3_data)
-                            } else {
-                                let t2_data = t2_cell.write();
-                                let t1_data = t1_cell.write();
-                                (t1_data, t2_data, t3_data)
-                            }
+                            let t3_data = t3_cell.write_untracked();
+                            let t2_data = t2_cell.write_untracked();
+                            (t1_data, t2_data, t3_data)
                         }
-                    };
+                    } else if t2_id == total_min {
+                        let t2_data = t2_cell.write_untracked();
+                        if t3_id == min(t1_id, t3_id) {
+                            let t3_data = t3_cell.write_untracked();
+                            let t1_data = t1_cell.write_untracked();
+                            (t1_data, t2_data, t3_data)
+                        } else {
+                            let t1_data = t1_cell.write_untracked();
+                            let t3_data = t3_cell.write_untracked();
+                            (t1_data, t2_data, t3_data)
+                        }
+                    } else {
+                        let t3_data = t3_cell.write_untracked();
+                        if t1_id == min(t1_id, t2_id) {
+                            let t1_data = t1_cell.write_untracked();
+                            let t2_data = t2_cell.write_untracked();
+                            (t1_data, t2_data, t3_data)
+                        } else {
+                            let t2_data = t2_cell.write_untracked();
+                            let t1_data = t1_cell.write_untracked();
+                            (t1_data, t2_data, t3_data)
+                        }
+                    }
+                };
 
-                    f(*entity, &mut *t1_data, &mut *t2_data, &mut *t3_data);
-                }
+                f(*entity, &mut *t1_data, &mut *t2_data, &mut *t3_data);
             }
         }
     }
@@ -2188,70 +2764,65 @@ impl Compound {
         let t2_storage = self.get_or_create_storage::<T2>();
         let t3_storage = self.get_or_create_storage::<T3>();
 
-        let t1_storage_guard = t1_storage.read();
+        let t1_storage_guard = lock::read_storage(&t1_storage);
 
-        let t2_storage_guard = t2_storage.read();
+        let t2_storage_guard = lock::read_storage(&t2_storage);
 
-        let t3_storage_guard = t3_storage.read();
+        let t3_storage_guard = lock::read_storage(&t3_storage);
 
         let modified_storage = self.get_or_create_storage::<Modified>();
-        let modified_storage_guard = modified_storage.read();
+        let modified_storage_guard = lock::read_storage(&modified_storage);
 
         for (entity, t1_cell) in &t1_storage_guard.compounds {
-            if let Some(t2_cell) = t2_storage_guard.compounds.get(entity) {
-                if let Some(t3_cell) = t3_storage_guard.compounds.get(entity) {
-                    if let Some(modified_flag) = modified_storage_guard.compounds.get(entity) {
-                        let mut modified_flag = modified_flag.write();
-
-                        if modified_flag.is_modified() {
-                            // Lock aquisition ordering to prevent deadlocks
-                            let (mut t1_data, mut t2_data, mut t3_data) = {
-                                let t1_id = TypeId::of::<T1>();
-                                let t2_id = TypeId::of::<T2>();
-                                let t3_id = TypeId::of::<T3>();
-
-                                let total_min = min(t1_id, min(t2_id, t3_id));
-                                if t1_id == total_min {
-                                    let t1_data = t1_cell.write();
-                                    if t2_id == min(t2_id, t3_id) {
-                                        let t2_data = t2_cell.write();
-                                        let t3_data = t3_cell.write();
-                                        (t1_data, t2_data, t3_data)
-                                    } else {
-                                        let t3_data = t3_cell.write();
-                                        let t2_data = t2_cell.write();
-                                        (t1_data, t2_data, t3_data)
-                                    }
-                                } else if t2_id == total_min {
-                                    let t2_data = t2_cell.write();
-                                    if t3_id == min(t1_id, t3_id) {
-                                        let t3_data = t3_cell.write();
-                                        let t1_data = t1_cell.write();
-                                        (t1_data, t2_data, t3_data)
-                                    } else {
-                                        let t1_data = t1_cell.write();
-                                        let t3_data = t3_cell.write();
-                                        (t1_data, t2_data, t3_data)
-                                    }
-                                } else {
-                                    let t3_data = t3_cell.write();
-                                    if t1_id == min(t1_id, t2_id) {
-                                        let t1_data = t1_cell.write();
-                                        let t2_data = t2_cell.write();
-                                        (t1_data, t2_data, t3_data)
-                                    } else {
-                                        let t2_data = t2_cell.write();
-                                        let t1_data = t1_cell.write();
-                                        (t1_data, t2_data, t3_data)
-                                    }
-                                }
-                            };
+            if let Some(t2_cell) = t2_storage_guard.compounds.get(entity)
+                && let Some(t3_cell) = t3_storage_guard.compounds.get(entity)
+                && let Some(modified_flag) = modified_storage_guard.compounds.get(entity)
+                && modified_flag.write().take_modified()
+            {
+                // Lock aquisition ordering to prevent deadlocks
+                let (mut t1_data, mut t2_data, mut t3_data) = {
+                    let t1_id = TypeId::of::<T1>();
+                    let t2_id = TypeId::of::<T2>();
+                    let t3_id = TypeId::of::<T3>();
 
-                            f(*entity, &mut *t1_data, &mut *t2_data, &mut *t3_data);
-                            modified_flag.clear_modified();
+                    let total_min = min(t1_id, min(t2_id, t3_id));
+                    if t1_id == total_min {
+                        let t1_data = t1_cell.write();
+                        if t2_id == min(t2_id, t3_id) {
+                            let t2_data = t2_cell.write();
+                            let t3_data = t3_cell.write();
+                            (t1_data, t2_data, t3_data)
+                        } else {
+                            let t3_data = t3_cell.write();
+                            let t2_data = t2_cell.write();
+                            (t1_data, t2_data, t3_data)
+                        }
+                    } else if t2_id == total_min {
+                        let t2_data = t2_cell.write();
+                        if t3_id == min(t1_id, t3_id) {
+                            let t3_data = t3_cell.write();
+                            let t1_data = t1_cell.write();
+                            (t1_data, t2_data, t3_data)
+                        } else {
+                            let t1_data = t1_cell.write();
+                            let t3_data = t3_cell.write();
+                            (t1_data, t2_data, t3_data)
+                        }
+                    } else {
+                        let t3_data = t3_cell.write();
+                        if t1_id == min(t1_id, t2_id) {
+                            let t1_data = t1_cell.write();
+                            let t2_data = t2_cell.write();
+                            (t1_data, t2_data, t3_data)
+                        } else {
+                            let t2_data = t2_cell.write();
+                            let t1_data = t1_cell.write();
+                            (t1_data, t2_data, t3_data)
                         }
                     }
-                }
+                };
+
+                f(*entity, &mut *t1_data, &mut *t2_data, &mut *t3_data);
             }
         }
     }
@@ -2308,63 +2879,63 @@ impl Compound {
         let t2_storage = self.get_or_create_storage::<T2>();
         let t3_storage = self.get_or_create_storage::<T3>();
 
-        let t1_storage_guard = t1_storage.read();
-        let t2_storage_guard = t2_storage.read();
-        let t3_storage_guard = t3_storage.read();
+        let t1_storage_guard = lock::read_storage(&t1_storage);
+        let t2_storage_guard = lock::read_storage(&t2_storage);
+        let t3_storage_guard = lock::read_storage(&t3_storage);
 
         let w_storage = self.get_or_create_storage::<W>();
-        let w_storage_guard = w_storage.read();
+        let w_storage_guard = lock::read_storage(&w_storage);
 
         for (entity, t1_cell) in &t1_storage_guard.compounds {
-            if let Some(t2_cell) = t2_storage_guard.compounds.get(entity) {
-                if let Some(t3_cell) = t3_storage_guard.compounds.get(entity) {
-                    if let Some(_) = w_storage_guard.compounds.get(entity) {
-                    } else {
-                        // Lock aquisition ordering to prevent deadlocks
-                        let (mut t1_data, mut t2_data, mut t3_data) = {
-                            let t1_id = TypeId::of::<T1>();
-                            let t2_id = TypeId::of::<T2>();
-                            let t3_id = TypeId::of::<T3>();
+            if let Some(t2_cell) = t2_storage_guard.compounds.get(entity)
+                && let Some(t3_cell) = t3_storage_guard.compounds.get(entity)
+            {
+                if let Some(_) = w_storage_guard.compounds.get(entity) {
+                } else {
+                    // Lock aquisition ordering to prevent deadlocks
+                    let (mut t1_data, mut t2_data, mut t3_data) = {
+                        let t1_id = TypeId::of::<T1>();
+                        let t2_id = TypeId::of::<T2>();
+                        let t3_id = TypeId::of::<T3>();
 
-                            let total_min = min(t1_id, min(t2_id, t3_id));
-                            if t1_id == total_min {
-                                let t1_data = t1_cell.write();
-                                if t2_id == min(t2_id, t3_id) {
-                                    let t2_data = t2_cell.write();
-                                    let t3_data = t3_cell.write();
-                                    (t1_data, t2_data, t3_data)
-                                } else {
-                                    let t3_data = t3_cell.write();
-                                    let t2_data = t2_cell.write();
-                                    (t1_data, t2_data, t3_data)
-                                }
-                            } else if t2_id == total_min {
-                                let t2_data = t2_cell.write();
-                                if t3_id == min(t1_id, t3_id) {
-                                    let t3_data = t3_cell.write();
-                                    let t1_data = t1_cell.write();
-                                    (t1_data, t2_data, t3_data)
-                                } else {
-                                    let t1_data = t1_cell.write();
-                                    let t3_data = t3_cell.write();
-                                    (t1_data, t2_data, t3_data)
-                                }
+                        let total_min = min(t1_id, min(t2_id, t3_id));
+                        if t1_id == total_min {
+                            let t1_data = t1_cell.write_untracked();
+                            if t2_id == min(t2_id, t3_id) {
+                                let t2_data = t2_cell.write_untracked();
+                                let t3_data = t3_cell.write_untracked();
+                                (t1_data, t2_data, t3_data)
                             } else {
-                                let t3_data = t3_cell.write();
-                                if t1_id == min(t1_id, t2_id) {
-                                    let t1_data = t1_cell.write();
-                                    let t2_data = t2_cell.write();
-                                    (t1_data, t2_data, t3_data)
-                                } else {
-                                    let t2_data = t2_cell.write();
-                                    let t1_data = t1_cell.write();
-                                    (t1_data, t2_data, t3_data)
-                                }
+                                let t3_data = t3_cell.write_untracked();
+                                let t2_data = t2_cell.write_untracked();
+                                (t1_data, t2_data, t3_data)
                             }
-                        };
+                        } else if t2_id == total_min {
+                            let t2_data = t2_cell.write_untracked();
+                            if t3_id == min(t1_id, t3_id) {
+                                let t3_data = t3_cell.write_untracked();
+                                let t1_data = t1_cell.write_untracked();
+                                (t1_data, t2_data, t3_data)
+                            } else {
+                                let t1_data = t1_cell.write_untracked();
+                                let t3_data = t3_cell.write_untracked();
+                                (t1_data, t2_data, t3_data)
+                            }
+                        } else {
+                            let t3_data = t3_cell.write_untracked();
+                            if t1_id == min(t1_id, t2_id) {
+                                let t1_data = t1_cell.write_untracked();
+                                let t2_data = t2_cell.write_untracked();
+                                (t1_data, t2_data, t3_data)
+                            } else {
+                                let t2_data = t2_cell.write_untracked();
+                                let t1_data = t1_cell.write_untracked();
+                                (t1_data, t2_data, t3_data)
+                            }
+                        }
+                    };
 
-                        f(*entity, &mut *t1_data, &mut *t2_data, &mut *t3_data);
-                    }
+                    f(*entity, &mut *t1_data, &mut *t2_data, &mut *t3_data);
                 }
             }
         }
@@ -2423,71 +2994,71 @@ impl Compound {
         let t2_storage = self.get_or_create_storage::<T2>();
         let t3_storage = self.get_or_create_storage::<T3>();
 
-        let t1_storage_guard = t1_storage.read();
-        let t2_storage_guard = t2_storage.read();
-        let t3_storage_guard = t3_storage.read();
+        let t1_storage_guard = lock::read_storage(&t1_storage);
+        let t2_storage_guard = lock::read_storage(&t2_storage);
+        let t3_storage_guard = lock::read_storage(&t3_storage);
 
         let w_storage = self.get_or_create_storage::<W>();
-        let w_storage_guard = w_storage.read();
+        let w_storage_guard = lock::read_storage(&w_storage);
 
         let modified_storage = self.get_or_create_storage::<Modified>();
-        let modified_storage_guard = modified_storage.read();
+        let modified_storage_guard = lock::read_storage(&modified_storage);
 
         for (entity, t1_cell) in &t1_storage_guard.compounds {
-            if let Some(t2_cell) = t2_storage_guard.compounds.get(entity) {
-                if let Some(t3_cell) = t3_storage_guard.compounds.get(entity) {
-                    if let Some(_) = w_storage_guard.compounds.get(entity) {
-                    } else {
-                        // Set the modified flag for the entity
-                        if let Some(modified_flag) = modified_storage_guard.compounds.get(entity) {
-                            modified_flag.write().set_modified();
-                        }
+            if let Some(t2_cell) = t2_storage_guard.compounds.get(entity)
+                && let Some(t3_cell) = t3_storage_guard.compounds.get(entity)
+            {
+                if let Some(_) = w_storage_guard.compounds.get(entity) {
+                } else {
+                    // Set the modified flag for the entity
+                    if let Some(modified_flag) = modified_storage_guard.compounds.get(entity) {
+                        modified_flag.write().set_modified();
+                    }
 
-                        // Lock aquisition ordering to prevent deadlocks
-                        let (mut t1_data, mut t2_data, mut t3_data) = {
-                            let t1_id = TypeId::of::<T1>();
-                            let t2_id = TypeId::of::<T2>();
-                            let t3_id = TypeId::of::<T3>();
+                    // Lock aquisition ordering to prevent deadlocks
+                    let (mut t1_data, mut t2_data, mut t3_data) = {
+                        let t1_id = TypeId::of::<T1>();
+                        let t2_id = TypeId::of::<T2>();
+                        let t3_id = TypeId::of::<T3>();
 
-                            let total_min = min(t1_id, min(t2_id, t3_id));
-                            if t1_id == total_min {
-                                let t1_data = t1_cell.write();
-                                if t2_id == min(t2_id, t3_id) {
-                                    let t2_data = t2_cell.write();
-                                    let t3_data = t3_cell.write();
-                                    (t1_data, t2_data, t3_data)
-                                } else {
-                                    let t3_data = t3_cell.write();
-                                    let t2_data = t2_cell.write();
-                                    (t1_data, t2_data, t3_data)
-                                }
-                            } else if t2_id == total_min {
+                        let total_min = min(t1_id, min(t2_id, t3_id));
+                        if t1_id == total_min {
+                            let t1_data = t1_cell.write();
+                            if t2_id == min(t2_id, t3_id) {
                                 let t2_data = t2_cell.write();
-                                if t3_id == min(t1_id, t3_id) {
-                                    let t3_data = t3_cell.write();
-                                    let t1_data = t1_cell.write();
-                                    (t1_data, t2_data, t3_data)
-                                } else {
-                                    let t1_data = t1_cell.write();
-                                    let t3_data = t3_cell.write();
-                                    (t1_data, t2_data, t3_data)
-                                }
+                                let t3_data = t3_cell.write();
+                                (t1_data, t2_data, t3_data)
                             } else {
                                 let t3_data = t3_cell.write();
-                                if t1_id == min(t1_id, t2_id) {
-                                    let t1_data = t1_cell.write();
-                                    let t2_data = t2_cell.write();
-                                    (t1_data, t2_data, t3_data)
-                                } else {
-                                    let t2_data = t2_cell.write();
-                                    let t1_data = t1_cell.write();
-                                    (t1_data, t2_data, t3_data)
-                                }
+                                let t2_data = t2_cell.write();
+                                (t1_data, t2_data, t3_data)
                             }
-                        };
+                        } else if t2_id == total_min {
+                            let t2_data = t2_cell.write();
+                            if t3_id == min(t1_id, t3_id) {
+                                let t3_data = t3_cell.write();
+                                let t1_data = t1_cell.write();
+                                (t1_data, t2_data, t3_data)
+                            } else {
+                                let t1_data = t1_cell.write();
+                                let t3_data = t3_cell.write();
+                                (t1_data, t2_data, t3_data)
+                            }
+                        } else {
+                            let t3_data = t3_cell.write();
+                            if t1_id == min(t1_id, t2_id) {
+                                let t1_data = t1_cell.write();
+                                let t2_data = t2_cell.write();
+                                (t1_data, t2_data, t3_data)
+                            } else {
+                                let t2_data = t2_cell.write();
+                                let t1_data = t1_cell.write();
+                                (t1_data, t2_data, t3_data)
+                            }
+                        }
+                    };
 
-                        f(*entity, &mut *t1_data, &mut *t2_data, &mut *t3_data);
-                    }
+                    f(*entity, &mut *t1_data, &mut *t2_data, &mut *t3_data);
                 }
             }
         }
@@ -2545,77 +3116,217 @@ impl Compound {
         let t2_storage = self.get_or_create_storage::<T2>();
         let t3_storage = self.get_or_create_storage::<T3>();
 
-        let t1_storage_guard = t1_storage.read();
-        let t2_storage_guard = t2_storage.read();
-        let t3_storage_guard = t3_storage.read();
+        let t1_storage_guard = lock::read_storage(&t1_storage);
+        let t2_storage_guard = lock::read_storage(&t2_storage);
+        let t3_storage_guard = lock::read_storage(&t3_storage);
 
         let w_storage = self.get_or_create_storage::<W>();
-        let w_storage_guard = w_storage.read();
+        let w_storage_guard = lock::read_storage(&w_storage);
 
         let modified_storage = self.get_or_create_storage::<Modified>();
-        let modified_storage_guard = modified_storage.read();
+        let modified_storage_guard = lock::read_storage(&modified_storage);
 
         for (entity, t1_cell) in &t1_storage_guard.compounds {
-            if let Some(t2_cell) = t2_storage_guard.compounds.get(entity) {
-                if let Some(t3_cell) = t3_storage_guard.compounds.get(entity) {
-                    if let Some(_) = w_storage_guard.compounds.get(entity) {
-                    } else {
-                        if let Some(modified_flag) = modified_storage_guard.compounds.get(entity) {
-                            let mut modified_flag = modified_flag.write();
-
-                            if modified_flag.is_modified() {
-                                // Lock aquisition ordering to prevent deadlocks
-                                let (mut t1_data, mut t2_data, mut t3_data) = {
-                                    let t1_id = TypeId::of::<T1>();
-                                    let t2_id = TypeId::of::<T2>();
-                                    let t3_id = TypeId::of::<T3>();
-
-                                    let total_min = min(t1_id, min(t2_id, t3_id));
-                                    if t1_id == total_min {
-                                        let t1_data = t1_cell.write();
-                                        if t2_id == min(t2_id, t3_id) {
-                                            let t2_data = t2_cell.write();
-                                            let t3_data = t3_cell.write();
-                                            (t1_data, t2_data, t3_data)
-                                        } else {
-                                            let t3_data = t3_cell.write();
-                                            let t2_data = t2_cell.write();
-                                            (t1_data, t2_data, t3_data)
-                                        }
-                                    } else if t2_id == total_min {
-                                        let t2_data = t2_cell.write();
-                                        if t3_id == min(t1_id, t3_id) {
-                                            let t3_data = t3_cell.write();
-                                            let t1_data = t1_cell.write();
-                                            (t1_data, t2_data, t3_data)
-                                        } else {
-                                            let t1_data = t1_cell.write();
-                                            let t3_data = t3_cell.write();
-                                            (t1_data, t2_data, t3_data)
-                                        }
-                                    } else {
-                                        let t3_data = t3_cell.write();
-                                        if t1_id == min(t1_id, t2_id) {
-                                            let t1_data = t1_cell.write();
-                                            let t2_data = t2_cell.write();
-                                            (t1_data, t2_data, t3_data)
-                                        } else {
-                                            let t2_data = t2_cell.write();
-                                            let t1_data = t1_cell.write();
-                                            (t1_data, t2_data, t3_data)
-                                        }
-                                    }
-                                };
-
-                                f(*entity, &mut *t1_data, &mut *t2_data, &mut *t3_data);
-                                modified_flag.clear_modified();
+            if let Some(t2_cell) = t2_storage_guard.compounds.get(entity)
+                && let Some(t3_cell) = t3_storage_guard.compounds.get(entity)
+            {
+                if let Some(_) = w_storage_guard.compounds.get(entity) {
+                } else {
+                    if let Some(modified_flag) = modified_storage_guard.compounds.get(entity)
+                        && modified_flag.write().take_modified()
+                    {
+                        // Lock aquisition ordering to prevent deadlocks
+                        let (mut t1_data, mut t2_data, mut t3_data) = {
+                            let t1_id = TypeId::of::<T1>();
+                            let t2_id = TypeId::of::<T2>();
+                            let t3_id = TypeId::of::<T3>();
+
+                            let total_min = min(t1_id, min(t2_id, t3_id));
+                            if t1_id == total_min {
+                                let t1_data = t1_cell.write();
+                                if t2_id == min(t2_id, t3_id) {
+                                    let t2_data = t2_cell.write();
+                                    let t3_data = t3_cell.write();
+                                    (t1_data, t2_data, t3_data)
+                                } else {
+                                    let t3_data = t3_cell.write();
+                                    let t2_data = t2_cell.write();
+                                    (t1_data, t2_data, t3_data)
+                                }
+                            } else if t2_id == total_min {
+                                let t2_data = t2_cell.write();
+                                if t3_id == min(t1_id, t3_id) {
+                                    let t3_data = t3_cell.write();
+                                    let t1_data = t1_cell.write();
+                                    (t1_data, t2_data, t3_data)
+                                } else {
+                                    let t1_data = t1_cell.write();
+                                    let t3_data = t3_cell.write();
+                                    (t1_data, t2_data, t3_data)
+                                }
+                            } else {
+                                let t3_data = t3_cell.write();
+                                if t1_id == min(t1_id, t2_id) {
+                                    let t1_data = t1_cell.write();
+                                    let t2_data = t2_cell.write();
+                                    (t1_data, t2_data, t3_data)
+                                } else {
+                                    let t2_data = t2_cell.write();
+                                    let t1_data = t1_cell.write();
+                                    (t1_data, t2_data, t3_data)
+                                }
                             }
-                        }
+                        };
+
+                        f(*entity, &mut *t1_data, &mut *t2_data, &mut *t3_data);
                     }
                 }
             }
         }
     }
+
+    // Queries ================================================
+    /// Creates a query over the entities with a combination of molecules.
+    ///
+    /// Queries cover any number of molecules with any mix of read and write
+    /// access, and any number of `With` and `Without` filters, in place of
+    /// the fixed `iter_*` methods.
+    ///
+    /// # Type Parameters
+    /// - `D`: The molecules to access, a tuple of `&T` and `&mut T`
+    /// - `F`: The filter of the entities to visit, `()` to visit all of them
+    ///
+    /// # Returns
+    /// A `Query` to refine with `with`, `without`, `modified` or `unmod`, run with `for_each`
+    ///
+    /// # Example
+    /// ```ignore
+    /// compound
+    ///     .query::<(&Transform3D, &mut Model), Without<Frozen>>()
+    ///     .for_each(|entity, (transform, model)| {
+    ///         model.update(transform);
+    ///     });
+    ///
+    /// // Multiple exclusions
+    /// compound
+    ///     .query::<(&Health,), (Without<Invulnerable>, Without<Dead>)>()
+    ///     .modified()
+    ///     .for_each(|entity, (health,)| {
+    ///         println!("Entity {} has {} health", entity, health.current);
+    ///     });
+    /// ```
+    pub fn query<D, F>(&self) -> Query<'_, D, F>
+    where
+        D: QueryData,
+        F: QueryFilter,
+    {
+        Query::new(self)
+    }
+
+    /// Inserts a resource, a single value of a type that belongs to the world
+    /// instead of an entity, such as gravity, the score or game settings.
+    ///
+    /// # Arguments
+    /// - `resource`: The value to store, replacing any resource of the same type
+    ///
+    /// # Returns
+    /// The resource that was replaced, `None` if there wasn't one or it was
+    /// being accessed from another thread
+    ///
+    /// # Example
+    /// ```ignore
+    /// compound.insert_resource(Gravity(Vector3::new(0.0, -9.81, 0.0)));
+    /// ```
+    pub fn insert_resource<T: Send + Sync + 'static>(&self, resource: T) -> Option<T> {
+        let previous = self
+            .resources
+            .write()
+            .insert(TypeId::of::<T>(), Arc::new(MoleculeCell::new(resource)));
+
+        previous.and_then(Self::unwrap_resource)
+    }
+
+    /// Removes a resource from the world.
+    ///
+    /// # Returns
+    /// The resource, `None` if there wasn't one or it was being accessed from
+    /// another thread
+    pub fn remove_resource<T: Send + Sync + 'static>(&self) -> Option<T> {
+        let resource = self.resources.write().remove(&TypeId::of::<T>());
+
+        resource.and_then(Self::unwrap_resource)
+    }
+
+    /// Checks if the world has a resource of a type
+    pub fn has_resource<T: Send + Sync + 'static>(&self) -> bool {
+        self.resources.read().contains_key(&TypeId::of::<T>())
+    }
+
+    /// Reads a resource.
+    ///
+    /// Only the lock of this resource is held while the callback runs, so other
+    /// resources and molecules can be accessed from inside of it.
+    ///
+    /// # Arguments
+    /// - `f`: Callback that receives a reference to the resource
+    ///
+    /// # Returns
+    /// What the callback returned, `None` if the world has no resource of type `T`
+    ///
+    /// # Example
+    /// ```ignore
+    /// let gravity = compound.resource::<Gravity, _>(|gravity| gravity.0);
+    /// ```
+    pub fn resource<T, R>(&self, f: impl FnOnce(&T) -> R) -> Option<R>
+    where
+        T: Send + Sync + 'static,
+    {
+        let cell = self.resource_cell::<T>()?;
+        let resource = cell.read();
+        Some(f(&resource))
+    }
+
+    /// Modifies a resource.
+    ///
+    /// # Arguments
+    /// - `f`: Callback that receives a mutable reference to the resource
+    ///
+    /// # Returns
+    /// What the callback returned, `None` if the world has no resource of type `T`
+    ///
+    /// # Example
+    /// ```ignore
+    /// compound.resource_mut::<Score, _>(|score| score.0 += 10);
+    /// ```
+    pub fn resource_mut<T, R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R>
+    where
+        T: Send + Sync + 'static,
+    {
+        let cell = self.resource_cell::<T>()?;
+        let mut resource = cell.write();
+        Some(f(&mut resource))
+    }
+
+    // Clones the cell out of the map so the map isn't locked while the resource is used
+    fn resource_cell<T: Send + Sync + 'static>(&self) -> Option<Arc<MoleculeCell<T>>> {
+        let resource = self.resources.read().get(&TypeId::of::<T>())?.clone();
+
+        resource.downcast::<MoleculeCell<T>>().ok()
+    }
+
+    fn unwrap_resource<T: Send + Sync + 'static>(
+        resource: Arc<dyn Any + Send + Sync>,
+    ) -> Option<T> {
+        let cell = resource.downcast::<MoleculeCell<T>>().ok()?;
+
+        match Arc::try_unwrap(cell) {
+            Ok(cell) => Some(cell.data.into_inner()),
+            Err(_) => {
+                warn!("Resource was removed while it was being accessed");
+                None
+            }
+        }
+    }
 }
 
 // For adding multiple molecules to the same entity
@@ -2666,24 +3377,23 @@ impl Compound {
 /// ```
 ///
 /// ## Custom bundle types
-/// While this trait is automatically implemented for tuples, you could also implement it
-/// for custom types if you want named bundles:
+/// Named bundles derive the trait, adding every field as a molecule. Fields
+/// marked with `#[bundle]` are bundles themselves and add their molecules
+/// instead:
 ///
 /// ```ignore
-/// struct PlayerBundle {
+/// #[derive(MoleculeBundle)]
+/// struct CharacterBundle {
 ///     position: Position,
 ///     velocity: Velocity,
 ///     health: Health,
-///     name: Name,
 /// }
 ///
-/// impl MoleculeBundle for PlayerBundle {
-///     fn add_to_entity(self, compound: &Compound, entity: Entity) {
-///         compound.add_molecule(entity, self.position);
-///         compound.add_molecule(entity, self.velocity);
-///         compound.add_molecule(entity, self.health);
-///         compound.add_molecule(entity, self.name);
-///     }
+/// #[derive(MoleculeBundle)]
+/// struct PlayerBundle {
+///     #[bundle]
+///     character: CharacterBundle,
+///     name: Name,
 /// }
 /// ```
 pub trait MoleculeBundle {
@@ -3047,4 +3757,1119 @@ mod ecs_test {
             println!("Label id: {}", label.id);
         });
     }
+
+    #[test]
+    fn test_ecs_move_entity() {
+        struct Label {
+            name: String,
+        }
+
+        struct Collar {
+            address: String,
+        }
+
+        let level_one = Compound::new();
+        let level_two = Compound::new();
+
+        _ = level_two.spawn((Label {
+            name: "Resident".to_string(),
+        },));
+
+        let sparky = level_one.spawn((
+            Label {
+                name: "Sparky".to_string(),
+            },
+            Collar {
+                address: "1 main St.".to_string(),
+            },
+        ));
+        let snivvy = level_one.spawn((Label {
+            name: "Snivvy".to_string(),
+        },));
+
+        // Clear the modified flags so the move can be observed
+        level_two.iter_mol_mod(|_entity, _label: &Label| {});
+
+        let moved = level_one.move_entity(sparky, &level_two);
+
+        let mut remaining = Vec::new();
+        level_one.iter_mol(|entity, label: &Label| remaining.push((entity, label.name.clone())));
+        assert_eq!(remaining, vec![(snivvy, "Snivvy".to_string())]);
+        level_one.iter_mol(|_entity, _collar: &Collar| panic!("Collar was not moved"));
+
+        let mut arrived = Vec::new();
+        level_two.iter_duo(|entity, label: &Label, collar: &Collar| {
+            arrived.push((entity, label.name.clone(), collar.address.clone()))
+        });
+        assert_eq!(
+            arrived,
+            vec![(moved, "Sparky".to_string(), "1 main St.".to_string())]
+        );
+
+        let mut modified = Vec::new();
+        level_two.iter_mol_mod(|entity, _label: &Label| modified.push(entity));
+        assert_eq!(modified, vec![moved]);
+
+        // Moving within the same compound keeps the entity
+        assert_eq!(level_two.move_entity(moved, &level_two), moved);
+    }
+
+    #[test]
+    fn test_ecs_remove_molecule() {
+        struct Label {
+            name: String,
+        }
+
+        struct Collar {
+            address: String,
+        }
+
+        let compound = Compound::new();
+
+        let sparky = compound.spawn((
+            Label {
+                name: "Sparky".to_string(),
+            },
+            Collar {
+                address: "1 main St.".to_string(),
+            },
+        ));
+
+        // Clear the modified flags so the removal can be observed
+        compound.iter_mol_mod(|_entity, _label: &Label| {});
+
+        let collar = compound.remove_molecule::<Collar>(sparky);
+        assert_eq!(
+            collar.map(|collar| collar.address),
+            Some("1 main St.".to_string())
+        );
+        assert!(compound.remove_molecule::<Collar>(sparky).is_none());
+        compound.iter_mol(|_entity, _collar: &Collar| panic!("Collar was not removed"));
+
+        let mut modified = Vec::new();
+        compound.iter_mol_mod(|entity, label: &Label| modified.push((entity, label.name.clone())));
+        assert_eq!(modified, vec![(sparky, "Sparky".to_string())]);
+
+        // Types that were never added have no storage to remove from
+        assert!(compound.remove_molecule::<u32>(sparky).is_none());
+    }
+
+    #[test]
+    fn test_ecs_move_all_entities() {
+        struct Label(String);
+        struct Follows(Entity);
+
+        let loading = Compound::new();
+        let main = Compound::new();
+        main.spawn((Label("Player".to_string()),));
+
+        let gate = loading.spawn((Label("Gate".to_string()), Name::new("Gate")));
+        let guard = loading.spawn((Label("Guard".to_string()), Follows(gate)));
+        loading.insert_resource(0u32);
+
+        let moved = loading.move_all_entities(&main);
+        assert_eq!(moved.len(), 2);
+        assert!(moved[&gate] < moved[&guard]);
+
+        assert!(!loading.entity_exists(gate));
+        assert!(!loading.entity_exists(guard));
+        assert!(loading.has_resource::<u32>());
+
+        // References between moved entities are remapped with the returned IDs
+        main.iter_mut_mol(|_entity, follows: &mut Follows| follows.0 = moved[&follows.0]);
+        main.iter_duo(|entity, label: &Label, follows: &Follows| {
+            assert_eq!(entity, moved[&guard]);
+            assert_eq!(label.0, "Guard");
+            assert_eq!(follows.0, moved[&gate]);
+        });
+        assert_eq!(main.find_by_name("Gate"), Some(moved[&gate]));
+
+        let mut labels = 0;
+        main.iter_mol_mod(|_entity, _label: &Label| labels += 1);
+        assert_eq!(labels, 3);
+    }
+
+    #[test]
+    fn test_ecs_entity_mut() {
+        struct Label(String);
+        struct Health(u32);
+        struct Spawning;
+
+        let compound = Compound::new();
+        let other = compound.spawn((Label("Other".to_string()),));
+
+        let player = compound
+            .entity(compound.create_entity())
+            .insert_bundle((Label("Player".to_string()), Spawning))
+            .insert(Health(100))
+            .insert(Name::new("Player"))
+            .remove::<Spawning>()
+            .id();
+
+        assert!(compound.entity(player).has::<Health>());
+        assert!(!compound.has_molecule::<Spawning>(player));
+        assert_eq!(compound.find_by_name("Player"), Some(player));
+        assert_eq!(
+            compound
+                .entity(player)
+                .take::<Health>()
+                .map(|health| health.0),
+            Some(100)
+        );
+
+        assert!(compound.entity(player).despawn());
+        assert!(!compound.entity_exists(player));
+        assert!(!compound.entity(player).despawn());
+        assert_eq!(compound.find_by_name("Player"), None);
+
+        // Despawning one entity leaves the rest alone
+        assert!(compound.entity_exists(other));
+        let mut labels = Vec::new();
+        compound.iter_mol(|_entity, label: &Label| labels.push(label.0.clone()));
+        assert_eq!(labels, ["Other"]);
+    }
+
+    #[test]
+    fn test_ecs_derive_bundle() {
+        struct Label(String);
+        struct Health(u32);
+        struct Player;
+
+        #[derive(MoleculeBundle)]
+        struct CharacterBundle {
+            label: Label,
+            health: Health,
+        }
+
+        #[derive(MoleculeBundle)]
+        struct PlayerBundle {
+            #[bundle]
+            character: CharacterBundle,
+            player: Player,
+        }
+
+        // Named through a path to the crate, the way crates that re-export it do
+        #[derive(MoleculeBundle)]
+        #[bundle(crate = "crate")]
+        struct Wrapper<T: Send + Sync + 'static>(T);
+
+        let compound = Compound::new();
+        let player = compound.spawn(PlayerBundle {
+            character: CharacterBundle {
+                label: Label("Player".to_string()),
+                health: Health(100),
+            },
+            player: Player,
+        });
+        let enemy = compound.spawn(Wrapper(Health(20)));
+
+        compound.iter_trio(|entity, label: &Label, health: &Health, _player: &Player| {
+            assert_eq!(entity, player);
+            assert_eq!(label.0, "Player");
+            assert_eq!(health.0, 100);
+        });
+        assert!(compound.has_molecule::<Player>(player));
+        assert!(compound.has_molecule::<Health>(enemy));
+        assert!(!compound.has_molecule::<Label>(enemy));
+        assert!(!compound.has_molecule::<CharacterBundle>(player));
+    }
+
+    #[test]
+    fn test_ecs_has_molecule() {
+        struct Label;
+        struct Collar;
+
+        let compound = Compound::new();
+
+        let sparky = compound.spawn((Label, Collar));
+        let john = compound.spawn((Label,));
+        let never_spawned = compound.create_entity();
+
+        assert!(compound.has_molecule::<Collar>(sparky));
+        assert!(!compound.has_molecule::<Collar>(john));
+        assert!(!compound.has_molecule::<u32>(sparky));
+
+        assert!(compound.entity_exists(sparky));
+        assert!(compound.entity_exists(john));
+        assert!(!compound.entity_exists(never_spawned));
+
+        compound.remove_molecule::<Collar>(sparky);
+        assert!(!compound.has_molecule::<Collar>(sparky));
+        assert!(compound.entity_exists(sparky));
+
+        compound.clear();
+        assert!(!compound.has_molecule::<Label>(john));
+        assert!(!compound.entity_exists(john));
+    }
+
+    #[test]
+    fn test_ecs_clear() {
+        struct Label {
+            name: String,
+        }
+
+        struct Collar;
+
+        let compound = Compound::new();
+
+        let sparky = compound.spawn((
+            Label {
+                name: "Sparky".to_string(),
+            },
+            Collar,
+            Name::new("Sparky"),
+        ));
+        compound.spawn((
+            Label {
+                name: "Snivvy".to_string(),
+            },
+            Collar,
+        ));
+        let john = compound.spawn((Label {
+            name: "John".to_string(),
+        },));
+        compound.insert_resource(9.81_f32);
+
+        // Clear the modified flags so the removal can be observed
+        compound.iter_mol_mod(|_entity, _label: &Label| {});
+
+        assert_eq!(compound.clear_molecules::<Collar>(), 2);
+        assert_eq!(compound.clear_molecules::<Collar>(), 0);
+        compound.iter_mol(|_entity, _collar: &Collar| panic!("Collar was not cleared"));
+
+        let mut modified = Vec::new();
+        compound.iter_mol_mod(|_entity, label: &Label| modified.push(label.name.clone()));
+        modified.sort();
+        assert_eq!(modified, vec!["Snivvy".to_string(), "Sparky".to_string()]);
+
+        assert_eq!(compound.find_by_name("Sparky"), Some(sparky));
+        assert_eq!(compound.clear_molecules::<Name>(), 1);
+        assert_eq!(compound.find_by_name("Sparky"), None);
+
+        compound.clear();
+        compound.iter_mol(|_entity, _label: &Label| panic!("Label was not cleared"));
+        assert_eq!(compound.resource::<f32, _>(|gravity| *gravity), Some(9.81));
+
+        // IDs from before the clear are never reused
+        let rex = compound.spawn((Label {
+            name: "Rex".to_string(),
+        },));
+        assert!(rex > john);
+        compound.iter_mol(|entity, label: &Label| {
+            assert_eq!((entity, label.name.as_str()), (rex, "Rex"));
+        });
+    }
+
+    #[test]
+    fn test_ecs_query() {
+        struct Position(f32);
+        struct Velocity(f32);
+        struct Mass(f32);
+        struct Drag(f32);
+        struct Frozen;
+        struct Dead;
+
+        let compound = Compound::new();
+
+        let moving = compound.spawn((Position(0.0), Velocity(1.0), Mass(2.0), Drag(0.5)));
+        let frozen = compound.spawn((Position(0.0), Velocity(1.0), Mass(2.0), Drag(0.5), Frozen));
+        let dead = compound.spawn((Position(0.0), Velocity(1.0), Mass(2.0), Drag(0.5), Dead));
+        let light = compound.spawn((Position(0.0), Velocity(1.0)));
+
+        // Four molecules with mixed access and two exclusions
+        let mut visited = Vec::new();
+        compound
+            .query::<(&mut Position, &Velocity, &Mass, &Drag), (Without<Frozen>, Without<Dead>)>()
+            .for_each(|entity, (position, velocity, mass, drag)| {
+                position.0 += velocity.0 * mass.0 * drag.0;
+                visited.push(entity);
+            });
+        assert_eq!(visited, vec![moving]);
+
+        let mut positions = Vec::new();
+        compound
+            .query::<(&Position,), ()>()
+            .for_each(|entity, (position,)| {
+                positions.push((entity, position.0));
+            });
+        positions.sort_by_key(|(entity, _)| *entity);
+        assert_eq!(
+            positions,
+            vec![(moving, 1.0), (frozen, 0.0), (dead, 0.0), (light, 0.0)]
+        );
+
+        // Builder filters compose with the filter type
+        assert_eq!(compound.query::<(&Position,), With<Mass>>().count(), 3);
+        assert_eq!(
+            compound
+                .query::<(&Position,), With<Mass>>()
+                .without::<Frozen>()
+                .without::<Dead>()
+                .count(),
+            1
+        );
+        assert_eq!(compound.query::<(&Velocity, &Frozen), ()>().count(), 1);
+
+        // Clear the modified flags of every entity
+        compound
+            .query::<(&Position,), ()>()
+            .modified()
+            .for_each(|_, _| {});
+        assert_eq!(compound.query::<(&Position,), ()>().modified().count(), 4);
+
+        // Unmodified writes don't mark entities
+        compound
+            .query::<(&mut Velocity,), ()>()
+            .unmod()
+            .for_each(|_, (velocity,)| velocity.0 = 2.0);
+        let mut modified = Vec::new();
+        compound
+            .query::<(&Position,), ()>()
+            .modified()
+            .for_each(|entity, _| modified.push(entity));
+        assert!(modified.is_empty());
+
+        // Writes mark the entities they visit
+        compound
+            .query::<(&mut Velocity,), Without<Mass>>()
+            .for_each(|_, (velocity,)| velocity.0 = 3.0);
+        compound
+            .query::<(&Position,), ()>()
+            .modified()
+            .for_each(|entity, _| modified.push(entity));
+        assert_eq!(modified, vec![light]);
+
+        // Reads don't mark the entities they visit
+        compound.query::<(&Velocity,), ()>().for_each(|_, _| {});
+        compound
+            .query::<(&Position,), ()>()
+            .modified()
+            .for_each(|entity, _| modified.push(entity));
+        assert_eq!(modified, vec![light]);
+    }
+
+    #[test]
+    fn test_ecs_quad_quint() {
+        struct Position(f32);
+        struct Velocity(f32);
+        struct Mass(f32);
+        struct Drag(f32);
+        struct Visible(bool);
+        struct Frozen;
+
+        let compound = Compound::new();
+
+        let moving = compound.spawn((
+            Position(0.0),
+            Velocity(1.0),
+            Mass(2.0),
+            Drag(0.5),
+            Visible(true),
+        ));
+        let frozen = compound.spawn((Position(0.0), Velocity(1.0), Mass(2.0), Drag(0.5), Frozen));
+        compound.spawn((Position(0.0), Velocity(1.0), Mass(2.0)));
+
+        let mut visited = Vec::new();
+        compound.iter_quad(|entity, _: &Position, _: &Velocity, _: &Mass, _: &Drag| {
+            visited.push(entity);
+        });
+        visited.sort();
+        assert_eq!(visited, vec![moving, frozen]);
+
+        let mut visited = Vec::new();
+        compound.iter_quint(
+            |entity, _: &Position, _: &Velocity, _: &Mass, _: &Drag, visible: &Visible| {
+                assert!(visible.0);
+                visited.push(entity);
+            },
+        );
+        assert_eq!(visited, vec![moving]);
+
+        compound.iter_mut_without_quad::<Frozen, _, _, _, _, _>(
+            |_,
+             position: &mut Position,
+             velocity: &mut Velocity,
+             mass: &mut Mass,
+             drag: &mut Drag| {
+                position.0 += velocity.0 * mass.0 * drag.0;
+            },
+        );
+        compound.iter_mol(|entity, position: &Position| {
+            let expected = if entity == moving { 1.0 } else { 0.0 };
+            assert_eq!(position.0, expected);
+        });
+
+        // Clear the modified flags, then only touch one entity without marking it
+        compound.iter_quad_mod(|_, _: &Position, _: &Velocity, _: &Mass, _: &Drag| {});
+        compound.iter_mut_quint_unmod(
+            |_,
+             _: &mut Position,
+             _: &mut Velocity,
+             _: &mut Mass,
+             _: &mut Drag,
+             visible: &mut Visible| {
+                visible.0 = false;
+            },
+        );
+
+        let mut modified = 0;
+        compound.iter_mut_quad_mod(
+            |_, _: &mut Position, _: &mut Velocity, _: &mut Mass, _: &mut Drag| modified += 1,
+        );
+        assert_eq!(modified, 0);
+
+        compound.iter_mut_quint(
+            |_, _: &mut Position, _: &mut Velocity, _: &mut Mass, _: &mut Drag, _: &mut Visible| {},
+        );
+        let mut modified = Vec::new();
+        compound.iter_without_quad_mod::<Frozen, _, _, _, _, _>(
+            |entity, _: &Position, _: &Velocity, _: &Mass, _: &Drag| modified.push(entity),
+        );
+        assert_eq!(modified, vec![moving]);
+    }
+
+    #[test]
+    fn test_ecs_resources() {
+        #[derive(Debug, PartialEq)]
+        struct Gravity(f32);
+
+        #[derive(Debug, PartialEq)]
+        struct Score(u32);
+
+        let compound = Compound::new();
+
+        assert!(!compound.has_resource::<Gravity>());
+        assert_eq!(compound.resource::<Gravity, _>(|gravity| gravity.0), None);
+
+        assert_eq!(compound.insert_resource(Gravity(-9.81)), None);
+        compound.insert_resource(Score(0));
+        assert!(compound.has_resource::<Gravity>());
+
+        compound.resource_mut::<Score, _>(|score| {
+            // Other resources can be used while one is borrowed
+            let gravity = compound
+                .resource::<Gravity, _>(|gravity| gravity.0)
+                .unwrap();
+            score.0 += gravity.abs() as u32;
+        });
+        assert_eq!(compound.resource::<Score, _>(|score| score.0), Some(9));
+
+        assert_eq!(
+            compound.insert_resource(Gravity(-1.62)),
+            Some(Gravity(-9.81))
+        );
+        assert_eq!(compound.remove_resource::<Gravity>(), Some(Gravity(-1.62)));
+        assert!(!compound.has_resource::<Gravity>());
+        assert_eq!(compound.remove_resource::<Gravity>(), None);
+    }
+
+    #[test]
+    fn test_ecs_schedule() {
+        use std::sync::Mutex;
+
+        struct Velocity(f32);
+        struct Position(f32);
+        struct Score(u32);
+
+        let compound = Compound::new();
+        compound.spawn((Position(0.0), Velocity(2.0)));
+        compound.insert_resource(Score(0));
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut schedule = Schedule::<f32>::new();
+
+        let log = order.clone();
+        schedule.add_system(
+            System::new("movement", move |compound: &Compound, dt: &f32| {
+                compound.iter_mut_duo(
+                    |_entity, position: &mut Position, velocity: &mut Velocity| {
+                        position.0 += velocity.0 * dt;
+                    },
+                );
+                log.lock().unwrap().push("movement");
+            })
+            .writes::<Position>()
+            .reads::<Velocity>(),
+        );
+
+        let log = order.clone();
+        schedule.add_system(
+            System::new("score", move |compound: &Compound, _dt: &f32| {
+                compound.resource_mut::<Score, _>(|score| score.0 += 1);
+                log.lock().unwrap().push("score");
+            })
+            .writes::<Score>(),
+        );
+
+        let log = order.clone();
+        schedule.add_system(
+            System::new("drag", move |compound: &Compound, _dt: &f32| {
+                compound.iter_mut_mol(|_entity, velocity: &mut Velocity| velocity.0 *= 0.5);
+                log.lock().unwrap().push("drag");
+            })
+            .writes::<Velocity>()
+            .before("movement"),
+        );
+
+        // Drag has to finish before movement, score conflicts with neither
+        assert_eq!(
+            schedule.batches().unwrap(),
+            vec![vec!["score", "drag"], vec!["movement"]]
+        );
+
+        schedule.run(&compound, &1.0).unwrap();
+        let order = order.lock().unwrap().clone();
+        assert_eq!(order.len(), 3);
+        assert_eq!(order.last(), Some(&"movement"));
+
+        compound.iter_mol(|_entity, position: &Position| assert_eq!(position.0, 1.0));
+        assert_eq!(compound.resource::<Score, _>(|score| score.0), Some(1));
+
+        // Conflicting systems without ordering run in the order they were added
+        schedule.add_system(System::new("reset", |_: &Compound, _: &f32| {}).writes::<Position>());
+        assert_eq!(schedule.batches().unwrap().last().unwrap(), &vec!["reset"]);
+
+        schedule.add_system(
+            System::new("cycle", |_: &Compound, _: &f32| {})
+                .after("movement")
+                .before("drag"),
+        );
+        assert!(schedule.run(&compound, &1.0).is_err());
+
+        assert!(schedule.remove_system("cycle"));
+        assert!(schedule.run(&compound, &1.0).is_ok());
+    }
+
+    #[test]
+    fn test_ecs_change_filters() {
+        use std::sync::Mutex;
+
+        struct Health(i32);
+        struct Frozen;
+
+        let compound = Compound::new();
+        let mut schedule = Schedule::<()>::new();
+
+        let initialized = Arc::new(Mutex::new(Vec::new()));
+        let log = initialized.clone();
+        schedule.add_system(
+            System::new("init", move |compound: &Compound, _: &()| {
+                compound
+                    .query::<(&Health,), Added<Health>>()
+                    .for_each(|entity, _| log.lock().unwrap().push(entity));
+            })
+            .reads::<Health>(),
+        );
+
+        let reacted = Arc::new(Mutex::new(Vec::new()));
+        let log = reacted.clone();
+        schedule.add_system(
+            System::new("react", move |compound: &Compound, _: &()| {
+                compound
+                    .query::<(&Health,), ()>()
+                    .changed::<Health>()
+                    .for_each(|entity, _| log.lock().unwrap().push(entity));
+            })
+            .reads::<Health>(),
+        );
+
+        let frozen = Arc::new(Mutex::new(Vec::new()));
+        let log = frozen.clone();
+        schedule.add_system(
+            System::new("freeze", move |compound: &Compound, _: &()| {
+                compound
+                    .query::<(&Health,), Added<Frozen>>()
+                    .for_each(|entity, _| log.lock().unwrap().push(entity));
+            })
+            .reads::<Health>(),
+        );
+
+        let mut run = || {
+            schedule.run(&compound, &()).unwrap();
+            [&initialized, &reacted, &frozen].map(|log| {
+                let mut entities = std::mem::take(&mut *log.lock().unwrap());
+                entities.sort();
+                entities
+            })
+        };
+
+        let a = compound.spawn((Health(100),));
+        let b = compound.spawn((Health(100),));
+
+        // Added molecules count as changed as well
+        assert_eq!(run(), [vec![a, b], vec![a, b], vec![]]);
+        assert_eq!(run(), [vec![], vec![], vec![]]);
+
+        // Replacing changes a molecule without adding it
+        compound.add_molecule(a, Health(50));
+        let c = compound.spawn((Health(100),));
+        compound.add_molecule(b, Frozen);
+        assert_eq!(run(), [vec![c], vec![a, c], vec![b]]);
+
+        // Writes without change detection aren't seen
+        compound.iter_mut_mol_unmod(|_, health: &mut Health| health.0 += 1);
+        compound
+            .query::<(&mut Health,), ()>()
+            .unmod()
+            .for_each(|_, (health,)| health.0 += 1);
+        assert_eq!(run(), [vec![], vec![], vec![]]);
+
+        compound.iter_mut_mol(|_, health: &mut Health| health.0 -= 1);
+        assert_eq!(run(), [vec![], vec![a, b, c], vec![]]);
+
+        // The filters never consumed the modified flags
+        let mut modified = 0;
+        compound.iter_mol_mod(|_, _: &Health| modified += 1);
+        assert_eq!(modified, 3);
+
+        // A system doesn't see its own writes on its next run
+        let mut regen = Schedule::<()>::new();
+        let regenerated = Arc::new(Mutex::new(0));
+        let count = regenerated.clone();
+        regen.add_system(
+            System::new("regen", move |compound: &Compound, _: &()| {
+                compound
+                    .query::<(&mut Health,), Changed<Health>>()
+                    .for_each(|_, (health,)| {
+                        health.0 += 1;
+                        *count.lock().unwrap() += 1;
+                    });
+            })
+            .writes::<Health>(),
+        );
+        regen.run(&compound, &()).unwrap();
+        regen.run(&compound, &()).unwrap();
+        assert_eq!(*regenerated.lock().unwrap(), 3);
+
+        // Outside of a schedule the caller keeps the tick
+        let since = compound.advance_change_tick();
+        compound.add_molecule(c, Health(1));
+        assert_eq!(
+            compound
+                .query::<(&Health,), Changed<Health>>()
+                .since(since)
+                .count(),
+            1
+        );
+        assert_eq!(compound.query::<(&Health,), Added<Health>>().count(), 3);
+    }
+
+    #[test]
+    fn test_ecs_snapshot() {
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Position {
+            x: f32,
+            y: f32,
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Target(Entity);
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Score(u32);
+
+        // Not registered, so it isn't saved
+        struct Cache;
+
+        let mut registry = TypeRegistry::new();
+        registry
+            .register::<Position>("position")
+            .register::<Target>("target")
+            .register_resource::<Score>("score");
+        assert!(registry.is_registered::<Position>());
+        assert!(registry.is_registered::<Score>());
+        assert!(!registry.is_registered::<Cache>());
+
+        let compound = Compound::new();
+        let unsaved = compound.spawn((Cache,));
+        let player = compound.spawn((Position { x: 1.0, y: 2.0 }, Cache));
+        let enemy = compound.spawn((Position { x: -3.0, y: 0.5 }, Target(player)));
+        compound.insert_resource(Score(42));
+
+        let check = |loaded: &Compound| {
+            let mut positions = Vec::new();
+            loaded.iter_mol(|entity, position: &Position| {
+                positions.push((entity, position.x, position.y))
+            });
+            positions.sort_by_key(|(entity, _, _)| *entity);
+            assert_eq!(positions, vec![(player, 1.0, 2.0), (enemy, -3.0, 0.5)]);
+
+            let mut targets = Vec::new();
+            loaded.iter_mol(|entity, target: &Target| targets.push((entity, target.0)));
+            assert_eq!(targets, vec![(enemy, player)]);
+
+            let mut caches = 0;
+            loaded.iter_mol(|_entity, _cache: &Cache| caches += 1);
+            assert_eq!(caches, 0);
+
+            assert_eq!(loaded.resource::<Score, _>(|score| score.0), Some(42));
+
+            // New entities don't reuse the IDs of saved ones
+            let created = loaded.create_entity();
+            assert!(created > unsaved && created > player && created > enemy);
+        };
+
+        let ron = compound.to_ron(&registry).unwrap();
+        assert!(ron.contains("position"));
+        check(&Compound::from_ron(&registry, &ron).unwrap());
+
+        let bytes = compound.to_bincode(&registry).unwrap();
+        check(&Compound::from_bincode(&registry, &bytes).unwrap());
+
+        // Unregistered names are skipped
+        let mut partial = TypeRegistry::new();
+        partial.register::<Position>("position");
+        let loaded = Compound::from_ron(&partial, &ron).unwrap();
+        let mut targets = 0;
+        loaded.iter_mol(|_entity, _target: &Target| targets += 1);
+        assert_eq!(targets, 0);
+        assert!(!loaded.has_resource::<Score>());
+
+        assert!(Compound::from_ron(&registry, "not a snapshot").is_err());
+    }
+
+    #[test]
+    fn test_ecs_state_hash() {
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Position(f32, f32);
+
+        let mut registry = TypeRegistry::new();
+        registry.register::<Position>("position");
+
+        let first = Compound::new();
+        let second = Compound::new();
+        for compound in [&first, &second] {
+            compound.spawn((Position(1.0, 2.0),));
+            compound.spawn((Position(3.0, 4.0),));
+        }
+
+        let hash = first.state_hash(&registry).unwrap();
+        assert_eq!(hash, second.state_hash(&registry).unwrap());
+
+        // Unregistered molecules don't change the hash
+        first.add_molecule(0, 5_u32);
+        assert_eq!(hash, first.state_hash(&registry).unwrap());
+
+        second.iter_mut_mol(|_entity, position: &mut Position| position.0 += f32::EPSILON);
+        assert_ne!(hash, second.state_hash(&registry).unwrap());
+
+        // A loaded snapshot hashes the same as the compound it was saved from
+        let loaded = Compound::from_bincode(&registry, &first.to_bincode(&registry).unwrap());
+        assert_eq!(hash, loaded.unwrap().state_hash(&registry).unwrap());
+    }
+
+    #[test]
+    fn test_ecs_names() {
+        let compound = Compound::new();
+        let level = Compound::new();
+
+        assert_eq!(compound.find_by_name("Player"), None);
+
+        let player = compound.spawn((Name::new("Player"),));
+        let first_enemy = compound.spawn((Name::new("Enemy"),));
+        let second_enemy = compound.spawn((Name::new("Enemy"),));
+        assert_eq!(compound.find_by_name("Player"), Some(player));
+        assert_eq!(compound.find_by_name("Enemy"), Some(first_enemy));
+
+        // Renaming moves the entity in the index
+        compound.add_molecule(first_enemy, Name::new("Boss"));
+        assert_eq!(compound.find_by_name("Boss"), Some(first_enemy));
+        assert_eq!(compound.find_by_name("Enemy"), Some(second_enemy));
+
+        assert_eq!(
+            compound.remove_molecule::<Name>(second_enemy),
+            Some(Name::new("Enemy"))
+        );
+        assert_eq!(compound.find_by_name("Enemy"), None);
+
+        // Names changed in place are never found under their old name
+        compound.iter_mut_mol(|_entity, name: &mut Name| {
+            if name.as_str() == "Boss" {
+                *name = Name::new("Defeated Boss");
+            }
+        });
+        assert_eq!(compound.find_by_name("Boss"), None);
+
+        let moved = compound.move_entity(player, &level);
+        assert_eq!(compound.find_by_name("Player"), None);
+        assert_eq!(level.find_by_name("Player"), Some(moved));
+    }
+
+    #[test]
+    fn test_ecs_tags() {
+        use std::sync::atomic::{AtomicI32, Ordering};
+
+        static ALIVE: AtomicI32 = AtomicI32::new(0);
+
+        struct Frozen;
+
+        impl Frozen {
+            fn new() -> Self {
+                ALIVE.fetch_add(1, Ordering::Relaxed);
+                Frozen
+            }
+        }
+
+        impl Drop for Frozen {
+            fn drop(&mut self) {
+                ALIVE.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+
+        struct Position(f32);
+
+        {
+            let compound = Compound::new();
+            let frozen = compound.spawn((Position(0.0), Frozen::new()));
+            let moving = compound.spawn((Position(0.0),));
+            compound.spawn((Position(0.0), Frozen::new()));
+            compound.add_molecule(moving, Frozen::new());
+            assert_eq!(ALIVE.load(Ordering::Relaxed), 3);
+
+            let mut tagged = Vec::new();
+            compound.iter_mol(|entity, _frozen: &Frozen| tagged.push(entity));
+            tagged.sort();
+            assert_eq!(tagged, vec![0, 1, 2]);
+
+            assert!(compound.remove_molecule::<Frozen>(moving).is_some());
+            assert!(compound.remove_molecule::<Frozen>(moving).is_none());
+            assert_eq!(ALIVE.load(Ordering::Relaxed), 2);
+
+            compound
+                .query::<(&mut Position,), Without<Frozen>>()
+                .for_each(|_, (position,)| position.0 = 1.0);
+            compound
+                .query::<(&Position, &mut Frozen), ()>()
+                .for_each(|entity, (position, _)| {
+                    assert_ne!(entity, moving);
+                    assert_eq!(position.0, 0.0);
+                });
+            assert!(compound.remove_molecule::<Frozen>(frozen).is_some());
+            assert_eq!(ALIVE.load(Ordering::Relaxed), 1);
+        }
+
+        assert_eq!(ALIVE.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_ecs_nested_iteration() {
+        struct Position(f32);
+        struct Velocity(f32);
+
+        let compound = Compound::new();
+        for index in 0..10 {
+            compound.spawn((Position(index as f32), Velocity(1.0)));
+        }
+
+        // Reading the molecules the outer iterator holds
+        let mut pairs = 0;
+        compound.iter_mol(|_, _: &Position| {
+            compound.iter_duo(|_, _: &Position, _: &Velocity| pairs += 1);
+        });
+        assert_eq!(pairs, 100);
+
+        // Writing other molecules from inside a modified only iterator
+        let mut visited = 0;
+        compound.iter_mol_mod(|entity, _: &Position| {
+            visited += 1;
+            compound
+                .query::<(&mut Velocity,), ()>()
+                .for_each(|other, (velocity,)| {
+                    if other == entity {
+                        velocity.0 += 1.0;
+                    }
+                });
+        });
+        assert_eq!(visited, 10);
+
+        // The writes marked every entity modified again
+        visited = 0;
+        compound.iter_mol_mod(|_, _: &Position| visited += 1);
+        assert_eq!(visited, 10);
+        compound.iter_mol(|_, velocity: &Velocity| assert_eq!(velocity.0, 2.0));
+
+        // The same molecule read twice by one query
+        compound
+            .query::<(&Position, &Position), ()>()
+            .for_each(|_, (a, b)| assert_eq!(a.0, b.0));
+    }
+
+    #[test]
+    fn test_ecs_nested_iteration_panics() {
+        use std::panic::{AssertUnwindSafe, catch_unwind};
+
+        struct Position(f32);
+
+        let compound = Compound::new();
+        let entity = compound.spawn((Position(0.0),));
+
+        // Writing a molecule the outer iterator is reading
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            compound.iter_mol(|_, _: &Position| {
+                compound.iter_mut_mol(|_, position: &mut Position| position.0 = 1.0);
+            });
+        }));
+        assert!(result.is_err());
+
+        // Adding molecules of the type being iterated
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            compound.iter_mol(|entity, _: &Position| {
+                compound.add_molecule(entity, Position(1.0));
+            });
+        }));
+        assert!(result.is_err());
+
+        // The locks were released by the panics
+        compound.iter_mut_mol(|_, position: &mut Position| position.0 = 2.0);
+        compound.add_molecule(entity, Position(3.0));
+        compound.iter_mol(|_, position: &Position| assert_eq!(position.0, 3.0));
+    }
+
+    #[test]
+    fn test_ecs_nested_iteration_stress() {
+        use std::sync::mpsc;
+
+        struct Position(f32);
+        struct Velocity(f32);
+        struct Health(i32);
+
+        const ITERATIONS: usize = 50;
+
+        let compound = Arc::new(Compound::new());
+        for index in 0..16 {
+            compound.spawn((Position(index as f32), Velocity(1.0), Health(100)));
+        }
+
+        let (done, finished) = mpsc::channel();
+        let mut workers = Vec::new();
+
+        // Nested readers of the same storages
+        for _ in 0..3 {
+            let compound = compound.clone();
+            let done = done.clone();
+            workers.push(thread::spawn(move || {
+                for _ in 0..ITERATIONS {
+                    compound.iter_mol(|_, _: &Position| {
+                        compound.iter_duo(|_, position: &Position, velocity: &Velocity| {
+                            assert!(position.0 >= 0.0 && velocity.0 >= 0.0);
+                        });
+                    });
+                    compound
+                        .query::<(&Velocity, &Position), ()>()
+                        .for_each(|_, _| compound.iter_mol(|_, _: &Velocity| {}));
+                }
+                done.send(()).unwrap();
+            }));
+        }
+
+        // Writers of other molecules nested inside modified only readers
+        {
+            let compound = compound.clone();
+            let done = done.clone();
+            workers.push(thread::spawn(move || {
+                for _ in 0..ITERATIONS {
+                    compound.iter_mol_mod(|_, _: &Position| {
+                        compound.iter_mut_mol(|_, health: &mut Health| health.0 -= 1);
+                    });
+                }
+                done.send(()).unwrap();
+            }));
+        }
+
+        // Structural changes queued up behind the readers
+        for _ in 0..2 {
+            let compound = compound.clone();
+            let done = done.clone();
+            workers.push(thread::spawn(move || {
+                for index in 0..ITERATIONS {
+                    let entity = compound.spawn((Position(0.0), Velocity(0.0), Health(1)));
+                    if index % 2 == 0 {
+                        compound.remove_molecule::<Velocity>(entity);
+                    }
+                }
+                done.send(()).unwrap();
+            }));
+        }
+
+        for _ in 0..workers.len() {
+            finished
+                .recv_timeout(Duration::from_secs(60))
+                .expect("Nested iteration deadlocked");
+        }
+
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        let mut positions = 0;
+        compound.iter_mol(|_, _: &Position| positions += 1);
+        assert_eq!(positions, 16 + 2 * ITERATIONS);
+    }
+
+    #[cfg(feature = "lock_diagnostics")]
+    #[test]
+    fn test_ecs_lock_diagnostics() {
+        use lock_diagnostics::{LockKind, LockReport};
+
+        struct Position(f32);
+        struct Velocity(f32);
+
+        let compound = Compound::new();
+        compound.spawn((Position(0.0), Velocity(0.0)));
+
+        // Only reports about the molecules of this test, tests run in parallel
+        let reports = || {
+            lock_diagnostics::take_lock_reports()
+                .into_iter()
+                .filter(|report| match report {
+                    LockReport::OrderInversion { cycle, .. } => cycle
+                        .iter()
+                        .any(|class| class.type_name == std::any::type_name::<Position>()),
+                    LockReport::LongHold { class, .. } | LockReport::Contention { class, .. } => {
+                        class.type_name == std::any::type_name::<Position>()
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Nested reads in both orders never wait on each other
+        compound.iter_mol(|_, _: &Position| compound.iter_mol(|_, _: &Velocity| {}));
+        compound.iter_mol(|_, _: &Velocity| compound.iter_mol(|_, _: &Position| {}));
+        assert!(reports().is_empty());
+
+        // Adding molecules of one type while iterating the other, both ways around
+        compound.iter_mol(|entity, _: &Position| compound.add_molecule(entity, Velocity(1.0)));
+        compound.iter_mol(|entity, _: &Velocity| compound.add_molecule(entity, Position(1.0)));
+
+        let reports = reports();
+        assert!(reports.iter().any(|report| match report {
+            LockReport::OrderInversion { cycle, .. } => {
+                cycle.first() == cycle.last()
+                    && cycle.iter().any(|class| {
+                        class.kind == LockKind::Storage
+                            && class.type_name == std::any::type_name::<Velocity>()
+                    })
+            }
+            _ => false,
+        }));
+
+        // A write lock held past the threshold
+        compound.iter_mut_mol(|_, _: &mut Position| {
+            thread::sleep(lock_diagnostics::DEFAULT_LONG_HOLD_THRESHOLD * 2)
+        });
+        assert!(
+            lock_diagnostics::take_lock_reports()
+                .iter()
+                .any(|report| matches!(
+                    report,
+                    LockReport::LongHold { class, write: true, .. }
+                        if class.kind == LockKind::Molecule
+                            && class.type_name == std::any::type_name::<Position>()
+                ))
+        );
+    }
 }
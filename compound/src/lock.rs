@@ -0,0 +1,208 @@
+//! The locking discipline of a `Compound`.
+//!
+//! Molecules are locked at two levels, the storage of every molecule type and
+//! the cell of every molecule. Iterators hand molecules to user code that may
+//! run more iterators, so locks are taken by these rules to keep nested
+//! iteration from deadlocking:
+//!
+//! 1. Storages are read locked for the whole iteration. A thread that is
+//!    already reading a storage takes any further read locks recursively, so
+//!    an iterator nested inside another never waits on a writer that queued
+//!    up between the two, which could be waiting on the outer iterator of
+//!    another thread. The outermost iterator of a thread still waits its turn
+//!    behind writers so they aren't starved.
+//! 2. A storage is only write locked to add or remove its molecules. A thread
+//!    can't do that to a storage it is iterating over, which would wait on
+//!    itself forever, so it panics instead. Collect the changes and apply
+//!    them after the iteration.
+//! 3. The cells of one entity are locked in the order of their `TypeId`, so
+//!    two iterators over the same molecules never wait on each other.
+//! 4. Cells are read recursively by a thread that already holds a cell, the
+//!    same as storages. A cell a thread is writing can't be locked again and
+//!    a cell it is reading can't be written, both panic the way a `RefCell`
+//!    borrowed twice does instead of deadlocking.
+//! 5. Modified flags are only locked to check or change them, never while
+//!    user code runs.
+//!
+//! With the `lock_diagnostics` feature every storage and molecule lock is
+//! also recorded by `lock_diagnostics`, which reports the deadlocks these
+//! rules can't rule out.
+
+use std::{
+    any::type_name,
+    cell::RefCell,
+    ops::{Deref, DerefMut},
+};
+
+use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::MoleculeStorage;
+#[cfg(feature = "lock_diagnostics")]
+use crate::lock_diagnostics;
+
+thread_local! {
+    /// Addresses of the storages this thread is iterating over, once per iterator
+    static ITERATED_STORAGES: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+
+    /// Addresses of the molecules this thread has locked, and whether they are written
+    static LOCKED_CELLS: RefCell<Vec<(usize, bool)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Read access to a `MoleculeStorage` that is held while iterating over it.
+///
+/// Dereferences to the storage, and marks it as iterated by this thread
+/// until it is dropped.
+pub struct StorageGuard<'a, T: Send + Sync + 'static> {
+    guard: RwLockReadGuard<'a, MoleculeStorage<T>>,
+    address: usize,
+}
+
+impl<T: Send + Sync + 'static> Deref for StorageGuard<'_, T> {
+    type Target = MoleculeStorage<T>;
+
+    fn deref(&self) -> &MoleculeStorage<T> {
+        &self.guard
+    }
+}
+
+impl<T: Send + Sync + 'static> Drop for StorageGuard<'_, T> {
+    fn drop(&mut self) {
+        ITERATED_STORAGES.with_borrow_mut(|storages| {
+            if let Some(index) = storages
+                .iter()
+                .rposition(|address| *address == self.address)
+            {
+                storages.swap_remove(index);
+            }
+        });
+
+        #[cfg(feature = "lock_diagnostics")]
+        lock_diagnostics::released(self.address);
+    }
+}
+
+/// Write access to a `MoleculeStorage` to add or remove its molecules.
+///
+/// Dereferences to the storage.
+pub struct StorageWriteGuard<'a, T: Send + Sync + 'static> {
+    guard: RwLockWriteGuard<'a, MoleculeStorage<T>>,
+    #[cfg(feature = "lock_diagnostics")]
+    address: usize,
+}
+
+impl<T: Send + Sync + 'static> Deref for StorageWriteGuard<'_, T> {
+    type Target = MoleculeStorage<T>;
+
+    fn deref(&self) -> &MoleculeStorage<T> {
+        &self.guard
+    }
+}
+
+impl<T: Send + Sync + 'static> DerefMut for StorageWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut MoleculeStorage<T> {
+        &mut self.guard
+    }
+}
+
+#[cfg(feature = "lock_diagnostics")]
+impl<T: Send + Sync + 'static> Drop for StorageWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        lock_diagnostics::released(self.address);
+    }
+}
+
+/// Read locks a storage to iterate over it.
+///
+/// The lock is taken recursively if this thread already reads a storage, so
+/// iterators nested inside each other never wait on a writer queued between
+/// them.
+pub(crate) fn read_storage<T: Send + Sync + 'static>(
+    storage: &RwLock<MoleculeStorage<T>>,
+) -> StorageGuard<'_, T> {
+    let address = storage.data_ptr() as usize;
+    let recursive = ITERATED_STORAGES.with_borrow(|storages| !storages.is_empty());
+
+    #[cfg(feature = "lock_diagnostics")]
+    lock_diagnostics::acquiring(
+        address,
+        lock_diagnostics::storage_class::<T>(),
+        false,
+        recursive,
+    );
+
+    let guard = if recursive {
+        storage.read_recursive()
+    } else {
+        storage.read()
+    };
+    ITERATED_STORAGES.with_borrow_mut(|storages| storages.push(address));
+
+    #[cfg(feature = "lock_diagnostics")]
+    lock_diagnostics::acquired(address, lock_diagnostics::storage_class::<T>(), false);
+
+    StorageGuard { guard, address }
+}
+
+/// Write locks a storage to add or remove its molecules.
+///
+/// # Panics
+/// If this thread is iterating over the storage, which would deadlock
+pub(crate) fn write_storage<T: Send + Sync + 'static>(
+    storage: &RwLock<MoleculeStorage<T>>,
+) -> StorageWriteGuard<'_, T> {
+    let address = storage.data_ptr() as usize;
+
+    if ITERATED_STORAGES.with_borrow(|storages| storages.contains(&address)) {
+        panic!(
+            "Can't add or remove {} molecules while iterating over them, apply the changes after the iteration",
+            type_name::<T>()
+        );
+    }
+
+    #[cfg(feature = "lock_diagnostics")]
+    lock_diagnostics::acquiring(address, lock_diagnostics::storage_class::<T>(), true, false);
+
+    let guard = storage.write();
+
+    #[cfg(feature = "lock_diagnostics")]
+    lock_diagnostics::acquired(address, lock_diagnostics::storage_class::<T>(), true);
+
+    StorageWriteGuard {
+        guard,
+        #[cfg(feature = "lock_diagnostics")]
+        address,
+    }
+}
+
+/// Returns whether this thread has locked the molecule at an address, and if it is writing it
+pub(crate) fn cell_lock(address: usize) -> Option<bool> {
+    LOCKED_CELLS.with_borrow(|cells| {
+        cells
+            .iter()
+            .rev()
+            .find(|(cell, _)| *cell == address)
+            .map(|(_, write)| *write)
+    })
+}
+
+/// Returns whether this thread holds a lock on any molecule
+pub(crate) fn holds_cells() -> bool {
+    LOCKED_CELLS.with_borrow(|cells| !cells.is_empty())
+}
+
+/// Records that this thread locked the molecule at an address
+pub(crate) fn lock_cell(address: usize, write: bool) {
+    LOCKED_CELLS.with_borrow_mut(|cells| cells.push((address, write)));
+}
+
+/// Records that this thread released a lock on the molecule at an address
+pub(crate) fn unlock_cell(address: usize) {
+    LOCKED_CELLS.with_borrow_mut(|cells| {
+        if let Some(index) = cells.iter().rposition(|(cell, _)| *cell == address) {
+            cells.remove(index);
+        }
+    });
+
+    #[cfg(feature = "lock_diagnostics")]
+    lock_diagnostics::released(address);
+}
@@ -0,0 +1,501 @@
+//! Diagnostics for the locks of a `Compound`, built with the `lock_diagnostics` feature.
+//!
+//! Every storage and molecule lock is recorded as it is taken and released,
+//! which finds the deadlocks the locking discipline in `lock` can't rule out:
+//!
+//! - **Order inversions**: the locks of two molecule types taken in opposite
+//!   orders, on any threads at any time. These deadlock once both orders
+//!   happen at the same time, so they are reported the first time they are
+//!   seen rather than when they hang. Locks are grouped by their molecule
+//!   type, so an inversion between two entities counts the same as one on a
+//!   single entity
+//! - **Long holds**: locks held long enough to stall every thread waiting on them
+//! - **Contention**: threads that timed out waiting on a lock, along with the
+//!   threads holding it
+//!
+//! Reports are logged as they are found and kept for `take_lock_reports`.
+//! Every lock goes through a global registry, so this is far too slow to
+//! leave on outside of debugging.
+//!
+//! # Example
+//! ```ignore
+//! compound::lock_diagnostics::set_long_hold_threshold(Duration::from_millis(20));
+//!
+//! schedule.run(&compound);
+//!
+//! for report in compound::lock_diagnostics::take_lock_reports() {
+//!     println!("{report}");
+//! }
+//! ```
+
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::LazyLock,
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::{error, warn};
+use parking_lot::Mutex;
+
+/// How long a lock can be held before it is reported, unless changed with `set_long_hold_threshold`
+pub const DEFAULT_LONG_HOLD_THRESHOLD: Duration = Duration::from_millis(100);
+
+// Older reports are dropped once this many haven't been taken
+const MAX_REPORTS: usize = 256;
+
+/// The level a lock is taken at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LockKind {
+    /// The storage of every molecule of a type
+    Storage,
+    /// A single molecule
+    Molecule,
+}
+
+/// Every lock of one kind on one molecule type, which is what lock orders are tracked by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LockClass {
+    pub kind: LockKind,
+    pub type_name: &'static str,
+}
+
+impl fmt::Display for LockClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            LockKind::Storage => write!(f, "{} storage", self.type_name),
+            LockKind::Molecule => write!(f, "{} molecule", self.type_name),
+        }
+    }
+}
+
+/// A thread holding a lock when another timed out waiting on it
+#[derive(Debug, Clone)]
+pub struct LockHolder {
+    pub thread: String,
+    pub write: bool,
+    pub held: Duration,
+}
+
+/// A problem found with the locks of a `Compound`
+#[derive(Debug, Clone)]
+pub enum LockReport {
+    /// Locks taken in an order that closes a cycle with the orders taken before
+    OrderInversion {
+        /// The locks of the cycle, starting and ending with the lock that was held
+        cycle: Vec<LockClass>,
+        /// The thread that closed the cycle
+        thread: String,
+        /// The thread that first took the locks the other way around
+        other_thread: String,
+    },
+    /// A lock held for longer than the long hold threshold
+    LongHold {
+        class: LockClass,
+        write: bool,
+        thread: String,
+        held: Duration,
+    },
+    /// A thread that timed out waiting on a lock
+    Contention {
+        class: LockClass,
+        write: bool,
+        thread: String,
+        holders: Vec<LockHolder>,
+    },
+}
+
+impl fmt::Display for LockReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockReport::OrderInversion {
+                cycle,
+                thread,
+                other_thread,
+            } => {
+                let cycle = cycle
+                    .iter()
+                    .map(|class| class.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+
+                write!(
+                    f,
+                    "Lock order inversion: thread '{thread}' locked {cycle}, while thread '{other_thread}' \
+                     locked them the other way around. These threads deadlock if they run at the same time, \
+                     lock these molecules in one order everywhere or release one before locking the next"
+                )
+            }
+            LockReport::LongHold {
+                class,
+                write,
+                thread,
+                held,
+            } => write!(
+                f,
+                "Long lock hold: thread '{thread}' held the {} lock on the {class} for {held:?}, stalling \
+                 every thread waiting on it. Copy what is needed out of the lock, or move the slow work out \
+                 of the iteration",
+                mode(*write)
+            ),
+            LockReport::Contention {
+                class,
+                write,
+                thread,
+                holders,
+            } => {
+                write!(
+                    f,
+                    "Lock contention: thread '{thread}' timed out waiting to {} the {class}",
+                    mode(*write)
+                )?;
+
+                match holders.is_empty() {
+                    true => write!(
+                        f,
+                        ", which is held by no thread, a writer may have been queued"
+                    ),
+                    false => {
+                        let holders = holders
+                            .iter()
+                            .map(|holder| {
+                                format!(
+                                    "'{}' ({} for {:?})",
+                                    holder.thread,
+                                    mode(holder.write),
+                                    holder.held
+                                )
+                            })
+                            .collect::<Vec<_>>()
+                            .join(", ");
+
+                        write!(f, ", held by {holders}")
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn mode(write: bool) -> &'static str {
+    match write {
+        true => "write",
+        false => "read",
+    }
+}
+
+// A lock held by the current thread
+struct HeldLock {
+    address: usize,
+    class: LockClass,
+    write: bool,
+    since: Instant,
+}
+
+// A lock taken while holding another
+struct Edge {
+    // The first thread to take the locks in this order
+    thread: String,
+    // Whether the lock held first was ever written
+    held_write: bool,
+    // Whether the lock taken second ever waited on readers, which any write
+    // or non recursive read does
+    waits_on_readers: bool,
+}
+
+struct Holder {
+    thread: String,
+    write: bool,
+    since: Instant,
+}
+
+struct Registry {
+    // The order locks were taken in, from the lock held to the lock taken
+    order: HashMap<LockClass, HashMap<LockClass, Edge>>,
+    // The cycles already reported, by their sorted locks
+    reported: HashSet<Vec<LockClass>>,
+    // The threads holding each lock by its address
+    holders: HashMap<usize, Vec<Holder>>,
+    reports: Vec<LockReport>,
+    long_hold_threshold: Duration,
+}
+
+static REGISTRY: LazyLock<Mutex<Registry>> = LazyLock::new(|| {
+    Mutex::new(Registry {
+        order: HashMap::new(),
+        reported: HashSet::new(),
+        holders: HashMap::new(),
+        reports: Vec::new(),
+        long_hold_threshold: DEFAULT_LONG_HOLD_THRESHOLD,
+    })
+});
+
+thread_local! {
+    static HELD: RefCell<Vec<HeldLock>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Sets how long a lock can be held before it is reported.
+///
+/// # Arguments
+/// * `threshold` - The longest a lock can be held without a report
+pub fn set_long_hold_threshold(threshold: Duration) {
+    REGISTRY.lock().long_hold_threshold = threshold;
+}
+
+/// Takes every report found since the last call.
+///
+/// # Returns
+/// The reports in the order they were found
+pub fn take_lock_reports() -> Vec<LockReport> {
+    std::mem::take(&mut REGISTRY.lock().reports)
+}
+
+/// Forgets the lock orders seen so far, for when the order of a system
+/// changed on purpose and the old order is no longer taken
+pub fn reset_lock_order() {
+    let mut registry = REGISTRY.lock();
+    registry.order.clear();
+    registry.reported.clear();
+}
+
+fn thread_name() -> String {
+    let thread = thread::current();
+    match thread.name() {
+        Some(name) => name.to_string(),
+        None => format!("{:?}", thread.id()),
+    }
+}
+
+fn report(registry: &mut Registry, report: LockReport) {
+    match report {
+        LockReport::OrderInversion { .. } => error!("{report}"),
+        _ => warn!("{report}"),
+    }
+
+    if registry.reports.len() >= MAX_REPORTS {
+        registry.reports.remove(0);
+    }
+    registry.reports.push(report);
+}
+
+impl Registry {
+    // Finds the locks taken after one another from one lock to another
+    fn path(&self, from: LockClass, to: LockClass) -> Option<Vec<LockClass>> {
+        let mut previous = HashMap::from([(from, from)]);
+        let mut queue = vec![from];
+
+        while let Some(class) = queue.pop() {
+            if class == to {
+                let mut path = vec![to];
+                let mut class = to;
+                while class != from {
+                    class = previous[&class];
+                    path.push(class);
+                }
+                path.reverse();
+
+                return Some(path);
+            }
+
+            for next in self
+                .order
+                .get(&class)
+                .into_iter()
+                .flat_map(|edges| edges.keys())
+            {
+                if !previous.contains_key(next) {
+                    previous.insert(*next, class);
+                    queue.push(*next);
+                }
+            }
+        }
+
+        None
+    }
+
+    // Whether a cycle can deadlock, which needs every thread of it to wait
+    // on the lock held by the next
+    fn can_deadlock(&self, cycle: &[LockClass]) -> bool {
+        let edges = cycle
+            .windows(2)
+            .map(|pair| &self.order[&pair[0]][&pair[1]])
+            .collect::<Vec<_>>();
+
+        (0..edges.len()).all(|index| {
+            edges[index].waits_on_readers || edges[(index + 1) % edges.len()].held_write
+        })
+    }
+}
+
+/// Records that this thread is about to wait on a lock, checking the order
+/// against the locks it already holds
+pub(crate) fn acquiring(address: usize, class: LockClass, write: bool, recursive: bool) {
+    let held = HELD.with_borrow(|held| {
+        held.iter()
+            .filter(|held| held.address != address && held.class != class)
+            .map(|held| (held.class, held.write))
+            .collect::<Vec<_>>()
+    });
+
+    if held.is_empty() {
+        return;
+    }
+
+    let thread = thread_name();
+    let mut registry = REGISTRY.lock();
+    for (from, held_write) in held {
+        let waits_on_readers = write || !recursive;
+        let edge = registry
+            .order
+            .entry(from)
+            .or_default()
+            .entry(class)
+            .or_insert_with(|| Edge {
+                thread: thread.clone(),
+                held_write: false,
+                waits_on_readers: false,
+            });
+
+        // Only an order that is new, or now waits where it didn't, can close a new cycle
+        let changed =
+            (held_write && !edge.held_write) || (waits_on_readers && !edge.waits_on_readers);
+        edge.held_write |= held_write;
+        edge.waits_on_readers |= waits_on_readers;
+        if !changed {
+            continue;
+        }
+
+        let Some(path) = registry.path(class, from) else {
+            continue;
+        };
+
+        let mut cycle = vec![from];
+        cycle.extend(path);
+        if !registry.can_deadlock(&cycle) {
+            continue;
+        }
+
+        let mut key = cycle[1..].to_vec();
+        key.sort();
+        if !registry.reported.insert(key) {
+            continue;
+        }
+
+        let other_thread = registry.order[&cycle[1]][&cycle[2]].thread.clone();
+        report(
+            &mut registry,
+            LockReport::OrderInversion {
+                cycle,
+                thread: thread.clone(),
+                other_thread,
+            },
+        );
+    }
+}
+
+/// Records that this thread took a lock
+pub(crate) fn acquired(address: usize, class: LockClass, write: bool) {
+    let since = Instant::now();
+    HELD.with_borrow_mut(|held| {
+        held.push(HeldLock {
+            address,
+            class,
+            write,
+            since,
+        })
+    });
+
+    REGISTRY
+        .lock()
+        .holders
+        .entry(address)
+        .or_default()
+        .push(Holder {
+            thread: thread_name(),
+            write,
+            since,
+        });
+}
+
+/// Records that this thread released a lock, reporting it if it was held too long
+pub(crate) fn released(address: usize) {
+    let Some(lock) = HELD.with_borrow_mut(|held| {
+        let index = held.iter().rposition(|held| held.address == address)?;
+        Some(held.remove(index))
+    }) else {
+        return;
+    };
+
+    let thread = thread_name();
+    let mut registry = REGISTRY.lock();
+    if let Some(holders) = registry.holders.get_mut(&address) {
+        if let Some(index) = holders
+            .iter()
+            .rposition(|holder| holder.thread == thread && holder.since == lock.since)
+        {
+            holders.swap_remove(index);
+        }
+
+        if holders.is_empty() {
+            registry.holders.remove(&address);
+        }
+    }
+
+    let held = lock.since.elapsed();
+    if held > registry.long_hold_threshold {
+        report(
+            &mut registry,
+            LockReport::LongHold {
+                class: lock.class,
+                write: lock.write,
+                thread,
+                held,
+            },
+        );
+    }
+}
+
+/// Reports that this thread timed out waiting on a lock, along with who holds it
+pub(crate) fn contended(address: usize, class: LockClass, write: bool) {
+    let thread = thread_name();
+    let mut registry = REGISTRY.lock();
+    let holders = registry
+        .holders
+        .get(&address)
+        .into_iter()
+        .flatten()
+        .map(|holder| LockHolder {
+            thread: holder.thread.clone(),
+            write: holder.write,
+            held: holder.since.elapsed(),
+        })
+        .collect();
+
+    report(
+        &mut registry,
+        LockReport::Contention {
+            class,
+            write,
+            thread,
+            holders,
+        },
+    );
+}
+
+/// Returns the class of the storage of a molecule type
+pub(crate) fn storage_class<T: 'static>() -> LockClass {
+    LockClass {
+        kind: LockKind::Storage,
+        type_name: std::any::type_name::<T>(),
+    }
+}
+
+/// Returns the class of the molecules of a type
+pub(crate) fn molecule_class<T: 'static>() -> LockClass {
+    LockClass {
+        kind: LockKind::Molecule,
+        type_name: std::any::type_name::<T>(),
+    }
+}
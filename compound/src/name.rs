@@ -0,0 +1,132 @@
+use std::{
+    any::{Any, TypeId},
+    fmt::Display,
+    sync::Arc,
+};
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+use crate::{Compound, Entity, MoleculeStorage, lock};
+
+/// Molecule naming an entity so it can be found with `Compound::find_by_name`.
+///
+/// Names don't have to be unique, the oldest entity with a name is found
+/// first. An entity is renamed by adding a new `Name`, a name changed in place
+/// through a mutable iterator isn't seen by the index.
+///
+/// # Example
+/// ```ignore
+/// compound.spawn((Name::new("Player"), Transform3D::default()));
+///
+/// if let Some(player) = compound.find_by_name("Player") {
+///     compound.add_molecule(player, Name::new("Player (Dead)"));
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Name(String);
+
+impl Name {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for Name {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for Name {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<String> for Name {
+    fn from(name: String) -> Self {
+        Self(name)
+    }
+}
+
+impl Compound {
+    /// Finds an entity by its `Name`.
+    ///
+    /// # Arguments
+    /// - `name`: The name to look for
+    ///
+    /// # Returns
+    /// The oldest entity with the name, or `None` if no entity has it
+    ///
+    /// # Example
+    /// ```ignore
+    /// let boss = compound.find_by_name("Boss")?;
+    /// compound.add_molecule(boss, Enraged);
+    /// ```
+    pub fn find_by_name(&self, name: &str) -> Option<Entity> {
+        let candidates = self.names.read().get(name)?.clone();
+        let storage = self.name_storage()?;
+        let storage = lock::read_storage(&storage);
+
+        // Skip entities whose name was changed in place
+        candidates.into_iter().find(|entity| {
+            storage
+                .compounds
+                .get(entity)
+                .is_some_and(|cell| cell.read().as_str() == name)
+        })
+    }
+
+    /// Keeps the name index up to date when a molecule of an entity is replaced
+    /// or removed
+    pub(crate) fn index_name<T: 'static>(&self, entity: Entity, old: Option<&T>, new: Option<&T>) {
+        let old = old.and_then(|old| (old as &dyn Any).downcast_ref::<Name>());
+        let new = new.and_then(|new| (new as &dyn Any).downcast_ref::<Name>());
+        if old.is_none() && new.is_none() {
+            return;
+        }
+
+        let mut names = self.names.write();
+
+        if let Some(old) = old
+            && let Some(entities) = names.get_mut(old.as_str())
+        {
+            entities.retain(|named| *named != entity);
+            if entities.is_empty() {
+                names.remove(old.as_str());
+            }
+        }
+
+        if let Some(new) = new {
+            let entities = names.entry(new.0.clone()).or_default();
+            // Older entities come first
+            let index = entities.partition_point(|named| *named < entity);
+            entities.insert(index, entity);
+        }
+    }
+
+    /// Removes an entity that is leaving the compound from the name index
+    pub(crate) fn unindex_name(&self, entity: Entity) {
+        let Some(storage) = self.name_storage() else {
+            return;
+        };
+
+        if let Some(cell) = lock::read_storage(&storage).compounds.get(&entity) {
+            self.index_name(entity, Some(&*cell.read()), None);
+        }
+    }
+
+    fn name_storage(&self) -> Option<Arc<RwLock<MoleculeStorage<Name>>>> {
+        self.storages
+            .read()
+            .get(&TypeId::of::<Name>())?
+            .as_any()
+            .downcast_ref::<Arc<RwLock<MoleculeStorage<Name>>>>()
+            .cloned()
+    }
+}
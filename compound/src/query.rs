@@ -0,0 +1,724 @@
+//! Composable queries over the molecules of a `Compound`.
+//!
+//! A query names the molecules it reads or writes as a tuple of references
+//! and the molecules that include or exclude entities as a filter, so any
+//! combination of access and filters is covered by a single implementation
+//! instead of one `iter_*` method per combination.
+
+use std::{any::TypeId, marker::PhantomData, sync::Arc};
+
+use parking_lot::RwLock;
+
+use crate::{
+    Compound, Entity, Modified, MoleculeCell, MoleculeMut, MoleculeRef, MoleculeStorage, change,
+    lock::{self, StorageGuard},
+};
+
+/// A molecule accessed by a query, `&T` to read it or `&mut T` to write it.
+pub trait QueryParam {
+    type Molecule: Send + Sync + 'static;
+    type Lock<'a>;
+    type Item<'a>;
+
+    /// Whether accessing the molecule marks the entity as modified
+    const MUTABLE: bool;
+
+    /// Locks the molecule, `track` stamps written molecules as changed
+    fn lock(cell: &MoleculeCell<Self::Molecule>, track: bool) -> Self::Lock<'_>;
+
+    fn item<'a>(lock: &'a mut Self::Lock<'_>) -> Self::Item<'a>;
+}
+
+impl<T: Send + Sync + 'static> QueryParam for &T {
+    type Molecule = T;
+    type Lock<'a> = MoleculeRef<'a, T>;
+    type Item<'a> = &'a T;
+
+    const MUTABLE: bool = false;
+
+    fn lock(cell: &MoleculeCell<T>, _track: bool) -> Self::Lock<'_> {
+        cell.read()
+    }
+
+    fn item<'a>(lock: &'a mut Self::Lock<'_>) -> Self::Item<'a> {
+        lock
+    }
+}
+
+impl<T: Send + Sync + 'static> QueryParam for &mut T {
+    type Molecule = T;
+    type Lock<'a> = MoleculeMut<'a, T>;
+    type Item<'a> = &'a mut T;
+
+    const MUTABLE: bool = true;
+
+    fn lock(cell: &MoleculeCell<T>, track: bool) -> Self::Lock<'_> {
+        if track {
+            cell.write()
+        } else {
+            cell.write_untracked()
+        }
+    }
+
+    fn item<'a>(lock: &'a mut Self::Lock<'_>) -> Self::Item<'a> {
+        lock
+    }
+}
+
+/// The molecules a query hands to its closure, a tuple of `QueryParam`s.
+///
+/// Implemented for tuples of 1 to 8 molecules. Entities are visited in the
+/// order of the storage of the first molecule, so putting the rarest
+/// molecule first visits the fewest entities.
+pub trait QueryData {
+    type Storages;
+    type Guards<'s>;
+    type Items<'a>;
+
+    /// Whether any molecule of the query is written
+    const MUTABLE: bool;
+
+    fn storages(compound: &Compound) -> Self::Storages;
+
+    fn guards(storages: &Self::Storages) -> Self::Guards<'_>;
+
+    /// Returns the entities that have the first molecule of the query
+    fn entities<'g>(guards: &'g Self::Guards<'_>) -> impl Iterator<Item = &'g Entity>;
+
+    /// Returns whether an entity has every molecule of the query
+    fn contains(guards: &Self::Guards<'_>, entity: &Entity) -> bool;
+
+    /// Locks the molecules of an entity and calls the closure with them,
+    /// `track` stamps the written molecules as changed.
+    ///
+    /// # Returns
+    /// What the closure returns, or `None` if the entity is missing a molecule
+    fn fetch<R, V>(guards: &Self::Guards<'_>, entity: &Entity, track: bool, visit: V) -> Option<R>
+    where
+        V: for<'a> FnOnce(Self::Items<'a>) -> R;
+}
+
+/// Macro to implement `QueryData` for a tuple of `QueryParam`s.
+///
+/// Every parameter is given with its index in the tuple, the molecules of an
+/// entity are locked in the order of their `TypeId` to prevent deadlocks, the
+/// same as the `iter_*_duo` and `iter_*_trio` methods.
+macro_rules! impl_query_data_for_tuple {
+    ($($P:ident $index:tt), *) => {
+        #[allow(non_snake_case)]
+        impl<$($P: QueryParam),*> QueryData for ($($P,)*) {
+            type Storages = ($(Arc<RwLock<MoleculeStorage<$P::Molecule>>>,)*);
+            type Guards<'s> = ($(StorageGuard<'s, $P::Molecule>,)*);
+            type Items<'a> = ($($P::Item<'a>,)*);
+
+            const MUTABLE: bool = false $(|| $P::MUTABLE)*;
+
+            fn storages(compound: &Compound) -> Self::Storages {
+                ($(compound.get_or_create_storage::<$P::Molecule>(),)*)
+            }
+
+            fn guards(storages: &Self::Storages) -> Self::Guards<'_> {
+                ($(lock::read_storage(&storages.$index),)*)
+            }
+
+            fn entities<'g>(guards: &'g Self::Guards<'_>) -> impl Iterator<Item = &'g Entity> {
+                guards.0.compounds.iter().map(|(entity, _)| entity)
+            }
+
+            fn contains(guards: &Self::Guards<'_>, entity: &Entity) -> bool {
+                true $(&& guards.$index.compounds.get(entity).is_some())*
+            }
+
+            fn fetch<R, Visit>(
+                guards: &Self::Guards<'_>,
+                entity: &Entity,
+                track: bool,
+                visit: Visit,
+            ) -> Option<R>
+            where
+                Visit: for<'a> FnOnce(Self::Items<'a>) -> R,
+            {
+                let cells = ($(guards.$index.compounds.get(entity)?,)*);
+
+                // Lock aquisition ordering to prevent deadlocks
+                let mut order = [$((TypeId::of::<$P::Molecule>(), $index)),*];
+                order.sort();
+
+                let mut locks = ($(None::<$P::Lock<'_>>,)*);
+                for (_, index) in order {
+                    match index {
+                        $($index => locks.$index = Some($P::lock(cells.$index, track)),)*
+                        _ => unreachable!(),
+                    }
+                }
+
+                Some(visit(($($P::item(locks.$index.as_mut()?),)*)))
+            }
+        }
+    };
+}
+
+impl_query_data_for_tuple!(A 0);
+impl_query_data_for_tuple!(A 0, B 1);
+impl_query_data_for_tuple!(A 0, B 1, C 2);
+impl_query_data_for_tuple!(A 0, B 1, C 2, D 3);
+impl_query_data_for_tuple!(A 0, B 1, C 2, D 3, E 4);
+impl_query_data_for_tuple!(A 0, B 1, C 2, D 3, E 4, F 5);
+impl_query_data_for_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_query_data_for_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+
+/// Decides which entities a query visits, without accessing their molecules.
+///
+/// Implemented for `()` to visit every entity, `With<T>`, `Without<T>`,
+/// `Added<T>`, `Changed<T>` and tuples of 1 to 8 filters that must all match.
+pub trait QueryFilter {
+    type Storages;
+    type Guards<'s>;
+
+    fn storages(compound: &Compound) -> Self::Storages;
+
+    fn guards(storages: &Self::Storages) -> Self::Guards<'_>;
+
+    /// Returns whether the filter lets an entity through, `since` is the
+    /// change tick after which molecules count as added or changed
+    fn matches(guards: &Self::Guards<'_>, entity: &Entity, since: u64) -> bool;
+}
+
+/// Only visits entities that have the molecule `T`
+pub struct With<T>(PhantomData<fn() -> T>);
+
+/// Only visits entities that don't have the molecule `T`
+pub struct Without<T>(PhantomData<fn() -> T>);
+
+/// Only visits entities whose molecule `T` was added since the system last
+/// ran, for systems that set up new molecules.
+///
+/// Replacing a molecule with `add_molecule` changes it but doesn't add it.
+pub struct Added<T>(PhantomData<fn() -> T>);
+
+/// Only visits entities whose molecule `T` was added or written since the
+/// system last ran, for systems that react to changes.
+///
+/// Molecules count as written when they are accessed mutably, except by the
+/// `*_unmod` iterators and unmod queries. Tags can't be written, so they
+/// only change when they are added.
+pub struct Changed<T>(PhantomData<fn() -> T>);
+
+impl QueryFilter for () {
+    type Storages = ();
+    type Guards<'s> = ();
+
+    fn storages(_compound: &Compound) -> Self::Storages {}
+
+    fn guards(_storages: &Self::Storages) -> Self::Guards<'_> {}
+
+    fn matches(_guards: &Self::Guards<'_>, _entity: &Entity, _since: u64) -> bool {
+        true
+    }
+}
+
+impl<T: Send + Sync + 'static> QueryFilter for With<T> {
+    type Storages = Arc<RwLock<MoleculeStorage<T>>>;
+    type Guards<'s> = StorageGuard<'s, T>;
+
+    fn storages(compound: &Compound) -> Self::Storages {
+        compound.get_or_create_storage::<T>()
+    }
+
+    fn guards(storages: &Self::Storages) -> Self::Guards<'_> {
+        lock::read_storage(storages)
+    }
+
+    fn matches(guards: &Self::Guards<'_>, entity: &Entity, _since: u64) -> bool {
+        guards.compounds.get(entity).is_some()
+    }
+}
+
+impl<T: Send + Sync + 'static> QueryFilter for Without<T> {
+    type Storages = Arc<RwLock<MoleculeStorage<T>>>;
+    type Guards<'s> = StorageGuard<'s, T>;
+
+    fn storages(compound: &Compound) -> Self::Storages {
+        compound.get_or_create_storage::<T>()
+    }
+
+    fn guards(storages: &Self::Storages) -> Self::Guards<'_> {
+        lock::read_storage(storages)
+    }
+
+    fn matches(guards: &Self::Guards<'_>, entity: &Entity, _since: u64) -> bool {
+        guards.compounds.get(entity).is_none()
+    }
+}
+
+impl<T: Send + Sync + 'static> QueryFilter for Added<T> {
+    type Storages = Arc<RwLock<MoleculeStorage<T>>>;
+    type Guards<'s> = StorageGuard<'s, T>;
+
+    fn storages(compound: &Compound) -> Self::Storages {
+        compound.get_or_create_storage::<T>()
+    }
+
+    fn guards(storages: &Self::Storages) -> Self::Guards<'_> {
+        lock::read_storage(storages)
+    }
+
+    fn matches(guards: &Self::Guards<'_>, entity: &Entity, since: u64) -> bool {
+        guards
+            .compounds
+            .added(entity)
+            .is_some_and(|added| added > since)
+    }
+}
+
+impl<T: Send + Sync + 'static> QueryFilter for Changed<T> {
+    type Storages = Arc<RwLock<MoleculeStorage<T>>>;
+    type Guards<'s> = StorageGuard<'s, T>;
+
+    fn storages(compound: &Compound) -> Self::Storages {
+        compound.get_or_create_storage::<T>()
+    }
+
+    fn guards(storages: &Self::Storages) -> Self::Guards<'_> {
+        lock::read_storage(storages)
+    }
+
+    fn matches(guards: &Self::Guards<'_>, entity: &Entity, since: u64) -> bool {
+        let Some(added) = guards.compounds.added(entity) else {
+            return false;
+        };
+
+        // Every entity shares the cell of a tag, so its tick says nothing
+        let changed = match size_of::<T>() {
+            0 => added,
+            _ => guards
+                .compounds
+                .get(entity)
+                .map_or(added, |cell| cell.changed().max(added)),
+        };
+
+        changed > since
+    }
+}
+
+/// Macro to implement `QueryFilter` for a tuple of filters that must all match
+macro_rules! impl_query_filter_for_tuple {
+    ($($Q:ident $index:tt), *) => {
+        impl<$($Q: QueryFilter),*> QueryFilter for ($($Q,)*) {
+            type Storages = ($($Q::Storages,)*);
+            type Guards<'s> = ($($Q::Guards<'s>,)*);
+
+            fn storages(compound: &Compound) -> Self::Storages {
+                ($($Q::storages(compound),)*)
+            }
+
+            fn guards(storages: &Self::Storages) -> Self::Guards<'_> {
+                ($($Q::guards(&storages.$index),)*)
+            }
+
+            fn matches(guards: &Self::Guards<'_>, entity: &Entity, since: u64) -> bool {
+                true $(&& $Q::matches(&guards.$index, entity, since))*
+            }
+        }
+    };
+}
+
+impl_query_filter_for_tuple!(A 0);
+impl_query_filter_for_tuple!(A 0, B 1);
+impl_query_filter_for_tuple!(A 0, B 1, C 2);
+impl_query_filter_for_tuple!(A 0, B 1, C 2, D 3);
+impl_query_filter_for_tuple!(A 0, B 1, C 2, D 3, E 4);
+impl_query_filter_for_tuple!(A 0, B 1, C 2, D 3, E 4, F 5);
+impl_query_filter_for_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_query_filter_for_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+
+/// How a query treats the modified flag of the entities it visits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChangeDetection {
+    /// Marks visited entities as modified if the query writes a molecule
+    Mark,
+    /// Leaves the modified flag as it is, like the `*_unmod` iterators
+    Ignore,
+    /// Only visits modified entities and clears their flag, like the `*_mod` iterators
+    OnlyModified,
+}
+
+/// A query over the entities of a `Compound`, created with `Compound::query`.
+///
+/// # Type Parameters
+/// - `D`: The molecules to access, a tuple of `&T` and `&mut T`
+/// - `F`: The filter of the entities to visit, `()` to visit all of them
+///
+/// # Modified Flag Behavior
+/// By default a query that writes a molecule marks every entity it visits
+/// as modified, the same as the `iter_mut_*` methods. Use `modified` to only
+/// visit modified entities or `unmod` to leave the flags untouched.
+///
+/// # Example
+/// ```ignore
+/// compound
+///     .query::<(&Velocity, &mut Position), Without<Frozen>>()
+///     .without::<Sleeping>()
+///     .for_each(|entity, (velocity, position)| {
+///         position.x += velocity.x * dt;
+///     });
+/// ```
+pub struct Query<'c, D, F = ()> {
+    compound: &'c Compound,
+    changes: ChangeDetection,
+    since: Option<u64>,
+    _marker: PhantomData<fn() -> (D, F)>,
+}
+
+impl<'c, D: QueryData, F: QueryFilter> Query<'c, D, F> {
+    pub(crate) fn new(compound: &'c Compound) -> Self {
+        Self {
+            compound,
+            changes: ChangeDetection::Mark,
+            since: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Also requires the molecule `T`
+    pub fn with<T: Send + Sync + 'static>(self) -> Query<'c, D, (F, With<T>)> {
+        Query {
+            compound: self.compound,
+            changes: self.changes,
+            since: self.since,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Also excludes entities with the molecule `T`
+    pub fn without<T: Send + Sync + 'static>(self) -> Query<'c, D, (F, Without<T>)> {
+        Query {
+            compound: self.compound,
+            changes: self.changes,
+            since: self.since,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Only visits entities whose molecule `T` was added since the system last ran
+    pub fn added<T: Send + Sync + 'static>(self) -> Query<'c, D, (F, Added<T>)> {
+        Query {
+            compound: self.compound,
+            changes: self.changes,
+            since: self.since,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Only visits entities whose molecule `T` was added or written since the system last ran
+    pub fn changed<T: Send + Sync + 'static>(self) -> Query<'c, D, (F, Changed<T>)> {
+        Query {
+            compound: self.compound,
+            changes: self.changes,
+            since: self.since,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Sets the change tick after which molecules count as added or changed.
+    ///
+    /// Inside a system run by a `Schedule` it defaults to the last run of the
+    /// system. Outside of one it defaults to 0, which counts everything, so
+    /// code that checks for changes on its own passes the tick it got from
+    /// `Compound::advance_change_tick` the last time it checked.
+    pub fn since(mut self, tick: u64) -> Self {
+        self.since = Some(tick);
+        self
+    }
+
+    /// Only visits entities that have been modified, clearing their flag after
+    pub fn modified(mut self) -> Self {
+        self.changes = ChangeDetection::OnlyModified;
+        self
+    }
+
+    /// Writes molecules without marking the entities as modified
+    pub fn unmod(mut self) -> Self {
+        self.changes = ChangeDetection::Ignore;
+        self
+    }
+
+    /// Calls the closure with every entity that matches the query.
+    ///
+    /// # Arguments
+    /// - `f`: A closure that receives the entity ID and a tuple of the molecules of the query
+    ///
+    /// # Panics
+    /// If the same molecule appears twice in the data of a query and one of
+    /// them is written, an entity would have to lock it twice.
+    pub fn for_each<C>(self, mut f: C)
+    where
+        C: for<'a> FnMut(Entity, D::Items<'a>),
+    {
+        let storages = D::storages(self.compound);
+        let guards = D::guards(&storages);
+
+        let filter_storages = F::storages(self.compound);
+        let filter_guards = F::guards(&filter_storages);
+
+        let modified_storage = self.compound.get_or_create_storage::<Modified>();
+        let modified_storage_guard = lock::read_storage(&modified_storage);
+
+        let since = self.change_horizon();
+        let track = self.changes != ChangeDetection::Ignore;
+
+        for entity in D::entities(&guards) {
+            if !F::matches(&filter_guards, entity, since) {
+                continue;
+            }
+
+            let modified_flag = modified_storage_guard.compounds.get(entity);
+
+            match self.changes {
+                ChangeDetection::Mark => {
+                    if D::fetch(&guards, entity, track, |items| f(*entity, items)).is_some()
+                        && D::MUTABLE
+                    {
+                        // Set the modified flag for the entity
+                        if let Some(modified_flag) = modified_flag {
+                            modified_flag.write().set_modified();
+                        }
+                    }
+                }
+                ChangeDetection::Ignore => {
+                    _ = D::fetch(&guards, entity, track, |items| f(*entity, items));
+                }
+                ChangeDetection::OnlyModified => {
+                    // The flag is cleared before the closure runs so it isn't locked meanwhile
+                    if let Some(modified_flag) = modified_flag
+                        && D::contains(&guards, entity)
+                        && modified_flag.write().take_modified()
+                    {
+                        _ = D::fetch(&guards, entity, track, |items| f(*entity, items));
+                    }
+                }
+            }
+        }
+    }
+
+    // The change tick after which molecules count as added or changed
+    fn change_horizon(&self) -> u64 {
+        self.since.or_else(change::last_run).unwrap_or(0)
+    }
+
+    /// Counts the entities that match the query, without locking their molecules.
+    ///
+    /// The modified flags are neither checked nor changed.
+    pub fn count(self) -> usize {
+        let storages = D::storages(self.compound);
+        let guards = D::guards(&storages);
+
+        let filter_storages = F::storages(self.compound);
+        let filter_guards = F::guards(&filter_storages);
+
+        let since = self.change_horizon();
+
+        D::entities(&guards)
+            .filter(|entity| F::matches(&filter_guards, entity, since))
+            .filter(|entity| D::contains(&guards, entity))
+            .count()
+    }
+}
+
+/// Macro to implement the `iter_*` methods for a number of molecules on top of
+/// `Query`, the same variants the `iter_*_duo` and `iter_*_trio` methods have.
+///
+/// The names of the methods are given in the order `iter`, `iter_mod`,
+/// `iter_without`, `iter_without_mod`, `iter_mut`, `iter_mut_unmod`,
+/// `iter_mut_mod`, `iter_mut_without`, `iter_mut_without_unmod` and
+/// `iter_mut_without_mod`, followed by the molecule type parameters.
+macro_rules! impl_iter_for_molecules {
+    (
+        $count:literal,
+        $iter:ident,
+        $iter_mod:ident,
+        $iter_without:ident,
+        $iter_without_mod:ident,
+        $iter_mut:ident,
+        $iter_mut_unmod:ident,
+        $iter_mut_mod:ident,
+        $iter_mut_without:ident,
+        $iter_mut_without_unmod:ident,
+        $iter_mut_without_mod:ident,
+        $($T:ident $t:ident), *
+    ) => {
+        impl Compound {
+            #[doc = concat!("Iterates over all entities that have all ", $count, " specified component types.")]
+            ///
+            /// Provides read-only access to the components, the same as `iter_trio`.
+            ///
+            /// # Example
+            /// ```ignore
+            #[doc = concat!("compound.", stringify!($iter), "(|entity, ", stringify!($($t),*), "| {")]
+            ///     // Read the components
+            /// });
+            /// ```
+            pub fn $iter<$($T,)* F>(&self, mut f: F)
+            where
+                $($T: Send + Sync + 'static,)*
+                F: FnMut(Entity, $(&$T),*),
+            {
+                self.query::<($(&$T,)*), ()>()
+                    .for_each(|entity, ($($t,)*)| f(entity, $($t),*));
+            }
+
+            #[doc = concat!("Iterates over modified entities that have all ", $count, " specified component types.")]
+            ///
+            /// Only processes modified entities and clears their flag, the same
+            /// as the other `*_mod` methods.
+            pub fn $iter_mod<$($T,)* F>(&self, mut f: F)
+            where
+                $($T: Send + Sync + 'static,)*
+                F: FnMut(Entity, $(&$T),*),
+            {
+                self.query::<($(&$T,)*), ()>()
+                    .modified()
+                    .for_each(|entity, ($($t,)*)| f(entity, $($t),*));
+            }
+
+            #[doc = concat!("Iterates over entities with all ", $count, " component types but without component W.")]
+            ///
+            /// Provides read-only access to the required components.
+            pub fn $iter_without<W, $($T,)* F>(&self, mut f: F)
+            where
+                W: Send + Sync + 'static,
+                $($T: Send + Sync + 'static,)*
+                F: FnMut(Entity, $(&$T),*),
+            {
+                self.query::<($(&$T,)*), Without<W>>()
+                    .for_each(|entity, ($($t,)*)| f(entity, $($t),*));
+            }
+
+            #[doc = concat!("Iterates over modified entities with all ", $count, " component types but without component W.")]
+            ///
+            /// Only processes modified entities and clears their flag.
+            pub fn $iter_without_mod<W, $($T,)* F>(&self, mut f: F)
+            where
+                W: Send + Sync + 'static,
+                $($T: Send + Sync + 'static,)*
+                F: FnMut(Entity, $(&$T),*),
+            {
+                self.query::<($(&$T,)*), Without<W>>()
+                    .modified()
+                    .for_each(|entity, ($($t,)*)| f(entity, $($t),*));
+            }
+
+            #[doc = concat!("Iterates over entities with ", $count, " components, providing mutable access to all of them.")]
+            ///
+            /// Every entity visited is marked as modified, the same as `iter_mut_trio`.
+            ///
+            /// # Example
+            /// ```ignore
+            #[doc = concat!("compound.", stringify!($iter_mut), "(|entity, ", stringify!($($t),*), "| {")]
+            ///     // Write the components
+            /// });
+            /// ```
+            pub fn $iter_mut<$($T,)* F>(&self, mut f: F)
+            where
+                $($T: Send + Sync + 'static,)*
+                F: FnMut(Entity, $(&mut $T),*),
+            {
+                self.query::<($(&mut $T,)*), ()>()
+                    .for_each(|entity, ($($t,)*)| f(entity, $($t),*));
+            }
+
+            #[doc = concat!("Iterates over entities with ", $count, " components with mutable access, without marking them as modified.")]
+            pub fn $iter_mut_unmod<$($T,)* F>(&self, mut f: F)
+            where
+                $($T: Send + Sync + 'static,)*
+                F: FnMut(Entity, $(&mut $T),*),
+            {
+                self.query::<($(&mut $T,)*), ()>()
+                    .unmod()
+                    .for_each(|entity, ($($t,)*)| f(entity, $($t),*));
+            }
+
+            #[doc = concat!("Iterates over modified entities with ", $count, " components with mutable access.")]
+            ///
+            /// Only processes modified entities and clears their flag.
+            pub fn $iter_mut_mod<$($T,)* F>(&self, mut f: F)
+            where
+                $($T: Send + Sync + 'static,)*
+                F: FnMut(Entity, $(&mut $T),*),
+            {
+                self.query::<($(&mut $T,)*), ()>()
+                    .modified()
+                    .for_each(|entity, ($($t,)*)| f(entity, $($t),*));
+            }
+
+            #[doc = concat!("Iterates over entities with ", $count, " components but without component W, with mutable access.")]
+            ///
+            /// Every entity visited is marked as modified.
+            pub fn $iter_mut_without<W, $($T,)* F>(&self, mut f: F)
+            where
+                W: Send + Sync + 'static,
+                $($T: Send + Sync + 'static,)*
+                F: FnMut(Entity, $(&mut $T),*),
+            {
+                self.query::<($(&mut $T,)*), Without<W>>()
+                    .for_each(|entity, ($($t,)*)| f(entity, $($t),*));
+            }
+
+            #[doc = concat!("Iterates over entities with ", $count, " components but without component W, with mutable access and without marking them as modified.")]
+            pub fn $iter_mut_without_unmod<W, $($T,)* F>(&self, mut f: F)
+            where
+                W: Send + Sync + 'static,
+                $($T: Send + Sync + 'static,)*
+                F: FnMut(Entity, $(&mut $T),*),
+            {
+                self.query::<($(&mut $T,)*), Without<W>>()
+                    .unmod()
+                    .for_each(|entity, ($($t,)*)| f(entity, $($t),*));
+            }
+
+            #[doc = concat!("Iterates over modified entities with ", $count, " components but without component W, with mutable access.")]
+            ///
+            /// Only processes modified entities and clears their flag.
+            pub fn $iter_mut_without_mod<W, $($T,)* F>(&self, mut f: F)
+            where
+                W: Send + Sync + 'static,
+                $($T: Send + Sync + 'static,)*
+                F: FnMut(Entity, $(&mut $T),*),
+            {
+                self.query::<($(&mut $T,)*), Without<W>>()
+                    .modified()
+                    .for_each(|entity, ($($t,)*)| f(entity, $($t),*));
+            }
+        }
+    };
+}
+
+impl_iter_for_molecules!(
+    "four",
+    iter_quad,
+    iter_quad_mod,
+    iter_without_quad,
+    iter_without_quad_mod,
+    iter_mut_quad,
+    iter_mut_quad_unmod,
+    iter_mut_quad_mod,
+    iter_mut_without_quad,
+    iter_mut_without_quad_unmod,
+    iter_mut_without_quad_mod,
+    T1 t1, T2 t2, T3 t3, T4 t4
+);
+
+impl_iter_for_molecules!(
+    "five",
+    iter_quint,
+    iter_quint_mod,
+    iter_without_quint,
+    iter_without_quint_mod,
+    iter_mut_quint,
+    iter_mut_quint_unmod,
+    iter_mut_quint_mod,
+    iter_mut_without_quint,
+    iter_mut_without_quint_unmod,
+    iter_mut_without_quint_mod,
+    T1 t1, T2 t2, T3 t3, T4 t4, T5 t5
+);
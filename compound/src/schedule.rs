@@ -0,0 +1,386 @@
+//! Systems that run over a `Compound` in parallel where their access allows.
+//!
+//! Every system declares the molecules and resources it reads and writes.
+//! Systems that don't write anything another one touches run at the same time
+//! as jobs on the `gluon` pool, everything else runs one after another, in an order that
+//! honors the `before` and `after` constraints of every system.
+
+use std::{any::TypeId, collections::HashMap};
+
+use anyhow::{Result, anyhow};
+use log::{debug, warn};
+
+use crate::{Compound, change};
+
+type SystemFn<C> = Box<dyn FnMut(&Compound, &C) + Send>;
+
+/// A function run by a `Schedule` with the access it needs.
+///
+/// # Type Parameters
+/// - `C`: Context passed to every system of the schedule, such as the time step
+///
+/// # Example
+/// ```ignore
+/// let gravity = System::new("gravity", |compound: &Compound, dt: &f32| {
+///     compound.iter_mut_mol(|_entity, velocity: &mut Velocity| velocity.y -= 9.81 * dt);
+/// })
+/// .writes::<Velocity>()
+/// .before("movement");
+/// ```
+pub struct System<C = ()> {
+    name: String,
+    reads: Vec<TypeId>,
+    writes: Vec<TypeId>,
+    exclusive: bool,
+    before: Vec<String>,
+    after: Vec<String>,
+    run: SystemFn<C>,
+    /// The change tick of the last run, what the `Added` and `Changed` filters compare against
+    last_run: u64,
+}
+
+impl<C> System<C> {
+    /// Creates a system that accesses nothing until declared.
+    ///
+    /// # Arguments
+    /// - `name`: Unique name other systems order themselves by
+    /// - `run`: Function called with the compound and context every run
+    pub fn new<F>(name: impl Into<String>, run: F) -> Self
+    where
+        F: FnMut(&Compound, &C) + Send + 'static,
+    {
+        Self {
+            name: name.into(),
+            reads: Vec::new(),
+            writes: Vec::new(),
+            exclusive: false,
+            before: Vec::new(),
+            after: Vec::new(),
+            run: Box::new(run),
+            last_run: 0,
+        }
+    }
+
+    /// Declares that the system reads a molecule or resource
+    pub fn reads<T: 'static>(mut self) -> Self {
+        self.reads.push(TypeId::of::<T>());
+        self
+    }
+
+    /// Declares that the system writes, adds or removes a molecule or resource
+    pub fn writes<T: 'static>(mut self) -> Self {
+        self.writes.push(TypeId::of::<T>());
+        self
+    }
+
+    /// Never runs the system at the same time as another, for systems that
+    /// can't declare everything they access such as ones that call user code
+    pub fn exclusive(mut self) -> Self {
+        self.exclusive = true;
+        self
+    }
+
+    /// Runs the system before the system with the given name
+    pub fn before(mut self, name: impl Into<String>) -> Self {
+        self.before.push(name.into());
+        self
+    }
+
+    /// Runs the system after the system with the given name
+    pub fn after(mut self, name: impl Into<String>) -> Self {
+        self.after.push(name.into());
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Runs the system with its own change tick
+    fn run(&mut self, compound: &Compound, context: &C) {
+        let run = &mut self.run;
+        change::run_system(&mut self.last_run, || run(compound, context));
+    }
+
+    /// Whether the two systems can't run at the same time
+    fn conflicts_with(&self, other: &System<C>) -> bool {
+        if self.exclusive || other.exclusive {
+            return true;
+        }
+
+        let writes_into = |writer: &System<C>, accessor: &System<C>| {
+            writer.writes.iter().any(|molecule| {
+                accessor.reads.contains(molecule) || accessor.writes.contains(molecule)
+            })
+        };
+
+        writes_into(self, other) || writes_into(other, self)
+    }
+}
+
+/// An ordered set of systems that run over a compound.
+///
+/// The systems are split into batches, every system of a batch runs at the
+/// same time and a batch only starts once the one before it has finished.
+/// Systems are placed in the earliest batch after everything they run after
+/// that has no system they conflict with, and conflicting systems without an
+/// explicit order run in the order they were added. The batches are only
+/// worked out again when systems are added or removed.
+///
+/// # Example
+/// ```ignore
+/// let mut schedule = Schedule::new();
+/// schedule.add_system(
+///     System::new("movement", |compound, dt: &f32| {
+///         compound.iter_mut_duo(|_entity, position: &mut Position, velocity: &mut Velocity| {
+///             position.x += velocity.x * dt;
+///         });
+///     })
+///     .writes::<Position>()
+///     .reads::<Velocity>(),
+/// );
+/// schedule.add_system(System::new("ai", ai_system).writes::<Brain>().before("movement"));
+///
+/// schedule.run(&compound, &dt)?;
+/// ```
+pub struct Schedule<C = ()> {
+    systems: Vec<System<C>>,
+    batches: Option<Vec<Vec<usize>>>,
+}
+
+impl<C> Default for Schedule<C> {
+    fn default() -> Self {
+        Self {
+            systems: Vec::new(),
+            batches: None,
+        }
+    }
+}
+
+impl<C: Sync> Schedule<C> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a system, replacing any system with the same name
+    pub fn add_system(&mut self, system: System<C>) -> &mut Self {
+        match self
+            .systems
+            .iter()
+            .position(|other| other.name == system.name)
+        {
+            Some(index) => {
+                warn!("Replacing system {}", system.name);
+                self.systems[index] = system;
+            }
+            None => self.systems.push(system),
+        }
+
+        self.batches = None;
+        self
+    }
+
+    /// Removes the system with the given name
+    ///
+    /// # Returns
+    /// `true` if the system existed
+    pub fn remove_system(&mut self, name: &str) -> bool {
+        let count = self.systems.len();
+        self.systems.retain(|system| system.name != name);
+        self.batches = None;
+
+        self.systems.len() != count
+    }
+
+    pub fn has_system(&self, name: &str) -> bool {
+        self.systems.iter().any(|system| system.name == name)
+    }
+
+    /// Returns the names of the systems of every batch in the order they run
+    ///
+    /// # Returns
+    /// The batches, or an error if the ordering constraints form a cycle
+    pub fn batches(&mut self) -> Result<Vec<Vec<&str>>> {
+        self.plan()?;
+
+        Ok(self
+            .batches
+            .iter()
+            .flatten()
+            .map(|batch| {
+                batch
+                    .iter()
+                    .map(|index| self.systems[*index].name.as_str())
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// Runs every system once.
+    ///
+    /// # Arguments
+    /// - `compound`: The world the systems run over
+    /// - `context`: Passed to every system
+    ///
+    /// # Returns
+    /// An error without running anything if the ordering constraints form a cycle
+    pub fn run(&mut self, compound: &Compound, context: &C) -> Result<()> {
+        self.plan()?;
+        let Some(batches) = self.batches.as_ref() else {
+            return Ok(());
+        };
+
+        for batch in batches.iter() {
+            if let [index] = batch.as_slice() {
+                self.systems[*index].run(compound, context);
+                continue;
+            }
+
+            // Hand out the systems of the batch to the job pool
+            let mut systems = self
+                .systems
+                .iter_mut()
+                .enumerate()
+                .filter(|(index, _)| batch.contains(index))
+                .map(|(_, system)| system)
+                .collect::<Vec<_>>();
+
+            gluon::scope(|scope| {
+                let mut systems = systems.iter_mut();
+                let first = systems.next();
+
+                for system in systems {
+                    scope.spawn(move || system.run(compound, context));
+                }
+
+                // The calling thread takes a system instead of waiting idle
+                if let Some(system) = first {
+                    system.run(compound, context);
+                }
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Works out the batches if systems changed since they last were
+    fn plan(&mut self) -> Result<()> {
+        if self.batches.is_some() {
+            return Ok(());
+        }
+
+        let indices = self
+            .systems
+            .iter()
+            .enumerate()
+            .map(|(index, system)| (system.name.as_str(), index))
+            .collect::<HashMap<_, _>>();
+
+        // The systems each system has to run after
+        let mut dependencies = vec![Vec::new(); self.systems.len()];
+        for (index, system) in self.systems.iter().enumerate() {
+            for name in system.after.iter() {
+                match indices.get(name.as_str()) {
+                    Some(other) => dependencies[index].push(*other),
+                    None => warn!("{} runs after unknown system {}", system.name, name),
+                }
+            }
+
+            for name in system.before.iter() {
+                match indices.get(name.as_str()) {
+                    Some(other) => dependencies[*other].push(index),
+                    None => warn!("{} runs before unknown system {}", system.name, name),
+                }
+            }
+        }
+
+        // Conflicting systems run in the order they were added, unless they
+        // are explicitly ordered the other way around
+        for later in 0..self.systems.len() {
+            for earlier in 0..later {
+                if self.systems[later].conflicts_with(&self.systems[earlier])
+                    && !runs_after(&dependencies, earlier, later)
+                {
+                    dependencies[later].push(earlier);
+                }
+            }
+        }
+
+        let mut batch_of: Vec<Option<usize>> = vec![None; self.systems.len()];
+        let mut batches: Vec<Vec<usize>> = Vec::new();
+        let mut placed = 0;
+
+        while placed < self.systems.len() {
+            let mut progressed = false;
+
+            for index in 0..self.systems.len() {
+                if batch_of[index].is_some() {
+                    continue;
+                }
+
+                // Wait until everything it runs after has a batch
+                let Some(earliest) = dependencies[index]
+                    .iter()
+                    .map(|dependency| batch_of[*dependency].map(|batch| batch + 1))
+                    .try_fold(0, |earliest, batch| batch.map(|batch| batch.max(earliest)))
+                else {
+                    continue;
+                };
+
+                let batch = (earliest..batches.len())
+                    .find(|batch| {
+                        batches[*batch]
+                            .iter()
+                            .all(|other| !self.systems[index].conflicts_with(&self.systems[*other]))
+                    })
+                    .unwrap_or(batches.len());
+
+                if batch == batches.len() {
+                    batches.push(Vec::new());
+                }
+                batches[batch].push(index);
+                batch_of[index] = Some(batch);
+
+                placed += 1;
+                progressed = true;
+            }
+
+            if !progressed {
+                let cycle = (0..self.systems.len())
+                    .filter(|index| batch_of[*index].is_none())
+                    .map(|index| self.systems[index].name.as_str())
+                    .collect::<Vec<_>>();
+
+                return Err(anyhow!(
+                    "Systems have cyclic ordering: {}",
+                    cycle.join(", ")
+                ));
+            }
+        }
+
+        debug!("Scheduled {} systems in {} batches", placed, batches.len());
+        self.batches = Some(batches);
+        Ok(())
+    }
+}
+
+/// Whether `system` has to run after `other` through its dependencies
+fn runs_after(dependencies: &[Vec<usize>], system: usize, other: usize) -> bool {
+    let mut visited = vec![false; dependencies.len()];
+    let mut stack = vec![system];
+
+    while let Some(current) = stack.pop() {
+        for dependency in dependencies[current].iter() {
+            if *dependency == other {
+                return true;
+            }
+
+            if !visited[*dependency] {
+                visited[*dependency] = true;
+                stack.push(*dependency);
+            }
+        }
+    }
+
+    false
+}
@@ -0,0 +1,430 @@
+//! Saving a `Compound` to RON or bincode and loading it back.
+//!
+//! Only molecules and resources whose types were registered with a
+//! `TypeRegistry` are saved, under the name they were registered with, so a
+//! snapshot can be loaded by a build where the Rust type names have changed.
+
+use std::{any::TypeId, collections::BTreeMap, sync::atomic::Ordering};
+
+use anyhow::{Result, anyhow};
+use log::{debug, warn};
+use ron::{ser::PrettyConfig, value::RawValue};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{Compound, Entity, Modified};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Types a `Compound` saves to and loads from snapshots, by name.
+///
+/// # Example
+/// ```ignore
+/// #[derive(Serialize, Deserialize)]
+/// struct Position { x: f32, y: f32 }
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Score(u32);
+///
+/// let mut registry = TypeRegistry::new();
+/// registry
+///     .register::<Position>("position")
+///     .register_resource::<Score>("score");
+///
+/// let save = compound.to_ron(&registry)?;
+/// let loaded = Compound::from_ron(&registry, &save)?;
+/// ```
+#[derive(Default)]
+pub struct TypeRegistry {
+    molecules: Vec<Registration>,
+    resources: Vec<Registration>,
+}
+
+impl TypeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a molecule type to be saved in snapshots.
+    ///
+    /// # Arguments
+    /// - `name`: The name the molecule is saved under, registering another type
+    ///   with the same name replaces it
+    ///
+    /// # Returns
+    /// The registry, so registrations can be chained
+    pub fn register<T>(&mut self, name: impl Into<String>) -> &mut Self
+    where
+        T: Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        Self::insert(
+            &mut self.molecules,
+            Registration {
+                name: name.into(),
+                type_id: TypeId::of::<T>(),
+                ron: Codec {
+                    save: save_molecule::<T, Ron>,
+                    load: load_molecule::<T, Ron>,
+                },
+                bincode: Codec {
+                    save: save_molecule::<T, Bincode>,
+                    load: load_molecule::<T, Bincode>,
+                },
+            },
+        );
+
+        self
+    }
+
+    /// Registers a resource type to be saved in snapshots.
+    ///
+    /// # Arguments
+    /// - `name`: The name the resource is saved under, registering another type
+    ///   with the same name replaces it
+    ///
+    /// # Returns
+    /// The registry, so registrations can be chained
+    pub fn register_resource<T>(&mut self, name: impl Into<String>) -> &mut Self
+    where
+        T: Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        Self::insert(
+            &mut self.resources,
+            Registration {
+                name: name.into(),
+                type_id: TypeId::of::<T>(),
+                ron: Codec {
+                    save: save_resource::<T, Ron>,
+                    load: load_resource::<T, Ron>,
+                },
+                bincode: Codec {
+                    save: save_resource::<T, Bincode>,
+                    load: load_resource::<T, Bincode>,
+                },
+            },
+        );
+
+        self
+    }
+
+    /// Checks if a molecule or resource type has been registered
+    pub fn is_registered<T: 'static>(&self) -> bool {
+        let type_id = TypeId::of::<T>();
+        self.molecules
+            .iter()
+            .chain(self.resources.iter())
+            .any(|registration| registration.type_id == type_id)
+    }
+
+    fn insert(registrations: &mut Vec<Registration>, registration: Registration) {
+        registrations.retain(|existing| {
+            let replaced =
+                existing.name == registration.name || existing.type_id == registration.type_id;
+            if replaced {
+                warn!("Replacing snapshot registration {}", existing.name);
+            }
+
+            !replaced
+        });
+
+        registrations.push(registration);
+    }
+}
+
+impl Compound {
+    /// Saves the registered molecules and resources of the compound to RON.
+    ///
+    /// # Arguments
+    /// - `registry`: The types to save
+    ///
+    /// # Returns
+    /// The snapshot as pretty printed RON, or an error if a value failed to serialize
+    pub fn to_ron(&self, registry: &TypeRegistry) -> Result<String> {
+        let snapshot = self.save::<Ron>(registry)?;
+
+        Ok(ron::ser::to_string_pretty(
+            &snapshot,
+            PrettyConfig::default(),
+        )?)
+    }
+
+    /// Loads a compound from a snapshot saved with `to_ron`.
+    ///
+    /// Entities keep the IDs they were saved with, so entities stored inside of
+    /// molecules and resources stay valid. Molecules and resources with names
+    /// that aren't registered are skipped.
+    ///
+    /// # Arguments
+    /// - `registry`: The types to load
+    /// - `source`: The RON snapshot
+    ///
+    /// # Returns
+    /// A new compound with the saved entities, or an error if the snapshot
+    /// couldn't be parsed
+    pub fn from_ron(registry: &TypeRegistry, source: &str) -> Result<Compound> {
+        let snapshot: Snapshot<Box<RawValue>> = ron::from_str(source)?;
+
+        Self::load::<Ron>(registry, &snapshot)
+    }
+
+    /// Saves the registered molecules and resources of the compound to bincode.
+    ///
+    /// Bincode is smaller and faster to load than RON but can only be loaded
+    /// with the same registered types it was saved with.
+    ///
+    /// # Arguments
+    /// - `registry`: The types to save
+    ///
+    /// # Returns
+    /// The snapshot as bytes, or an error if a value failed to serialize
+    pub fn to_bincode(&self, registry: &TypeRegistry) -> Result<Vec<u8>> {
+        let snapshot = self.save::<Bincode>(registry)?;
+
+        Ok(bincode::serialize(&snapshot)?)
+    }
+
+    /// Loads a compound from a snapshot saved with `to_bincode`.
+    ///
+    /// See [`Compound::from_ron`].
+    pub fn from_bincode(registry: &TypeRegistry, bytes: &[u8]) -> Result<Compound> {
+        let snapshot: Snapshot<Vec<u8>> = bincode::deserialize(bytes)?;
+
+        Self::load::<Bincode>(registry, &snapshot)
+    }
+
+    /// Hashes the registered molecules and resources of the compound.
+    ///
+    /// Two compounds with the same registered state hash the same on every
+    /// platform and build, so hashes can be compared between runs or peers to
+    /// find where a simulation stopped being deterministic. Molecules that
+    /// serialize unordered collections such as `HashMap` can hash differently
+    /// while holding the same values.
+    ///
+    /// # Arguments
+    /// - `registry`: The types to hash
+    ///
+    /// # Returns
+    /// The hash, or an error if a value failed to serialize
+    pub fn state_hash(&self, registry: &TypeRegistry) -> Result<u64> {
+        let snapshot = self.save::<Bincode>(registry)?;
+        let bytes = bincode::serialize(&snapshot)?;
+
+        // FNV-1a, which unlike the hasher of std is stable between Rust versions
+        Ok(bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+        }))
+    }
+
+    fn save<F: Format>(&self, registry: &TypeRegistry) -> Result<Snapshot<F::Data>> {
+        let mut snapshot = Snapshot {
+            next_entity: self.num_entities.load(Ordering::Relaxed),
+            entities: BTreeMap::new(),
+            resources: BTreeMap::new(),
+        };
+
+        for registration in registry.molecules.iter().chain(registry.resources.iter()) {
+            (F::codec(registration).save)(self, &mut snapshot, &registration.name)?;
+        }
+
+        Ok(snapshot)
+    }
+
+    fn load<F: Format>(registry: &TypeRegistry, snapshot: &Snapshot<F::Data>) -> Result<Compound> {
+        let compound = Compound::new();
+        compound
+            .num_entities
+            .store(snapshot.next_entity, Ordering::Relaxed);
+
+        for (entity, molecules) in snapshot.entities.iter() {
+            if *entity >= snapshot.next_entity {
+                return Err(anyhow!(
+                    "Snapshot has entity {} that was never created",
+                    entity
+                ));
+            }
+
+            for (name, data) in molecules.iter() {
+                let Some(registration) = find(&registry.molecules, name) else {
+                    warn!(
+                        "Skipping unregistered molecule {} of entity {}",
+                        name, entity
+                    );
+                    continue;
+                };
+
+                (F::codec(registration).load)(&compound, Some(*entity), data).map_err(|err| {
+                    anyhow!("Failed to load {} of entity {}: {}", name, entity, err)
+                })?;
+            }
+
+            compound.add_molecule(*entity, Modified::default());
+        }
+
+        for (name, data) in snapshot.resources.iter() {
+            let Some(registration) = find(&registry.resources, name) else {
+                warn!("Skipping unregistered resource {}", name);
+                continue;
+            };
+
+            (F::codec(registration).load)(&compound, None, data)
+                .map_err(|err| anyhow!("Failed to load resource {}: {}", name, err))?;
+        }
+
+        debug!(
+            "Loaded snapshot with {} entities and {} resources",
+            snapshot.entities.len(),
+            snapshot.resources.len()
+        );
+
+        Ok(compound)
+    }
+}
+
+// What is written to disk, with every value already serialized by its codec
+#[derive(Serialize, Deserialize)]
+struct Snapshot<D> {
+    next_entity: u64,
+    entities: BTreeMap<Entity, BTreeMap<String, D>>,
+    resources: BTreeMap<String, D>,
+}
+
+struct Codec<D> {
+    save: fn(&Compound, &mut Snapshot<D>, &str) -> Result<()>,
+    // Resources are loaded without an entity
+    load: fn(&Compound, Option<Entity>, &D) -> Result<()>,
+}
+
+struct Registration {
+    name: String,
+    type_id: TypeId,
+    ron: Codec<Box<RawValue>>,
+    bincode: Codec<Vec<u8>>,
+}
+
+fn find<'a>(registrations: &'a [Registration], name: &str) -> Option<&'a Registration> {
+    registrations
+        .iter()
+        .find(|registration| registration.name == name)
+}
+
+// How every value of a snapshot is serialized
+trait Format {
+    type Data: Serialize + DeserializeOwned + Send + Sync;
+
+    fn encode<T: Serialize>(value: &T) -> Result<Self::Data>;
+    fn decode<T: DeserializeOwned>(data: &Self::Data) -> Result<T>;
+    fn codec(registration: &Registration) -> &Codec<Self::Data>;
+}
+
+struct Ron;
+
+impl Format for Ron {
+    // Kept as RON text so unregistered values can be skipped when loading
+    type Data = Box<RawValue>;
+
+    fn encode<T: Serialize>(value: &T) -> Result<Self::Data> {
+        Ok(RawValue::from_rust(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(data: &Self::Data) -> Result<T> {
+        Ok(data.into_rust()?)
+    }
+
+    fn codec(registration: &Registration) -> &Codec<Self::Data> {
+        &registration.ron
+    }
+}
+
+struct Bincode;
+
+impl Format for Bincode {
+    // Every value is encoded on its own so unregistered values can be skipped when loading
+    type Data = Vec<u8>;
+
+    fn encode<T: Serialize>(value: &T) -> Result<Self::Data> {
+        Ok(bincode::serialize(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(data: &Self::Data) -> Result<T> {
+        Ok(bincode::deserialize(data)?)
+    }
+
+    fn codec(registration: &Registration) -> &Codec<Self::Data> {
+        &registration.bincode
+    }
+}
+
+fn save_molecule<T, F>(
+    compound: &Compound,
+    snapshot: &mut Snapshot<F::Data>,
+    name: &str,
+) -> Result<()>
+where
+    T: Serialize + Send + Sync + 'static,
+    F: Format,
+{
+    let mut result = Ok(());
+    compound.iter_mol(|entity, molecule: &T| {
+        if result.is_err() {
+            return;
+        }
+
+        match F::encode(molecule) {
+            Ok(data) => {
+                snapshot
+                    .entities
+                    .entry(entity)
+                    .or_default()
+                    .insert(name.to_string(), data);
+            }
+            Err(err) => {
+                result = Err(anyhow!(
+                    "Failed to save {} of entity {}: {}",
+                    name,
+                    entity,
+                    err
+                ))
+            }
+        }
+    });
+
+    result
+}
+
+fn load_molecule<T, F>(compound: &Compound, entity: Option<Entity>, data: &F::Data) -> Result<()>
+where
+    T: DeserializeOwned + Send + Sync + 'static,
+    F: Format,
+{
+    let entity = entity.ok_or_else(|| anyhow!("Molecules have to be loaded onto an entity"))?;
+    compound.add_molecule(entity, F::decode::<T>(data)?);
+
+    Ok(())
+}
+
+fn save_resource<T, F>(
+    compound: &Compound,
+    snapshot: &mut Snapshot<F::Data>,
+    name: &str,
+) -> Result<()>
+where
+    T: Serialize + Send + Sync + 'static,
+    F: Format,
+{
+    if let Some(data) = compound.resource::<T, _>(|resource| F::encode(resource)) {
+        let data = data.map_err(|err| anyhow!("Failed to save resource {}: {}", name, err))?;
+        snapshot.resources.insert(name.to_string(), data);
+    }
+
+    Ok(())
+}
+
+fn load_resource<T, F>(compound: &Compound, _entity: Option<Entity>, data: &F::Data) -> Result<()>
+where
+    T: DeserializeOwned + Send + Sync + 'static,
+    F: Format,
+{
+    compound.insert_resource(F::decode::<T>(data)?);
+
+    Ok(())
+}
@@ -0,0 +1,327 @@
+use std::{mem::ManuallyDrop, slice};
+
+use crate::{Entity, change};
+
+/// Number of entities covered by one page of the sparse index
+const PAGE_SIZE: usize = 1024;
+
+/// Marks an entity of a page that has no value in the set
+const EMPTY: u32 = u32::MAX;
+
+type Page = Box<[u32; PAGE_SIZE]>;
+
+/// Maps entities to values stored in dense parallel vectors.
+///
+/// The values of a set are packed together in the order they were added,
+/// so iterating is a linear scan over contiguous memory, and the sparse
+/// index finds the value of an entity with two array lookups instead of a
+/// hash. Removing swaps the last value into the hole, which keeps the
+/// vectors dense at the cost of their order.
+///
+/// The sparse index is split into pages that are only allocated once an
+/// entity in their range is added, so sets of rare molecules stay small
+/// even when entity IDs grow large.
+///
+/// A set of tags, values that carry no data, is only a set of entities. It
+/// keeps the first value added to stand in for the value of every entity and
+/// recreates values with its `conjure` function when they are taken out.
+///
+/// The change tick every entity was added at is kept next to it, for the
+/// `Added` filter.
+pub(crate) struct SparseSet<V> {
+    sparse: Vec<Option<Page>>,
+    entities: Vec<Entity>,
+    added: Vec<u64>,
+    values: Vec<V>,
+    tags: Option<Tags<V>>,
+}
+
+struct Tags<V> {
+    shared: Option<ManuallyDrop<V>>,
+    conjure: fn() -> V,
+}
+
+impl<V> SparseSet<V> {
+    pub(crate) fn new() -> Self {
+        Self {
+            sparse: Vec::new(),
+            entities: Vec::new(),
+            added: Vec::new(),
+            values: Vec::new(),
+            tags: None,
+        }
+    }
+
+    /// Creates a set of tags.
+    ///
+    /// Values added to the set are forgotten, every entity shares the first
+    /// one, so this is only sound for values that don't hold data and don't
+    /// care which instance they are, such as the cells of zero sized molecules.
+    ///
+    /// # Arguments
+    /// - `conjure`: Recreates a value that was added, only called once one was
+    pub(crate) fn tags(conjure: fn() -> V) -> Self {
+        Self {
+            sparse: Vec::new(),
+            entities: Vec::new(),
+            added: Vec::new(),
+            values: Vec::new(),
+            tags: Some(Tags {
+                shared: None,
+                conjure,
+            }),
+        }
+    }
+
+    /// Returns the page of an entity and its position in the page
+    fn locate(entity: Entity) -> (usize, usize) {
+        let entity = entity as usize;
+        (entity / PAGE_SIZE, entity % PAGE_SIZE)
+    }
+
+    fn dense_index(&self, entity: Entity) -> Option<usize> {
+        let (page, offset) = Self::locate(entity);
+
+        match self.sparse.get(page)? {
+            Some(page) if page[offset] != EMPTY => Some(page[offset] as usize),
+            _ => None,
+        }
+    }
+
+    fn set_dense_index(&mut self, entity: Entity, index: u32) {
+        let (page, offset) = Self::locate(entity);
+
+        if self.sparse.len() <= page {
+            self.sparse.resize_with(page + 1, || None);
+        }
+
+        self.sparse[page].get_or_insert_with(|| Box::new([EMPTY; PAGE_SIZE]))[offset] = index;
+    }
+
+    pub(crate) fn get(&self, entity: &Entity) -> Option<&V> {
+        let index = self.dense_index(*entity)?;
+
+        match self.tags.as_ref() {
+            Some(tags) => tags.shared.as_deref(),
+            None => Some(&self.values[index]),
+        }
+    }
+
+    /// Returns the change tick an entity was added at
+    pub(crate) fn added(&self, entity: &Entity) -> Option<u64> {
+        Some(self.added[self.dense_index(*entity)?])
+    }
+
+    /// Adds the value of an entity, returning the value it replaces.
+    ///
+    /// Replacing a value keeps the tick the entity was added at.
+    pub(crate) fn insert(&mut self, entity: Entity, value: V) -> Option<V> {
+        let replaced = self.dense_index(entity);
+
+        if let Some(tags) = self.tags.as_mut() {
+            // Tags can't be told apart, so the new one stands in for the old one
+            if replaced.is_some() {
+                return Some(value);
+            }
+
+            match tags.shared {
+                Some(_) => std::mem::forget(value),
+                None => tags.shared = Some(ManuallyDrop::new(value)),
+            }
+        } else if let Some(index) = replaced {
+            return Some(std::mem::replace(&mut self.values[index], value));
+        } else {
+            self.values.push(value);
+        }
+
+        self.set_dense_index(entity, self.entities.len() as u32);
+        self.entities.push(entity);
+        self.added.push(change::tick());
+
+        None
+    }
+
+    pub(crate) fn remove(&mut self, entity: &Entity) -> Option<V> {
+        let index = self.dense_index(*entity)?;
+
+        self.set_dense_index(*entity, EMPTY);
+        self.entities.swap_remove(index);
+        self.added.swap_remove(index);
+        let value = match self.tags.as_ref() {
+            Some(tags) => (tags.conjure)(),
+            None => self.values.swap_remove(index),
+        };
+
+        // The last value moved into the hole, point its entity at the new place
+        if let Some(moved) = self.entities.get(index) {
+            self.set_dense_index(*moved, index as u32);
+        }
+
+        Some(value)
+    }
+
+    pub(crate) fn iter(&self) -> Iter<'_, V> {
+        Iter {
+            entities: self.entities.iter(),
+            values: match self.tags.as_ref() {
+                Some(tags) => Values::Shared(tags.shared.as_deref()),
+                None => Values::Dense(self.values.iter()),
+            },
+        }
+    }
+}
+
+impl<V> Drop for SparseSet<V> {
+    fn drop(&mut self) {
+        let Some(tags) = self.tags.as_mut() else {
+            return;
+        };
+
+        // Every entity left owns a value, the shared one counts as the first
+        let Some(shared) = tags.shared.take() else {
+            return;
+        };
+
+        if self.entities.is_empty() {
+            return;
+        }
+
+        drop(ManuallyDrop::into_inner(shared));
+        for _ in 1..self.entities.len() {
+            drop((tags.conjure)());
+        }
+    }
+}
+
+enum Values<'a, V> {
+    Dense(slice::Iter<'a, V>),
+    Shared(Option<&'a V>),
+}
+
+/// Iterator over the entities of a `SparseSet` and their values
+pub(crate) struct Iter<'a, V> {
+    entities: slice::Iter<'a, Entity>,
+    values: Values<'a, V>,
+}
+
+impl<'a, V> Iterator for Iter<'a, V> {
+    type Item = (&'a Entity, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entity = self.entities.next()?;
+        let value = match &mut self.values {
+            Values::Dense(values) => values.next()?,
+            Values::Shared(shared) => (*shared)?,
+        };
+
+        Some((entity, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entities.size_hint()
+    }
+}
+
+impl<'a, V> IntoIterator for &'a SparseSet<V> {
+    type Item = (&'a Entity, &'a V);
+    type IntoIter = Iter<'a, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparse_set() {
+        let mut set = SparseSet::new();
+
+        assert_eq!(set.insert(3, "three"), None);
+        assert_eq!(set.insert(5000, "five thousand"), None);
+        assert_eq!(set.insert(7, "seven"), None);
+        assert_eq!(set.insert(3, "drei"), Some("three"));
+        assert_eq!(set.iter().count(), 3);
+
+        assert_eq!(set.get(&3), Some(&"drei"));
+        assert_eq!(set.get(&4), None);
+        assert_eq!(set.get(&1_000_000), None);
+
+        // Pages are only allocated for the entities that were added
+        assert_eq!(set.sparse.iter().filter(|page| page.is_some()).count(), 2);
+
+        // Removing moves the last value into the hole
+        assert_eq!(set.remove(&3), Some("drei"));
+        assert_eq!(set.remove(&3), None);
+        assert_eq!(set.get(&7), Some(&"seven"));
+        assert_eq!(set.get(&5000), Some(&"five thousand"));
+
+        let mut entries = set
+            .iter()
+            .map(|(entity, value)| (*entity, *value))
+            .collect::<Vec<_>>();
+        entries.sort();
+        assert_eq!(entries, vec![(7, "seven"), (5000, "five thousand")]);
+
+        assert_eq!(set.remove(&7), Some("seven"));
+        assert_eq!(set.remove(&5000), Some("five thousand"));
+        assert_eq!(set.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_sparse_set_tags() {
+        use std::sync::atomic::{AtomicI32, Ordering};
+
+        // Tags that are alive, which has to be back to zero once the set is gone
+        static ALIVE: AtomicI32 = AtomicI32::new(0);
+
+        struct Tag;
+
+        impl Tag {
+            fn new() -> Self {
+                ALIVE.fetch_add(1, Ordering::Relaxed);
+                Tag
+            }
+        }
+
+        impl Drop for Tag {
+            fn drop(&mut self) {
+                ALIVE.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+
+        {
+            // Recreating a tag stands for one that was added, it isn't a new one
+            let mut set = SparseSet::tags(|| Tag);
+
+            assert!(set.get(&1).is_none());
+            assert!(set.insert(1, Tag::new()).is_none());
+            assert!(set.insert(2048, Tag::new()).is_none());
+            assert!(set.insert(9, Tag::new()).is_none());
+            assert_eq!(ALIVE.load(Ordering::Relaxed), 3);
+
+            // Replacing hands back the old tag
+            drop(set.insert(9, Tag::new()));
+            assert_eq!(ALIVE.load(Ordering::Relaxed), 3);
+
+            assert!(set.get(&2048).is_some());
+            assert!(set.get(&3).is_none());
+            assert_eq!(set.iter().count(), 3);
+
+            assert!(set.remove(&1).is_some());
+            assert!(set.remove(&1).is_none());
+            assert_eq!(ALIVE.load(Ordering::Relaxed), 2);
+
+            let mut entities = set.iter().map(|(entity, _)| *entity).collect::<Vec<_>>();
+            entities.sort();
+            assert_eq!(entities, vec![9, 2048]);
+
+            // No values are stored for tags
+            assert!(set.values.is_empty());
+        }
+
+        assert_eq!(ALIVE.load(Ordering::Relaxed), 0);
+    }
+}
@@ -0,0 +1,135 @@
+//! Derive macros for Compound.
+//!
+//! Use them through the `compound` crate, which re-exports every macro next
+//! to the trait it implements.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    Data, DeriveInput, Error, Fields, Index, LitStr, Path, parse_macro_input, spanned::Spanned,
+};
+
+/// Implements `MoleculeBundle` for a struct by adding each of its fields to
+/// the entity as a molecule.
+///
+/// Fields marked with `#[bundle]` are bundles themselves and add all of
+/// their molecules instead, so bundles can be built out of other bundles.
+/// Every field is added in the order it is declared.
+///
+/// The implementation names the trait through the `compound` crate, crates
+/// that re-export it, such as `isotope`, are named with
+/// `#[bundle(crate = "isotope")]` on the struct instead.
+///
+/// # Example
+/// ```ignore
+/// #[derive(MoleculeBundle)]
+/// struct CharacterBundle {
+///     transform: Transform3D,
+///     health: Health,
+/// }
+///
+/// #[derive(MoleculeBundle)]
+/// struct PlayerBundle {
+///     #[bundle]
+///     character: CharacterBundle,
+///     actions: ActionMap,
+/// }
+///
+/// compound.spawn(PlayerBundle { ... });
+/// ```
+#[proc_macro_derive(MoleculeBundle, attributes(bundle))]
+pub fn derive_molecule_bundle(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match molecule_bundle(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn molecule_bundle(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new(
+            input.span(),
+            "MoleculeBundle can only be derived for structs",
+        ));
+    };
+
+    let krate = crate_path(input)?;
+
+    let adds = match &data.fields {
+        Fields::Named(fields) => fields
+            .named
+            .iter()
+            .map(|field| {
+                let name = &field.ident;
+                add_field(field, quote!(self.#name), &krate)
+            })
+            .collect::<syn::Result<Vec<_>>>()?,
+        Fields::Unnamed(fields) => fields
+            .unnamed
+            .iter()
+            .enumerate()
+            .map(|(index, field)| {
+                let index = Index::from(index);
+                add_field(field, quote!(self.#index), &krate)
+            })
+            .collect::<syn::Result<Vec<_>>>()?,
+        Fields::Unit => Vec::new(),
+    };
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics #krate::MoleculeBundle for #name #type_generics #where_clause {
+            fn add_to_entity(self, compound: &#krate::Compound, entity: #krate::Entity) {
+                #(#adds)*
+            }
+        }
+    })
+}
+
+/// Returns the path to the crate of the trait, `compound` unless the struct
+/// is marked with `#[bundle(crate = "...")]`
+fn crate_path(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let mut krate = quote!(::compound);
+
+    for attribute in input.attrs.iter() {
+        if attribute.path().is_ident("bundle") {
+            attribute.parse_nested_meta(|meta| {
+                if meta.path.is_ident("crate") {
+                    let path: Path = meta.value()?.parse::<LitStr>()?.parse()?;
+                    krate = quote!(#path);
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `crate = \"...\"`"))
+                }
+            })?;
+        }
+    }
+
+    Ok(krate)
+}
+
+/// Adds a field to the entity as a molecule, or as a bundle if it is marked with `#[bundle]`
+fn add_field(
+    field: &syn::Field,
+    access: TokenStream2,
+    krate: &TokenStream2,
+) -> syn::Result<TokenStream2> {
+    let mut nested = false;
+    for attribute in field.attrs.iter() {
+        if attribute.path().is_ident("bundle") {
+            attribute.meta.require_path_only()?;
+            nested = true;
+        }
+    }
+
+    Ok(if nested {
+        quote!(#krate::MoleculeBundle::add_to_entity(#access, compound, entity);)
+    } else {
+        quote!(compound.add_molecule(entity, #access);)
+    })
+}
@@ -0,0 +1,80 @@
+use std::{sync::Arc, thread};
+
+use parking_lot::{Condvar, Mutex};
+
+use crate::pool::Shared;
+
+/// Where a job leaves its result for its handle
+pub(crate) struct Slot<T> {
+    result: Mutex<Option<thread::Result<T>>>,
+    done: Condvar,
+}
+
+impl<T> Default for Slot<T> {
+    fn default() -> Self {
+        Self {
+            result: Mutex::new(None),
+            done: Condvar::new(),
+        }
+    }
+}
+
+impl<T> Slot<T> {
+    pub(crate) fn fill(&self, result: thread::Result<T>) {
+        *self.result.lock() = Some(result);
+        self.done.notify_all();
+    }
+
+    pub(crate) fn is_filled(&self) -> bool {
+        self.result.lock().is_some()
+    }
+
+    /// Takes the result once the job is done, running queued jobs in the meantime
+    ///
+    /// # Arguments
+    /// * `shared` - The pool the job was queued on
+    /// * `background` - Whether background jobs may be run while waiting
+    pub(crate) fn wait(&self, shared: &Shared, background: bool) -> thread::Result<T> {
+        loop {
+            if let Some(result) = self.result.lock().take() {
+                return result;
+            }
+
+            if shared.run_one(background) {
+                continue;
+            }
+
+            // The job is running on another thread
+            let mut result = self.result.lock();
+            if result.is_none() {
+                self.done.wait(&mut result);
+            }
+        }
+    }
+}
+
+/// A background job started with `JobPool::spawn`, like the `JoinHandle` of
+/// a thread.
+pub struct JobHandle<T> {
+    shared: Arc<Shared>,
+    slot: Arc<Slot<T>>,
+}
+
+impl<T> JobHandle<T> {
+    pub(crate) fn new(shared: Arc<Shared>, slot: Arc<Slot<T>>) -> Self {
+        Self { shared, slot }
+    }
+
+    /// Returns whether the job is done, so `join` returns right away
+    pub fn is_finished(&self) -> bool {
+        self.slot.is_filled()
+    }
+
+    /// Waits for the job to finish, running queued jobs in the meantime.
+    ///
+    /// # Returns
+    /// What the job returned, or the panic it raised
+    pub fn join(self) -> thread::Result<T> {
+        self.slot.wait(&self.shared, true)
+    }
+}
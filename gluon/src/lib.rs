@@ -0,0 +1,285 @@
+//! # Gluon - The Job System Shared by the Engine
+//!
+//! Gluon runs the parallel work of every subsystem on one pool of worker
+//! threads instead of each subsystem spawning threads of its own. Workers keep
+//! their own queue of jobs and steal from each other once theirs runs dry, and
+//! sleep on a condition variable while there is nothing to do.
+//!
+//! ## Kinds of Jobs
+//! - **Scoped jobs** (`scope`) borrow from the caller, which helps run queued
+//!   jobs until every job of the scope is done, like `std::thread::scope`
+//! - **Background jobs** (`spawn`) may take several frames, such as decoding
+//!   assets, and hand back their result through a `JobHandle`. Only workers
+//!   pick them up so they never stall a thread waiting on its own jobs
+//! - **Frame jobs** (`spawn_frame`) have to be done by the end of the frame,
+//!   which waits for them at the frame boundary with `fence`
+//!
+//! Long running engine threads, like the state and physics threads, are
+//! started, paused and stopped through a `ThreadSignal` instead.
+//!
+//! ## Example
+//! ```ignore
+//! // Split the work over the pool, borrowing the agents on the stack
+//! let mut velocities = vec![Vector2::zero(); agents.len()];
+//! gluon::scope(|scope| {
+//!     for (agents, velocities) in agents.chunks(64).zip(velocities.chunks_mut(64)) {
+//!         scope.spawn(move || steer(agents, velocities));
+//!     }
+//! });
+//!
+//! // Decode a texture over the next few frames
+//! let decoding = gluon::spawn(move || image::load_from_memory(&bytes));
+//! if decoding.is_finished() {
+//!     let image = decoding.join();
+//! }
+//! ```
+
+mod handle;
+mod pool;
+mod scope;
+mod signal;
+
+pub use handle::JobHandle;
+pub use pool::JobPool;
+pub use scope::{Scope, ScopedHandle};
+pub use signal::ThreadSignal;
+
+/// Runs a job on the shared pool in the background, see `JobPool::spawn`
+pub fn spawn<F, T>(job: F) -> JobHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    JobPool::global().spawn(job)
+}
+
+/// Runs jobs borrowing from the caller on the shared pool, see `JobPool::scope`
+pub fn scope<'env, F, T>(f: F) -> T
+where
+    F: for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> T,
+{
+    JobPool::global().scope(f)
+}
+
+/// Runs a job on the shared pool before the next frame boundary, see `JobPool::spawn_frame`
+pub fn spawn_frame<F>(job: F)
+where
+    F: FnOnce() + Send + 'static,
+{
+    JobPool::global().spawn_frame(job)
+}
+
+/// Waits for the frame jobs of the shared pool, see `JobPool::fence`
+pub fn fence() {
+    JobPool::global().fence()
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    use super::*;
+
+    const JOBS: usize = 1000;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    /// Tests that the pool can be shared between threads.
+    #[test]
+    fn test_send_sync() {
+        assert_send_sync::<JobPool>();
+        assert_send_sync::<JobHandle<u32>>();
+        assert_send_sync::<ThreadSignal>();
+    }
+
+    /// Tests that scoped jobs can borrow from the caller and are all done
+    /// when the scope returns.
+    #[test]
+    fn test_scope() {
+        let pool = JobPool::new(4);
+        let mut values = vec![0; JOBS];
+        let counter = AtomicUsize::new(0);
+
+        pool.scope(|scope| {
+            for (index, value) in values.iter_mut().enumerate() {
+                let counter = &counter;
+                scope.spawn(move || {
+                    *value = index * 2;
+                    counter.fetch_add(1, Ordering::Relaxed);
+                });
+            }
+        });
+
+        assert_eq!(counter.load(Ordering::Relaxed), JOBS);
+        assert!(
+            values
+                .iter()
+                .enumerate()
+                .all(|(index, value)| *value == index * 2)
+        );
+    }
+
+    /// Tests that scopes nested in scoped jobs finish without deadlocking,
+    /// even with a single worker.
+    #[test]
+    fn test_nested_scope() {
+        let pool = JobPool::new(1);
+        let counter = AtomicUsize::new(0);
+
+        pool.scope(|scope| {
+            for _ in 0..8 {
+                let (pool, counter) = (&pool, &counter);
+                scope.spawn(move || {
+                    pool.scope(|scope| {
+                        for _ in 0..8 {
+                            scope.spawn(move || counter.fetch_add(1, Ordering::Relaxed));
+                        }
+                    })
+                });
+            }
+        });
+
+        assert_eq!(counter.load(Ordering::Relaxed), 64);
+    }
+
+    /// Tests that scoped handles hand back the result of their job, and that
+    /// a panic in a joined job is returned instead of unwinding the scope.
+    #[test]
+    fn test_scoped_join() {
+        let pool = JobPool::new(2);
+
+        let (sum, panicked) = pool.scope(|scope| {
+            let sum = scope.spawn(|| (0..100).sum::<u32>());
+            let panicked = scope.spawn(|| panic!("Job failed"));
+
+            (sum.join().unwrap(), panicked.join().is_err())
+        });
+
+        assert_eq!(sum, 4950);
+        assert!(panicked);
+    }
+
+    /// Tests that a panic in a job that is never joined unwinds the scope.
+    #[test]
+    fn test_scope_panic() {
+        let pool = JobPool::new(2);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            pool.scope(|scope| {
+                scope.spawn(|| panic!("Job failed"));
+            })
+        }));
+
+        assert!(result.is_err());
+    }
+
+    /// Tests that background jobs finish on their own and hand back their result.
+    #[test]
+    fn test_spawn() {
+        let pool = JobPool::new(2);
+
+        let handles = (0..JOBS as u64)
+            .map(|index| pool.spawn(move || index * index))
+            .collect::<Vec<_>>();
+
+        let waited = handles.last().unwrap();
+        while !waited.is_finished() {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        for (index, handle) in handles.into_iter().enumerate() {
+            assert_eq!(handle.join().unwrap(), (index * index) as u64);
+        }
+    }
+
+    /// Tests that the fence waits for every frame job spawned before it.
+    #[test]
+    fn test_fence() {
+        let pool = JobPool::new(4);
+        let counter = Arc::new(AtomicUsize::new(0));
+
+        for frame in 1..=3 {
+            for _ in 0..JOBS {
+                let counter = counter.clone();
+                pool.spawn_frame(move || {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                });
+            }
+
+            pool.fence();
+            assert_eq!(counter.load(Ordering::Relaxed), frame * JOBS);
+        }
+    }
+
+    /// Tests that dropping a pool with queued jobs still lets their handles
+    /// be joined.
+    #[test]
+    fn test_drop_pool() {
+        let pool = JobPool::new(1);
+        let handles = (0..JOBS)
+            .map(|index| pool.spawn(move || index))
+            .collect::<Vec<_>>();
+        drop(pool);
+
+        for (index, handle) in handles.into_iter().enumerate() {
+            assert_eq!(handle.join().unwrap(), index);
+        }
+    }
+
+    /// Tests that a thread waiting on a signal starts, pauses and stops when
+    /// told to, and that stopping wakes it from a long sleep.
+    #[test]
+    fn test_thread_signal() {
+        let signal = Arc::new(ThreadSignal::new());
+        let ticks = Arc::new(AtomicUsize::new(0));
+
+        let handle = {
+            let (signal, ticks) = (signal.clone(), ticks.clone());
+            std::thread::spawn(move || {
+                if !signal.wait_for_start() {
+                    return;
+                }
+
+                while signal.wait_while_paused().is_some() {
+                    ticks.fetch_add(1, Ordering::Relaxed);
+                    signal.sleep(Duration::from_millis(1));
+                }
+            })
+        };
+
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(ticks.load(Ordering::Relaxed), 0);
+
+        signal.start();
+        while ticks.load(Ordering::Relaxed) < 3 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        signal.set_paused(true);
+        std::thread::sleep(Duration::from_millis(20));
+        let paused_ticks = ticks.load(Ordering::Relaxed);
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(ticks.load(Ordering::Relaxed), paused_ticks);
+
+        signal.stop();
+        handle.join().unwrap();
+        assert!(!signal.sleep(Duration::from_secs(60)));
+        assert!(signal.wait_while_paused().is_none());
+    }
+
+    /// Tests that a thread stopped before it was started never runs.
+    #[test]
+    fn test_thread_signal_stopped_before_start() {
+        let signal = ThreadSignal::new();
+        signal.stop();
+        signal.start();
+
+        assert!(!signal.wait_for_start());
+    }
+}
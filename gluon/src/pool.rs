@@ -0,0 +1,316 @@
+use std::{
+    cell::Cell,
+    collections::VecDeque,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        Arc, OnceLock,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread::{self, JoinHandle},
+};
+
+use log::{error, info};
+use parking_lot::{Condvar, Mutex};
+
+use crate::{
+    handle::{JobHandle, Slot},
+    scope::Scope,
+};
+
+/// A job with its result and panic already taken care of
+pub(crate) type Job = Box<dyn FnOnce() + Send + 'static>;
+
+thread_local! {
+    // The pool and the queue of the worker running on this thread
+    static WORKER: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
+
+/// The queues and signals shared by the workers of a pool and whoever waits on it
+pub(crate) struct Shared {
+    // One queue per worker, each worker takes from the back of its own and
+    // steals from the front of the others
+    queues: Box<[Mutex<VecDeque<Job>>]>,
+    // Jobs queued from threads outside the pool
+    injector: Mutex<VecDeque<Job>>,
+    // Jobs that may take several frames, only ever run by workers
+    background: Mutex<VecDeque<Job>>,
+    queued: AtomicUsize,
+    sleep: Mutex<()>,
+    wake: Condvar,
+    running: AtomicBool,
+    // Frame jobs not done yet
+    frame_jobs: Mutex<usize>,
+    frame_done: Condvar,
+}
+
+impl Shared {
+    /// Queues a job on the worker running this thread, or for any worker if
+    /// this thread is not one of the pool's
+    pub(crate) fn push(&self, job: Job) {
+        match self.worker() {
+            Some(index) => self.queues[index].lock().push_back(job),
+            None => self.injector.lock().push_back(job),
+        }
+
+        self.notify();
+    }
+
+    fn push_background(&self, job: Job) {
+        self.background.lock().push_back(job);
+        self.notify();
+    }
+
+    fn notify(&self) {
+        self.queued.fetch_add(1, Ordering::AcqRel);
+
+        // Taking the lock makes sure a worker about to sleep sees the job first
+        drop(self.sleep.lock());
+        self.wake.notify_one();
+    }
+
+    /// Runs one queued job on this thread.
+    ///
+    /// # Arguments
+    /// * `background` - Whether background jobs may be picked, which could
+    ///   keep this thread busy for a long time
+    ///
+    /// # Returns
+    /// Whether there was a job to run
+    pub(crate) fn run_one(&self, background: bool) -> bool {
+        let local = self.worker();
+
+        let job = local
+            .and_then(|index| self.queues[index].lock().pop_back())
+            .or_else(|| self.injector.lock().pop_front())
+            .or_else(|| {
+                let start = local.map_or(0, |index| index + 1);
+                (0..self.queues.len())
+                    .map(|offset| (start + offset) % self.queues.len())
+                    .filter(|index| Some(*index) != local)
+                    .find_map(|index| self.queues[index].lock().pop_front())
+            })
+            .or_else(|| match background {
+                true => self.background.lock().pop_front(),
+                false => None,
+            });
+
+        match job {
+            Some(job) => {
+                self.queued.fetch_sub(1, Ordering::AcqRel);
+                job();
+                true
+            }
+            None => false,
+        }
+    }
+
+    // The index of the worker running this thread if it belongs to this pool
+    fn worker(&self) -> Option<usize> {
+        WORKER
+            .get()
+            .filter(|(pool, _)| *pool == self as *const Self as usize)
+            .map(|(_, index)| index)
+    }
+}
+
+/// A pool of worker threads running jobs for the whole engine.
+///
+/// Every subsystem shares the pool from `JobPool::global`, sized to the cores
+/// of the machine, so parallel work never oversubscribes the CPU. Threads
+/// waiting on jobs of their own help run queued jobs rather than sleeping.
+///
+/// # Example
+/// ```ignore
+/// let pool = JobPool::new(2);
+/// let lengths = pool.scope(|scope| {
+///     let a = scope.spawn(|| first.len());
+///     let b = scope.spawn(|| second.len());
+///     a.join().unwrap() + b.join().unwrap()
+/// });
+/// ```
+pub struct JobPool {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl Drop for JobPool {
+    fn drop(&mut self) {
+        self.shared.running.store(false, Ordering::Release);
+
+        drop(self.shared.sleep.lock());
+        self.shared.wake.notify_all();
+
+        for worker in self.workers.drain(..) {
+            if worker.join().is_err() {
+                error!("Gluon worker panicked");
+            }
+        }
+    }
+}
+
+impl JobPool {
+    /// Starts a pool of worker threads.
+    ///
+    /// # Arguments
+    /// * `workers` - The number of worker threads, at least 1
+    pub fn new(workers: usize) -> Self {
+        let workers = workers.max(1);
+        let shared = Arc::new(Shared {
+            queues: (0..workers).map(|_| Mutex::new(VecDeque::new())).collect(),
+            injector: Mutex::new(VecDeque::new()),
+            background: Mutex::new(VecDeque::new()),
+            queued: AtomicUsize::new(0),
+            sleep: Mutex::new(()),
+            wake: Condvar::new(),
+            running: AtomicBool::new(true),
+            frame_jobs: Mutex::new(0),
+            frame_done: Condvar::new(),
+        });
+
+        let workers = (0..workers)
+            .filter_map(|index| {
+                let shared = shared.clone();
+
+                thread::Builder::new()
+                    .name(format!("gluon-worker-{}", index))
+                    .spawn(move || work(shared, index))
+                    .inspect_err(|err| error!("Failed to start gluon worker: {}", err))
+                    .ok()
+            })
+            .collect();
+
+        Self { shared, workers }
+    }
+
+    /// Returns the pool shared by the engine, started on first use with a
+    /// worker for every core but the one of the calling thread
+    pub fn global() -> &'static JobPool {
+        static GLOBAL: OnceLock<JobPool> = OnceLock::new();
+
+        GLOBAL.get_or_init(|| {
+            let workers = thread::available_parallelism()
+                .map(|cores| cores.get().saturating_sub(1))
+                .unwrap_or(1);
+            info!("Starting gluon with {} workers", workers.max(1));
+
+            JobPool::new(workers)
+        })
+    }
+
+    /// Returns the number of worker threads of the pool
+    pub fn workers(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Runs a job in the background, for work that may take longer than a
+    /// frame such as loading assets. Background jobs only run on workers, so
+    /// scopes and fences never end up waiting on one they picked up.
+    ///
+    /// # Arguments
+    /// * `job` - The job to run
+    ///
+    /// # Returns
+    /// A handle to poll for the result, dropping it lets the job finish unobserved
+    pub fn spawn<F, T>(&self, job: F) -> JobHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let slot = Arc::new(Slot::default());
+        let job_slot = slot.clone();
+
+        self.shared.push_background(Box::new(move || {
+            job_slot.fill(panic::catch_unwind(AssertUnwindSafe(job)));
+        }));
+
+        JobHandle::new(self.shared.clone(), slot)
+    }
+
+    /// Runs jobs that borrow from the caller, returning once all of them are done.
+    ///
+    /// The calling thread runs queued jobs while it waits. If a job panics
+    /// and its handle is never joined, the panic is raised again here.
+    ///
+    /// # Arguments
+    /// * `f` - Spawns the jobs on the scope it is given
+    ///
+    /// # Returns
+    /// What `f` returns
+    pub fn scope<'env, F, T>(&self, f: F) -> T
+    where
+        F: for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> T,
+    {
+        let scope = Scope::new(&self.shared);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(&scope)));
+
+        // The jobs borrow from the caller, so they have to be done even if `f` panicked
+        scope.wait();
+
+        match result {
+            Err(payload) => panic::resume_unwind(payload),
+            Ok(_) if scope.panicked() => panic!("A scoped job panicked"),
+            Ok(value) => value,
+        }
+    }
+
+    /// Runs a job that has to be done by the next frame boundary, for work
+    /// started during a frame whose result the frame doesn't wait on.
+    ///
+    /// # Arguments
+    /// * `job` - The job to run, a panic is logged and otherwise ignored
+    pub fn spawn_frame<F>(&self, job: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        *self.shared.frame_jobs.lock() += 1;
+
+        let shared = self.shared.clone();
+        self.shared.push(Box::new(move || {
+            if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                error!("Frame job panicked");
+            }
+
+            let mut frame_jobs = shared.frame_jobs.lock();
+            *frame_jobs -= 1;
+            if *frame_jobs == 0 {
+                shared.frame_done.notify_all();
+            }
+        }));
+    }
+
+    /// Waits at a frame boundary for every frame job spawned so far, running
+    /// queued jobs on the calling thread in the meantime
+    pub fn fence(&self) {
+        loop {
+            if *self.shared.frame_jobs.lock() == 0 {
+                return;
+            }
+
+            if self.shared.run_one(false) {
+                continue;
+            }
+
+            // The rest are running on workers
+            let mut frame_jobs = self.shared.frame_jobs.lock();
+            if *frame_jobs > 0 {
+                self.shared.frame_done.wait(&mut frame_jobs);
+            }
+        }
+    }
+}
+
+/// Runs jobs until the pool stops, sleeping while there are none
+fn work(shared: Arc<Shared>, index: usize) {
+    WORKER.set(Some((Arc::as_ptr(&shared) as usize, index)));
+
+    while shared.running.load(Ordering::Acquire) {
+        if shared.run_one(true) {
+            continue;
+        }
+
+        let mut sleep = shared.sleep.lock();
+        if shared.queued.load(Ordering::Acquire) == 0 && shared.running.load(Ordering::Acquire) {
+            shared.wake.wait(&mut sleep);
+        }
+    }
+}
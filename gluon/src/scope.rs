@@ -0,0 +1,149 @@
+use std::{
+    marker::PhantomData,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+};
+
+use parking_lot::{Condvar, Mutex};
+
+use crate::{
+    handle::Slot,
+    pool::{Job, Shared},
+};
+
+// Counts the jobs of a scope that are not done yet
+#[derive(Default)]
+struct ScopeState {
+    outstanding: Mutex<usize>,
+    done: Condvar,
+    // Panics nobody joined the job of yet
+    panics: AtomicUsize,
+}
+
+/// Spawns jobs that may borrow anything living longer than the scope, made
+/// by `JobPool::scope`.
+///
+/// # Type Parameters
+/// - `'scope`: How long the scope lives, every job is done before it ends
+/// - `'env`: What the jobs borrow from, outliving the scope
+pub struct Scope<'scope, 'env: 'scope> {
+    shared: &'scope Shared,
+    state: Arc<ScopeState>,
+    scope: PhantomData<&'scope mut &'scope ()>,
+    env: PhantomData<&'env mut &'env ()>,
+}
+
+impl<'scope, 'env> Scope<'scope, 'env> {
+    pub(crate) fn new(shared: &'scope Shared) -> Self {
+        Self {
+            shared,
+            state: Arc::new(ScopeState::default()),
+            scope: PhantomData,
+            env: PhantomData,
+        }
+    }
+
+    /// Queues a job on the pool.
+    ///
+    /// # Arguments
+    /// * `job` - The job to run, borrowing anything that outlives the scope
+    ///
+    /// # Returns
+    /// A handle to wait for the result, dropping it still waits for the job
+    /// at the end of the scope
+    pub fn spawn<F, T>(&'scope self, job: F) -> ScopedHandle<'scope, T>
+    where
+        F: FnOnce() -> T + Send + 'scope,
+        T: Send + 'scope,
+    {
+        let slot = Arc::new(Slot::default());
+        let job_slot = slot.clone();
+        let state = self.state.clone();
+        *state.outstanding.lock() += 1;
+
+        let job: Box<dyn FnOnce() + Send + 'scope> = Box::new(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(job));
+            if result.is_err() {
+                state.panics.fetch_add(1, Ordering::AcqRel);
+            }
+
+            job_slot.fill(result);
+            // Nothing borrowed for 'scope may be touched once the scope can end
+            drop(job_slot);
+
+            let mut outstanding = state.outstanding.lock();
+            *outstanding -= 1;
+            if *outstanding == 0 {
+                state.done.notify_all();
+            }
+        });
+
+        // SAFETY: `JobPool::scope` doesn't return before every job of the
+        // scope has run, and a job drops everything it borrows before it
+        // counts itself as done, so nothing it borrows is used after 'scope.
+        let job = unsafe { std::mem::transmute::<Box<dyn FnOnce() + Send + 'scope>, Job>(job) };
+        self.shared.push(job);
+
+        ScopedHandle {
+            shared: self.shared,
+            state: &self.state,
+            slot,
+        }
+    }
+
+    /// Waits for every job of the scope, running queued jobs in the meantime
+    pub(crate) fn wait(&self) {
+        loop {
+            if *self.state.outstanding.lock() == 0 {
+                return;
+            }
+
+            if self.shared.run_one(false) {
+                continue;
+            }
+
+            // The rest are running on other threads
+            let mut outstanding = self.state.outstanding.lock();
+            if *outstanding > 0 {
+                self.state.done.wait(&mut outstanding);
+            }
+        }
+    }
+
+    /// Whether a job panicked without its handle being joined
+    pub(crate) fn panicked(&self) -> bool {
+        self.state.panics.load(Ordering::Acquire) > 0
+    }
+}
+
+/// A job spawned on a `Scope`.
+pub struct ScopedHandle<'scope, T> {
+    shared: &'scope Shared,
+    state: &'scope Arc<ScopeState>,
+    slot: Arc<Slot<T>>,
+}
+
+impl<T> ScopedHandle<'_, T> {
+    /// Returns whether the job is done, so `join` returns right away
+    pub fn is_finished(&self) -> bool {
+        self.slot.is_filled()
+    }
+
+    /// Waits for the job to finish, running queued jobs in the meantime.
+    ///
+    /// # Returns
+    /// What the job returned, or the panic it raised, which then no longer
+    /// unwinds the scope
+    pub fn join(self) -> thread::Result<T> {
+        let result = self.slot.wait(self.shared, false);
+        if result.is_err() {
+            self.state.panics.fetch_sub(1, Ordering::AcqRel);
+        }
+
+        result
+    }
+}
@@ -0,0 +1,136 @@
+use std::time::{Duration, Instant};
+
+use parking_lot::{Condvar, Mutex};
+
+#[derive(Debug, Default)]
+struct SignalState {
+    started: bool,
+    paused: bool,
+    stopped: bool,
+}
+
+/// Starts, pauses and stops a long running engine thread, such as the state
+/// or physics thread, which blocks on a condition variable until told
+/// otherwise rather than polling a flag.
+///
+/// # Example
+/// ```ignore
+/// let signal = Arc::new(ThreadSignal::new());
+/// let thread_signal = signal.clone();
+/// let handle = std::thread::spawn(move || {
+///     if !thread_signal.wait_for_start() {
+///         return;
+///     }
+///
+///     while thread_signal.wait_while_paused().is_some() {
+///         tick();
+///         thread_signal.sleep(TICK_RATE);
+///     }
+/// });
+///
+/// signal.start();
+/// // Later, wakes the thread even if it is sleeping or paused
+/// signal.stop();
+/// handle.join();
+/// ```
+#[derive(Debug, Default)]
+pub struct ThreadSignal {
+    state: Mutex<SignalState>,
+    changed: Condvar,
+}
+
+impl ThreadSignal {
+    /// Creates a signal for a thread that waits to be started
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lets the thread start running, does nothing once stopped
+    pub fn start(&self) {
+        self.update(|state| state.started = true);
+    }
+
+    /// Stops the thread for good, waking it wherever it waits
+    pub fn stop(&self) {
+        self.update(|state| state.stopped = true);
+    }
+
+    /// Pauses or resumes the thread, which waits in `wait_while_paused`
+    pub fn set_paused(&self, paused: bool) {
+        self.update(|state| state.paused = paused);
+    }
+
+    /// Returns whether the thread is paused
+    pub fn is_paused(&self) -> bool {
+        self.state.lock().paused
+    }
+
+    /// Returns whether the thread was stopped
+    pub fn is_stopped(&self) -> bool {
+        self.state.lock().stopped
+    }
+
+    /// Waits until the thread is started.
+    ///
+    /// # Returns
+    /// Whether the thread should run, false if it was stopped before it started
+    pub fn wait_for_start(&self) -> bool {
+        let mut state = self.state.lock();
+        while !state.started && !state.stopped {
+            self.changed.wait(&mut state);
+        }
+
+        !state.stopped
+    }
+
+    /// Waits while the thread is paused.
+    ///
+    /// # Returns
+    /// How long the thread waited, so paused time isn't counted as passed, or
+    /// `None` if the thread was stopped
+    pub fn wait_while_paused(&self) -> Option<Duration> {
+        let mut paused_at = None;
+        let mut state = self.state.lock();
+        while state.paused && !state.stopped {
+            paused_at.get_or_insert_with(Instant::now);
+            self.changed.wait(&mut state);
+        }
+
+        match state.stopped {
+            true => None,
+            false => Some(paused_at.map_or(Duration::ZERO, |paused_at| paused_at.elapsed())),
+        }
+    }
+
+    /// Sleeps between ticks, waking early if the thread is stopped.
+    ///
+    /// # Arguments
+    /// * `duration` - How long to sleep
+    ///
+    /// # Returns
+    /// Whether the thread should keep running
+    pub fn sleep(&self, duration: Duration) -> bool {
+        let deadline = Instant::now() + duration;
+        let mut state = self.state.lock();
+        while !state.stopped {
+            if self.changed.wait_until(&mut state, deadline).timed_out() {
+                break;
+            }
+        }
+
+        !state.stopped
+    }
+
+    /// Waits until the thread is stopped
+    pub fn wait_for_stop(&self) {
+        let mut state = self.state.lock();
+        while !state.stopped {
+            self.changed.wait(&mut state);
+        }
+    }
+
+    fn update(&self, change: impl FnOnce(&mut SignalState)) {
+        change(&mut self.state.lock());
+        self.changed.notify_all();
+    }
+}
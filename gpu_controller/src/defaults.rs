@@ -10,7 +10,23 @@
 //! - **Safe** to use without additional configuration
 //! - **Flexible** enough to serve as a starting point for customization
 
-use wgpu::{CompositeAlphaMode, PresentMode, SurfaceConfiguration, TextureFormat, TextureUsages};
+use wgpu::{
+    Backends, CompositeAlphaMode, PresentMode, SurfaceConfiguration, TextureFormat, TextureUsages,
+};
+
+/// Graphics backends requested on Android, GLES covers devices without Vulkan
+#[cfg(target_os = "android")]
+pub(crate) const DEFAULT_BACKENDS: Backends = Backends::VULKAN.union(Backends::GL);
+
+/// Graphics backends requested on iOS
+#[cfg(target_os = "ios")]
+pub(crate) const DEFAULT_BACKENDS: Backends = Backends::METAL;
+
+/// Graphics backends requested on desktop platforms
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+pub(crate) const DEFAULT_BACKENDS: Backends = Backends::VULKAN
+    .union(Backends::DX12)
+    .union(Backends::METAL);
 
 /// Default surface configuration for WGPU rendering contexts.
 ///
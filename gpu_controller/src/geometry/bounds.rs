@@ -0,0 +1,126 @@
+use super::{Position, mesh::Distance, vertex::Vertex};
+
+/// Bounding volumes of a piece of geometry in its local space.
+///
+/// Holds both an axis aligned bounding box and a bounding sphere so systems
+/// can pick the cheaper test for their needs without recomputing either.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min: Position,
+    pub max: Position,
+    /// Center of the bounding sphere
+    pub center: Position,
+    /// Radius of the bounding sphere
+    pub radius: f32,
+}
+
+impl Default for Bounds {
+    fn default() -> Self {
+        Self {
+            min: [0.0; 3],
+            max: [0.0; 3],
+            center: [0.0; 3],
+            radius: 0.0,
+        }
+    }
+}
+
+impl Bounds {
+    /// Computes the bounds of a set of points.
+    ///
+    /// The sphere is centered on the box and grown to contain every point,
+    /// which is not minimal but is stable and cheap to compute.
+    ///
+    /// # Returns
+    /// The bounds, or empty bounds at the origin if there are no points
+    pub fn from_positions<'a, I>(positions: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Position> + Clone,
+    {
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        let mut empty = true;
+
+        for position in positions.clone() {
+            empty = false;
+
+            for axis in 0..3 {
+                min[axis] = min[axis].min(position[axis]);
+                max[axis] = max[axis].max(position[axis]);
+            }
+        }
+
+        if empty {
+            return Self::default();
+        }
+
+        let center = [0, 1, 2].map(|axis| (min[axis] + max[axis]) * 0.5);
+        let radius = positions
+            .into_iter()
+            .map(|position| [0, 1, 2].map(|axis| position[axis] - center[axis]).dist())
+            .fold(0.0, f32::max);
+
+        Self {
+            min,
+            max,
+            center,
+            radius,
+        }
+    }
+
+    /// Computes the bounds of the positions of a set of vertices
+    pub fn from_vertices(vertices: &[Vertex]) -> Self {
+        let positions = vertices
+            .iter()
+            .map(|vertex| vertex.position)
+            .collect::<Vec<_>>();
+
+        Self::from_positions(&positions)
+    }
+
+    /// Returns bounds that contain both these bounds and `other`
+    pub fn union(&self, other: &Bounds) -> Self {
+        let min = [0, 1, 2].map(|axis| self.min[axis].min(other.min[axis]));
+        let max = [0, 1, 2].map(|axis| self.max[axis].max(other.max[axis]));
+        let center = [0, 1, 2].map(|axis| (min[axis] + max[axis]) * 0.5);
+
+        // Grow the sphere around the new center to contain both spheres
+        let radius = [self, other]
+            .iter()
+            .map(|bounds| {
+                [0, 1, 2]
+                    .map(|axis| bounds.center[axis] - center[axis])
+                    .dist()
+                    + bounds.radius
+            })
+            .fold(0.0, f32::max);
+
+        Self {
+            min,
+            max,
+            center,
+            radius,
+        }
+    }
+
+    /// Returns half the size of the box along each axis
+    pub fn half_extents(&self) -> Position {
+        [0, 1, 2].map(|axis| (self.max[axis] - self.min[axis]) * 0.5)
+    }
+
+    /// Returns the eight corners of the box
+    pub fn corners(&self) -> [Position; 8] {
+        let (min, max) = (self.min, self.max);
+
+        [
+            [min[0], min[1], min[2]],
+            [max[0], min[1], min[2]],
+            [max[0], max[1], min[2]],
+            [min[0], max[1], min[2]],
+            [min[0], min[1], max[2]],
+            [max[0], min[1], max[2]],
+            [max[0], max[1], max[2]],
+            [min[0], max[1], max[2]],
+        ]
+    }
+}
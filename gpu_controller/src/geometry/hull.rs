@@ -0,0 +1,424 @@
+use std::collections::{HashMap, HashSet};
+
+use cgmath::{InnerSpace, Vector3, Zero};
+
+use super::Position;
+
+// Distance a point must be outside a face, relative to the size of the point
+// set, before it counts as outside
+const HULL_EPSILON: f64 = 1e-6;
+
+/// A triangle of the hull being built, wound counter clockwise seen from outside
+struct Face {
+    vertices: [usize; 3],
+    normal: Vector3<f64>,
+    offset: f64,
+    // Points above this face that have not been added to the hull yet
+    outside: Vec<usize>,
+    alive: bool,
+}
+
+impl Face {
+    fn new(vertices: [usize; 3], points: &[Vector3<f64>]) -> Self {
+        let [a, b, c] = vertices.map(|index| points[index]);
+        let normal = (b - a).cross(c - a);
+        let normal = if normal.magnitude2() > 0.0 {
+            normal.normalize()
+        } else {
+            Vector3::zero()
+        };
+
+        Self {
+            vertices,
+            normal,
+            offset: normal.dot(a),
+            outside: Vec::new(),
+            alive: true,
+        }
+    }
+
+    fn distance(&self, point: Vector3<f64>) -> f64 {
+        self.normal.dot(point) - self.offset
+    }
+
+    fn edges(&self) -> [(usize, usize); 3] {
+        let [a, b, c] = self.vertices;
+        [(a, b), (b, c), (c, a)]
+    }
+}
+
+/// Computes the vertices of the convex hull of a set of points with quickhull.
+///
+/// Points inside the hull or on its faces are dropped, so the result is the
+/// smallest set of points with the same hull. Flat point sets return the
+/// corners of their outline, and collinear sets the two ends of the line.
+///
+/// # Arguments
+/// * `positions` - The points to wrap, such as the vertices of a mesh
+///
+/// # Returns
+/// The points on the corners of the hull, in no particular order
+pub fn hull_points(positions: &[Position]) -> Vec<Position> {
+    let points = positions
+        .iter()
+        .map(|position| Vector3::new(position[0], position[1], position[2]).cast::<f64>())
+        .collect::<Option<Vec<_>>>()
+        .unwrap_or_default();
+
+    if points.is_empty() {
+        return Vec::new();
+    }
+
+    let extent = points
+        .iter()
+        .flat_map(|point| [point.x.abs(), point.y.abs(), point.z.abs()])
+        .fold(1.0, f64::max);
+    let epsilon = extent * HULL_EPSILON;
+
+    let corners = match initial_simplex(&points, epsilon) {
+        Simplex::Point(a) => vec![a],
+        Simplex::Line(a, b) => vec![a, b],
+        Simplex::Plane(a, b, c) => planar_hull(&points, [a, b, c], epsilon),
+        Simplex::Tetrahedron(simplex) => quickhull(&points, simplex, epsilon),
+    };
+
+    corners.into_iter().map(|index| positions[index]).collect()
+}
+
+enum Simplex {
+    Point(usize),
+    Line(usize, usize),
+    Plane(usize, usize, usize),
+    Tetrahedron([usize; 4]),
+}
+
+/// Finds four points spanning a volume to start the hull from, or the lower
+/// dimensional shape the points are stuck in
+fn initial_simplex(points: &[Vector3<f64>], epsilon: f64) -> Simplex {
+    // Ties are broken by the coordinates of the points so a point in the
+    // middle of an edge or face is never picked over its corners
+    let furthest = |score: &dyn Fn(Vector3<f64>) -> f64| {
+        (0..points.len())
+            .max_by(|&a, &b| {
+                let (a, b) = (points[a], points[b]);
+
+                if (score(a) - score(b)).abs() > epsilon {
+                    score(a).total_cmp(&score(b))
+                } else {
+                    a.x.total_cmp(&b.x)
+                        .then(a.y.total_cmp(&b.y))
+                        .then(a.z.total_cmp(&b.z))
+                }
+            })
+            .unwrap_or_default()
+    };
+
+    // The two extremes furthest apart along one of the axes
+    let (a, b) = (0..3)
+        .map(|axis| {
+            (
+                furthest(&|point| -point[axis]),
+                furthest(&|point| point[axis]),
+            )
+        })
+        .max_by(|&(a, b), &(c, d)| {
+            (points[b] - points[a])
+                .magnitude2()
+                .total_cmp(&(points[d] - points[c]).magnitude2())
+        })
+        .unwrap_or_default();
+
+    if (points[b] - points[a]).magnitude() <= epsilon {
+        return Simplex::Point(a);
+    }
+
+    let line = (points[b] - points[a]).normalize();
+    let line_distance = |point: Vector3<f64>| (point - points[a]).cross(line).magnitude();
+    let c = furthest(&line_distance);
+
+    if line_distance(points[c]) <= epsilon {
+        return Simplex::Line(a, b);
+    }
+
+    let normal = (points[b] - points[a])
+        .cross(points[c] - points[a])
+        .normalize();
+    let plane_distance = |point: Vector3<f64>| normal.dot(point - points[a]);
+    let d = furthest(&|point| plane_distance(point).abs());
+
+    if plane_distance(points[d]).abs() <= epsilon {
+        return Simplex::Plane(a, b, c);
+    }
+
+    Simplex::Tetrahedron([a, b, c, d])
+}
+
+fn quickhull(points: &[Vector3<f64>], simplex: [usize; 4], epsilon: f64) -> Vec<usize> {
+    let [a, b, c, d] = simplex;
+    let centroid = simplex
+        .iter()
+        .fold(Vector3::zero(), |sum, &index| sum + points[index])
+        / 4.0;
+
+    let mut faces: Vec<Face> = Vec::new();
+    // Maps every directed edge of a live face to that face
+    let mut edges: HashMap<(usize, usize), usize> = HashMap::new();
+
+    let mut add_face = |faces: &mut Vec<Face>, vertices: [usize; 3]| {
+        let mut face = Face::new(vertices, points);
+
+        // Wind the face so its normal points away from the inside of the hull
+        if face.distance(centroid) > 0.0 {
+            let [a, b, c] = vertices;
+            face = Face::new([a, c, b], points);
+        }
+
+        for edge in face.edges() {
+            edges.insert(edge, faces.len());
+        }
+
+        faces.push(face);
+        faces.len() - 1
+    };
+
+    for vertices in [[a, b, c], [a, b, d], [a, c, d], [b, c, d]] {
+        add_face(&mut faces, vertices);
+    }
+
+    // Hand every point to the first face it is outside of
+    for (index, point) in points.iter().enumerate() {
+        if simplex.contains(&index) {
+            continue;
+        }
+
+        if let Some(face) = faces
+            .iter_mut()
+            .find(|face| face.distance(*point) > epsilon)
+        {
+            face.outside.push(index);
+        }
+    }
+
+    while let Some(start) = faces
+        .iter()
+        .position(|face| face.alive && !face.outside.is_empty())
+    {
+        let eye = faces[start]
+            .outside
+            .iter()
+            .copied()
+            .max_by(|&a, &b| {
+                faces[start]
+                    .distance(points[a])
+                    .total_cmp(&faces[start].distance(points[b]))
+            })
+            .unwrap_or_default();
+
+        // Flood out from the face to every connected face the eye can see
+        let mut visible = vec![start];
+        let mut seen = HashSet::from([start]);
+        let mut next = 0;
+
+        while next < visible.len() {
+            let face = visible[next];
+            next += 1;
+
+            for (from, to) in faces[face].edges() {
+                if let Some(&neighbor) = edges.get(&(to, from))
+                    && faces[neighbor].alive
+                    && faces[neighbor].distance(points[eye]) > epsilon
+                    && seen.insert(neighbor)
+                {
+                    visible.push(neighbor);
+                }
+            }
+        }
+
+        // Edges of the visible region that border a hidden face
+        let horizon = visible
+            .iter()
+            .flat_map(|&face| faces[face].edges())
+            .filter(|&(from, to)| {
+                edges
+                    .get(&(to, from))
+                    .is_none_or(|neighbor| !seen.contains(neighbor))
+            })
+            .collect::<Vec<_>>();
+
+        let mut orphans = Vec::new();
+
+        for &face in visible.iter() {
+            faces[face].alive = false;
+            orphans.append(&mut faces[face].outside);
+
+            for edge in faces[face].edges() {
+                if edges.get(&edge) == Some(&face) {
+                    edges.remove(&edge);
+                }
+            }
+        }
+
+        // Cone the horizon to the eye, keeping the winding of the faces it replaces
+        let new_faces = horizon
+            .into_iter()
+            .map(|(from, to)| {
+                let face = Face::new([from, to, eye], points);
+
+                for edge in face.edges() {
+                    edges.insert(edge, faces.len());
+                }
+
+                faces.push(face);
+                faces.len() - 1
+            })
+            .collect::<Vec<_>>();
+
+        for orphan in orphans {
+            if orphan == eye {
+                continue;
+            }
+
+            if let Some(&face) = new_faces
+                .iter()
+                .find(|&&face| faces[face].distance(points[orphan]) > epsilon)
+            {
+                faces[face].outside.push(orphan);
+            }
+        }
+    }
+
+    let mut corners = faces
+        .iter()
+        .filter(|face| face.alive)
+        .flat_map(|face| face.vertices)
+        .collect::<Vec<_>>();
+    corners.sort_unstable();
+    corners.dedup();
+
+    corners
+}
+
+/// Computes the outline of points lying in one plane with a monotone chain
+fn planar_hull(points: &[Vector3<f64>], triangle: [usize; 3], epsilon: f64) -> Vec<usize> {
+    let [a, b, c] = triangle.map(|index| points[index]);
+    let u = (b - a).normalize();
+    let v = (b - a).cross(c - a).cross(b - a).normalize();
+
+    let projected = points
+        .iter()
+        .map(|point| ((point - a).dot(u), (point - a).dot(v)))
+        .collect::<Vec<_>>();
+
+    let mut order = (0..points.len()).collect::<Vec<_>>();
+    order.sort_by(|&i, &j| {
+        projected[i]
+            .0
+            .total_cmp(&projected[j].0)
+            .then(projected[i].1.total_cmp(&projected[j].1))
+    });
+
+    let turn = |o: usize, p: usize, q: usize| {
+        let (o, p, q) = (projected[o], projected[p], projected[q]);
+        (p.0 - o.0) * (q.1 - o.1) - (p.1 - o.1) * (q.0 - o.0)
+    };
+
+    let chain = |indices: &mut dyn Iterator<Item = usize>| {
+        let mut chain: Vec<usize> = Vec::new();
+
+        for index in indices {
+            while chain.len() >= 2
+                && turn(chain[chain.len() - 2], chain[chain.len() - 1], index) <= epsilon * epsilon
+            {
+                chain.pop();
+            }
+
+            chain.push(index);
+        }
+
+        chain.pop();
+        chain
+    };
+
+    let mut outline = chain(&mut order.iter().copied());
+    outline.append(&mut chain(&mut order.iter().rev().copied()));
+    outline
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(mut points: Vec<Position>) -> Vec<Position> {
+        points.sort_by(|a, b| {
+            a.iter()
+                .zip(b.iter())
+                .map(|(a, b)| a.total_cmp(b))
+                .find(|order| order.is_ne())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        points
+    }
+
+    fn cube_corners() -> Vec<Position> {
+        let mut corners = Vec::new();
+        for x in [-1.0, 1.0] {
+            for y in [-1.0, 1.0] {
+                for z in [-1.0, 1.0] {
+                    corners.push([x, y, z]);
+                }
+            }
+        }
+        corners
+    }
+
+    #[test]
+    fn test_hull_drops_inside_and_face_points() {
+        let mut points = cube_corners();
+        // Inside the cube, on a face and on an edge
+        points.extend([
+            [0.0, 0.0, 0.0],
+            [0.3, -0.2, 0.5],
+            [1.0, 0.2, 0.4],
+            [1.0, 1.0, 0.0],
+        ]);
+
+        assert_eq!(sorted(hull_points(&points)), sorted(cube_corners()));
+    }
+
+    #[test]
+    fn test_hull_of_sphere_keeps_every_point() {
+        // Points on a sphere are all corners of their hull, which a fixed set
+        // of sampled directions would miss
+        let golden_angle = std::f32::consts::PI * (3.0 - 5.0_f32.sqrt());
+        let points = (0..500)
+            .map(|index| {
+                let y = 1.0 - (index as f32 + 0.5) / 500.0 * 2.0;
+                let radius = (1.0 - y * y).sqrt();
+                let theta = golden_angle * index as f32;
+                [radius * theta.cos(), y, radius * theta.sin()]
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(hull_points(&points).len(), points.len());
+    }
+
+    #[test]
+    fn test_hull_of_flat_and_degenerate_points() {
+        let square = vec![
+            [0.0, 0.0, 0.0],
+            [2.0, 0.0, 0.0],
+            [2.0, 0.0, 2.0],
+            [0.0, 0.0, 2.0],
+        ];
+        let mut points = square.clone();
+        points.extend([[1.0, 0.0, 1.0], [1.0, 0.0, 0.0]]);
+
+        assert_eq!(sorted(hull_points(&points)), sorted(square));
+
+        let line = hull_points(&[[0.0, 1.0, 0.0], [0.0, 2.0, 0.0], [0.0, 3.0, 0.0]]);
+        assert_eq!(sorted(line), vec![[0.0, 1.0, 0.0], [0.0, 3.0, 0.0]]);
+
+        assert_eq!(hull_points(&[[1.0, 1.0, 1.0]; 3]), vec![[1.0, 1.0, 1.0]]);
+        assert!(hull_points(&[]).is_empty());
+    }
+}
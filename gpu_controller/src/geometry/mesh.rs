@@ -2,11 +2,24 @@ use std::{f32, sync::Arc};
 
 use anyhow::{Result, anyhow};
 use log::{info, warn};
-use wgpu::{Buffer, BufferUsages, IndexFormat, RenderPass, util::BufferInitDescriptor};
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, Buffer, BufferAddress, BufferUsages,
+    IndexFormat, RenderPass, util::BufferInitDescriptor,
+};
 
 use crate::{GpuController, defaults::VERTECIES_BUFFER_INDEX};
 
-use super::vertex::Vertex;
+use super::{
+    Position,
+    bounds::Bounds,
+    hull::hull_points,
+    quantization::{
+        QUANTIZATION_BIND_GROUP, QUANTIZATION_BIND_GROUP_LAYOUT_DESCRIPTOR, QuantizedVertex,
+        VertexEncoding, VertexQuantization,
+    },
+    vertex::Vertex,
+    vertex_layout::VertexLayout,
+};
 
 pub enum Mesh {
     Cpu {
@@ -14,6 +27,9 @@ pub enum Mesh {
 
         vertices: Vec<Vertex>,
         indices: Vec<u32>,
+        /// Keeps the positions and indices on the CPU once the mesh is
+        /// buffered, for convex hull and triangle mesh colliders built from it
+        collision_geometry: bool,
     },
     Gpu {
         gpu_controller: Arc<GpuController>,
@@ -22,11 +38,18 @@ pub enum Mesh {
         vertex_buffer: Buffer,
         index_buffer: Buffer,
         num_indices: u32,
+        bounds: Bounds,
+        // Positions and indices kept on the CPU for colliders, only when the
+        // mesh was buffered with its collision geometry
+        triangles: Option<Box<(Vec<Position>, Vec<u32>)>>,
+        layout: VertexLayout,
+        // Set when the vertices are stored as `QuantizedVertex`
+        quantization: Option<(VertexQuantization, BindGroup)>,
     },
 }
 
 // logic used in this crate
-trait Distance {
+pub(crate) trait Distance {
     fn dist(&self) -> f32;
 }
 
@@ -43,28 +66,56 @@ impl Mesh {
         vertices: &[Vertex],
         indices: &[u32],
     ) -> Self {
-        let vertices = Vec::from(vertices);
-        let indices = Vec::from(indices);
-
-        let vertex_buffer = gpu_controller.create_buffer_init(&BufferInitDescriptor {
-            label: Some(&format!("{} Vertex Buffer", label)),
-            contents: bytemuck::cast_slice(&vertices),
-            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
-        });
-
-        let index_buffer = gpu_controller.create_buffer_init(&BufferInitDescriptor {
-            label: Some(&format!("{} Index Buffer", label)),
-            contents: bytemuck::cast_slice(&indices),
-            usage: BufferUsages::INDEX | BufferUsages::COPY_DST,
-        });
-
-        Self::Gpu {
-            gpu_controller,
+        let mut mesh = Self::Cpu {
             label,
-            vertex_buffer,
-            index_buffer,
-            num_indices: indices.len() as u32,
-        }
+            vertices: Vec::from(vertices),
+            indices: Vec::from(indices),
+            collision_geometry: false,
+        };
+
+        mesh.buffer(gpu_controller);
+        mesh
+    }
+
+    /// Creates a mesh like `new` that keeps its triangles on the CPU, so
+    /// convex hull and triangle mesh colliders can be built from it.
+    pub fn new_collidable(
+        gpu_controller: Arc<GpuController>,
+        label: String,
+        vertices: &[Vertex],
+        indices: &[u32],
+    ) -> Self {
+        let mut mesh = Self::Cpu {
+            label,
+            vertices: Vec::from(vertices),
+            indices: Vec::from(indices),
+            collision_geometry: true,
+        };
+
+        mesh.buffer(gpu_controller);
+        mesh
+    }
+
+    /// Creates a mesh whose vertices are stored as `QuantizedVertex`, half the size of `Vertex`.
+    ///
+    /// Positions are stored within the bounds of the mesh, so large meshes
+    /// lose more precision than small ones. Quantized meshes are drawn by the
+    /// quantized pipelines, which decode the vertices in the vertex shader.
+    pub fn new_quantized(
+        gpu_controller: Arc<GpuController>,
+        label: String,
+        vertices: &[Vertex],
+        indices: &[u32],
+    ) -> Self {
+        let mut mesh = Self::Cpu {
+            label,
+            vertices: Vec::from(vertices),
+            indices: Vec::from(indices),
+            collision_geometry: false,
+        };
+
+        mesh.buffer_quantized(gpu_controller);
+        mesh
     }
 
     pub fn label(&self) -> &String {
@@ -74,12 +125,95 @@ impl Mesh {
         }
     }
 
+    /// Returns the bounds of the mesh in its local space.
+    ///
+    /// Bounds are computed once when the mesh is buffered, meshes still on
+    /// the CPU compute them on every call.
+    pub fn bounds(&self) -> Bounds {
+        match self {
+            Mesh::Cpu { vertices, .. } => Bounds::from_vertices(vertices),
+            Mesh::Gpu { bounds, .. } => *bounds,
+        }
+    }
+
+    /// Returns the layout of the vertices of the mesh.
+    ///
+    /// Meshes built from `Vertex` use `VertexLayout::standard`, meshes from a
+    /// `MeshBuilder` use the layout they were built with. Pipelines drawing
+    /// the mesh must be created with the same layout.
+    pub fn layout(&self) -> VertexLayout {
+        match self {
+            Mesh::Cpu { .. } => VertexLayout::standard(),
+            Mesh::Gpu { layout, .. } => layout.clone(),
+        }
+    }
+
+    /// Computes the corners of the convex hull of the mesh.
+    ///
+    /// Buffered meshes are only wrapped tightly when they kept their
+    /// collision geometry, as with `new_collidable`, the hull of any other
+    /// buffered mesh is its bounds.
+    pub fn hull_points(&self) -> Vec<Position> {
+        match self {
+            Mesh::Cpu { vertices, .. } => hull_points(&Self::positions(vertices)),
+            Mesh::Gpu {
+                label,
+                bounds,
+                triangles,
+                ..
+            } => match triangles {
+                Some(triangles) => hull_points(&triangles.0),
+                None => {
+                    warn!(
+                        "Mesh {} was buffered without its collision geometry, its hull is its bounds",
+                        label
+                    );
+                    bounds.corners().to_vec()
+                }
+            },
+        }
+    }
+
+    /// Gives the positions and indices of the triangles of the mesh, such as
+    /// to build a triangle mesh collider from it.
+    ///
+    /// Buffered meshes only keep their triangles on the CPU when they were
+    /// created with their collision geometry, as with `new_collidable`.
+    ///
+    /// # Returns
+    /// The result of the callback, or an error if the mesh was buffered
+    /// without its triangles
+    pub fn triangles<F, R>(&self, callback: F) -> Result<R>
+    where
+        F: FnOnce(&[Position], &[u32]) -> R,
+    {
+        match self {
+            Mesh::Cpu {
+                vertices, indices, ..
+            } => Ok(callback(&Self::positions(vertices), indices)),
+            Mesh::Gpu {
+                label, triangles, ..
+            } => match triangles {
+                Some(triangles) => Ok(callback(&triangles.0, &triangles.1)),
+                None => Err(anyhow!(
+                    "Mesh {} was buffered without its collision geometry",
+                    label
+                )),
+            },
+        }
+    }
+
+    fn positions(vertices: &[Vertex]) -> Vec<Position> {
+        vertices.iter().map(|vertex| vertex.position).collect()
+    }
+
     pub fn buffer(&mut self, gpu_controller: Arc<GpuController>) {
         match self {
             Self::Cpu {
                 label,
                 vertices,
                 indices,
+                collision_geometry,
             } => {
                 let vertex_buffer = gpu_controller.create_buffer_init(&BufferInitDescriptor {
                     label: Some(&format!("{} Vertex Buffer", label)),
@@ -99,6 +233,11 @@ impl Mesh {
                     vertex_buffer,
                     index_buffer,
                     num_indices: indices.len() as u32,
+                    bounds: Bounds::from_vertices(vertices),
+                    triangles: collision_geometry
+                        .then(|| Box::new((Self::positions(vertices), std::mem::take(indices)))),
+                    layout: VertexLayout::standard(),
+                    quantization: None,
                 };
             }
             Self::Gpu { .. } => {}
@@ -107,6 +246,92 @@ impl Mesh {
         info!("New mesh has been buffered");
     }
 
+    /// Buffers the mesh like `buffer`, storing its vertices as `QuantizedVertex`
+    pub fn buffer_quantized(&mut self, gpu_controller: Arc<GpuController>) {
+        match self {
+            Self::Cpu {
+                label,
+                vertices,
+                indices,
+                collision_geometry,
+            } => {
+                let bounds = Bounds::from_vertices(vertices);
+                let quantization = VertexQuantization::from_bounds(&bounds);
+
+                let quantized_vertices = vertices
+                    .iter()
+                    .map(|vertex| quantization.quantize(vertex))
+                    .collect::<Vec<QuantizedVertex>>();
+
+                let vertex_buffer = gpu_controller.create_buffer_init(&BufferInitDescriptor {
+                    label: Some(&format!("{} Quantized Vertex Buffer", label)),
+                    contents: bytemuck::cast_slice(&quantized_vertices),
+                    usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+                });
+
+                let index_buffer = gpu_controller.create_buffer_init(&BufferInitDescriptor {
+                    label: Some(&format!("{} Index Buffer", label)),
+                    contents: bytemuck::cast_slice(indices),
+                    usage: BufferUsages::INDEX | BufferUsages::COPY_DST,
+                });
+
+                let quantization_buffer =
+                    gpu_controller.create_buffer_init(&BufferInitDescriptor {
+                        label: Some(&format!("{} Quantization Buffer", label)),
+                        contents: bytemuck::cast_slice(&[quantization]),
+                        usage: BufferUsages::UNIFORM,
+                    });
+
+                let quantization_bind_group_layout = gpu_controller
+                    .create_bind_group_layout(&QUANTIZATION_BIND_GROUP_LAYOUT_DESCRIPTOR);
+
+                let quantization_bind_group =
+                    gpu_controller.create_bind_group(&BindGroupDescriptor {
+                        label: Some(&format!("{} Quantization Bind Group", label)),
+                        layout: &quantization_bind_group_layout,
+                        entries: &[BindGroupEntry {
+                            binding: 0,
+                            resource: quantization_buffer.as_entire_binding(),
+                        }],
+                    });
+
+                *self = Self::Gpu {
+                    gpu_controller,
+                    label: label.clone(),
+                    vertex_buffer,
+                    index_buffer,
+                    num_indices: indices.len() as u32,
+                    bounds,
+                    triangles: collision_geometry
+                        .then(|| Box::new((Self::positions(vertices), std::mem::take(indices)))),
+                    layout: VertexLayout::quantized(),
+                    quantization: Some((quantization, quantization_bind_group)),
+                };
+            }
+            Self::Gpu { .. } => {}
+        }
+
+        info!("New quantized mesh has been buffered");
+    }
+
+    /// Returns the box the positions of a quantized mesh are stored within,
+    /// `None` if the mesh has full precision vertices
+    pub fn quantization(&self) -> Option<VertexQuantization> {
+        match self {
+            Self::Cpu { .. } => None,
+            Self::Gpu { quantization, .. } => {
+                quantization.as_ref().map(|(quantization, _)| *quantization)
+            }
+        }
+    }
+
+    pub fn encoding(&self) -> VertexEncoding {
+        match self.quantization() {
+            Some(_) => VertexEncoding::Quantized,
+            None => VertexEncoding::Full,
+        }
+    }
+
     pub fn vertices<F, R>(&mut self, vertices_callback: F) -> Result<R>
     where
         F: FnOnce(&mut Vec<Vertex>) -> R,
@@ -150,12 +375,58 @@ impl Mesh {
                 vertex_buffer,
                 index_buffer,
                 num_indices,
+                quantization,
                 ..
             } => {
+                if let Some((_, bind_group)) = quantization {
+                    render_pass.set_bind_group(QUANTIZATION_BIND_GROUP, bind_group, &[]);
+                }
+
                 render_pass.set_vertex_buffer(VERTECIES_BUFFER_INDEX, vertex_buffer.slice(..));
                 render_pass.set_index_buffer(index_buffer.slice(..), IndexFormat::Uint32);
                 render_pass.draw_indexed(0..*num_indices, 0, 0..num_instances);
             }
         }
     }
+
+    /// Renders the mesh with draw arguments written by the GPU.
+    ///
+    /// # Arguments
+    /// * `render_pass` - The render pass to draw in
+    /// * `indirect_buffer` - Buffer holding `DrawIndexedIndirectArgs`
+    /// * `indirect_offset` - Byte offset of the arguments of this mesh in the buffer
+    pub fn render_indirect(
+        &self,
+        render_pass: &mut RenderPass,
+        indirect_buffer: &Buffer,
+        indirect_offset: BufferAddress,
+    ) {
+        match self {
+            Self::Cpu { .. } => {
+                warn!("Mesh in unbuffered CPU state, not rendered");
+            }
+            Self::Gpu {
+                vertex_buffer,
+                index_buffer,
+                quantization,
+                ..
+            } => {
+                if let Some((_, bind_group)) = quantization {
+                    render_pass.set_bind_group(QUANTIZATION_BIND_GROUP, bind_group, &[]);
+                }
+
+                render_pass.set_vertex_buffer(VERTECIES_BUFFER_INDEX, vertex_buffer.slice(..));
+                render_pass.set_index_buffer(index_buffer.slice(..), IndexFormat::Uint32);
+                render_pass.draw_indexed_indirect(indirect_buffer, indirect_offset);
+            }
+        }
+    }
+
+    /// Returns the number of indices drawn when rendering the mesh
+    pub fn num_indices(&self) -> u32 {
+        match self {
+            Self::Cpu { indices, .. } => indices.len() as u32,
+            Self::Gpu { num_indices, .. } => *num_indices,
+        }
+    }
 }
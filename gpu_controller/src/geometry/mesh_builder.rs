@@ -0,0 +1,199 @@
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow};
+use wgpu::{BufferUsages, util::BufferInitDescriptor};
+
+use crate::GpuController;
+
+use super::{
+    NormalVec, Position, UvCoord,
+    bounds::Bounds,
+    mesh::Mesh,
+    vertex_layout::{VertexAttributeKind, VertexLayout},
+};
+
+/// Builds a mesh whose vertices follow a custom `VertexLayout`.
+///
+/// Every attribute of the layout is given as its own list with one value
+/// per vertex, the builder interleaves them into a single vertex buffer.
+///
+/// # Example
+/// ```ignore
+/// let mesh = MeshBuilder::new("Terrain", layout)
+///     .positions(positions)
+///     .uv_coords(uv_coords)
+///     .normals(normals)
+///     .colors(paint)
+///     .indices(indices)
+///     .build(gpu_controller)?;
+/// ```
+pub struct MeshBuilder {
+    label: String,
+    layout: VertexLayout,
+    positions: Vec<Position>,
+    uv_coords: Vec<UvCoord>,
+    normals: Vec<NormalVec>,
+    colors: Vec<[f32; 4]>,
+    second_uv_coords: Vec<UvCoord>,
+    weights: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+    collision_geometry: bool,
+}
+
+impl MeshBuilder {
+    pub fn new(label: impl Into<String>, layout: VertexLayout) -> Self {
+        Self {
+            label: label.into(),
+            layout,
+            positions: Vec::new(),
+            uv_coords: Vec::new(),
+            normals: Vec::new(),
+            colors: Vec::new(),
+            second_uv_coords: Vec::new(),
+            weights: Vec::new(),
+            indices: Vec::new(),
+            collision_geometry: false,
+        }
+    }
+
+    pub fn positions(mut self, positions: Vec<Position>) -> Self {
+        self.positions = positions;
+        self
+    }
+
+    pub fn uv_coords(mut self, uv_coords: Vec<UvCoord>) -> Self {
+        self.uv_coords = uv_coords;
+        self
+    }
+
+    pub fn normals(mut self, normals: Vec<NormalVec>) -> Self {
+        self.normals = normals;
+        self
+    }
+
+    pub fn colors(mut self, colors: Vec<[f32; 4]>) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    pub fn second_uv_coords(mut self, second_uv_coords: Vec<UvCoord>) -> Self {
+        self.second_uv_coords = second_uv_coords;
+        self
+    }
+
+    pub fn weights(mut self, weights: Vec<[f32; 4]>) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    pub fn indices(mut self, indices: Vec<u32>) -> Self {
+        self.indices = indices;
+        self
+    }
+
+    /// Keeps the positions and indices on the CPU once the mesh is built, so
+    /// convex hull and triangle mesh colliders can be built from it
+    pub fn with_collision_geometry(mut self) -> Self {
+        self.collision_geometry = true;
+        self
+    }
+
+    /// Returns the values given for an attribute, flattened to `f32`s
+    fn attribute_data(&self, kind: VertexAttributeKind) -> Vec<f32> {
+        match kind {
+            VertexAttributeKind::Position => self.positions.concat(),
+            VertexAttributeKind::UvCoord => self.uv_coords.concat(),
+            VertexAttributeKind::Normal => self.normals.concat(),
+            VertexAttributeKind::Color => self.colors.concat(),
+            VertexAttributeKind::SecondUvCoord => self.second_uv_coords.concat(),
+            VertexAttributeKind::Weights => self.weights.concat(),
+        }
+    }
+
+    /// Interleaves the attributes into the contents of the vertex buffer
+    ///
+    /// # Returns
+    /// The vertex data, or an error if an attribute of the layout doesn't
+    /// have one value for every vertex
+    pub(crate) fn interleave(&self) -> Result<Vec<f32>> {
+        let vertex_count = self.positions.len();
+
+        let attributes = self
+            .layout
+            .kinds()
+            .iter()
+            .map(|kind| {
+                let data = self.attribute_data(*kind);
+
+                if data.len() != vertex_count * kind.components() {
+                    return Err(anyhow!(
+                        "Mesh {} has {} vertices but {} values for {:?}",
+                        self.label,
+                        vertex_count,
+                        data.len() / kind.components(),
+                        kind
+                    ));
+                }
+
+                Ok((data, kind.components()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut vertices = Vec::with_capacity(self.layout.stride() as usize / 4 * vertex_count);
+        for vertex in 0..vertex_count {
+            for (data, components) in attributes.iter() {
+                vertices.extend_from_slice(&data[vertex * components..(vertex + 1) * components]);
+            }
+        }
+
+        Ok(vertices)
+    }
+
+    /// Uploads the mesh to the GPU.
+    ///
+    /// # Returns
+    /// The buffered mesh, or an error if the attributes or indices don't
+    /// match the number of vertices
+    pub fn build(self, gpu_controller: Arc<GpuController>) -> Result<Mesh> {
+        let vertices = self.interleave()?;
+
+        if let Some(index) = self
+            .indices
+            .iter()
+            .find(|index| **index as usize >= self.positions.len())
+        {
+            return Err(anyhow!(
+                "Mesh {} has an index {} past its {} vertices",
+                self.label,
+                index,
+                self.positions.len()
+            ));
+        }
+
+        let vertex_buffer = gpu_controller.create_buffer_init(&BufferInitDescriptor {
+            label: Some(&format!("{} Vertex Buffer", self.label)),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        });
+
+        let index_buffer = gpu_controller.create_buffer_init(&BufferInitDescriptor {
+            label: Some(&format!("{} Index Buffer", self.label)),
+            contents: bytemuck::cast_slice(&self.indices),
+            usage: BufferUsages::INDEX | BufferUsages::COPY_DST,
+        });
+
+        Ok(Mesh::Gpu {
+            gpu_controller,
+            vertex_buffer,
+            index_buffer,
+            num_indices: self.indices.len() as u32,
+            bounds: Bounds::from_positions(&self.positions),
+            triangles: self
+                .collision_geometry
+                .then(|| Box::new((self.positions, self.indices))),
+            layout: self.layout,
+            label: self.label,
+            quantization: None,
+        })
+    }
+}
@@ -1,6 +1,11 @@
+pub mod bounds;
+pub mod hull;
 pub mod instance;
 pub mod mesh;
+pub mod mesh_builder;
+pub mod quantization;
 pub mod vertex;
+pub mod vertex_layout;
 
 pub type Position = [f32; 3];
 pub type UvCoord = [f32; 2];
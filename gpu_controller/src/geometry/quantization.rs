@@ -0,0 +1,295 @@
+use std::mem;
+
+use wgpu::{
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType, BufferAddress, BufferBindingType,
+    ShaderStages, VertexAttribute, VertexBufferLayout, VertexFormat, VertexStepMode,
+};
+
+use crate::Buffered;
+
+use super::{Position, bounds::Bounds, vertex::Vertex};
+
+/// Bind group quantized meshes bind their `VertexQuantization` to while drawing
+pub const QUANTIZATION_BIND_GROUP: u32 = 3;
+
+pub const QUANTIZATION_BIND_GROUP_LAYOUT_DESCRIPTOR: BindGroupLayoutDescriptor =
+    BindGroupLayoutDescriptor {
+        label: Some("Vertex Quantization Bind Group Layout"),
+        entries: &[BindGroupLayoutEntry {
+            binding: 0,
+            visibility: ShaderStages::VERTEX,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+    };
+
+/// How the vertices of a mesh are stored, each is drawn by its own pipelines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VertexEncoding {
+    /// Stored as `Vertex`
+    Full,
+    /// Stored as `QuantizedVertex`
+    Quantized,
+}
+
+/// A vertex compressed to half the size of a `Vertex`.
+///
+/// * Positions are 16-bit normalized within the bounds of their mesh
+/// * UV coordinates are half precision floats
+/// * Normals are octahedral encoded into two 16-bit normalized values
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct QuantizedVertex {
+    // The fourth component is unused, it pads the position to a vertex format
+    pub position: [u16; 4],
+    pub uv_coord: [u16; 2],
+    pub normal_vec: [i16; 2],
+}
+
+impl Buffered for QuantizedVertex {
+    fn desc() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: mem::size_of::<QuantizedVertex>() as BufferAddress,
+            step_mode: VertexStepMode::Vertex,
+            attributes: &[
+                // Position
+                VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: VertexFormat::Unorm16x4,
+                },
+                // UV Coordinates
+                VertexAttribute {
+                    offset: mem::size_of::<[u16; 4]>() as BufferAddress,
+                    shader_location: 1,
+                    format: VertexFormat::Float16x2,
+                },
+                // Normal Vector
+                VertexAttribute {
+                    offset: (mem::size_of::<[u16; 4]>() + mem::size_of::<[u16; 2]>())
+                        as BufferAddress,
+                    shader_location: 2,
+                    format: VertexFormat::Snorm16x2,
+                },
+            ],
+        }
+    }
+}
+
+/// The box the positions of a quantized mesh are stored within, as laid out
+/// in the shaders that decode them.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct VertexQuantization {
+    // The fourth components are unused
+    offset: [f32; 4],
+    scale: [f32; 4],
+}
+
+impl VertexQuantization {
+    /// Creates the quantization that covers the bounds with the full 16-bit range
+    pub fn from_bounds(bounds: &Bounds) -> Self {
+        let [min_x, min_y, min_z] = bounds.min;
+        let scale = [0, 1, 2].map(|axis| bounds.max[axis] - bounds.min[axis]);
+
+        Self {
+            offset: [min_x, min_y, min_z, 0.0],
+            scale: [scale[0], scale[1], scale[2], 0.0],
+        }
+    }
+
+    /// Compresses a vertex, positions outside the bounds are clamped to them
+    pub fn quantize(&self, vertex: &Vertex) -> QuantizedVertex {
+        let position = [0, 1, 2].map(|axis| {
+            let t = if self.scale[axis] > 0.0 {
+                (vertex.position[axis] - self.offset[axis]) / self.scale[axis]
+            } else {
+                0.0
+            };
+
+            (t.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16
+        });
+
+        QuantizedVertex {
+            position: [position[0], position[1], position[2], 0],
+            uv_coord: vertex.uv_coord.map(f32_to_f16),
+            normal_vec: octahedral_encode(vertex.normal_vec),
+        }
+    }
+
+    /// Decompresses a vertex the way the shaders do
+    pub fn dequantize(&self, vertex: &QuantizedVertex) -> Vertex {
+        let position: Position = [0, 1, 2].map(|axis| {
+            self.offset[axis] + vertex.position[axis] as f32 / u16::MAX as f32 * self.scale[axis]
+        });
+
+        Vertex {
+            position,
+            uv_coord: vertex.uv_coord.map(f16_to_f32),
+            normal_vec: octahedral_decode(vertex.normal_vec),
+        }
+    }
+}
+
+fn snorm16(value: f32) -> i16 {
+    (value.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16
+}
+
+/// Folds a unit vector onto an octahedron and flattens it to two values
+fn octahedral_encode(normal: [f32; 3]) -> [i16; 2] {
+    let length = normal[0].abs() + normal[1].abs() + normal[2].abs();
+    if length <= f32::EPSILON {
+        return [0, 0];
+    }
+
+    let [x, y, z] = normal.map(|component| component / length);
+
+    // The lower half of the octahedron is folded over the upper half
+    let (x, y) = if z < 0.0 {
+        ((1.0 - y.abs()) * x.signum(), (1.0 - x.abs()) * y.signum())
+    } else {
+        (x, y)
+    };
+
+    [snorm16(x), snorm16(y)]
+}
+
+fn octahedral_decode(encoded: [i16; 2]) -> [f32; 3] {
+    let [x, y] = encoded.map(|value| (value as f32 / i16::MAX as f32).max(-1.0));
+    let z = 1.0 - x.abs() - y.abs();
+
+    // Unfolds the lower half of the octahedron
+    let t = (-z).max(0.0);
+    let x = x - t * x.signum();
+    let y = y - t * y.signum();
+
+    let length = (x * x + y * y + z * z).sqrt();
+    [x / length, y / length, z / length]
+}
+
+/// Converts to a half precision float, rounding to the nearest value
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x007f_ffff;
+
+    // Infinity and NaN
+    if exponent == 0xff {
+        return sign | 0x7c00 | if mantissa != 0 { 0x0200 } else { 0 };
+    }
+
+    let exponent = exponent - 127 + 15;
+
+    // Too large, becomes infinity
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    // Too small for a normal half, becomes subnormal or zero
+    if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+
+        let mantissa = mantissa | 0x0080_0000;
+        let shift = (14 - exponent) as u32;
+        let half = mantissa >> shift;
+        let round = (mantissa >> (shift - 1)) & 1;
+
+        return sign | (half + round) as u16;
+    }
+
+    let half = ((exponent as u32) << 10) | (mantissa >> 13);
+    let round = (mantissa >> 12) & 1;
+
+    // Rounding can carry into the exponent, which is still the right value
+    sign | (half + round) as u16
+}
+
+fn f16_to_f32(value: u16) -> f32 {
+    let sign = ((value & 0x8000) as u32) << 16;
+    let exponent = ((value >> 10) & 0x1f) as u32;
+    let mantissa = (value & 0x03ff) as u32;
+
+    let bits = match exponent {
+        0 if mantissa == 0 => sign,
+        // Subnormal, scaled by the smallest normal exponent
+        0 => {
+            let magnitude = (mantissa as f32 / 1024.0) * 2f32.powi(-14);
+            return if sign != 0 { -magnitude } else { magnitude };
+        }
+        0x1f => sign | 0x7f80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 127 - 15) << 23) | (mantissa << 13),
+    };
+
+    f32::from_bits(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quantized_vertex_is_half_size() {
+        assert_eq!(
+            mem::size_of::<QuantizedVertex>() * 2,
+            mem::size_of::<Vertex>()
+        );
+    }
+
+    #[test]
+    fn test_position_round_trip() {
+        let bounds = Bounds::from_positions(&[[-2.0, 0.0, -8.0], [2.0, 4.0, 8.0]]);
+        let quantization = VertexQuantization::from_bounds(&bounds);
+
+        let vertex = Vertex::new([1.3, 2.71, -5.5], [0.25, 0.75], [0.0, 1.0, 0.0]);
+        let decoded = quantization.dequantize(&quantization.quantize(&vertex));
+
+        for axis in 0..3 {
+            let step = (bounds.max[axis] - bounds.min[axis]) / u16::MAX as f32;
+            assert!((decoded.position[axis] - vertex.position[axis]).abs() <= step);
+        }
+    }
+
+    #[test]
+    fn test_normal_round_trip() {
+        let normals: [[f32; 3]; 6] = [
+            [0.0, 0.0, 1.0],
+            [0.0, 0.0, -1.0],
+            [1.0, 0.0, 0.0],
+            [0.0, -1.0, 0.0],
+            [0.577, -0.577, -0.577],
+            [-0.267, 0.535, 0.802],
+        ];
+
+        for normal in normals {
+            let length =
+                (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt();
+            let normal = normal.map(|component: f32| component / length);
+            let decoded = octahedral_decode(octahedral_encode(normal));
+
+            let dot = (0..3).map(|axis| normal[axis] * decoded[axis]).sum::<f32>();
+            assert!(dot > 0.9999, "{:?} decoded as {:?}", normal, decoded);
+        }
+    }
+
+    #[test]
+    fn test_half_float_round_trip() {
+        for value in [0.0, 1.0, -1.0, 0.5, 3.25, 1000.0, -0.001, 65504.0] {
+            let decoded = f16_to_f32(f32_to_f16(value));
+            assert!(
+                (decoded - value).abs() <= value.abs() / 1024.0,
+                "{} decoded as {}",
+                value,
+                decoded
+            );
+        }
+
+        assert_eq!(f16_to_f32(f32_to_f16(1e6)), f32::INFINITY);
+    }
+}
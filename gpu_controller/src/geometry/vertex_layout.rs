@@ -0,0 +1,182 @@
+use anyhow::{Result, anyhow};
+use wgpu::{BufferAddress, VertexAttribute, VertexBufferLayout, VertexFormat, VertexStepMode};
+
+use crate::Buffered;
+
+use super::{quantization::QuantizedVertex, vertex::Vertex};
+
+/// A per-vertex value a `VertexLayout` can contain.
+///
+/// Every kind is always bound to the same shader location so shaders can be
+/// written against the kinds without knowing the layout of the buffer.
+/// Locations 3 and 4 belong to the `Instance` buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VertexAttributeKind {
+    /// `vec3<f32>` at location 0
+    Position,
+    /// `vec2<f32>` at location 1
+    UvCoord,
+    /// `vec3<f32>` at location 2
+    Normal,
+    /// `vec4<f32>` at location 5, such as vertex painted colors or blend weights of terrain layers
+    Color,
+    /// `vec2<f32>` at location 6, such as a lightmap or detail UV set
+    SecondUvCoord,
+    /// `vec4<f32>` at location 7, such as skinning weights
+    Weights,
+}
+
+impl VertexAttributeKind {
+    pub fn shader_location(&self) -> u32 {
+        match self {
+            Self::Position => 0,
+            Self::UvCoord => 1,
+            Self::Normal => 2,
+            Self::Color => 5,
+            Self::SecondUvCoord => 6,
+            Self::Weights => 7,
+        }
+    }
+
+    pub fn format(&self) -> VertexFormat {
+        match self {
+            Self::Position | Self::Normal => VertexFormat::Float32x3,
+            Self::UvCoord | Self::SecondUvCoord => VertexFormat::Float32x2,
+            Self::Color | Self::Weights => VertexFormat::Float32x4,
+        }
+    }
+
+    /// Number of `f32`s the attribute takes up in a vertex
+    pub fn components(&self) -> usize {
+        self.format().size() as usize / std::mem::size_of::<f32>()
+    }
+}
+
+/// The attributes of every vertex in a vertex buffer, in the order they are stored.
+///
+/// Layouts compare and hash by their attributes, so they can be used as part
+/// of the key of pipelines built for them.
+///
+/// # Example
+/// ```ignore
+/// // Terrain that blends its texture layers by a painted color
+/// let layout = VertexLayout::new(&[
+///     VertexAttributeKind::Position,
+///     VertexAttributeKind::UvCoord,
+///     VertexAttributeKind::Normal,
+///     VertexAttributeKind::Color,
+/// ])?;
+///
+/// let pipeline = gpu_controller.create_render_pipeline(&RenderPipelineDescriptor {
+///     vertex: VertexState {
+///         buffers: &[layout.desc(), Instance::desc()],
+///         ..
+///     },
+///     ..
+/// });
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VertexLayout {
+    kinds: Vec<VertexAttributeKind>,
+    attributes: Vec<VertexAttribute>,
+    stride: BufferAddress,
+}
+
+impl VertexLayout {
+    /// Creates a layout with the attributes stored in the given order.
+    ///
+    /// # Arguments
+    /// * `kinds` - The attributes of a vertex, must contain a position and no duplicates
+    ///
+    /// # Returns
+    /// The layout, or an error if the attributes are not valid
+    pub fn new(kinds: &[VertexAttributeKind]) -> Result<Self> {
+        if !kinds.contains(&VertexAttributeKind::Position) {
+            return Err(anyhow!("Vertex layout needs a position"));
+        }
+
+        for (index, kind) in kinds.iter().enumerate() {
+            if kinds[..index].contains(kind) {
+                return Err(anyhow!("Vertex layout contains {:?} more than once", kind));
+            }
+        }
+
+        let mut offset = 0;
+        let attributes = kinds
+            .iter()
+            .map(|kind| {
+                let attribute = VertexAttribute {
+                    offset,
+                    shader_location: kind.shader_location(),
+                    format: kind.format(),
+                };
+                offset += kind.format().size();
+                attribute
+            })
+            .collect();
+
+        Ok(Self {
+            kinds: kinds.to_vec(),
+            attributes,
+            stride: offset,
+        })
+    }
+
+    /// The layout of `Vertex`, used by every mesh that doesn't declare its own
+    pub fn standard() -> Self {
+        let desc = Vertex::desc();
+
+        Self {
+            kinds: vec![
+                VertexAttributeKind::Position,
+                VertexAttributeKind::UvCoord,
+                VertexAttributeKind::Normal,
+            ],
+            attributes: desc.attributes.to_vec(),
+            stride: desc.array_stride,
+        }
+    }
+
+    /// The layout of `QuantizedVertex`, used by quantized meshes
+    pub fn quantized() -> Self {
+        let desc = QuantizedVertex::desc();
+
+        Self {
+            kinds: vec![
+                VertexAttributeKind::Position,
+                VertexAttributeKind::UvCoord,
+                VertexAttributeKind::Normal,
+            ],
+            attributes: desc.attributes.to_vec(),
+            stride: desc.array_stride,
+        }
+    }
+
+    pub fn kinds(&self) -> &[VertexAttributeKind] {
+        &self.kinds
+    }
+
+    pub fn contains(&self, kind: VertexAttributeKind) -> bool {
+        self.kinds.contains(&kind)
+    }
+
+    /// Size of a vertex in bytes
+    pub fn stride(&self) -> BufferAddress {
+        self.stride
+    }
+
+    /// Returns the layout in the form a render pipeline takes
+    pub fn desc(&self) -> VertexBufferLayout<'_> {
+        VertexBufferLayout {
+            array_stride: self.stride,
+            step_mode: VertexStepMode::Vertex,
+            attributes: &self.attributes,
+        }
+    }
+}
+
+impl Default for VertexLayout {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
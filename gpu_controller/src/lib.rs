@@ -35,44 +35,72 @@ const DESIRED_MAX_FRAME_LATENCY: u32 = 2;
 use std::{
     borrow::Cow,
     collections::HashMap,
-    sync::{Arc, RwLock},
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
 };
 
 use anyhow::{Result, anyhow};
-use defaults::DEFAULT_SURFACE_CONFIGURATION;
+use defaults::{DEFAULT_BACKENDS, DEFAULT_SURFACE_CONFIGURATION};
 pub use defaults::{INSTANCE_BUFFER_INDEX, VERTECIES_BUFFER_INDEX};
-use log::info;
+use log::{info, warn};
 use wgpu::{
-    Adapter, Backends, Device, DeviceDescriptor, InstanceDescriptor, MemoryHints, PipelineLayout,
-    PollError, PollStatus, PowerPreference, Queue, RequestAdapterOptionsBase, Trace,
-    VertexBufferLayout, util::DeviceExt,
+    Adapter, Device, DeviceDescriptor, InstanceDescriptor, MemoryHints, PollError, PollStatus,
+    PowerPreference, Queue, RequestAdapterOptionsBase, Trace, util::DeviceExt,
 };
 
 // public re-exports
-pub use geometry::{instance::Instance, mesh::Mesh, vertex::Vertex};
+pub use geometry::{
+    bounds::Bounds,
+    hull::hull_points,
+    instance::Instance,
+    mesh::Mesh,
+    mesh_builder::MeshBuilder,
+    quantization::{
+        QUANTIZATION_BIND_GROUP, QUANTIZATION_BIND_GROUP_LAYOUT_DESCRIPTOR, QuantizedVertex,
+        VertexEncoding, VertexQuantization,
+    },
+    vertex::Vertex,
+    vertex_layout::{VertexAttributeKind, VertexLayout},
+};
+pub use per_frame::{FRAMES_IN_FLIGHT, PerFrame};
+pub use pipeline_cache::PipelineCache;
+pub use shader_preprocessor::{ShaderFileSystem, ShaderPreprocessor};
+pub use stats::GpuStats;
+pub use texture_array::{
+    TEXTURE_ARRAY_BIND_GROUP_LAYOUT_DESCRIPTOR, TextureArray, TextureArrayBuilder,
+};
 pub use wgpu::{
     AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
     BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, BlendComponent,
-    BlendFactor, BlendOperation, BlendState, Buffer, BufferBindingType, BufferDescriptor,
-    BufferUsages, Color, ColorTargetState, ColorWrites, CommandEncoder, CommandEncoderDescriptor,
-    CompareFunction, CompositeAlphaMode, ComputePassDescriptor, ComputePipeline,
-    ComputePipelineDescriptor, DepthBiasState, DepthStencilState, Extent3d, Face, Features,
-    FilterMode, FragmentState, FrontFace, IndexFormat, Limits, LoadOp, MaintainBase, MapMode,
-    MultisampleState, Operations, Origin3d, PipelineCompilationOptions, PipelineLayoutDescriptor,
-    PolygonMode, PresentMode, PrimitiveState, PrimitiveTopology, RenderPass,
-    RenderPassColorAttachment, RenderPassDepthStencilAttachment, RenderPassDescriptor,
-    RenderPipeline, RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor,
-    ShaderModule, ShaderModuleDescriptor, ShaderSource, ShaderStages, StencilState,
-    StorageTextureAccess, StoreOp, Surface, SurfaceConfiguration, SurfaceTexture,
+    BlendFactor, BlendOperation, BlendState, Buffer, BufferAddress, BufferBindingType,
+    BufferDescriptor, BufferUsages, Color, ColorTargetState, ColorWrites, CommandEncoder,
+    CommandEncoderDescriptor, CompareFunction, CompositeAlphaMode, ComputePass,
+    ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor, DepthBiasState,
+    DepthStencilState, Extent3d, Face, Features, FilterMode, FragmentState, FrontFace, IndexFormat,
+    Limits, LoadOp, MaintainBase, MapMode, MultisampleState, Operations, Origin3d,
+    PipelineCompilationOptions, PipelineLayout, PipelineLayoutDescriptor, PolygonMode, PresentMode,
+    PrimitiveState, PrimitiveTopology, RenderPass, RenderPassColorAttachment,
+    RenderPassDepthStencilAttachment, RenderPassDescriptor, RenderPipeline,
+    RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor, ShaderModule,
+    ShaderModuleDescriptor, ShaderSource, ShaderStages, StencilFaceState, StencilOperation,
+    StencilState, StorageTextureAccess, StoreOp, Surface, SurfaceConfiguration, SurfaceTexture,
     TexelCopyBufferInfo, TexelCopyBufferLayout, TexelCopyTextureInfo, Texture, TextureAspect,
     TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
-    TextureView, TextureViewDescriptor, TextureViewDimension, VertexState,
-    util::BufferInitDescriptor,
+    TextureView, TextureViewDescriptor, TextureViewDimension, VertexAttribute, VertexBufferLayout,
+    VertexFormat, VertexState, VertexStepMode,
+    util::{BufferInitDescriptor, DrawIndexedIndirectArgs},
 };
 use winit::window::Window;
 
 mod defaults;
 mod geometry;
+mod per_frame;
+mod pipeline_cache;
+mod shader_preprocessor;
+mod stats;
+mod texture_array;
 
 /// The main GPU controller that manages all WGPU resources and provides a simplified interface
 /// for GPU operations.
@@ -104,6 +132,10 @@ pub struct GpuController {
     // Interior Mutability
     surface_configuration: RwLock<SurfaceConfiguration>,
     layouts: RwLock<HashMap<String, BindGroupLayout>>,
+    // Set while a graphics debugger is capturing, only one capture can be active
+    capturing: AtomicBool,
+    // Frames ended so far, picks the copy of `PerFrame` resources
+    frame_index: AtomicU64,
 }
 
 impl GpuController {
@@ -161,7 +193,7 @@ impl GpuController {
 
         // Initialize WGPU
         let instance = wgpu::Instance::new(&InstanceDescriptor {
-            backends: Backends::VULKAN | Backends::DX12 | Backends::METAL,
+            backends: DEFAULT_BACKENDS,
             ..Default::default()
         });
 
@@ -207,6 +239,8 @@ impl GpuController {
             queue,
             surface_configuration,
             layouts,
+            capturing: AtomicBool::new(false),
+            frame_index: AtomicU64::new(0),
         }))
     }
 
@@ -464,6 +498,33 @@ impl GpuController {
             .write_texture(texture.as_image_copy(), data, layout, size);
     }
 
+    /// Writes to a single mip level of a layer of a texture array
+    pub fn write_texture_layer(
+        &self,
+        texture: &Texture,
+        mip_level: u32,
+        layer: u32,
+        data: &[u8],
+        layout: TexelCopyBufferLayout,
+        size: Extent3d,
+    ) {
+        self.queue.write_texture(
+            TexelCopyTextureInfo {
+                texture,
+                mip_level,
+                origin: Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: layer,
+                },
+                aspect: TextureAspect::All,
+            },
+            data,
+            layout,
+            size,
+        );
+    }
+
     pub fn create_pipeline_layout(
         &self,
         pipeline_layout_descriptor: &PipelineLayoutDescriptor,
@@ -540,6 +601,83 @@ impl GpuController {
         self.device.poll(base)
     }
 
+    /// Starts capturing the GPU commands of this device in an attached graphics
+    /// debugger, such as RenderDoc or Xcode.
+    ///
+    /// Nothing is captured when the application wasn't started with a graphics
+    /// debugger attached. Every command recorded and submitted until
+    /// [`stop_frame_capture`](Self::stop_frame_capture) is part of the capture.
+    ///
+    /// ## Returns
+    ///
+    /// `false` if a capture is already active
+    pub fn start_frame_capture(&self) -> bool {
+        if self.capturing.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+
+        // Only one capture is active at a time, the rest is up to the debugger
+        unsafe { self.device.start_graphics_debugger_capture() };
+        true
+    }
+
+    /// Ends the capture started with [`start_frame_capture`](Self::start_frame_capture),
+    /// waiting for the submitted work to finish so the debugger sees all of it.
+    ///
+    /// ## Returns
+    ///
+    /// `false` if no capture is active
+    pub fn stop_frame_capture(&self) -> bool {
+        if !self.capturing.load(Ordering::Acquire) {
+            return false;
+        }
+
+        if let Err(err) = self.device.poll(MaintainBase::Wait) {
+            warn!("Failed to wait for the GPU before ending capture: {}", err);
+        }
+
+        unsafe { self.device.stop_graphics_debugger_capture() };
+        self.capturing.store(false, Ordering::Release);
+        true
+    }
+
+    pub fn is_capturing_frame(&self) -> bool {
+        self.capturing.load(Ordering::Acquire)
+    }
+
+    /// Returns the index of the frame being recorded, the number of frames
+    /// ended before it
+    #[inline]
+    pub fn frame_index(&self) -> u64 {
+        self.frame_index.load(Ordering::Acquire)
+    }
+
+    /// Ends the frame being recorded, call once its commands are submitted
+    /// and the surface is presented.
+    ///
+    /// `PerFrame` resources move on to their next copy, leaving the copy of
+    /// this frame alone until the GPU is done with it.
+    pub fn end_frame(&self) {
+        self.frame_index.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Counts the live GPU objects of the device and the memory they hold.
+    ///
+    /// The counts come from the registries of wgpu and are always kept, the
+    /// memory is only counted with the `counters` feature of this crate.
+    /// Collecting them walks every registry, so read them for a debug
+    /// overlay or around a load, not for every draw.
+    ///
+    /// ## Returns
+    ///
+    /// The stats, with counts of 0 on backends wgpu doesn't report on
+    pub fn stats(&self) -> GpuStats {
+        GpuStats::from_reports(
+            self.instance.generate_report().as_ref(),
+            &self.device.get_internal_counters(),
+        )
+    }
+
     pub fn device(&self) -> &Device {
         &self.device
     }
@@ -569,4 +707,73 @@ mod test {
     fn test_create_gpu_controller() {
         assert!(block_on(GpuController::new(None, None, None)).is_ok());
     }
+
+    /// Tests that bounds contain every point and that unions contain both inputs.
+    #[test]
+    fn test_bounds() {
+        let bounds = Bounds::from_positions(&[[-1.0, 0.0, 2.0], [3.0, -2.0, 4.0]]);
+
+        assert_eq!(bounds.min, [-1.0, -2.0, 2.0]);
+        assert_eq!(bounds.max, [3.0, 0.0, 4.0]);
+        assert_eq!(bounds.center, [1.0, -1.0, 3.0]);
+        assert!((bounds.radius - 6.0_f32.sqrt()).abs() < 1e-5);
+
+        let other = Bounds::from_positions(&[[10.0, 10.0, 10.0]]);
+        let union = bounds.union(&other);
+
+        assert_eq!(union.min, [-1.0, -2.0, 2.0]);
+        assert_eq!(union.max, [10.0, 10.0, 10.0]);
+        for corner in bounds.corners().iter().chain(other.corners().iter()) {
+            let distance = [0, 1, 2]
+                .map(|axis| corner[axis] - union.center[axis])
+                .iter()
+                .map(|component| component * component)
+                .sum::<f32>()
+                .sqrt();
+            assert!(distance <= union.radius + 1e-5);
+        }
+
+        assert_eq!(Bounds::from_positions(&[]), Bounds::default());
+    }
+
+    /// Tests that custom layouts match the standard vertex and interleave builder attributes.
+    #[test]
+    fn test_vertex_layout() {
+        assert_eq!(
+            VertexLayout::new(&[
+                VertexAttributeKind::Position,
+                VertexAttributeKind::UvCoord,
+                VertexAttributeKind::Normal,
+            ])
+            .unwrap(),
+            VertexLayout::standard()
+        );
+        assert!(VertexLayout::new(&[VertexAttributeKind::UvCoord]).is_err());
+        assert!(
+            VertexLayout::new(&[VertexAttributeKind::Position, VertexAttributeKind::Position])
+                .is_err()
+        );
+
+        let layout =
+            VertexLayout::new(&[VertexAttributeKind::Position, VertexAttributeKind::Color])
+                .unwrap();
+        assert_eq!(layout.stride(), 28);
+        assert_eq!(layout.desc().attributes[1].offset, 12);
+        assert_eq!(layout.desc().attributes[1].shader_location, 5);
+
+        let builder = MeshBuilder::new("Painted", layout.clone())
+            .positions(vec![[0.0, 1.0, 2.0], [3.0, 4.0, 5.0]])
+            .colors(vec![[0.1, 0.2, 0.3, 0.4], [0.5, 0.6, 0.7, 0.8]]);
+        assert_eq!(
+            builder.interleave().unwrap(),
+            vec![
+                0.0, 1.0, 2.0, 0.1, 0.2, 0.3, 0.4, 3.0, 4.0, 5.0, 0.5, 0.6, 0.7, 0.8
+            ]
+        );
+
+        let missing_color = MeshBuilder::new("Unpainted", layout)
+            .positions(vec![[0.0, 1.0, 2.0]])
+            .colors(vec![]);
+        assert!(missing_color.interleave().is_err());
+    }
 }
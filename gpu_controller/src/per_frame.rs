@@ -0,0 +1,133 @@
+use crate::{DESIRED_MAX_FRAME_LATENCY, GpuController};
+
+/// Frames the CPU can record while the GPU still works on earlier ones, the
+/// frames queued for the surface and the one being recorded
+pub const FRAMES_IN_FLIGHT: usize = DESIRED_MAX_FRAME_LATENCY as usize + 1;
+
+/// One copy of a resource for every frame in flight, such as a staging
+/// buffer, an instance buffer or a query set.
+///
+/// Writing into the copy of the current frame never touches a copy the GPU
+/// may still be reading for an earlier frame. The copies are picked by the
+/// frame index of the `GpuController`, which moves on with
+/// [`GpuController::end_frame`].
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// # use gpu_controller::{BufferDescriptor, BufferUsages, GpuController, PerFrame};
+/// # fn example(gpu: &GpuController, data: &[u8]) {
+/// let uploads = PerFrame::new(|_| {
+///     gpu.create_buffer(&BufferDescriptor {
+///         label: Some("Upload Buffer"),
+///         size: 1024,
+///         usage: BufferUsages::MAP_WRITE | BufferUsages::COPY_SRC,
+///         mapped_at_creation: false,
+///     })
+/// });
+///
+/// // Only the buffer of this frame is written to
+/// let upload = uploads.current(gpu);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct PerFrame<T> {
+    frames: Vec<T>,
+}
+
+impl<T> PerFrame<T> {
+    /// Creates a copy of the resource for each of the `FRAMES_IN_FLIGHT`
+    ///
+    /// ## Arguments
+    ///
+    /// * `create` - Creates the copy of a frame, given its index among the frames in flight
+    pub fn new<F>(create: F) -> Self
+    where
+        F: FnMut(usize) -> T,
+    {
+        Self::with_frames_in_flight(FRAMES_IN_FLIGHT, create)
+    }
+
+    /// Creates a copy of the resource for a number of frames in flight, for
+    /// resources that are read back later than the frame after next
+    ///
+    /// ## Arguments
+    ///
+    /// * `frames_in_flight` - Copies to create, at least 1
+    /// * `create` - Creates the copy of a frame, given its index among the frames in flight
+    pub fn with_frames_in_flight<F>(frames_in_flight: usize, create: F) -> Self
+    where
+        F: FnMut(usize) -> T,
+    {
+        Self {
+            frames: (0..frames_in_flight.max(1)).map(create).collect(),
+        }
+    }
+
+    /// Returns the copy of a frame
+    #[inline]
+    pub fn get(&self, frame_index: u64) -> &T {
+        &self.frames[self.slot(frame_index)]
+    }
+
+    /// Returns the copy of a frame mutably
+    #[inline]
+    pub fn get_mut(&mut self, frame_index: u64) -> &mut T {
+        let slot = self.slot(frame_index);
+        &mut self.frames[slot]
+    }
+
+    /// Returns the copy of the frame being recorded
+    #[inline]
+    pub fn current(&self, gpu_controller: &GpuController) -> &T {
+        self.get(gpu_controller.frame_index())
+    }
+
+    /// Returns the copy of the frame being recorded mutably
+    #[inline]
+    pub fn current_mut(&mut self, gpu_controller: &GpuController) -> &mut T {
+        self.get_mut(gpu_controller.frame_index())
+    }
+
+    /// Returns the number of copies
+    pub fn frames_in_flight(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Iterates over every copy, such as to resize all of them at once
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.frames.iter()
+    }
+
+    /// Iterates over every copy mutably
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.frames.iter_mut()
+    }
+
+    fn slot(&self, frame_index: u64) -> usize {
+        (frame_index % self.frames.len() as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_per_frame() {
+        let mut per_frame = PerFrame::new(|index| index * 10);
+        assert_eq!(per_frame.frames_in_flight(), FRAMES_IN_FLIGHT);
+
+        // Consecutive frames never share a copy until the frames in flight wrap around
+        for frame_index in 0..FRAMES_IN_FLIGHT as u64 {
+            assert_eq!(*per_frame.get(frame_index), frame_index as usize * 10);
+        }
+        assert_eq!(per_frame.get(FRAMES_IN_FLIGHT as u64), per_frame.get(0));
+
+        *per_frame.get_mut(1) = 7;
+        assert_eq!(*per_frame.get(FRAMES_IN_FLIGHT as u64 + 1), 7);
+
+        let single = PerFrame::with_frames_in_flight(0, |_| ());
+        assert_eq!(single.frames_in_flight(), 1);
+    }
+}
@@ -0,0 +1,186 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{
+        RwLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use anyhow::{Result, anyhow};
+use log::{debug, info};
+use wgpu::RenderPipeline;
+
+type PipelineBuilder<K, P> = Box<dyn Fn(&K) -> Result<P> + Send + Sync>;
+
+/// Compiles the variants of a pipeline the first time they are used.
+///
+/// Every variant is identified by a key, anything that changes the pipeline
+/// (material flags, vertex layout, pass, shader defines) belongs in it. The
+/// builder compiles the variant of a key and the cache keeps it for every
+/// later use, so only the variants that are drawn are ever compiled.
+///
+/// Variants that are known to be needed can be compiled up front with
+/// `warm_up` to avoid a hitch the first time they are drawn.
+///
+/// # Example
+/// ```ignore
+/// let pipelines = PipelineCache::new("Terrain", move |key: &TerrainKey| {
+///     let source = assets.preprocess_shader("terrain.wgsl", &key.defines())?;
+///     Ok(create_terrain_pipeline(&gpu_controller, &source, key))
+/// });
+///
+/// pipelines.warm_up([TerrainKey::default()])?;
+/// render_pass.set_pipeline(&pipelines.get(&key)?);
+/// ```
+pub struct PipelineCache<K, P = RenderPipeline> {
+    label: String,
+    build: PipelineBuilder<K, P>,
+    pipelines: RwLock<HashMap<K, P>>,
+    // Variants compiled after warming up, each one is a potential hitch
+    misses: AtomicUsize,
+}
+
+impl<K, P> PipelineCache<K, P>
+where
+    K: Hash + Eq + Clone,
+    P: Clone,
+{
+    pub fn new<F>(label: impl Into<String>, build: F) -> Self
+    where
+        F: Fn(&K) -> Result<P> + Send + Sync + 'static,
+    {
+        Self {
+            label: label.into(),
+            build: Box::new(build),
+            pipelines: RwLock::new(HashMap::new()),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the variant of a key, compiling it if it is not cached yet.
+    ///
+    /// # Returns
+    /// The pipeline, or the error of the builder if the variant failed to compile
+    pub fn get(&self, key: &K) -> Result<P> {
+        match self.pipelines.read() {
+            Ok(pipelines) => {
+                if let Some(pipeline) = pipelines.get(key) {
+                    return Ok(pipeline.clone());
+                }
+            }
+            Err(err) => return Err(anyhow!("Failed to read {} pipelines: {}", self.label, err)),
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        debug!("Compiling a new {} pipeline variant", self.label);
+
+        self.compile(key)
+    }
+
+    /// Compiles the variants of the keys ahead of their first use.
+    ///
+    /// Variants that are already cached are skipped, compiling stops at the
+    /// first variant that fails.
+    pub fn warm_up<I>(&self, keys: I) -> Result<()>
+    where
+        I: IntoIterator<Item = K>,
+    {
+        let mut compiled = 0;
+        for key in keys {
+            if !self.contains(&key) {
+                self.compile(&key)?;
+                compiled += 1;
+            }
+        }
+
+        info!("Warmed up {} {} pipeline variants", compiled, self.label);
+        Ok(())
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.pipelines
+            .read()
+            .is_ok_and(|pipelines| pipelines.contains_key(key))
+    }
+
+    /// Returns the number of variants compiled so far
+    pub fn len(&self) -> usize {
+        self.pipelines.read().map_or(0, |pipelines| pipelines.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns how many variants were compiled on first use instead of by `warm_up`
+    pub fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Drops every variant, for example after the shaders they were built from changed
+    pub fn clear(&self) {
+        if let Ok(mut pipelines) = self.pipelines.write() {
+            pipelines.clear();
+        }
+    }
+
+    fn compile(&self, key: &K) -> Result<P> {
+        // Compiled without holding the lock so other variants can be used meanwhile
+        let pipeline = (self.build)(key)?;
+
+        let mut pipelines = self
+            .pipelines
+            .write()
+            .map_err(|err| anyhow!("Failed to write {} pipelines: {}", self.label, err))?;
+
+        // Another thread may have compiled the same variant first, keep that one
+        Ok(pipelines.entry(key.clone()).or_insert(pipeline).clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn test_pipeline_cache() {
+        let builds = Arc::new(AtomicUsize::new(0));
+
+        let counter = builds.clone();
+        let cache = PipelineCache::new("Test", move |key: &(u32, bool)| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            match key {
+                (0, _) => Err(anyhow!("Variant 0 doesn't compile")),
+                (flags, shadows) => Ok(format!("{}-{}", flags, shadows)),
+            }
+        });
+
+        cache.warm_up([(1, false), (1, true)]).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert_eq!(builds.load(Ordering::Relaxed), 2);
+
+        // Warmed up variants are not compiled again
+        assert_eq!(cache.get(&(1, true)).unwrap(), "1-true");
+        cache.warm_up([(1, false)]).unwrap();
+        assert_eq!(builds.load(Ordering::Relaxed), 2);
+        assert_eq!(cache.misses(), 0);
+
+        // New variants compile lazily once
+        assert_eq!(cache.get(&(2, false)).unwrap(), "2-false");
+        assert_eq!(cache.get(&(2, false)).unwrap(), "2-false");
+        assert_eq!(builds.load(Ordering::Relaxed), 3);
+        assert_eq!(cache.misses(), 1);
+
+        // Failed variants are not cached
+        assert!(cache.get(&(0, false)).is_err());
+        assert!(!cache.contains(&(0, false)));
+        assert!(cache.warm_up([(3, false), (0, true)]).is_err());
+        assert!(cache.contains(&(3, false)));
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,331 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{Result, anyhow};
+use log::debug;
+
+/// Where the preprocessor reads the files named by `#include` from.
+///
+/// Paths are always `/` separated and relative to the root of the file
+/// system, includes are resolved relative to the file that includes them.
+pub trait ShaderFileSystem: Send + Sync {
+    fn read_shader(&self, path: &str) -> Result<String>;
+}
+
+/// Shaders kept in memory, keyed by their path
+impl ShaderFileSystem for HashMap<String, String> {
+    fn read_shader(&self, path: &str) -> Result<String> {
+        self.get(path)
+            .cloned()
+            .ok_or_else(|| anyhow!("Shader not found: {}", path))
+    }
+}
+
+/// Expands the directives of WGSL shaders before they are compiled.
+///
+/// # Directives
+/// * `#include "path"` - Pastes another file in place, every file is only
+///   included once per shader so shared files can include what they need
+/// * `#define NAME` - Defines a feature for the rest of the shader
+/// * `#ifdef NAME` / `#ifndef NAME` - Keeps the lines up to the matching
+///   `#else` or `#endif` only if the feature is (not) defined
+/// * `#else` / `#endif` - Closes the branches of an `#ifdef` or `#ifndef`
+///
+/// # Example
+/// ```ignore
+/// let source = ShaderPreprocessor::new(&files)
+///     .define("SHADOWS")
+///     .process("pbr.wgsl")?;
+///
+/// let module = gpu_controller.create_shader(&source);
+/// ```
+pub struct ShaderPreprocessor<'a> {
+    files: &'a dyn ShaderFileSystem,
+    defines: HashSet<String>,
+}
+
+/// A branch of an `#ifdef` or `#ifndef` being expanded
+struct Conditional {
+    // Whether the lines of the current branch are kept
+    active: bool,
+    // Whether the conditional is inside a branch that is dropped
+    parent_active: bool,
+    has_else: bool,
+}
+
+impl<'a> ShaderPreprocessor<'a> {
+    pub fn new(files: &'a dyn ShaderFileSystem) -> Self {
+        Self {
+            files,
+            defines: HashSet::new(),
+        }
+    }
+
+    /// Defines a feature, as if the shader started with `#define name`
+    pub fn define(mut self, name: impl Into<String>) -> Self {
+        self.defines.insert(name.into());
+        self
+    }
+
+    /// Defines every feature of a list
+    pub fn defines<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.defines.extend(names.into_iter().map(Into::into));
+        self
+    }
+
+    /// Reads a shader from the file system and expands its directives.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the shader in the file system
+    ///
+    /// # Returns
+    /// The WGSL source ready to compile, or an error naming the file and line of a bad directive
+    pub fn process(&self, path: &str) -> Result<String> {
+        let mut defines = self.defines.clone();
+        let mut included = HashSet::new();
+        let mut output = String::new();
+
+        self.expand(path, &mut defines, &mut included, &mut output)?;
+
+        debug!("Preprocessed shader {}", path);
+        Ok(output)
+    }
+
+    /// Expands a shader that is already in memory, includes are resolved from the root
+    pub fn process_source(&self, name: &str, source: &str) -> Result<String> {
+        let mut defines = self.defines.clone();
+        let mut included = HashSet::from([name.to_string()]);
+        let mut output = String::new();
+
+        self.expand_source(name, source, &mut defines, &mut included, &mut output)?;
+
+        Ok(output)
+    }
+
+    fn expand(
+        &self,
+        path: &str,
+        defines: &mut HashSet<String>,
+        included: &mut HashSet<String>,
+        output: &mut String,
+    ) -> Result<()> {
+        if !included.insert(path.to_string()) {
+            return Ok(());
+        }
+
+        let source = self.files.read_shader(path)?;
+        self.expand_source(path, &source, defines, included, output)
+    }
+
+    fn expand_source(
+        &self,
+        path: &str,
+        source: &str,
+        defines: &mut HashSet<String>,
+        included: &mut HashSet<String>,
+        output: &mut String,
+    ) -> Result<()> {
+        let mut conditionals: Vec<Conditional> = Vec::new();
+
+        for (line_index, line) in source.lines().enumerate() {
+            let error = |message: String| anyhow!("{}:{}: {}", path, line_index + 1, message);
+            let active = conditionals.last().is_none_or(|branch| branch.active);
+
+            let Some(directive) = line.trim_start().strip_prefix('#') else {
+                if active {
+                    output.push_str(line);
+                    output.push('\n');
+                }
+                continue;
+            };
+
+            let mut tokens = directive.split_whitespace();
+            let keyword = tokens.next().unwrap_or_default();
+            let argument = tokens.next();
+
+            match keyword {
+                "ifdef" | "ifndef" => {
+                    let name =
+                        argument.ok_or_else(|| error(format!("#{} needs a name", keyword)))?;
+                    let defined = defines.contains(name);
+
+                    conditionals.push(Conditional {
+                        active: active && (defined == (keyword == "ifdef")),
+                        parent_active: active,
+                        has_else: false,
+                    });
+                }
+                "else" => {
+                    let branch = conditionals
+                        .last_mut()
+                        .ok_or_else(|| error("#else without #ifdef".to_string()))?;
+
+                    if branch.has_else {
+                        return Err(error("Second #else for the same #ifdef".to_string()));
+                    }
+
+                    branch.has_else = true;
+                    branch.active = branch.parent_active && !branch.active;
+                }
+                "endif" => {
+                    conditionals
+                        .pop()
+                        .ok_or_else(|| error("#endif without #ifdef".to_string()))?;
+                }
+                _ if !active => {}
+                "define" => {
+                    let name = argument.ok_or_else(|| error("#define needs a name".to_string()))?;
+                    defines.insert(name.to_string());
+                }
+                "include" => {
+                    let included_path = argument
+                        .and_then(|argument| argument.strip_prefix('"'))
+                        .and_then(|argument| argument.strip_suffix('"'))
+                        .ok_or_else(|| error("#include needs a quoted path".to_string()))?;
+
+                    let resolved = resolve_include(path, included_path);
+                    self.expand(&resolved, defines, included, output)
+                        .map_err(|err| error(format!("Failed to include {}: {}", resolved, err)))?;
+                }
+                other => return Err(error(format!("Unknown directive #{}", other))),
+            }
+        }
+
+        if !conditionals.is_empty() {
+            return Err(anyhow!("{}: #ifdef without #endif", path));
+        }
+
+        Ok(())
+    }
+}
+
+/// Resolves an include relative to the directory of the file including it
+fn resolve_include(including: &str, include: &str) -> String {
+    let mut parts: Vec<&str> = including.split('/').collect();
+    // Drop the name of the including file
+    parts.pop();
+
+    for part in include.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files() -> HashMap<String, String> {
+        HashMap::from([
+            (
+                "pbr.wgsl".to_string(),
+                "#include \"common/lights.wgsl\"\n\
+                 #include \"common/camera.wgsl\"\n\
+                 #ifdef SHADOWS\n\
+                 fn shadow() -> f32 { return 0.5; }\n\
+                 #else\n\
+                 fn shadow() -> f32 { return 1.0; }\n\
+                 #endif\n"
+                    .to_string(),
+            ),
+            (
+                "common/lights.wgsl".to_string(),
+                "#include \"camera.wgsl\"\nstruct Light { color: vec3<f32> }\n".to_string(),
+            ),
+            (
+                "common/camera.wgsl".to_string(),
+                "#define HAS_CAMERA\nstruct Camera { position: vec3<f32> }\n".to_string(),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_includes_once() {
+        let files = files();
+        let source = ShaderPreprocessor::new(&files).process("pbr.wgsl").unwrap();
+
+        assert_eq!(source.matches("struct Camera").count(), 1);
+        assert!(source.find("struct Camera") < source.find("struct Light"));
+        assert!(!source.contains('#'));
+    }
+
+    #[test]
+    fn test_defines() {
+        let files = files();
+
+        let without = ShaderPreprocessor::new(&files).process("pbr.wgsl").unwrap();
+        assert!(without.contains("return 1.0"));
+        assert!(!without.contains("return 0.5"));
+
+        let with = ShaderPreprocessor::new(&files)
+            .define("SHADOWS")
+            .process("pbr.wgsl")
+            .unwrap();
+        assert!(with.contains("return 0.5"));
+        assert!(!with.contains("return 1.0"));
+
+        // Defines made by included files apply to the rest of the shader
+        let source = ShaderPreprocessor::new(&files)
+            .process_source(
+                "main.wgsl",
+                "#include \"common/camera.wgsl\"\n\
+                 #ifndef HAS_CAMERA\nmissing\n#endif\n\
+                 #ifdef A\n#ifdef B\nboth\n#else\nonly_a\n#endif\n#else\nneither\n#endif\n",
+            )
+            .unwrap();
+        assert!(!source.contains("missing"));
+        assert!(source.contains("neither"));
+        assert!(!source.contains("only_a"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let files = files();
+        let preprocessor = ShaderPreprocessor::new(&files);
+
+        assert!(preprocessor.process("missing.wgsl").is_err());
+        assert!(preprocessor.process_source("a", "#ifdef A\n").is_err());
+        assert!(preprocessor.process_source("a", "#endif\n").is_err());
+        assert!(
+            preprocessor
+                .process_source("a", "#ifdef A\n#else\n#else\n#endif\n")
+                .is_err()
+        );
+        assert!(preprocessor.process_source("a", "#pragma once\n").is_err());
+        assert!(
+            preprocessor
+                .process_source("a", "#include \"missing.wgsl\"\n")
+                .is_err()
+        );
+
+        // Directives of dropped branches are not checked
+        assert!(
+            preprocessor
+                .process_source("a", "#ifdef A\n#include \"missing.wgsl\"\n#endif\n")
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_resolve_include() {
+        assert_eq!(resolve_include("pbr.wgsl", "lights.wgsl"), "lights.wgsl");
+        assert_eq!(
+            resolve_include("shaders/pbr.wgsl", "./common/lights.wgsl"),
+            "shaders/common/lights.wgsl"
+        );
+        assert_eq!(
+            resolve_include("shaders/common/lights.wgsl", "../fog.wgsl"),
+            "shaders/fog.wgsl"
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use wgpu::{InternalCounters, wgc::global::GlobalReport};
+
+/// Counts of the live GPU objects of a device and the memory they hold.
+///
+/// Read with `GpuController::stats` by debug overlays. A count that keeps
+/// growing while nothing new is loaded, such as bind groups recreated every
+/// frame without the old ones being dropped, is a leak, see `grown_since`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GpuStats {
+    pub buffers: usize,
+    pub textures: usize,
+    pub texture_views: usize,
+    pub samplers: usize,
+    pub bind_groups: usize,
+    pub bind_group_layouts: usize,
+    pub pipeline_layouts: usize,
+    pub render_pipelines: usize,
+    pub compute_pipelines: usize,
+    pub shader_modules: usize,
+    pub query_sets: usize,
+    /// Command buffers that have been created but not yet freed
+    pub command_buffers: usize,
+
+    /// Bytes of memory held by buffers, `None` without the `counters` feature
+    pub buffer_memory: Option<u64>,
+    /// Bytes of memory held by textures, `None` without the `counters` feature
+    pub texture_memory: Option<u64>,
+    /// Memory allocations made by the driver, `None` without the `counters` feature
+    pub memory_allocations: Option<u64>,
+}
+
+impl GpuStats {
+    /// Builds the stats from the reports of wgpu.
+    ///
+    /// # Arguments
+    /// * `report` - The registry report of the instance, `None` leaves the counts at 0
+    /// * `counters` - The internal counters of the device, which are only
+    ///   counted with the `counters` feature
+    pub(crate) fn from_reports(report: Option<&GlobalReport>, counters: &InternalCounters) -> Self {
+        let mut stats = Self::default();
+
+        if let Some(report) = report {
+            let hub = report.hub_report();
+            stats.buffers = hub.buffers.num_allocated;
+            stats.textures = hub.textures.num_allocated;
+            stats.texture_views = hub.texture_views.num_allocated;
+            stats.samplers = hub.samplers.num_allocated;
+            stats.bind_groups = hub.bind_groups.num_allocated;
+            stats.bind_group_layouts = hub.bind_group_layouts.num_allocated;
+            stats.pipeline_layouts = hub.pipeline_layouts.num_allocated;
+            stats.render_pipelines = hub.render_pipelines.num_allocated;
+            stats.compute_pipelines = hub.compute_pipelines.num_allocated;
+            stats.shader_modules = hub.shader_modules.num_allocated;
+            stats.query_sets = hub.query_sets.num_allocated;
+            stats.command_buffers = hub.command_buffers.num_allocated;
+        }
+
+        if cfg!(feature = "counters") {
+            let memory = |counter: isize| Some(counter.max(0) as u64);
+            stats.buffer_memory = memory(counters.hal.buffer_memory.read());
+            stats.texture_memory = memory(counters.hal.texture_memory.read());
+            stats.memory_allocations = memory(counters.hal.memory_allocations.read());
+        }
+
+        stats
+    }
+
+    /// Returns the name and count of every kind of object
+    pub fn counts(&self) -> [(&'static str, usize); 12] {
+        [
+            ("buffers", self.buffers),
+            ("textures", self.textures),
+            ("texture_views", self.texture_views),
+            ("samplers", self.samplers),
+            ("bind_groups", self.bind_groups),
+            ("bind_group_layouts", self.bind_group_layouts),
+            ("pipeline_layouts", self.pipeline_layouts),
+            ("render_pipelines", self.render_pipelines),
+            ("compute_pipelines", self.compute_pipelines),
+            ("shader_modules", self.shader_modules),
+            ("query_sets", self.query_sets),
+            ("command_buffers", self.command_buffers),
+        ]
+    }
+
+    /// Finds the kinds of objects there are more of than in earlier stats.
+    ///
+    /// # Arguments
+    /// * `earlier` - Stats read at a point the same objects should be alive,
+    ///   such as before a level was loaded and unloaded again
+    ///
+    /// # Returns
+    /// The name of every kind of object that grew and by how many
+    ///
+    /// # Example
+    /// ```ignore
+    /// let before = gpu_controller.stats();
+    /// load_and_unload_level()?;
+    ///
+    /// for (kind, grown) in gpu_controller.stats().grown_since(&before) {
+    ///     warn!("{} more {} than before the level", grown, kind);
+    /// }
+    /// ```
+    pub fn grown_since(&self, earlier: &GpuStats) -> Vec<(&'static str, usize)> {
+        self.counts()
+            .into_iter()
+            .zip(earlier.counts())
+            .filter(|((_, count), (_, earlier_count))| count > earlier_count)
+            .map(|((kind, count), (_, earlier_count))| (kind, count - earlier_count))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_grown_since() {
+        let earlier = GpuStats {
+            buffers: 10,
+            bind_groups: 4,
+            textures: 3,
+            ..Default::default()
+        };
+        let now = GpuStats {
+            buffers: 10,
+            bind_groups: 9,
+            textures: 2,
+            render_pipelines: 1,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            now.grown_since(&earlier),
+            vec![("bind_groups", 5), ("render_pipelines", 1)]
+        );
+        assert!(earlier.grown_since(&earlier).is_empty());
+    }
+}
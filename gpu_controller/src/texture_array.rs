@@ -0,0 +1,392 @@
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow};
+use log::info;
+use wgpu::{
+    AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, Extent3d, FilterMode, Sampler,
+    SamplerBindingType, SamplerDescriptor, ShaderStages, TexelCopyBufferLayout, Texture,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+    TextureView, TextureViewDescriptor, TextureViewDimension,
+};
+
+use crate::GpuController;
+
+// Layers are always stored as 8-bit RGBA
+const BYTES_PER_TEXEL: u32 = 4;
+
+pub const TEXTURE_ARRAY_BIND_GROUP_LAYOUT_DESCRIPTOR: BindGroupLayoutDescriptor =
+    BindGroupLayoutDescriptor {
+        label: Some("Texture Array Bind Group Layout"),
+        entries: &[
+            // Layers
+            BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX_FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: true },
+                    view_dimension: TextureViewDimension::D2Array,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            // Sampler
+            BindGroupLayoutEntry {
+                binding: 1,
+                visibility: ShaderStages::VERTEX_FRAGMENT,
+                ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+    };
+
+/// Same-size textures packed into the layers of a single texture.
+///
+/// The whole array is bound with one bind group laid out as
+/// `TEXTURE_ARRAY_BIND_GROUP_LAYOUT_DESCRIPTOR`, so shaders can blend any
+/// number of layers (terrain splats, tiles) in a single pass.
+#[derive(Debug)]
+pub struct TextureArray {
+    texture: Texture,
+    view: TextureView,
+    sampler: Sampler,
+    bind_group: BindGroup,
+    layer_count: u32,
+    mip_level_count: u32,
+}
+
+impl TextureArray {
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn view(&self) -> &TextureView {
+        &self.view
+    }
+
+    pub fn sampler(&self) -> &Sampler {
+        &self.sampler
+    }
+
+    pub fn bind_group(&self) -> &BindGroup {
+        &self.bind_group
+    }
+
+    pub fn layer_count(&self) -> u32 {
+        self.layer_count
+    }
+
+    pub fn mip_level_count(&self) -> u32 {
+        self.mip_level_count
+    }
+}
+
+/// Builds a `TextureArray` from layers of 8-bit RGBA texels.
+///
+/// Every layer must have the size given to the builder, the layer index in
+/// the shaders is the order the layers were added in. Mipmaps are generated
+/// on the CPU by averaging 2x2 blocks of the level above.
+///
+/// # Example
+/// ```ignore
+/// let splats = TextureArrayBuilder::new("Terrain Splats", 512, 512)
+///     .layer(grass)
+///     .layer(dirt)
+///     .layer(rock)
+///     .build(gpu_controller)?;
+/// ```
+pub struct TextureArrayBuilder {
+    label: String,
+    width: u32,
+    height: u32,
+    srgb: bool,
+    mipmaps: bool,
+    layers: Vec<Vec<u8>>,
+}
+
+impl TextureArrayBuilder {
+    pub fn new(label: impl Into<String>, width: u32, height: u32) -> Self {
+        Self {
+            label: label.into(),
+            width,
+            height,
+            srgb: true,
+            mipmaps: true,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Whether the layers hold colors in sRGB, defaults to `true`
+    pub fn srgb(mut self, srgb: bool) -> Self {
+        self.srgb = srgb;
+        self
+    }
+
+    /// Whether to generate the full mip chain, defaults to `true`
+    pub fn mipmaps(mut self, mipmaps: bool) -> Self {
+        self.mipmaps = mipmaps;
+        self
+    }
+
+    /// Adds a layer of `width * height` RGBA texels, row by row
+    pub fn layer(mut self, texels: Vec<u8>) -> Self {
+        self.layers.push(texels);
+        self
+    }
+
+    pub fn build(self, gpu_controller: Arc<GpuController>) -> Result<TextureArray> {
+        self.validate()?;
+
+        let mip_level_count = if self.mipmaps {
+            mip_level_count(self.width, self.height)
+        } else {
+            1
+        };
+
+        let texture = gpu_controller.create_texture(&TextureDescriptor {
+            label: Some(&format!("{} Texture Array", self.label)),
+            size: Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: self.layers.len() as u32,
+            },
+            mip_level_count,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: if self.srgb {
+                TextureFormat::Rgba8UnormSrgb
+            } else {
+                TextureFormat::Rgba8Unorm
+            },
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        for (layer, texels) in self.layers.iter().enumerate() {
+            let mut level = texels.clone();
+            let (mut width, mut height) = (self.width, self.height);
+
+            for mip_level in 0..mip_level_count {
+                if mip_level > 0 {
+                    (level, width, height) = downsample(&level, width, height, self.srgb);
+                }
+
+                gpu_controller.write_texture_layer(
+                    &texture,
+                    mip_level,
+                    layer as u32,
+                    &level,
+                    TexelCopyBufferLayout {
+                        offset: 0,
+                        bytes_per_row: Some(BYTES_PER_TEXEL * width),
+                        rows_per_image: Some(height),
+                    },
+                    Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                );
+            }
+        }
+
+        let view = texture.create_view(&TextureViewDescriptor {
+            label: Some(&format!("{} Texture Array View", self.label)),
+            dimension: Some(TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+
+        // Layers of terrain and tiles are meant to be tiled across surfaces
+        let sampler = gpu_controller.create_sampler(&SamplerDescriptor {
+            label: Some(&format!("{} Texture Array Sampler", self.label)),
+            address_mode_u: AddressMode::Repeat,
+            address_mode_v: AddressMode::Repeat,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bind_group_layout =
+            gpu_controller.create_bind_group_layout(&TEXTURE_ARRAY_BIND_GROUP_LAYOUT_DESCRIPTOR);
+
+        let bind_group = gpu_controller.create_bind_group(&BindGroupDescriptor {
+            label: Some(&format!("{} Texture Array Bind Group", self.label)),
+            layout: &bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        info!(
+            "Built texture array {} with {} layers and {} mip levels",
+            self.label,
+            self.layers.len(),
+            mip_level_count
+        );
+
+        Ok(TextureArray {
+            texture,
+            view,
+            sampler,
+            bind_group,
+            layer_count: self.layers.len() as u32,
+            mip_level_count,
+        })
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.width == 0 || self.height == 0 {
+            return Err(anyhow!("Texture array {} has no size", self.label));
+        }
+
+        if self.layers.is_empty() {
+            return Err(anyhow!("Texture array {} has no layers", self.label));
+        }
+
+        let layer_size = (self.width * self.height * BYTES_PER_TEXEL) as usize;
+        for (layer, texels) in self.layers.iter().enumerate() {
+            if texels.len() != layer_size {
+                return Err(anyhow!(
+                    "Layer {} of texture array {} has {} bytes, expected {} for {}x{} texels",
+                    layer,
+                    self.label,
+                    texels.len(),
+                    layer_size,
+                    self.width,
+                    self.height
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the number of levels in the full mip chain of a size
+fn mip_level_count(width: u32, height: u32) -> u32 {
+    u32::BITS - width.max(height).leading_zeros()
+}
+
+/// Halves a level by averaging 2x2 blocks, odd edges reuse their last texel.
+///
+/// sRGB colors are averaged in linear space so the levels don't darken.
+fn downsample(texels: &[u8], width: u32, height: u32, srgb: bool) -> (Vec<u8>, u32, u32) {
+    let next_width = (width / 2).max(1);
+    let next_height = (height / 2).max(1);
+
+    let texel = |x: u32, y: u32, channel: u32| {
+        let x = x.min(width - 1);
+        let y = y.min(height - 1);
+        texels[((y * width + x) * BYTES_PER_TEXEL + channel) as usize]
+    };
+
+    let mut next = Vec::with_capacity((next_width * next_height * BYTES_PER_TEXEL) as usize);
+    for y in 0..next_height {
+        for x in 0..next_width {
+            for channel in 0..BYTES_PER_TEXEL {
+                // Alpha is always linear
+                let linear = srgb && channel < 3;
+
+                let sum = [(0, 0), (1, 0), (0, 1), (1, 1)]
+                    .map(|(dx, dy)| {
+                        let value = texel(x * 2 + dx, y * 2 + dy, channel) as f32 / 255.0;
+                        if linear { srgb_to_linear(value) } else { value }
+                    })
+                    .iter()
+                    .sum::<f32>();
+
+                let average = sum / 4.0;
+                let average = if linear {
+                    linear_to_srgb(average)
+                } else {
+                    average
+                };
+
+                next.push((average.clamp(0.0, 1.0) * 255.0).round() as u8);
+            }
+        }
+    }
+
+    (next, next_width, next_height)
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mip_level_count() {
+        assert_eq!(mip_level_count(1, 1), 1);
+        assert_eq!(mip_level_count(512, 512), 10);
+        assert_eq!(mip_level_count(512, 64), 10);
+        assert_eq!(mip_level_count(300, 5), 9);
+    }
+
+    #[test]
+    fn test_downsample() {
+        // A 2x2 checker of black and white becomes one grey texel
+        let checker = [0, 255, 255, 0]
+            .iter()
+            .flat_map(|&value| [value, value, value, 255])
+            .collect::<Vec<u8>>();
+
+        let (linear, width, height) = downsample(&checker, 2, 2, false);
+        assert_eq!((width, height), (1, 1));
+        assert_eq!(linear, vec![128, 128, 128, 255]);
+
+        // Averaged in linear space, half the light is brighter than half the value in sRGB
+        let (srgb, _, _) = downsample(&checker, 2, 2, true);
+        assert_eq!(srgb[3], 255);
+        assert!(srgb[0] > 180 && srgb[0] < 195, "{:?}", srgb);
+
+        // Odd sizes keep their last row and column
+        let (_, width, height) = downsample(&[0; 3 * 4], 3, 1, false);
+        assert_eq!((width, height), (1, 1));
+    }
+
+    #[test]
+    fn test_validate_layers() {
+        let layer = vec![0; 4 * 4 * 4];
+
+        assert!(
+            TextureArrayBuilder::new("Tiles", 4, 4)
+                .layer(layer.clone())
+                .layer(layer.clone())
+                .validate()
+                .is_ok()
+        );
+        assert!(TextureArrayBuilder::new("Tiles", 4, 4).validate().is_err());
+        assert!(
+            TextureArrayBuilder::new("Tiles", 4, 4)
+                .layer(layer)
+                .layer(vec![0; 2 * 2 * 4])
+                .validate()
+                .is_err()
+        );
+    }
+}
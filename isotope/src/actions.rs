@@ -0,0 +1,392 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+pub use winit::event::MouseButton;
+use winit::{keyboard::KeyCode, keyboard::ModifiersState};
+
+// Time allowed between the two presses of a double tap if none is given
+const DEFAULT_DOUBLE_TAP_WINDOW: Duration = Duration::from_millis(300);
+
+/// Most inputs an action map keeps until `ActionMap::take_inputs`, the oldest are dropped first
+pub const MAX_QUEUED_INPUTS: usize = 1024;
+
+/// A key or mouse button that can trigger an action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputTrigger {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+impl From<KeyCode> for InputTrigger {
+    fn from(key: KeyCode) -> Self {
+        Self::Key(key)
+    }
+}
+
+impl From<MouseButton> for InputTrigger {
+    fn from(button: MouseButton) -> Self {
+        Self::Mouse(button)
+    }
+}
+
+/// The modifier keys held while a trigger is pressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    /// The Windows or Command key
+    pub logo: bool,
+}
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers {
+        ctrl: false,
+        shift: false,
+        alt: false,
+        logo: false,
+    };
+    pub const CTRL: Modifiers = Modifiers {
+        ctrl: true,
+        ..Modifiers::NONE
+    };
+    pub const SHIFT: Modifiers = Modifiers {
+        shift: true,
+        ..Modifiers::NONE
+    };
+    pub const ALT: Modifiers = Modifiers {
+        alt: true,
+        ..Modifiers::NONE
+    };
+    pub const LOGO: Modifiers = Modifiers {
+        logo: true,
+        ..Modifiers::NONE
+    };
+
+    /// Returns the modifiers held in either set
+    pub fn with(self, other: Modifiers) -> Modifiers {
+        Modifiers {
+            ctrl: self.ctrl || other.ctrl,
+            shift: self.shift || other.shift,
+            alt: self.alt || other.alt,
+            logo: self.logo || other.logo,
+        }
+    }
+
+    /// Returns whether every modifier in `other` is also in this set
+    pub fn contains(&self, other: Modifiers) -> bool {
+        (self.ctrl || !other.ctrl)
+            && (self.shift || !other.shift)
+            && (self.alt || !other.alt)
+            && (self.logo || !other.logo)
+    }
+
+    fn count(&self) -> u32 {
+        [self.ctrl, self.shift, self.alt, self.logo]
+            .iter()
+            .filter(|held| **held)
+            .count() as u32
+    }
+}
+
+impl From<ModifiersState> for Modifiers {
+    fn from(state: ModifiersState) -> Self {
+        Self {
+            ctrl: state.control_key(),
+            shift: state.shift_key(),
+            alt: state.alt_key(),
+            logo: state.super_key(),
+        }
+    }
+}
+
+/// What happened in a `TimedInput`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputKind {
+    /// A key or mouse button went down, repeats of a held key aren't recorded
+    Pressed(InputTrigger),
+    /// A key or mouse button went up
+    Released(InputTrigger),
+    /// The mouse moved, filtered the same as the motion passed to the state
+    MouseMoved { delta: (f64, f64) },
+}
+
+/// A keyboard or mouse input with when it arrived within the tick.
+///
+/// Several inputs can arrive between two ticks of the state thread. The
+/// offset places them within the tick and the sequence orders inputs with
+/// the same offset, so fast gameplay and replays can apply them in the
+/// order they happened.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimedInput {
+    pub kind: InputKind,
+    /// Time since the start of the last tick when the input arrived
+    pub offset: Duration,
+    /// Counts up with every input of the action map, never repeats
+    pub sequence: u64,
+}
+
+/// A combination of inputs that triggers an action.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ActionBinding {
+    /// Triggers when `trigger` is pressed while `modifiers` are held
+    ///
+    /// When several bindings of a trigger match, only the ones needing the
+    /// most modifiers trigger, so Ctrl+S does not also trigger S.
+    Press {
+        trigger: InputTrigger,
+        modifiers: Modifiers,
+    },
+    /// Triggers when the last of the inputs is pressed while the others are held
+    Chord(Vec<InputTrigger>),
+    /// Triggers when `trigger` is pressed twice within `window`
+    DoubleTap {
+        trigger: InputTrigger,
+        window: Duration,
+    },
+}
+
+impl ActionBinding {
+    /// Binds a key or mouse button without modifiers
+    pub fn press(trigger: impl Into<InputTrigger>) -> Self {
+        Self::Press {
+            trigger: trigger.into(),
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    /// Binds a key or mouse button pressed while modifiers are held, such as Ctrl+S
+    pub fn press_with(trigger: impl Into<InputTrigger>, modifiers: Modifiers) -> Self {
+        Self::Press {
+            trigger: trigger.into(),
+            modifiers,
+        }
+    }
+
+    /// Binds several keys or mouse buttons held together
+    pub fn chord<I, T>(triggers: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<InputTrigger>,
+    {
+        Self::Chord(triggers.into_iter().map(Into::into).collect())
+    }
+
+    /// Binds a key or mouse button pressed twice in quick succession
+    pub fn double_tap(trigger: impl Into<InputTrigger>) -> Self {
+        Self::DoubleTap {
+            trigger: trigger.into(),
+            window: DEFAULT_DOUBLE_TAP_WINDOW,
+        }
+    }
+}
+
+/// Maps keys and mouse buttons to named actions.
+///
+/// Add an `ActionMap` to an entity and Isotope feeds it every key, mouse
+/// button and modifier change of the window. Actions that triggered since
+/// the last check are read with `take_triggered`, and actions whose inputs
+/// are held down with `is_active`. Every input is also kept with its time
+/// within the tick for `take_inputs`.
+///
+/// # Example
+/// ```ignore
+/// let mut actions = ActionMap::new();
+/// actions
+///     .bind("save", ActionBinding::press_with(KeyCode::KeyS, Modifiers::CTRL))
+///     .bind("select_more", ActionBinding::press_with(MouseButton::Left, Modifiers::SHIFT))
+///     .bind("debug_menu", ActionBinding::chord([KeyCode::Backquote, KeyCode::KeyD]))
+///     .bind("dodge", ActionBinding::double_tap(KeyCode::KeyA));
+/// compound.spawn((actions,));
+///
+/// // Later, in update
+/// compound.iter_mut_mol(|_entity, actions: &mut ActionMap| {
+///     for action in actions.take_triggered() {
+///         // ...
+///     }
+/// });
+/// ```
+#[derive(Debug, Default)]
+pub struct ActionMap {
+    bindings: Vec<(String, ActionBinding)>,
+    held: HashSet<InputTrigger>,
+    modifiers: Modifiers,
+    // Time of the last press of every trigger, for double taps
+    last_press: HashMap<InputTrigger, f32>,
+    triggered: Vec<String>,
+    inputs: Vec<TimedInput>,
+    next_sequence: u64,
+}
+
+impl ActionMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a binding to an action, an action can have any number of bindings
+    ///
+    /// # Arguments
+    /// * `action` - The name of the action
+    /// * `binding` - The inputs that trigger the action
+    pub fn bind(&mut self, action: impl Into<String>, binding: ActionBinding) -> &mut Self {
+        self.bindings.push((action.into(), binding));
+        self
+    }
+
+    /// Removes every binding of an action
+    pub fn unbind(&mut self, action: &str) {
+        self.bindings
+            .retain(|(bound_action, _)| bound_action != action);
+    }
+
+    /// Returns the actions triggered since the last call, in the order they triggered
+    pub fn take_triggered(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.triggered)
+    }
+
+    /// Returns the inputs since the last call with their time within the tick.
+    ///
+    /// Only the last `MAX_QUEUED_INPUTS` are kept between calls.
+    ///
+    /// # Returns
+    /// The inputs in the order they arrived
+    ///
+    /// # Example
+    /// ```ignore
+    /// compound.iter_mut_mol(|_entity, actions: &mut ActionMap| {
+    ///     for input in actions.take_inputs() {
+    ///         if input.kind == InputKind::Pressed(KeyCode::Space.into()) {
+    ///             // Jump from where the player was when the key went down
+    ///             let rewind = context.delta_t - input.offset.as_secs_f32();
+    ///         }
+    ///     }
+    /// });
+    /// ```
+    pub fn take_inputs(&mut self) -> Vec<TimedInput> {
+        std::mem::take(&mut self.inputs)
+    }
+
+    /// Returns whether the inputs of any press or chord binding of an action are held
+    pub fn is_active(&self, action: &str) -> bool {
+        self.bindings
+            .iter()
+            .filter(|(bound_action, _)| bound_action == action)
+            .any(|(_, binding)| match binding {
+                ActionBinding::Press { trigger, modifiers } => {
+                    self.held.contains(trigger) && self.modifiers.contains(*modifiers)
+                }
+                ActionBinding::Chord(triggers) => {
+                    triggers.iter().all(|trigger| self.held.contains(trigger))
+                }
+                ActionBinding::DoubleTap { .. } => false,
+            })
+    }
+
+    /// Returns the modifier keys currently held
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    pub(crate) fn set_modifiers(&mut self, modifiers: Modifiers) {
+        self.modifiers = modifiers;
+    }
+
+    /// Records a press and triggers every action it completes
+    ///
+    /// # Arguments
+    /// * `trigger` - The key or button that was pressed
+    /// * `t` - The time of the press in seconds
+    /// * `offset` - Time since the start of the last tick
+    pub(crate) fn press(&mut self, trigger: InputTrigger, t: f32, offset: Duration) {
+        // Held keys repeat, only the first press counts
+        if !self.held.insert(trigger) {
+            return;
+        }
+
+        self.record(InputKind::Pressed(trigger), offset);
+
+        let previous_press = self.last_press.insert(trigger, t);
+
+        // Only the most specific press bindings of the trigger apply
+        let most_modifiers = self
+            .bindings
+            .iter()
+            .filter_map(|(_, binding)| match binding {
+                ActionBinding::Press {
+                    trigger: bound,
+                    modifiers,
+                } if *bound == trigger && self.modifiers.contains(*modifiers) => {
+                    Some(modifiers.count())
+                }
+                _ => None,
+            })
+            .max();
+
+        let mut double_tapped = false;
+        for (action, binding) in self.bindings.iter() {
+            let triggers = match binding {
+                ActionBinding::Press {
+                    trigger: bound,
+                    modifiers,
+                } => {
+                    *bound == trigger
+                        && self.modifiers.contains(*modifiers)
+                        && Some(modifiers.count()) == most_modifiers
+                }
+                ActionBinding::Chord(triggers) => {
+                    triggers.contains(&trigger)
+                        && triggers.iter().all(|bound| self.held.contains(bound))
+                }
+                ActionBinding::DoubleTap {
+                    trigger: bound,
+                    window,
+                } => {
+                    let tapped = *bound == trigger
+                        && previous_press
+                            .is_some_and(|previous| t - previous <= window.as_secs_f32());
+                    double_tapped |= tapped;
+                    tapped
+                }
+            };
+
+            if triggers {
+                self.triggered.push(action.clone());
+            }
+        }
+
+        // A third tap starts a new double tap instead of completing another
+        if double_tapped {
+            self.last_press.remove(&trigger);
+        }
+    }
+
+    pub(crate) fn release(&mut self, trigger: InputTrigger, offset: Duration) {
+        if self.held.remove(&trigger) {
+            self.record(InputKind::Released(trigger), offset);
+        }
+    }
+
+    pub(crate) fn mouse_moved(&mut self, delta: (f64, f64), offset: Duration) {
+        self.record(InputKind::MouseMoved { delta }, offset);
+    }
+
+    fn record(&mut self, kind: InputKind, offset: Duration) {
+        self.inputs.push(TimedInput {
+            kind,
+            offset,
+            sequence: self.next_sequence,
+        });
+        self.next_sequence += 1;
+
+        let overflow = self.inputs.len().saturating_sub(MAX_QUEUED_INPUTS);
+        self.inputs.drain(..overflow);
+    }
+
+    /// Releases everything, used when the window loses focus and releases are no longer seen
+    pub(crate) fn release_all(&mut self) {
+        self.held.clear();
+        self.modifiers = Modifiers::NONE;
+    }
+}
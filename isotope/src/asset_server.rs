@@ -1,13 +1,63 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, RwLock},
+};
 
+use anyhow::{Result, anyhow};
 use gpu_controller::{
     BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType, BufferBindingType, GpuController,
-    SamplerBindingType, ShaderStages, TextureSampleType, TextureViewDimension,
+    Mesh, SamplerBindingType, ShaderFileSystem, ShaderPreprocessor, ShaderStages, TextureArray,
+    TextureArrayBuilder, TextureSampleType, TextureViewDimension,
 };
-use matter_vault::MatterVault;
+use image::ImageReader;
+use log::{error, info, warn};
+use matter_vault::{MatterSharing, MatterVault, SharedMatter};
 use photon::renderer::defered_renderer::{
     ALBEDO_BINDING, MATERIAL_BINDING, NORMAL_BINDING, POSITION_BINDING, SAMPLER_BINDING,
 };
+use photon::renderer::{FRAME_CONSTANTS_WGSL, TEXTURE_ARRAY_WGSL};
+
+use crate::{
+    ImportSettings,
+    material::Material,
+    texture::IsotopeTexture,
+    vfs::{Vfs, VfsMount},
+};
+
+impl ShaderFileSystem for AssetServer {
+    fn read_shader(&self, path: &str) -> Result<String> {
+        let shader = match self.shaders.read() {
+            Ok(shaders) => shaders.get(path).cloned(),
+            Err(err) => return Err(anyhow!("Failed to read shaders: {}", err)),
+        };
+
+        match shader {
+            Some(shader) => Ok(shader),
+            None => self.vfs.read_to_string(path),
+        }
+    }
+}
+
+/// An asset that failed to load and was replaced by a fallback
+#[derive(Debug, Clone)]
+pub struct FailedLoad {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+/// How many meshes, materials and textures are loaded and how many handles
+/// share them, see `AssetServer::sharing_stats`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AssetSharingStats {
+    /// Meshes, with a handle for every model drawing one
+    pub meshes: MatterSharing,
+    /// Materials, with a handle for every model drawing with one
+    pub materials: MatterSharing,
+    /// Textures, with a handle for every material or override using one
+    pub textures: MatterSharing,
+}
 
 unsafe impl Send for AssetServer {}
 unsafe impl Sync for AssetServer {}
@@ -15,6 +65,10 @@ unsafe impl Sync for AssetServer {}
 pub struct AssetServer {
     pub(crate) asset_manager: Arc<MatterVault>,
     pub(crate) gpu_controller: Arc<GpuController>,
+    // Shaders added in memory, found by `#include` before the mounted files
+    shaders: RwLock<HashMap<String, String>>,
+    pub(crate) vfs: Vfs,
+    failed_loads: RwLock<Vec<FailedLoad>>,
 }
 
 impl AssetServer {
@@ -63,16 +117,48 @@ impl AssetServer {
                 "Global Transform".to_string(),
                 gpu_controller.create_bind_group_layout(&BindGroupLayoutDescriptor {
                     label: Some("Global Transform Bind Group Layout"),
-                    entries: &[BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: ShaderStages::VERTEX,
-                        ty: BindingType::Buffer {
-                            ty: BufferBindingType::Storage { read_only: true },
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
+                    entries: &[
+                        // Global Transform
+                        BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: ShaderStages::VERTEX,
+                            ty: BindingType::Buffer {
+                                ty: BufferBindingType::Storage { read_only: true },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
                         },
-                        count: None,
-                    }],
+                        // Material Overrides
+                        BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: ShaderStages::FRAGMENT,
+                            ty: BindingType::Buffer {
+                                ty: BufferBindingType::Storage { read_only: true },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        // Override Texture
+                        BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: ShaderStages::FRAGMENT,
+                            ty: BindingType::Texture {
+                                sample_type: TextureSampleType::Float { filterable: true },
+                                view_dimension: TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        // Override Sampler
+                        BindGroupLayoutEntry {
+                            binding: 3,
+                            visibility: ShaderStages::FRAGMENT,
+                            ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                            count: None,
+                        },
+                    ],
                 }),
             );
 
@@ -162,6 +248,28 @@ impl AssetServer {
                             },
                             count: None,
                         },
+                        // Light Probes
+                        BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: ShaderStages::FRAGMENT,
+                            ty: BindingType::Buffer {
+                                ty: BufferBindingType::Storage { read_only: true },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        // Probe Grid
+                        BindGroupLayoutEntry {
+                            binding: 3,
+                            visibility: ShaderStages::FRAGMENT,
+                            ty: BindingType::Buffer {
+                                ty: BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
                     ],
                 }),
             );
@@ -172,7 +280,7 @@ impl AssetServer {
                     label: Some("Camera Bind Group Layout Descriptor"),
                     entries: &[BindGroupLayoutEntry {
                         binding: 0,
-                        visibility: ShaderStages::VERTEX_FRAGMENT,
+                        visibility: ShaderStages::VERTEX_FRAGMENT | ShaderStages::COMPUTE,
                         ty: BindingType::Buffer {
                             ty: BufferBindingType::Uniform,
                             has_dynamic_offset: false,
@@ -182,11 +290,330 @@ impl AssetServer {
                     }],
                 }),
             );
+
+            layouts.insert(
+                "Culling".to_string(),
+                gpu_controller.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                    label: Some("Culling Bind Group Layout"),
+                    entries: &[
+                        // Global Transform
+                        BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: ShaderStages::COMPUTE,
+                            ty: BindingType::Buffer {
+                                ty: BufferBindingType::Storage { read_only: true },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        // Cull Info
+                        BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: ShaderStages::COMPUTE,
+                            ty: BindingType::Buffer {
+                                ty: BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        // Instances
+                        BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: ShaderStages::COMPUTE,
+                            ty: BindingType::Buffer {
+                                ty: BufferBindingType::Storage { read_only: true },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        // Visible Instances
+                        BindGroupLayoutEntry {
+                            binding: 3,
+                            visibility: ShaderStages::COMPUTE,
+                            ty: BindingType::Buffer {
+                                ty: BufferBindingType::Storage { read_only: false },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        // Draw Arguments
+                        BindGroupLayoutEntry {
+                            binding: 4,
+                            visibility: ShaderStages::COMPUTE,
+                            ty: BindingType::Buffer {
+                                ty: BufferBindingType::Storage { read_only: false },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                    ],
+                }),
+            );
         });
 
+        let shaders = HashMap::from([
+            (
+                "isotope/texture_array.wgsl".to_string(),
+                TEXTURE_ARRAY_WGSL.to_string(),
+            ),
+            (
+                "isotope/frame.wgsl".to_string(),
+                FRAME_CONSTANTS_WGSL.to_string(),
+            ),
+        ]);
+
         Self {
             asset_manager,
             gpu_controller,
+            shaders: RwLock::new(shaders),
+            vfs: Vfs::new(),
+            failed_loads: RwLock::new(Vec::new()),
         }
     }
+
+    /// Returns the assets that failed to load and were replaced by fallbacks.
+    ///
+    /// Missing textures are replaced by a magenta checker, missing materials
+    /// by the error material, and models loaded with
+    /// `Model::from_obj_or_placeholder` by a checkered unit cube.
+    pub fn failed_loads(&self) -> Vec<FailedLoad> {
+        match self.failed_loads.read() {
+            Ok(failed_loads) => failed_loads.clone(),
+            Err(err) => {
+                error!("Failed to read failed loads: {}", err);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Logs an asset that failed to load and records it for `failed_loads`
+    pub(crate) fn report_fallback<P>(&self, path: P, err: &anyhow::Error)
+    where
+        P: AsRef<Path>,
+    {
+        error!(
+            "Failed to load {:#?}, using a fallback instead: {}",
+            path.as_ref(),
+            err
+        );
+
+        match self.failed_loads.write() {
+            Ok(mut failed_loads) => failed_loads.push(FailedLoad {
+                path: path.as_ref().to_path_buf(),
+                error: err.to_string(),
+            }),
+            Err(err) => error!("Failed to record failed load: {}", err),
+        }
+    }
+
+    /// Returns how many meshes, materials and textures are loaded and how
+    /// often models share them.
+    ///
+    /// Meshes are shared by the file and object they were loaded from and
+    /// textures by their file, so models loading the same files reuse the
+    /// same GPU buffers and textures instead of uploading copies.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let stats = assets.sharing_stats();
+    /// info!("{} meshes drawn by {} models", stats.meshes.unique, stats.meshes.handles);
+    /// ```
+    pub fn sharing_stats(&self) -> AssetSharingStats {
+        AssetSharingStats {
+            meshes: self.asset_manager.sharing::<Mesh>(),
+            materials: self.asset_manager.sharing::<Material>(),
+            textures: self.asset_manager.sharing::<IsotopeTexture>(),
+        }
+    }
+
+    /// Returns the texture at a path, loading it the first time it is used.
+    ///
+    /// # Returns
+    /// The shared texture, or an error if it isn't loaded yet and fails to load
+    pub(crate) fn shared_texture<P>(&self, path: P) -> Result<SharedMatter<IsotopeTexture>>
+    where
+        P: AsRef<Path>,
+    {
+        let key = asset_key(&path);
+
+        self.asset_manager.share(&key).or_else(|_| {
+            self.asset_manager
+                .add(key, IsotopeTexture::new_from_path(&path, self)?)
+        })
+    }
+
+    /// Mounts a source of assets under a prefix, every loader reads through it.
+    ///
+    /// Mount a directory during development and a pack or embedded files in
+    /// shipped builds under the same prefix, and the asset paths of the game
+    /// stay the same. Paths without a `scheme://` prefix keep loading from disk.
+    ///
+    /// # Arguments
+    /// * `prefix` - Prefix the assets of the source are found under, like `game://`
+    /// * `source` - A `DirectoryMount`, `PackMount`, `EmbeddedMount` or custom `VfsMount`
+    ///
+    /// # Example
+    /// ```ignore
+    /// if cfg!(debug_assertions) {
+    ///     assets.mount("game://", DirectoryMount::new("assets"))?;
+    /// } else {
+    ///     assets.mount("game://", PackMount::open("assets.pack")?)?;
+    /// }
+    ///
+    /// let model = Model::from_obj("game://models/crate.obj", assets, None)?;
+    /// ```
+    pub fn mount<M>(&self, prefix: &str, source: M) -> Result<()>
+    where
+        M: VfsMount + 'static,
+    {
+        self.vfs.mount(prefix, source)
+    }
+
+    /// Removes the mount added last under a prefix, returns `false` if there was none
+    pub fn unmount(&self, prefix: &str) -> bool {
+        self.vfs.unmount(prefix)
+    }
+
+    /// Reads the bytes of an asset through the mounts
+    pub fn read_asset<P>(&self, path: P) -> Result<Vec<u8>>
+    where
+        P: AsRef<Path>,
+    {
+        self.vfs.read(path)
+    }
+
+    /// Returns the import settings of an asset from its `.meta` file.
+    ///
+    /// The metadata file is generated with default settings if the asset has
+    /// not been imported before.
+    ///
+    /// # Arguments
+    /// * `asset_path` - Path to the asset, not the metadata file
+    pub fn import_settings<P>(&self, asset_path: P) -> ImportSettings
+    where
+        P: AsRef<Path>,
+    {
+        ImportSettings::load_or_create(asset_path, &self.vfs)
+    }
+
+    /// Adds a shader in memory so other shaders can include it.
+    ///
+    /// Shaders added this way take precedence over files on disk with the
+    /// same path. The engine adds its own shared shaders under `isotope/`,
+    /// such as the texture array helpers as `isotope/texture_array.wgsl` and
+    /// the frame constants every pass binds at group 0 as `isotope/frame.wgsl`.
+    ///
+    /// # Arguments
+    /// * `path` - Path other shaders include the shader by
+    /// * `source` - WGSL source of the shader
+    pub fn add_shader(&self, path: impl Into<String>, source: impl Into<String>) {
+        match self.shaders.write() {
+            Ok(mut shaders) => _ = shaders.insert(path.into(), source.into()),
+            Err(err) => error!("Failed to add shader: {}", err),
+        }
+    }
+
+    /// Reads a shader and expands its `#include` and `#ifdef` directives.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the shader, in memory or in the mounts
+    /// * `defines` - Features defined for the `#ifdef` directives of the shader
+    ///
+    /// # Returns
+    /// The WGSL source ready to compile, or an error if a file is missing or a directive is invalid
+    ///
+    /// # Example
+    /// ```ignore
+    /// let source = assets.preprocess_shader("shaders/terrain.wgsl", &["SHADOWS"])?;
+    /// ```
+    pub fn preprocess_shader(&self, path: &str, defines: &[&str]) -> Result<String> {
+        ShaderPreprocessor::new(self)
+            .defines(defines.iter().copied())
+            .process(path)
+    }
+
+    /// Loads same-size images into the layers of a `TextureArray`, with mipmaps.
+    ///
+    /// The layers are sRGB if the import settings of the first image are, the
+    /// layer index of each image in the shaders is its index in `paths`.
+    ///
+    /// # Arguments
+    /// * `label` - Name of the texture array, used to label its GPU resources
+    /// * `paths` - Paths to the images of the layers
+    ///
+    /// # Returns
+    /// The texture array, or an error if an image could not be read or its size differs from the first
+    pub fn load_texture_array<P>(&self, label: &str, paths: &[P]) -> Result<TextureArray>
+    where
+        P: AsRef<Path>,
+    {
+        let first = paths
+            .first()
+            .ok_or_else(|| anyhow!("Texture array {} has no layers", label))?;
+        let srgb = self.import_settings(first).texture.srgb;
+
+        let mut builder = None;
+        for path in paths {
+            info!("Loading texture array layer: {:#?}", path.as_ref());
+
+            if self.import_settings(path).texture.srgb != srgb {
+                warn!(
+                    "Layer {:#?} of texture array {} differs in srgb from the first layer, using {}",
+                    path.as_ref(),
+                    label,
+                    srgb
+                );
+            }
+
+            let image = ImageReader::new(Cursor::new(self.read_asset(path)?))
+                .with_guessed_format()?
+                .decode()?
+                .to_rgba8();
+            let (width, height) = image.dimensions();
+
+            // The first layer decides the size of the array
+            builder = Some(
+                builder
+                    .unwrap_or_else(|| TextureArrayBuilder::new(label, width, height).srgb(srgb))
+                    .layer(image.into_raw()),
+            );
+        }
+
+        builder
+            .ok_or_else(|| anyhow!("Texture array {} has no layers", label))?
+            .build(self.gpu_controller.clone())
+    }
+}
+
+/// Returns the label an asset loaded from a file is shared under.
+///
+/// `.` and `..` are resolved so the same file reached through different
+/// relative paths, like a texture referenced by materials in different
+/// folders, is only loaded once.
+pub(crate) fn asset_key<P>(path: P) -> String
+where
+    P: AsRef<Path>,
+{
+    let mut resolved = PathBuf::new();
+    for component in path.as_ref().components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(
+                    resolved.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                resolved.pop();
+            }
+            component => resolved.push(component),
+        }
+    }
+
+    resolved.to_string_lossy().into_owned()
 }
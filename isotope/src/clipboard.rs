@@ -0,0 +1,65 @@
+use anyhow::{Result, anyhow};
+
+/// Connection to the system clipboard, opened the first time it is used.
+///
+/// The connection is kept open afterwards since on X11 and Wayland copied
+/// text is only available while the application that copied it holds on to
+/// the clipboard.
+#[derive(Default)]
+pub(crate) struct Clipboard {
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    system: std::sync::Mutex<Option<arboard::Clipboard>>,
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+impl Clipboard {
+    /// Gives the connection to the system clipboard, opening it if needed
+    fn with_system<F, R>(&self, callback: F) -> Result<R>
+    where
+        F: FnOnce(&mut arboard::Clipboard) -> Result<R>,
+    {
+        let mut system = self
+            .system
+            .lock()
+            .map_err(|err| anyhow!("Clipboard lock poisoned: {}", err))?;
+
+        let system = match system.as_mut() {
+            Some(system) => system,
+            None => system.insert(
+                arboard::Clipboard::new()
+                    .map_err(|err| anyhow!("No clipboard available: {}", err))?,
+            ),
+        };
+
+        callback(system)
+    }
+
+    /// Reads text from the system clipboard
+    pub(crate) fn read_text(&self) -> Result<String> {
+        self.with_system(|system| {
+            system
+                .get_text()
+                .map_err(|err| anyhow!("Failed to read clipboard: {}", err))
+        })
+    }
+
+    /// Writes text to the system clipboard
+    pub(crate) fn write_text(&self, text: &str) -> Result<()> {
+        self.with_system(|system| {
+            system
+                .set_text(text)
+                .map_err(|err| anyhow!("Failed to write clipboard: {}", err))
+        })
+    }
+}
+
+#[cfg(any(target_os = "android", target_os = "ios"))]
+impl Clipboard {
+    pub(crate) fn read_text(&self) -> Result<String> {
+        Err(anyhow!("No clipboard available on this platform"))
+    }
+
+    pub(crate) fn write_text(&self, _text: &str) -> Result<()> {
+        Err(anyhow!("No clipboard available on this platform"))
+    }
+}
@@ -0,0 +1,795 @@
+use std::path::Path;
+
+use anyhow::Result;
+use boson::{BosonBody, BosonObject, ColliderBuilder, RigidBody};
+use cgmath::{InnerSpace, Quaternion, Vector3, Zero};
+use gpu_controller::{Mesh, Vertex};
+use matter_vault::SharedMatter;
+
+use crate::{AssetServer, Model, material::load_first_material, model::read_obj_geometry};
+
+// Points closer to a plane than this are on the plane, so faces that are
+// nearly coplanar are treated as coplanar
+const PLANE_EPSILON: f64 = 1e-5;
+
+#[derive(Debug, Clone, Copy)]
+struct CsgVertex {
+    position: Vector3<f64>,
+    normal: Vector3<f64>,
+    uv: [f64; 2],
+}
+
+impl CsgVertex {
+    fn lerp(&self, other: &CsgVertex, t: f64) -> Self {
+        Self {
+            position: self.position + (other.position - self.position) * t,
+            normal: self.normal + (other.normal - self.normal) * t,
+            uv: [
+                self.uv[0] + (other.uv[0] - self.uv[0]) * t,
+                self.uv[1] + (other.uv[1] - self.uv[1]) * t,
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Plane {
+    normal: Vector3<f64>,
+    distance: f64,
+}
+
+impl Plane {
+    fn from_points(a: Vector3<f64>, b: Vector3<f64>, c: Vector3<f64>) -> Option<Self> {
+        let normal = (b - a).cross(c - a);
+        if normal.magnitude2() <= f64::EPSILON {
+            return None;
+        }
+
+        let normal = normal.normalize();
+        Some(Self {
+            normal,
+            distance: normal.dot(a),
+        })
+    }
+
+    fn flip(&mut self) {
+        self.normal = -self.normal;
+        self.distance = -self.distance;
+    }
+
+    /// Sorts a polygon into the lists of the side it is on, splitting it if it
+    /// spans the plane. Coplanar polygons go to the side their face points to.
+    fn split(
+        &self,
+        polygon: CsgPolygon,
+        coplanar_front: &mut Vec<CsgPolygon>,
+        coplanar_back: &mut Vec<CsgPolygon>,
+        front: &mut Vec<CsgPolygon>,
+        back: &mut Vec<CsgPolygon>,
+    ) {
+        const COPLANAR: u8 = 0;
+        const FRONT: u8 = 1;
+        const BACK: u8 = 2;
+        const SPANNING: u8 = 3;
+
+        let sides = polygon
+            .vertices
+            .iter()
+            .map(|vertex| {
+                let distance = self.normal.dot(vertex.position) - self.distance;
+                if distance < -PLANE_EPSILON {
+                    BACK
+                } else if distance > PLANE_EPSILON {
+                    FRONT
+                } else {
+                    COPLANAR
+                }
+            })
+            .collect::<Vec<_>>();
+
+        match sides.iter().fold(COPLANAR, |all, side| all | side) {
+            COPLANAR => {
+                if self.normal.dot(polygon.plane.normal) > 0.0 {
+                    coplanar_front.push(polygon);
+                } else {
+                    coplanar_back.push(polygon);
+                }
+            }
+            FRONT => front.push(polygon),
+            BACK => back.push(polygon),
+            _ => {
+                let mut front_vertices = Vec::new();
+                let mut back_vertices = Vec::new();
+
+                for (index, vertex) in polygon.vertices.iter().enumerate() {
+                    let next_index = (index + 1) % polygon.vertices.len();
+                    let next = &polygon.vertices[next_index];
+                    let (side, next_side) = (sides[index], sides[next_index]);
+
+                    if side != BACK {
+                        front_vertices.push(*vertex);
+                    }
+                    if side != FRONT {
+                        back_vertices.push(*vertex);
+                    }
+
+                    if side | next_side == SPANNING {
+                        let t = (self.distance - self.normal.dot(vertex.position))
+                            / self.normal.dot(next.position - vertex.position);
+                        let split = vertex.lerp(next, t);
+                        front_vertices.push(split);
+                        back_vertices.push(split);
+                    }
+                }
+
+                if front_vertices.len() >= 3 {
+                    front.push(CsgPolygon {
+                        vertices: front_vertices,
+                        plane: polygon.plane,
+                    });
+                }
+                if back_vertices.len() >= 3 {
+                    back.push(CsgPolygon {
+                        vertices: back_vertices,
+                        plane: polygon.plane,
+                    });
+                }
+            }
+        }
+    }
+}
+
+// A convex polygon, counter clockwise seen from the side it faces
+#[derive(Debug, Clone)]
+struct CsgPolygon {
+    vertices: Vec<CsgVertex>,
+    plane: Plane,
+}
+
+impl CsgPolygon {
+    fn new(vertices: Vec<CsgVertex>) -> Option<Self> {
+        let plane = Plane::from_points(
+            vertices.first()?.position,
+            vertices.get(1)?.position,
+            vertices.get(2)?.position,
+        )?;
+
+        Some(Self { vertices, plane })
+    }
+
+    fn flip(&mut self) {
+        self.vertices.reverse();
+        for vertex in self.vertices.iter_mut() {
+            vertex.normal = -vertex.normal;
+        }
+        self.plane.flip();
+    }
+}
+
+// Node of a BSP tree, the polygons of a node lie in its plane
+#[derive(Debug, Default)]
+struct BspNode {
+    plane: Option<Plane>,
+    front: Option<Box<BspNode>>,
+    back: Option<Box<BspNode>>,
+    polygons: Vec<CsgPolygon>,
+}
+
+impl BspNode {
+    fn new(polygons: Vec<CsgPolygon>) -> Self {
+        let mut node = Self::default();
+        node.build(polygons);
+        node
+    }
+
+    // Turns solid space into empty space and empty space into solid space
+    fn invert(&mut self) {
+        for polygon in self.polygons.iter_mut() {
+            polygon.flip();
+        }
+        if let Some(plane) = self.plane.as_mut() {
+            plane.flip();
+        }
+        if let Some(front) = self.front.as_mut() {
+            front.invert();
+        }
+        if let Some(back) = self.back.as_mut() {
+            back.invert();
+        }
+        std::mem::swap(&mut self.front, &mut self.back);
+    }
+
+    // Removes the parts of the polygons that are inside of this tree
+    fn clip_polygons(&self, polygons: Vec<CsgPolygon>) -> Vec<CsgPolygon> {
+        let Some(plane) = self.plane else {
+            return polygons;
+        };
+
+        let mut front = Vec::new();
+        let mut back = Vec::new();
+        for polygon in polygons {
+            // Coplanar polygons are kept with the side they face
+            let (mut coplanar_front, mut coplanar_back) = (Vec::new(), Vec::new());
+            plane.split(
+                polygon,
+                &mut coplanar_front,
+                &mut coplanar_back,
+                &mut front,
+                &mut back,
+            );
+            front.append(&mut coplanar_front);
+            back.append(&mut coplanar_back);
+        }
+
+        let mut front = match &self.front {
+            Some(node) => node.clip_polygons(front),
+            None => front,
+        };
+        let back = match &self.back {
+            Some(node) => node.clip_polygons(back),
+            // Behind a leaf is solid, so everything there is removed
+            None => Vec::new(),
+        };
+
+        front.extend(back);
+        front
+    }
+
+    // Removes the parts of this tree's polygons that are inside of `other`
+    fn clip_to(&mut self, other: &BspNode) {
+        self.polygons = other.clip_polygons(std::mem::take(&mut self.polygons));
+        if let Some(front) = self.front.as_mut() {
+            front.clip_to(other);
+        }
+        if let Some(back) = self.back.as_mut() {
+            back.clip_to(other);
+        }
+    }
+
+    fn all_polygons(&self) -> Vec<CsgPolygon> {
+        let mut polygons = self.polygons.clone();
+        if let Some(front) = &self.front {
+            polygons.extend(front.all_polygons());
+        }
+        if let Some(back) = &self.back {
+            polygons.extend(back.all_polygons());
+        }
+        polygons
+    }
+
+    fn build(&mut self, polygons: Vec<CsgPolygon>) {
+        if polygons.is_empty() {
+            return;
+        }
+
+        let plane = *self.plane.get_or_insert(polygons[0].plane);
+
+        let mut front = Vec::new();
+        let mut back = Vec::new();
+        for polygon in polygons {
+            let mut coplanar_back = Vec::new();
+            plane.split(
+                polygon,
+                &mut self.polygons,
+                &mut coplanar_back,
+                &mut front,
+                &mut back,
+            );
+            self.polygons.append(&mut coplanar_back);
+        }
+
+        if !front.is_empty() {
+            self.front.get_or_insert_default().build(front);
+        }
+        if !back.is_empty() {
+            self.back.get_or_insert_default().build(back);
+        }
+    }
+}
+
+/// A closed mesh that can be combined with others with boolean operations
+/// (constructive solid geometry), for blocking out levels and cutting holes
+/// into walls at runtime.
+///
+/// Meshes must be closed and their triangles wound counter clockwise when
+/// seen from the outside. Faces of both meshes that lie in the same plane
+/// are kept once, so boxes that touch merge without seams.
+///
+/// # Example
+/// ```ignore
+/// // A wall with a doorway cut into it
+/// let wall = CsgMesh::cube([4.0, 2.0, 0.2]);
+/// let doorway = CsgMesh::cube([0.6, 1.0, 0.5]).transformed([0.0, -1.0, 0.0], Quaternion::one());
+///
+/// let wall = wall.subtract(&doorway);
+/// compound.spawn((
+///     wall.to_model("Wall", Some("game://materials/brick.mtl"), &asset_server)?,
+///     Transform3D::default(),
+///     wall.rigid_body(0.0),
+/// ));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CsgMesh {
+    polygons: Vec<CsgPolygon>,
+}
+
+impl CsgMesh {
+    /// Creates a mesh from triangles.
+    ///
+    /// # Arguments
+    /// * `vertices` - The vertices of the mesh
+    /// * `indices` - The triangle indices of the mesh, triangles with no area are skipped
+    pub fn from_geometry(vertices: &[Vertex], indices: &[u32]) -> Self {
+        let polygons = indices
+            .chunks_exact(3)
+            .filter_map(|triangle| {
+                CsgPolygon::new(
+                    triangle
+                        .iter()
+                        .map(|index| {
+                            let vertex = vertices[*index as usize];
+                            CsgVertex {
+                                position: Vector3::from(vertex.position.map(f64::from)),
+                                normal: Vector3::from(vertex.normal_vec.map(f64::from)),
+                                uv: vertex.uv_coord.map(f64::from),
+                            }
+                        })
+                        .collect(),
+                )
+            })
+            .collect();
+
+        Self { polygons }
+    }
+
+    /// Reads a mesh from an OBJ file, all objects in the file are merged
+    pub fn from_obj<P>(path: P, asset_server: &AssetServer) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let (vertices, indices) = read_obj_geometry(path, asset_server)?;
+        Ok(Self::from_geometry(&vertices, &indices))
+    }
+
+    /// Creates a box centered on the origin
+    pub fn cube(half_extents: [f32; 3]) -> Self {
+        let half_extents = Vector3::from(half_extents.map(f64::from));
+
+        // Every face as its normal and its corners in counter clockwise order
+        let faces = [
+            (
+                [1.0, 0.0, 0.0],
+                [[1, -1, -1], [1, 1, -1], [1, 1, 1], [1, -1, 1]],
+            ),
+            (
+                [-1.0, 0.0, 0.0],
+                [[-1, -1, 1], [-1, 1, 1], [-1, 1, -1], [-1, -1, -1]],
+            ),
+            (
+                [0.0, 1.0, 0.0],
+                [[-1, 1, -1], [-1, 1, 1], [1, 1, 1], [1, 1, -1]],
+            ),
+            (
+                [0.0, -1.0, 0.0],
+                [[-1, -1, 1], [-1, -1, -1], [1, -1, -1], [1, -1, 1]],
+            ),
+            (
+                [0.0, 0.0, 1.0],
+                [[1, -1, 1], [1, 1, 1], [-1, 1, 1], [-1, -1, 1]],
+            ),
+            (
+                [0.0, 0.0, -1.0],
+                [[-1, -1, -1], [-1, 1, -1], [1, 1, -1], [1, -1, -1]],
+            ),
+        ];
+        let uvs = [[0.0, 1.0], [0.0, 0.0], [1.0, 0.0], [1.0, 1.0]];
+
+        let polygons = faces
+            .iter()
+            .filter_map(|(normal, corners)| {
+                CsgPolygon::new(
+                    corners
+                        .iter()
+                        .zip(uvs)
+                        .map(|(corner, uv)| CsgVertex {
+                            position: Vector3::new(
+                                corner[0] as f64 * half_extents.x,
+                                corner[1] as f64 * half_extents.y,
+                                corner[2] as f64 * half_extents.z,
+                            ),
+                            normal: Vector3::from(*normal),
+                            uv,
+                        })
+                        .collect(),
+                )
+            })
+            .collect();
+
+        Self { polygons }
+    }
+
+    /// Creates a cylinder along the Y axis centered on the origin
+    ///
+    /// # Arguments
+    /// * `radius` - The radius of the cylinder
+    /// * `height` - The height of the cylinder
+    /// * `segments` - The number of sides around the cylinder, at least 3
+    pub fn cylinder(radius: f32, height: f32, segments: usize) -> Self {
+        let (radius, half_height) = (radius as f64, height as f64 * 0.5);
+        let segments = segments.max(3);
+
+        let around = |index: usize| {
+            let angle = index as f64 / segments as f64 * std::f64::consts::TAU;
+            Vector3::new(angle.cos(), 0.0, -angle.sin())
+        };
+        let vertex = |position: Vector3<f64>, normal: Vector3<f64>, uv: [f64; 2]| CsgVertex {
+            position,
+            normal,
+            uv,
+        };
+
+        let mut polygons = Vec::new();
+        let (top, bottom) = (
+            Vector3::unit_y() * half_height,
+            -Vector3::unit_y() * half_height,
+        );
+
+        for index in 0..segments {
+            let (start, end) = (around(index), around(index + 1));
+            let (u0, u1) = (
+                index as f64 / segments as f64,
+                (index + 1) as f64 / segments as f64,
+            );
+
+            polygons.extend(CsgPolygon::new(vec![
+                vertex(bottom + start * radius, start, [u0, 1.0]),
+                vertex(bottom + end * radius, end, [u1, 1.0]),
+                vertex(top + end * radius, end, [u1, 0.0]),
+                vertex(top + start * radius, start, [u0, 0.0]),
+            ]));
+
+            let cap_uv =
+                |direction: Vector3<f64>| [0.5 + direction.x * 0.5, 0.5 + direction.z * 0.5];
+            polygons.extend(CsgPolygon::new(vec![
+                vertex(top, Vector3::unit_y(), [0.5, 0.5]),
+                vertex(top + start * radius, Vector3::unit_y(), cap_uv(start)),
+                vertex(top + end * radius, Vector3::unit_y(), cap_uv(end)),
+            ]));
+            polygons.extend(CsgPolygon::new(vec![
+                vertex(bottom, -Vector3::unit_y(), [0.5, 0.5]),
+                vertex(bottom + end * radius, -Vector3::unit_y(), cap_uv(end)),
+                vertex(bottom + start * radius, -Vector3::unit_y(), cap_uv(start)),
+            ]));
+        }
+
+        Self { polygons }
+    }
+
+    /// Returns the mesh rotated and then moved
+    pub fn transformed<V, Q>(&self, position: V, rotation: Q) -> Self
+    where
+        V: Into<Vector3<f32>>,
+        Q: Into<Quaternion<f32>>,
+    {
+        let position = position.into().cast().unwrap_or(Vector3::zero());
+        let rotation: Quaternion<f64> = rotation
+            .into()
+            .cast()
+            .unwrap_or(Quaternion::new(1.0, 0.0, 0.0, 0.0));
+
+        let polygons = self
+            .polygons
+            .iter()
+            .filter_map(|polygon| {
+                CsgPolygon::new(
+                    polygon
+                        .vertices
+                        .iter()
+                        .map(|vertex| CsgVertex {
+                            position: rotation * vertex.position + position,
+                            normal: rotation * vertex.normal,
+                            uv: vertex.uv,
+                        })
+                        .collect(),
+                )
+            })
+            .collect();
+
+        Self { polygons }
+    }
+
+    /// Returns the space inside of either mesh
+    pub fn union(&self, other: &CsgMesh) -> Self {
+        let mut a = BspNode::new(self.polygons.clone());
+        let mut b = BspNode::new(other.polygons.clone());
+
+        a.clip_to(&b);
+        b.clip_to(&a);
+        b.invert();
+        b.clip_to(&a);
+        b.invert();
+        a.build(b.all_polygons());
+
+        Self {
+            polygons: a.all_polygons(),
+        }
+    }
+
+    /// Returns the space inside of this mesh but not inside of `other`
+    pub fn subtract(&self, other: &CsgMesh) -> Self {
+        let mut a = BspNode::new(self.polygons.clone());
+        let mut b = BspNode::new(other.polygons.clone());
+
+        a.invert();
+        a.clip_to(&b);
+        b.clip_to(&a);
+        b.invert();
+        b.clip_to(&a);
+        b.invert();
+        a.build(b.all_polygons());
+        a.invert();
+
+        Self {
+            polygons: a.all_polygons(),
+        }
+    }
+
+    /// Returns the space inside of both meshes
+    pub fn intersect(&self, other: &CsgMesh) -> Self {
+        let mut a = BspNode::new(self.polygons.clone());
+        let mut b = BspNode::new(other.polygons.clone());
+
+        a.invert();
+        b.clip_to(&a);
+        b.invert();
+        a.clip_to(&b);
+        b.clip_to(&a);
+        a.build(b.all_polygons());
+        a.invert();
+
+        Self {
+            polygons: a.all_polygons(),
+        }
+    }
+
+    /// Returns whether the mesh has no faces, such as the intersection of meshes that don't touch
+    pub fn is_empty(&self) -> bool {
+        self.polygons.is_empty()
+    }
+
+    /// Triangulates the mesh.
+    ///
+    /// # Returns
+    /// The vertices and triangle indices of the mesh
+    pub fn to_geometry(&self) -> (Vec<Vertex>, Vec<u32>) {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+
+        for polygon in self.polygons.iter() {
+            let base = vertices.len() as u32;
+
+            vertices.extend(polygon.vertices.iter().map(|vertex| {
+                let normal = if vertex.normal.magnitude2() > 0.0 {
+                    vertex.normal.normalize()
+                } else {
+                    polygon.plane.normal
+                };
+
+                Vertex {
+                    position: vertex.position.map(|axis| axis as f32).into(),
+                    uv_coord: vertex.uv.map(|axis| axis as f32),
+                    normal_vec: normal.map(|axis| axis as f32).into(),
+                }
+            }));
+
+            // Fan triangulation of the convex polygon
+            for index in 1..polygon.vertices.len() as u32 - 1 {
+                indices.extend_from_slice(&[base, base + index, base + index + 1]);
+            }
+        }
+
+        (vertices, indices)
+    }
+
+    /// Buffers the mesh on the GPU.
+    ///
+    /// The mesh isn't shared through the asset server, so meshes that are cut
+    /// again and again free their old versions.
+    pub fn to_mesh(&self, label: impl Into<String>, asset_server: &AssetServer) -> Mesh {
+        let (vertices, indices) = self.to_geometry();
+
+        Mesh::new(
+            asset_server.gpu_controller.clone(),
+            label.into(),
+            &vertices,
+            &indices,
+        )
+    }
+
+    /// Creates a model that draws the mesh.
+    ///
+    /// # Arguments
+    /// * `label` - The label of the mesh
+    /// * `material` - Path to an MTL file whose first material the mesh is
+    ///   drawn with, `None` for the error material
+    /// * `asset_server` - The asset server to create the model with
+    pub fn to_model(
+        &self,
+        label: impl Into<String>,
+        material: Option<&str>,
+        asset_server: &AssetServer,
+    ) -> Result<Model> {
+        let mesh = SharedMatter::new(self.to_mesh(label, asset_server));
+        let material = load_first_material(material, asset_server)?;
+
+        Model::from_meshes(vec![(Some(0), mesh)], vec![material], asset_server, None)
+    }
+
+    /// Creates a rigid body that collides with the mesh.
+    ///
+    /// Colliders are convex, so the collider is the convex hull of the mesh and
+    /// fills in any holes or dents cut into it.
+    ///
+    /// # Arguments
+    /// * `mass` - The mass of the body, a mass of 0 makes it immovable
+    pub fn rigid_body(&self, mass: f64) -> BosonObject {
+        let points = self
+            .polygons
+            .iter()
+            .flat_map(|polygon| polygon.vertices.iter())
+            .map(|vertex| vertex.position.map(|axis| axis as f32).into())
+            .collect::<Vec<[f32; 3]>>();
+
+        let boson_object = RigidBody::new(mass, ColliderBuilder::FromModelConvexHull);
+        boson_object.modify_body(|body| {
+            if let BosonBody::RigidBody(rigid_body) = body {
+                rigid_body.build_collider_from_points(&points);
+            }
+        });
+
+        boson_object
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 1e-4;
+
+    // Volume enclosed by the mesh, negative when it is wound inwards
+    fn volume(mesh: &CsgMesh) -> f64 {
+        mesh.polygons
+            .iter()
+            .flat_map(|polygon| {
+                (1..polygon.vertices.len() - 1).map(|index| {
+                    let a = polygon.vertices[0].position;
+                    let b = polygon.vertices[index].position;
+                    let c = polygon.vertices[index + 1].position;
+                    a.dot(b.cross(c)) / 6.0
+                })
+            })
+            .sum()
+    }
+
+    // Sum of the area weighted normals, which is zero for a closed mesh
+    fn vector_area(mesh: &CsgMesh) -> Vector3<f64> {
+        mesh.polygons
+            .iter()
+            .flat_map(|polygon| {
+                (1..polygon.vertices.len() - 1).map(|index| {
+                    let a = polygon.vertices[0].position;
+                    let b = polygon.vertices[index].position;
+                    let c = polygon.vertices[index + 1].position;
+                    (b - a).cross(c - a) * 0.5
+                })
+            })
+            .fold(Vector3::zero(), |sum, area| sum + area)
+    }
+
+    fn bounds(mesh: &CsgMesh) -> (Vector3<f64>, Vector3<f64>) {
+        mesh.polygons
+            .iter()
+            .flat_map(|polygon| polygon.vertices.iter())
+            .fold(
+                (Vector3::from([f64::MAX; 3]), Vector3::from([f64::MIN; 3])),
+                |(min, max), vertex| {
+                    (
+                        Vector3::new(
+                            min.x.min(vertex.position.x),
+                            min.y.min(vertex.position.y),
+                            min.z.min(vertex.position.z),
+                        ),
+                        Vector3::new(
+                            max.x.max(vertex.position.x),
+                            max.y.max(vertex.position.y),
+                            max.z.max(vertex.position.z),
+                        ),
+                    )
+                },
+            )
+    }
+
+    fn assert_solid(mesh: &CsgMesh, expected_volume: f64, min: [f64; 3], max: [f64; 3]) {
+        assert!(
+            vector_area(mesh).magnitude() < EPSILON,
+            "mesh is not closed, its faces sum to {:?}",
+            vector_area(mesh)
+        );
+        assert!(
+            (volume(mesh) - expected_volume).abs() < EPSILON,
+            "expected a volume of {} but got {}",
+            expected_volume,
+            volume(mesh)
+        );
+
+        let (mesh_min, mesh_max) = bounds(mesh);
+        assert!((mesh_min - Vector3::from(min)).magnitude() < EPSILON);
+        assert!((mesh_max - Vector3::from(max)).magnitude() < EPSILON);
+    }
+
+    // Two cubes with an edge length of 2, the second moved along X by `offset`
+    fn cubes(offset: f32) -> (CsgMesh, CsgMesh) {
+        let cube = CsgMesh::cube([1.0, 1.0, 1.0]);
+        let moved = cube.transformed([offset, 0.0, 0.0], Quaternion::new(1.0, 0.0, 0.0, 0.0));
+        (cube, moved)
+    }
+
+    #[test]
+    fn test_cube() {
+        let (cube, _) = cubes(0.0);
+        assert_solid(&cube, 8.0, [-1.0; 3], [1.0; 3]);
+    }
+
+    #[test]
+    fn test_overlapping_union() {
+        let (a, b) = cubes(1.0);
+        assert_solid(&a.union(&b), 12.0, [-1.0, -1.0, -1.0], [2.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_overlapping_subtract() {
+        let (a, b) = cubes(1.0);
+        assert_solid(&a.subtract(&b), 4.0, [-1.0, -1.0, -1.0], [0.0, 1.0, 1.0]);
+        assert_solid(&b.subtract(&a), 4.0, [1.0, -1.0, -1.0], [2.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_overlapping_intersect() {
+        let (a, b) = cubes(1.0);
+        assert_solid(&a.intersect(&b), 4.0, [0.0, -1.0, -1.0], [1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_face_sharing_union() {
+        let (a, b) = cubes(2.0);
+        let union = a.union(&b);
+        assert_solid(&union, 16.0, [-1.0, -1.0, -1.0], [3.0, 1.0, 1.0]);
+
+        // The shared face is inside of the union, so it is removed
+        assert!(union.polygons.iter().all(|polygon| {
+            polygon
+                .vertices
+                .iter()
+                .any(|vertex| (vertex.position.x - 1.0).abs() > EPSILON)
+        }));
+    }
+
+    #[test]
+    fn test_face_sharing_subtract() {
+        let (a, b) = cubes(2.0);
+        assert_solid(&a.subtract(&b), 8.0, [-1.0; 3], [1.0; 3]);
+    }
+
+    #[test]
+    fn test_face_sharing_intersect() {
+        let (a, b) = cubes(2.0);
+        assert!(volume(&a.intersect(&b)).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_disjoint_intersect_is_empty() {
+        let (a, b) = cubes(3.0);
+        assert!(a.intersect(&b).is_empty());
+    }
+}
@@ -0,0 +1,228 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::Path,
+};
+
+use anyhow::{Result, anyhow};
+use compound::{Compound, TypeRegistry};
+use log::{debug, error, info};
+
+/// Default number of ticks whose hashes are kept
+pub const DEFAULT_DETERMINISM_HISTORY: usize = 600;
+
+/// A tick where the state of the world didn't match the expected hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeterminismMismatch {
+    /// Fixed tick of the world, counted from when the audit was inserted
+    pub tick: u64,
+    pub expected: u64,
+    pub actual: u64,
+}
+
+/// Determinism audit mode, which hashes the state of the world every fixed tick.
+///
+/// Inserting the audit as a resource of a world turns it on. After every
+/// `IsotopeState::fixed_update` the molecules and resources of the registry
+/// are hashed with `Compound::state_hash` and compared against the hashes
+/// expected for that tick, recorded by another run with `save_hashes` or sent
+/// by a network peer. The first tick that doesn't match is where the simulation
+/// stopped being deterministic.
+///
+/// # Example
+/// ```ignore
+/// let mut registry = TypeRegistry::new();
+/// registry.register::<Transform3D>("transform").register::<Health>("health");
+///
+/// let mut audit = DeterminismAudit::new(registry);
+/// audit.load_reference("replays/run_1.hashes")?;
+/// compound.insert_resource(audit);
+///
+/// // Later, with hashes from a lockstep peer
+/// compound.resource_mut::<DeterminismAudit, _>(|audit| {
+///     audit.expect(peer_tick, peer_hash);
+///     for mismatch in audit.drain_mismatches() {
+///         // Desynced
+///     }
+/// });
+/// ```
+pub struct DeterminismAudit {
+    registry: TypeRegistry,
+    tick: u64,
+    history: VecDeque<(u64, u64)>,
+    history_len: usize,
+    expected: BTreeMap<u64, u64>,
+    mismatches: Vec<DeterminismMismatch>,
+    first_divergence: Option<u64>,
+    log_hashes: bool,
+}
+
+impl DeterminismAudit {
+    /// Creates an audit of the types in the registry
+    pub fn new(registry: TypeRegistry) -> Self {
+        Self {
+            registry,
+            tick: 0,
+            history: VecDeque::new(),
+            history_len: DEFAULT_DETERMINISM_HISTORY,
+            expected: BTreeMap::new(),
+            mismatches: Vec::new(),
+            first_divergence: None,
+            log_hashes: false,
+        }
+    }
+
+    /// Sets how many ticks of hashes are kept to compare and save
+    pub fn with_history(mut self, ticks: usize) -> Self {
+        self.history_len = ticks;
+        self
+    }
+
+    /// Logs the hash of every tick
+    pub fn with_logging(mut self, log_hashes: bool) -> Self {
+        self.log_hashes = log_hashes;
+        self
+    }
+
+    /// Returns the number of fixed ticks that have been hashed
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Returns the kept hashes as `(tick, hash)`, oldest first
+    pub fn hashes(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.history.iter().copied()
+    }
+
+    /// Returns the hash of a tick if it is still kept
+    pub fn hash_at(&self, tick: u64) -> Option<u64> {
+        self.history
+            .iter()
+            .find(|(hashed, _)| *hashed == tick)
+            .map(|(_, hash)| *hash)
+    }
+
+    /// Sets the hash a tick is expected to have, such as one sent by a peer.
+    ///
+    /// Ticks that were already hashed are compared right away.
+    pub fn expect(&mut self, tick: u64, hash: u64) {
+        if tick < self.tick {
+            if let Some(actual) = self.hash_at(tick) {
+                self.compare(tick, hash, actual);
+            } else {
+                debug!("Expected hash of tick {} is no longer kept", tick);
+            }
+            return;
+        }
+
+        self.expected.insert(tick, hash);
+    }
+
+    /// Returns the first tick that didn't match its expected hash
+    pub fn first_divergence(&self) -> Option<u64> {
+        self.first_divergence
+    }
+
+    /// Takes the mismatches found since the last time they were drained
+    pub fn drain_mismatches(&mut self) -> Vec<DeterminismMismatch> {
+        std::mem::take(&mut self.mismatches)
+    }
+
+    /// Expects the hashes saved by another run with `save_hashes`.
+    ///
+    /// # Arguments
+    /// * `path` - File with a `tick hash` line per tick, hashes in hexadecimal
+    pub fn load_reference<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let source = std::fs::read_to_string(path.as_ref())?;
+
+        for (line_number, line) in source.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let tokens = line.split_whitespace().collect::<Vec<_>>();
+            match tokens.as_slice() {
+                [] => {}
+                [tick, hash] => {
+                    let tick = tick.parse::<u64>()?;
+                    let hash = u64::from_str_radix(hash.trim_start_matches("0x"), 16)?;
+                    self.expect(tick, hash);
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "Invalid hash on line {} of {:#?}",
+                        line_number + 1,
+                        path.as_ref()
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Saves the kept hashes for `load_reference` in another run
+    pub fn save_hashes<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let hashes = self
+            .history
+            .iter()
+            .map(|(tick, hash)| format!("{} {:016x}\n", tick, hash))
+            .collect::<String>();
+
+        std::fs::write(path.as_ref(), hashes)?;
+        info!(
+            "Saved {} tick hashes to {:#?}",
+            self.history.len(),
+            path.as_ref()
+        );
+
+        Ok(())
+    }
+
+    fn record(&mut self, hash: u64) {
+        let tick = self.tick;
+        self.tick += 1;
+
+        if self.log_hashes {
+            info!("Tick {} hash {:016x}", tick, hash);
+        }
+
+        if let Some(expected) = self.expected.remove(&tick) {
+            self.compare(tick, expected, hash);
+        }
+
+        self.history.push_back((tick, hash));
+        while self.history.len() > self.history_len {
+            self.history.pop_front();
+        }
+    }
+
+    fn compare(&mut self, tick: u64, expected: u64, actual: u64) {
+        if expected == actual {
+            return;
+        }
+
+        // Every tick after the first desync differs as well
+        if self.first_divergence.is_none_or(|first| tick < first) {
+            self.first_divergence = Some(tick);
+            error!(
+                "World diverged on tick {}: expected hash {:016x}, got {:016x}",
+                tick, expected, actual
+            );
+        } else {
+            debug!("Tick {} also diverged", tick);
+        }
+
+        self.mismatches.push(DeterminismMismatch {
+            tick,
+            expected,
+            actual,
+        });
+    }
+}
+
+/// Hashes the state of a world after a fixed tick if it is being audited
+pub(crate) fn audit_determinism(compound: &Compound) {
+    compound.resource_mut::<DeterminismAudit, _>(|audit| {
+        match compound.state_hash(&audit.registry) {
+            Ok(hash) => audit.record(hash),
+            Err(err) => error!("Failed to hash tick {}: {}", audit.tick, err),
+        }
+    });
+}
@@ -0,0 +1,601 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use anyhow::{Result, anyhow};
+use compound::Compound;
+use log::{debug, warn};
+
+use crate::AssetServer;
+
+use super::time_scales;
+
+/// The value of a parameter of an animator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnimatorParameter {
+    Float(f32),
+    Bool(bool),
+    /// Set until a transition that checks it is taken
+    Trigger(bool),
+}
+
+/// What a state plays.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlendTree {
+    /// Plays one clip
+    Clip(String),
+    /// Blends between the clips on either side of the value of a float
+    /// parameter, the clips are sorted by their threshold
+    Blend1D {
+        parameter: String,
+        clips: Vec<(String, f32)>,
+    },
+}
+
+/// A state of an animator controller.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimatorState {
+    pub name: String,
+    pub tree: BlendTree,
+    /// Multiplier of how fast the clips play
+    pub speed: f32,
+    /// Seconds it takes to play the state once, at which `exit` conditions pass
+    pub length: f32,
+    /// Whether the clips start over once the state has played
+    pub looping: bool,
+}
+
+/// A check of a transition on the parameters of an animator.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransitionCondition {
+    Greater(String, f32),
+    Less(String, f32),
+    /// A bool parameter is true or a trigger is set
+    IsSet(String),
+    /// A bool parameter is false
+    NotSet(String),
+    /// The state has played through once
+    Exit,
+}
+
+/// A change from one state to another once all of its conditions pass.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimatorTransition {
+    /// The state the transition leaves, none to leave any state
+    pub from: Option<String>,
+    pub to: String,
+    /// Seconds the states are crossfaded over
+    pub duration: f32,
+    pub conditions: Vec<TransitionCondition>,
+}
+
+/// The states, parameters and transitions of an animator, loaded from a
+/// `.animator` asset and shared by every animator that uses it.
+///
+/// Transitions are checked in the order they are written and the first one
+/// whose conditions all pass is taken. Conditions are `speed>0.5`,
+/// `speed<0.5`, `grounded` for a bool that is true or a trigger that is set,
+/// `!grounded` for a bool that is false and `exit` once the state has played
+/// through.
+///
+/// # Example
+/// ```ignore
+/// # game://animation/character.animator
+/// parameter speed float 0
+/// parameter grounded bool true
+/// parameter jump trigger
+///
+/// state locomotion
+/// blend speed idle 0 walk 2 run 6
+///
+/// state jump
+/// clip jump
+/// length 0.8
+/// loop false
+///
+/// transition locomotion jump 0.1 jump grounded
+/// transition jump locomotion 0.25 exit grounded
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnimatorController {
+    pub parameters: HashMap<String, AnimatorParameter>,
+    pub states: Vec<AnimatorState>,
+    pub transitions: Vec<AnimatorTransition>,
+    /// The state animators start in, the first state if none
+    pub default_state: Option<String>,
+}
+
+impl AnimatorController {
+    /// Loads an animator controller from an asset.
+    ///
+    /// # Arguments
+    /// * `asset_server` - The asset server to read the asset from
+    /// * `path` - Path of the `.animator` asset
+    ///
+    /// # Returns
+    /// The controller, or an error if the asset could not be read or is invalid
+    pub fn load<P>(asset_server: &AssetServer, path: P) -> Result<Arc<Self>>
+    where
+        P: AsRef<Path>,
+    {
+        let source = String::from_utf8(asset_server.read_asset(path)?)?;
+
+        Ok(Arc::new(Self::parse(&source)?))
+    }
+
+    /// Parses the contents of a `.animator` asset
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut controller = Self::default();
+
+        for line in source.lines() {
+            let tokens = line.split_whitespace().collect::<Vec<_>>();
+
+            if tokens.is_empty() || tokens[0].starts_with('#') {
+                continue;
+            }
+
+            let value = |index: usize| {
+                tokens
+                    .get(index)
+                    .copied()
+                    .ok_or_else(|| anyhow!("Missing value for {}", tokens[0]))
+            };
+
+            match tokens[0] {
+                "parameter" => {
+                    let parameter = match value(2)? {
+                        "float" => AnimatorParameter::Float(
+                            tokens.get(3).map_or(Ok(0.0), |value| value.parse())?,
+                        ),
+                        "bool" => AnimatorParameter::Bool(
+                            tokens.get(3).map_or(Ok(false), |value| value.parse())?,
+                        ),
+                        "trigger" => AnimatorParameter::Trigger(false),
+                        other => return Err(anyhow!("Unknown parameter type: {}", other)),
+                    };
+                    controller
+                        .parameters
+                        .insert(value(1)?.to_string(), parameter);
+                }
+                "state" => {
+                    controller.states.push(AnimatorState {
+                        name: value(1)?.to_string(),
+                        tree: BlendTree::Clip(value(1)?.to_string()),
+                        speed: 1.0,
+                        length: 1.0,
+                        looping: true,
+                    });
+                }
+                "clip" => {
+                    last_state(&mut controller.states, tokens[0])?.tree =
+                        BlendTree::Clip(value(1)?.to_string())
+                }
+                "blend" => {
+                    let mut clips = tokens
+                        .get(2..)
+                        .unwrap_or_default()
+                        .chunks(2)
+                        .map(|clip| match clip {
+                            [name, threshold] => Ok((name.to_string(), threshold.parse::<f32>()?)),
+                            _ => Err(anyhow!("Missing threshold for {}", clip[0])),
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    clips.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+
+                    last_state(&mut controller.states, tokens[0])?.tree = BlendTree::Blend1D {
+                        parameter: value(1)?.to_string(),
+                        clips,
+                    };
+                }
+                "speed" => {
+                    last_state(&mut controller.states, tokens[0])?.speed = value(1)?.parse()?
+                }
+                "length" => {
+                    last_state(&mut controller.states, tokens[0])?.length = value(1)?.parse()?
+                }
+                "loop" => {
+                    last_state(&mut controller.states, tokens[0])?.looping = value(1)?.parse()?
+                }
+                "default" => controller.default_state = Some(value(1)?.to_string()),
+                "transition" => {
+                    let conditions = tokens
+                        .get(4..)
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|condition| parse_condition(condition))
+                        .collect::<Result<Vec<_>>>()?;
+
+                    controller.transitions.push(AnimatorTransition {
+                        from: match value(1)? {
+                            "any" => None,
+                            from => Some(from.to_string()),
+                        },
+                        to: value(2)?.to_string(),
+                        duration: value(3)?.parse()?,
+                        conditions,
+                    });
+                }
+                _ => debug!("Ignoring unknown animator key: {}", tokens[0]),
+            }
+        }
+
+        controller.validate()?;
+        Ok(controller)
+    }
+
+    pub fn state(&self, name: &str) -> Option<&AnimatorState> {
+        self.states.iter().find(|state| state.name == name)
+    }
+
+    /// Checks that everything the controller refers to exists
+    fn validate(&self) -> Result<()> {
+        let state_exists = |name: &str| {
+            self.state(name)
+                .map(|_| ())
+                .ok_or_else(|| anyhow!("Unknown animator state: {}", name))
+        };
+
+        if self.states.is_empty() {
+            return Err(anyhow!("Animator controller has no states"));
+        }
+
+        if let Some(default_state) = &self.default_state {
+            state_exists(default_state)?;
+        }
+
+        for transition in self.transitions.iter() {
+            if let Some(from) = &transition.from {
+                state_exists(from)?;
+            }
+            state_exists(&transition.to)?;
+
+            for condition in transition.conditions.iter() {
+                match condition {
+                    TransitionCondition::Greater(parameter, _)
+                    | TransitionCondition::Less(parameter, _)
+                    | TransitionCondition::IsSet(parameter)
+                    | TransitionCondition::NotSet(parameter) => {
+                        if !self.parameters.contains_key(parameter) {
+                            return Err(anyhow!("Unknown animator parameter: {}", parameter));
+                        }
+                    }
+                    TransitionCondition::Exit => {}
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the state the keys that follow it belong to
+fn last_state<'a>(states: &'a mut [AnimatorState], key: &str) -> Result<&'a mut AnimatorState> {
+    states
+        .last_mut()
+        .ok_or_else(|| anyhow!("{} outside of a state", key))
+}
+
+fn parse_condition(condition: &str) -> Result<TransitionCondition> {
+    if condition == "exit" {
+        return Ok(TransitionCondition::Exit);
+    }
+
+    if let Some((parameter, value)) = condition.split_once('>') {
+        return Ok(TransitionCondition::Greater(
+            parameter.to_string(),
+            value.parse()?,
+        ));
+    }
+
+    if let Some((parameter, value)) = condition.split_once('<') {
+        return Ok(TransitionCondition::Less(
+            parameter.to_string(),
+            value.parse()?,
+        ));
+    }
+
+    Ok(match condition.strip_prefix('!') {
+        Some(parameter) => TransitionCondition::NotSet(parameter.to_string()),
+        None => TransitionCondition::IsSet(condition.to_string()),
+    })
+}
+
+/// How much a clip contributes to the pose of an animator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClipWeight {
+    pub clip: String,
+    pub weight: f32,
+    /// Seconds into the clip
+    pub time: f32,
+}
+
+/// What gameplay is told about the state changes of an animator.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnimatorEvent {
+    /// A transition was taken, the states crossfade over its duration
+    StateChanged { from: String, to: String },
+}
+
+#[derive(Debug, Clone)]
+struct PlayingState {
+    index: usize,
+    // Seconds the state has been playing, scaled by its speed
+    elapsed: f32,
+}
+
+/// Component that runs an animator controller with its own parameters.
+///
+/// Gameplay sets the parameters, such as the speed of the character, and
+/// every tick the engine advances the states, takes transitions whose
+/// conditions pass and works out the weight of every clip. Whatever plays the
+/// clips reads them from `pose`.
+///
+/// # Example
+/// ```ignore
+/// let controller = AnimatorController::load(&asset_server, "game://animation/character.animator")?;
+/// compound.spawn((Transform3D::default(), Animator::new(controller)));
+///
+/// // In IsotopeState::update
+/// compound.iter_mut_duo(|_entity, animator: &mut Animator, agent: &mut CrowdAgent| {
+///     animator.set_float("speed", agent.velocity().magnitude());
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct Animator {
+    controller: Arc<AnimatorController>,
+    parameters: HashMap<String, AnimatorParameter>,
+    current: PlayingState,
+    // The state faded out of and how far the crossfade is, from 0 to 1
+    previous: Option<(PlayingState, f32)>,
+    fade_duration: f32,
+    pose: Vec<ClipWeight>,
+    events: Vec<AnimatorEvent>,
+}
+
+impl Animator {
+    /// Creates an animator in the default state of the controller, with the
+    /// default values of its parameters
+    pub fn new(controller: Arc<AnimatorController>) -> Self {
+        let index = controller
+            .default_state
+            .as_ref()
+            .and_then(|name| {
+                controller
+                    .states
+                    .iter()
+                    .position(|state| state.name == *name)
+            })
+            .unwrap_or(0);
+
+        let mut animator = Self {
+            parameters: controller.parameters.clone(),
+            controller,
+            current: PlayingState {
+                index,
+                elapsed: 0.0,
+            },
+            previous: None,
+            fade_duration: 0.0,
+            pose: Vec::new(),
+            events: Vec::new(),
+        };
+        animator.update_pose();
+
+        animator
+    }
+
+    pub fn controller(&self) -> &Arc<AnimatorController> {
+        &self.controller
+    }
+
+    pub fn set_float(&mut self, name: &str, value: f32) {
+        self.set(name, AnimatorParameter::Float(value));
+    }
+
+    pub fn set_bool(&mut self, name: &str, value: bool) {
+        self.set(name, AnimatorParameter::Bool(value));
+    }
+
+    /// Sets a trigger until a transition that checks it is taken
+    pub fn set_trigger(&mut self, name: &str) {
+        self.set(name, AnimatorParameter::Trigger(true));
+    }
+
+    pub fn reset_trigger(&mut self, name: &str) {
+        self.set(name, AnimatorParameter::Trigger(false));
+    }
+
+    pub fn parameter(&self, name: &str) -> Option<AnimatorParameter> {
+        self.parameters.get(name).copied()
+    }
+
+    /// Returns the name of the state the animator is in, or fading into
+    pub fn current_state(&self) -> &str {
+        &self.controller.states[self.current.index].name
+    }
+
+    /// Whether the animator is crossfading between two states
+    pub fn is_transitioning(&self) -> bool {
+        self.previous.is_some()
+    }
+
+    /// Returns the clips that make up the pose, with weights that add up to 1
+    pub fn pose(&self) -> &[ClipWeight] {
+        &self.pose
+    }
+
+    /// Takes the events queued since the last time they were drained
+    pub fn drain_events(&mut self) -> Vec<AnimatorEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn set(&mut self, name: &str, value: AnimatorParameter) {
+        match self.parameters.get_mut(name) {
+            Some(parameter)
+                if std::mem::discriminant(parameter) == std::mem::discriminant(&value) =>
+            {
+                *parameter = value;
+            }
+            Some(parameter) => warn!(
+                "Animator parameter {} is {:?}, not {:?}",
+                name, parameter, value
+            ),
+            None => warn!("Unknown animator parameter: {}", name),
+        }
+    }
+
+    fn float(&self, name: &str) -> f32 {
+        match self.parameters.get(name) {
+            Some(AnimatorParameter::Float(value)) => *value,
+            _ => 0.0,
+        }
+    }
+
+    fn is_set(&self, name: &str) -> bool {
+        matches!(
+            self.parameters.get(name),
+            Some(AnimatorParameter::Bool(true) | AnimatorParameter::Trigger(true))
+        )
+    }
+
+    /// Advances the states, takes the first transition that passes and
+    /// updates the pose
+    fn update(&mut self, dt: f32) {
+        let controller = self.controller.clone();
+
+        self.current.elapsed += dt * controller.states[self.current.index].speed;
+        if let Some((previous, fade)) = self.previous.as_mut() {
+            previous.elapsed += dt * controller.states[previous.index].speed;
+            *fade += dt / self.fade_duration.max(f32::EPSILON);
+
+            if *fade >= 1.0 {
+                self.previous = None;
+            }
+        }
+
+        let current_state = &controller.states[self.current.index];
+        let transition = controller.transitions.iter().find(|transition| {
+            let leaves = match &transition.from {
+                Some(from) => *from == current_state.name,
+                None => transition.to != current_state.name,
+            };
+
+            leaves
+                && transition
+                    .conditions
+                    .iter()
+                    .all(|condition| match condition {
+                        TransitionCondition::Greater(parameter, value) => {
+                            self.float(parameter) > *value
+                        }
+                        TransitionCondition::Less(parameter, value) => {
+                            self.float(parameter) < *value
+                        }
+                        TransitionCondition::IsSet(parameter) => self.is_set(parameter),
+                        TransitionCondition::NotSet(parameter) => !self.is_set(parameter),
+                        TransitionCondition::Exit => self.current.elapsed >= current_state.length,
+                    })
+        });
+
+        if let Some(transition) = transition
+            && let Some(index) = controller
+                .states
+                .iter()
+                .position(|state| state.name == transition.to)
+        {
+            // Triggers are used up by the transition that checked them
+            for condition in transition.conditions.iter() {
+                if let TransitionCondition::IsSet(parameter) = condition
+                    && let Some(AnimatorParameter::Trigger(set)) =
+                        self.parameters.get_mut(parameter)
+                {
+                    *set = false;
+                }
+            }
+
+            self.events.push(AnimatorEvent::StateChanged {
+                from: current_state.name.clone(),
+                to: transition.to.clone(),
+            });
+
+            let previous = std::mem::replace(
+                &mut self.current,
+                PlayingState {
+                    index,
+                    elapsed: 0.0,
+                },
+            );
+            self.previous = (transition.duration > 0.0).then_some((previous, 0.0));
+            self.fade_duration = transition.duration;
+        }
+
+        self.update_pose();
+    }
+
+    fn update_pose(&mut self) {
+        let mut pose = Vec::new();
+
+        match &self.previous {
+            Some((previous, fade)) => {
+                let fade = fade.clamp(0.0, 1.0);
+                self.state_pose(previous, 1.0 - fade, &mut pose);
+                self.state_pose(&self.current, fade, &mut pose);
+            }
+            None => self.state_pose(&self.current, 1.0, &mut pose),
+        }
+
+        pose.retain(|clip| clip.weight > 0.0);
+        self.pose = pose;
+    }
+
+    /// Adds the clips of a playing state to a pose
+    fn state_pose(&self, playing: &PlayingState, weight: f32, pose: &mut Vec<ClipWeight>) {
+        let state = &self.controller.states[playing.index];
+        let time = if state.looping {
+            playing.elapsed % state.length.max(f32::EPSILON)
+        } else {
+            playing.elapsed.min(state.length)
+        };
+
+        let mut add = |clip: &str, clip_weight: f32| {
+            pose.push(ClipWeight {
+                clip: clip.to_string(),
+                weight: weight * clip_weight,
+                time,
+            });
+        };
+
+        match &state.tree {
+            BlendTree::Clip(clip) => add(clip, 1.0),
+            BlendTree::Blend1D { parameter, clips } => {
+                let value = self.float(parameter);
+                let Some(above) = clips.iter().position(|(_, threshold)| *threshold >= value)
+                else {
+                    if let Some((clip, _)) = clips.last() {
+                        add(clip, 1.0);
+                    }
+                    return;
+                };
+
+                if above == 0 {
+                    add(&clips[0].0, 1.0);
+                    return;
+                }
+
+                let (below_clip, below) = &clips[above - 1];
+                let (above_clip, above) = &clips[above];
+                let t = (value - below) / (above - below).max(f32::EPSILON);
+                add(below_clip, 1.0 - t);
+                add(above_clip, t);
+            }
+        }
+    }
+}
+
+/// Advances every animator of a compound, by the `TimeScale` of its entity
+/// if it has one.
+pub(crate) fn update_animators(compound: &Compound, dt: f32) {
+    let time_scales = time_scales(compound);
+
+    compound.iter_mut_mol(|entity, animator: &mut Animator| {
+        animator.update(dt * time_scales.get(&entity).copied().unwrap_or(1.0));
+    });
+}
@@ -1,13 +1,218 @@
 use cgmath::{InnerSpace, Point3, Vector3};
+use compound::Compound;
+use gpu_controller::Color;
 use log::warn;
-use photon::camera::{PerspectiveCamera3D, PhotonCamera};
+use photon::{
+    camera::{PerspectiveCamera3D, PhotonCamera, ViewModelProjection},
+    renderer::lens::{DepthOfField, MotionBlur},
+};
 
-use crate::AssetServer;
+use crate::{AssetServer, RenderLayers, Transform3D};
 
 const DEFAULT_FOVY: f32 = 45.0;
 const DEFAULT_NEAR: f32 = 0.1;
 const DEFAULT_FAR: f32 = 1000.0;
 
+/// Describes a 3D perspective camera that the engine builds and maintains.
+///
+/// Spawn this next to a `Transform3D` instead of constructing a `Camera`
+/// directly. Before every frame the engine creates the GPU camera for any
+/// descriptor that does not have one yet, and re-applies the descriptor to the
+/// camera whenever any of its fields change, so the projection can be
+/// adjusted at runtime by modifying the descriptor.
+///
+/// # Example
+/// ```ignore
+/// ecs.spawn((
+///     Camera3DDescriptor {
+///         fovy: 70.0,
+///         clear_color: [0.1, 0.1, 0.2, 1.0],
+///         ..Default::default()
+///     },
+///     Transform3D::default(),
+/// ));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera3DDescriptor {
+    /// Vertical field of view in degrees
+    pub fovy: f32,
+    /// Distance to the near clipping plane
+    pub near: f32,
+    /// Distance to the far clipping plane
+    pub far: f32,
+    /// Color the output is cleared to where nothing is drawn
+    pub clear_color: [f64; 4],
+    /// Whether the output is cleared before rendering. Overlay cameras set
+    /// this to `false` to draw over the cameras rendered before them
+    pub clear: bool,
+    /// Cameras render from the lowest order to the highest
+    pub order: i32,
+    /// Blurs what is out of focus at the end of the frame
+    pub depth_of_field: Option<DepthOfField>,
+    /// Blurs what moved since the last frame at the end of the frame
+    pub motion_blur: Option<MotionBlur>,
+    /// Draws the models of a render layer as the first person viewmodel
+    pub view_model: Option<ViewModel>,
+}
+
+impl Default for Camera3DDescriptor {
+    fn default() -> Self {
+        Self {
+            fovy: DEFAULT_FOVY,
+            near: DEFAULT_NEAR,
+            far: DEFAULT_FAR,
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+            clear: true,
+            order: 0,
+            depth_of_field: None,
+            motion_blur: None,
+            view_model: None,
+        }
+    }
+}
+
+/// The weapon and hands of a first person camera.
+///
+/// Models on the layer are drawn with a field of view and clipping planes of
+/// their own, in front of everything else the camera sees, so the viewmodel
+/// keeps its shape when the world's field of view changes and never clips
+/// into walls. The viewmodel is still lit and shadowed where it is in the
+/// world. Models on the layer are only drawn as the viewmodel of cameras
+/// with one, other cameras draw them like the rest of their layers.
+///
+/// # Example
+/// ```ignore
+/// const VIEWMODEL: u32 = 1;
+///
+/// ecs.spawn((weapon_model, weapon_transform, RenderLayers::layer(VIEWMODEL)));
+///
+/// ecs.spawn((
+///     Camera3DDescriptor {
+///         fovy: 90.0,
+///         view_model: Some(ViewModel::new(VIEWMODEL).with_fovy(55.0)),
+///         ..Default::default()
+///     },
+///     Transform3D::default(),
+/// ));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewModel {
+    /// The render layer of the models drawn as the viewmodel
+    pub layer: u32,
+    /// Vertical field of view in degrees the viewmodel is drawn with
+    pub fovy: f32,
+    /// Distance to the near clipping plane of the viewmodel
+    pub near: f32,
+    /// Distance to the far clipping plane of the viewmodel
+    pub far: f32,
+}
+
+impl Default for ViewModel {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl ViewModel {
+    pub fn new(layer: u32) -> Self {
+        let ViewModelProjection { fovy, near, far } = ViewModelProjection::default();
+
+        Self {
+            layer,
+            fovy,
+            near,
+            far,
+        }
+    }
+
+    pub fn with_fovy(mut self, fovy: f32) -> Self {
+        self.fovy = fovy;
+        self
+    }
+
+    pub fn with_clipping(mut self, near: f32, far: f32) -> Self {
+        self.near = near;
+        self.far = far;
+        self
+    }
+
+    /// Returns the layers of the models drawn as the viewmodel
+    pub fn layers(&self) -> RenderLayers {
+        RenderLayers::layer(self.layer)
+    }
+
+    fn projection(&self) -> ViewModelProjection {
+        ViewModelProjection {
+            fovy: self.fovy,
+            near: self.near,
+            far: self.far,
+        }
+    }
+}
+
+// Bookkeeping for the descriptor that was last applied to an entity's camera
+struct AppliedCamera3DDescriptor(Option<Camera3DDescriptor>);
+
+/// Creates and updates cameras from their `Camera3DDescriptor`s.
+///
+/// Entities with a descriptor and a transform but no camera get a new
+/// perspective camera looking along the transform's rotation. Entities whose
+/// descriptor differs from the one last applied have the new values copied
+/// onto their camera.
+pub(crate) fn build_cameras(compound: &Compound, asset_server: &AssetServer) {
+    // Descriptors that have never been seen
+    let mut new_descriptors = Vec::new();
+    compound.iter_without_duo::<AppliedCamera3DDescriptor, _, _, _>(
+        |entity, descriptor: &Camera3DDescriptor, transform: &Transform3D| {
+            new_descriptors.push((entity, *descriptor, *transform));
+        },
+    );
+
+    let mut has_camera = Vec::new();
+    compound.iter_duo(
+        |entity, _descriptor: &Camera3DDescriptor, _camera: &Camera| {
+            has_camera.push(entity);
+        },
+    );
+
+    for (entity, descriptor, transform) in new_descriptors {
+        // A user supplied camera is left alone and only has the descriptor applied
+        if !has_camera.contains(&entity) {
+            let (eye, target) = transform.get_position_and_rotation(|pos, rot| {
+                (*pos, (*rot * Vector3::new(0.0, 0.0, 1.0)).normalize())
+            });
+
+            compound.add_molecule(
+                entity,
+                Camera::new_perspective_3d(
+                    asset_server,
+                    eye,
+                    target,
+                    Vector3::unit_y(),
+                    descriptor.fovy,
+                    descriptor.near,
+                    descriptor.far,
+                ),
+            );
+        }
+
+        compound.add_molecule(entity, AppliedCamera3DDescriptor(None));
+    }
+
+    // Descriptors that changed since they were last applied
+    compound.iter_mut_trio_unmod(
+        |_entity,
+         descriptor: &mut Camera3DDescriptor,
+         applied: &mut AppliedCamera3DDescriptor,
+         camera: &mut Camera| {
+            if applied.0 != Some(*descriptor) {
+                camera.apply_descriptor(descriptor);
+                applied.0 = Some(*descriptor);
+            }
+        },
+    );
+}
+
 pub enum Camera {
     PerspectiveCamera3D(PerspectiveCamera3D),
 }
@@ -19,6 +224,55 @@ impl PhotonCamera for Camera {
             Self::PerspectiveCamera3D(camera) => camera.bind_group(),
         }
     }
+
+    #[inline]
+    fn buffer(&self) -> &gpu_controller::Buffer {
+        match self {
+            Self::PerspectiveCamera3D(camera) => camera.buffer(),
+        }
+    }
+
+    #[inline]
+    fn clear_color(&self) -> Color {
+        match self {
+            Self::PerspectiveCamera3D(camera) => camera.clear_color(),
+        }
+    }
+
+    #[inline]
+    fn clears(&self) -> bool {
+        match self {
+            Self::PerspectiveCamera3D(camera) => camera.clears(),
+        }
+    }
+
+    #[inline]
+    fn depth_of_field(&self) -> Option<DepthOfField> {
+        match self {
+            Self::PerspectiveCamera3D(camera) => PhotonCamera::depth_of_field(camera),
+        }
+    }
+
+    #[inline]
+    fn motion_blur(&self) -> Option<MotionBlur> {
+        match self {
+            Self::PerspectiveCamera3D(camera) => PhotonCamera::motion_blur(camera),
+        }
+    }
+
+    #[inline]
+    fn view_model(&self) -> Option<ViewModelProjection> {
+        match self {
+            Self::PerspectiveCamera3D(camera) => PhotonCamera::view_model(camera),
+        }
+    }
+
+    #[inline]
+    fn position(&self) -> [f32; 3] {
+        match self {
+            Self::PerspectiveCamera3D(camera) => PhotonCamera::position(camera),
+        }
+    }
 }
 
 impl Camera {
@@ -156,4 +410,78 @@ impl Camera {
             Self::PerspectiveCamera3D(camera) => camera.all(callback),
         }
     }
+
+    pub fn clear_color<F>(&mut self, callback: F)
+    where
+        F: FnOnce(&mut Color),
+    {
+        match self {
+            Self::PerspectiveCamera3D(camera) => camera.clear_color(callback),
+        }
+    }
+
+    pub fn clears<F>(&mut self, callback: F)
+    where
+        F: FnOnce(&mut bool),
+    {
+        match self {
+            Self::PerspectiveCamera3D(camera) => camera.clears(callback),
+        }
+    }
+
+    pub fn depth_of_field<F>(&mut self, callback: F)
+    where
+        F: FnOnce(&mut Option<DepthOfField>),
+    {
+        match self {
+            Self::PerspectiveCamera3D(camera) => camera.depth_of_field(callback),
+        }
+    }
+
+    pub fn motion_blur<F>(&mut self, callback: F)
+    where
+        F: FnOnce(&mut Option<MotionBlur>),
+    {
+        match self {
+            Self::PerspectiveCamera3D(camera) => camera.motion_blur(callback),
+        }
+    }
+
+    pub fn view_model<F>(&mut self, callback: F)
+    where
+        F: FnOnce(&mut Option<ViewModelProjection>),
+    {
+        match self {
+            Self::PerspectiveCamera3D(camera) => camera.view_model(callback),
+        }
+    }
+
+    // Motion blur of the next frame is measured from where the camera is now
+    pub(crate) fn end_frame(&mut self) {
+        match self {
+            Self::PerspectiveCamera3D(camera) => camera.end_frame(),
+        }
+    }
+
+    fn apply_descriptor(&mut self, descriptor: &Camera3DDescriptor) {
+        self.all(|_, _, _, _, fovy, znear, zfar| {
+            *fovy = descriptor.fovy;
+            *znear = descriptor.near;
+            *zfar = descriptor.far;
+        });
+
+        self.clear_color(|clear_color| {
+            let [r, g, b, a] = descriptor.clear_color;
+            *clear_color = Color { r, g, b, a };
+        });
+
+        self.clears(|clears| *clears = descriptor.clear);
+        self.depth_of_field(|depth_of_field| *depth_of_field = descriptor.depth_of_field);
+        self.motion_blur(|motion_blur| *motion_blur = descriptor.motion_blur);
+        self.view_model(|view_model| {
+            *view_model = descriptor
+                .view_model
+                .map(|view_model| view_model.projection())
+        });
+    }
 }
@@ -0,0 +1,160 @@
+use compound::Compound;
+
+/// Determines how the UI scale of a `Canvas` is computed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CanvasScaleMode {
+    /// UI elements keep the same size in logical pixels, only the monitor's
+    /// DPI scale factor is applied
+    ConstantPixelSize,
+    /// UI elements scale with the physical height of the window relative to
+    /// a reference height, so the layout looks the same at any resolution or DPI
+    ScaleWithHeight { reference_height: f32 },
+}
+
+/// Insets in UI units that UI should stay clear of.
+///
+/// On desktop these are zero, on devices with notches, rounded corners or
+/// overscan they describe the unobstructed part of the window.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SafeAreaInsets {
+    pub top: f32,
+    pub bottom: f32,
+    pub left: f32,
+    pub right: f32,
+}
+
+/// A surface UI is laid out on.
+///
+/// The engine keeps the physical size and DPI scale factor of every canvas in
+/// sync with the window, UI code works in logical pixels and converts with
+/// `to_physical` / `to_logical`.
+///
+/// # Example
+/// ```ignore
+/// ecs.spawn((Canvas::new(CanvasScaleMode::ScaleWithHeight {
+///     reference_height: 1080.0,
+/// }),));
+///
+/// ecs.iter_mol(|_entity, canvas: &Canvas| {
+///     let (x, y, width, height) = canvas.safe_rect();
+///     // Lay out the HUD inside the safe rect
+/// });
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Canvas {
+    scale_mode: CanvasScaleMode,
+    safe_area: SafeAreaInsets,
+
+    physical_size: (u32, u32),
+    dpi_scale: f64,
+}
+
+impl Canvas {
+    /// Creates a new canvas with the given scale mode
+    pub fn new(scale_mode: CanvasScaleMode) -> Self {
+        Self {
+            scale_mode,
+            safe_area: SafeAreaInsets::default(),
+            physical_size: (1, 1),
+            dpi_scale: 1.0,
+        }
+    }
+
+    /// Provides mutable access to the scale mode through a callback.
+    ///
+    /// # Arguments
+    /// * `callback` - Function that receives a mutable reference to the scale mode
+    ///
+    /// # Returns
+    /// The return value of the callback function
+    pub fn scale_mode<F, R>(&mut self, callback: F) -> R
+    where
+        F: FnOnce(&mut CanvasScaleMode) -> R,
+    {
+        callback(&mut self.scale_mode)
+    }
+
+    /// Provides mutable access to the safe area insets through a callback.
+    ///
+    /// # Arguments
+    /// * `callback` - Function that receives a mutable reference to the insets
+    ///
+    /// # Returns
+    /// The return value of the callback function
+    pub fn safe_area<F, R>(&mut self, callback: F) -> R
+    where
+        F: FnOnce(&mut SafeAreaInsets) -> R,
+    {
+        callback(&mut self.safe_area)
+    }
+
+    /// Returns the size of the window in physical pixels
+    pub fn physical_size(&self) -> (u32, u32) {
+        self.physical_size
+    }
+
+    /// Returns the DPI scale factor of the monitor the window is on
+    pub fn dpi_scale(&self) -> f64 {
+        self.dpi_scale
+    }
+
+    /// Returns the number of physical pixels per UI unit.
+    ///
+    /// This combines the DPI scale factor with the scale mode of the canvas.
+    pub fn scale_factor(&self) -> f32 {
+        let dpi_scale = self.dpi_scale as f32;
+
+        match self.scale_mode {
+            CanvasScaleMode::ConstantPixelSize => dpi_scale,
+            CanvasScaleMode::ScaleWithHeight { reference_height } => {
+                if reference_height <= 0.0 {
+                    dpi_scale
+                } else {
+                    self.physical_size.1 as f32 / reference_height
+                }
+            }
+        }
+    }
+
+    /// Returns the size of the canvas in UI units
+    pub fn logical_size(&self) -> (f32, f32) {
+        let scale = self.scale_factor();
+
+        (
+            self.physical_size.0 as f32 / scale,
+            self.physical_size.1 as f32 / scale,
+        )
+    }
+
+    /// Returns the unobstructed area of the canvas in UI units as (x, y, width, height)
+    pub fn safe_rect(&self) -> (f32, f32, f32, f32) {
+        let (width, height) = self.logical_size();
+
+        (
+            self.safe_area.left,
+            self.safe_area.top,
+            (width - self.safe_area.left - self.safe_area.right).max(0.0),
+            (height - self.safe_area.top - self.safe_area.bottom).max(0.0),
+        )
+    }
+
+    /// Converts a point in UI units to physical pixels
+    pub fn to_physical(&self, logical: (f32, f32)) -> (f32, f32) {
+        let scale = self.scale_factor();
+        (logical.0 * scale, logical.1 * scale)
+    }
+
+    /// Converts a point in physical pixels, such as a cursor position, to UI units
+    pub fn to_logical(&self, physical: (f32, f32)) -> (f32, f32) {
+        let scale = self.scale_factor();
+        (physical.0 / scale, physical.1 / scale)
+    }
+}
+
+/// Keeps every canvas in sync with the size and scale factor of the window
+pub(crate) fn update_canvases(compound: &Compound, physical_size: (u32, u32), dpi_scale: f64) {
+    compound.iter_mut_mol_unmod(|_entity, canvas: &mut Canvas| {
+        canvas.physical_size = (physical_size.0.max(1), physical_size.1.max(1));
+        canvas.dpi_scale = dpi_scale;
+    });
+}
@@ -0,0 +1,600 @@
+use std::collections::HashMap;
+
+use boson::{BosonBody, BosonObject};
+use cgmath::{InnerSpace, Quaternion, Rad, Rotation3, Vector2, Vector3, Zero};
+use compound::{Compound, Entity, Without};
+
+use crate::{SpatialHash, Transform3D};
+
+// Below this the solver treats lines as parallel and velocities as zero
+const CROWD_EPSILON: f32 = 1e-5;
+
+/// Settings shared by every agent of a crowd.
+///
+/// Insert as a resource of the compound to change them, the defaults are used
+/// otherwise.
+///
+/// # Example
+/// ```ignore
+/// compound.insert_resource(CrowdSettings {
+///     workers: 4,
+///     ..Default::default()
+/// });
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct CrowdSettings {
+    /// Seconds ahead agents look for collisions with each other, larger values
+    /// make agents react earlier but more cautiously
+    pub time_horizon: f32,
+    /// Distance within which other agents are avoided
+    pub neighbor_distance: f32,
+    /// The number of closest neighbors each agent avoids
+    pub max_neighbors: usize,
+    /// Jobs the avoidance is split into on the job pool, 1 runs it on the state thread
+    pub workers: usize,
+}
+
+impl Default for CrowdSettings {
+    fn default() -> Self {
+        Self {
+            time_horizon: 2.0,
+            neighbor_distance: 10.0,
+            max_neighbors: 10,
+            workers: 1,
+        }
+    }
+}
+
+/// Component for an entity that walks along a path while avoiding other agents.
+///
+/// Every tick the engine steers the agent towards the next point of its path,
+/// adjusts that velocity with reciprocal collision avoidance (ORCA) so agents
+/// pass each other without colliding, and limits how fast the agent may speed
+/// up and turn. Agents move on the XZ plane.
+///
+/// Agents with a `BosonObject` have the velocity of their body set and are
+/// moved by physics. Otherwise the `Transform3D` is moved directly and turned
+/// to face where the agent is going, with +Z as forward.
+///
+/// # Example
+/// ```ignore
+/// let agent = compound.spawn((
+///     Transform3D::default(),
+///     CrowdAgent::new(0.5, 3.0).with_max_turn_rate(std::f32::consts::PI),
+/// ));
+///
+/// compound.iter_mut_mol(|_entity, agent: &mut CrowdAgent| {
+///     agent.set_path(vec![Vector3::new(10.0, 0.0, 0.0), Vector3::new(10.0, 0.0, 10.0)]);
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct CrowdAgent {
+    /// Radius of the agent on the XZ plane
+    pub radius: f32,
+    /// Fastest the agent moves, in units per second
+    pub max_speed: f32,
+    /// Fastest the agent changes its velocity, in units per second squared
+    pub max_acceleration: f32,
+    /// Fastest the agent turns, in radians per second
+    pub max_turn_rate: f32,
+    /// Distance from a point of the path at which it counts as reached
+    pub arrival_radius: f32,
+
+    path: Vec<Vector3<f32>>,
+    next_point: usize,
+    velocity: Vector2<f32>,
+}
+
+impl CrowdAgent {
+    /// Creates an agent without a path, which stands still.
+    ///
+    /// # Arguments
+    /// * `radius` - Radius of the agent on the XZ plane
+    /// * `max_speed` - Fastest the agent moves, in units per second
+    pub fn new(radius: f32, max_speed: f32) -> Self {
+        Self {
+            radius,
+            max_speed,
+            max_acceleration: max_speed * 4.0,
+            max_turn_rate: std::f32::consts::TAU,
+            arrival_radius: radius,
+            path: Vec::new(),
+            next_point: 0,
+            velocity: Vector2::zero(),
+        }
+    }
+
+    pub fn with_max_acceleration(mut self, max_acceleration: f32) -> Self {
+        self.max_acceleration = max_acceleration;
+        self
+    }
+
+    pub fn with_max_turn_rate(mut self, max_turn_rate: f32) -> Self {
+        self.max_turn_rate = max_turn_rate;
+        self
+    }
+
+    pub fn with_arrival_radius(mut self, arrival_radius: f32) -> Self {
+        self.arrival_radius = arrival_radius;
+        self
+    }
+
+    /// Replaces the path of the agent, such as one found on a navmesh
+    pub fn set_path(&mut self, path: Vec<Vector3<f32>>) {
+        self.path = path;
+        self.next_point = 0;
+    }
+
+    /// Makes the agent walk straight towards a point
+    pub fn set_destination(&mut self, destination: Vector3<f32>) {
+        self.set_path(vec![destination]);
+    }
+
+    /// Stops the agent where it is
+    pub fn clear_path(&mut self) {
+        self.set_path(Vec::new());
+    }
+
+    pub fn path(&self) -> &[Vector3<f32>] {
+        &self.path
+    }
+
+    /// Whether the agent has reached the end of its path
+    pub fn has_arrived(&self) -> bool {
+        self.next_point >= self.path.len()
+    }
+
+    /// Returns the velocity the agent moved with last tick
+    pub fn velocity(&self) -> Vector3<f32> {
+        Vector3::new(self.velocity.x, 0.0, self.velocity.y)
+    }
+
+    /// Skips the points of the path that were reached and returns the
+    /// velocity towards the next one
+    fn preferred_velocity(&mut self, position: Vector2<f32>) -> Vector2<f32> {
+        while let Some(point) = self.path.get(self.next_point) {
+            if (flatten(*point) - position).magnitude() > self.arrival_radius {
+                break;
+            }
+            self.next_point += 1;
+        }
+
+        let Some(point) = self.path.get(self.next_point) else {
+            return Vector2::zero();
+        };
+
+        let offset = flatten(*point) - position;
+        let distance = offset.magnitude();
+        if distance < CROWD_EPSILON {
+            return Vector2::zero();
+        }
+
+        // Slow down coming up to the last point instead of overshooting it
+        let speed = if self.next_point + 1 == self.path.len() {
+            self.max_speed.min(distance)
+        } else {
+            self.max_speed
+        };
+
+        offset / distance * speed
+    }
+
+    /// Limits how much the velocity may change in one tick
+    fn constrain(&self, velocity: Vector2<f32>, dt: f32) -> Vector2<f32> {
+        let mut velocity = velocity;
+
+        let current_speed = self.velocity.magnitude();
+        let speed = velocity.magnitude();
+        if current_speed > CROWD_EPSILON && speed > CROWD_EPSILON {
+            let max_turn = self.max_turn_rate * dt;
+            let current_angle = self.velocity.y.atan2(self.velocity.x);
+            let mut turn = velocity.y.atan2(velocity.x) - current_angle;
+            if turn > std::f32::consts::PI {
+                turn -= std::f32::consts::TAU;
+            } else if turn < -std::f32::consts::PI {
+                turn += std::f32::consts::TAU;
+            }
+
+            if turn.abs() > max_turn {
+                let angle = current_angle + max_turn.copysign(turn);
+                velocity = Vector2::new(angle.cos(), angle.sin()) * speed;
+            }
+        }
+
+        let change = velocity - self.velocity;
+        let max_change = self.max_acceleration * dt;
+        if change.magnitude2() > max_change * max_change {
+            velocity = self.velocity + change.normalize_to(max_change);
+        }
+
+        velocity
+    }
+}
+
+// What the avoidance of an agent needs to know about it and its neighbors
+#[derive(Clone, Copy)]
+struct AgentSnapshot {
+    entity: Entity,
+    position: Vector2<f32>,
+    velocity: Vector2<f32>,
+    preferred_velocity: Vector2<f32>,
+    radius: f32,
+    max_speed: f32,
+}
+
+/// Steers every crowd agent of a compound and moves it.
+pub(crate) fn update_crowds(compound: &Compound, dt: f32) {
+    if dt <= 0.0 {
+        return;
+    }
+
+    let settings = compound
+        .resource::<CrowdSettings, _>(|settings| *settings)
+        .unwrap_or_default();
+
+    let mut agents = Vec::new();
+    compound
+        .query::<(&mut CrowdAgent, &Transform3D), ()>()
+        .unmod()
+        .for_each(|entity, (agent, transform)| {
+            let position = transform.get_position(|position| flatten(*position));
+            agents.push(AgentSnapshot {
+                entity,
+                position,
+                velocity: agent.velocity,
+                preferred_velocity: agent.preferred_velocity(position),
+                radius: agent.radius,
+                max_speed: agent.max_speed,
+            });
+        });
+
+    if agents.is_empty() {
+        return;
+    }
+
+    // Hash by index into the snapshots so neighbors can be looked up directly
+    let mut spatial_hash = SpatialHash::new(settings.neighbor_distance.max(CROWD_EPSILON));
+    for (index, agent) in agents.iter().enumerate() {
+        spatial_hash.insert(
+            index as Entity,
+            Vector3::new(agent.position.x, 0.0, agent.position.y),
+        );
+    }
+
+    let avoid = |index: usize| avoid_neighbors(index, &agents, &spatial_hash, &settings, dt);
+    let velocities: Vec<Vector2<f32>> = if settings.workers > 1 {
+        let chunk_size = agents.len().div_ceil(settings.workers);
+        gluon::scope(|scope| {
+            let jobs = (0..agents.len())
+                .step_by(chunk_size)
+                .map(|start| {
+                    let end = (start + chunk_size).min(agents.len());
+                    scope.spawn(move || (start..end).map(avoid).collect::<Vec<_>>())
+                })
+                .collect::<Vec<_>>();
+
+            jobs.into_iter()
+                .flat_map(|job| job.join().unwrap_or_default())
+                .collect()
+        })
+    } else {
+        (0..agents.len()).map(avoid).collect()
+    };
+
+    let velocities = agents
+        .iter()
+        .zip(velocities)
+        .map(|(agent, velocity)| (agent.entity, velocity))
+        .collect::<HashMap<Entity, Vector2<f32>>>();
+
+    // Agents with a body are moved by physics
+    compound
+        .query::<(&mut CrowdAgent, &BosonObject), ()>()
+        .unmod()
+        .for_each(|entity, (agent, boson_object)| {
+            let Some(velocity) = velocities.get(&entity) else {
+                return;
+            };
+
+            agent.velocity = agent.constrain(*velocity, dt);
+            boson_object.modify_body(|body| match body {
+                BosonBody::PointMass(point_mass) => {
+                    point_mass.velocity.x = agent.velocity.x as f64;
+                    point_mass.velocity.z = agent.velocity.y as f64;
+                }
+                BosonBody::RigidBody(rigid_body) => {
+                    rigid_body.velocity.x = agent.velocity.x as f64;
+                    rigid_body.velocity.z = agent.velocity.y as f64;
+                }
+                BosonBody::StaticCollider(_) => {}
+            });
+        });
+
+    compound
+        .query::<(&mut CrowdAgent, &mut Transform3D), Without<BosonObject>>()
+        .for_each(|entity, (agent, transform)| {
+            let Some(velocity) = velocities.get(&entity) else {
+                return;
+            };
+
+            agent.velocity = agent.constrain(*velocity, dt);
+            let velocity = agent.velocity;
+            transform.position_and_rotation(|position, rotation| {
+                position.x += velocity.x * dt;
+                position.z += velocity.y * dt;
+
+                if velocity.magnitude2() > CROWD_EPSILON {
+                    *rotation = Quaternion::from_angle_y(Rad(velocity.x.atan2(velocity.y)));
+                }
+            });
+        });
+}
+
+/// Finds the velocity closest to the preferred velocity of an agent that
+/// won't collide with any of its neighbors within the time horizon
+fn avoid_neighbors(
+    index: usize,
+    agents: &[AgentSnapshot],
+    spatial_hash: &SpatialHash,
+    settings: &CrowdSettings,
+    dt: f32,
+) -> Vector2<f32> {
+    let agent = &agents[index];
+
+    let mut neighbors = Vec::new();
+    spatial_hash.for_each_within(
+        Vector3::new(agent.position.x, 0.0, agent.position.y),
+        settings.neighbor_distance,
+        |other, _| {
+            let other = other as usize;
+            if other != index {
+                let distance = (agents[other].position - agent.position).magnitude2();
+                neighbors.push((distance, other));
+            }
+        },
+    );
+    neighbors.sort_by(|a, b| a.0.total_cmp(&b.0));
+    neighbors.truncate(settings.max_neighbors);
+
+    let inverse_time_horizon = 1.0 / settings.time_horizon.max(CROWD_EPSILON);
+    let lines = neighbors
+        .iter()
+        .map(|(_, other)| orca_line(agent, &agents[*other], inverse_time_horizon, 1.0 / dt))
+        .collect::<Vec<_>>();
+
+    let mut velocity = Vector2::zero();
+    let failed_line = linear_program_2(
+        &lines,
+        agent.max_speed,
+        agent.preferred_velocity,
+        false,
+        &mut velocity,
+    );
+    if failed_line < lines.len() {
+        linear_program_3(&lines, failed_line, agent.max_speed, &mut velocity);
+    }
+
+    velocity
+}
+
+// A half plane of allowed velocities, to the left of the direction
+#[derive(Clone, Copy)]
+struct Line {
+    point: Vector2<f32>,
+    direction: Vector2<f32>,
+}
+
+fn flatten(vector: Vector3<f32>) -> Vector2<f32> {
+    Vector2::new(vector.x, vector.z)
+}
+
+fn det(a: Vector2<f32>, b: Vector2<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+/// The half plane of velocities that avoid a neighbor, with each agent taking
+/// half of the responsibility
+fn orca_line(
+    agent: &AgentSnapshot,
+    other: &AgentSnapshot,
+    inverse_time_horizon: f32,
+    inverse_time_step: f32,
+) -> Line {
+    let relative_position = other.position - agent.position;
+    let relative_velocity = agent.velocity - other.velocity;
+    let distance_squared = relative_position.magnitude2();
+    let combined_radius = agent.radius + other.radius;
+    let combined_radius_squared = combined_radius * combined_radius;
+
+    let (direction, u) = if distance_squared > combined_radius_squared {
+        // Vector from the cutoff center to the relative velocity
+        let w = relative_velocity - relative_position * inverse_time_horizon;
+        let w_length_squared = w.magnitude2();
+        let dot = w.dot(relative_position);
+
+        if dot < 0.0 && dot * dot > combined_radius_squared * w_length_squared {
+            // Closest to the cutoff circle
+            let w_length = w_length_squared.sqrt();
+            let unit_w = w / w_length;
+            (
+                Vector2::new(unit_w.y, -unit_w.x),
+                unit_w * (combined_radius * inverse_time_horizon - w_length),
+            )
+        } else {
+            // Closest to one of the legs of the cone
+            let leg = (distance_squared - combined_radius_squared).sqrt();
+            let direction = if det(relative_position, w) > 0.0 {
+                Vector2::new(
+                    relative_position.x * leg - relative_position.y * combined_radius,
+                    relative_position.x * combined_radius + relative_position.y * leg,
+                ) / distance_squared
+            } else {
+                -Vector2::new(
+                    relative_position.x * leg + relative_position.y * combined_radius,
+                    -relative_position.x * combined_radius + relative_position.y * leg,
+                ) / distance_squared
+            };
+
+            (
+                direction,
+                direction * relative_velocity.dot(direction) - relative_velocity,
+            )
+        }
+    } else {
+        // Already overlapping, separate within one tick
+        let w = relative_velocity - relative_position * inverse_time_step;
+        let w_length = w.magnitude().max(CROWD_EPSILON);
+        let unit_w = w / w_length;
+        (
+            Vector2::new(unit_w.y, -unit_w.x),
+            unit_w * (combined_radius * inverse_time_step - w_length),
+        )
+    };
+
+    Line {
+        point: agent.velocity + u * 0.5,
+        direction,
+    }
+}
+
+/// Finds the best velocity on one line that satisfies the lines before it
+fn linear_program_1(
+    lines: &[Line],
+    line: usize,
+    radius: f32,
+    optimal: Vector2<f32>,
+    direction_optimal: bool,
+    result: &mut Vector2<f32>,
+) -> bool {
+    let Line { point, direction } = lines[line];
+
+    let dot = point.dot(direction);
+    let discriminant = dot * dot + radius * radius - point.magnitude2();
+    if discriminant < 0.0 {
+        // The line is outside of the max speed
+        return false;
+    }
+
+    let discriminant = discriminant.sqrt();
+    let mut t_left = -dot - discriminant;
+    let mut t_right = -dot + discriminant;
+
+    for other in lines[..line].iter() {
+        let denominator = det(direction, other.direction);
+        let numerator = det(other.direction, point - other.point);
+
+        if denominator.abs() <= CROWD_EPSILON {
+            // Parallel lines
+            if numerator < 0.0 {
+                return false;
+            }
+            continue;
+        }
+
+        let t = numerator / denominator;
+        if denominator >= 0.0 {
+            t_right = t_right.min(t);
+        } else {
+            t_left = t_left.max(t);
+        }
+
+        if t_left > t_right {
+            return false;
+        }
+    }
+
+    let t = if direction_optimal {
+        if optimal.dot(direction) > 0.0 {
+            t_right
+        } else {
+            t_left
+        }
+    } else {
+        direction.dot(optimal - point).clamp(t_left, t_right)
+    };
+    *result = point + direction * t;
+
+    true
+}
+
+/// Finds the velocity closest to the optimal one that satisfies every line.
+///
+/// # Returns
+/// The number of lines, or the index of the line that could not be satisfied
+fn linear_program_2(
+    lines: &[Line],
+    radius: f32,
+    optimal: Vector2<f32>,
+    direction_optimal: bool,
+    result: &mut Vector2<f32>,
+) -> usize {
+    *result = if direction_optimal {
+        optimal * radius
+    } else if optimal.magnitude2() > radius * radius {
+        optimal.normalize_to(radius)
+    } else {
+        optimal
+    };
+
+    for (index, line) in lines.iter().enumerate() {
+        if det(line.direction, line.point - *result) > 0.0 {
+            let previous = *result;
+            if !linear_program_1(lines, index, radius, optimal, direction_optimal, result) {
+                *result = previous;
+                return index;
+            }
+        }
+    }
+
+    lines.len()
+}
+
+/// When the agents are too crowded to satisfy every line, finds the velocity
+/// that violates them the least
+fn linear_program_3(lines: &[Line], begin: usize, radius: f32, result: &mut Vector2<f32>) {
+    let mut distance = 0.0;
+
+    for (index, line) in lines.iter().enumerate().skip(begin) {
+        if det(line.direction, line.point - *result) <= distance {
+            continue;
+        }
+
+        let projected = lines[..index]
+            .iter()
+            .filter_map(|other| {
+                let determinant = det(line.direction, other.direction);
+                let point = if determinant.abs() <= CROWD_EPSILON {
+                    if line.direction.dot(other.direction) > 0.0 {
+                        // Same direction, the other line is already covered
+                        return None;
+                    }
+                    (line.point + other.point) * 0.5
+                } else {
+                    line.point
+                        + line.direction
+                            * (det(other.direction, line.point - other.point) / determinant)
+                };
+
+                Some(Line {
+                    point,
+                    direction: (other.direction - line.direction).normalize(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let previous = *result;
+        if linear_program_2(
+            &projected,
+            radius,
+            Vector2::new(-line.direction.y, line.direction.x),
+            true,
+            result,
+        ) < projected.len()
+        {
+            // Only fails from floating point error, keep the last result
+            *result = previous;
+        }
+
+        distance = det(line.direction, line.point - *result);
+    }
+}
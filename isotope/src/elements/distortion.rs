@@ -0,0 +1,66 @@
+use compound::Compound;
+use photon::renderer::{
+    Renderer,
+    distortion::{DistortionKind, DistortionSource},
+};
+
+use super::Transform3D;
+
+/// Bends the scene seen through a sphere around the entity, for heat haze
+/// above fires and shockwaves of explosions.
+///
+/// Shockwaves are animated by moving the `progress` of their kind from 0 to 1.
+///
+/// # Example
+/// ```ignore
+/// // Shimmering air above a campfire
+/// compound.spawn((
+///     Transform3D::new([0.0, 1.5, 0.0], [0.0, 0.0, 0.0, 1.0]),
+///     Distortion {
+///         radius: 1.0,
+///         strength: 0.004,
+///         kind: DistortionKind::HeatHaze {
+///             frequency: 8.0,
+///             speed: 4.0,
+///         },
+///     },
+/// ));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Distortion {
+    pub radius: f32,
+    /// Largest offset of the scene in texture coordinates, such as 0.01
+    pub strength: f32,
+    pub kind: DistortionKind,
+}
+
+impl Default for Distortion {
+    fn default() -> Self {
+        Self {
+            radius: 1.0,
+            strength: 0.005,
+            kind: DistortionKind::HeatHaze {
+                frequency: 8.0,
+                speed: 4.0,
+            },
+        }
+    }
+}
+
+/// Gives every `Distortion` of the world to the renderer
+pub(crate) fn update_distortion(compound: &Compound, photon: &mut Renderer) {
+    let mut sources = Vec::new();
+
+    compound.iter_duo(
+        |_entity, distortion: &Distortion, transform: &Transform3D| {
+            sources.push(DistortionSource {
+                position: transform.get_position(|position| (*position).into()),
+                radius: distortion.radius,
+                strength: distortion.strength,
+                kind: distortion.kind,
+            });
+        },
+    );
+
+    photon.set_distortion_sources(&sources);
+}
@@ -0,0 +1,76 @@
+use compound::Compound;
+use photon::renderer::{Renderer, volumetrics::FogSettings};
+
+/// Fog that lights scatter through, producing light shafts around what blocks them.
+///
+/// The fog of the active world fills the view of every camera. Light shafts
+/// only come from geometry on screen, since whether a light reaches a point
+/// in the fog is found from what the camera has drawn.
+///
+/// # Example
+/// ```ignore
+/// // Thin haze with strong forward scattering for sun beams through a window
+/// compound.spawn((VolumetricFog {
+///     density: 0.03,
+///     anisotropy: 0.7,
+///     ..Default::default()
+/// },));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VolumetricFog {
+    /// How much of each color the fog scatters
+    pub color: [f32; 3],
+    /// How much light the fog scatters and absorbs per unit
+    pub density: f32,
+    /// From -1 to 1, positive values scatter light forward so shafts are brighter looking towards a light
+    pub anisotropy: f32,
+    /// How far from the camera the fog reaches
+    pub max_distance: f32,
+    /// Samples taken through the fog for every pixel, more reduce noise
+    pub steps: u32,
+    /// Samples taken towards every light to find what blocks it
+    pub shadow_steps: u32,
+    /// The number of lights that scatter through the fog
+    pub max_lights: u32,
+}
+
+impl Default for VolumetricFog {
+    fn default() -> Self {
+        Self {
+            color: [1.0; 3],
+            density: 0.02,
+            anisotropy: 0.3,
+            max_distance: 100.0,
+            steps: 32,
+            shadow_steps: 8,
+            max_lights: 4,
+        }
+    }
+}
+
+impl From<&VolumetricFog> for FogSettings {
+    fn from(fog: &VolumetricFog) -> Self {
+        FogSettings::new(
+            fog.color,
+            fog.density,
+            fog.anisotropy,
+            fog.max_distance,
+            fog.steps,
+            fog.shadow_steps,
+            fog.max_lights,
+        )
+    }
+}
+
+/// Gives the first `VolumetricFog` of the world to the renderer, or turns volumetrics off without one
+pub(crate) fn update_fog(compound: &Compound, photon: &mut Renderer) {
+    let mut fog = None;
+
+    compound.iter_mol(|_entity, volumetric_fog: &VolumetricFog| {
+        if fog.is_none() {
+            fog = Some(FogSettings::from(volumetric_fog));
+        }
+    });
+
+    photon.set_fog(fog);
+}
@@ -0,0 +1,915 @@
+use std::{collections::HashMap, path::Path, sync::RwLock};
+
+use anyhow::Result;
+use boson::{Boson, BosonBody, BosonObject, ColliderBuilder, RigidBody};
+use cgmath::{InnerSpace, Vector2, Vector3, Zero};
+use compound::Compound;
+use gpu_controller::{Vertex, hull_points};
+use log::{error, warn};
+
+use crate::{
+    AssetServer, Discarded, Model, Transform3D, model::read_obj_geometry, physics::PhysicsImpacts,
+};
+
+/// Default number of seconds a piece of debris lives before it is cleaned up
+pub const DEFAULT_DEBRIS_LIFETIME: f32 = 5.0;
+
+/// Default mass of a single piece of debris
+pub const DEFAULT_DEBRIS_MASS: f64 = 1.0;
+
+/// Default damage a fracturable takes per unit of speed it is hit with
+pub const DEFAULT_IMPACT_DAMAGE: f32 = 1.0;
+
+// Speed at which debris is pushed away from the center of the original mesh
+const DEBRIS_SEPARATION_SPEED: f32 = 2.0;
+
+// Points within this distance of a cell boundary are considered on the boundary
+const CLIP_EPSILON: f32 = 1e-6;
+
+// Ends of cut edges within this distance of each other are joined into one cap outline
+const CAP_WELD_EPSILON: f32 = 1e-4;
+
+/// A single pre-shattered piece of a fracturable mesh.
+///
+/// The vertices of the piece are stored relative to `offset`, which is the
+/// centroid of the piece in the local space of the original mesh, so the
+/// piece can be spawned as its own model at the correct location. Pieces of a
+/// closed mesh are closed as well, the faces cut by the cell are capped.
+#[derive(Clone)]
+pub struct FracturePiece {
+    pub(crate) vertices: Vec<Vertex>,
+    pub(crate) indices: Vec<u32>,
+    pub(crate) offset: Vector3<f32>,
+}
+
+impl FracturePiece {
+    /// Returns the centroid of the piece in the local space of the original mesh
+    pub fn offset(&self) -> Vector3<f32> {
+        self.offset
+    }
+
+    /// Returns the number of triangles that make up the piece
+    pub fn num_triangles(&self) -> usize {
+        self.indices.len() / 3
+    }
+}
+
+/// Component that makes a model break apart once it has taken enough damage.
+///
+/// The mesh is pre-shattered into pieces when the component is created. Until
+/// the damage threshold is reached the entity is rendered with its regular
+/// `Model` acting as a single proxy. Once the threshold is crossed the engine
+/// despawns the proxy, removes its body from physics and spawns every piece as
+/// its own debris entity with a convex hull rigid body and a `Debris` lifetime.
+///
+/// Entities with a `BosonObject` take damage from the impacts of physics,
+/// scaled by the speed of the impact, see `Fracturable::impact_damage`.
+///
+/// # Example
+/// ```ignore
+/// compound.spawn((
+///     Model::from_obj("crate.obj", assets, None)?,
+///     Transform3D::default(),
+///     RigidBody::new(10.0, ColliderBuilder::FromModelConvexHull),
+///     Fracturable::from_obj("crate.obj", assets, 8, 50.0)?,
+/// ));
+///
+/// // Gameplay can break it as well, such as from an explosion
+/// compound.iter_mut_mol(|_entity, fracturable: &mut Fracturable| {
+///     fracturable.apply_damage(25.0);
+/// });
+/// ```
+pub struct Fracturable {
+    pub(crate) label: String,
+    pub(crate) pieces: Vec<FracturePiece>,
+
+    damage: f32,
+    damage_threshold: f32,
+
+    pub(crate) debris_lifetime: f32,
+    pub(crate) debris_mass: f64,
+    pub(crate) impact_damage: f32,
+    pub(crate) shattered: bool,
+}
+
+impl Fracturable {
+    /// Pre-shatters the given mesh into `num_pieces` Voronoi pieces.
+    ///
+    /// # Arguments
+    /// * `label` - Label used to share the piece meshes with the asset server
+    /// * `vertices` - The vertices of the mesh to shatter
+    /// * `indices` - The triangle indices of the mesh to shatter
+    /// * `num_pieces` - The number of Voronoi cells to split the mesh into
+    /// * `damage_threshold` - The accumulated damage at which the mesh breaks
+    pub fn new<S>(
+        label: S,
+        vertices: &[Vertex],
+        indices: &[u32],
+        num_pieces: usize,
+        damage_threshold: f32,
+    ) -> Self
+    where
+        S: Into<String>,
+    {
+        let seeds = generate_seeds(vertices, num_pieces);
+
+        Self {
+            label: label.into(),
+            pieces: voronoi_shatter(vertices, indices, &seeds),
+            damage: 0.0,
+            damage_threshold,
+            debris_lifetime: DEFAULT_DEBRIS_LIFETIME,
+            debris_mass: DEFAULT_DEBRIS_MASS,
+            impact_damage: DEFAULT_IMPACT_DAMAGE,
+            shattered: false,
+        }
+    }
+
+    /// Reads the geometry of an OBJ file and pre-shatters it.
+    ///
+    /// All objects in the file are merged into a single mesh before shattering.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the OBJ file
+    /// * `asset_server` - The asset server to read the file through
+    /// * `num_pieces` - The number of Voronoi cells to split the mesh into
+    /// * `damage_threshold` - The accumulated damage at which the mesh breaks
+    pub fn from_obj<P>(
+        path: P,
+        asset_server: &AssetServer,
+        num_pieces: usize,
+        damage_threshold: f32,
+    ) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let (vertices, indices) = read_obj_geometry(&path, asset_server)?;
+
+        Ok(Self::new(
+            path.as_ref().to_string_lossy(),
+            &vertices,
+            &indices,
+            num_pieces,
+            damage_threshold,
+        ))
+    }
+
+    /// Accumulates damage on the fracturable.
+    ///
+    /// # Arguments
+    /// * `amount` - The amount of damage to apply
+    ///
+    /// # Returns
+    /// `true` if this damage caused the threshold to be crossed
+    pub fn apply_damage(&mut self, amount: f32) -> bool {
+        let was_broken = self.is_broken();
+        self.damage += amount;
+
+        !was_broken && self.is_broken()
+    }
+
+    /// Returns whether the accumulated damage has reached the threshold
+    pub fn is_broken(&self) -> bool {
+        self.damage >= self.damage_threshold
+    }
+
+    /// Returns whether the debris for this fracturable has already been spawned
+    pub fn is_shattered(&self) -> bool {
+        self.shattered
+    }
+
+    /// Returns the pre-shattered pieces
+    pub fn pieces(&self) -> &[FracturePiece] {
+        &self.pieces
+    }
+
+    /// Provides mutable access to the number of seconds each piece of debris lives.
+    ///
+    /// # Arguments
+    /// * `callback` - Function that receives a mutable reference to the debris lifetime
+    ///
+    /// # Returns
+    /// The return value of the callback function
+    pub fn debris_lifetime<F, R>(&mut self, callback: F) -> R
+    where
+        F: FnOnce(&mut f32) -> R,
+    {
+        callback(&mut self.debris_lifetime)
+    }
+
+    /// Provides mutable access to the mass of each piece of debris.
+    ///
+    /// # Arguments
+    /// * `callback` - Function that receives a mutable reference to the debris mass
+    ///
+    /// # Returns
+    /// The return value of the callback function
+    pub fn debris_mass<F, R>(&mut self, callback: F) -> R
+    where
+        F: FnOnce(&mut f64) -> R,
+    {
+        callback(&mut self.debris_mass)
+    }
+
+    /// Provides mutable access to the damage taken per unit of speed of an impact.
+    ///
+    /// # Arguments
+    /// * `callback` - Function that receives a mutable reference to the impact damage
+    ///
+    /// # Returns
+    /// The return value of the callback function
+    pub fn impact_damage<F, R>(&mut self, callback: F) -> R
+    where
+        F: FnOnce(&mut f32) -> R,
+    {
+        callback(&mut self.impact_damage)
+    }
+}
+
+/// Component attached to spawned debris that tracks how long it has been alive.
+///
+/// Debris that outlives its lifetime is despawned and removed from the
+/// physics engine by the engine.
+pub struct Debris {
+    pub(crate) lifetime: f32,
+    pub(crate) age: f32,
+}
+
+impl Debris {
+    /// Creates new debris that expires after `lifetime` seconds
+    pub fn new(lifetime: f32) -> Self {
+        Self { lifetime, age: 0.0 }
+    }
+
+    /// Advances the age of the debris.
+    ///
+    /// # Returns
+    /// `true` if the debris has outlived its lifetime
+    pub(crate) fn age(&mut self, dt: f32) -> bool {
+        self.age += dt;
+        self.age >= self.lifetime
+    }
+}
+
+/// Damages every fracturable whose body was hit in the impacts of the last step.
+///
+/// Both bodies of an impact take damage, scaled by the speed they hit each
+/// other with.
+pub(crate) fn damage_fracturables(compound: &Compound) {
+    let Some(impacts) = compound.resource(|impacts: &PhysicsImpacts| impacts.0.clone()) else {
+        return;
+    };
+
+    if impacts.is_empty() {
+        return;
+    }
+
+    let mut speeds: HashMap<usize, f32> = HashMap::new();
+    for impact in impacts {
+        for object in [impact.a, impact.b] {
+            *speeds.entry(object.id()).or_default() += impact.speed as f32;
+        }
+    }
+
+    let mut hit = HashMap::new();
+    compound.iter_duo(
+        |entity, _fracturable: &Fracturable, boson_object: &BosonObject| {
+            if let Some(speed) = speeds.get(&boson_object.id()) {
+                hit.insert(entity, *speed);
+            }
+        },
+    );
+
+    compound.iter_mut_mol(|entity, fracturable: &mut Fracturable| {
+        if let Some(speed) = hit.get(&entity) {
+            fracturable.apply_damage(speed * fracturable.impact_damage);
+        }
+    });
+}
+
+/// Spawns debris for every fracturable that crossed its damage threshold.
+///
+/// The original entity is discarded along with its body, and every
+/// pre-shattered piece becomes its own entity with a model, transform, convex
+/// hull rigid body and `Debris`.
+pub(crate) fn shatter_fracturables(
+    compound: &Compound,
+    asset_server: &AssetServer,
+    boson: &RwLock<Boson>,
+) {
+    let mut to_shatter = Vec::new();
+
+    compound.iter_mut_trio_unmod(
+        |entity, fracturable: &mut Fracturable, transform: &mut Transform3D, model: &mut Model| {
+            if fracturable.is_broken() && !fracturable.shattered {
+                fracturable.shattered = true;
+                to_shatter.push((
+                    entity,
+                    fracturable.label.clone(),
+                    fracturable.pieces.clone(),
+                    fracturable.debris_lifetime,
+                    fracturable.debris_mass,
+                    *transform,
+                    model.material(),
+                ));
+            }
+        },
+    );
+
+    // The bodies of the proxies would keep colliding with the debris
+    let mut proxies = Vec::new();
+    compound.iter_mol(|entity, boson_object: &BosonObject| {
+        if to_shatter.iter().any(|shattered| shattered.0 == entity) {
+            proxies.push(boson_object.clone());
+        }
+    });
+
+    if !proxies.is_empty() {
+        match boson.write() {
+            Ok(mut boson) => {
+                for proxy in proxies.iter() {
+                    boson.remove_object(proxy);
+                }
+            }
+            Err(err) => error!("Failed to remove fracturables from boson: {}", err),
+        }
+    }
+
+    for (entity, label, pieces, lifetime, mass, transform, material) in to_shatter {
+        compound.add_molecule(entity, Discarded);
+
+        let Some(material) = material else {
+            warn!("Fracturable {} has no material, debris not spawned", label);
+            continue;
+        };
+
+        let (position, rotation) = transform.get_position_and_rotation(|pos, rot| (*pos, *rot));
+
+        for (index, piece) in pieces.iter().enumerate() {
+            let model = match Model::from_geometry(
+                format!("{} Fracture Piece {}", label, index),
+                &piece.vertices,
+                &piece.indices,
+                material.clone(),
+                asset_server,
+                None,
+            ) {
+                Ok(model) => model,
+                Err(err) => {
+                    error!("Failed to create debris model: {}", err);
+                    continue;
+                }
+            };
+
+            let world_offset = rotation * piece.offset;
+            let velocity = if world_offset.magnitude2() > 0.0 {
+                world_offset.normalize_to(DEBRIS_SEPARATION_SPEED)
+            } else {
+                Vector3::zero()
+            };
+
+            let positions = piece
+                .vertices
+                .iter()
+                .map(|vertex| vertex.position)
+                .collect::<Vec<_>>();
+            let boson_object =
+                RigidBody::new(mass, ColliderBuilder::ConvexHull(hull_points(&positions)));
+            boson_object.modify_body(|body| {
+                if let BosonBody::RigidBody(rigid_body) = body {
+                    rigid_body.velocity = velocity.cast().unwrap_or(Vector3::zero());
+                }
+            });
+
+            compound.spawn((
+                model,
+                Transform3D::new(position + world_offset, rotation),
+                boson_object,
+                Debris::new(lifetime),
+            ));
+        }
+    }
+}
+
+/// Ages all live debris and cleans up any that has outlived its lifetime.
+///
+/// Expired debris is removed from the physics engine and discarded, so it is
+/// despawned before the next physics step.
+pub(crate) fn expire_debris(compound: &Compound, boson: &RwLock<Boson>, dt: f32) {
+    let mut expired = Vec::new();
+
+    compound.iter_mut_without_duo_unmod::<Discarded, _, _, _>(
+        |entity, debris: &mut Debris, boson_object: &mut BosonObject| {
+            if debris.age(dt) {
+                expired.push((entity, boson_object.clone()));
+            }
+        },
+    );
+
+    for (entity, boson_object) in expired {
+        match boson.write() {
+            Ok(mut boson) => boson.remove_object(&boson_object),
+            Err(err) => error!("Failed to remove debris from boson: {}", err),
+        }
+
+        compound.add_molecule(entity, Discarded);
+    }
+}
+
+/// Splits a triangle mesh into pieces along the Voronoi cells of the given seeds.
+///
+/// Every cell starts from the whole mesh and is clipped against the bisecting
+/// planes between its seed and every other seed, so triangles that cross a
+/// cell boundary are cut and their attributes interpolated. Each cut is capped
+/// with the outline it leaves on the plane, so the pieces of a closed mesh are
+/// closed solids. Cross sections with holes in them are capped as if solid.
+///
+/// # Arguments
+/// * `vertices` - The vertices of the mesh to shatter
+/// * `indices` - The triangle indices of the mesh to shatter
+/// * `seeds` - The Voronoi seed points in the local space of the mesh
+///
+/// # Returns
+/// One piece for every seed whose cell contains part of the mesh
+pub fn voronoi_shatter(
+    vertices: &[Vertex],
+    indices: &[u32],
+    seeds: &[Vector3<f32>],
+) -> Vec<FracturePiece> {
+    let triangles = indices
+        .chunks_exact(3)
+        .map(|triangle| {
+            triangle
+                .iter()
+                .map(|index| vertices[*index as usize])
+                .collect::<Vec<Vertex>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut pieces = Vec::new();
+
+    for (seed_index, seed) in seeds.iter().enumerate() {
+        let mut polygons = triangles.clone();
+
+        for (other_index, other) in seeds.iter().enumerate() {
+            if other_index == seed_index || polygons.is_empty() {
+                continue;
+            }
+
+            let normal = other - seed;
+            let midpoint = (seed + other) * 0.5;
+            polygons = clip_mesh(&polygons, normal, normal.dot(midpoint));
+        }
+
+        let mut piece_vertices: Vec<Vertex> = Vec::new();
+        let mut piece_indices: Vec<u32> = Vec::new();
+
+        for mut polygon in polygons {
+            // Fan triangulation of the clipped convex polygon
+            let base = piece_vertices.len() as u32;
+            for i in 1..(polygon.len() as u32 - 1) {
+                piece_indices.extend_from_slice(&[base, base + i, base + i + 1]);
+            }
+            piece_vertices.append(&mut polygon);
+        }
+
+        if piece_indices.is_empty() {
+            continue;
+        }
+
+        let offset = piece_vertices.iter().fold(Vector3::zero(), |sum, vertex| {
+            sum + Vector3::from(vertex.position)
+        }) / piece_vertices.len() as f32;
+
+        for vertex in piece_vertices.iter_mut() {
+            vertex.position = (Vector3::from(vertex.position) - offset).into();
+        }
+
+        pieces.push(FracturePiece {
+            vertices: piece_vertices,
+            indices: piece_indices,
+            offset,
+        });
+    }
+
+    pieces
+}
+
+/// Keeps the part of a mesh of convex polygons where normal . p <= distance
+/// and caps the hole the cut leaves with triangles facing along the normal
+fn clip_mesh(polygons: &[Vec<Vertex>], normal: Vector3<f32>, distance: f32) -> Vec<Vec<Vertex>> {
+    let on_plane = |vertex: &Vertex| {
+        (normal.dot(Vector3::from(vertex.position)) - distance).abs()
+            <= CLIP_EPSILON * normal.magnitude().max(1.0)
+    };
+
+    let mut clipped = Vec::with_capacity(polygons.len());
+    let mut cut_edges: Vec<(Vector3<f32>, Vector3<f32>)> = Vec::new();
+
+    for polygon in polygons {
+        let polygon = clip_polygon(polygon, normal, distance);
+        if polygon.len() < 3 {
+            continue;
+        }
+
+        // Faces lying in the plane already close the mesh there
+        if !polygon.iter().all(on_plane) {
+            for (i, current) in polygon.iter().enumerate() {
+                let next = &polygon[(i + 1) % polygon.len()];
+
+                if on_plane(current) && on_plane(next) {
+                    cut_edges.push((current.position.into(), next.position.into()));
+                }
+            }
+        }
+
+        clipped.push(polygon);
+    }
+
+    for outline in cap_outlines(cut_edges) {
+        for triangle in triangulate_cap(&outline, normal) {
+            clipped.push(triangle.to_vec());
+        }
+    }
+
+    clipped
+}
+
+/// Joins the edges a cut left on a plane into closed outlines.
+///
+/// Edges that run both ways between the same points border faces on either
+/// side of the plane and cancel out, and edges that don't close into an
+/// outline, such as those of an open mesh, are dropped.
+fn cap_outlines(mut edges: Vec<(Vector3<f32>, Vector3<f32>)>) -> Vec<Vec<Vector3<f32>>> {
+    let same = |a: Vector3<f32>, b: Vector3<f32>| (a - b).magnitude2() <= CAP_WELD_EPSILON.powi(2);
+
+    let mut index = 0;
+    while index < edges.len() {
+        let (start, end) = edges[index];
+        match edges
+            .iter()
+            .skip(index + 1)
+            .position(|(other_start, other_end)| same(start, *other_end) && same(end, *other_start))
+        {
+            Some(twin) => {
+                edges.remove(index + 1 + twin);
+                edges.remove(index);
+            }
+            None => index += 1,
+        }
+    }
+
+    let mut outlines = Vec::new();
+
+    while let Some((start, end)) = edges.pop() {
+        let mut outline = vec![start];
+        let mut current = end;
+
+        let closed = loop {
+            if same(current, start) {
+                break true;
+            }
+
+            let Some(next) = edges
+                .iter()
+                .position(|(from, to)| same(*from, current) || same(*to, current))
+            else {
+                break false;
+            };
+
+            let (from, to) = edges.swap_remove(next);
+            outline.push(current);
+            current = if same(from, current) { to } else { from };
+        };
+
+        if closed && outline.len() >= 3 {
+            outlines.push(outline);
+        }
+    }
+
+    outlines
+}
+
+/// Triangulates the outline of a cap by ear clipping, facing along the normal.
+///
+/// The cap is textured by projecting it onto the plane.
+fn triangulate_cap(outline: &[Vector3<f32>], normal: Vector3<f32>) -> Vec<[Vertex; 3]> {
+    let normal = normal.normalize();
+    let tangent = if normal.x.abs() < 0.9 {
+        Vector3::unit_x().cross(normal).normalize()
+    } else {
+        Vector3::unit_y().cross(normal).normalize()
+    };
+    let bitangent = normal.cross(tangent);
+
+    let projected = outline
+        .iter()
+        .map(|point| Vector2::new(point.dot(tangent), point.dot(bitangent)))
+        .collect::<Vec<_>>();
+
+    let cross = |o: Vector2<f32>, a: Vector2<f32>, b: Vector2<f32>| {
+        (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x)
+    };
+
+    // Wind the outline counter clockwise seen from the normal
+    let area = (0..projected.len())
+        .map(|i| {
+            let (a, b) = (projected[i], projected[(i + 1) % projected.len()]);
+            a.x * b.y - b.x * a.y
+        })
+        .sum::<f32>();
+    let mut remaining = (0..outline.len()).collect::<Vec<_>>();
+    if area < 0.0 {
+        remaining.reverse();
+    }
+
+    let mut triangles = Vec::new();
+
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let ear = (0..count).find(|&i| {
+            let (a, b, c) = (
+                remaining[(i + count - 1) % count],
+                remaining[i],
+                remaining[(i + 1) % count],
+            );
+            let (pa, pb, pc) = (projected[a], projected[b], projected[c]);
+
+            cross(pa, pb, pc) > 0.0
+                && remaining.iter().all(|&other| {
+                    other == a
+                        || other == b
+                        || other == c
+                        || cross(pa, pb, projected[other]) < 0.0
+                        || cross(pb, pc, projected[other]) < 0.0
+                        || cross(pc, pa, projected[other]) < 0.0
+                })
+        });
+
+        // A degenerate outline has no ear left, fan the rest
+        let Some(ear) = ear else {
+            break;
+        };
+
+        triangles.push([
+            remaining[(ear + count - 1) % count],
+            remaining[ear],
+            remaining[(ear + 1) % count],
+        ]);
+        remaining.remove(ear);
+    }
+
+    for i in 1..remaining.len().saturating_sub(1) {
+        triangles.push([remaining[0], remaining[i], remaining[i + 1]]);
+    }
+
+    let vertex = |index: usize| Vertex {
+        position: outline[index].into(),
+        uv_coord: projected[index].into(),
+        normal_vec: normal.into(),
+    };
+
+    triangles
+        .into_iter()
+        .map(|triangle| triangle.map(vertex))
+        .collect()
+}
+
+// Keeps the part of the polygon where normal . p <= distance (Sutherland-Hodgman)
+fn clip_polygon(polygon: &[Vertex], normal: Vector3<f32>, distance: f32) -> Vec<Vertex> {
+    let signed_distance = |vertex: &Vertex| normal.dot(Vector3::from(vertex.position)) - distance;
+
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+
+    for (i, current) in polygon.iter().enumerate() {
+        let next = &polygon[(i + 1) % polygon.len()];
+        let current_distance = signed_distance(current);
+        let next_distance = signed_distance(next);
+
+        if current_distance <= CLIP_EPSILON {
+            clipped.push(*current);
+        }
+
+        if (current_distance > CLIP_EPSILON && next_distance < -CLIP_EPSILON)
+            || (current_distance < -CLIP_EPSILON && next_distance > CLIP_EPSILON)
+        {
+            let t = current_distance / (current_distance - next_distance);
+            clipped.push(lerp_vertex(current, next, t));
+        }
+    }
+
+    clipped
+}
+
+fn lerp_vertex(a: &Vertex, b: &Vertex, t: f32) -> Vertex {
+    let lerp = |a: f32, b: f32| a + (b - a) * t;
+
+    let normal = Vector3::new(
+        lerp(a.normal_vec[0], b.normal_vec[0]),
+        lerp(a.normal_vec[1], b.normal_vec[1]),
+        lerp(a.normal_vec[2], b.normal_vec[2]),
+    );
+
+    Vertex {
+        position: [
+            lerp(a.position[0], b.position[0]),
+            lerp(a.position[1], b.position[1]),
+            lerp(a.position[2], b.position[2]),
+        ],
+        uv_coord: [
+            lerp(a.uv_coord[0], b.uv_coord[0]),
+            lerp(a.uv_coord[1], b.uv_coord[1]),
+        ],
+        normal_vec: if normal.magnitude2() > 0.0 {
+            normal.normalize().into()
+        } else {
+            a.normal_vec
+        },
+    }
+}
+
+// Deterministically scatters seeds inside the bounding box of the mesh so the
+// same mesh always shatters the same way
+fn generate_seeds(vertices: &[Vertex], num_pieces: usize) -> Vec<Vector3<f32>> {
+    if vertices.is_empty() {
+        return Vec::new();
+    }
+
+    let (min, max) = vertices.iter().fold(
+        (
+            Vector3::from(vertices[0].position),
+            Vector3::from(vertices[0].position),
+        ),
+        |(min, max), vertex| {
+            let position = Vector3::from(vertex.position);
+            (
+                Vector3::new(
+                    min.x.min(position.x),
+                    min.y.min(position.y),
+                    min.z.min(position.z),
+                ),
+                Vector3::new(
+                    max.x.max(position.x),
+                    max.y.max(position.y),
+                    max.z.max(position.z),
+                ),
+            )
+        },
+    );
+
+    // xorshift32
+    let mut state: u32 = 0x9E37_79B9;
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state as f32 / u32::MAX as f32
+    };
+
+    (0..num_pieces.max(1))
+        .map(|_| {
+            Vector3::new(
+                min.x + (max.x - min.x) * next(),
+                min.y + (max.y - min.y) * next(),
+                min.z + (max.z - min.z) * next(),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use boson::PointMass;
+
+    use super::*;
+    use crate::physics::despawn_discarded;
+
+    // A cube with an edge length of 2 centered on the origin, wound counter
+    // clockwise seen from outside
+    fn cube() -> (Vec<Vertex>, Vec<u32>) {
+        let faces: [(Vector3<f32>, Vector3<f32>, Vector3<f32>); 6] = [
+            (Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()),
+            (-Vector3::unit_x(), Vector3::unit_z(), Vector3::unit_y()),
+            (Vector3::unit_y(), Vector3::unit_z(), Vector3::unit_x()),
+            (-Vector3::unit_y(), Vector3::unit_x(), Vector3::unit_z()),
+            (Vector3::unit_z(), Vector3::unit_x(), Vector3::unit_y()),
+            (-Vector3::unit_z(), Vector3::unit_y(), Vector3::unit_x()),
+        ];
+
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+
+        for (normal, u, v) in faces {
+            let base = vertices.len() as u32;
+            for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+                vertices.push(Vertex {
+                    position: (normal + u * x + v * y).into(),
+                    uv_coord: [x, y],
+                    normal_vec: normal.into(),
+                });
+            }
+            indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+        }
+
+        (vertices, indices)
+    }
+
+    fn volume(piece: &FracturePiece) -> f32 {
+        piece
+            .indices
+            .chunks_exact(3)
+            .map(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|corner| {
+                    Vector3::from(piece.vertices[triangle[corner] as usize].position)
+                });
+                a.dot(b.cross(c)) / 6.0
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_cube_is_wound_outwards() {
+        let (vertices, indices) = cube();
+        let piece = FracturePiece {
+            vertices,
+            indices,
+            offset: Vector3::zero(),
+        };
+
+        assert!((volume(&piece) - 8.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_pieces_are_closed() {
+        let (vertices, indices) = cube();
+        let pieces = voronoi_shatter(&vertices, &indices, &generate_seeds(&vertices, 8));
+        assert!(pieces.len() > 1);
+
+        for piece in pieces.iter() {
+            // Every edge is shared with a triangle running it the other way
+            // Joins the copies of a point the triangles of the piece each have
+            let mut welded: Vec<Vector3<f32>> = Vec::new();
+            let mut weld = |index: u32| {
+                let position = Vector3::from(piece.vertices[index as usize].position);
+                match welded
+                    .iter()
+                    .position(|other| (other - position).magnitude() < 1e-3)
+                {
+                    Some(welded_index) => welded_index,
+                    None => {
+                        welded.push(position);
+                        welded.len() - 1
+                    }
+                }
+            };
+
+            let mut edges: HashMap<(usize, usize), i32> = HashMap::new();
+            for triangle in piece.indices.chunks_exact(3) {
+                for corner in 0..3 {
+                    let (from, to) = (weld(triangle[corner]), weld(triangle[(corner + 1) % 3]));
+                    if from == to {
+                        continue;
+                    }
+
+                    *edges.entry((from, to)).or_default() += 1;
+                    *edges.entry((to, from)).or_default() -= 1;
+                }
+            }
+
+            assert!(edges.values().all(|count| *count == 0));
+            assert!(volume(piece) > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_pieces_fill_the_mesh() {
+        let (vertices, indices) = cube();
+        let pieces = voronoi_shatter(&vertices, &indices, &generate_seeds(&vertices, 6));
+
+        let total = pieces.iter().map(volume).sum::<f32>();
+        assert!((total - 8.0).abs() < 1e-3, "total volume {}", total);
+    }
+
+    #[test]
+    fn test_expired_debris_is_despawned() {
+        let compound = Compound::new();
+        let boson = RwLock::new(Boson::new());
+
+        for lifetime in [1.0, 1.0, 5.0] {
+            let body = PointMass::new(1.0);
+            boson.write().unwrap().add_object(&body);
+            compound.spawn((Transform3D::default(), body, Debris::new(lifetime)));
+        }
+
+        let entities = || compound.query::<(&Transform3D,), ()>().count();
+        assert_eq!(entities(), 3);
+
+        expire_debris(&compound, &boson, 2.0);
+        despawn_discarded(&compound, &boson);
+        assert_eq!(entities(), 1);
+
+        expire_debris(&compound, &boson, 4.0);
+        despawn_discarded(&compound, &boson);
+        assert_eq!(entities(), 0);
+    }
+}
@@ -0,0 +1,109 @@
+use std::f32::consts::TAU;
+
+use cgmath::{Quaternion, Vector3};
+use compound::Compound;
+use photon::GizmoVertex;
+
+use super::pathfinding::collect_path_gizmos;
+use crate::{Model, Transform3D};
+
+// Number of line segments used for each circle of a bounding sphere
+const SPHERE_SEGMENTS: usize = 32;
+
+// Pairs of corner indices from `Bounds::corners` that make up the box edges
+const BOX_EDGES: [(usize, usize); 12] = [
+    (0, 1),
+    (1, 2),
+    (2, 3),
+    (3, 0),
+    (4, 5),
+    (5, 6),
+    (6, 7),
+    (7, 4),
+    (0, 4),
+    (1, 5),
+    (2, 6),
+    (3, 7),
+];
+
+/// Draws the bounds of an entity's `Model` as a debug overlay.
+///
+/// The bounding box is drawn oriented with the entity's `Transform3D`, and
+/// the bounding sphere is drawn as three circles around its center.
+///
+/// # Example
+/// ```ignore
+/// ecs.spawn((
+///     Model::from_obj("crate.obj", assets, None)?,
+///     Transform3D::default(),
+///     BoundsGizmo::default(),
+/// ));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundsGizmo {
+    pub color: [f32; 4],
+    /// Whether the bounding box is drawn
+    pub show_box: bool,
+    /// Whether the bounding sphere is drawn
+    pub show_sphere: bool,
+}
+
+impl Default for BoundsGizmo {
+    fn default() -> Self {
+        Self {
+            color: [0.0, 1.0, 0.0, 1.0],
+            show_box: true,
+            show_sphere: false,
+        }
+    }
+}
+
+/// Builds the lines for every `BoundsGizmo` in world space
+pub(crate) fn collect_gizmos(compound: &Compound) -> Vec<GizmoVertex> {
+    let mut vertices = Vec::new();
+
+    compound.iter_trio(
+        |_entity, gizmo: &BoundsGizmo, model: &Model, transform: &Transform3D| {
+            let bounds = model.bounds();
+            let position = Vector3::from(transform.position);
+            let rotation = Quaternion::from(transform.rotation);
+
+            let to_world = |point: [f32; 3]| -> [f32; 3] {
+                (rotation * Vector3::from(point) + position).into()
+            };
+
+            if gizmo.show_box {
+                let corners = bounds.corners().map(to_world);
+
+                for (start, end) in BOX_EDGES {
+                    vertices.push(GizmoVertex::new(corners[start], gizmo.color));
+                    vertices.push(GizmoVertex::new(corners[end], gizmo.color));
+                }
+            }
+
+            if gizmo.show_sphere {
+                let radius = bounds.radius;
+
+                // One circle in each of the local XY, YZ and XZ planes
+                for (u, v) in [(0, 1), (1, 2), (0, 2)] {
+                    let point = |segment: usize| {
+                        let angle = segment as f32 / SPHERE_SEGMENTS as f32 * TAU;
+                        let mut point = bounds.center;
+                        point[u] += radius * angle.cos();
+                        point[v] += radius * angle.sin();
+                        to_world(point)
+                    };
+
+                    for segment in 0..SPHERE_SEGMENTS {
+                        vertices.push(GizmoVertex::new(point(segment), gizmo.color));
+                        vertices.push(GizmoVertex::new(point(segment + 1), gizmo.color));
+                    }
+                }
+            }
+        },
+    );
+
+    collect_path_gizmos(compound, &mut vertices);
+
+    vertices
+}
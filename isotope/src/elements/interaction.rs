@@ -0,0 +1,319 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use boson::{Boson, BosonObject};
+use cgmath::{InnerSpace, Rotation, Vector3};
+use compound::{Compound, Entity, Without};
+use log::error;
+
+use super::perception::to_f64;
+use crate::{Discarded, Transform3D};
+
+/// Default distance an interactor can reach
+pub const DEFAULT_REACH: f32 = 2.5;
+
+/// Component for anything a player can use, such as doors, buttons and pickups.
+///
+/// An interactable is focused by an `Interactor` when the ray from the
+/// interactor hits the sphere around it within range and nothing blocks the
+/// line between them. With a facing requirement it can only be used from in
+/// front of it, +Z of its transform. Every use is queued on the interactable
+/// for gameplay to drain.
+///
+/// # Example
+/// ```ignore
+/// compound.spawn((
+///     Transform3D::new(door_position, door_rotation),
+///     Interactable::new("Open door")
+///         .with_radius(1.0)
+///         .with_facing(std::f32::consts::FRAC_PI_2),
+/// ));
+///
+/// // In IsotopeState::update
+/// compound.iter_mut_mol(|_entity, interactable: &mut Interactable| {
+///     for interactor in interactable.drain_interactions() {
+///         // Open the door
+///     }
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct Interactable {
+    /// Text shown to the player while the interactable is focused
+    pub prompt: String,
+    /// Furthest distance it can be used from, limited by the reach of the interactor
+    pub range: f32,
+    /// Radius of the sphere around the interactable that is aimed at
+    pub radius: f32,
+    /// Largest angle between the front of the interactable and the direction
+    /// to the interactor, none to use it from any side
+    pub facing: Option<f32>,
+    /// Disabled interactables can't be focused
+    pub enabled: bool,
+
+    interactions: Vec<Entity>,
+}
+
+impl Interactable {
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            range: f32::INFINITY,
+            radius: 0.5,
+            facing: None,
+            enabled: true,
+            interactions: Vec::new(),
+        }
+    }
+
+    pub fn with_range(mut self, range: f32) -> Self {
+        self.range = range;
+        self
+    }
+
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    pub fn with_facing(mut self, half_angle: f32) -> Self {
+        self.facing = Some(half_angle);
+        self
+    }
+
+    /// Takes the interactors that used the interactable since the last time
+    /// they were drained
+    pub fn drain_interactions(&mut self) -> Vec<Entity> {
+        std::mem::take(&mut self.interactions)
+    }
+}
+
+/// What a UI shows while an interactor focuses an interactable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InteractionPrompt {
+    pub entity: Entity,
+    pub text: String,
+    /// Position of the interactable, to place the prompt on screen
+    pub position: Vector3<f32>,
+    /// Distance from the interactor to the interactable
+    pub distance: f32,
+}
+
+/// What gameplay is told about the focus and use of an interactor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InteractionEvent {
+    /// An interactable came into focus
+    Focused { entity: Entity },
+    /// The focused interactable is no longer focused
+    Unfocused { entity: Entity },
+    /// The focused interactable was used
+    Interacted { entity: Entity },
+}
+
+/// Component for an entity that uses interactables, usually the camera of the
+/// player.
+///
+/// Every tick the engine casts a ray along +Z of the transform and focuses the
+/// closest interactable it hits. Calling `interact`, such as when the use key
+/// is pressed, uses the focused interactable on the next tick.
+///
+/// # Example
+/// ```ignore
+/// compound.spawn((
+///     Camera3DDescriptor::default(),
+///     Transform3D::default(),
+///     Interactor::new(),
+/// ));
+///
+/// // In IsotopeState::update
+/// compound.iter_mut_duo(|_entity, actions: &mut ActionMap, interactor: &mut Interactor| {
+///     if actions.take_triggered().iter().any(|action| action == "use") {
+///         interactor.interact();
+///     }
+///
+///     if let Some(prompt) = interactor.prompt() {
+///         println!("[E] {}", prompt.text);
+///     }
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct Interactor {
+    /// Furthest distance the interactor can use interactables from
+    pub reach: f32,
+
+    prompt: Option<InteractionPrompt>,
+    requested: bool,
+    events: Vec<InteractionEvent>,
+}
+
+impl Default for Interactor {
+    fn default() -> Self {
+        Self {
+            reach: DEFAULT_REACH,
+            prompt: None,
+            requested: false,
+            events: Vec::new(),
+        }
+    }
+}
+
+impl Interactor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_reach(mut self, reach: f32) -> Self {
+        self.reach = reach;
+        self
+    }
+
+    /// Uses the focused interactable on the next tick
+    pub fn interact(&mut self) {
+        self.requested = true;
+    }
+
+    /// Returns the prompt of the focused interactable
+    pub fn prompt(&self) -> Option<&InteractionPrompt> {
+        self.prompt.as_ref()
+    }
+
+    /// Returns the focused interactable
+    pub fn focused(&self) -> Option<Entity> {
+        self.prompt.as_ref().map(|prompt| prompt.entity)
+    }
+
+    /// Takes the events queued since the last time they were drained
+    pub fn drain_events(&mut self) -> Vec<InteractionEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+/// Focuses the interactable every interactor is aiming at and uses it if the
+/// interactor asked to.
+pub(crate) fn update_interactions(compound: &Compound, boson: &RwLock<Boson>) {
+    let mut interactables = Vec::new();
+    compound
+        .query::<(&Interactable, &Transform3D), Without<Discarded>>()
+        .for_each(|entity, (interactable, transform)| {
+            if !interactable.enabled {
+                return;
+            }
+
+            let (position, rotation) =
+                transform.get_position_and_rotation(|position, rotation| (*position, *rotation));
+            interactables.push((
+                entity,
+                position,
+                rotation.rotate_vector(Vector3::unit_z()),
+                interactable.clone(),
+            ));
+        });
+
+    // Bodies don't block the interactors and interactables they belong to
+    let mut bodies = HashMap::new();
+    compound.iter_mol(|entity, boson_object: &BosonObject| {
+        bodies.insert(entity, boson_object.clone());
+    });
+
+    let boson = match boson.read() {
+        Ok(boson) => boson,
+        Err(err) => {
+            error!("Failed to read physics for interactions: {}", err);
+            return;
+        }
+    };
+
+    let mut used = Vec::new();
+    compound
+        .query::<(&mut Interactor, &Transform3D), Without<Discarded>>()
+        .unmod()
+        .for_each(|entity, (interactor, transform)| {
+            let (eye, forward) = transform.get_position_and_rotation(|position, rotation| {
+                (*position, rotation.rotate_vector(Vector3::unit_z()))
+            });
+
+            let mut focus: Option<InteractionPrompt> = None;
+            for (other, position, front, interactable) in interactables.iter() {
+                if *other == entity {
+                    continue;
+                }
+
+                let offset = position - eye;
+                let distance = offset.magnitude();
+                if distance > interactor.reach.min(interactable.range) {
+                    continue;
+                }
+
+                if let Some(facing) = interactable.facing
+                    && distance > f32::EPSILON
+                    && front.dot(-offset / distance) < facing.cos()
+                {
+                    continue;
+                }
+
+                // Closest approach of the ray to the center of the interactable
+                let along = offset.dot(forward);
+                if along < 0.0 && distance > interactable.radius {
+                    continue;
+                }
+                if (offset - forward * along).magnitude() > interactable.radius {
+                    continue;
+                }
+
+                if focus
+                    .as_ref()
+                    .is_some_and(|focus| focus.distance <= distance)
+                {
+                    continue;
+                }
+
+                let ignore = [bodies.get(&entity), bodies.get(other)]
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>();
+                if boson.is_occluded(to_f64(eye), to_f64(*position), &ignore) {
+                    continue;
+                }
+
+                focus = Some(InteractionPrompt {
+                    entity: *other,
+                    text: interactable.prompt.clone(),
+                    position: *position,
+                    distance,
+                });
+            }
+
+            let previous = interactor.focused();
+            let current = focus.as_ref().map(|focus| focus.entity);
+            if previous != current {
+                if let Some(previous) = previous {
+                    interactor
+                        .events
+                        .push(InteractionEvent::Unfocused { entity: previous });
+                }
+                if let Some(current) = current {
+                    interactor
+                        .events
+                        .push(InteractionEvent::Focused { entity: current });
+                }
+            }
+            interactor.prompt = focus;
+
+            if std::mem::take(&mut interactor.requested)
+                && let Some(current) = current
+            {
+                interactor
+                    .events
+                    .push(InteractionEvent::Interacted { entity: current });
+                used.push((current, entity));
+            }
+        });
+
+    if used.is_empty() {
+        return;
+    }
+
+    compound.iter_mut_mol_unmod(|entity, interactable: &mut Interactable| {
+        for (_, interactor) in used.iter().filter(|(used, _)| *used == entity) {
+            interactable.interactions.push(*interactor);
+        }
+    });
+}
@@ -0,0 +1,900 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use anyhow::{Result, anyhow};
+use cgmath::{InnerSpace, Quaternion, Rotation, Vector3, Zero};
+use compound::{Compound, Entity, Without};
+use log::{debug, warn};
+
+use crate::{AssetServer, Discarded, PendingModel, Transform3D};
+
+/// Seconds before a dropped item can be picked up again
+pub const PICKUP_DELAY: f32 = 1.0;
+
+/// Distance in front of an entity that its dropped items are placed at
+pub const DROP_DISTANCE: f32 = 1.0;
+
+/// The data that describes a kind of item, loaded from a `.item` asset.
+///
+/// The id of an item is the file name of its asset without the extension.
+/// Unknown keys are ignored so games can keep their own data in the file.
+///
+/// # Example
+/// ```ignore
+/// # game://items/health_potion.item
+/// name Health Potion
+/// max_stack 10
+/// model game://models/potion.obj
+/// slot belt
+/// property heal 25
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemDefinition {
+    pub id: String,
+    /// Name shown to the player
+    pub name: String,
+    /// The most items that fit in one inventory slot
+    pub max_stack: u32,
+    /// Model shown when the item is equipped or lies in the world
+    pub model: Option<String>,
+    /// The equipment slot the item can be equipped in
+    pub slot: Option<String>,
+    /// Numbers for gameplay, such as damage or weight
+    pub properties: HashMap<String, f32>,
+}
+
+impl ItemDefinition {
+    /// Creates an item that does not stack and is not equippable
+    pub fn new(id: impl Into<String>) -> Self {
+        let id = id.into();
+
+        Self {
+            name: id.clone(),
+            id,
+            max_stack: 1,
+            model: None,
+            slot: None,
+            properties: HashMap::new(),
+        }
+    }
+
+    /// Loads an item definition from an asset.
+    ///
+    /// # Arguments
+    /// * `asset_server` - The asset server to read the asset from
+    /// * `path` - Path of the `.item` asset
+    ///
+    /// # Returns
+    /// The item definition, or an error if the asset could not be read or a value is invalid
+    pub fn load<P>(asset_server: &AssetServer, path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let id = path
+            .as_ref()
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| anyhow!("Invalid item path: {:#?}", path.as_ref()))?;
+        let source = String::from_utf8(asset_server.read_asset(path.as_ref())?)?;
+
+        Self::parse(id, &source)
+    }
+
+    /// Parses the contents of a `.item` asset
+    pub fn parse(id: &str, source: &str) -> Result<Self> {
+        let mut definition = Self::new(id);
+
+        for line in source.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let value = value.trim();
+            if value.is_empty() {
+                return Err(anyhow!("Missing value for {} in item {}", key, id));
+            }
+
+            match key {
+                "name" => definition.name = value.to_string(),
+                "max_stack" => definition.max_stack = value.parse::<u32>()?.max(1),
+                "model" => definition.model = Some(value.to_string()),
+                "slot" => definition.slot = Some(value.to_string()),
+                "property" => {
+                    let (name, amount) = value
+                        .split_once(char::is_whitespace)
+                        .ok_or_else(|| anyhow!("Missing amount for property {}", value))?;
+                    definition
+                        .properties
+                        .insert(name.to_string(), amount.trim().parse::<f32>()?);
+                }
+                _ => debug!("Ignoring unknown item key: {}", key),
+            }
+        }
+
+        Ok(definition)
+    }
+
+    pub fn property(&self, name: &str) -> Option<f32> {
+        self.properties.get(name).copied()
+    }
+}
+
+/// Every item definition of a game.
+///
+/// Insert as a resource of the compound, inventories are only updated by the
+/// engine in compounds that have one.
+///
+/// # Example
+/// ```ignore
+/// let mut items = ItemDatabase::new();
+/// items.load(&asset_server, "game://items/health_potion.item")?;
+/// items.load(&asset_server, "game://items/sword.item")?;
+/// compound.insert_resource(items);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ItemDatabase {
+    items: HashMap<String, ItemDefinition>,
+}
+
+impl ItemDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an item definition, replacing any with the same id
+    pub fn insert(&mut self, definition: ItemDefinition) {
+        self.items.insert(definition.id.clone(), definition);
+    }
+
+    /// Loads an item definition from an asset and adds it
+    ///
+    /// # Returns
+    /// The id of the item, or an error if the asset could not be loaded
+    pub fn load<P>(&mut self, asset_server: &AssetServer, path: P) -> Result<String>
+    where
+        P: AsRef<Path>,
+    {
+        let definition = ItemDefinition::load(asset_server, path)?;
+        let id = definition.id.clone();
+        self.insert(definition);
+
+        Ok(id)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ItemDefinition> {
+        self.items.get(id)
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.items.contains_key(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+/// A number of items of the same kind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemStack {
+    pub item: String,
+    pub count: u32,
+}
+
+impl ItemStack {
+    pub fn new(item: impl Into<String>, count: u32) -> Self {
+        Self {
+            item: item.into(),
+            count,
+        }
+    }
+}
+
+/// What gameplay is told about changes to inventories and equipment.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InventoryEvent {
+    /// Items were taken from a pickup, which is discarded once it is empty
+    PickedUp {
+        item: String,
+        count: u32,
+        pickup: Entity,
+    },
+    /// Items were dropped into the world as a new pickup
+    Dropped {
+        item: String,
+        count: u32,
+        pickup: Entity,
+    },
+    /// An item was equipped, shown by the given entity
+    Equipped {
+        slot: String,
+        item: String,
+        entity: Entity,
+    },
+    /// An item was taken out of an equipment slot
+    Unequipped { slot: String, item: String },
+}
+
+/// Component holding the items of an entity in a fixed number of slots.
+///
+/// Items stack up to the `max_stack` of their definition in the
+/// `ItemDatabase`. With a pickup radius the entity collects the `Pickup`s
+/// that come within it, and dropped items are placed in front of it as new
+/// pickups. Changes made by the engine are queued as `InventoryEvent`s.
+///
+/// # Example
+/// ```ignore
+/// compound.spawn((
+///     Transform3D::default(),
+///     Inventory::new(20).with_pickup_radius(1.5),
+/// ));
+///
+/// // In IsotopeState::update
+/// compound.iter_mut_mol(|_entity, inventory: &mut Inventory| {
+///     for event in inventory.drain_events() {
+///         if let InventoryEvent::PickedUp { item, count, .. } = event {
+///             println!("Picked up {} {}", count, item);
+///         }
+///     }
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct Inventory {
+    /// Distance within which pickups are collected, none to never collect them
+    pub pickup_radius: Option<f32>,
+
+    slots: Vec<Option<ItemStack>>,
+    drops: Vec<ItemStack>,
+    events: Vec<InventoryEvent>,
+}
+
+impl Inventory {
+    /// Creates an empty inventory.
+    ///
+    /// # Arguments
+    /// * `capacity` - The number of slots
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            pickup_radius: None,
+            slots: vec![None; capacity],
+            drops: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn with_pickup_radius(mut self, pickup_radius: f32) -> Self {
+        self.pickup_radius = Some(pickup_radius);
+        self
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn slots(&self) -> &[Option<ItemStack>] {
+        &self.slots
+    }
+
+    pub fn slot(&self, index: usize) -> Option<&ItemStack> {
+        self.slots.get(index).and_then(|slot| slot.as_ref())
+    }
+
+    /// Returns how many of an item are in the inventory
+    pub fn count(&self, item: &str) -> u32 {
+        self.slots
+            .iter()
+            .flatten()
+            .filter(|stack| stack.item == item)
+            .map(|stack| stack.count)
+            .sum()
+    }
+
+    /// Adds items, filling up existing stacks before using empty slots.
+    ///
+    /// # Arguments
+    /// * `items` - The definitions of the items
+    /// * `item` - The id of the item to add
+    /// * `count` - How many to add
+    ///
+    /// # Returns
+    /// How many of the items did not fit, all of them if the item is unknown
+    pub fn add(&mut self, items: &ItemDatabase, item: &str, count: u32) -> u32 {
+        let Some(definition) = items.get(item) else {
+            warn!("Unknown item {} not added to inventory", item);
+            return count;
+        };
+
+        let mut remaining = count;
+
+        for stack in self.slots.iter_mut().flatten() {
+            if remaining == 0 {
+                break;
+            }
+
+            if stack.item == item && stack.count < definition.max_stack {
+                let added = remaining.min(definition.max_stack - stack.count);
+                stack.count += added;
+                remaining -= added;
+            }
+        }
+
+        for slot in self.slots.iter_mut() {
+            if remaining == 0 {
+                break;
+            }
+
+            if slot.is_none() {
+                let added = remaining.min(definition.max_stack);
+                *slot = Some(ItemStack::new(item, added));
+                remaining -= added;
+            }
+        }
+
+        remaining
+    }
+
+    /// Removes items, from the last slots first
+    ///
+    /// # Returns
+    /// How many of the items were removed
+    pub fn remove(&mut self, item: &str, count: u32) -> u32 {
+        let mut removed = 0;
+
+        for slot in self.slots.iter_mut().rev() {
+            if removed == count {
+                break;
+            }
+
+            if let Some(stack) = slot
+                && stack.item == item
+            {
+                let taken = stack.count.min(count - removed);
+                stack.count -= taken;
+                removed += taken;
+
+                if stack.count == 0 {
+                    *slot = None;
+                }
+            }
+        }
+
+        removed
+    }
+
+    /// Takes the whole stack out of a slot
+    pub fn take(&mut self, index: usize) -> Option<ItemStack> {
+        self.slots.get_mut(index).and_then(|slot| slot.take())
+    }
+
+    /// Swaps the contents of two slots
+    pub fn swap(&mut self, first: usize, second: usize) {
+        if first < self.slots.len() && second < self.slots.len() {
+            self.slots.swap(first, second);
+        }
+    }
+
+    /// Drops items from a slot into the world in front of the entity, the
+    /// engine spawns the pickup on the next tick.
+    ///
+    /// # Returns
+    /// How many of the items were dropped
+    pub fn drop_items(&mut self, index: usize, count: u32) -> u32 {
+        let Some(slot) = self.slots.get_mut(index) else {
+            return 0;
+        };
+        let Some(stack) = slot else {
+            return 0;
+        };
+
+        let dropped = stack.count.min(count);
+        if dropped == 0 {
+            return 0;
+        }
+
+        stack.count -= dropped;
+        self.drops.push(ItemStack::new(stack.item.clone(), dropped));
+
+        if stack.count == 0 {
+            *slot = None;
+        }
+
+        dropped
+    }
+
+    /// Takes the events queued since the last time they were drained
+    pub fn drain_events(&mut self) -> Vec<InventoryEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+/// Component for items lying in the world, collected by inventories with a
+/// pickup radius.
+///
+/// # Example
+/// ```ignore
+/// compound.spawn((
+///     Transform3D::new(Vector3::new(3.0, 0.0, 5.0), Quaternion::one()),
+///     PendingModel::new("game://models/potion.obj"),
+///     Pickup::new(ItemStack::new("health_potion", 3)),
+/// ));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Pickup {
+    pub stack: ItemStack,
+    /// Seconds until the pickup can be collected
+    pub delay: f32,
+}
+
+impl Pickup {
+    pub fn new(stack: ItemStack) -> Self {
+        Self { stack, delay: 0.0 }
+    }
+
+    pub fn with_delay(mut self, delay: f32) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+/// A place on an entity that an equipped item is attached to, relative to
+/// the transform of the entity.
+#[derive(Debug, Clone)]
+pub struct EquipSocket {
+    pub local_position: Vector3<f32>,
+    pub local_rotation: Quaternion<f32>,
+
+    item: Option<String>,
+    shown: Option<(String, Entity)>,
+    changed: bool,
+}
+
+/// Component with the equipment slots of an entity.
+///
+/// Equipping an item spawns an entity with the model of the item, which the
+/// engine keeps at the socket of the slot as the entity moves. The change is
+/// queued as an `InventoryEvent` on the `Inventory` of the entity, if it has
+/// one.
+///
+/// # Example
+/// ```ignore
+/// let mut equipment = Equipment::new()
+///     .with_socket("right_hand", Vector3::new(0.4, 1.2, 0.3), Quaternion::one());
+///
+/// if inventory.remove("sword", 1) == 1 {
+///     equipment.equip(&items, "right_hand", "sword")?;
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Equipment {
+    sockets: BTreeMap<String, EquipSocket>,
+}
+
+impl Equipment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an equipment slot with the socket its items are attached to
+    pub fn with_socket(
+        mut self,
+        slot: impl Into<String>,
+        local_position: Vector3<f32>,
+        local_rotation: Quaternion<f32>,
+    ) -> Self {
+        self.sockets.insert(
+            slot.into(),
+            EquipSocket {
+                local_position,
+                local_rotation,
+                item: None,
+                shown: None,
+                changed: false,
+            },
+        );
+        self
+    }
+
+    pub fn socket(&self, slot: &str) -> Option<&EquipSocket> {
+        self.sockets.get(slot)
+    }
+
+    /// Moves the socket of a slot
+    pub fn set_socket(
+        &mut self,
+        slot: &str,
+        local_position: Vector3<f32>,
+        local_rotation: Quaternion<f32>,
+    ) -> bool {
+        match self.sockets.get_mut(slot) {
+            Some(socket) => {
+                socket.local_position = local_position;
+                socket.local_rotation = local_rotation;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Equips an item in a slot.
+    ///
+    /// # Arguments
+    /// * `items` - The definitions of the items
+    /// * `slot` - The slot to equip the item in
+    /// * `item` - The id of the item
+    ///
+    /// # Returns
+    /// The item that was equipped in the slot before, or an error if the slot
+    /// does not exist or the item can't be equipped in it
+    pub fn equip(
+        &mut self,
+        items: &ItemDatabase,
+        slot: &str,
+        item: &str,
+    ) -> Result<Option<String>> {
+        let definition = items
+            .get(item)
+            .ok_or_else(|| anyhow!("Unknown item: {}", item))?;
+
+        if let Some(item_slot) = definition.slot.as_deref()
+            && item_slot != slot
+        {
+            return Err(anyhow!(
+                "{} is equipped in {}, not {}",
+                item,
+                item_slot,
+                slot
+            ));
+        }
+
+        let socket = self
+            .sockets
+            .get_mut(slot)
+            .ok_or_else(|| anyhow!("Unknown equipment slot: {}", slot))?;
+
+        socket.changed = true;
+        Ok(socket.item.replace(item.to_string()))
+    }
+
+    /// Takes the item out of a slot
+    ///
+    /// # Returns
+    /// The item that was equipped in the slot
+    pub fn unequip(&mut self, slot: &str) -> Option<String> {
+        let socket = self.sockets.get_mut(slot)?;
+
+        let item = socket.item.take();
+        if item.is_some() {
+            socket.changed = true;
+        }
+
+        item
+    }
+
+    /// Returns the item equipped in a slot
+    pub fn equipped(&self, slot: &str) -> Option<&str> {
+        self.sockets
+            .get(slot)
+            .and_then(|socket| socket.item.as_deref())
+    }
+
+    /// Returns the entity showing the item equipped in a slot
+    pub fn equipped_entity(&self, slot: &str) -> Option<Entity> {
+        self.sockets
+            .get(slot)
+            .and_then(|socket| socket.shown.as_ref().map(|(_, entity)| *entity))
+    }
+}
+
+/// Component of the entity spawned for an equipped item.
+#[derive(Debug, Clone)]
+pub struct Equipped {
+    owner: Entity,
+    slot: String,
+}
+
+impl Equipped {
+    /// Returns the entity the item is equipped on
+    pub fn owner(&self) -> Entity {
+        self.owner
+    }
+
+    pub fn slot(&self) -> &str {
+        &self.slot
+    }
+}
+
+/// Collects pickups, spawns dropped items and keeps equipped items at their
+/// sockets in compounds with an `ItemDatabase`.
+pub(crate) fn update_inventories(compound: &Compound, dt: f32) {
+    compound.resource::<ItemDatabase, _>(|items| {
+        collect_pickups(compound, items, dt);
+        spawn_drops(compound, items);
+        update_equipment(compound, items);
+    });
+}
+
+fn collect_pickups(compound: &Compound, items: &ItemDatabase, dt: f32) {
+    let mut pickups = Vec::new();
+    compound
+        .query::<(&mut Pickup, &Transform3D), Without<Discarded>>()
+        .unmod()
+        .for_each(|entity, (pickup, transform)| {
+            pickup.delay = (pickup.delay - dt).max(0.0);
+            if pickup.delay <= 0.0 && pickup.stack.count > 0 {
+                pickups.push((
+                    entity,
+                    transform.get_position(|position| *position),
+                    pickup.stack.clone(),
+                ));
+            }
+        });
+
+    if pickups.is_empty() {
+        return;
+    }
+
+    // Inventories collect in entity order so the same world always splits
+    // pickups the same way
+    let mut collected: HashMap<Entity, u32> = HashMap::new();
+    compound
+        .query::<(&mut Inventory, &Transform3D), Without<Discarded>>()
+        .unmod()
+        .for_each(|_entity, (inventory, transform)| {
+            let Some(pickup_radius) = inventory.pickup_radius else {
+                return;
+            };
+            let position = transform.get_position(|position| *position);
+
+            for (pickup, pickup_position, stack) in pickups.iter_mut() {
+                if stack.count == 0 || (*pickup_position - position).magnitude() > pickup_radius {
+                    continue;
+                }
+
+                let taken = stack.count - inventory.add(items, &stack.item, stack.count);
+                if taken == 0 {
+                    continue;
+                }
+
+                stack.count -= taken;
+                *collected.entry(*pickup).or_default() += taken;
+                inventory.events.push(InventoryEvent::PickedUp {
+                    item: stack.item.clone(),
+                    count: taken,
+                    pickup: *pickup,
+                });
+            }
+        });
+
+    let mut emptied = Vec::new();
+    compound.iter_mut_without_mol::<Discarded, _, _>(|entity, pickup: &mut Pickup| {
+        if let Some(taken) = collected.get(&entity) {
+            pickup.stack.count = pickup.stack.count.saturating_sub(*taken);
+            if pickup.stack.count == 0 {
+                emptied.push(entity);
+            }
+        }
+    });
+
+    for entity in emptied {
+        compound.add_molecule(entity, Discarded);
+    }
+}
+
+fn spawn_drops(compound: &Compound, items: &ItemDatabase) {
+    let mut drops = Vec::new();
+    compound
+        .query::<(&mut Inventory, &Transform3D), ()>()
+        .unmod()
+        .for_each(|owner, (inventory, transform)| {
+            if inventory.drops.is_empty() {
+                return;
+            }
+
+            let position = transform.get_position_and_rotation(|position, rotation| {
+                position + rotation.rotate_vector(Vector3::unit_z()) * DROP_DISTANCE
+            });
+            for stack in inventory.drops.drain(..) {
+                drops.push((owner, position, stack));
+            }
+        });
+
+    let mut dropped = Vec::new();
+    for (owner, position, stack) in drops {
+        let transform = Transform3D::new(position, Quaternion::new(1.0, 0.0, 0.0, 0.0));
+        let pickup = Pickup::new(stack.clone()).with_delay(PICKUP_DELAY);
+
+        let entity = match items.get(&stack.item).and_then(|item| item.model.as_ref()) {
+            Some(model) => compound.spawn((transform, pickup, PendingModel::new(model))),
+            None => compound.spawn((transform, pickup)),
+        };
+        dropped.push((owner, entity, stack));
+    }
+
+    for (owner, pickup, stack) in dropped {
+        push_event(
+            compound,
+            owner,
+            InventoryEvent::Dropped {
+                item: stack.item,
+                count: stack.count,
+                pickup,
+            },
+        );
+    }
+}
+
+fn update_equipment(compound: &Compound, items: &ItemDatabase) {
+    let mut changes = Vec::new();
+    let mut sockets = HashMap::new();
+
+    compound
+        .query::<(&mut Equipment, &Transform3D), Without<Discarded>>()
+        .unmod()
+        .for_each(|owner, (equipment, transform)| {
+            let (position, rotation) =
+                transform.get_position_and_rotation(|position, rotation| (*position, *rotation));
+
+            for (slot, socket) in equipment.sockets.iter_mut() {
+                if socket.changed {
+                    socket.changed = false;
+                    changes.push((
+                        owner,
+                        slot.clone(),
+                        socket.shown.take(),
+                        socket.item.clone(),
+                    ));
+                }
+
+                sockets.insert(
+                    (owner, slot.clone()),
+                    (
+                        position + rotation.rotate_vector(socket.local_position),
+                        rotation * socket.local_rotation,
+                    ),
+                );
+            }
+        });
+
+    for (owner, slot, previous, item) in changes {
+        if let Some((previous, entity)) = previous {
+            compound.add_molecule(entity, Discarded);
+            push_event(
+                compound,
+                owner,
+                InventoryEvent::Unequipped {
+                    slot: slot.clone(),
+                    item: previous,
+                },
+            );
+        }
+
+        let Some(item) = item else {
+            continue;
+        };
+
+        let (position, rotation) = sockets
+            .get(&(owner, slot.clone()))
+            .copied()
+            .unwrap_or((Vector3::zero(), Quaternion::new(1.0, 0.0, 0.0, 0.0)));
+        let equipped = Equipped {
+            owner,
+            slot: slot.clone(),
+        };
+        let transform = Transform3D::new(position, rotation);
+
+        let entity = match items.get(&item).and_then(|item| item.model.as_ref()) {
+            Some(model) => compound.spawn((transform, equipped, PendingModel::new(model))),
+            None => compound.spawn((transform, equipped)),
+        };
+
+        compound.iter_mut_mol_unmod(|entity_owner, equipment: &mut Equipment| {
+            if entity_owner == owner
+                && let Some(socket) = equipment.sockets.get_mut(&slot)
+            {
+                socket.shown = Some((item.clone(), entity));
+            }
+        });
+
+        push_event(
+            compound,
+            owner,
+            InventoryEvent::Equipped { slot, item, entity },
+        );
+    }
+
+    compound
+        .query::<(&Equipped, &mut Transform3D), Without<Discarded>>()
+        .for_each(|_entity, (equipped, transform)| {
+            if let Some((position, rotation)) =
+                sockets.get(&(equipped.owner, equipped.slot.clone()))
+            {
+                transform.position_and_rotation(|transform_position, transform_rotation| {
+                    *transform_position = *position;
+                    *transform_rotation = *rotation;
+                });
+            }
+        });
+}
+
+/// Queues an event on the inventory of an entity, if it has one
+fn push_event(compound: &Compound, owner: Entity, event: InventoryEvent) {
+    let mut event = Some(event);
+    compound.iter_mut_mol_unmod(|entity, inventory: &mut Inventory| {
+        if entity == owner
+            && let Some(event) = event.take()
+        {
+            inventory.events.push(event);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::RwLock;
+
+    use boson::Boson;
+
+    use super::*;
+    use crate::physics::despawn_discarded;
+
+    fn items() -> ItemDatabase {
+        let mut items = ItemDatabase::new();
+        items.insert(ItemDefinition::new("sword"));
+        items.insert(ItemDefinition::new("axe"));
+        items
+    }
+
+    #[test]
+    fn test_dropping_nothing_queues_no_pickup() {
+        let mut inventory = Inventory::new(2);
+        inventory.add(&items(), "sword", 1);
+
+        assert_eq!(inventory.drop_items(0, 0), 0);
+        assert_eq!(inventory.drop_items(1, 1), 0);
+        assert!(inventory.drops.is_empty());
+
+        assert_eq!(inventory.drop_items(0, 3), 1);
+        assert_eq!(inventory.drops.len(), 1);
+        assert!(inventory.slot(0).is_none());
+    }
+
+    #[test]
+    fn test_swapping_equipment_despawns_shown_items() {
+        let compound = Compound::new();
+        let boson = RwLock::new(Boson::new());
+        compound.insert_resource(items());
+
+        let owner = compound.spawn((
+            Transform3D::default(),
+            Equipment::new().with_socket(
+                "hand",
+                Vector3::zero(),
+                Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            ),
+        ));
+
+        for item in ["sword", "axe"].into_iter().cycle().take(8) {
+            compound.iter_mut_mol(|entity, equipment: &mut Equipment| {
+                if entity == owner {
+                    compound
+                        .resource(|items: &ItemDatabase| equipment.equip(items, "hand", item))
+                        .unwrap()
+                        .unwrap();
+                }
+            });
+
+            update_inventories(&compound, 0.1);
+            despawn_discarded(&compound, &boson);
+
+            assert_eq!(compound.query::<(&Equipped,), ()>().count(), 1);
+        }
+    }
+}
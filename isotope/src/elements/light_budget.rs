@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+
+use cgmath::{InnerSpace, Vector3};
+use compound::{Compound, Entity, Without};
+
+use crate::{Camera3DDescriptor, Discarded, Light, RenderLayers, Transform3D};
+
+// How much wider than the vertical field of view lights still count as on
+// screen, to cover the wider horizontal view and lights just off the edge
+const VIEW_CONE_MARGIN: f32 = 1.5;
+
+// Lights off screen still light what is on it, but their shadows mostly fall
+// out of view
+const OFF_SCREEN_INFLUENCE: f32 = 0.25;
+
+/// How many lights cast shadows at once and how quickly shadows fade as
+/// lights go in and out of the budget.
+///
+/// Every frame the lights are ranked by how much they light what the cameras
+/// see, scaled by their `LightPriority`, and only the first
+/// `max_shadowed_lights` cast shadows. Insert as a resource of the compound
+/// to change the budget, the defaults are used otherwise.
+///
+/// # Example
+/// ```ignore
+/// // A scene full of torches only shadows the two that matter most
+/// compound.insert_resource(LightBudget {
+///     max_shadowed_lights: 2,
+///     ..Default::default()
+/// });
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightBudget {
+    /// The most lights casting shadows at once, 0 turns shadows off
+    pub max_shadowed_lights: usize,
+    /// Seconds a shadow takes to fade in or out, 0 swaps them instantly
+    pub fade_time: f32,
+}
+
+impl Default for LightBudget {
+    fn default() -> Self {
+        Self {
+            max_shadowed_lights: 4,
+            fade_time: 0.5,
+        }
+    }
+}
+
+/// Scales how much a light wants to cast a shadow when the light budget ranks
+/// it, for lights the player should always see shadows from. Lights without
+/// one have a priority of 1, and lights with a priority of 0 never cast one.
+///
+/// # Example
+/// ```ignore
+/// // The flashlight of the player outranks any lamp in the level
+/// compound.spawn((
+///     Light::new([0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [1.0, 1.0, 1.0], 2.0),
+///     LightPriority(10.0),
+/// ));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightPriority(pub f32);
+
+impl Default for LightPriority {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+// The shadow of every light in the last frame, for the lights still fading
+pub(crate) struct LightShadowFades(HashMap<Entity, f32>);
+
+// Where a camera is, where it looks and how wide it sees
+struct BudgetView {
+    position: Vector3<f32>,
+    forward: Vector3<f32>,
+    cos_half_angle: f32,
+}
+
+impl BudgetView {
+    // How strongly a light lights what this camera sees, falling off with
+    // the distance to the camera
+    fn influence(&self, light: &Light) -> f32 {
+        let offset = Vector3::from(light.position) - self.position;
+        let distance_squared = offset.magnitude2();
+        let brightness = light.intensity * light.color.iter().copied().fold(0.0, f32::max);
+
+        let on_screen = distance_squared <= f32::EPSILON
+            || self.forward.dot(offset / distance_squared.sqrt()) >= self.cos_half_angle;
+
+        let influence = brightness / (1.0 + distance_squared);
+        if on_screen {
+            influence
+        } else {
+            influence * OFF_SCREEN_INFLUENCE
+        }
+    }
+}
+
+/// Ranks the lights by their influence on the cameras and their priority,
+/// then fades the shadows of the lights in the budget in and the rest out.
+///
+/// # Arguments
+/// * `compound` - The world the lights belong to
+/// * `lights` - The cached lights of the world, their shadows are written in place
+/// * `dt` - Seconds since the last frame
+///
+/// # Returns
+/// Whether the shadow of any light changed, so the lights need to be uploaded again
+pub(crate) fn update_light_budget(
+    compound: &Compound,
+    lights: &mut [(Entity, Light, RenderLayers)],
+    dt: f32,
+) -> bool {
+    let budget = compound
+        .resource::<LightBudget, _>(|budget| *budget)
+        .unwrap_or_default();
+
+    let mut views = Vec::new();
+    compound
+        .query::<(&Camera3DDescriptor, &Transform3D), Without<Discarded>>()
+        .for_each(|_entity, (descriptor, transform)| {
+            let (position, rotation) =
+                transform.get_position_and_rotation(|position, rotation| (*position, *rotation));
+            let half_angle =
+                (descriptor.fovy.to_radians() * 0.5 * VIEW_CONE_MARGIN).min(std::f32::consts::PI);
+
+            views.push(BudgetView {
+                position,
+                forward: (rotation * Vector3::unit_z()).normalize(),
+                cos_half_angle: half_angle.cos(),
+            });
+        });
+
+    let mut priorities = HashMap::new();
+    compound.iter_mol(|entity, priority: &LightPriority| {
+        priorities.insert(entity, priority.0.max(0.0));
+    });
+
+    // Highest influence first, lights nobody sees or with no priority never make it in
+    let mut ranked = lights
+        .iter()
+        .enumerate()
+        .map(|(index, (entity, light, _))| {
+            let priority = priorities.get(entity).copied().unwrap_or(1.0);
+            let influence = views
+                .iter()
+                .map(|view| view.influence(light))
+                .fold(0.0, f32::max);
+
+            (index, influence * priority)
+        })
+        .filter(|(_, score)| *score > 0.0)
+        .collect::<Vec<_>>();
+    ranked.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+    let mut in_budget = vec![false; lights.len()];
+    for (index, _) in ranked.iter().take(budget.max_shadowed_lights) {
+        in_budget[*index] = true;
+    }
+
+    let previous = compound
+        .remove_resource::<LightShadowFades>()
+        .map(|fades| fades.0)
+        .unwrap_or_default();
+    let fade_step = if budget.fade_time > 0.0 {
+        dt / budget.fade_time
+    } else {
+        1.0
+    };
+
+    let mut fades = HashMap::new();
+    let mut changed = false;
+    for ((entity, light, _), in_budget) in lights.iter_mut().zip(in_budget) {
+        let current = previous.get(entity).copied().unwrap_or(0.0);
+        let shadow = if in_budget {
+            (current + fade_step).min(1.0)
+        } else {
+            (current - fade_step).max(0.0)
+        };
+
+        if shadow > 0.0 {
+            fades.insert(*entity, shadow);
+        }
+
+        if light.shadow != shadow {
+            light.shadow = shadow;
+            changed = true;
+        }
+    }
+
+    compound.insert_resource(LightShadowFades(fades));
+
+    changed
+}
@@ -0,0 +1,229 @@
+use anyhow::{Result, anyhow};
+use compound::Compound;
+use photon::{
+    Light,
+    photon_lighting::{IrradianceProbe, ProbeGridInfo},
+    renderer::Renderer,
+};
+
+// Probes refreshed every frame if no budget is given
+const DEFAULT_PROBES_PER_FRAME: usize = 64;
+
+/// A grid of irradiance probes that lights surfaces with the bounce light around them.
+///
+/// Surfaces inside the grid blend the eight probes around them instead of
+/// using a constant ambient term, so dynamic objects moving through the
+/// world pick up the light of the places they pass. Outside the grid the
+/// nearest probes on its edge are used.
+///
+/// Probes are estimated from the lights of the world and refreshed a few at a
+/// time whenever the lights change, or baked offline and given with `bake`.
+/// The first grid of the active world is used.
+///
+/// # Example
+/// ```ignore
+/// // A 20x5x20 grid of probes two units apart covering the level
+/// compound.spawn((LightProbeGrid::new([-20.0, 0.0, -20.0], [2.0; 3], [20, 5, 20]),));
+/// ```
+#[derive(Debug, Clone)]
+pub struct LightProbeGrid {
+    origin: [f32; 3],
+    spacing: [f32; 3],
+    counts: [u32; 3],
+    /// Share of the light reflected back by the surroundings
+    pub bounce: f32,
+    /// Irradiance arriving at every probe regardless of the lights
+    pub sky_color: [f32; 3],
+    /// Number of probes refreshed each frame while the lights change
+    pub probes_per_frame: usize,
+
+    probes: Vec<IrradianceProbe>,
+    baked: bool,
+    // Probe the next refresh starts at and how many are still stale
+    next_probe: usize,
+    stale: usize,
+    // The probes given to the renderer need replacing entirely
+    needs_upload: bool,
+}
+
+impl LightProbeGrid {
+    /// Creates a grid whose probes are estimated from the lights of the world
+    ///
+    /// # Arguments
+    /// * `origin` - The position of the first probe
+    /// * `spacing` - The distance between neighbouring probes along each axis
+    /// * `counts` - The number of probes along each axis, at least one
+    pub fn new(origin: [f32; 3], spacing: [f32; 3], counts: [u32; 3]) -> Self {
+        let counts = counts.map(|count| count.max(1));
+        let sky_color = [0.01; 3];
+        let num_probes = counts.iter().map(|count| *count as usize).product();
+
+        Self {
+            origin,
+            spacing: spacing.map(|spacing| spacing.max(f32::EPSILON)),
+            counts,
+            bounce: 0.3,
+            sky_color,
+            probes_per_frame: DEFAULT_PROBES_PER_FRAME,
+            probes: vec![IrradianceProbe::uniform(sky_color); num_probes],
+            baked: false,
+            next_probe: 0,
+            stale: num_probes,
+            needs_upload: true,
+        }
+    }
+
+    /// Replaces every probe with baked ones, they are no longer estimated from the lights
+    ///
+    /// # Arguments
+    /// * `probes` - Every probe of the grid, ordered along X, then Y, then Z
+    ///
+    /// # Returns
+    /// An error if the number of probes doesn't match the grid
+    pub fn bake(&mut self, probes: Vec<IrradianceProbe>) -> Result<()> {
+        if probes.len() != self.probes.len() {
+            return Err(anyhow!(
+                "Probe grid has {} probes but {} were baked",
+                self.probes.len(),
+                probes.len()
+            ));
+        }
+
+        self.probes = probes;
+        self.baked = true;
+        self.stale = 0;
+        self.needs_upload = true;
+
+        Ok(())
+    }
+
+    /// Goes back to estimating the probes from the lights, refreshing all of them.
+    ///
+    /// Call after changing `bounce` or `sky_color` for the change to show.
+    pub fn refresh(&mut self) {
+        self.baked = false;
+        self.stale = self.probes.len();
+    }
+
+    pub fn probes(&self) -> &[IrradianceProbe] {
+        &self.probes
+    }
+
+    /// Returns the position of a probe in world space
+    pub fn probe_position(&self, index: usize) -> [f32; 3] {
+        let [count_x, count_y, _] = self.counts.map(|count| count as usize);
+        let grid_position = [
+            index % count_x,
+            index / count_x % count_y,
+            index / (count_x * count_y),
+        ];
+
+        [0, 1, 2].map(|axis| self.origin[axis] + grid_position[axis] as f32 * self.spacing[axis])
+    }
+
+    /// Returns the irradiance reaching a surface at a point, as the lighting pass samples it
+    ///
+    /// # Arguments
+    /// * `position` - The position in world space
+    /// * `normal` - The direction the surface faces
+    pub fn irradiance(&self, position: [f32; 3], normal: [f32; 3]) -> [f32; 3] {
+        let cell = [0, 1, 2].map(|axis| {
+            ((position[axis] - self.origin[axis]) / self.spacing[axis])
+                .clamp(0.0, (self.counts[axis] - 1) as f32)
+        });
+        let low = cell.map(|cell| cell.floor() as u32);
+        let high = [0, 1, 2].map(|axis| (low[axis] + 1).min(self.counts[axis] - 1));
+        let t = [0, 1, 2].map(|axis| cell[axis] - cell[axis].floor());
+
+        let mut color = [0.0; 3];
+        for corner in 0..8 {
+            let upper = [corner & 1 != 0, corner & 2 != 0, corner & 4 != 0];
+            let grid_position =
+                [0, 1, 2].map(|axis| if upper[axis] { high[axis] } else { low[axis] });
+            let weight = (0..3)
+                .map(|axis| if upper[axis] { t[axis] } else { 1.0 - t[axis] })
+                .product::<f32>();
+
+            let index = grid_position[0]
+                + grid_position[1] * self.counts[0]
+                + grid_position[2] * self.counts[0] * self.counts[1];
+            let irradiance = self.probes[index as usize].irradiance(normal);
+
+            for (value, probe_value) in color.iter_mut().zip(irradiance.iter()) {
+                *value += probe_value * weight;
+            }
+        }
+
+        color
+    }
+
+    fn info(&self) -> ProbeGridInfo {
+        ProbeGridInfo::new(self.origin, self.spacing, self.counts)
+    }
+
+    /// Refreshes the next stale probes from the lights
+    ///
+    /// # Returns
+    /// The index of the first refreshed probe and the number refreshed
+    fn refresh_stale(&mut self, lights: &[Light]) -> (usize, usize) {
+        let start = self.next_probe;
+        let end = (start + self.probes_per_frame.max(1).min(self.stale)).min(self.probes.len());
+
+        for index in start..end {
+            self.probes[index] = IrradianceProbe::from_lights(
+                self.probe_position(index),
+                lights,
+                self.bounce,
+                self.sky_color,
+            );
+        }
+
+        self.next_probe = if end == self.probes.len() { 0 } else { end };
+        self.stale -= end - start;
+
+        (start, end - start)
+    }
+}
+
+/// Refreshes the probes of the first `LightProbeGrid` and gives them to the renderer
+///
+/// # Arguments
+/// * `compound` - The compound of the active world
+/// * `lights` - Every light of the world
+/// * `lights_changed` - Whether the lights changed since the last frame
+/// * `world_changed` - Whether a different world was rendered last frame
+/// * `photon` - The renderer to give the probes to
+pub(crate) fn update_light_probes(
+    compound: &Compound,
+    lights: &[Light],
+    lights_changed: bool,
+    world_changed: bool,
+    photon: &mut Renderer,
+) {
+    let mut found = false;
+
+    compound.iter_mut_mol(|_entity, grid: &mut LightProbeGrid| {
+        if found {
+            return;
+        }
+        found = true;
+
+        if lights_changed && !grid.baked {
+            grid.stale = grid.probes.len();
+        }
+
+        if grid.needs_upload || world_changed {
+            photon.set_light_probes(grid.info(), &grid.probes);
+            grid.needs_upload = false;
+        }
+
+        if grid.stale > 0 {
+            let (first, count) = grid.refresh_stale(lights);
+            photon.update_light_probes(first, &grid.probes[first..first + count]);
+        }
+    });
+
+    if !found {
+        photon.clear_light_probes();
+    }
+}
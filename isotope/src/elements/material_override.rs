@@ -0,0 +1,99 @@
+use std::path::PathBuf;
+
+use compound::Compound;
+use log::warn;
+
+use crate::{AssetServer, Model};
+
+/// Overrides parameters of a model's materials for a single entity.
+///
+/// The overrides are stored in per object data next to the model's transform,
+/// so changing them does not clone the shared material or its pipeline. The
+/// engine uploads the overrides whenever any of the fields change, and
+/// restores the model's own materials when the override is removed.
+///
+/// # Example
+/// ```ignore
+/// // Flash an enemy red when it is hit
+/// ecs.iter_mut_duo(|_entity, enemy: &mut Enemy, material_override: &mut MaterialOverride| {
+///     if enemy.hit {
+///         material_override.tint = [1.0, 0.2, 0.2, 1.0];
+///         material_override.emissive_strength = 0.5;
+///     } else {
+///         *material_override = MaterialOverride::default();
+///     }
+/// });
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaterialOverride {
+    /// Color multiplied with the albedo of every material on the model
+    pub tint: [f32; 4],
+    /// Color the model emits regardless of the lights in the scene
+    pub emissive_color: [f32; 3],
+    /// Multiplier for the emissive color, zero disables emission
+    pub emissive_strength: f32,
+    /// Path to a texture that replaces the diffuse texture of the materials
+    pub texture: Option<PathBuf>,
+}
+
+impl Default for MaterialOverride {
+    fn default() -> Self {
+        Self {
+            tint: [1.0, 1.0, 1.0, 1.0],
+            emissive_color: [1.0, 1.0, 1.0],
+            emissive_strength: 0.0,
+            texture: None,
+        }
+    }
+}
+
+// Bookkeeping for the override that was last uploaded for an entity's model
+struct AppliedMaterialOverride(Option<MaterialOverride>);
+
+/// Uploads the `MaterialOverride` of every model whose override differs from
+/// the one last applied, and resets the models whose override was removed.
+pub(crate) fn apply_material_overrides(compound: &Compound, asset_server: &AssetServer) {
+    // The default override leaves the materials of the model as they are
+    let mut removed_overrides = Vec::new();
+    compound.iter_mut_without_duo_unmod::<MaterialOverride, _, _, _>(
+        |entity, _applied: &mut AppliedMaterialOverride, model: &mut Model| {
+            if let Err(err) =
+                model.set_material_override(&MaterialOverride::default(), asset_server)
+            {
+                warn!("Failed to remove material override: {}", err);
+            }
+
+            removed_overrides.push(entity);
+        },
+    );
+
+    for entity in removed_overrides {
+        compound.remove_molecule::<AppliedMaterialOverride>(entity);
+    }
+
+    let mut new_overrides = Vec::new();
+    compound.iter_without_duo::<AppliedMaterialOverride, _, _, _>(
+        |entity, _material_override: &MaterialOverride, _model: &Model| {
+            new_overrides.push(entity);
+        },
+    );
+
+    for entity in new_overrides {
+        compound.add_molecule(entity, AppliedMaterialOverride(None));
+    }
+
+    compound.iter_mut_trio_unmod(
+        |_entity,
+         material_override: &mut MaterialOverride,
+         applied: &mut AppliedMaterialOverride,
+         model: &mut Model| {
+            if applied.0.as_ref() != Some(material_override) {
+                if let Err(err) = model.set_material_override(material_override, asset_server) {
+                    warn!("Failed to apply material override: {}", err);
+                }
+
+                applied.0 = Some(material_override.clone());
+            }
+        },
+    );
+}
@@ -1,9 +1,65 @@
+pub use animator::*;
 pub use camera::*;
+pub use canvas::*;
+pub use crowd::*;
+pub use distortion::*;
+pub use fog::*;
+pub use fracture::*;
+pub use gizmos::*;
 pub use instancer::*;
+pub use interaction::*;
+pub use inventory::*;
+pub use light_budget::*;
+pub use light_probes::*;
+pub use material_override::*;
+pub use particles::*;
+pub use pathfinding::*;
+pub use pending::*;
+pub use perception::*;
+pub use photo_mode::*;
+pub use render_layers::*;
+pub use silhouette::*;
+pub use socket::*;
+pub use spatial_hash::*;
+pub use spline_mesh::*;
+pub use surface::*;
+pub use time_dilation::*;
+pub use trail::*;
 pub use transform::*;
+pub use trigger::*;
+pub use vfx::*;
+pub use voxels::*;
 pub use window_controller::*;
 
+mod animator;
 mod camera;
+mod canvas;
+mod crowd;
+mod distortion;
+mod fog;
+mod fracture;
+mod gizmos;
 mod instancer;
+mod interaction;
+mod inventory;
+mod light_budget;
+mod light_probes;
+mod material_override;
+mod particles;
+mod pathfinding;
+mod pending;
+mod perception;
+mod photo_mode;
+mod render_layers;
+mod silhouette;
+mod socket;
+mod spatial_hash;
+mod spline_mesh;
+mod surface;
+mod time_dilation;
+mod trail;
 mod transform;
+mod trigger;
+mod vfx;
+mod voxels;
 mod window_controller;
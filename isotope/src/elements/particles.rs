@@ -0,0 +1,219 @@
+use std::collections::{HashMap, HashSet};
+
+use boson::{BosonBody, BosonObject, Collider};
+use cgmath::{InnerSpace, Quaternion, Rotation, Vector3};
+use compound::Compound;
+use photon::renderer::{
+    Renderer,
+    particles::{ParticleCollider, ParticleEmitterSettings, ParticleMeshId},
+};
+
+use crate::{AssetServer, Time, model::read_obj_geometry};
+
+use super::{Transform3D, VisualEffect, draw_orders, time_scales};
+
+/// Spawns particles at the entity's transform that are simulated and drawn on the GPU.
+///
+/// Particles collide with the world as the `collision` of the settings says,
+/// `ParticleCollision::Depth` is cheap but only knows about what the camera
+/// sees, `ParticleCollision::Colliders` collides with the sphere and plane
+/// colliders of every rigid body wherever they are.
+///
+/// Particles are camera facing quads, or instances of the mesh of an OBJ
+/// file when the emitter has one, turned along their velocity.
+///
+/// # Example
+/// ```ignore
+/// // Sparks that bounce off of whatever is on screen
+/// compound.spawn((
+///     Transform3D::new([0.0, 2.0, 0.0], [0.0, 0.0, 0.0, 1.0]),
+///     ParticleEmitter {
+///         settings: ParticleEmitterSettings {
+///             rate: 200.0,
+///             speed: (4.0, 6.0),
+///             collision: ParticleCollision::Depth {
+///                 restitution: 0.4,
+///                 friction: 0.2,
+///                 thickness: 0.5,
+///                 kill: false,
+///             },
+///             ..Default::default()
+///         },
+///         ..Default::default()
+///     },
+/// ));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParticleEmitter {
+    pub settings: ParticleEmitterSettings,
+    /// The direction particles are spawned towards, rotated with the transform
+    pub direction: [f32; 3],
+    /// Whether new particles are spawned, living ones die out either way
+    pub emitting: bool,
+    /// Path of the OBJ file every particle is drawn as, the mesh of the
+    /// settings is replaced with it
+    pub mesh: Option<String>,
+}
+
+impl Default for ParticleEmitter {
+    fn default() -> Self {
+        Self {
+            settings: ParticleEmitterSettings::default(),
+            direction: [0.0, 1.0, 0.0],
+            emitting: true,
+            mesh: None,
+        }
+    }
+}
+
+/// Meshes particles are drawn as, uploaded to the renderer the first time an
+/// emitter uses them
+#[derive(Default)]
+pub(crate) struct ParticleMeshes {
+    meshes: HashMap<String, Option<ParticleMeshId>>,
+}
+
+impl ParticleMeshes {
+    /// Returns the mesh of the OBJ file, loading it if no emitter used it before
+    ///
+    /// # Returns
+    /// The mesh, or `None` if the file could not be loaded and the particles are drawn as quads
+    fn get(
+        &mut self,
+        path: &str,
+        asset_server: &AssetServer,
+        photon: &mut Renderer,
+    ) -> Option<ParticleMeshId> {
+        *self.meshes.entry(path.to_string()).or_insert_with(|| {
+            match read_obj_geometry(path, asset_server) {
+                Ok((vertices, indices)) => Some(photon.add_particle_mesh(&vertices, &indices)),
+                Err(err) => {
+                    asset_server.report_fallback(path, &err);
+                    None
+                }
+            }
+        })
+    }
+
+    /// Forgets every mesh, for when the renderer they were uploaded to is replaced
+    pub(crate) fn clear(&mut self) {
+        self.meshes.clear();
+    }
+}
+
+/// Gives the emitters and visual effects of the world and the shapes their
+/// particles collide with to the renderer
+///
+/// Particles are simulated with real time, so emitters are slowed down by
+/// the `Time` of the world and the `TimeScale` of their entity here.
+pub(crate) fn update_particles(
+    compound: &Compound,
+    photon: &mut Renderer,
+    asset_server: &AssetServer,
+    meshes: &mut ParticleMeshes,
+) {
+    let mut emitters = HashSet::new();
+
+    let world_time_scale = compound
+        .resource::<Time, _>(|time| time.scale.max(0.0))
+        .unwrap_or(1.0);
+    let time_scales = time_scales(compound);
+    let draw_orders = draw_orders(compound);
+
+    let mut update_emitter = |entity,
+                              index,
+                              emitter: &ParticleEmitter,
+                              origin: Vector3<f32>,
+                              rotation: &Quaternion<f32>,
+                              emitting| {
+        let mut settings = emitter.settings;
+        settings.time_scale *= world_time_scale * time_scales.get(&entity).copied().unwrap_or(1.0);
+        if let Some(mesh) = &emitter.mesh {
+            settings.mesh = meshes.get(mesh, asset_server, photon);
+        }
+
+        let direction = rotation.rotate_vector(Vector3::from(emitter.direction));
+        photon.update_particle_emitter(
+            entity,
+            index,
+            &settings,
+            origin.into(),
+            direction.into(),
+            emitting,
+        );
+        photon.set_particle_emitter_order(
+            entity,
+            index,
+            draw_orders.get(&entity).copied().unwrap_or_default(),
+        );
+
+        emitters.insert((entity, index));
+    };
+
+    compound.iter_duo(
+        |entity, emitter: &ParticleEmitter, transform: &Transform3D| {
+            transform.get_position_and_rotation(|position, rotation| {
+                update_emitter(entity, 0, emitter, *position, rotation, emitter.emitting);
+            });
+        },
+    );
+
+    // The emitters of an effect come after the one of a `ParticleEmitter` on the same entity
+    compound.iter_duo(|entity, effect: &VisualEffect, transform: &Transform3D| {
+        transform.get_position_and_rotation(|position, rotation| {
+            for (index, vfx_emitter) in effect.effect.emitters.iter().enumerate() {
+                let origin = position + rotation.rotate_vector(Vector3::from(vfx_emitter.offset));
+
+                update_emitter(
+                    entity,
+                    index as u32 + 1,
+                    &vfx_emitter.emitter,
+                    origin,
+                    rotation,
+                    effect.emitting,
+                );
+            }
+        });
+    });
+
+    photon.retain_particle_emitters(|id, index| emitters.contains(&(id, index)));
+
+    if emitters.is_empty() {
+        return;
+    }
+
+    let mut colliders = Vec::new();
+    compound.iter_mol(|_entity, object: &BosonObject| {
+        let (_, current, _) = object.read_state();
+
+        object.read_body(|body| {
+            let BosonBody::RigidBody(rigid_body) = body else {
+                return;
+            };
+
+            let center = current.position
+                + current
+                    .orientation
+                    .rotate_vector(rigid_body.center_of_mass());
+
+            match rigid_body.collider() {
+                Collider::Sphere { radius } => colliders.push(ParticleCollider::Sphere {
+                    center: center.cast().unwrap_or(Vector3::new(0.0, 0.0, 0.0)).into(),
+                    radius: *radius as f32,
+                }),
+                Collider::Plane { normal, distance } => {
+                    let normal = current.orientation.rotate_vector(normal.normalize());
+
+                    colliders.push(ParticleCollider::Plane {
+                        normal: normal.cast().unwrap_or(Vector3::unit_y()).into(),
+                        distance: (normal.dot(center) + distance) as f32,
+                    });
+                }
+                // Boxes and hulls would need more than a handful of planes each
+                _ => {}
+            }
+        });
+    });
+
+    photon.set_particle_colliders(&colliders);
+}
@@ -0,0 +1,734 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, VecDeque},
+};
+
+use boson::Boson;
+use cgmath::{InnerSpace, Vector2, Vector3, Zero};
+use compound::Compound;
+use photon::GizmoVertex;
+
+use super::perception::to_f64;
+
+/// Cost of a cell that can't be walked through
+pub const BLOCKED: f32 = f32::INFINITY;
+
+// Neighbor offsets, the straight ones first
+const NEIGHBORS: [(isize, isize); 8] = [
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
+
+/// A grid of walking costs on the XZ plane for pathfinding in top down games.
+///
+/// Every cell has a cost of at least 1 that multiplies the distance walked
+/// through it, so rough terrain can be made more expensive than roads, and
+/// `BLOCKED` cells can't be entered at all. Paths move in 8 directions and
+/// never cut the corner of a blocked cell.
+///
+/// # Example
+/// ```ignore
+/// let mut grid = PathGrid::from_tiles(&tiles, 64, 1.0, Vector3::zero(), |tile| match tile {
+///     Tile::Wall => BLOCKED,
+///     Tile::Mud => 3.0,
+///     _ => 1.0,
+/// });
+/// grid.block_colliders(&boson, 0.1, 2.0);
+///
+/// if let Some(path) = grid.find_path(start, goal) {
+///     agent.set_path(path);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PathGrid {
+    width: usize,
+    height: usize,
+    cell_size: f32,
+    origin: Vector3<f32>,
+    costs: Vec<f32>,
+}
+
+impl PathGrid {
+    /// Creates a grid where every cell has a cost of 1.
+    ///
+    /// # Arguments
+    /// * `width` - Number of cells along X
+    /// * `height` - Number of cells along Z
+    /// * `cell_size` - Edge length of a cell in world units
+    /// * `origin` - World position of the corner of the first cell
+    pub fn new(width: usize, height: usize, cell_size: f32, origin: Vector3<f32>) -> Self {
+        Self {
+            width,
+            height,
+            cell_size,
+            origin,
+            costs: vec![1.0; width * height],
+        }
+    }
+
+    /// Creates a grid from the tiles of a tilemap, stored row by row.
+    ///
+    /// # Arguments
+    /// * `tiles` - The tiles, `width` of them per row of Z
+    /// * `width` - Number of tiles along X
+    /// * `cell_size` - Edge length of a tile in world units
+    /// * `origin` - World position of the corner of the first tile
+    /// * `cost` - The cost of walking through a tile, or `BLOCKED`
+    pub fn from_tiles<T, F>(
+        tiles: &[T],
+        width: usize,
+        cell_size: f32,
+        origin: Vector3<f32>,
+        cost: F,
+    ) -> Self
+    where
+        F: Fn(&T) -> f32,
+    {
+        let height = tiles.len().checked_div(width).unwrap_or(0);
+
+        Self {
+            width,
+            height,
+            cell_size,
+            origin,
+            costs: tiles[..width * height]
+                .iter()
+                .map(|tile| cost(tile).max(1.0))
+                .collect(),
+        }
+    }
+
+    /// Blocks every cell whose center has a physics collider in the height
+    /// range above the origin of the grid.
+    ///
+    /// # Arguments
+    /// * `boson` - The physics of the world
+    /// * `min_height` - Bottom of the range, above the ground so it isn't counted
+    /// * `max_height` - Top of the range, colliders above it can be walked under
+    pub fn block_colliders(&mut self, boson: &Boson, min_height: f32, max_height: f32) {
+        for z in 0..self.height {
+            for x in 0..self.width {
+                let center = self.cell_center(x, z);
+                let bottom = center + Vector3::unit_y() * min_height;
+                let top = center + Vector3::unit_y() * max_height;
+
+                if boson.is_occluded(to_f64(bottom), to_f64(top), &[]) {
+                    self.set_cost(x, z, BLOCKED);
+                }
+            }
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// Returns the cost of a cell, `BLOCKED` outside of the grid
+    pub fn cost(&self, x: usize, z: usize) -> f32 {
+        if x < self.width && z < self.height {
+            self.costs[z * self.width + x]
+        } else {
+            BLOCKED
+        }
+    }
+
+    /// Sets the cost of a cell, costs below 1 are raised to 1
+    pub fn set_cost(&mut self, x: usize, z: usize, cost: f32) {
+        if x < self.width && z < self.height {
+            self.costs[z * self.width + x] = cost.max(1.0);
+        }
+    }
+
+    pub fn is_blocked(&self, x: usize, z: usize) -> bool {
+        self.cost(x, z) == BLOCKED
+    }
+
+    /// Returns the cell a world position is in
+    pub fn world_to_cell(&self, position: Vector3<f32>) -> Option<(usize, usize)> {
+        let x = ((position.x - self.origin.x) / self.cell_size).floor();
+        let z = ((position.z - self.origin.z) / self.cell_size).floor();
+
+        (x >= 0.0 && z >= 0.0 && (x as usize) < self.width && (z as usize) < self.height)
+            .then_some((x as usize, z as usize))
+    }
+
+    /// Returns the world position of the center of a cell
+    pub fn cell_center(&self, x: usize, z: usize) -> Vector3<f32> {
+        self.origin
+            + Vector3::new(
+                (x as f32 + 0.5) * self.cell_size,
+                0.0,
+                (z as f32 + 0.5) * self.cell_size,
+            )
+    }
+
+    /// Finds the cheapest path between two points with A*.
+    ///
+    /// # Arguments
+    /// * `start` - Where the path starts in world space
+    /// * `goal` - Where the path ends in world space
+    ///
+    /// # Returns
+    /// The centers of the cells along the path ending at `goal`, or `None` if
+    /// either point is off the grid or blocked or the goal can't be reached
+    pub fn find_path(&self, start: Vector3<f32>, goal: Vector3<f32>) -> Option<Vec<Vector3<f32>>> {
+        let start_cell = self.world_to_cell(start)?;
+        let goal_cell = self.world_to_cell(goal)?;
+        if self.is_blocked(start_cell.0, start_cell.1) || self.is_blocked(goal_cell.0, goal_cell.1)
+        {
+            return None;
+        }
+
+        let start_index = self.index(start_cell);
+        let goal_index = self.index(goal_cell);
+
+        let mut costs = vec![f32::INFINITY; self.costs.len()];
+        let mut came_from = vec![usize::MAX; self.costs.len()];
+        let mut open = BinaryHeap::new();
+
+        costs[start_index] = 0.0;
+        open.push(OpenCell {
+            priority: self.heuristic(start_cell, goal_cell),
+            index: start_index,
+        });
+
+        while let Some(OpenCell { priority, index }) = open.pop() {
+            if index == goal_index {
+                break;
+            }
+
+            let cell = (index % self.width, index / self.width);
+            // Skip cells that were queued again with a cheaper cost
+            if priority > costs[index] + self.heuristic(cell, goal_cell) {
+                continue;
+            }
+
+            for (neighbor, step_cost) in self.neighbors(cell) {
+                let neighbor_index = self.index(neighbor);
+                let cost = costs[index] + step_cost;
+
+                if cost < costs[neighbor_index] {
+                    costs[neighbor_index] = cost;
+                    came_from[neighbor_index] = index;
+                    open.push(OpenCell {
+                        priority: cost + self.heuristic(neighbor, goal_cell),
+                        index: neighbor_index,
+                    });
+                }
+            }
+        }
+
+        if costs[goal_index] == f32::INFINITY {
+            return None;
+        }
+
+        let mut path = VecDeque::new();
+        let mut index = goal_index;
+        while index != start_index {
+            path.push_front(self.cell_center(index % self.width, index / self.width));
+            index = came_from[index];
+        }
+
+        // End exactly on the goal rather than the center of its cell
+        match path.back_mut() {
+            Some(last) => *last = goal,
+            None => path.push_back(goal),
+        }
+
+        Some(path.into())
+    }
+
+    /// Finds the paths of many units at once, in parallel on the job pool.
+    ///
+    /// # Arguments
+    /// * `requests` - Where every path starts and ends in world space
+    ///
+    /// # Returns
+    /// The path of every request in order, as `find_path` would find it
+    ///
+    /// # Example
+    /// ```ignore
+    /// let requests = units.iter().map(|unit| (unit.position, unit.target)).collect::<Vec<_>>();
+    /// for (unit, path) in units.iter_mut().zip(grid.find_paths(&requests)) {
+    ///     unit.path = path;
+    /// }
+    /// ```
+    pub fn find_paths(
+        &self,
+        requests: &[(Vector3<f32>, Vector3<f32>)],
+    ) -> Vec<Option<Vec<Vector3<f32>>>> {
+        gluon::scope(|scope| {
+            let jobs = requests
+                .iter()
+                .map(|(start, goal)| scope.spawn(move || self.find_path(*start, *goal)))
+                .collect::<Vec<_>>();
+
+            jobs.into_iter()
+                .map(|job| job.join().unwrap_or_default())
+                .collect()
+        })
+    }
+
+    /// Builds a flow field that leads every cell of the grid to a goal, for
+    /// many units sharing the same destination.
+    ///
+    /// # Arguments
+    /// * `goal` - The destination in world space
+    ///
+    /// # Returns
+    /// The flow field, every cell of which is unreachable if the goal is off
+    /// the grid or blocked
+    pub fn flow_field(&self, goal: Vector3<f32>) -> FlowField {
+        let mut distances = vec![f32::INFINITY; self.costs.len()];
+        let mut directions = vec![Vector2::zero(); self.costs.len()];
+
+        if let Some(goal_cell) = self.world_to_cell(goal)
+            && !self.is_blocked(goal_cell.0, goal_cell.1)
+        {
+            // Dijkstra outwards from the goal
+            let goal_index = self.index(goal_cell);
+            let mut open = BinaryHeap::new();
+            distances[goal_index] = 0.0;
+            open.push(OpenCell {
+                priority: 0.0,
+                index: goal_index,
+            });
+
+            while let Some(OpenCell { priority, index }) = open.pop() {
+                if priority > distances[index] {
+                    continue;
+                }
+
+                // Walking from a neighbor into this cell costs what this cell
+                // costs, scaled by the length of the step
+                let cell = (index % self.width, index / self.width);
+                for (neighbor, step_cost) in self.neighbors(cell) {
+                    let neighbor_index = self.index(neighbor);
+                    let distance = priority
+                        + step_cost / self.cost(neighbor.0, neighbor.1) * self.cost(cell.0, cell.1);
+
+                    if distance < distances[neighbor_index] {
+                        distances[neighbor_index] = distance;
+                        open.push(OpenCell {
+                            priority: distance,
+                            index: neighbor_index,
+                        });
+                    }
+                }
+            }
+
+            // Every cell points to the neighbor its cheapest path steps to
+            for index in 0..distances.len() {
+                if index == goal_index || distances[index] == f32::INFINITY {
+                    continue;
+                }
+
+                let cell = (index % self.width, index / self.width);
+                let closest = self
+                    .neighbors(cell)
+                    .map(|(neighbor, step_cost)| {
+                        (neighbor, distances[self.index(neighbor)] + step_cost)
+                    })
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .map(|(neighbor, _)| neighbor);
+
+                if let Some(neighbor) = closest {
+                    directions[index] = Vector2::new(
+                        neighbor.0 as f32 - cell.0 as f32,
+                        neighbor.1 as f32 - cell.1 as f32,
+                    )
+                    .normalize();
+                }
+            }
+        }
+
+        FlowField {
+            grid: self.clone_layout(),
+            distances,
+            directions,
+            goal,
+        }
+    }
+
+    fn index(&self, cell: (usize, usize)) -> usize {
+        cell.1 * self.width + cell.0
+    }
+
+    // Octile distance, admissible since every cost is at least 1
+    fn heuristic(&self, from: (usize, usize), to: (usize, usize)) -> f32 {
+        let dx = from.0.abs_diff(to.0) as f32;
+        let dz = from.1.abs_diff(to.1) as f32;
+
+        dx.max(dz) + (std::f32::consts::SQRT_2 - 1.0) * dx.min(dz)
+    }
+
+    /// The walkable neighbors of a cell and the cost of stepping to them
+    fn neighbors(&self, cell: (usize, usize)) -> impl Iterator<Item = ((usize, usize), f32)> + '_ {
+        NEIGHBORS.iter().filter_map(move |(dx, dz)| {
+            let x = cell.0.checked_add_signed(*dx)?;
+            let z = cell.1.checked_add_signed(*dz)?;
+            let cost = self.cost(x, z);
+            if cost == BLOCKED {
+                return None;
+            }
+
+            if *dx != 0 && *dz != 0 {
+                // Don't cut the corners of blocked cells
+                if self.is_blocked(x, cell.1) || self.is_blocked(cell.0, z) {
+                    return None;
+                }
+
+                return Some(((x, z), cost * std::f32::consts::SQRT_2));
+            }
+
+            Some(((x, z), cost))
+        })
+    }
+
+    fn clone_layout(&self) -> Self {
+        Self {
+            width: self.width,
+            height: self.height,
+            cell_size: self.cell_size,
+            origin: self.origin,
+            costs: Vec::new(),
+        }
+    }
+}
+
+// Entry of the open set, ordered so the heap pops the lowest priority first
+#[derive(PartialEq)]
+struct OpenCell {
+    priority: f32,
+    index: usize,
+}
+
+impl Eq for OpenCell {}
+
+impl PartialOrd for OpenCell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenCell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .priority
+            .total_cmp(&self.priority)
+            .then_with(|| other.index.cmp(&self.index))
+    }
+}
+
+/// Directions towards a goal from every cell of a `PathGrid`.
+///
+/// Built once with `PathGrid::flow_field`, then any number of units can look
+/// up which way to go from where they are.
+///
+/// # Example
+/// ```ignore
+/// let flow_field = grid.flow_field(rally_point);
+///
+/// compound.iter_mut_duo(|_entity, unit: &mut Unit, transform: &mut Transform3D| {
+///     let position = transform.get_position(|position| *position);
+///     if let Some(direction) = flow_field.direction_at(position) {
+///         unit.velocity = direction * unit.speed;
+///     }
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct FlowField {
+    grid: PathGrid,
+    distances: Vec<f32>,
+    directions: Vec<Vector2<f32>>,
+    goal: Vector3<f32>,
+}
+
+impl FlowField {
+    pub fn goal(&self) -> Vector3<f32> {
+        self.goal
+    }
+
+    /// Returns which way to move from a position on the XZ plane, zero in the
+    /// cell of the goal, or `None` if the goal can't be reached from there
+    pub fn direction_at(&self, position: Vector3<f32>) -> Option<Vector3<f32>> {
+        let cell = self.grid.world_to_cell(position)?;
+        let index = self.grid.index(cell);
+        if self.distances[index] == f32::INFINITY {
+            return None;
+        }
+
+        let direction = self.directions[index];
+        Some(Vector3::new(direction.x, 0.0, direction.y))
+    }
+
+    /// Returns the cost of the cheapest path to the goal from a position
+    pub fn distance_at(&self, position: Vector3<f32>) -> Option<f32> {
+        let cell = self.grid.world_to_cell(position)?;
+        let distance = self.distances[self.grid.index(cell)];
+
+        (distance != f32::INFINITY).then_some(distance)
+    }
+}
+
+/// Draws a `PathGrid` or `FlowField` on the same entity as a debug overlay.
+///
+/// Cells of a grid are outlined from green for a cost of 1 to red for
+/// `max_cost` and above, and blocked cells are crossed out. The cells of a
+/// flow field get an arrow in the direction they flow.
+///
+/// # Example
+/// ```ignore
+/// compound.spawn((grid, PathGridGizmo::default()));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathGridGizmo {
+    /// Cost drawn fully red
+    pub max_cost: f32,
+    /// Height of the overlay above the grid
+    pub height: f32,
+    pub show_costs: bool,
+    pub show_flow: bool,
+}
+
+impl Default for PathGridGizmo {
+    fn default() -> Self {
+        Self {
+            max_cost: 5.0,
+            height: 0.05,
+            show_costs: true,
+            show_flow: true,
+        }
+    }
+}
+
+const BLOCKED_COLOR: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+const FLOW_COLOR: [f32; 4] = [0.2, 0.6, 1.0, 1.0];
+
+/// Builds the lines for every `PathGridGizmo` in world space
+pub(crate) fn collect_path_gizmos(compound: &Compound, vertices: &mut Vec<GizmoVertex>) {
+    compound.iter_duo(|_entity, grid: &PathGrid, gizmo: &PathGridGizmo| {
+        if !gizmo.show_costs {
+            return;
+        }
+
+        let lift = Vector3::unit_y() * gizmo.height;
+        let half = grid.cell_size * 0.5;
+        for z in 0..grid.height {
+            for x in 0..grid.width {
+                let center = grid.cell_center(x, z) + lift;
+                let corners = [
+                    center + Vector3::new(-half, 0.0, -half),
+                    center + Vector3::new(half, 0.0, -half),
+                    center + Vector3::new(half, 0.0, half),
+                    center + Vector3::new(-half, 0.0, half),
+                ];
+
+                let cost = grid.cost(x, z);
+                if cost == BLOCKED {
+                    for (start, end) in [(0, 2), (1, 3)] {
+                        vertices.push(GizmoVertex::new(corners[start].into(), BLOCKED_COLOR));
+                        vertices.push(GizmoVertex::new(corners[end].into(), BLOCKED_COLOR));
+                    }
+                    continue;
+                }
+
+                let heat = ((cost - 1.0) / (gizmo.max_cost - 1.0).max(f32::EPSILON)).min(1.0);
+                let color = [heat, 1.0 - heat, 0.0, 1.0];
+                for edge in 0..4 {
+                    vertices.push(GizmoVertex::new(corners[edge].into(), color));
+                    vertices.push(GizmoVertex::new(corners[(edge + 1) % 4].into(), color));
+                }
+            }
+        }
+    });
+
+    compound.iter_duo(|_entity, flow_field: &FlowField, gizmo: &PathGridGizmo| {
+        if !gizmo.show_flow {
+            return;
+        }
+
+        let grid = &flow_field.grid;
+        let lift = Vector3::unit_y() * gizmo.height;
+        let length = grid.cell_size * 0.4;
+        for z in 0..grid.height {
+            for x in 0..grid.width {
+                let direction = flow_field.directions[grid.index((x, z))];
+                if direction.magnitude2() == 0.0 {
+                    continue;
+                }
+
+                let direction = Vector3::new(direction.x, 0.0, direction.y) * length;
+                let side = Vector3::new(-direction.z, 0.0, direction.x) * 0.3;
+                let center = grid.cell_center(x, z) + lift;
+                let tip = center + direction * 0.5;
+                let tail = center - direction * 0.5;
+
+                for (start, end) in [
+                    (tail, tip),
+                    (tip, tip - direction * 0.3 + side),
+                    (tip, tip - direction * 0.3 - side),
+                ] {
+                    vertices.push(GizmoVertex::new(start.into(), FLOW_COLOR));
+                    vertices.push(GizmoVertex::new(end.into(), FLOW_COLOR));
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-4;
+
+    // Builds a grid of unit cells from rows of characters, `#` is blocked and
+    // digits are the cost of a cell
+    fn grid(rows: &[&str]) -> PathGrid {
+        let tiles = rows.iter().flat_map(|row| row.chars()).collect::<Vec<_>>();
+
+        PathGrid::from_tiles(
+            &tiles,
+            rows[0].len(),
+            1.0,
+            Vector3::zero(),
+            |tile| match tile {
+                '#' => BLOCKED,
+                digit => digit.to_digit(10).unwrap_or(1) as f32,
+            },
+        )
+    }
+
+    // The cost of walking a path from `start` through the cells it visits
+    fn path_cost(grid: &PathGrid, start: Vector3<f32>, path: &[Vector3<f32>]) -> f32 {
+        let mut cell = grid.world_to_cell(start).unwrap();
+        let mut cost = 0.0;
+
+        for point in path {
+            let next = grid.world_to_cell(*point).unwrap();
+            let (dx, dz) = (cell.0.abs_diff(next.0), cell.1.abs_diff(next.1));
+            assert!(
+                dx <= 1 && dz <= 1,
+                "path jumps from {:?} to {:?}",
+                cell,
+                next
+            );
+
+            let length = if dx + dz == 2 {
+                std::f32::consts::SQRT_2
+            } else {
+                1.0
+            };
+            cost += grid.cost(next.0, next.1) * length;
+            cell = next;
+        }
+
+        cost
+    }
+
+    #[test]
+    fn test_path_goes_around_expensive_cells() {
+        let grid = grid(&["11111", "19991", "11111"]);
+        let (start, goal) = (grid.cell_center(0, 1), grid.cell_center(4, 1));
+
+        let path = grid.find_path(start, goal).unwrap();
+
+        assert_eq!(*path.last().unwrap(), goal);
+        assert!(
+            (path_cost(&grid, start, &path) - (2.0 + 2.0 * std::f32::consts::SQRT_2)).abs()
+                < EPSILON
+        );
+    }
+
+    #[test]
+    fn test_path_crosses_expensive_cells_when_cheapest() {
+        let grid = grid(&["11311", "11311", "11311"]);
+        let (start, goal) = (grid.cell_center(0, 1), grid.cell_center(4, 1));
+
+        let path = grid.find_path(start, goal).unwrap();
+
+        assert!((path_cost(&grid, start, &path) - 6.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_path_does_not_cut_corners() {
+        let grid = grid(&["1#", "11"]);
+        let (start, goal) = (grid.cell_center(0, 0), grid.cell_center(1, 1));
+
+        let path = grid.find_path(start, goal).unwrap();
+
+        assert_eq!(path, vec![grid.cell_center(0, 1), goal]);
+    }
+
+    #[test]
+    fn test_no_path_to_unreachable_goal() {
+        let grid = grid(&["11#11", "11#11", "11#11"]);
+        let (start, goal) = (grid.cell_center(0, 1), grid.cell_center(4, 1));
+
+        assert!(grid.find_path(start, goal).is_none());
+        assert!(grid.find_path(start, grid.cell_center(2, 1)).is_none());
+        assert!(
+            grid.find_path(start, Vector3::new(-1.0, 0.0, 0.0))
+                .is_none()
+        );
+
+        let flow_field = grid.flow_field(goal);
+        assert!(flow_field.direction_at(start).is_none());
+        assert!(flow_field.distance_at(start).is_none());
+    }
+
+    #[test]
+    fn test_flow_field_leads_downhill_to_goal() {
+        let grid = grid(&["11111", "1#9#1", "11#11", "11111"]);
+        let goal = grid.cell_center(2, 1);
+        let flow_field = grid.flow_field(goal);
+
+        assert_eq!(flow_field.distance_at(goal), Some(0.0));
+        assert_eq!(flow_field.direction_at(goal), Some(Vector3::zero()));
+
+        for z in 0..grid.height() {
+            for x in 0..grid.width() {
+                let center = grid.cell_center(x, z);
+                let Some(distance) = flow_field.distance_at(center) else {
+                    assert!(grid.is_blocked(x, z));
+                    continue;
+                };
+                if (x, z) == (2, 1) {
+                    continue;
+                }
+
+                // Following the flow always gets closer to the goal
+                let direction = flow_field.direction_at(center).unwrap();
+                let next = center + direction * std::f32::consts::SQRT_2;
+                let next_distance = flow_field.distance_at(next).unwrap();
+                assert!(
+                    next_distance < distance,
+                    "cell ({}, {}) flows from {} to {}",
+                    x,
+                    z,
+                    distance,
+                    next_distance
+                );
+
+                // And matches the cost of the cheapest path
+                let path = grid.find_path(center, goal).unwrap();
+                assert!((path_cost(&grid, center, &path) - distance).abs() < EPSILON);
+            }
+        }
+    }
+}
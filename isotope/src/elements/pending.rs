@@ -0,0 +1,108 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Result;
+use compound::Compound;
+use gluon::JobHandle;
+use gpu_controller::Instance;
+use log::{debug, error, info};
+
+use crate::{AssetServer, Model};
+
+/// Component that loads a model in the background.
+///
+/// The engine starts loading the model as soon as the entity is simulated and
+/// replaces the `PendingModel` with the loaded `Model` once it is ready, so a
+/// game state never has to poll for it. A model that fails to load is replaced
+/// with the placeholder cube, see `Model::from_obj_or_placeholder`.
+///
+/// # Example
+/// ```ignore
+/// compound.spawn((
+///     PendingModel::new("game://models/crate.obj"),
+///     Transform3D::default(),
+/// ));
+///
+/// // Later, once the model has loaded
+/// compound.iter_mol(|_entity, model: &Model| {
+///     println!("Crate bounds: {:?}", model.bounds());
+/// });
+/// ```
+pub struct PendingModel {
+    path: PathBuf,
+    instances: Option<Vec<Instance>>,
+    load: Option<JobHandle<Result<Model>>>,
+}
+
+impl PendingModel {
+    pub fn new<P>(path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            instances: None,
+            load: None,
+        }
+    }
+
+    /// Loads the model with instances, as with `Model::from_obj`
+    pub fn with_instances(mut self, instances: &[Instance]) -> Self {
+        self.instances = Some(instances.to_vec());
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the model has started loading
+    pub fn is_loading(&self) -> bool {
+        self.load.is_some()
+    }
+}
+
+/// Starts loading new pending models and swaps the finished ones for their `Model`.
+pub(crate) fn load_pending_models(compound: &Compound, asset_server: &Arc<AssetServer>) {
+    let mut finished = Vec::new();
+
+    compound.iter_mut_mol_unmod(|entity, pending: &mut PendingModel| match &pending.load {
+        Some(load) => {
+            if load.is_finished() {
+                finished.push(entity);
+            }
+        }
+        None => {
+            info!("Loading {:#?} in the background", pending.path);
+
+            let path = pending.path.clone();
+            let instances = pending.instances.take();
+            let asset_server = asset_server.clone();
+            pending.load = Some(gluon::spawn(move || {
+                Model::from_obj_or_placeholder(&path, &asset_server, instances.as_deref())
+            }));
+        }
+    });
+
+    for entity in finished {
+        // The entity could have been despawned since it was iterated
+        let Some(pending) = compound.remove_molecule::<PendingModel>(entity) else {
+            continue;
+        };
+
+        let Some(load) = pending.load else {
+            continue;
+        };
+
+        match load.join() {
+            Ok(Ok(model)) => {
+                debug!("Loaded {:#?} for entity {}", pending.path, entity);
+                compound.add_molecule(entity, model);
+            }
+            Ok(Err(err)) => error!("Failed to load {:#?}: {}", pending.path, err),
+            Err(_) => error!("Loading {:#?} panicked", pending.path),
+        }
+    }
+}
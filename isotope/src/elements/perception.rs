@@ -0,0 +1,400 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::RwLock,
+};
+
+use boson::{Boson, BosonObject};
+use cgmath::{InnerSpace, Rotation, Vector3};
+use compound::{Compound, Entity};
+use log::error;
+
+use crate::{SpatialHash, Transform3D};
+
+/// Default seconds it takes a perceiver to forget something it sensed
+pub const DEFAULT_MEMORY_DURATION: f32 = 10.0;
+
+/// Marker component for entities that perceivers can see and hear.
+pub struct Perceivable;
+
+/// How a perceiver noticed an entity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sense {
+    Sight,
+    Hearing,
+}
+
+/// What AI logic is told about the perception of an entity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PerceptionEvent {
+    /// A perceivable entity came into sight
+    Spotted {
+        entity: Entity,
+        position: Vector3<f32>,
+    },
+    /// An entity that was in sight no longer is
+    Lost {
+        entity: Entity,
+        last_known_position: Vector3<f32>,
+    },
+    /// A sound was heard, from an entity if it has a source
+    Heard {
+        source: Option<Entity>,
+        position: Vector3<f32>,
+        loudness: f32,
+    },
+    /// An entity has not been sensed for long enough to be forgotten
+    Forgotten { entity: Entity },
+}
+
+/// What a perceiver remembers about an entity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PerceptionMemory {
+    /// Where the entity was last seen or heard
+    pub last_known_position: Vector3<f32>,
+    /// How sure the perceiver is of the position, 1 when it is sensed and
+    /// decaying to 0 after which it is forgotten
+    pub confidence: f32,
+    /// How the entity was last sensed
+    pub sense: Sense,
+    /// Whether the entity is in sight right now
+    pub visible: bool,
+}
+
+/// A sound made by gameplay, such as footsteps or a gunshot, that perceivers
+/// within range can hear.
+///
+/// # Example
+/// ```ignore
+/// SoundEvent::new(position, 20.0).from_source(player).emit(compound);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SoundEvent {
+    pub position: Vector3<f32>,
+    /// Distance the sound can be heard from, scaled by the hearing of a perceiver
+    pub loudness: f32,
+    /// The entity that made the sound, remembered by whoever hears it
+    pub source: Option<Entity>,
+}
+
+impl SoundEvent {
+    pub fn new(position: Vector3<f32>, loudness: f32) -> Self {
+        Self {
+            position,
+            loudness,
+            source: None,
+        }
+    }
+
+    pub fn from_source(mut self, source: Entity) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Queues the sound to be heard by the perceivers of a compound on the next tick
+    pub fn emit(self, compound: &Compound) {
+        if compound
+            .resource_mut::<PendingSounds, _>(|sounds| sounds.0.push(self))
+            .is_none()
+        {
+            compound.insert_resource(PendingSounds(vec![self]));
+        }
+    }
+}
+
+// Sounds emitted since perception was last updated
+pub(crate) struct PendingSounds(Vec<SoundEvent>);
+
+/// Component that gives an entity sight and hearing.
+///
+/// Every tick the engine checks which `Perceivable` entities are within the
+/// sight cone of the perceiver and not occluded by a physics collider, and
+/// which emitted sounds it is close enough to hear. Sensed entities are
+/// remembered with a confidence that decays once they are no longer sensed.
+/// Changes are queued as `PerceptionEvent`s for AI logic to drain.
+///
+/// Entities are processed in order and nothing is random, so the same world
+/// always produces the same events. The sight cone faces +Z of the transform.
+///
+/// # Example
+/// ```ignore
+/// compound.spawn((
+///     Transform3D::default(),
+///     Perceiver::new(30.0, std::f32::consts::FRAC_PI_4).with_eye_height(1.7),
+/// ));
+///
+/// // In IsotopeState::update
+/// compound.iter_mut_mol(|_entity, perceiver: &mut Perceiver| {
+///     for event in perceiver.drain_events() {
+///         if let PerceptionEvent::Spotted { entity, .. } = event {
+///             println!("Spotted {}", entity);
+///         }
+///     }
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct Perceiver {
+    /// Furthest distance the perceiver can see
+    pub sight_range: f32,
+    /// Angle between the center and edge of the sight cone, in radians
+    pub sight_half_angle: f32,
+    /// Height of the eyes above the position of the transform
+    pub eye_height: f32,
+    /// Multiplier of how far away sounds can be heard
+    pub hearing: f32,
+    /// Seconds it takes to forget an entity that is no longer sensed
+    pub memory_duration: f32,
+
+    memories: BTreeMap<Entity, PerceptionMemory>,
+    events: Vec<PerceptionEvent>,
+}
+
+impl Perceiver {
+    /// Creates a perceiver with regular hearing and memory.
+    ///
+    /// # Arguments
+    /// * `sight_range` - Furthest distance the perceiver can see
+    /// * `sight_half_angle` - Angle between the center and edge of the sight cone, in radians
+    pub fn new(sight_range: f32, sight_half_angle: f32) -> Self {
+        Self {
+            sight_range,
+            sight_half_angle,
+            eye_height: 0.0,
+            hearing: 1.0,
+            memory_duration: DEFAULT_MEMORY_DURATION,
+            memories: BTreeMap::new(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn with_eye_height(mut self, eye_height: f32) -> Self {
+        self.eye_height = eye_height;
+        self
+    }
+
+    pub fn with_hearing(mut self, hearing: f32) -> Self {
+        self.hearing = hearing;
+        self
+    }
+
+    pub fn with_memory_duration(mut self, memory_duration: f32) -> Self {
+        self.memory_duration = memory_duration;
+        self
+    }
+
+    /// Returns what the perceiver remembers about an entity
+    pub fn memory(&self, entity: Entity) -> Option<&PerceptionMemory> {
+        self.memories.get(&entity)
+    }
+
+    /// Calls the callback with every remembered entity, in entity order
+    pub fn memories<F>(&self, mut callback: F)
+    where
+        F: FnMut(Entity, &PerceptionMemory),
+    {
+        for (entity, memory) in self.memories.iter() {
+            callback(*entity, memory);
+        }
+    }
+
+    /// Whether an entity is in sight right now
+    pub fn can_see(&self, entity: Entity) -> bool {
+        self.memories
+            .get(&entity)
+            .is_some_and(|memory| memory.visible)
+    }
+
+    /// Takes the events queued since the last time they were drained
+    pub fn drain_events(&mut self) -> Vec<PerceptionEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn remember(&mut self, entity: Entity, position: Vector3<f32>, sense: Sense, confidence: f32) {
+        let memory = self.memories.entry(entity).or_insert(PerceptionMemory {
+            last_known_position: position,
+            confidence: 0.0,
+            sense,
+            visible: false,
+        });
+
+        memory.last_known_position = position;
+        memory.confidence = memory.confidence.max(confidence);
+        memory.sense = sense;
+    }
+
+    /// Updates the memory of an entity with whether it was seen this tick
+    fn update_sight(&mut self, entity: Entity, position: Option<Vector3<f32>>) {
+        match position {
+            Some(position) => {
+                if !self.can_see(entity) {
+                    self.events
+                        .push(PerceptionEvent::Spotted { entity, position });
+                }
+
+                self.remember(entity, position, Sense::Sight, 1.0);
+                if let Some(memory) = self.memories.get_mut(&entity) {
+                    memory.confidence = 1.0;
+                    memory.visible = true;
+                }
+            }
+            None => {
+                if let Some(memory) = self.memories.get_mut(&entity)
+                    && memory.visible
+                {
+                    memory.visible = false;
+                    self.events.push(PerceptionEvent::Lost {
+                        entity,
+                        last_known_position: memory.last_known_position,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Fades the memories of entities that are not sensed and forgets the ones
+    /// that faded completely
+    fn decay(&mut self, dt: f32) {
+        let decay = dt / self.memory_duration.max(f32::EPSILON);
+
+        let mut forgotten = Vec::new();
+        for (entity, memory) in self.memories.iter_mut() {
+            if memory.visible {
+                continue;
+            }
+
+            memory.confidence -= decay;
+            if memory.confidence <= 0.0 {
+                forgotten.push(*entity);
+            }
+        }
+
+        for entity in forgotten {
+            self.memories.remove(&entity);
+            self.events.push(PerceptionEvent::Forgotten { entity });
+        }
+    }
+}
+
+/// Updates what every perceiver of a compound sees, hears and remembers.
+pub(crate) fn update_perception(compound: &Compound, boson: &RwLock<Boson>, dt: f32) {
+    let sounds = compound
+        .remove_resource::<PendingSounds>()
+        .map(|sounds| sounds.0)
+        .unwrap_or_default();
+
+    // Perceivables sorted by entity so events are always in the same order
+    let mut perceivables = Vec::new();
+    compound.iter_duo(
+        |entity, transform: &Transform3D, _perceivable: &Perceivable| {
+            perceivables.push((entity, transform.get_position(|position| *position)));
+        },
+    );
+
+    let mut max_sight_range = None;
+    compound.iter_mol(|_entity, perceiver: &Perceiver| {
+        max_sight_range = Some(perceiver.sight_range.max(max_sight_range.unwrap_or(0.0)));
+    });
+    let Some(max_sight_range) = max_sight_range else {
+        return;
+    };
+
+    perceivables.sort_by_key(|(entity, _)| *entity);
+
+    let mut spatial_hash = SpatialHash::new(max_sight_range.max(1.0));
+    for (entity, position) in perceivables.iter() {
+        spatial_hash.insert(*entity, *position);
+    }
+
+    // Bodies don't occlude the entities they belong to
+    let mut bodies = HashMap::new();
+    compound.iter_mol(|entity, boson_object: &BosonObject| {
+        bodies.insert(entity, boson_object.clone());
+    });
+
+    let boson = match boson.read() {
+        Ok(boson) => boson,
+        Err(err) => {
+            error!("Failed to read physics for perception: {}", err);
+            return;
+        }
+    };
+
+    compound.iter_mut_duo_unmod(
+        |entity, perceiver: &mut Perceiver, transform: &mut Transform3D| {
+            let (position, rotation) =
+                transform.get_position_and_rotation(|position, rotation| (*position, *rotation));
+            let eye = position + Vector3::unit_y() * perceiver.eye_height;
+            let forward = rotation.rotate_vector(Vector3::unit_z());
+            let cos_half_angle = perceiver.sight_half_angle.cos();
+
+            let mut in_range = Vec::new();
+            spatial_hash.for_each_within(position, perceiver.sight_range, |other, position| {
+                if other != entity {
+                    in_range.push((other, position));
+                }
+            });
+            in_range.sort_by_key(|(other, _)| *other);
+
+            let mut seen = HashMap::new();
+            for (other, target) in in_range {
+                let offset = target - eye;
+                let distance = offset.magnitude();
+                if distance > f32::EPSILON && forward.dot(offset / distance) < cos_half_angle {
+                    continue;
+                }
+
+                let ignore = [bodies.get(&entity), bodies.get(&other)]
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>();
+                if boson.is_occluded(to_f64(eye), to_f64(target), &ignore) {
+                    continue;
+                }
+
+                seen.insert(other, target);
+            }
+
+            // Everything remembered or seen, in entity order
+            let mut entities = perceiver
+                .memories
+                .keys()
+                .copied()
+                .chain(seen.keys().copied())
+                .collect::<Vec<_>>();
+            entities.sort();
+            entities.dedup();
+
+            for other in entities {
+                perceiver.update_sight(other, seen.get(&other).copied());
+            }
+
+            for sound in sounds.iter() {
+                if sound.source == Some(entity) {
+                    continue;
+                }
+
+                let range = sound.loudness * perceiver.hearing;
+                let distance = (sound.position - eye).magnitude();
+                if distance > range {
+                    continue;
+                }
+
+                let loudness = 1.0 - distance / range.max(f32::EPSILON);
+                perceiver.events.push(PerceptionEvent::Heard {
+                    source: sound.source,
+                    position: sound.position,
+                    loudness,
+                });
+
+                if let Some(source) = sound.source {
+                    perceiver.remember(source, sound.position, Sense::Hearing, loudness);
+                }
+            }
+
+            perceiver.decay(dt);
+        },
+    );
+}
+
+pub(crate) fn to_f64(vector: Vector3<f32>) -> Vector3<f64> {
+    Vector3::new(vector.x as f64, vector.y as f64, vector.z as f64)
+}
@@ -0,0 +1,317 @@
+use std::path::PathBuf;
+
+use anyhow::{Result, anyhow};
+use cgmath::{InnerSpace, Quaternion, Rad, Rotation, Rotation3, Vector3};
+use compound::{Compound, Entity, With, Without};
+use log::info;
+use photon::renderer::lens::DepthOfField;
+
+use crate::{
+    Camera, Camera3DDescriptor, Discarded, GamePaused, RenderLayers, Screenshot, Transform3D,
+};
+
+// Keeps the free camera from flipping over when looking straight up or down
+const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+// Marks the camera spawned by photo mode
+struct PhotoCamera;
+
+/// How photo mode behaves, passed to `PhotoMode::enter`.
+#[derive(Debug, Clone)]
+pub struct PhotoModeSettings {
+    /// Layers drawn by UI cameras and world space UI, which photo mode hides
+    pub ui_layers: RenderLayers,
+    /// Units per second the camera flies at full input
+    pub move_speed: f32,
+    /// Radians per second the camera rolls at full input
+    pub roll_speed: f32,
+    /// Degrees per second the field of view changes at full input
+    pub zoom_speed: f32,
+    /// Narrowest and widest vertical field of view in degrees
+    pub fovy_range: (f32, f32),
+    /// Furthest the camera may fly from where photo mode was entered
+    pub max_distance: f32,
+    /// Depth of field of the photo camera, the game camera's if none
+    pub depth_of_field: Option<DepthOfField>,
+    /// Directory captures are saved to
+    pub capture_directory: PathBuf,
+}
+
+impl Default for PhotoModeSettings {
+    fn default() -> Self {
+        Self {
+            ui_layers: RenderLayers::none(),
+            move_speed: 5.0,
+            roll_speed: 1.0,
+            zoom_speed: 30.0,
+            fovy_range: (10.0, 100.0),
+            max_distance: 20.0,
+            depth_of_field: None,
+            capture_directory: PathBuf::from("screenshots"),
+        }
+    }
+}
+
+/// Input that moves the photo camera for a tick.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhotoCameraInput {
+    /// Movement relative to the camera, +Z forward, from -1 to 1 on each axis
+    pub movement: Vector3<f32>,
+    /// Radians to turn around the vertical axis
+    pub yaw: f32,
+    /// Radians to look up or down
+    pub pitch: f32,
+    /// How fast to roll, from -1 to 1
+    pub roll: f32,
+    /// How fast to change the field of view, from -1 to 1, positive zooms in
+    pub zoom: f32,
+}
+
+impl Default for PhotoCameraInput {
+    fn default() -> Self {
+        Self {
+            movement: Vector3::new(0.0, 0.0, 0.0),
+            yaw: 0.0,
+            pitch: 0.0,
+            roll: 0.0,
+            zoom: 0.0,
+        }
+    }
+}
+
+/// Pauses the game and takes over with a free flying camera for screenshots.
+///
+/// Entering photo mode pauses the world with `GamePaused` and spawns a camera
+/// where the game camera is, drawn after every other camera and without the
+/// UI layers. The game drives the camera with `fly` from its input and saves
+/// what it sees with `capture`. Exiting removes the camera and unpauses the
+/// game, unless it was paused before.
+///
+/// Photo mode keeps its state as a resource of the compound.
+///
+/// # Example
+/// ```ignore
+/// // In IsotopeState::update
+/// if toggled_photo_mode {
+///     if PhotoMode::is_active(ecs) {
+///         PhotoMode::exit(ecs)?;
+///     } else {
+///         PhotoMode::enter(ecs, PhotoModeSettings {
+///             ui_layers: RenderLayers::layer(UI_LAYER),
+///             ..Default::default()
+///         })?;
+///     }
+/// }
+///
+/// PhotoMode::fly(ecs, PhotoCameraInput { movement, yaw, pitch, ..Default::default() }, delta_t);
+/// ```
+#[derive(Debug, Clone)]
+pub struct PhotoMode {
+    settings: PhotoModeSettings,
+    camera: Entity,
+    origin: Vector3<f32>,
+    yaw: f32,
+    pitch: f32,
+    roll: f32,
+    // Whether the game was already paused when photo mode was entered
+    was_paused: bool,
+    captures: u32,
+}
+
+impl PhotoMode {
+    /// Pauses the game and spawns the photo camera at the game camera.
+    ///
+    /// # Arguments
+    /// * `compound` - The world to take photos of
+    /// * `settings` - How photo mode behaves
+    ///
+    /// # Returns
+    /// An error if photo mode is already active or the world has no camera
+    pub fn enter(compound: &Compound, settings: PhotoModeSettings) -> Result<()> {
+        if Self::is_active(compound) {
+            return Err(anyhow!("Photo mode is already active"));
+        }
+
+        // The game camera is the first one drawn, the others are overlays
+        let mut game_camera: Option<(Entity, Camera3DDescriptor, Transform3D)> = None;
+        let mut last_order = i32::MIN;
+        compound
+            .query::<(&Camera3DDescriptor, &Transform3D), Without<Discarded>>()
+            .for_each(|entity, (descriptor, transform)| {
+                last_order = last_order.max(descriptor.order);
+
+                if game_camera
+                    .as_ref()
+                    .is_none_or(|(_, game_camera, _)| descriptor.order < game_camera.order)
+                {
+                    game_camera = Some((entity, *descriptor, *transform));
+                }
+            });
+
+        let Some((game_entity, descriptor, transform)) = game_camera else {
+            return Err(anyhow!("No camera to enter photo mode from"));
+        };
+
+        let mut layers = RenderLayers::default();
+        compound.iter_mol(|entity, game_layers: &RenderLayers| {
+            if entity == game_entity {
+                layers = *game_layers;
+            }
+        });
+
+        let (origin, forward) = transform.get_position_and_rotation(|position, rotation| {
+            (*position, rotation.rotate_vector(Vector3::unit_z()))
+        });
+
+        let camera = compound.spawn((
+            Camera3DDescriptor {
+                order: last_order.saturating_add(1),
+                clear: true,
+                depth_of_field: settings.depth_of_field.or(descriptor.depth_of_field),
+                motion_blur: None,
+                ..descriptor
+            },
+            transform,
+            RenderLayers::from_bits(layers.bits() & !settings.ui_layers.bits()),
+            PhotoCamera,
+        ));
+
+        let was_paused = compound.has_resource::<GamePaused>();
+        if !was_paused {
+            compound.insert_resource(GamePaused);
+        }
+
+        info!("Entered photo mode");
+        compound.insert_resource(PhotoMode {
+            settings,
+            camera,
+            origin,
+            yaw: forward.x.atan2(forward.z),
+            pitch: (-forward.y).asin(),
+            roll: 0.0,
+            was_paused,
+            captures: 0,
+        });
+
+        Ok(())
+    }
+
+    /// Removes the photo camera and unpauses the game if photo mode paused it.
+    ///
+    /// # Returns
+    /// An error if photo mode is not active
+    pub fn exit(compound: &Compound) -> Result<()> {
+        let photo_mode = compound
+            .remove_resource::<PhotoMode>()
+            .ok_or_else(|| anyhow!("Photo mode is not active"))?;
+
+        compound.remove_molecule::<Camera>(photo_mode.camera);
+        compound.remove_molecule::<Camera3DDescriptor>(photo_mode.camera);
+        compound.add_molecule(photo_mode.camera, Discarded);
+
+        if !photo_mode.was_paused {
+            compound.remove_resource::<GamePaused>();
+        }
+
+        info!("Exited photo mode");
+        Ok(())
+    }
+
+    pub fn is_active(compound: &Compound) -> bool {
+        compound.has_resource::<PhotoMode>()
+    }
+
+    /// Returns the entity of the photo camera
+    pub fn camera(compound: &Compound) -> Option<Entity> {
+        compound.resource::<PhotoMode, _>(|photo_mode| photo_mode.camera)
+    }
+
+    /// Moves, turns, rolls and zooms the photo camera.
+    ///
+    /// # Arguments
+    /// * `compound` - The world in photo mode
+    /// * `input` - The input of the player this tick
+    /// * `dt` - Seconds since the last tick
+    pub fn fly(compound: &Compound, input: PhotoCameraInput, dt: f32) {
+        let Some((rotation, movement, zoom, max_distance, origin, fovy_range)) = compound
+            .resource_mut::<PhotoMode, _>(|photo_mode| {
+                photo_mode.yaw += input.yaw;
+                photo_mode.pitch = (photo_mode.pitch + input.pitch).clamp(-MAX_PITCH, MAX_PITCH);
+                photo_mode.roll +=
+                    input.roll.clamp(-1.0, 1.0) * photo_mode.settings.roll_speed * dt;
+
+                let rotation = Quaternion::from_angle_y(Rad(photo_mode.yaw))
+                    * Quaternion::from_angle_x(Rad(photo_mode.pitch))
+                    * Quaternion::from_angle_z(Rad(photo_mode.roll));
+
+                (
+                    rotation,
+                    input.movement * photo_mode.settings.move_speed * dt,
+                    input.zoom.clamp(-1.0, 1.0) * photo_mode.settings.zoom_speed * dt,
+                    photo_mode.settings.max_distance,
+                    photo_mode.origin,
+                    photo_mode.settings.fovy_range,
+                )
+            })
+        else {
+            return;
+        };
+
+        compound
+            .query::<(&mut Transform3D, &mut Camera3DDescriptor), With<PhotoCamera>>()
+            .for_each(|_entity, (transform, descriptor)| {
+                transform.position_and_rotation(|position, transform_rotation| {
+                    let mut offset = *position + rotation.rotate_vector(movement) - origin;
+                    if offset.magnitude() > max_distance {
+                        offset = offset.normalize_to(max_distance);
+                    }
+
+                    *position = origin + offset;
+                    *transform_rotation = rotation;
+                });
+
+                descriptor.fovy = (descriptor.fovy - zoom).clamp(fovy_range.0, fovy_range.1);
+            });
+
+        // Cameras follow the rotation of their transform without its roll
+        compound
+            .query::<(&mut Camera,), With<PhotoCamera>>()
+            .unmod()
+            .for_each(|_entity, (camera,)| {
+                camera.up(|up| *up = rotation.rotate_vector(Vector3::unit_y()));
+            });
+    }
+
+    /// Sets the depth of field of the photo camera, none to turn it off
+    pub fn set_depth_of_field(compound: &Compound, depth_of_field: Option<DepthOfField>) {
+        compound
+            .query::<(&mut Camera3DDescriptor,), With<PhotoCamera>>()
+            .for_each(|_entity, (descriptor,)| descriptor.depth_of_field = depth_of_field);
+    }
+
+    /// Saves what the photo camera sees at the end of the next frame
+    ///
+    /// # Returns
+    /// The path the capture is saved to, or an error if photo mode is not active
+    pub fn capture(compound: &Compound) -> Result<PathBuf> {
+        let path = compound
+            .resource_mut::<PhotoMode, _>(|photo_mode| {
+                photo_mode.captures += 1;
+
+                let seconds = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|time| time.as_secs())
+                    .unwrap_or_default();
+                photo_mode
+                    .settings
+                    .capture_directory
+                    .join(format!("photo_{}_{}.png", seconds, photo_mode.captures))
+            })
+            .ok_or_else(|| anyhow!("Photo mode is not active"))?;
+
+        std::fs::create_dir_all(path.parent().unwrap_or(&path))?;
+        Screenshot::new(&path).capture(compound);
+
+        Ok(path)
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use compound::{Compound, Entity};
+use photon::renderer::draw_order::DrawOrder;
+
+/// Bitmask of the render layers an entity belongs to.
+///
+/// Cameras only draw models and receive lights that share at least one layer
+/// with them. Entities without a `RenderLayers` component are on layer 0 only,
+/// so everything behaves as before until layers are assigned.
+///
+/// # Example
+/// ```ignore
+/// const WORLD: u32 = 0;
+/// const VIEWMODEL: u32 = 1;
+///
+/// // A weapon that only the first person camera draws
+/// ecs.spawn((weapon_model, Transform3D::default(), RenderLayers::layer(VIEWMODEL)));
+///
+/// // The first person camera draws both the world and the weapon
+/// ecs.spawn((
+///     Camera3DDescriptor::default(),
+///     Transform3D::default(),
+///     RenderLayers::layer(WORLD).with(VIEWMODEL),
+/// ));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RenderLayers(u32);
+
+impl Default for RenderLayers {
+    fn default() -> Self {
+        Self::layer(0)
+    }
+}
+
+impl RenderLayers {
+    /// The number of layers available
+    pub const TOTAL_LAYERS: u32 = u32::BITS;
+
+    /// Creates layers containing only `layer`
+    pub const fn layer(layer: u32) -> Self {
+        Self(1 << (layer % Self::TOTAL_LAYERS))
+    }
+
+    /// Creates layers containing every layer
+    pub const fn all() -> Self {
+        Self(u32::MAX)
+    }
+
+    /// Creates layers containing no layers, entities on no layers are never drawn
+    pub const fn none() -> Self {
+        Self(0)
+    }
+
+    /// Creates layers from a raw bitmask
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// Returns the raw bitmask
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns these layers with `layer` added
+    pub const fn with(self, layer: u32) -> Self {
+        Self(self.0 | Self::layer(layer).0)
+    }
+
+    /// Returns these layers with `layer` removed
+    pub const fn without(self, layer: u32) -> Self {
+        Self(self.0 & !Self::layer(layer).0)
+    }
+
+    /// Returns whether `layer` is one of these layers
+    pub const fn contains(&self, layer: u32) -> bool {
+        self.0 & Self::layer(layer).0 != 0
+    }
+
+    /// Returns whether these layers share at least one layer with `other`
+    pub const fn intersects(&self, other: &RenderLayers) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+/// Returns the `DrawOrder` of every entity that has one, the blended draws of
+/// every other entity are drawn in the default order
+pub(crate) fn draw_orders(compound: &Compound) -> HashMap<Entity, DrawOrder> {
+    let mut draw_orders = HashMap::new();
+    compound.iter_mol(|entity, draw_order: &DrawOrder| {
+        draw_orders.insert(entity, *draw_order);
+    });
+
+    draw_orders
+}
@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+use compound::Compound;
+use photon::renderer::Renderer;
+
+use crate::Model;
+
+/// Draws a tinted silhouette of the entity's model wherever it is hidden
+/// behind other geometry, so the player of a third person camera or the
+/// selected units of an RTS stay visible behind walls.
+///
+/// Only the parts of the model hidden behind something else are tinted, the
+/// model never shows through itself. Removing the molecule removes the
+/// silhouette.
+///
+/// # Example
+/// ```ignore
+/// // Outline the selected units in green while they are behind buildings
+/// for unit in selected_units {
+///     compound.add_molecule(unit, Silhouette::new([0.2, 1.0, 0.4, 0.6]));
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Silhouette {
+    /// Color of the silhouette, the alpha is how strongly it covers the geometry in front
+    pub color: [f32; 4],
+}
+
+impl Silhouette {
+    pub fn new(color: [f32; 4]) -> Self {
+        Self { color }
+    }
+}
+
+impl Default for Silhouette {
+    fn default() -> Self {
+        Self::new([0.3, 0.7, 1.0, 0.6])
+    }
+}
+
+/// Uploads the silhouette of every model whose `Silhouette` changed and
+/// turns the silhouette pass on while any model has one
+pub(crate) fn update_silhouettes(compound: &Compound, photon: &mut Renderer) {
+    let mut silhouettes = HashMap::new();
+    compound.iter_mol(|entity, silhouette: &Silhouette| {
+        silhouettes.insert(entity, silhouette.color);
+    });
+
+    let mut any_silhouettes = false;
+    compound.iter_mut_mol_unmod(|entity, model: &mut Model| {
+        let color = silhouettes.get(&entity).copied();
+
+        if model.silhouette() != color {
+            model.set_silhouette(color);
+        }
+
+        any_silhouettes |= color.is_some();
+    });
+
+    photon.set_silhouettes(any_silhouettes);
+}
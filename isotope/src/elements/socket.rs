@@ -0,0 +1,267 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use cgmath::{Quaternion, Rotation, Vector3, Zero};
+use compound::{Compound, Entity};
+use log::warn;
+
+use crate::Transform3D;
+
+/// A named place on a skeleton or model, relative to the transform of the
+/// entity, such as a hand or the top of the head.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Socket {
+    pub local_position: Vector3<f32>,
+    pub local_rotation: Quaternion<f32>,
+}
+
+/// Component with the named sockets of an entity that other entities can be
+/// attached to with `AttachedTo`.
+///
+/// The sockets follow the bones they belong to by being moved with
+/// `set_socket` by whatever plays the clips of the entity's `Animator`,
+/// attached entities pick up the new place on the same tick.
+///
+/// # Example
+/// ```ignore
+/// let sockets = Sockets::new()
+///     .with_socket("right_hand", Vector3::new(0.4, 1.2, 0.3), Quaternion::one())
+///     .with_socket("head", Vector3::new(0.0, 1.8, 0.0), Quaternion::one());
+///
+/// let player = compound.spawn((Transform3D::default(), sockets));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Sockets {
+    sockets: BTreeMap<String, Socket>,
+}
+
+impl Sockets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a socket, replacing the one with the same name
+    pub fn with_socket(
+        mut self,
+        name: impl Into<String>,
+        local_position: Vector3<f32>,
+        local_rotation: Quaternion<f32>,
+    ) -> Self {
+        self.sockets.insert(
+            name.into(),
+            Socket {
+                local_position,
+                local_rotation,
+            },
+        );
+        self
+    }
+
+    pub fn socket(&self, name: &str) -> Option<&Socket> {
+        self.sockets.get(name)
+    }
+
+    /// Moves a socket, such as to the pose of its bone after an animation
+    /// step.
+    ///
+    /// # Returns
+    /// Whether the socket exists
+    pub fn set_socket(
+        &mut self,
+        name: &str,
+        local_position: Vector3<f32>,
+        local_rotation: Quaternion<f32>,
+    ) -> bool {
+        match self.sockets.get_mut(name) {
+            Some(socket) => {
+                socket.local_position = local_position;
+                socket.local_rotation = local_rotation;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Iterates over the names and sockets in name order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Socket)> {
+        self.sockets
+            .iter()
+            .map(|(name, socket)| (name.as_str(), socket))
+    }
+}
+
+/// Component that keeps the transform of an entity attached to another
+/// entity, or to one of its `Sockets`, such as a weapon in a hand or a hat
+/// on a head.
+///
+/// The transform of the entity is overwritten every tick after animation
+/// with the transform of the parent, then the socket, then the offset.
+/// Attachments can be chained, an entity attached to an attached entity
+/// follows both. Until the parent exists with a `Transform3D`, or while the
+/// socket is missing, the entity stays where it is.
+///
+/// # Example
+/// ```ignore
+/// // Hold the sword by its grip instead of its origin
+/// compound.add_molecule(
+///     sword,
+///     AttachedTo::socket(player, "right_hand")
+///         .with_offset(Vector3::new(0.0, -0.1, 0.0), Quaternion::one()),
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachedTo {
+    pub parent: Entity,
+    /// The socket of the parent, `None` to attach to the origin of the parent
+    pub socket: Option<String>,
+    pub local_position: Vector3<f32>,
+    pub local_rotation: Quaternion<f32>,
+}
+
+impl AttachedTo {
+    /// Attaches to the origin of the parent
+    pub fn new(parent: Entity) -> Self {
+        Self {
+            parent,
+            socket: None,
+            local_position: Vector3::zero(),
+            local_rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+        }
+    }
+
+    /// Attaches to a socket of the parent
+    pub fn socket(parent: Entity, socket: impl Into<String>) -> Self {
+        Self {
+            socket: Some(socket.into()),
+            ..Self::new(parent)
+        }
+    }
+
+    /// Sets the offset of the entity from the socket, in the space of the socket
+    pub fn with_offset(
+        mut self,
+        local_position: Vector3<f32>,
+        local_rotation: Quaternion<f32>,
+    ) -> Self {
+        self.local_position = local_position;
+        self.local_rotation = local_rotation;
+        self
+    }
+}
+
+type Pose = (Vector3<f32>, Quaternion<f32>);
+
+/// Moves every entity with an `AttachedTo` to its parent's socket, the
+/// transform propagation step that runs after the animators.
+pub(crate) fn update_attachments(compound: &Compound) {
+    let mut attachments = HashMap::new();
+    compound.iter_mol(|entity, attached_to: &AttachedTo| {
+        attachments.insert(entity, attached_to.clone());
+    });
+
+    if attachments.is_empty() {
+        return;
+    }
+
+    let parents: HashSet<Entity> = attachments
+        .values()
+        .map(|attached_to| attached_to.parent)
+        .collect();
+
+    let mut transforms = HashMap::new();
+    compound.iter_mol(|entity, transform: &Transform3D| {
+        if parents.contains(&entity) {
+            transforms.insert(
+                entity,
+                transform.get_position_and_rotation(|position, rotation| (*position, *rotation)),
+            );
+        }
+    });
+
+    let mut sockets = HashMap::new();
+    compound.iter_mol(|entity, entity_sockets: &Sockets| {
+        if parents.contains(&entity) {
+            sockets.insert(entity, entity_sockets.clone());
+        }
+    });
+
+    let mut resolved = HashMap::new();
+    for entity in attachments.keys() {
+        resolve(
+            *entity,
+            &attachments,
+            &transforms,
+            &sockets,
+            &mut resolved,
+            &mut HashSet::new(),
+        );
+    }
+
+    compound
+        .query::<(&AttachedTo, &mut Transform3D), ()>()
+        .for_each(|entity, (_attached_to, transform)| {
+            if let Some(Some((position, rotation))) = resolved.get(&entity) {
+                transform.position_and_rotation(|transform_position, transform_rotation| {
+                    *transform_position = *position;
+                    *transform_rotation = *rotation;
+                });
+            }
+        });
+}
+
+/// Finds the world transform of an entity, resolving the attachments of its
+/// parents first
+fn resolve(
+    entity: Entity,
+    attachments: &HashMap<Entity, AttachedTo>,
+    transforms: &HashMap<Entity, Pose>,
+    sockets: &HashMap<Entity, Sockets>,
+    resolved: &mut HashMap<Entity, Option<Pose>>,
+    visiting: &mut HashSet<Entity>,
+) -> Option<Pose> {
+    if let Some(pose) = resolved.get(&entity) {
+        return *pose;
+    }
+
+    let Some(attached_to) = attachments.get(&entity) else {
+        return transforms.get(&entity).copied();
+    };
+
+    if !visiting.insert(entity) {
+        warn!(
+            "Entity {} is attached to itself through its parents",
+            entity
+        );
+        resolved.insert(entity, None);
+        return None;
+    }
+
+    let pose = resolve(
+        attached_to.parent,
+        attachments,
+        transforms,
+        sockets,
+        resolved,
+        visiting,
+    )
+    .and_then(|(parent_position, parent_rotation)| {
+        let (socket_position, socket_rotation) = match &attached_to.socket {
+            Some(name) => {
+                let socket = sockets.get(&attached_to.parent)?.socket(name)?;
+                (socket.local_position, socket.local_rotation)
+            }
+            None => (Vector3::zero(), Quaternion::new(1.0, 0.0, 0.0, 0.0)),
+        };
+
+        let rotation = parent_rotation * socket_rotation;
+        Some((
+            parent_position
+                + parent_rotation.rotate_vector(socket_position)
+                + rotation.rotate_vector(attached_to.local_position),
+            rotation * attached_to.local_rotation,
+        ))
+    });
+
+    visiting.remove(&entity);
+    resolved.insert(entity, pose);
+    pose
+}
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+
+use cgmath::{InnerSpace, Vector3};
+use compound::{Compound, Entity};
+use log::warn;
+
+use crate::Transform3D;
+
+// Integer coordinates of a cell of the grid
+type Cell = (i32, i32, i32);
+
+/// Default edge length of a cell of the spatial hash
+pub const DEFAULT_SPATIAL_HASH_CELL_SIZE: f32 = 2.0;
+
+/// Marker component for entities that are put in the `SpatialHash`.
+///
+/// Only entities with both a `SpatialHashed` and a `Transform3D` are hashed,
+/// so the hash stays small even when the world has many static objects.
+pub struct SpatialHashed;
+
+/// A grid of the positions of every `SpatialHashed` entity for fast neighbor
+/// queries, such as for boids, crowd avoidance, or proximity triggers.
+///
+/// The hash is separate from the physics broadphase and only knows about
+/// positions. It is a resource of the compound and the engine rebuilds it after
+/// every physics step, so queries see the positions of the last tick. Cells
+/// should be about the size of the radius most queries use.
+///
+/// # Example
+/// ```ignore
+/// // In IsotopeState::init
+/// compound.insert_resource(SpatialHash::new(2.0));
+/// compound.spawn((Transform3D::default(), SpatialHashed, Boid::default()));
+///
+/// // In IsotopeState::update
+/// compound.resource::<SpatialHash, _>(|spatial_hash| {
+///     compound.iter_mut_mol(|entity, boid: &mut Boid| {
+///         boid.neighbors = spatial_hash.neighbors_within(entity, 5.0);
+///     });
+/// });
+/// ```
+#[derive(Debug)]
+pub struct SpatialHash {
+    cell_size: f32,
+    cells: HashMap<Cell, Vec<(Entity, Vector3<f32>)>>,
+    positions: HashMap<Entity, Vector3<f32>>,
+}
+
+impl Default for SpatialHash {
+    fn default() -> Self {
+        Self::new(DEFAULT_SPATIAL_HASH_CELL_SIZE)
+    }
+}
+
+impl SpatialHash {
+    /// Creates an empty spatial hash.
+    ///
+    /// # Arguments
+    /// * `cell_size` - The edge length of a cell, in world units
+    pub fn new(cell_size: f32) -> Self {
+        let cell_size = if cell_size > 0.0 {
+            cell_size
+        } else {
+            warn!(
+                "Spatial hash cell size must be positive, using {}",
+                DEFAULT_SPATIAL_HASH_CELL_SIZE
+            );
+            DEFAULT_SPATIAL_HASH_CELL_SIZE
+        };
+
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            positions: HashMap::new(),
+        }
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// Returns the number of entities in the hash
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Removes every entity from the hash
+    pub fn clear(&mut self) {
+        // Keep the cells that were used so rebuilding every tick doesn't
+        // reallocate them, and drop the ones that were left empty
+        self.cells.retain(|_, cell| {
+            let used = !cell.is_empty();
+            cell.clear();
+            used
+        });
+        self.positions.clear();
+    }
+
+    /// Adds an entity to the hash, or moves it if it is already in it
+    pub fn insert(&mut self, entity: Entity, position: Vector3<f32>) {
+        if let Some(previous) = self.positions.insert(entity, position)
+            && let Some(cell) = self.cells.get_mut(&self.cell_of(previous))
+        {
+            cell.retain(|(other, _)| *other != entity);
+        }
+
+        self.cells
+            .entry(self.cell_of(position))
+            .or_default()
+            .push((entity, position));
+    }
+
+    /// Returns the position an entity was hashed at
+    pub fn position(&self, entity: Entity) -> Option<Vector3<f32>> {
+        self.positions.get(&entity).copied()
+    }
+
+    /// Finds the entities within a radius of another entity.
+    ///
+    /// # Arguments
+    /// * `entity` - The entity to search around, which is not included in the result
+    /// * `radius` - The distance to search within
+    ///
+    /// # Returns
+    /// The neighboring entities, empty if `entity` is not in the hash
+    pub fn neighbors_within(&self, entity: Entity, radius: f32) -> Vec<Entity> {
+        let Some(position) = self.position(entity) else {
+            return Vec::new();
+        };
+
+        let mut neighbors = Vec::new();
+        self.for_each_within(position, radius, |other, _| {
+            if other != entity {
+                neighbors.push(other);
+            }
+        });
+
+        neighbors
+    }
+
+    /// Finds the entities within a radius of a point.
+    ///
+    /// # Arguments
+    /// * `point` - The point to search around
+    /// * `radius` - The distance to search within
+    ///
+    /// # Returns
+    /// The entities in range
+    pub fn within(&self, point: Vector3<f32>, radius: f32) -> Vec<Entity> {
+        let mut entities = Vec::new();
+        self.for_each_within(point, radius, |entity, _| entities.push(entity));
+
+        entities
+    }
+
+    /// Calls the callback with every entity within a radius of a point and its
+    /// position, without collecting them
+    pub fn for_each_within<F>(&self, point: Vector3<f32>, radius: f32, mut callback: F)
+    where
+        F: FnMut(Entity, Vector3<f32>),
+    {
+        let radius = radius.max(0.0);
+        let radius_squared = radius * radius;
+
+        let min = self.cell_of(point - Vector3::new(radius, radius, radius));
+        let max = self.cell_of(point + Vector3::new(radius, radius, radius));
+
+        for x in min.0..=max.0 {
+            for y in min.1..=max.1 {
+                for z in min.2..=max.2 {
+                    let Some(cell) = self.cells.get(&(x, y, z)) else {
+                        continue;
+                    };
+
+                    for (entity, position) in cell.iter() {
+                        if (position - point).magnitude2() <= radius_squared {
+                            callback(*entity, *position);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn cell_of(&self, position: Vector3<f32>) -> Cell {
+        (
+            (position.x / self.cell_size).floor() as i32,
+            (position.y / self.cell_size).floor() as i32,
+            (position.z / self.cell_size).floor() as i32,
+        )
+    }
+}
+
+/// Rebuilds the spatial hash of a compound from the transforms of its
+/// `SpatialHashed` entities, if it has one.
+pub(crate) fn update_spatial_hash(compound: &Compound) {
+    compound.resource_mut::<SpatialHash, _>(|spatial_hash| {
+        spatial_hash.clear();
+
+        compound.iter_duo(
+            |entity, transform: &Transform3D, _spatial_hashed: &SpatialHashed| {
+                let position = transform.get_position(|position| *position);
+                spatial_hash.insert(entity, position);
+            },
+        );
+    });
+}
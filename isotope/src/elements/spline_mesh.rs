@@ -0,0 +1,818 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::Result;
+use boson::{Boson, BosonBody, BosonObject, ColliderBuilder, RigidBody};
+use cgmath::{InnerSpace, Vector3, Zero};
+use compound::{Compound, Entity, Without};
+use gpu_controller::{Mesh, Vertex};
+use log::{debug, error};
+use matter_vault::SharedMatter;
+
+use crate::{
+    AssetServer, Camera3DDescriptor, Discarded, Model, Transform3D,
+    material::{Material, load_first_material},
+};
+
+// Steps every span of a spline is split into when measuring its length
+const ARC_LENGTH_STEPS: usize = 16;
+
+// Cross sections closer together than this are merged
+const MIN_SPACING: f32 = 1e-3;
+
+// Width of the curbs of `CrossSection::road`
+const CURB_WIDTH: f32 = 0.3;
+
+/// Height of the ground under a point, given the x and z of the point in the
+/// local space of the entity, `None` where there is no ground.
+pub type GroundHeight = Arc<dyn Fn(f32, f32) -> Option<f32> + Send + Sync>;
+
+/// A smooth curve through control points (a Catmull-Rom spline).
+///
+/// # Example
+/// ```ignore
+/// let spline = Spline::new(vec![
+///     [0.0, 0.0, 0.0],
+///     [20.0, 0.0, 10.0],
+///     [40.0, 2.0, 0.0],
+/// ]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Spline {
+    pub points: Vec<[f32; 3]>,
+    /// Whether the spline goes from the last point back to the first
+    pub closed: bool,
+}
+
+impl Spline {
+    pub fn new(points: Vec<[f32; 3]>) -> Self {
+        Self {
+            points,
+            closed: false,
+        }
+    }
+
+    /// Creates a spline that loops back to its first point, such as a race track
+    pub fn closed(points: Vec<[f32; 3]>) -> Self {
+        Self {
+            points,
+            closed: true,
+        }
+    }
+
+    /// Returns the number of spans between control points
+    pub fn num_spans(&self) -> usize {
+        match self.points.len() {
+            0 | 1 => 0,
+            len if self.closed => len,
+            len => len - 1,
+        }
+    }
+
+    /// Returns the point at `t` along the spline.
+    ///
+    /// # Arguments
+    /// * `t` - Where on the spline the point is, every span is one unit long
+    ///   so `t` goes from 0 to `num_spans`
+    pub fn point(&self, t: f32) -> Vector3<f32> {
+        let num_spans = self.num_spans();
+        if num_spans == 0 {
+            return self
+                .points
+                .first()
+                .map(|point| Vector3::from(*point))
+                .unwrap_or(Vector3::zero());
+        }
+
+        let t = t.clamp(0.0, num_spans as f32);
+        let span = (t.floor() as usize).min(num_spans - 1);
+        let t = t - span as f32;
+
+        let control = |index: isize| {
+            let len = self.points.len() as isize;
+            let index = if self.closed {
+                index.rem_euclid(len)
+            } else {
+                index.clamp(0, len - 1)
+            };
+
+            Vector3::from(self.points[index as usize])
+        };
+
+        let span = span as isize;
+        let (p0, p1, p2, p3) = (
+            control(span - 1),
+            control(span),
+            control(span + 1),
+            control(span + 2),
+        );
+
+        (p1 * 2.0
+            + (p2 - p0) * t
+            + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * (t * t)
+            + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * (t * t * t))
+            * 0.5
+    }
+
+    /// Returns the length of the spline
+    pub fn length(&self) -> f32 {
+        self.arc_lengths().last().copied().unwrap_or(0.0)
+    }
+
+    /// Samples points along the spline that are the same distance apart.
+    ///
+    /// # Arguments
+    /// * `spacing` - The largest distance between two points, it is shrunk so
+    ///   the last point lands on the end of the spline
+    ///
+    /// # Returns
+    /// Every point and its distance along the spline, both ends included
+    pub fn sample_evenly(&self, spacing: f32) -> Vec<(Vector3<f32>, f32)> {
+        let num_spans = self.num_spans();
+        if num_spans == 0 {
+            return Vec::new();
+        }
+
+        let arc_lengths = self.arc_lengths();
+        let length = arc_lengths.last().copied().unwrap_or(0.0);
+        let count = ((length / spacing.max(MIN_SPACING)).ceil() as usize).max(1);
+        let step_t = 1.0 / ARC_LENGTH_STEPS as f32;
+
+        let mut step = 0;
+        (0..=count)
+            .map(|index| {
+                let distance = length * index as f32 / count as f32;
+
+                while step + 2 < arc_lengths.len() && arc_lengths[step + 1] < distance {
+                    step += 1;
+                }
+
+                let (start, end) = (arc_lengths[step], arc_lengths[step + 1]);
+                let fraction = if end > start {
+                    ((distance - start) / (end - start)).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+
+                (self.point((step as f32 + fraction) * step_t), distance)
+            })
+            .collect()
+    }
+
+    // Length of the spline up to every step, starting with 0
+    fn arc_lengths(&self) -> Vec<f32> {
+        let steps = self.num_spans() * ARC_LENGTH_STEPS;
+        let mut lengths = Vec::with_capacity(steps + 1);
+        lengths.push(0.0);
+
+        let mut previous = self.point(0.0);
+        for step in 1..=steps {
+            let point = self.point(step as f32 / ARC_LENGTH_STEPS as f32);
+            lengths.push(lengths[step - 1] + (point - previous).magnitude());
+            previous = point;
+        }
+
+        lengths
+    }
+}
+
+/// The shape extruded along a spline.
+///
+/// Points are `[across, up]` from the spline, ordered so a segment going
+/// towards positive `across` faces up. Normals are smoothed between the
+/// segments of the profile, repeating a point makes a hard edge there.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrossSection {
+    pub points: Vec<[f32; 2]>,
+}
+
+impl CrossSection {
+    pub fn new(points: Vec<[f32; 2]>) -> Self {
+        Self { points }
+    }
+
+    /// A flat road `width` wide with a curb of `curb_height` on both sides
+    pub fn road(width: f32, curb_height: f32) -> Self {
+        let half = width * 0.5;
+        let edge = half + CURB_WIDTH;
+
+        Self::new(vec![
+            [-edge, curb_height],
+            [-half, curb_height],
+            [-half, curb_height],
+            [-half, 0.0],
+            [-half, 0.0],
+            [half, 0.0],
+            [half, 0.0],
+            [half, curb_height],
+            [half, curb_height],
+            [edge, curb_height],
+        ])
+    }
+
+    /// A rounded channel `width` wide at the top and `depth` deep in the
+    /// middle, for river beds
+    pub fn channel(width: f32, depth: f32, segments: usize) -> Self {
+        let segments = segments.max(1);
+
+        Self::new(
+            (0..=segments)
+                .map(|index| {
+                    let across = index as f32 / segments as f32 * 2.0 - 1.0;
+                    [across * width * 0.5, -depth * (1.0 - across * across)]
+                })
+                .collect(),
+        )
+    }
+
+    /// A single flat strip `width` wide, for the surface of water
+    pub fn strip(width: f32) -> Self {
+        Self::new(vec![[-width * 0.5, 0.0], [width * 0.5, 0.0]])
+    }
+
+    // Distance along the profile to every point, starting with 0
+    fn distances(&self) -> Vec<f32> {
+        let mut distances = Vec::with_capacity(self.points.len());
+        let mut total = 0.0;
+
+        for (index, point) in self.points.iter().enumerate() {
+            if index > 0 {
+                let previous = self.points[index - 1];
+                total +=
+                    ((point[0] - previous[0]).powi(2) + (point[1] - previous[1]).powi(2)).sqrt();
+            }
+            distances.push(total);
+        }
+
+        distances
+    }
+
+    // Normal of the segment starting at `index` in profile space, `None` if it has no length
+    fn segment_normal(&self, index: usize) -> Option<[f32; 2]> {
+        let (start, end) = (self.points.get(index)?, self.points.get(index + 1)?);
+        let (x, y) = (end[0] - start[0], end[1] - start[1]);
+        let length = (x * x + y * y).sqrt();
+
+        (length > MIN_SPACING).then(|| [-y / length, x / length])
+    }
+
+    // Normal at a point, averaged from the segments on both sides of it
+    fn point_normal(&self, index: usize) -> [f32; 2] {
+        let before = index
+            .checked_sub(1)
+            .and_then(|before| self.segment_normal(before));
+        let after = self.segment_normal(index);
+
+        match (before, after) {
+            (Some(before), Some(after)) => {
+                let (x, y) = (before[0] + after[0], before[1] + after[1]);
+                let length = (x * x + y * y).sqrt();
+                if length > 0.0 {
+                    [x / length, y / length]
+                } else {
+                    after
+                }
+            }
+            (Some(normal), None) | (None, Some(normal)) => normal,
+            (None, None) => [0.0, 1.0],
+        }
+    }
+}
+
+/// How a `SplineMesh` is built
+#[derive(Clone)]
+pub struct SplineMeshSettings {
+    /// Distance between cross sections of the most detailed level of detail
+    pub spacing: f32,
+    /// Distance along the spline the texture is repeated over
+    pub texture_length: f32,
+    /// Camera distance up to which each level of detail is drawn, the first
+    /// is the most detailed and every next one has half as many cross
+    /// sections. The last one is drawn beyond its distance too
+    pub lod_distances: Vec<f32>,
+    /// The ground the spline follows, `None` keeps the heights of the control points
+    pub ground: Option<GroundHeight>,
+    /// Height above the ground the spline is placed at
+    pub ground_offset: f32,
+    /// How far below the surface the colliders reach, `None` for no colliders
+    pub collider_thickness: Option<f32>,
+    /// Distance between cross sections of the colliders
+    pub collider_spacing: f32,
+}
+
+impl Default for SplineMeshSettings {
+    fn default() -> Self {
+        Self {
+            spacing: 1.0,
+            texture_length: 4.0,
+            lod_distances: vec![50.0, 150.0, 400.0],
+            ground: None,
+            ground_offset: 0.05,
+            collider_thickness: None,
+            collider_spacing: 4.0,
+        }
+    }
+}
+
+impl fmt::Debug for SplineMeshSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SplineMeshSettings")
+            .field("spacing", &self.spacing)
+            .field("texture_length", &self.texture_length)
+            .field("lod_distances", &self.lod_distances)
+            .field("ground", &self.ground.is_some())
+            .field("ground_offset", &self.ground_offset)
+            .field("collider_thickness", &self.collider_thickness)
+            .field("collider_spacing", &self.collider_spacing)
+            .finish()
+    }
+}
+
+// A cross section placed on the spline
+struct Frame {
+    position: Vector3<f32>,
+    right: Vector3<f32>,
+    up: Vector3<f32>,
+    distance: f32,
+}
+
+// Places cross sections along the spline, following the ground if there is one
+fn frames(spline: &Spline, spacing: f32, settings: &SplineMeshSettings) -> Vec<Frame> {
+    let mut samples = spline.sample_evenly(spacing);
+
+    if let Some(ground) = &settings.ground {
+        for (position, _) in samples.iter_mut() {
+            if let Some(height) = ground(position.x, position.z) {
+                position.y = height + settings.ground_offset;
+            }
+        }
+    }
+
+    let last = samples.len().saturating_sub(1);
+    let mut previous_right = Vector3::unit_x();
+
+    (0..samples.len())
+        .map(|index| {
+            // The ends of a closed spline are the same point, look past them
+            let (before, after) = match (index, spline.closed) {
+                (0, true) => (samples[last.saturating_sub(1)].0, samples[1.min(last)].0),
+                (index, true) if index == last => (samples[index - 1].0, samples[1].0),
+                (index, _) => (
+                    samples[index.saturating_sub(1)].0,
+                    samples[(index + 1).min(last)].0,
+                ),
+            };
+
+            let tangent = after - before;
+            let right = Vector3::unit_y().cross(tangent);
+            let right = if right.magnitude2() > MIN_SPACING * MIN_SPACING {
+                right.normalize()
+            } else {
+                // Going straight up or down, keep the previous orientation
+                previous_right
+            };
+            previous_right = right;
+
+            let up = if tangent.magnitude2() > 0.0 {
+                tangent.cross(right).normalize()
+            } else {
+                Vector3::unit_y()
+            };
+
+            let (position, distance) = samples[index];
+            Frame {
+                position,
+                right,
+                up,
+                distance,
+            }
+        })
+        .collect()
+}
+
+/// Extrudes a cross section along a spline into a mesh.
+///
+/// The U texture coordinate goes across the profile from 0 to 1 and the V
+/// coordinate along the spline, repeating every `texture_length`.
+///
+/// # Arguments
+/// * `spline` - The spline to follow
+/// * `profile` - The shape to extrude
+/// * `spacing` - The distance between cross sections
+/// * `settings` - The texture length and the ground to follow, the levels
+///   of detail and colliders are not used
+///
+/// # Returns
+/// The vertices and triangle indices of the mesh, empty if the spline has
+/// fewer than two points
+pub fn extrude_along_spline(
+    spline: &Spline,
+    profile: &CrossSection,
+    spacing: f32,
+    settings: &SplineMeshSettings,
+) -> (Vec<Vertex>, Vec<u32>) {
+    let frames = frames(spline, spacing, settings);
+    let num_points = profile.points.len();
+    if frames.len() < 2 || num_points < 2 {
+        return (Vec::new(), Vec::new());
+    }
+
+    let distances = profile.distances();
+    let profile_length = distances.last().copied().unwrap_or(0.0).max(MIN_SPACING);
+    let normals = (0..num_points)
+        .map(|index| profile.point_normal(index))
+        .collect::<Vec<_>>();
+
+    let mut vertices = Vec::with_capacity(frames.len() * num_points);
+    for frame in frames.iter() {
+        let v = frame.distance / settings.texture_length.max(MIN_SPACING);
+
+        for (index, point) in profile.points.iter().enumerate() {
+            let normal = frame.right * normals[index][0] + frame.up * normals[index][1];
+
+            vertices.push(Vertex {
+                position: (frame.position + frame.right * point[0] + frame.up * point[1]).into(),
+                uv_coord: [distances[index] / profile_length, v],
+                normal_vec: normal.normalize().into(),
+            });
+        }
+    }
+
+    let mut indices = Vec::with_capacity((frames.len() - 1) * (num_points - 1) * 6);
+    for ring in 0..frames.len() as u32 - 1 {
+        for index in 0..num_points as u32 - 1 {
+            // Repeated points only split the normals
+            if profile.segment_normal(index as usize).is_none() {
+                continue;
+            }
+
+            let a = ring * num_points as u32 + index;
+            let b = a + num_points as u32;
+            indices.extend_from_slice(&[a, b, a + 1, a + 1, b, b + 1]);
+        }
+    }
+
+    (vertices, indices)
+}
+
+/// Builds the convex pieces the colliders of an extruded spline are made of.
+///
+/// Every segment of the profile between two cross sections becomes a slab
+/// that reaches `thickness` below the surface, so curbs and channels keep
+/// their shape.
+///
+/// # Returns
+/// The points of every piece in the same space as the spline
+pub fn spline_collider_hulls(
+    spline: &Spline,
+    profile: &CrossSection,
+    thickness: f32,
+    settings: &SplineMeshSettings,
+) -> Vec<Vec<[f32; 3]>> {
+    let frames = frames(spline, settings.collider_spacing, settings);
+    let mut hulls = Vec::new();
+
+    for pair in frames.windows(2) {
+        for index in 0..profile.points.len().saturating_sub(1) {
+            if profile.segment_normal(index).is_none() {
+                continue;
+            }
+
+            let mut hull = Vec::with_capacity(8);
+            for frame in pair {
+                for point in &profile.points[index..index + 2] {
+                    let surface = frame.position + frame.right * point[0] + frame.up * point[1];
+                    hull.push(surface.into());
+                    hull.push((surface - frame.up * thickness).into());
+                }
+            }
+            hulls.push(hull);
+        }
+    }
+
+    hulls
+}
+
+/// Generates a mesh by extruding a cross section along a spline, for roads,
+/// rivers, fences and pipes.
+///
+/// The engine builds the mesh when the component is added and again after
+/// the spline, profile or settings are changed through their accessors, so
+/// splines can be edited while the game runs. A level of detail is picked
+/// every tick from the distance to the closest camera and drawn as the
+/// `Model` of the entity.
+///
+/// When the settings have a collider thickness, static bodies matching the
+/// surface are spawned where the entity is when the mesh is built, they do
+/// not follow the entity if it moves afterwards.
+///
+/// # Example
+/// ```ignore
+/// compound.spawn((
+///     Transform3D::default(),
+///     SplineMesh::new(
+///         Spline::new(vec![[0.0, 0.0, 0.0], [30.0, 0.0, 20.0], [60.0, 0.0, 0.0]]),
+///         CrossSection::road(6.0, 0.15),
+///         SplineMeshSettings {
+///             ground: Some(Arc::new(|x, z| Some(terrain.height_at(x, z)))),
+///             collider_thickness: Some(0.5),
+///             ..Default::default()
+///         },
+///     )
+///     .with_material("game://materials/asphalt.mtl"),
+/// ));
+/// ```
+pub struct SplineMesh {
+    spline: Spline,
+    profile: CrossSection,
+    settings: SplineMeshSettings,
+    material_path: Option<String>,
+
+    material: Option<SharedMatter<Material>>,
+    meshes: Vec<SharedMatter<Mesh>>,
+    // Local space bounds of the most detailed level of detail
+    bounds: (Vector3<f32>, Vector3<f32>),
+    lod: Option<usize>,
+    colliders: Vec<(Entity, BosonObject)>,
+    built: bool,
+}
+
+impl SplineMesh {
+    pub fn new(spline: Spline, profile: CrossSection, settings: SplineMeshSettings) -> Self {
+        Self {
+            spline,
+            profile,
+            settings,
+            material_path: None,
+            material: None,
+            meshes: Vec::new(),
+            bounds: (Vector3::zero(), Vector3::zero()),
+            lod: None,
+            colliders: Vec::new(),
+            built: false,
+        }
+    }
+
+    /// Draws the mesh with the first material of an MTL file instead of the error material
+    pub fn with_material(mut self, path: impl Into<String>) -> Self {
+        self.material_path = Some(path.into());
+        self
+    }
+
+    /// Provides mutable access to the spline, the mesh is rebuilt afterwards.
+    ///
+    /// # Arguments
+    /// * `callback` - Function that receives a mutable reference to the spline
+    ///
+    /// # Returns
+    /// The return value of the callback function
+    pub fn spline<F, R>(&mut self, callback: F) -> R
+    where
+        F: FnOnce(&mut Spline) -> R,
+    {
+        self.built = false;
+        callback(&mut self.spline)
+    }
+
+    /// Provides mutable access to the cross section, the mesh is rebuilt afterwards.
+    ///
+    /// # Arguments
+    /// * `callback` - Function that receives a mutable reference to the cross section
+    ///
+    /// # Returns
+    /// The return value of the callback function
+    pub fn profile<F, R>(&mut self, callback: F) -> R
+    where
+        F: FnOnce(&mut CrossSection) -> R,
+    {
+        self.built = false;
+        callback(&mut self.profile)
+    }
+
+    /// Provides mutable access to the settings, the mesh is rebuilt afterwards.
+    ///
+    /// # Arguments
+    /// * `callback` - Function that receives a mutable reference to the settings
+    ///
+    /// # Returns
+    /// The return value of the callback function
+    pub fn settings<F, R>(&mut self, callback: F) -> R
+    where
+        F: FnOnce(&mut SplineMeshSettings) -> R,
+    {
+        self.built = false;
+        callback(&mut self.settings)
+    }
+
+    /// Returns the level of detail being drawn, `None` until the mesh is built
+    pub fn lod(&self) -> Option<usize> {
+        self.lod
+    }
+
+    // Builds the mesh of every level of detail
+    fn build(&mut self, entity: Entity, asset_server: &AssetServer) -> Result<()> {
+        self.built = true;
+        self.lod = None;
+        self.meshes.clear();
+
+        if self.material.is_none() {
+            self.material = Some(load_first_material(
+                self.material_path.as_deref(),
+                asset_server,
+            )?);
+        }
+
+        for lod in 0..self.settings.lod_distances.len().max(1) {
+            let spacing = self.settings.spacing * 2.0_f32.powi(lod as i32);
+            let (vertices, indices) =
+                extrude_along_spline(&self.spline, &self.profile, spacing, &self.settings);
+            if indices.is_empty() {
+                break;
+            }
+
+            if lod == 0 {
+                self.bounds = vertices.iter().fold(
+                    (
+                        Vector3::from(vertices[0].position),
+                        Vector3::from(vertices[0].position),
+                    ),
+                    |(min, max), vertex| {
+                        let [x, y, z] = vertex.position;
+                        (
+                            Vector3::new(min.x.min(x), min.y.min(y), min.z.min(z)),
+                            Vector3::new(max.x.max(x), max.y.max(y), max.z.max(z)),
+                        )
+                    },
+                );
+            }
+
+            // Not shared through the asset server so edited splines free their old meshes
+            self.meshes.push(SharedMatter::new(Mesh::new(
+                asset_server.gpu_controller.clone(),
+                format!("Spline Mesh {} LOD {}", entity, lod),
+                &vertices,
+                &indices,
+            )));
+        }
+
+        debug!(
+            "Built {} levels of detail for spline mesh {}",
+            self.meshes.len(),
+            entity
+        );
+        Ok(())
+    }
+
+    // Picks the level of detail for a camera at `distance` from the mesh
+    fn select_lod(&self, distance: f32) -> usize {
+        self.settings
+            .lod_distances
+            .iter()
+            .position(|lod_distance| distance <= *lod_distance)
+            .unwrap_or(usize::MAX)
+            .min(self.meshes.len().saturating_sub(1))
+    }
+}
+
+/// Builds the spline meshes that changed, swaps the level of detail of every
+/// spline mesh for the closest camera and replaces their colliders
+pub(crate) fn update_spline_meshes(
+    compound: &Compound,
+    asset_server: &AssetServer,
+    boson: &RwLock<Boson>,
+) {
+    let mut cameras = Vec::new();
+    compound
+        .query::<(&Camera3DDescriptor, &Transform3D), Without<Discarded>>()
+        .for_each(|_entity, (_, transform)| {
+            cameras.push(transform.get_position(|position| *position));
+        });
+
+    let mut models = Vec::new();
+    let mut retired = Vec::new();
+    let mut new_colliders = Vec::new();
+
+    compound
+        .query::<(&mut SplineMesh, &Transform3D), Without<Discarded>>()
+        .unmod()
+        .for_each(|entity, (spline_mesh, transform)| {
+            let rebuilt = !spline_mesh.built;
+            if rebuilt {
+                retired.append(&mut spline_mesh.colliders);
+
+                if let Err(err) = spline_mesh.build(entity, asset_server) {
+                    error!("Failed to build spline mesh {}: {}", entity, err);
+                }
+
+                if let Some(thickness) = spline_mesh.settings.collider_thickness {
+                    let hulls = spline_collider_hulls(
+                        &spline_mesh.spline,
+                        &spline_mesh.profile,
+                        thickness,
+                        &spline_mesh.settings,
+                    );
+                    new_colliders.push((entity, *transform, hulls));
+                }
+            }
+
+            let (Some(material), false) = (&spline_mesh.material, spline_mesh.meshes.is_empty())
+            else {
+                return;
+            };
+
+            // Distance from the closest camera to the bounds of the mesh
+            let (min, max) = spline_mesh.bounds;
+            let distance = transform.get_position_and_rotation(|position, rotation| {
+                cameras
+                    .iter()
+                    .map(|camera| {
+                        let local = rotation.conjugate() * (camera - position);
+                        let outside = Vector3::new(
+                            (min.x - local.x).max(local.x - max.x).max(0.0),
+                            (min.y - local.y).max(local.y - max.y).max(0.0),
+                            (min.z - local.z).max(local.z - max.z).max(0.0),
+                        );
+                        outside.magnitude()
+                    })
+                    .fold(None, |closest: Option<f32>, distance| {
+                        Some(closest.map_or(distance, |closest| closest.min(distance)))
+                    })
+                    .unwrap_or(0.0)
+            });
+
+            let lod = spline_mesh.select_lod(distance);
+            if !rebuilt && spline_mesh.lod == Some(lod) {
+                return;
+            }
+
+            match Model::from_meshes(
+                vec![(Some(0), spline_mesh.meshes[lod].clone())],
+                vec![material.clone()],
+                asset_server,
+                None,
+            ) {
+                Ok(mut model) => {
+                    model.set_transform(transform);
+                    models.push((entity, model));
+                    spline_mesh.lod = Some(lod);
+                }
+                Err(err) => error!("Failed to create spline mesh model: {}", err),
+            }
+        });
+
+    for (entity, model) in models {
+        compound.add_molecule(entity, model);
+    }
+
+    if !retired.is_empty() {
+        match boson.write() {
+            Ok(mut boson) => {
+                for (_, boson_object) in retired.iter() {
+                    boson.remove_object(boson_object);
+                }
+            }
+            Err(err) => error!("Failed to remove spline colliders from boson: {}", err),
+        }
+
+        for (entity, _) in retired {
+            compound.add_molecule(entity, Discarded);
+        }
+    }
+
+    if new_colliders.is_empty() {
+        return;
+    }
+
+    let mut spawned = HashMap::new();
+    for (entity, transform, hulls) in new_colliders {
+        let colliders = hulls
+            .iter()
+            .map(|hull| {
+                // Immovable bodies whose shape is the hull, placed like the spline mesh
+                let boson_object = RigidBody::new(0.0, ColliderBuilder::FromModelConvexHull);
+                boson_object.modify_body(|body| {
+                    if let BosonBody::RigidBody(rigid_body) = body {
+                        rigid_body.build_collider_from_points(hull);
+                    }
+                });
+
+                let collider = compound.spawn((transform, boson_object.clone()));
+                (collider, boson_object)
+            })
+            .collect::<Vec<_>>();
+
+        spawned.insert(entity, colliders);
+    }
+
+    compound
+        .query::<(&mut SplineMesh,), ()>()
+        .unmod()
+        .for_each(|entity, (spline_mesh,)| {
+            if let Some(colliders) = spawned.remove(&entity) {
+                spline_mesh.colliders = colliders;
+            }
+        });
+}
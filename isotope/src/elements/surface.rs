@@ -0,0 +1,428 @@
+use std::{collections::HashMap, sync::Arc};
+
+use boson::BosonObject;
+use cgmath::{Quaternion, Vector3};
+use compound::{Compound, Entity};
+
+use crate::{Model, SoundEvent, Transform3D, VfxEffect, VisualEffect, physics::PhysicsImpacts};
+
+/// Seconds the emitters of an impact effect spawn particles for
+const IMPACT_EFFECT_BURST: f32 = 0.1;
+
+/// Seconds an impact effect is kept for its particles to die out
+const DEFAULT_IMPACT_EFFECT_LIFETIME: f32 = 2.0;
+
+/// Component naming what an entity is made of, such as concrete or metal,
+/// which picks how impacts and footsteps on it look and sound.
+///
+/// Entities without one use the `surface` statement of the first material of
+/// their model that has one, and the default response of `SurfaceResponses`
+/// otherwise.
+///
+/// # Example
+/// ```ignore
+/// compound.add_molecule(floor, SurfaceType::new("concrete"));
+///
+/// // Or in the material library of the model
+/// // newmtl Floor
+/// // surface concrete
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SurfaceType(pub String);
+
+impl SurfaceType {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+}
+
+/// How a surface answers being hit or stepped on.
+///
+/// The engine spawns the particles and makes the sound heard by
+/// `Perceiver`s, the sound and decal are passed on with every `SurfaceHit`
+/// for gameplay to play and place.
+#[derive(Debug, Clone)]
+pub struct SurfaceResponse {
+    /// The sound to play, such as the path of an audio file
+    pub sound: Option<String>,
+    /// Distance the sound can be heard from by perceivers, 0 to keep it quiet
+    pub loudness: f32,
+    /// Particles spawned where the surface was hit, +Y turned along the normal
+    pub particles: Option<Arc<VfxEffect>>,
+    /// The decal to place, such as the path of a texture
+    pub decal: Option<String>,
+    /// Slowest impact the surface answers, footsteps are always answered
+    pub min_impact_speed: f32,
+}
+
+impl Default for SurfaceResponse {
+    fn default() -> Self {
+        Self {
+            sound: None,
+            loudness: 0.0,
+            particles: None,
+            decal: None,
+            min_impact_speed: 1.0,
+        }
+    }
+}
+
+impl SurfaceResponse {
+    pub fn with_sound(mut self, sound: impl Into<String>, loudness: f32) -> Self {
+        self.sound = Some(sound.into());
+        self.loudness = loudness;
+        self
+    }
+
+    pub fn with_particles(mut self, particles: Arc<VfxEffect>) -> Self {
+        self.particles = Some(particles);
+        self
+    }
+
+    pub fn with_decal(mut self, decal: impl Into<String>) -> Self {
+        self.decal = Some(decal.into());
+        self
+    }
+
+    pub fn with_min_impact_speed(mut self, min_impact_speed: f32) -> Self {
+        self.min_impact_speed = min_impact_speed;
+        self
+    }
+}
+
+/// Resource with the response of every surface type, nothing answers hits
+/// in worlds without one.
+///
+/// # Example
+/// ```ignore
+/// compound.insert_resource(
+///     SurfaceResponses::new()
+///         .with_response(
+///             "metal",
+///             SurfaceResponse::default()
+///                 .with_sound("game://sounds/clang.ogg", 30.0)
+///                 .with_particles(VfxEffect::load(assets, "game://vfx/sparks.vfx")?)
+///                 .with_decal("game://decals/dent.png"),
+///         )
+///         .with_default(SurfaceResponse::default().with_sound("game://sounds/thud.ogg", 10.0)),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct SurfaceResponses {
+    responses: HashMap<String, SurfaceResponse>,
+    default: Option<SurfaceResponse>,
+    /// Seconds the particles of a response are kept for before they are removed
+    pub effect_lifetime: f32,
+}
+
+impl Default for SurfaceResponses {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SurfaceResponses {
+    pub fn new() -> Self {
+        Self {
+            responses: HashMap::new(),
+            default: None,
+            effect_lifetime: DEFAULT_IMPACT_EFFECT_LIFETIME,
+        }
+    }
+
+    pub fn with_response(mut self, surface: impl Into<String>, response: SurfaceResponse) -> Self {
+        self.responses.insert(surface.into(), response);
+        self
+    }
+
+    /// Sets the response of surfaces without a response of their own
+    pub fn with_default(mut self, response: SurfaceResponse) -> Self {
+        self.default = Some(response);
+        self
+    }
+
+    /// Returns the response of a surface, or the default response
+    pub fn get(&self, surface: Option<&SurfaceType>) -> Option<&SurfaceResponse> {
+        surface
+            .and_then(|surface| self.responses.get(&surface.0))
+            .or(self.default.as_ref())
+    }
+}
+
+/// A step of a character, emitted by the code that moves it so the ground
+/// answers the way it does to impacts.
+///
+/// # Example
+/// ```ignore
+/// // When a foot of the walk animation touches down
+/// Footstep::new(foot_position).on(ground).from_source(player).emit(compound);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Footstep {
+    pub position: Vector3<f32>,
+    /// The entity stepped on, the default response answers without one
+    pub ground: Option<Entity>,
+    /// The entity that stepped
+    pub source: Option<Entity>,
+}
+
+impl Footstep {
+    pub fn new(position: Vector3<f32>) -> Self {
+        Self {
+            position,
+            ground: None,
+            source: None,
+        }
+    }
+
+    pub fn on(mut self, ground: Entity) -> Self {
+        self.ground = Some(ground);
+        self
+    }
+
+    pub fn from_source(mut self, source: Entity) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Queues the footstep to be answered by the ground on the next tick
+    pub fn emit(self, compound: &Compound) {
+        if compound
+            .resource_mut::<PendingFootsteps, _>(|footsteps| footsteps.0.push(self))
+            .is_none()
+        {
+            compound.insert_resource(PendingFootsteps(vec![self]));
+        }
+    }
+}
+
+// Footsteps emitted since surfaces last answered
+pub(crate) struct PendingFootsteps(Vec<Footstep>);
+
+/// What made a `SurfaceHit`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SurfaceHitKind {
+    /// A physics body hit the surface
+    Impact {
+        /// The entity of the body that hit the surface
+        other: Option<Entity>,
+        /// How fast the bodies closed in on each other
+        speed: f32,
+    },
+    /// A character stepped on the surface
+    Footstep {
+        /// The entity that stepped
+        source: Option<Entity>,
+    },
+}
+
+/// A surface that answered a hit, for gameplay to play the sound and place
+/// the decal of its response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SurfaceHit {
+    pub kind: SurfaceHitKind,
+    /// The entity that was hit
+    pub entity: Option<Entity>,
+    pub surface: Option<SurfaceType>,
+    pub position: Vector3<f32>,
+    /// Direction out of the surface
+    pub normal: Vector3<f32>,
+    pub sound: Option<String>,
+    pub decal: Option<String>,
+}
+
+/// Resource with the surfaces that answered hits since gameplay last drained them.
+///
+/// # Example
+/// ```ignore
+/// // In IsotopeState::update
+/// for hit in compound.resource_mut(SurfaceHits::drain).unwrap_or_default() {
+///     if let Some(sound) = hit.sound {
+///         audio.play_at(sound, hit.position);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SurfaceHits(Vec<SurfaceHit>);
+
+impl SurfaceHits {
+    /// Takes the hits since the last time they were drained
+    pub fn drain(&mut self) -> Vec<SurfaceHit> {
+        std::mem::take(&mut self.0)
+    }
+}
+
+/// Component of the particles spawned by a surface response, removed once
+/// they died out.
+pub(crate) struct SurfaceEffect {
+    age: f32,
+    lifetime: f32,
+}
+
+/// Answers the impacts of physics and the footsteps of characters with the
+/// responses of the surfaces they hit, and removes the particles of earlier
+/// responses that died out
+pub(crate) fn update_surfaces(compound: &Compound, dt: f32) {
+    expire_surface_effects(compound, dt);
+
+    let impacts = compound
+        .resource(|impacts: &PhysicsImpacts| impacts.0.clone())
+        .unwrap_or_default();
+    let footsteps = compound
+        .remove_resource::<PendingFootsteps>()
+        .map(|footsteps| footsteps.0)
+        .unwrap_or_default();
+
+    let Some(responses) = compound.resource(|responses: &SurfaceResponses| responses.clone())
+    else {
+        return;
+    };
+
+    if impacts.is_empty() && footsteps.is_empty() {
+        return;
+    }
+
+    let mut entities = HashMap::new();
+    compound.iter_mol(|entity, object: &BosonObject| {
+        entities.insert(object.id(), entity);
+    });
+    let surfaces = surfaces(compound);
+    let surface_of =
+        |entity: Option<Entity>| entity.and_then(|entity| surfaces.get(&entity).cloned());
+
+    let mut hits = Vec::new();
+    for impact in impacts {
+        let a = entities.get(&impact.a.id()).copied();
+        let b = entities.get(&impact.b.id()).copied();
+        let (surface_a, surface_b) = (surface_of(a), surface_of(b));
+        let speed = impact.speed as f32;
+        let position = to_f32(impact.point);
+        let normal = to_f32(impact.normal);
+
+        // Both sides answer when they know what they are made of, the default answers once otherwise
+        let mut sides = Vec::new();
+        if surface_a.is_some() || surface_b.is_none() {
+            sides.push((a, surface_a, b, normal));
+        }
+        if surface_b.is_some() {
+            sides.push((b, surface_b, a, -normal));
+        }
+
+        for (entity, surface, other, normal) in sides {
+            if let Some(response) = responses.get(surface.as_ref())
+                && speed >= response.min_impact_speed
+            {
+                hits.push((
+                    SurfaceHit {
+                        kind: SurfaceHitKind::Impact { other, speed },
+                        entity,
+                        surface,
+                        position,
+                        normal,
+                        sound: response.sound.clone(),
+                        decal: response.decal.clone(),
+                    },
+                    response,
+                    other,
+                ));
+            }
+        }
+    }
+
+    for footstep in footsteps {
+        let surface = surface_of(footstep.ground);
+
+        if let Some(response) = responses.get(surface.as_ref()) {
+            hits.push((
+                SurfaceHit {
+                    kind: SurfaceHitKind::Footstep {
+                        source: footstep.source,
+                    },
+                    entity: footstep.ground,
+                    surface,
+                    position: footstep.position,
+                    normal: Vector3::unit_y(),
+                    sound: response.sound.clone(),
+                    decal: response.decal.clone(),
+                },
+                response,
+                footstep.source,
+            ));
+        }
+    }
+
+    let mut answered = Vec::with_capacity(hits.len());
+    for (hit, response, source) in hits {
+        if response.sound.is_some() && response.loudness > 0.0 {
+            let sound = SoundEvent::new(hit.position, response.loudness);
+            match source {
+                Some(source) => sound.from_source(source).emit(compound),
+                None => sound.emit(compound),
+            }
+        }
+
+        if let Some(particles) = &response.particles {
+            let rotation = Quaternion::from_arc(Vector3::unit_y(), hit.normal, None);
+            compound.spawn((
+                Transform3D::new(hit.position, rotation),
+                VisualEffect::new(particles.clone()),
+                SurfaceEffect {
+                    age: 0.0,
+                    lifetime: responses.effect_lifetime,
+                },
+            ));
+        }
+
+        answered.push(hit);
+    }
+
+    if compound.has_resource::<SurfaceHits>() {
+        compound.resource_mut(|surface_hits: &mut SurfaceHits| surface_hits.0.extend(answered));
+    } else {
+        compound.insert_resource(SurfaceHits(answered));
+    }
+}
+
+/// Returns what every entity that knows is made of
+fn surfaces(compound: &Compound) -> HashMap<Entity, SurfaceType> {
+    let mut surfaces = HashMap::new();
+    compound.iter_mol(|entity, model: &Model| {
+        if let Some(surface) = model.surface() {
+            surfaces.insert(entity, surface);
+        }
+    });
+
+    // The surface of the entity itself wins over the one of its model
+    compound.iter_mol(|entity, surface: &SurfaceType| {
+        surfaces.insert(entity, surface.clone());
+    });
+
+    surfaces
+}
+
+/// Stops the emitters of surface effects after their burst and removes the
+/// effects whose particles died out
+fn expire_surface_effects(compound: &Compound, dt: f32) {
+    let mut expired = Vec::new();
+
+    compound.iter_mut_duo_unmod(
+        |entity, effect: &mut SurfaceEffect, visual_effect: &mut VisualEffect| {
+            effect.age += dt;
+            visual_effect.emitting = effect.age < IMPACT_EFFECT_BURST;
+
+            if effect.age >= effect.lifetime {
+                expired.push(entity);
+            }
+        },
+    );
+
+    for entity in expired {
+        compound.remove_molecule::<SurfaceEffect>(entity);
+        compound.remove_molecule::<VisualEffect>(entity);
+        compound.remove_molecule::<Transform3D>(entity);
+    }
+}
+
+fn to_f32(vector: Vector3<f64>) -> Vector3<f32> {
+    vector.cast().unwrap_or(Vector3::new(0.0, 0.0, 0.0))
+}
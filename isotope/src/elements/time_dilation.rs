@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+
+use boson::{BosonBody, BosonObject};
+use cgmath::{MetricSpace, Vector3};
+use compound::{Compound, Entity};
+
+use crate::Transform3D;
+
+/// A region where time passes at a different speed, such as a stasis field
+/// or a slow motion bubble.
+///
+/// Entities whose transform is inside the sphere around the transform of the
+/// region are given a `TimeScale`, which slows down or speeds up their
+/// physics bodies, animators and particle emitters while everything outside
+/// runs normally. Time blends back to normal speed across the falloff
+/// outside the radius, and overlapping regions multiply. A region never
+/// dilates the entity it is on, so a bubble can follow the player without
+/// slowing them down.
+///
+/// # Example
+/// ```ignore
+/// // Everything near the player moves at a fifth of the speed, the player doesn't
+/// compound.add_molecule(player, TimeDilation::new(0.2, 8.0).with_falloff(2.0));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeDilation {
+    /// How fast time passes inside the region, 1 is normal speed and 0 freezes time
+    pub scale: f32,
+    /// Radius of the sphere time passes at the scale inside
+    pub radius: f32,
+    /// Distance outside the radius over which time blends back to normal speed
+    pub falloff: f32,
+}
+
+impl TimeDilation {
+    pub fn new(scale: f32, radius: f32) -> Self {
+        Self {
+            scale,
+            radius,
+            falloff: 0.0,
+        }
+    }
+
+    pub fn with_falloff(mut self, falloff: f32) -> Self {
+        self.falloff = falloff;
+        self
+    }
+
+    /// Returns how fast time passes at a distance from the center of the region
+    fn scale_at(&self, distance: f32) -> f32 {
+        if distance <= self.radius {
+            return self.scale;
+        }
+
+        if distance >= self.radius + self.falloff {
+            return 1.0;
+        }
+
+        let t = (distance - self.radius) / self.falloff;
+        self.scale + (1.0 - self.scale) * t
+    }
+}
+
+/// How fast time passes for a single entity.
+///
+/// The engine adds one to entities that enter a `TimeDilation` region, and
+/// gameplay can add one to scale an entity by itself. The scale of the
+/// `Time` of the world applies on top of it. Gameplay timers and tweens of
+/// the entity should advance by `scale_delta` to stay in step with its
+/// bodies and animations.
+///
+/// # Example
+/// ```ignore
+/// // A hasted enemy
+/// compound.add_molecule(enemy, TimeScale::new(1.5));
+///
+/// // In a system, a tween that slows down with the enemy
+/// compound.iter_mut_duo(|_entity, tween: &mut Tween, time_scale: &TimeScale| {
+///     tween.advance(time_scale.scale_delta(context.delta_t));
+/// });
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeScale {
+    /// How fast time passes for the entity by itself, 1 is normal speed
+    pub scale: f32,
+
+    dilation: f32,
+}
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl TimeScale {
+    pub fn new(scale: f32) -> Self {
+        Self {
+            scale,
+            dilation: 1.0,
+        }
+    }
+
+    /// Returns how fast the `TimeDilation` regions the entity is in make time pass
+    pub fn dilation(&self) -> f32 {
+        self.dilation
+    }
+
+    /// Returns how fast time passes for the entity, its own scale and the dilation together
+    pub fn get(&self) -> f32 {
+        (self.scale * self.dilation).max(0.0)
+    }
+
+    /// Scales seconds of game time to the seconds that pass for the entity
+    pub fn scale_delta(&self, delta_t: f32) -> f32 {
+        delta_t * self.get()
+    }
+}
+
+/// Returns how fast time passes for every entity with a `TimeScale`
+pub(crate) fn time_scales(compound: &Compound) -> HashMap<Entity, f32> {
+    let mut time_scales = HashMap::new();
+    compound.iter_mol(|entity, time_scale: &TimeScale| {
+        time_scales.insert(entity, time_scale.get());
+    });
+
+    time_scales
+}
+
+/// Finds the entities inside time dilation regions and scales the physics
+/// bodies of every entity with a `TimeScale`
+pub(crate) fn update_time_dilation(compound: &Compound) {
+    let mut regions = Vec::new();
+    compound.iter_duo(|entity, region: &TimeDilation, transform: &Transform3D| {
+        transform.get_position(|position| regions.push((entity, *position, *region)));
+    });
+
+    let mut dilations = HashMap::new();
+    if !regions.is_empty() {
+        compound.iter_mol(|entity, transform: &Transform3D| {
+            let dilation =
+                transform.get_position(|position| dilation_at(&regions, entity, *position));
+
+            if dilation != 1.0 {
+                dilations.insert(entity, dilation);
+            }
+        });
+    }
+
+    compound.iter_mut_mol(|entity, time_scale: &mut TimeScale| {
+        time_scale.dilation = dilations.remove(&entity).unwrap_or(1.0);
+    });
+
+    // Molecules can't be added while iterating over them
+    for (entity, dilation) in dilations {
+        compound.add_molecule(
+            entity,
+            TimeScale {
+                scale: 1.0,
+                dilation,
+            },
+        );
+    }
+
+    compound.iter_duo(|_entity, object: &BosonObject, time_scale: &TimeScale| {
+        let time_scale = time_scale.get() as f64;
+
+        let scaled = object.read_body(|body| match body {
+            BosonBody::RigidBody(rigid_body) => rigid_body.time_scale == time_scale,
+            BosonBody::PointMass(point_mass) => point_mass.time_scale == time_scale,
+            BosonBody::StaticCollider(_) => true,
+        });
+
+        // Only wait on the body when it changed, it is locked for whole physics steps
+        if !scaled {
+            object.modify_body(|body| match body {
+                BosonBody::RigidBody(rigid_body) => rigid_body.time_scale = time_scale,
+                BosonBody::PointMass(point_mass) => point_mass.time_scale = time_scale,
+                BosonBody::StaticCollider(_) => {}
+            });
+        }
+    });
+}
+
+/// Returns how fast time passes at a position, for the regions that aren't on the entity there
+fn dilation_at(
+    regions: &[(Entity, Vector3<f32>, TimeDilation)],
+    entity: Entity,
+    position: Vector3<f32>,
+) -> f32 {
+    regions
+        .iter()
+        .filter(|(region_entity, _, _)| *region_entity != entity)
+        .map(|(_, center, region)| region.scale_at(center.distance(position)))
+        .product()
+}
@@ -0,0 +1,132 @@
+use std::collections::{HashSet, VecDeque};
+
+use cgmath::{InnerSpace, Vector3};
+use compound::Compound;
+use photon::renderer::{
+    Renderer,
+    trails::{TrailPoint, TrailSettings},
+};
+
+use super::{Transform3D, draw_orders};
+
+/// Records where the entity has been and draws a ribbon through it, for
+/// sword slashes and missile trails.
+///
+/// A point is recorded whenever the entity has moved far enough from the last
+/// one, and dropped once it is older than the lifetime of the trail. The
+/// ribbon always reaches the entity's current position.
+///
+/// # Example
+/// ```ignore
+/// // A smoke trail that widens and fades behind a missile
+/// compound.add_molecule(
+///     missile,
+///     Trail::new(TrailSettings {
+///         lifetime: 2.0,
+///         width: (0.1, 0.8),
+///         start_color: [0.9, 0.9, 0.9, 0.8],
+///         end_color: [0.5, 0.5, 0.5, 0.0],
+///         ..Default::default()
+///     }),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct Trail {
+    pub settings: TrailSettings,
+    /// How far the entity moves before another point is recorded
+    pub min_distance: f32,
+    /// Whether new points are recorded, the trail fades out either way
+    pub emitting: bool,
+    points: VecDeque<TrailPoint>,
+}
+
+impl Default for Trail {
+    fn default() -> Self {
+        Self::new(TrailSettings::default())
+    }
+}
+
+impl Trail {
+    pub fn new(settings: TrailSettings) -> Self {
+        Self {
+            settings,
+            min_distance: 0.05,
+            emitting: true,
+            points: VecDeque::new(),
+        }
+    }
+
+    /// Forgets every recorded point, such as after teleporting the entity
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    /// Ages the points by a frame, drops the expired ones and records the position if it moved far enough
+    fn record(&mut self, position: Vector3<f32>, delta_t: f32) {
+        for point in self.points.iter_mut() {
+            point.age += delta_t;
+        }
+
+        while self
+            .points
+            .front()
+            .is_some_and(|point| point.age >= self.settings.lifetime)
+        {
+            self.points.pop_front();
+        }
+
+        if !self.emitting {
+            return;
+        }
+
+        let moved = self.points.back().is_none_or(|point| {
+            (position - Vector3::from(point.position)).magnitude() >= self.min_distance
+        });
+        if moved {
+            self.points.push_back(TrailPoint {
+                position: position.into(),
+                age: 0.0,
+            });
+        }
+
+        while self.points.len() > self.settings.max_points.max(1) as usize {
+            self.points.pop_front();
+        }
+    }
+}
+
+/// Records the positions of every `Trail` and gives their points to the renderer
+pub(crate) fn update_trails(compound: &Compound, photon: &mut Renderer, delta_t: f32) {
+    let mut trails = HashSet::new();
+    let draw_orders = draw_orders(compound);
+
+    compound
+        .query::<(&mut Trail, &Transform3D), ()>()
+        .unmod()
+        .for_each(|entity, (trail, transform)| {
+            let position = transform.get_position(|position| *position);
+            trail.record(position, delta_t);
+
+            // Reach the entity even when it has not moved far enough for a new point
+            let mut points = trail.points.iter().copied().collect::<Vec<_>>();
+            if trail.emitting
+                && points
+                    .last()
+                    .is_some_and(|point| Vector3::from(point.position) != position)
+            {
+                points.push(TrailPoint {
+                    position: position.into(),
+                    age: 0.0,
+                });
+            }
+
+            photon.update_trail(entity, &trail.settings, &points);
+            photon.set_trail_order(
+                entity,
+                draw_orders.get(&entity).copied().unwrap_or_default(),
+            );
+            trails.insert(entity);
+        });
+
+    photon.retain_trails(|id| trails.contains(&id));
+}
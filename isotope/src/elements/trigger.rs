@@ -0,0 +1,104 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use boson::{Boson, BosonObject, TriggerKind};
+use compound::{Compound, Entity};
+use log::error;
+
+/// An entity that went into or out of the sensor of another entity, the
+/// body of a trigger volume made with `RigidBody::new_sensor`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerEvent {
+    Enter {
+        /// The entity of the sensor
+        trigger: Entity,
+        /// The entity that went into the sensor
+        other: Entity,
+    },
+    Exit {
+        /// The entity of the sensor
+        trigger: Entity,
+        /// The entity that left the sensor, or was despawned inside it
+        other: Entity,
+    },
+}
+
+/// Resource with the trigger events since gameplay last drained them, for
+/// pickups, checkpoints and kill zones.
+///
+/// # Example
+/// ```ignore
+/// // In IsotopeState::update
+/// for event in compound.resource_mut(TriggerEvents::drain).unwrap_or_default() {
+///     if let TriggerEvent::Enter { trigger, other } = event
+///         && trigger == kill_zone
+///     {
+///         compound.entity(other).insert(Dead);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TriggerEvents(Vec<TriggerEvent>);
+
+impl TriggerEvents {
+    /// Takes the events since the last time they were drained
+    pub fn drain(&mut self) -> Vec<TriggerEvent> {
+        std::mem::take(&mut self.0)
+    }
+}
+
+// The entities of the bodies in the last tick, so bodies despawned inside a
+// sensor still exit it as the entity they were
+pub(crate) struct TriggerEntities(HashMap<usize, Entity>);
+
+/// Turns the trigger events of physics into `TriggerEvents` of the entities
+/// of the bodies
+pub(crate) fn update_triggers(compound: &Compound, boson: &RwLock<Boson>) {
+    let triggers = match boson.read() {
+        Ok(boson) => boson.drain_triggers(),
+        Err(err) => {
+            error!("Failed to read triggers: {}", err);
+            Vec::new()
+        }
+    };
+
+    let mut entities = HashMap::new();
+    compound.iter_mol(|entity, object: &BosonObject| {
+        entities.insert(object.id(), entity);
+    });
+
+    let previous = compound
+        .remove_resource::<TriggerEntities>()
+        .map(|previous| previous.0)
+        .unwrap_or_default();
+    let entity_of = |object: &BosonObject| {
+        entities
+            .get(&object.id())
+            .or_else(|| previous.get(&object.id()))
+            .copied()
+    };
+
+    let events = triggers
+        .iter()
+        .filter_map(|event| {
+            let trigger = entity_of(&event.sensor)?;
+            let other = entity_of(&event.other)?;
+
+            Some(match event.kind {
+                TriggerKind::Enter => TriggerEvent::Enter { trigger, other },
+                TriggerKind::Exit => TriggerEvent::Exit { trigger, other },
+            })
+        })
+        .collect::<Vec<_>>();
+
+    compound.insert_resource(TriggerEntities(entities));
+
+    if events.is_empty() {
+        return;
+    }
+
+    if compound.has_resource::<TriggerEvents>() {
+        compound.resource_mut(|trigger_events: &mut TriggerEvents| trigger_events.0.extend(events));
+    } else {
+        compound.insert_resource(TriggerEvents(events));
+    }
+}
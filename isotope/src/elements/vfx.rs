@@ -0,0 +1,368 @@
+use std::{fmt, path::Path, sync::Arc};
+
+use anyhow::{Result, anyhow};
+use log::debug;
+use photon::renderer::particles::ParticleCollision;
+
+use crate::AssetServer;
+
+use super::ParticleEmitter;
+
+/// An emitter of a visual effect.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VfxEmitter {
+    pub name: String,
+    /// Where the emitter is from the origin of the entity, rotated with its transform
+    pub offset: [f32; 3],
+    /// How the emitter spawns, moves and draws its particles, whether it is
+    /// emitting is decided by the `VisualEffect`
+    pub emitter: ParticleEmitter,
+}
+
+impl VfxEmitter {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            offset: [0.0; 3],
+            emitter: ParticleEmitter::default(),
+        }
+    }
+}
+
+/// A particle effect made of emitters, loaded from a `.vfx` asset so effects
+/// are tuned without rebuilding the game.
+///
+/// Every emitter is described by three modules. `spawn` keys decide where,
+/// how often and how fast particles are spawned, `update` keys how they move
+/// and collide and `output` keys how they are drawn. Writing an effect with
+/// `to_string` or `save` gives back the same format.
+///
+/// # Example
+/// ```ignore
+/// # game://vfx/impact.vfx
+/// emitter sparks
+/// spawn rate 300
+/// spawn max 256
+/// spawn direction 0 1 0
+/// spawn spread 0.6
+/// spawn speed 4 8
+/// spawn lifetime 0.3 0.6
+/// update gravity 0 -9.81 0
+/// update drag 0.5
+/// update collide depth 0.4 0.2 0.5
+/// output size 0.05 0.01
+/// output color 1 0.8 0.3 1 1 0.2 0 0
+///
+/// # Particles that bend the scene behind them instead of being drawn
+/// emitter heat
+/// spawn rate 10
+/// spawn speed 0.5 1
+/// update gravity 0 0 0
+/// output size 0.5 1
+/// output distortion 0.01
+///
+/// emitter debris
+/// spawn offset 0 0.1 0
+/// spawn rate 20
+/// update collide colliders 0.3 0.5
+/// output mesh game://models/shard.obj
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VfxEffect {
+    pub emitters: Vec<VfxEmitter>,
+}
+
+impl VfxEffect {
+    /// Loads a visual effect from an asset.
+    ///
+    /// # Arguments
+    /// * `asset_server` - The asset server to read the asset from
+    /// * `path` - Path of the `.vfx` asset
+    ///
+    /// # Returns
+    /// The effect, or an error if the asset could not be read or is invalid
+    pub fn load<P>(asset_server: &AssetServer, path: P) -> Result<Arc<Self>>
+    where
+        P: AsRef<Path>,
+    {
+        let source = String::from_utf8(asset_server.read_asset(path)?)?;
+
+        Ok(Arc::new(Self::parse(&source)?))
+    }
+
+    /// Writes the effect to a `.vfx` file on disk
+    pub fn save<P>(&self, path: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        std::fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    /// Parses the contents of a `.vfx` asset
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut effect = Self::default();
+
+        for line in source.lines() {
+            let tokens = line.split_whitespace().collect::<Vec<_>>();
+
+            if tokens.is_empty() || tokens[0].starts_with('#') {
+                continue;
+            }
+
+            if tokens[0] == "emitter" {
+                let name = tokens
+                    .get(1)
+                    .ok_or_else(|| anyhow!("Missing name for emitter"))?;
+                effect.emitters.push(VfxEmitter::new(*name));
+                continue;
+            }
+
+            let emitter = effect
+                .emitters
+                .last_mut()
+                .ok_or_else(|| anyhow!("{} outside of an emitter", tokens[0]))?;
+            let key = tokens
+                .get(1)
+                .copied()
+                .ok_or_else(|| anyhow!("Missing key for {} module", tokens[0]))?;
+            let values = &tokens[2..];
+
+            match tokens[0] {
+                "spawn" => parse_spawn(emitter, key, values)?,
+                "update" => parse_update(emitter, key, values)?,
+                "output" => parse_output(emitter, key, values)?,
+                other => debug!("Ignoring unknown vfx module: {}", other),
+            }
+        }
+
+        Ok(effect)
+    }
+}
+
+/// Parses the floats of a key, erroring if there are not exactly `N`
+fn floats<const N: usize>(key: &str, values: &[&str]) -> Result<[f32; N]> {
+    if values.len() != N {
+        return Err(anyhow!(
+            "Expected {} values for {} but found {}",
+            N,
+            key,
+            values.len()
+        ));
+    }
+
+    let mut floats = [0.0; N];
+    for (float, value) in floats.iter_mut().zip(values) {
+        *float = value.parse()?;
+    }
+
+    Ok(floats)
+}
+
+fn parse_spawn(emitter: &mut VfxEmitter, key: &str, values: &[&str]) -> Result<()> {
+    let settings = &mut emitter.emitter.settings;
+
+    match key {
+        "rate" => settings.rate = floats::<1>(key, values)?[0],
+        "max" => {
+            settings.max_particles = values
+                .first()
+                .ok_or_else(|| anyhow!("Missing value for max"))?
+                .parse()?
+        }
+        "offset" => emitter.offset = floats(key, values)?,
+        "direction" => emitter.emitter.direction = floats(key, values)?,
+        "spread" => settings.spread = floats::<1>(key, values)?[0],
+        "speed" => {
+            let [min, max] = floats(key, values)?;
+            settings.speed = (min, max);
+        }
+        "lifetime" => {
+            let [min, max] = floats(key, values)?;
+            settings.lifetime = (min, max);
+        }
+        _ => debug!("Ignoring unknown vfx spawn key: {}", key),
+    }
+
+    Ok(())
+}
+
+fn parse_update(emitter: &mut VfxEmitter, key: &str, values: &[&str]) -> Result<()> {
+    let settings = &mut emitter.emitter.settings;
+
+    match key {
+        "gravity" => settings.gravity = floats(key, values)?,
+        "drag" => settings.drag = floats::<1>(key, values)?[0],
+        "collide" => {
+            let kill = values.last() == Some(&"kill");
+            let values = values.strip_suffix(&["kill"]).unwrap_or(values);
+
+            settings.collision = match values.first().copied() {
+                Some("none") => ParticleCollision::None,
+                Some("depth") => {
+                    let [restitution, friction, thickness] = floats("collide depth", &values[1..])?;
+                    ParticleCollision::Depth {
+                        restitution,
+                        friction,
+                        thickness,
+                        kill,
+                    }
+                }
+                Some("colliders") => {
+                    let [restitution, friction] = floats("collide colliders", &values[1..])?;
+                    ParticleCollision::Colliders {
+                        restitution,
+                        friction,
+                        kill,
+                    }
+                }
+                other => return Err(anyhow!("Unknown vfx collision: {:?}", other)),
+            };
+        }
+        _ => debug!("Ignoring unknown vfx update key: {}", key),
+    }
+
+    Ok(())
+}
+
+fn parse_output(emitter: &mut VfxEmitter, key: &str, values: &[&str]) -> Result<()> {
+    let settings = &mut emitter.emitter.settings;
+
+    match key {
+        "size" => {
+            let [start, end] = floats(key, values)?;
+            settings.size = (start, end);
+        }
+        "color" => {
+            let [r0, g0, b0, a0, r1, g1, b1, a1] = floats(key, values)?;
+            settings.start_color = [r0, g0, b0, a0];
+            settings.end_color = [r1, g1, b1, a1];
+        }
+        "distortion" => settings.distortion = floats::<1>(key, values)?[0],
+        "billboard" => emitter.emitter.mesh = None,
+        "mesh" => {
+            emitter.emitter.mesh = Some(
+                values
+                    .first()
+                    .ok_or_else(|| anyhow!("Missing path for mesh"))?
+                    .to_string(),
+            )
+        }
+        _ => debug!("Ignoring unknown vfx output key: {}", key),
+    }
+
+    Ok(())
+}
+
+impl fmt::Display for VfxEffect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |values: &[f32]| {
+            values
+                .iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        for (index, vfx_emitter) in self.emitters.iter().enumerate() {
+            let emitter = &vfx_emitter.emitter;
+            let settings = &emitter.settings;
+
+            if index > 0 {
+                writeln!(f)?;
+            }
+
+            writeln!(f, "emitter {}", vfx_emitter.name)?;
+            writeln!(f, "spawn rate {}", settings.rate)?;
+            writeln!(f, "spawn max {}", settings.max_particles)?;
+            writeln!(f, "spawn offset {}", join(&vfx_emitter.offset))?;
+            writeln!(f, "spawn direction {}", join(&emitter.direction))?;
+            writeln!(f, "spawn spread {}", settings.spread)?;
+            writeln!(
+                f,
+                "spawn speed {}",
+                join(&[settings.speed.0, settings.speed.1])
+            )?;
+            writeln!(
+                f,
+                "spawn lifetime {}",
+                join(&[settings.lifetime.0, settings.lifetime.1])
+            )?;
+
+            writeln!(f, "update gravity {}", join(&settings.gravity))?;
+            writeln!(f, "update drag {}", settings.drag)?;
+            match settings.collision {
+                ParticleCollision::None => writeln!(f, "update collide none")?,
+                ParticleCollision::Depth {
+                    restitution,
+                    friction,
+                    thickness,
+                    kill,
+                } => writeln!(
+                    f,
+                    "update collide depth {}{}",
+                    join(&[restitution, friction, thickness]),
+                    if kill { " kill" } else { "" }
+                )?,
+                ParticleCollision::Colliders {
+                    restitution,
+                    friction,
+                    kill,
+                } => writeln!(
+                    f,
+                    "update collide colliders {}{}",
+                    join(&[restitution, friction]),
+                    if kill { " kill" } else { "" }
+                )?,
+            }
+
+            writeln!(
+                f,
+                "output size {}",
+                join(&[settings.size.0, settings.size.1])
+            )?;
+            writeln!(
+                f,
+                "output color {} {}",
+                join(&settings.start_color),
+                join(&settings.end_color)
+            )?;
+            if settings.distortion != 0.0 {
+                writeln!(f, "output distortion {}", settings.distortion)?;
+            }
+            match &emitter.mesh {
+                Some(mesh) => writeln!(f, "output mesh {}", mesh)?,
+                None => writeln!(f, "output billboard")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Plays a `VfxEffect` at the entity's transform.
+///
+/// # Example
+/// ```ignore
+/// let impact = VfxEffect::load(&asset_server, "game://vfx/impact.vfx")?;
+///
+/// compound.spawn((
+///     Transform3D::new(hit_point, [0.0, 0.0, 0.0, 1.0]),
+///     VisualEffect::new(impact.clone()),
+/// ));
+/// ```
+#[derive(Debug, Clone)]
+pub struct VisualEffect {
+    pub effect: Arc<VfxEffect>,
+    /// Whether the emitters of the effect spawn new particles, living ones die out either way
+    pub emitting: bool,
+}
+
+impl VisualEffect {
+    pub fn new(effect: Arc<VfxEffect>) -> Self {
+        Self {
+            effect,
+            emitting: true,
+        }
+    }
+}
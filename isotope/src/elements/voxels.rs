@@ -0,0 +1,791 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::anyhow;
+use boson::{Boson, BosonBody, BosonObject, ColliderBuilder, RigidBody};
+use cgmath::Vector3;
+use compound::{Compound, Entity, Without};
+use gluon::JobHandle;
+use gpu_controller::{GpuController, Mesh, Vertex};
+use log::{debug, error};
+use matter_vault::SharedMatter;
+
+use crate::{
+    AssetServer, Discarded, Model, Transform3D,
+    material::{Material, load_materials},
+};
+
+/// The type of a voxel, types above `AIR` pick the material the voxel is drawn with
+pub type VoxelId = u16;
+
+/// The empty voxel
+pub const AIR: VoxelId = 0;
+
+/// Number of voxels along every edge of a chunk
+pub const CHUNK_SIZE: usize = 16;
+
+const CHUNK_VOLUME: usize = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
+
+// A chunk with a border of one voxel from its neighbors, for culling faces between chunks
+const PADDED_SIZE: usize = CHUNK_SIZE + 2;
+
+// Chunks meshed at the same time, the rest wait for a free worker
+const MAX_MESHING_JOBS: usize = 4;
+
+/// Coordinates of a chunk, in chunks from the origin of the terrain
+pub type ChunkCoord = [i32; 3];
+
+// A chunk that finished meshing, added to the world once the terrain is no longer borrowed
+struct FinishedChunk {
+    terrain: Entity,
+    coord: ChunkCoord,
+    entity: Option<Entity>,
+    transform: Transform3D,
+    model: Option<Model>,
+    boxes: Vec<([f32; 3], [f32; 3])>,
+}
+
+// The meshes of every voxel type in a chunk and the boxes its colliders are made of
+struct ChunkMeshes {
+    meshes: Vec<(VoxelId, SharedMatter<Mesh>)>,
+    boxes: Vec<([f32; 3], [f32; 3])>,
+}
+
+struct VoxelChunk {
+    voxels: Box<[VoxelId]>,
+    dirty: bool,
+    entity: Option<Entity>,
+    colliders: Vec<(Entity, BosonObject)>,
+    job: Option<JobHandle<ChunkMeshes>>,
+}
+
+impl VoxelChunk {
+    fn new() -> Self {
+        Self {
+            voxels: vec![AIR; CHUNK_VOLUME].into_boxed_slice(),
+            dirty: true,
+            entity: None,
+            colliders: Vec::new(),
+            job: None,
+        }
+    }
+
+    fn index(local: [usize; 3]) -> usize {
+        local[0] + local[1] * CHUNK_SIZE + local[2] * CHUNK_SIZE * CHUNK_SIZE
+    }
+}
+
+/// Terrain made of voxels stored in chunks, for block worlds and destructible ground.
+///
+/// Voxels are addressed by their coordinates in the local space of the
+/// entity, voxel `[x, y, z]` fills the cube from `[x, y, z] * voxel_size` to
+/// `[x + 1, y + 1, z + 1] * voxel_size`. Every chunk is drawn as its own
+/// entity, and changing a voxel remeshes its chunk on a worker thread. Faces
+/// of the same type are merged into as few quads as possible.
+///
+/// The materials of an MTL file are used for the voxel types in order, type 1
+/// is drawn with the first material. Without a material file or for types
+/// past the last material, voxels are drawn with the last material or the
+/// error material.
+///
+/// Chunks are placed where the entity is when they are meshed, the terrain
+/// isn't meant to be moved.
+///
+/// # Example
+/// ```ignore
+/// const STONE: VoxelId = 1;
+/// const GRASS: VoxelId = 2;
+///
+/// let mut terrain = VoxelTerrain::new(1.0)
+///     .with_material("game://materials/blocks.mtl")
+///     .with_colliders();
+///
+/// terrain.fill([-32, -4, -32], [32, 0, 32], STONE);
+/// terrain.fill([-32, 0, -32], [32, 1, 32], GRASS);
+///
+/// compound.spawn((Transform3D::default(), terrain));
+///
+/// // Dig a hole where the player clicked
+/// compound.iter_mut_mol(|_entity, terrain: &mut VoxelTerrain| {
+///     let voxel = terrain.voxel_at(hit_point);
+///     terrain.set_voxel(voxel, AIR);
+/// });
+/// ```
+pub struct VoxelTerrain {
+    voxel_size: f32,
+    material_path: Option<String>,
+    colliders: bool,
+
+    materials: Option<Vec<SharedMatter<Material>>>,
+    chunks: HashMap<ChunkCoord, VoxelChunk>,
+}
+
+impl VoxelTerrain {
+    /// Creates empty terrain.
+    ///
+    /// # Arguments
+    /// * `voxel_size` - The length of the edges of every voxel
+    pub fn new(voxel_size: f32) -> Self {
+        Self {
+            voxel_size,
+            material_path: None,
+            colliders: false,
+            materials: None,
+            chunks: HashMap::new(),
+        }
+    }
+
+    /// Draws the voxels with the materials of an MTL file
+    pub fn with_material(mut self, path: impl Into<String>) -> Self {
+        self.material_path = Some(path.into());
+        self
+    }
+
+    /// Gives every chunk static colliders matching its voxels
+    pub fn with_colliders(mut self) -> Self {
+        self.colliders = true;
+        self
+    }
+
+    /// Returns the length of the edges of every voxel
+    pub fn voxel_size(&self) -> f32 {
+        self.voxel_size
+    }
+
+    /// Returns the voxel at a position in the local space of the entity
+    pub fn voxel_at(&self, position: Vector3<f32>) -> [i32; 3] {
+        let voxel = position / self.voxel_size;
+        [
+            voxel.x.floor() as i32,
+            voxel.y.floor() as i32,
+            voxel.z.floor() as i32,
+        ]
+    }
+
+    /// Returns the type of a voxel, `AIR` where nothing was placed
+    pub fn get_voxel(&self, voxel: [i32; 3]) -> VoxelId {
+        let (coord, local) = split(voxel);
+
+        self.chunks
+            .get(&coord)
+            .map(|chunk| chunk.voxels[VoxelChunk::index(local)])
+            .unwrap_or(AIR)
+    }
+
+    /// Changes the type of a voxel, its chunk is remeshed before the next frame or soon after.
+    ///
+    /// # Arguments
+    /// * `voxel` - The coordinates of the voxel
+    /// * `id` - The new type, `AIR` to remove the voxel
+    ///
+    /// # Returns
+    /// The type the voxel had before
+    pub fn set_voxel(&mut self, voxel: [i32; 3], id: VoxelId) -> VoxelId {
+        let (coord, local) = split(voxel);
+
+        if id == AIR && !self.chunks.contains_key(&coord) {
+            return AIR;
+        }
+
+        let chunk = self.chunks.entry(coord).or_insert_with(VoxelChunk::new);
+        let previous = std::mem::replace(&mut chunk.voxels[VoxelChunk::index(local)], id);
+        if previous == id {
+            return previous;
+        }
+        chunk.dirty = true;
+
+        // Faces between chunks are culled, so neighbors touching the voxel change too
+        for axis in 0..3 {
+            let neighbor = match local[axis] {
+                0 => -1,
+                edge if edge == CHUNK_SIZE - 1 => 1,
+                _ => continue,
+            };
+
+            let mut neighbor_coord = coord;
+            neighbor_coord[axis] += neighbor;
+            if let Some(neighbor) = self.chunks.get_mut(&neighbor_coord) {
+                neighbor.dirty = true;
+            }
+        }
+
+        previous
+    }
+
+    /// Sets every voxel from `min` up to but not including `max`
+    pub fn fill(&mut self, min: [i32; 3], max: [i32; 3], id: VoxelId) {
+        for z in min[2]..max[2] {
+            for y in min[1]..max[1] {
+                for x in min[0]..max[0] {
+                    self.set_voxel([x, y, z], id);
+                }
+            }
+        }
+    }
+
+    /// Returns the coordinates of every chunk that has been edited
+    pub fn chunks(&self) -> impl Iterator<Item = &ChunkCoord> {
+        self.chunks.keys()
+    }
+
+    /// Returns the number of chunks waiting for or being remeshed
+    pub fn pending_chunks(&self) -> usize {
+        self.chunks
+            .values()
+            .filter(|chunk| chunk.dirty || chunk.job.is_some())
+            .count()
+    }
+
+    // Copies a chunk with a border of its neighbors' voxels
+    fn padded(&self, coord: ChunkCoord) -> Vec<VoxelId> {
+        let origin = coord.map(|axis| axis * CHUNK_SIZE as i32 - 1);
+        let mut padded = vec![AIR; PADDED_SIZE * PADDED_SIZE * PADDED_SIZE];
+
+        for z in 0..PADDED_SIZE {
+            for y in 0..PADDED_SIZE {
+                for x in 0..PADDED_SIZE {
+                    padded[padded_index([x, y, z])] = self.get_voxel([
+                        origin[0] + x as i32,
+                        origin[1] + y as i32,
+                        origin[2] + z as i32,
+                    ]);
+                }
+            }
+        }
+
+        padded
+    }
+
+    fn load_materials(&self, asset_server: &AssetServer) -> Vec<SharedMatter<Material>> {
+        if let Some(path) = &self.material_path {
+            match load_materials(path, asset_server) {
+                Ok(materials) if !materials.is_empty() => return materials,
+                Ok(_) => asset_server.report_fallback(path, &anyhow!("No materials in file")),
+                Err(err) => asset_server.report_fallback(path, &err),
+            }
+        }
+
+        match Material::shared_error(asset_server) {
+            Ok(material) => vec![material],
+            Err(err) => {
+                error!("Failed to create the error material: {}", err);
+                Vec::new()
+            }
+        }
+    }
+}
+
+// Splits voxel coordinates into the chunk and the voxel within it
+fn split(voxel: [i32; 3]) -> (ChunkCoord, [usize; 3]) {
+    let size = CHUNK_SIZE as i32;
+
+    (
+        voxel.map(|axis| axis.div_euclid(size)),
+        voxel.map(|axis| axis.rem_euclid(size) as usize),
+    )
+}
+
+fn padded_index(padded: [usize; 3]) -> usize {
+    padded[0] + padded[1] * PADDED_SIZE + padded[2] * PADDED_SIZE * PADDED_SIZE
+}
+
+/// Meshes a chunk, merging faces of the same type into larger quads.
+///
+/// # Arguments
+/// * `padded` - The voxels of the chunk with a border of one voxel
+/// * `voxel_size` - The length of the edges of every voxel
+///
+/// # Returns
+/// The vertices and triangle indices of every voxel type that has a visible face
+fn greedy_mesh(padded: &[VoxelId], voxel_size: f32) -> HashMap<VoxelId, (Vec<Vertex>, Vec<u32>)> {
+    let mut geometry: HashMap<VoxelId, (Vec<Vertex>, Vec<u32>)> = HashMap::new();
+    let mut mask = vec![AIR; CHUNK_SIZE * CHUNK_SIZE];
+
+    // The voxel at chunk coordinates, which go one past the chunk on every side
+    let voxel = |position: [i32; 3]| padded[padded_index(position.map(|axis| (axis + 1) as usize))];
+
+    for axis in 0..3 {
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+
+        for direction in [-1, 1] {
+            let mut normal = [0.0; 3];
+            normal[axis] = direction as f32;
+
+            for slice in 0..CHUNK_SIZE as i32 {
+                // Faces of this slice that border air in the direction
+                for j in 0..CHUNK_SIZE {
+                    for i in 0..CHUNK_SIZE {
+                        let mut position = [0; 3];
+                        position[axis] = slice;
+                        position[u] = i as i32;
+                        position[v] = j as i32;
+
+                        let id = voxel(position);
+                        position[axis] += direction;
+
+                        mask[i + j * CHUNK_SIZE] = if id != AIR && voxel(position) == AIR {
+                            id
+                        } else {
+                            AIR
+                        };
+                    }
+                }
+
+                for j in 0..CHUNK_SIZE {
+                    let mut i = 0;
+                    while i < CHUNK_SIZE {
+                        let id = mask[i + j * CHUNK_SIZE];
+                        if id == AIR {
+                            i += 1;
+                            continue;
+                        }
+
+                        let mut width = 1;
+                        while i + width < CHUNK_SIZE && mask[i + width + j * CHUNK_SIZE] == id {
+                            width += 1;
+                        }
+
+                        let mut height = 1;
+                        while j + height < CHUNK_SIZE
+                            && (i..i + width)
+                                .all(|column| mask[column + (j + height) * CHUNK_SIZE] == id)
+                        {
+                            height += 1;
+                        }
+
+                        for row in j..j + height {
+                            mask[i + row * CHUNK_SIZE..i + width + row * CHUNK_SIZE].fill(AIR);
+                        }
+
+                        let (vertices, indices) = geometry.entry(id).or_default();
+                        let base = vertices.len() as u32;
+                        let plane = (slice + (direction > 0) as i32) as f32;
+
+                        for (corner_u, corner_v) in [
+                            (i, j),
+                            (i + width, j),
+                            (i + width, j + height),
+                            (i, j + height),
+                        ] {
+                            let mut position = [0.0; 3];
+                            position[axis] = plane * voxel_size;
+                            position[u] = corner_u as f32 * voxel_size;
+                            position[v] = corner_v as f32 * voxel_size;
+
+                            vertices.push(Vertex {
+                                position,
+                                // Textures repeat once per voxel
+                                uv_coord: [corner_u as f32, corner_v as f32],
+                                normal_vec: normal,
+                            });
+                        }
+
+                        // Counter clockwise seen from the side the face points to
+                        if direction > 0 {
+                            indices.extend_from_slice(&[
+                                base,
+                                base + 1,
+                                base + 2,
+                                base,
+                                base + 2,
+                                base + 3,
+                            ]);
+                        } else {
+                            indices.extend_from_slice(&[
+                                base,
+                                base + 2,
+                                base + 1,
+                                base,
+                                base + 3,
+                                base + 2,
+                            ]);
+                        }
+
+                        i += width;
+                    }
+                }
+            }
+        }
+    }
+
+    geometry
+}
+
+/// Merges the solid voxels of a chunk into as few boxes as possible.
+///
+/// # Returns
+/// The minimum and maximum corner of every box in the local space of the chunk
+fn greedy_boxes(padded: &[VoxelId], voxel_size: f32) -> Vec<([f32; 3], [f32; 3])> {
+    let solid = |[x, y, z]: [usize; 3]| padded[padded_index([x + 1, y + 1, z + 1])] != AIR;
+    let mut taken = vec![false; CHUNK_VOLUME];
+    let mut boxes = Vec::new();
+
+    for z in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                if taken[VoxelChunk::index([x, y, z])] || !solid([x, y, z]) {
+                    continue;
+                }
+
+                let free = |voxel: [usize; 3]| solid(voxel) && !taken[VoxelChunk::index(voxel)];
+
+                let mut max = [x + 1, y + 1, z + 1];
+                while max[0] < CHUNK_SIZE && free([max[0], y, z]) {
+                    max[0] += 1;
+                }
+                while max[1] < CHUNK_SIZE && (x..max[0]).all(|x| free([x, max[1], z])) {
+                    max[1] += 1;
+                }
+                while max[2] < CHUNK_SIZE
+                    && (y..max[1]).all(|y| (x..max[0]).all(|x| free([x, y, max[2]])))
+                {
+                    max[2] += 1;
+                }
+
+                for taken_z in z..max[2] {
+                    for taken_y in y..max[1] {
+                        for taken_x in x..max[0] {
+                            taken[VoxelChunk::index([taken_x, taken_y, taken_z])] = true;
+                        }
+                    }
+                }
+
+                boxes.push((
+                    [x, y, z].map(|axis| axis as f32 * voxel_size),
+                    max.map(|axis| axis as f32 * voxel_size),
+                ));
+            }
+        }
+    }
+
+    boxes
+}
+
+// Meshes a chunk and buffers its meshes, run as a background job
+fn mesh_chunk(
+    gpu_controller: Arc<GpuController>,
+    coord: ChunkCoord,
+    padded: Vec<VoxelId>,
+    voxel_size: f32,
+    colliders: bool,
+) -> ChunkMeshes {
+    let mut meshes = greedy_mesh(&padded, voxel_size)
+        .into_iter()
+        .map(|(id, (vertices, indices))| {
+            let mesh = Mesh::new(
+                gpu_controller.clone(),
+                format!("Voxel Chunk {:?} Type {}", coord, id),
+                &vertices,
+                &indices,
+            );
+
+            (id, SharedMatter::new(mesh))
+        })
+        .collect::<Vec<_>>();
+    meshes.sort_by_key(|(id, _)| *id);
+
+    ChunkMeshes {
+        meshes,
+        boxes: if colliders {
+            greedy_boxes(&padded, voxel_size)
+        } else {
+            Vec::new()
+        },
+    }
+}
+
+/// Remeshes the chunks of voxel terrain that changed and swaps in the
+/// finished meshes and colliders
+pub(crate) fn update_voxels(
+    compound: &Compound,
+    asset_server: &AssetServer,
+    boson: &RwLock<Boson>,
+) {
+    let mut finished = Vec::new();
+    let mut retired = Vec::new();
+
+    let mut jobs = 0;
+    compound
+        .query::<(&mut VoxelTerrain, &Transform3D), Without<Discarded>>()
+        .unmod()
+        .for_each(|entity, (terrain, transform)| {
+            jobs += terrain
+                .chunks
+                .values()
+                .filter(|chunk| chunk.job.is_some())
+                .count();
+
+            if terrain.materials.is_none() {
+                terrain.materials = Some(terrain.load_materials(asset_server));
+            }
+
+            let (position, rotation) =
+                transform.get_position_and_rotation(|position, rotation| (*position, *rotation));
+            let chunk_size = terrain.voxel_size * CHUNK_SIZE as f32;
+
+            // Swap in the chunks that finished meshing
+            for (coord, chunk) in terrain.chunks.iter_mut() {
+                if !chunk.job.as_ref().is_some_and(JobHandle::is_finished) {
+                    continue;
+                }
+
+                let Some(job) = chunk.job.take() else {
+                    continue;
+                };
+                jobs -= 1;
+
+                let chunk_meshes = match job.join() {
+                    Ok(chunk_meshes) => chunk_meshes,
+                    Err(_) => {
+                        error!("Meshing voxel chunk {:?} panicked", coord);
+                        continue;
+                    }
+                };
+
+                let origin = Vector3::new(coord[0] as f32, coord[1] as f32, coord[2] as f32);
+                let chunk_transform =
+                    Transform3D::new(position + rotation * (origin * chunk_size), rotation);
+
+                let model = if chunk_meshes.meshes.is_empty() {
+                    None
+                } else {
+                    let materials = terrain.materials.clone().unwrap_or_default();
+                    let meshes = chunk_meshes
+                        .meshes
+                        .into_iter()
+                        .map(|(id, mesh)| {
+                            let material = (id as usize - 1).min(materials.len().saturating_sub(1));
+                            (Some(material).filter(|_| !materials.is_empty()), mesh)
+                        })
+                        .collect();
+
+                    match Model::from_meshes(meshes, materials, asset_server, None) {
+                        Ok(mut model) => {
+                            model.set_transform(&chunk_transform);
+                            Some(model)
+                        }
+                        Err(err) => {
+                            error!("Failed to create voxel chunk model: {}", err);
+                            None
+                        }
+                    }
+                };
+
+                retired.append(&mut chunk.colliders);
+                finished.push(FinishedChunk {
+                    terrain: entity,
+                    coord: *coord,
+                    entity: chunk.entity,
+                    transform: chunk_transform,
+                    model,
+                    boxes: chunk_meshes.boxes,
+                });
+            }
+
+            // Start meshing changed chunks while there are free workers
+            let mut dirty = terrain
+                .chunks
+                .iter()
+                .filter(|(_, chunk)| chunk.dirty && chunk.job.is_none())
+                .map(|(coord, _)| *coord)
+                .collect::<Vec<_>>();
+            dirty.sort();
+
+            for coord in dirty
+                .into_iter()
+                .take(MAX_MESHING_JOBS.saturating_sub(jobs))
+            {
+                let padded = terrain.padded(coord);
+                let voxel_size = terrain.voxel_size;
+                let colliders = terrain.colliders;
+                let gpu_controller = asset_server.gpu_controller.clone();
+
+                if let Some(chunk) = terrain.chunks.get_mut(&coord) {
+                    chunk.dirty = false;
+                    chunk.job = Some(gluon::spawn(move || {
+                        mesh_chunk(gpu_controller, coord, padded, voxel_size, colliders)
+                    }));
+                    jobs += 1;
+                }
+            }
+        });
+
+    if !retired.is_empty() {
+        match boson.write() {
+            Ok(mut boson) => {
+                for (_, boson_object) in retired.iter() {
+                    boson.remove_object(boson_object);
+                }
+            }
+            Err(err) => error!("Failed to remove voxel colliders from boson: {}", err),
+        }
+
+        for (entity, _) in retired {
+            compound.add_molecule(entity, Discarded);
+        }
+    }
+
+    if finished.is_empty() {
+        return;
+    }
+
+    let mut spawned = HashMap::new();
+    for chunk in finished {
+        let entity = chunk
+            .entity
+            .unwrap_or_else(|| compound.spawn((chunk.transform,)));
+
+        match chunk.model {
+            Some(model) => compound.add_molecule(entity, model),
+            None => _ = compound.remove_molecule::<Model>(entity),
+        }
+
+        // Immovable boxes placed like the chunk
+        let colliders = chunk
+            .boxes
+            .iter()
+            .map(|(min, max)| {
+                let boson_object = RigidBody::new(0.0, ColliderBuilder::FromModelAabb);
+                boson_object.modify_body(|body| {
+                    if let BosonBody::RigidBody(rigid_body) = body {
+                        rigid_body.build_collider_from_points(&[*min, *max]);
+                    }
+                });
+
+                (
+                    compound.spawn((chunk.transform, boson_object.clone())),
+                    boson_object,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        debug!(
+            "Voxel chunk {:?} meshed with {} colliders",
+            chunk.coord,
+            colliders.len()
+        );
+        spawned.insert((chunk.terrain, chunk.coord), (entity, colliders));
+    }
+
+    compound
+        .query::<(&mut VoxelTerrain,), ()>()
+        .unmod()
+        .for_each(|entity, (terrain,)| {
+            for (coord, chunk) in terrain.chunks.iter_mut() {
+                if let Some((chunk_entity, colliders)) = spawned.remove(&(entity, *coord)) {
+                    chunk.entity = Some(chunk_entity);
+                    chunk.colliders = colliders;
+                }
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::InnerSpace;
+
+    use super::*;
+
+    const STONE: VoxelId = 1;
+    const GRASS: VoxelId = 2;
+
+    const EPSILON: f32 = 1e-4;
+
+    // Number of quads of a voxel type in the mesh of a chunk
+    fn quads(terrain: &VoxelTerrain, coord: ChunkCoord, id: VoxelId) -> usize {
+        greedy_mesh(&terrain.padded(coord), 1.0)
+            .get(&id)
+            .map(|(_, indices)| indices.len() / 6)
+            .unwrap_or(0)
+    }
+
+    // Sums the area of the faces of a chunk and the volume they enclose, which
+    // is negative if they point inwards
+    fn area_and_volume(terrain: &VoxelTerrain, coord: ChunkCoord) -> (f32, f32) {
+        let mut area = 0.0;
+        let mut volume = 0.0;
+
+        for (vertices, indices) in greedy_mesh(&terrain.padded(coord), 1.0).values() {
+            for triangle in indices.chunks_exact(3) {
+                let [a, b, c] = [0, 1, 2]
+                    .map(|corner| Vector3::from(vertices[triangle[corner] as usize].position));
+                area += (b - a).cross(c - a).magnitude() * 0.5;
+                volume += a.dot(b.cross(c)) / 6.0;
+            }
+        }
+
+        (area, volume)
+    }
+
+    fn assert_surface(terrain: &VoxelTerrain, coord: ChunkCoord, area: f32, volume: f32) {
+        let (mesh_area, mesh_volume) = area_and_volume(terrain, coord);
+        assert!(
+            (mesh_area - area).abs() < EPSILON,
+            "area {} isn't {}",
+            mesh_area,
+            area
+        );
+        assert!(
+            (mesh_volume - volume).abs() < EPSILON,
+            "volume {} isn't {}",
+            mesh_volume,
+            volume
+        );
+    }
+
+    #[test]
+    fn test_solid_block_is_six_quads() {
+        let mut terrain = VoxelTerrain::new(1.0);
+        terrain.fill([0, 0, 0], [4, 3, 2], STONE);
+
+        assert_eq!(quads(&terrain, [0, 0, 0], STONE), 6);
+        assert_surface(&terrain, [0, 0, 0], 52.0, 24.0);
+        assert_eq!(greedy_boxes(&terrain.padded([0, 0, 0]), 1.0).len(), 1);
+    }
+
+    #[test]
+    fn test_l_shape() {
+        // Three voxels along X with two stacked on the first
+        let mut terrain = VoxelTerrain::new(1.0);
+        terrain.fill([0, 0, 0], [3, 1, 1], STONE);
+        terrain.fill([0, 1, 0], [1, 3, 1], STONE);
+
+        // Two quads for each L shaped side, one for each end of a bar and one
+        // for each side the bars share
+        assert_eq!(quads(&terrain, [0, 0, 0], STONE), 10);
+        assert_surface(&terrain, [0, 0, 0], 22.0, 5.0);
+        assert_eq!(greedy_boxes(&terrain.padded([0, 0, 0]), 1.0).len(), 2);
+    }
+
+    #[test]
+    fn test_types_are_not_merged() {
+        let mut terrain = VoxelTerrain::new(1.0);
+        terrain.fill([0, 0, 0], [2, 1, 1], STONE);
+        terrain.fill([2, 0, 0], [4, 1, 1], GRASS);
+
+        // The faces between the types are hidden, the rest is split by type
+        assert_eq!(quads(&terrain, [0, 0, 0], STONE), 5);
+        assert_eq!(quads(&terrain, [0, 0, 0], GRASS), 5);
+        assert_surface(&terrain, [0, 0, 0], 18.0, 4.0);
+    }
+
+    #[test]
+    fn test_faces_between_chunks_are_hidden() {
+        let last = CHUNK_SIZE as i32 - 1;
+        let mut terrain = VoxelTerrain::new(1.0);
+        terrain.fill([last - 1, 0, 0], [last + 3, 1, 1], STONE);
+
+        // Each chunk draws its half without the faces where they touch
+        for coord in [[0, 0, 0], [1, 0, 0]] {
+            assert_eq!(quads(&terrain, coord, STONE), 5);
+            assert!((area_and_volume(&terrain, coord).0 - 9.0).abs() < EPSILON);
+        }
+
+        // Removing the voxels of one chunk uncovers the faces of the other
+        terrain.fill([last + 1, 0, 0], [last + 3, 1, 1], AIR);
+        assert_eq!(quads(&terrain, [0, 0, 0], STONE), 6);
+        assert_eq!(quads(&terrain, [1, 0, 0], STONE), 0);
+    }
+}
@@ -1,13 +1,26 @@
 use std::sync::Arc;
 
-use log::warn;
+use anyhow::Result;
+use compound::Compound;
+use log::{info, warn};
+use winit::dpi::PhysicalPosition;
 pub use winit::window::CursorGrabMode;
 use winit::window::Window;
 
+use crate::clipboard::Clipboard;
+
+// Distance in pixels from the center the cursor may move before it is recentered
+const RECENTER_TOLERANCE: f64 = 1.0;
+
 pub struct WindowController {
     window: Arc<Window>,
     cursor_grab_mode: CursorGrabMode,
     cursor_visible: bool,
+    // The grab mode the platform accepted, may differ from the requested one
+    applied_cursor_grab_mode: CursorGrabMode,
+    // Set when locking isn't supported and the cursor is held in place by moving it back
+    recenter_cursor: bool,
+    clipboard: Clipboard,
 }
 
 impl WindowController {
@@ -16,19 +29,107 @@ impl WindowController {
             window,
             cursor_grab_mode: CursorGrabMode::None,
             cursor_visible: true,
+            applied_cursor_grab_mode: CursorGrabMode::None,
+            recenter_cursor: false,
+            clipboard: Clipboard::default(),
         }
     }
 
-    fn update(&self) {
+    // Points the controller at a recreated window and restores the cursor state on it
+    pub(crate) fn set_window(&mut self, window: Arc<Window>) {
+        self.window = window;
+        self.update();
+    }
+
+    fn update(&mut self) {
+        // Not every platform supports every grab mode (macOS can't confine, X11
+        // can't lock), so fall back to the closest mode that works
+        let fallbacks: &[CursorGrabMode] = match self.cursor_grab_mode {
+            CursorGrabMode::None => &[CursorGrabMode::None],
+            CursorGrabMode::Confined => &[CursorGrabMode::Confined, CursorGrabMode::Locked],
+            CursorGrabMode::Locked => &[CursorGrabMode::Locked, CursorGrabMode::Confined],
+        };
+
+        let applied = fallbacks.iter().copied().find(|mode| {
+            self.window
+                .set_cursor_grab(*mode)
+                .map_err(|err| warn!("Cursor grab mode {:?} not supported: {}", mode, err))
+                .is_ok()
+        });
+
+        self.applied_cursor_grab_mode = applied.unwrap_or_else(|| {
+            warn!("No cursor grab mode supported, continuing...");
+            _ = self.window.set_cursor_grab(CursorGrabMode::None);
+            CursorGrabMode::None
+        });
+
+        // Without a lock the cursor is moved back to the center by hand
+        self.recenter_cursor = self.cursor_grab_mode == CursorGrabMode::Locked
+            && self.applied_cursor_grab_mode != CursorGrabMode::Locked;
+
+        if self.applied_cursor_grab_mode != self.cursor_grab_mode {
+            info!(
+                "Using cursor grab mode {:?} in place of {:?}{}",
+                self.applied_cursor_grab_mode,
+                self.cursor_grab_mode,
+                if self.recenter_cursor {
+                    " with manual recentering"
+                } else {
+                    ""
+                }
+            );
+        }
+
+        self.window.set_cursor_visible(self.cursor_visible);
+        _ = self.recenter(None);
+    }
+
+    /// Moves the cursor back to the center of the window when a lock is emulated
+    ///
+    /// # Arguments
+    /// * `position` - Where the cursor moved to, if known
+    ///
+    /// # Returns
+    /// The position the cursor was moved to, if it was moved
+    pub(crate) fn recenter(
+        &self,
+        position: Option<PhysicalPosition<f64>>,
+    ) -> Option<PhysicalPosition<f64>> {
+        if !self.recenter_cursor {
+            return None;
+        }
+
+        let size = self.window.inner_size();
+        let center = PhysicalPosition::new(size.width as f64 / 2.0, size.height as f64 / 2.0);
+
+        // Moving the cursor sends a move event of its own, which must not move it again
+        if let Some(position) = position
+            && (position.x - center.x).abs() <= RECENTER_TOLERANCE
+            && (position.y - center.y).abs() <= RECENTER_TOLERANCE
+        {
+            return None;
+        }
+
         self.window
-            .set_cursor_grab(self.cursor_grab_mode)
-            .unwrap_or_else(|err| {
+            .set_cursor_position(center)
+            .map(|_| center)
+            .map_err(|err| {
                 warn!(
-                    "Failed to set cursor Grab mode with error: {}, continuing...",
+                    "Failed to recenter cursor with error: {}, continuing...",
                     err
                 );
-            });
-        self.window.set_cursor_visible(self.cursor_visible);
+            })
+            .ok()
+    }
+
+    /// Returns the grab mode the platform accepted.
+    ///
+    /// This is `CursorGrabMode::Confined` if `Locked` was requested on a
+    /// platform that can't lock the cursor, in which case the cursor is
+    /// held at the center of the window instead. Mouse look should use the
+    /// deltas from `IsotopeState::mouse_is_moved`, which work in every mode.
+    pub fn applied_cursor_grab_mode(&self) -> CursorGrabMode {
+        self.applied_cursor_grab_mode
     }
 
     /// Modifies the cursor grab mode for the window.
@@ -92,3 +193,47 @@ impl WindowController {
         self.update();
     }
 }
+
+impl WindowController {
+    /// Reads text from the system clipboard.
+    ///
+    /// # Returns
+    /// The text on the clipboard, or an error if there is no clipboard on
+    /// this platform or it holds no text
+    ///
+    /// # Example
+    /// ```ignore
+    /// compound.iter_mol(|_entity, window_controller: &WindowController| {
+    ///     if let Ok(text) = window_controller.clipboard_text() {
+    ///         text_field.insert(&text);
+    ///     }
+    /// });
+    /// ```
+    pub fn clipboard_text(&self) -> Result<String> {
+        self.clipboard.read_text()
+    }
+
+    /// Writes text to the system clipboard.
+    ///
+    /// # Arguments
+    /// * `text` - The text to copy
+    ///
+    /// # Returns
+    /// An error if there is no clipboard on this platform
+    pub fn set_clipboard_text(&self, text: &str) -> Result<()> {
+        self.clipboard.write_text(text)
+    }
+}
+
+/// Points the window controller of a world at a window, adding one if the world has none
+pub(crate) fn attach_window(compound: &Compound, window: &Arc<Window>) {
+    let mut has_window_controller = false;
+    compound.iter_mut_mol_unmod(|_entity, window_controller: &mut WindowController| {
+        window_controller.set_window(window.clone());
+        has_window_controller = true;
+    });
+
+    if !has_window_controller {
+        compound.spawn((WindowController::new(window.clone()),));
+    }
+}
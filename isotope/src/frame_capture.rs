@@ -0,0 +1,101 @@
+use compound::Compound;
+use gpu_controller::GpuController;
+use log::{info, warn};
+
+/// A capture of every GPU command of one or more frames in an attached graphics
+/// debugger, such as RenderDoc.
+///
+/// The capture starts with the next frame that renders the compound and ends
+/// once the frames have been presented, so a problematic frame can be
+/// inspected without launching the game through the debugger's UI. Nothing is
+/// captured when no graphics debugger is attached to the process.
+///
+/// Captures can also be taken with the key set by `Isotope::set_frame_capture_key`.
+///
+/// # Example
+/// ```ignore
+/// // From a console command
+/// FrameCapture::new().with_frames(3).capture(compound);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameCapture {
+    frames: u32,
+}
+
+impl Default for FrameCapture {
+    fn default() -> Self {
+        Self { frames: 1 }
+    }
+}
+
+impl FrameCapture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Captures several consecutive frames in one capture
+    pub fn with_frames(mut self, frames: u32) -> Self {
+        self.frames = frames.max(1);
+        self
+    }
+
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Queues the capture to start with the next frame that renders the compound
+    pub fn capture(self, compound: &Compound) {
+        compound.insert_resource(self);
+    }
+}
+
+/// Captures of the renderer, requested by the debug key or queued in the compound
+#[derive(Debug, Default)]
+pub(crate) struct FrameCaptureState {
+    requested: Option<FrameCapture>,
+    // Frames left of the active capture
+    remaining: u32,
+}
+
+impl FrameCaptureState {
+    pub(crate) fn request(&mut self, capture: FrameCapture) {
+        self.requested = Some(capture);
+    }
+
+    /// Starts a requested capture before anything of the frame is recorded
+    pub(crate) fn begin_frame(&mut self, compound: &Compound, gpu_controller: &GpuController) {
+        let Some(capture) = compound
+            .remove_resource::<FrameCapture>()
+            .or(self.requested.take())
+        else {
+            return;
+        };
+
+        if self.remaining > 0 {
+            warn!("Frame capture requested while one is active, ignoring");
+            return;
+        }
+
+        if gpu_controller.start_frame_capture() {
+            info!(
+                "Capturing {} frame(s) for the graphics debugger",
+                capture.frames
+            );
+            self.remaining = capture.frames;
+        } else {
+            warn!("Failed to start frame capture, one is already active");
+        }
+    }
+
+    /// Ends the capture once its last frame has been presented
+    pub(crate) fn end_frame(&mut self, gpu_controller: &GpuController) {
+        if self.remaining == 0 {
+            return;
+        }
+
+        self.remaining -= 1;
+        if self.remaining == 0 && gpu_controller.stop_frame_capture() {
+            info!("Frame capture finished");
+        }
+    }
+}
@@ -0,0 +1,373 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, Cursor, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow};
+use log::{debug, info, warn};
+
+use crate::vfs::Vfs;
+
+const META_EXTENSION: &str = "meta";
+const META_VERSION: u32 = 1;
+
+/// How a texture is stored on the GPU after import.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureCompression {
+    /// Uncompressed 8 bit RGBA
+    None,
+    /// Block compressed, currently falls back to uncompressed
+    BlockCompressed,
+}
+
+/// Import settings used when loading a texture.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureImportSettings {
+    /// Whether the texture holds color data in sRGB space, disable for data
+    /// such as normal or roughness maps
+    pub srgb: bool,
+    pub compression: TextureCompression,
+}
+
+impl Default for TextureImportSettings {
+    fn default() -> Self {
+        Self {
+            srgb: true,
+            compression: TextureCompression::None,
+        }
+    }
+}
+
+/// The axis that points up in the source file of a mesh.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpAxis {
+    /// Y is up, the same as the engine so no conversion is done
+    Y,
+    /// Z is up and -Y is forward, as exported by most DCC tools and CAD software
+    Z,
+}
+
+/// The unit of length the source file of a mesh is authored in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthUnit {
+    Meters,
+    Centimeters,
+    Millimeters,
+    Inches,
+    Feet,
+}
+
+impl LengthUnit {
+    /// Returns the number of meters in one of this unit
+    pub fn to_meters(&self) -> f32 {
+        match self {
+            Self::Meters => 1.0,
+            Self::Centimeters => 0.01,
+            Self::Millimeters => 0.001,
+            Self::Inches => 0.0254,
+            Self::Feet => 0.3048,
+        }
+    }
+}
+
+/// Import settings used when loading a mesh.
+///
+/// Meshes are converted into the engine's Y-up meters convention when they
+/// are imported, so every system that reads the geometry afterwards, such as
+/// rendering or collider generation, sees the same converted data.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshImportSettings {
+    /// Uniform scale applied to every vertex position after unit conversion
+    pub scale: f32,
+    pub up_axis: UpAxis,
+    pub units: LengthUnit,
+    /// Stores the vertices as `QuantizedVertex`, half the size of full
+    /// precision vertices at the cost of some precision
+    pub quantize: bool,
+    /// Keeps the triangles on the CPU once the mesh is buffered, for
+    /// `FromModelConvexHull` and `FromModelTriMesh` colliders
+    pub collision_geometry: bool,
+}
+
+impl Default for MeshImportSettings {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            up_axis: UpAxis::Y,
+            units: LengthUnit::Meters,
+            quantize: false,
+            collision_geometry: false,
+        }
+    }
+}
+
+impl MeshImportSettings {
+    /// Returns the total uniform scale from the source file to engine meters
+    pub fn total_scale(&self) -> f32 {
+        self.units.to_meters() * self.scale
+    }
+
+    /// Converts a vertex position from the source file into engine space
+    pub fn convert_position(&self, position: [f32; 3]) -> [f32; 3] {
+        let scale = self.total_scale();
+        self.convert_axes(position)
+            .map(|component| component * scale)
+    }
+
+    /// Converts a vertex normal from the source file into engine space.
+    ///
+    /// Normals are only rotated, a uniform scale does not change their direction.
+    pub fn convert_normal(&self, normal: [f32; 3]) -> [f32; 3] {
+        self.convert_axes(normal)
+    }
+
+    fn convert_axes(&self, [x, y, z]: [f32; 3]) -> [f32; 3] {
+        match self.up_axis {
+            UpAxis::Y => [x, y, z],
+            // Rotate -90 degrees about X so +Z becomes +Y and +Y becomes -Z
+            UpAxis::Z => [x, z, -y],
+        }
+    }
+}
+
+/// Settings that control how an asset is imported.
+///
+/// Every asset can have a sidecar metadata file next to it with the same name
+/// and a `.meta` extension appended (`crate.obj` -> `crate.obj.meta`). The
+/// file is generated with default settings the first time the asset is
+/// imported, after that it is read on every import so the result is
+/// reproducible and the settings can be edited by hand or by tools.
+///
+/// # Example
+/// ```ignore
+/// # crate.obj.meta
+/// version 1
+/// srgb true
+/// compression none
+/// scale 1
+/// up_axis z
+/// units cm
+/// quantize true
+/// collision_geometry false
+/// material OldPaint NewPaint
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportSettings {
+    pub texture: TextureImportSettings,
+    pub mesh: MeshImportSettings,
+    /// Material names used by the asset mapped to the materials to use instead
+    pub material_assignments: HashMap<String, String>,
+}
+
+impl ImportSettings {
+    /// Returns the path of the metadata file for an asset
+    pub fn meta_path<P>(asset_path: P) -> PathBuf
+    where
+        P: AsRef<Path>,
+    {
+        let mut meta_path = asset_path.as_ref().as_os_str().to_owned();
+        meta_path.push(".");
+        meta_path.push(META_EXTENSION);
+
+        PathBuf::from(meta_path)
+    }
+
+    /// Reads the import settings from a metadata file.
+    ///
+    /// Unknown keys are ignored so files written by newer versions still load.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the metadata file
+    ///
+    /// # Returns
+    /// The import settings, or an error if the file could not be read or a value is invalid
+    pub fn read<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_reader(BufReader::new(File::open(path.as_ref())?), path.as_ref())
+    }
+
+    /// Reads the import settings from the contents of a metadata file,
+    /// `path` only names the file in warnings
+    fn from_reader<R>(reader: R, path: &Path) -> Result<Self>
+    where
+        R: BufRead,
+    {
+        let mut settings = Self::default();
+
+        for line in reader.lines().map_while(Result::ok) {
+            let tokens = line.split_whitespace().collect::<Vec<_>>();
+
+            if tokens.is_empty() || tokens[0].starts_with('#') {
+                continue;
+            }
+
+            let value = |index: usize| {
+                tokens
+                    .get(index)
+                    .ok_or_else(|| anyhow!("Missing value for {}", tokens[0]))
+            };
+
+            match tokens[0] {
+                "version" => {
+                    let version = value(1)?.parse::<u32>()?;
+                    if version > META_VERSION {
+                        warn!(
+                            "Metadata {:#?} has version {}, newer than {}",
+                            path, version, META_VERSION
+                        );
+                    }
+                }
+                "srgb" => {
+                    settings.texture.srgb = value(1)?.parse::<bool>()?;
+                }
+                "compression" => {
+                    settings.texture.compression = match *value(1)? {
+                        "none" => TextureCompression::None,
+                        "bc" => TextureCompression::BlockCompressed,
+                        other => return Err(anyhow!("Unknown compression: {}", other)),
+                    };
+                }
+                "scale" => {
+                    settings.mesh.scale = value(1)?.parse::<f32>()?;
+                }
+                "up_axis" => {
+                    settings.mesh.up_axis = match *value(1)? {
+                        "y" => UpAxis::Y,
+                        "z" => UpAxis::Z,
+                        other => return Err(anyhow!("Unknown up axis: {}", other)),
+                    };
+                }
+                "units" => {
+                    settings.mesh.units = match *value(1)? {
+                        "m" => LengthUnit::Meters,
+                        "cm" => LengthUnit::Centimeters,
+                        "mm" => LengthUnit::Millimeters,
+                        "in" => LengthUnit::Inches,
+                        "ft" => LengthUnit::Feet,
+                        other => return Err(anyhow!("Unknown units: {}", other)),
+                    };
+                }
+                "quantize" => {
+                    settings.mesh.quantize = value(1)?.parse::<bool>()?;
+                }
+                "collision_geometry" => {
+                    settings.mesh.collision_geometry = value(1)?.parse::<bool>()?;
+                }
+                "material" => {
+                    settings
+                        .material_assignments
+                        .insert(value(1)?.to_string(), value(2)?.to_string());
+                }
+                _ => {
+                    debug!("Ignoring unknown metadata key: {}", tokens[0]);
+                }
+            }
+        }
+
+        Ok(settings)
+    }
+
+    /// Writes the import settings to a metadata file, replacing its contents
+    pub fn write<P>(&self, path: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        self.to_writer(File::create(path.as_ref())?)
+    }
+
+    fn to_writer<W>(&self, mut file: W) -> Result<()>
+    where
+        W: Write,
+    {
+        writeln!(file, "# Isotope import settings")?;
+        writeln!(file, "version {}", META_VERSION)?;
+        writeln!(file, "srgb {}", self.texture.srgb)?;
+        writeln!(
+            file,
+            "compression {}",
+            match self.texture.compression {
+                TextureCompression::None => "none",
+                TextureCompression::BlockCompressed => "bc",
+            }
+        )?;
+        writeln!(file, "scale {}", self.mesh.scale)?;
+        writeln!(
+            file,
+            "up_axis {}",
+            match self.mesh.up_axis {
+                UpAxis::Y => "y",
+                UpAxis::Z => "z",
+            }
+        )?;
+        writeln!(
+            file,
+            "units {}",
+            match self.mesh.units {
+                LengthUnit::Meters => "m",
+                LengthUnit::Centimeters => "cm",
+                LengthUnit::Millimeters => "mm",
+                LengthUnit::Inches => "in",
+                LengthUnit::Feet => "ft",
+            }
+        )?;
+        writeln!(file, "quantize {}", self.mesh.quantize)?;
+        writeln!(file, "collision_geometry {}", self.mesh.collision_geometry)?;
+
+        // Sorted so the file does not change between writes
+        let mut assignments = self.material_assignments.iter().collect::<Vec<_>>();
+        assignments.sort();
+        for (material, assigned) in assignments {
+            writeln!(file, "material {} {}", material, assigned)?;
+        }
+
+        Ok(())
+    }
+
+    /// Loads the import settings of an asset, generating the metadata file
+    /// with default settings if it does not exist yet.
+    ///
+    /// Errors are logged and the default settings are returned, so an invalid
+    /// or unwritable metadata file never prevents an asset from loading.
+    /// Assets in read only mounts, like packs, load with the defaults if
+    /// they were shipped without their metadata.
+    pub fn load_or_create<P>(asset_path: P, vfs: &Vfs) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let meta_path = Self::meta_path(&asset_path);
+
+        if vfs.exists(&meta_path) {
+            vfs.read(&meta_path)
+                .and_then(|bytes| Self::from_reader(Cursor::new(bytes), &meta_path))
+                .unwrap_or_else(|err| {
+                    warn!("Failed to read metadata {:#?}: {}", meta_path, err);
+                    Self::default()
+                })
+        } else {
+            info!("Generating metadata: {:#?}", meta_path);
+
+            let settings = Self::default();
+            let mut contents = Vec::new();
+            if let Err(err) = settings
+                .to_writer(&mut contents)
+                .and_then(|_| vfs.write(&meta_path, &contents))
+            {
+                warn!("Failed to write metadata {:#?}: {}", meta_path, err);
+            }
+
+            settings
+        }
+    }
+
+    /// Returns the material that should be used in place of `material`
+    pub fn assigned_material<'a>(&'a self, material: &'a str) -> &'a str {
+        self.material_assignments
+            .get(material)
+            .map(|assigned| assigned.as_str())
+            .unwrap_or(material)
+    }
+}
@@ -0,0 +1,138 @@
+use winit::event::{Touch, TouchPhase};
+
+/// The stage of a pointer interaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointerPhase {
+    Down,
+    Moved,
+    Up,
+    Cancelled,
+}
+
+/// A pointer interaction, such as a finger on a touch screen.
+///
+/// Every pointer has an id that stays the same from `Down` until `Up` or
+/// `Cancelled`, so multiple simultaneous touches can be told apart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointerEvent {
+    pub id: u64,
+    pub phase: PointerPhase,
+    /// Position in physical pixels from the top left of the window
+    pub position: (f32, f32),
+    /// Normalized pressure from 0 to 1 if the device reports it
+    pub pressure: Option<f32>,
+}
+
+impl From<Touch> for PointerEvent {
+    fn from(touch: Touch) -> Self {
+        Self {
+            id: touch.id,
+            phase: match touch.phase {
+                TouchPhase::Started => PointerPhase::Down,
+                TouchPhase::Moved => PointerPhase::Moved,
+                TouchPhase::Ended => PointerPhase::Up,
+                TouchPhase::Cancelled => PointerPhase::Cancelled,
+            },
+            position: (touch.location.x as f32, touch.location.y as f32),
+            pressure: touch.force.map(|force| force.normalized() as f32),
+        }
+    }
+}
+
+/// Where mouse movement passed to `IsotopeState::mouse_is_moved` comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MouseInputMode {
+    /// Unaccelerated device motion, unaffected by the OS pointer speed
+    #[default]
+    Raw,
+    /// Movement of the cursor in the window, includes OS pointer acceleration
+    Cursor,
+}
+
+/// How mouse movement is turned into the deltas games receive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MouseSettings {
+    pub mode: MouseInputMode,
+    /// Multiplier applied to every delta. Cursor deltas are measured in
+    /// logical pixels so the same sensitivity feels the same at any DPI
+    pub sensitivity: f64,
+    /// How much of the previous delta carries into the next one, from 0
+    /// (no smoothing) up to but not including 1
+    pub smoothing: f64,
+}
+
+impl Default for MouseSettings {
+    fn default() -> Self {
+        Self {
+            mode: MouseInputMode::default(),
+            sensitivity: 1.0,
+            smoothing: 0.0,
+        }
+    }
+}
+
+/// Turns device and cursor motion into mouse deltas according to the `MouseSettings`.
+#[derive(Debug, Default)]
+pub(crate) struct MouseFilter {
+    settings: MouseSettings,
+    last_cursor_position: Option<(f64, f64)>,
+    smoothed: (f64, f64),
+}
+
+impl MouseFilter {
+    pub(crate) fn set_settings(&mut self, settings: MouseSettings) {
+        self.settings = settings;
+        self.smoothed = (0.0, 0.0);
+        self.last_cursor_position = None;
+    }
+
+    /// Filters raw device motion, returns `None` when not in raw mode
+    pub(crate) fn device_motion(&mut self, delta: (f64, f64)) -> Option<(f64, f64)> {
+        if self.settings.mode != MouseInputMode::Raw {
+            return None;
+        }
+
+        Some(self.filter(delta))
+    }
+
+    /// Filters the movement of the cursor, returns `None` when not in cursor mode
+    ///
+    /// # Arguments
+    /// * `position` - The new cursor position in physical pixels
+    /// * `scale_factor` - The scale factor of the window, used to convert to logical pixels
+    pub(crate) fn cursor_moved(
+        &mut self,
+        position: (f64, f64),
+        scale_factor: f64,
+    ) -> Option<(f64, f64)> {
+        let last_position = self.last_cursor_position.replace(position);
+
+        if self.settings.mode != MouseInputMode::Cursor {
+            return None;
+        }
+
+        let last_position = last_position?;
+        Some(self.filter((
+            (position.0 - last_position.0) / scale_factor,
+            (position.1 - last_position.1) / scale_factor,
+        )))
+    }
+
+    /// Moves the cursor without it counting as mouse movement, used when the
+    /// engine moves the cursor itself
+    pub(crate) fn warp_cursor(&mut self, position: (f64, f64)) {
+        self.last_cursor_position = Some(position);
+    }
+
+    fn filter(&mut self, delta: (f64, f64)) -> (f64, f64) {
+        let smoothing = self.settings.smoothing.clamp(0.0, 0.99);
+        let sensitivity = self.settings.sensitivity;
+
+        self.smoothed = (
+            self.smoothed.0 * smoothing + delta.0 * sensitivity * (1.0 - smoothing),
+            self.smoothed.1 * smoothing + delta.1 * sensitivity * (1.0 - smoothing),
+        );
+
+        self.smoothed
+    }
+}
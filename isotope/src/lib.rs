@@ -1,52 +1,136 @@
 use std::{
-    sync::{Arc, RwLock},
+    collections::HashMap,
+    sync::{Arc, Mutex, RwLock},
     thread::JoinHandle,
     time::{Duration, Instant},
 };
 
-use anyhow::Result;
-pub use asset_server::AssetServer;
+pub use actions::{
+    ActionBinding, ActionMap, InputKind, InputTrigger, MAX_QUEUED_INPUTS, Modifiers, MouseButton,
+    TimedInput,
+};
+use anyhow::{Result, anyhow};
+pub use asset_server::{AssetServer, AssetSharingStats, FailedLoad};
 use boson::Boson;
-pub use boson::{BosonBody, BosonObject, PointMass, RigidBody, StaticCollider};
+pub use boson::{
+    Axis, AxisLocks, BodyTransform, BodyVelocities, BosonBody, BosonObject, CastShape, Collider,
+    ColliderBuilder, Joint, JointId, JointKind, JointLimits, JointMotor, PhysicsStats, PointMass,
+    RayHit, RigidBody, ShapeHit, SolverSettings, StaticCollider, TriMesh,
+};
 pub use cgmath::*;
-pub use compound::Compound;
 pub use compound::Entity;
+pub use compound::{
+    Compound, EntityMut, MoleculeBundle, Name, Query, Schedule, System, TypeRegistry, With, Without,
+};
+pub use csg::CsgMesh;
+use determinism::audit_determinism;
+pub use determinism::{DEFAULT_DETERMINISM_HISTORY, DeterminismAudit, DeterminismMismatch};
 pub use elements::*;
-pub use gpu_controller::Instance;
+use elements::{
+    ParticleMeshes, apply_material_overrides, attach_window, build_cameras, collect_gizmos,
+    update_canvases, update_distortion, update_fog, update_light_budget, update_light_probes,
+    update_particles, update_silhouettes, update_trails,
+};
+pub use frame_capture::FrameCapture;
+use frame_capture::FrameCaptureState;
+use gluon::ThreadSignal;
+pub use gluon::{JobHandle, JobPool, Scope, ScopedHandle};
+pub use gpu_controller::{
+    Bounds, CommandEncoder, GpuStats, Instance, PerFrame, PipelineCache, ShaderFileSystem,
+    ShaderPreprocessor, TextureArray, TextureArrayBuilder,
+};
 use gpu_controller::{
     CompositeAlphaMode, Features, GpuController, PresentMode, SurfaceConfiguration, TextureFormat,
     TextureUsages,
 };
+pub use import_settings::{
+    ImportSettings, LengthUnit, MeshImportSettings, TextureCompression, TextureImportSettings,
+    UpAxis,
+};
+use input::MouseFilter;
+pub use input::{MouseInputMode, MouseSettings, PointerEvent, PointerPhase};
+use lifecycle::LifecycleHooks;
+pub use lifecycle::{BackgroundPolicy, LifecycleEvent, LifecycleHookId};
 pub use log::*;
+pub use matter_vault::MatterSharing;
 use matter_vault::MatterVault;
 pub use model::Model;
+pub use network::*;
 pub use photon::Light;
-use photon::renderer::Renderer;
-use physics::BosonCompat;
+pub use photon::photon_lighting::IrradianceProbe;
+pub use photon::renderer::distortion::{DISTORTION_FORMAT, DistortionKind, MAX_DISTORTION_SOURCES};
+pub use photon::renderer::draw_order::DrawOrder;
+pub use photon::renderer::hooks::{RenderPassContext, RenderPassHookId, RenderStage};
+pub use photon::renderer::lens::{DepthOfField, MotionBlur};
+pub use photon::renderer::particles::{
+    MAX_PARTICLE_COLLIDERS, ParticleCollider, ParticleCollision, ParticleEmitterSettings,
+    ParticleMeshId,
+};
+pub use photon::renderer::trails::{TrailPoint, TrailSettings};
+pub use photon::renderer::{FRAME_CONSTANTS_WGSL, TEXTURE_ARRAY_WGSL};
+use photon::renderer::{GeometryPass, Renderer};
+use physics::GizmoDebugger;
+pub use physics::RaycastHit;
+use render_sync::{RenderScene, render_streams};
 use rendering_window::{RenderingWindow, WindowInitializer};
+pub use screenshot::Screenshot;
+use screenshot::take_screenshots;
 use smol::block_on;
 pub use state::IsotopeState;
+use systems::engine_schedule;
+pub use systems::{
+    IsotopeSystem, PHYSICS_SYNC_SYSTEM, POST_PHYSICS_SYSTEM, PRE_PHYSICS_SYSTEM, SystemContext,
+};
+pub use vfs::{DirectoryMount, EmbeddedMount, PackMount, Vfs, VfsMount};
 pub use winit::keyboard::KeyCode;
 use winit::{
     application::ApplicationHandler,
     event::{DeviceEvent, ElementState, KeyEvent, WindowEvent},
+    event_loop::ControlFlow,
 };
+use world::Worlds;
+pub use world::{GamePaused, Time, WorldCadence, WorldId};
 
 pub const ISOTOPE_DEFAULT_TICK_RATE: Duration = Duration::from_micros(50);
+pub const ISOTOPE_DEFAULT_FIXED_TIMESTEP: Duration = Duration::from_micros(16_667);
+
+// Upper bound on fixed updates run in one tick so a long stall can't snowball
+const MAX_FIXED_STEPS_PER_TICK: u32 = 8;
 
+// Shortest fixed timestep, shorter ones could never catch up with real time
+const MIN_FIXED_TIMESTEP: Duration = Duration::from_millis(1);
+
+mod actions;
 mod asset_server;
+mod clipboard;
+mod csg;
+mod determinism;
 mod elements;
+mod frame_capture;
+mod import_settings;
+mod input;
+mod lifecycle;
 mod material;
 mod model;
+mod network;
 mod physics;
+mod render_sync;
 mod rendering_window;
+mod screenshot;
 mod state;
+mod systems;
 mod texture;
+mod vfs;
+mod world;
 
 // Structs for bookkeeping in ecs
 struct BosonCompliant;
 
-pub struct Isotope {
+// Entities that are no longer rendered or simulated, despawned by the engine
+// before the next physics step
+pub(crate) struct Discarded;
+
+pub struct Isotope<S: IsotopeState> {
     // GPU
     gpu_controller: Arc<GpuController>,
 
@@ -55,87 +139,163 @@ pub struct Isotope {
 
     // Rendering
     photon: Renderer,
+    // The lights, cameras and model layers streamed from the state thread
+    render_scene: RenderScene,
+    uploaded_light_layers: Option<RenderLayers>,
+    particle_meshes: ParticleMeshes,
 
-    // Entity component system
-    compound: Arc<Compound>,
+    // Entity component system and physics of every world
+    worlds: Arc<RwLock<Worlds>>,
+    // The world the render scene and window controller belong to
+    rendered_world: Option<WorldId>,
 
     // State for interacting with the engine
-    state: Arc<RwLock<dyn IsotopeState>>,
+    state: Arc<RwLock<S>>,
+    lifecycle_hooks: Arc<LifecycleHooks>,
+    // Systems every simulated world runs each tick
+    schedule: Arc<Mutex<Schedule<SystemContext>>>,
 
     // Physics Engine
-    boson: Arc<RwLock<Boson>>,
+    physics_debug: RwLock<bool>,
+
+    // Graphics debugger captures and the key that takes one
+    frame_capture: FrameCaptureState,
+    frame_capture_key: RwLock<Option<KeyCode>>,
 
     // ============== Multi-Threading ==============
-    state_thread: (Arc<RwLock<bool>>, JoinHandle<()>),
+    state_thread: (Arc<ThreadSignal>, JoinHandle<()>),
 
     // Timing
     time: Arc<Instant>,
+    // Time since `time` the last tick of the state thread started, inputs are timed from it
+    tick_start: Arc<RwLock<Duration>>,
     tick_rate: Duration,
+    fixed_timestep: Arc<RwLock<Duration>>,
+    last_frame_time: Instant,
+
+    // Set while the application is in the background
+    paused: RwLock<bool>,
+
+    // Turns mouse and cursor motion into the deltas passed to the state
+    mouse_filter: RwLock<MouseFilter>,
 
-    // Master Running state
-    running: Arc<RwLock<bool>>,
+    // Window focus and visibility, the application is in the background when
+    // it is unfocused or hidden
+    background_policy: RwLock<BackgroundPolicy>,
+    focused: bool,
+    occluded: bool,
+    in_background: bool,
+    background_paused: RwLock<bool>,
 }
 
-impl Isotope {
-    pub fn new<I>(gpu_controller: Arc<GpuController>, mut state: I) -> Result<Self>
-    where
-        I: IsotopeState,
-    {
+impl<S: IsotopeState> Isotope<S> {
+    pub fn new(gpu_controller: Arc<GpuController>, mut state: S) -> Result<Self> {
         let asset_server = Arc::new(AssetServer::new(
             Arc::new(MatterVault::new()),
             gpu_controller.clone(),
         ));
         let photon = Renderer::new_defered_3d(gpu_controller.clone())?;
-        let compound = Arc::new(Compound::new());
-        let running = Arc::new(RwLock::new(false));
         let time = Arc::new(Instant::now());
+        let tick_start = Arc::new(RwLock::new(Duration::ZERO));
         let tick_rate = ISOTOPE_DEFAULT_TICK_RATE;
+        let fixed_timestep = Arc::new(RwLock::new(ISOTOPE_DEFAULT_FIXED_TIMESTEP));
 
-        // Initialize the physics engine
-        let boson = Arc::new(RwLock::new(Boson::new(gpu_controller.clone())));
+        // Initialize the main world with its physics engine
+        let worlds = Worlds::new();
 
         // Initialize the game state and start the update thread
-        state.init(&compound, &asset_server);
+        state.init(&worlds.active().compound, &asset_server);
+        let worlds = Arc::new(RwLock::new(worlds));
         let state = Arc::new(RwLock::new(state));
-        let state_running = Arc::new(RwLock::new(true));
+        let lifecycle_hooks = Arc::new(LifecycleHooks::default());
+        let schedule = Arc::new(Mutex::new(engine_schedule()));
+        let state_signal = Arc::new(ThreadSignal::new());
+        let (mut render_sync, render_scene) = render_streams();
 
-        let state_ecs = compound.clone();
+        let state_worlds = worlds.clone();
         let state_asset_server = asset_server.clone();
-        let state_isotope_running = running.clone();
         let state_state = state.clone();
         let state_time = time.clone();
-        let state_state_running = state_running.clone();
+        let state_tick_start = tick_start.clone();
+        let thread_signal = state_signal.clone();
         let state_tick_rate = tick_rate.clone();
-        let state_boson = boson.clone();
+        let state_fixed_timestep = fixed_timestep.clone();
+        let state_lifecycle_hooks = lifecycle_hooks.clone();
+        let state_schedule = schedule.clone();
         let state_thread_handle = std::thread::spawn(move || {
             info!("Running State Update Thread");
 
-            if let Ok(running) = state_isotope_running.read() {
-                if !*running {
-                    debug!("Isotope not running. Waiting for initialization...");
-                }
-            }
-
             // Wait for Isotope to start running
-            while let Ok(running) = state_isotope_running.read() {
-                if *running {
-                    break;
-                }
+            debug!("Waiting for initialization...");
+            if !thread_signal.wait_for_start() {
+                return;
             }
 
             debug!("Isotope has started running! Starting State Thread...");
 
             let mut last_frame_time = Instant::now();
+            let mut fixed_accumulator = Duration::ZERO;
+
+            // Don't simulate while in the background, and don't count the time spent there
+            while let Some(paused_for) = thread_signal.wait_while_paused() {
+                last_frame_time += paused_for;
 
-            loop {
                 let now = Instant::now();
-                let dt = now.duration_since(last_frame_time).as_secs_f32();
+                let elapsed = now.duration_since(last_frame_time);
+                let dt = elapsed.as_secs_f32();
                 last_frame_time = now;
 
-                if let Ok(mut state) = state_state.write() {
+                if let Ok(mut tick_start) = state_tick_start.write() {
+                    *tick_start = now.duration_since(*state_time);
+                }
+
+                let fixed_timestep = state_fixed_timestep
+                    .read()
+                    .map(|fixed_timestep| (*fixed_timestep).max(MIN_FIXED_TIMESTEP))
+                    .unwrap_or(ISOTOPE_DEFAULT_FIXED_TIMESTEP);
+                fixed_accumulator += elapsed;
+
+                // Find the worlds to simulate this tick, the active world always is
+                let world_ticks = state_worlds
+                    .write()
+                    .map(|mut worlds| worlds.tick())
+                    .unwrap_or_default();
+
+                if let Some(active) = world_ticks.iter().find(|world| world.active)
+                    && let Ok(mut state) = state_state.write()
+                {
+                    let state_ecs = &active.compound;
                     let t = state_time.elapsed().as_secs_f32();
 
-                    state.update(&state_ecs, &state_asset_server, dt, t);
+                    state.update(state_ecs, &state_asset_server, dt, t);
+
+                    // Time stops for fixed steps while the game is paused
+                    if active.paused {
+                        fixed_accumulator = Duration::ZERO;
+                    }
+
+                    // Run as many fixed steps as have accumulated since the last tick
+                    let mut fixed_steps = 0;
+                    while fixed_accumulator >= fixed_timestep
+                        && fixed_steps < MAX_FIXED_STEPS_PER_TICK
+                    {
+                        fixed_accumulator -= fixed_timestep;
+
+                        state.fixed_update(
+                            state_ecs,
+                            &state_asset_server,
+                            fixed_timestep.as_secs_f32(),
+                            t,
+                        );
+                        audit_determinism(state_ecs);
+                        fixed_steps += 1;
+                    }
+
+                    // Steps a slow tick couldn't run are skipped rather than
+                    // making the next tick slower still
+                    if fixed_accumulator >= fixed_timestep {
+                        fixed_accumulator = Duration::ZERO;
+                    }
 
                     // // Run the instancer on any objects that have an instancer
                     // {
@@ -149,95 +309,800 @@ impl Isotope {
                     // }
                 }
 
-                // Add any new boson objects
-                {
-                    let mut to_add_as_boson_compliant: Vec<Entity> = Vec::new();
-
-                    state_ecs.iter_without_mol_mod::<BosonCompliant, _, _>(
-                        |entity, boson_object: &BosonObject| {
-                            if let Ok(mut boson) = state_boson.write() {
-                                info!("Adding Boson Object");
-                                boson.add_object(boson_object);
-                                to_add_as_boson_compliant.push(entity);
-                            }
-                        },
-                    );
+                // Simulate every world that is due this tick
+                for world in world_ticks.iter().filter(|world| !world.paused) {
+                    let context = SystemContext {
+                        world: world.id,
+                        delta_t: world.delta_t,
+                        t: state_time.elapsed().as_secs_f32(),
+                        assets: state_asset_server.clone(),
+                        boson: world.boson.clone(),
+                        lifecycle_hooks: state_lifecycle_hooks.clone(),
+                    };
 
-                    for entity in to_add_as_boson_compliant.into_iter() {
-                        state_ecs.add_molecule(entity, BosonCompliant);
-                        info!("Added Boson Object");
+                    match state_schedule.lock() {
+                        Ok(mut schedule) => {
+                            if let Err(err) = schedule.run(&world.compound, &context) {
+                                error!("Failed to run systems: {}", err);
+                            }
+                        }
+                        Err(err) => error!("Failed to run systems: {}", err),
                     }
                 }
 
-                // Update boson objects with any changed transforms first
-                {
-                    state_ecs.iter_mut_duo_mod(
-                        |_entity, transform: &mut Transform3D, boson_object: &mut BosonObject| {
-                            boson_object.write_transform(transform);
-                        },
-                    );
+                // Let the renderer know what changed in the world it draws
+                if let Some(active) = world_ticks.iter().find(|world| world.active) {
+                    render_sync.push(active.id, &active.compound);
                 }
 
-                // Update transforms with the new boson values
-                {
-                    // Unmodified so the transform update is not triggered at the next goaround
-                    state_ecs.iter_mut_duo_unmod(
-                        |_entity, transform: &mut Transform3D, boson_object: &mut BosonObject| {
-                            boson_object.read_position(|boson_pos| {
-                                transform.position(|transform_pos| {
-                                    transform_pos.x = boson_pos.x as f32;
-                                    transform_pos.y = boson_pos.y as f32;
-                                    transform_pos.z = boson_pos.z as f32;
-                                })
-                            })
-                        },
-                    );
-                }
-
-                if let Ok(running) = state_state_running.read() {
-                    if !*running {
-                        warn!("State Update Thread Exiting....");
-                        break;
-                    }
-                }
+                // Frame jobs started during the tick are done before the next one
+                gluon::fence();
 
                 // TODO: make tick rate dependent on how long update takes
                 // Sleep for a little so that the rest of Isotope can catch up
-                std::thread::sleep(state_tick_rate);
+                thread_signal.sleep(state_tick_rate);
             }
+
+            warn!("State Update Thread Exiting....");
         });
 
         Ok(Self {
             photon,
+            render_scene,
+            uploaded_light_layers: None,
+            particle_meshes: ParticleMeshes::default(),
             asset_server,
-            compound,
+            worlds,
+            rendered_world: None,
             state,
-            boson,
-            running,
+            lifecycle_hooks,
+            schedule,
+            paused: RwLock::new(false),
+            mouse_filter: RwLock::new(MouseFilter::default()),
+            background_policy: RwLock::new(BackgroundPolicy::default()),
+            focused: true,
+            occluded: false,
+            in_background: false,
+            background_paused: RwLock::new(false),
             time,
+            tick_start,
             tick_rate,
+            fixed_timestep,
+            last_frame_time: Instant::now(),
+            physics_debug: RwLock::new(false),
+            frame_capture: FrameCaptureState::default(),
+            frame_capture_key: RwLock::new(None),
             gpu_controller,
-            state_thread: (state_running, state_thread_handle),
+            state_thread: (state_signal, state_thread_handle),
         })
     }
+
+    /// Sets the timestep `IsotopeState::fixed_update` is called with.
+    ///
+    /// # Arguments
+    /// * `fixed_timestep` - The time between fixed updates, at least a millisecond
+    pub fn set_fixed_timestep(&self, fixed_timestep: Duration) {
+        if fixed_timestep < MIN_FIXED_TIMESTEP {
+            warn!(
+                "Fixed timestep of {:?} is shorter than {:?}, using {:?}",
+                fixed_timestep, MIN_FIXED_TIMESTEP, MIN_FIXED_TIMESTEP
+            );
+        }
+        let fixed_timestep = fixed_timestep.max(MIN_FIXED_TIMESTEP);
+
+        match self.fixed_timestep.write() {
+            Ok(mut timestep) => *timestep = fixed_timestep,
+            Err(err) => error!("Failed to set fixed timestep: {}", err),
+        }
+    }
+
+    /// Enables or disables drawing the colliders and velocities of every physics object.
+    ///
+    /// # Arguments
+    /// * `enabled` - Whether the physics debug overlay is drawn
+    pub fn set_physics_debug(&self, enabled: bool) {
+        match self.physics_debug.write() {
+            Ok(mut physics_debug) => *physics_debug = enabled,
+            Err(err) => error!("Failed to set physics debug: {}", err),
+        }
+    }
+
+    /// Binds a debug key that captures the next frame in an attached graphics
+    /// debugger such as RenderDoc, see [`FrameCapture`].
+    ///
+    /// # Arguments
+    /// * `key` - The key that takes the capture, none to unbind it
+    pub fn set_frame_capture_key(&self, key: Option<KeyCode>) {
+        match self.frame_capture_key.write() {
+            Ok(mut frame_capture_key) => *frame_capture_key = key,
+            Err(err) => error!("Failed to set frame capture key: {}", err),
+        }
+    }
+
+    /// Changes how the physics engine of every world resolves contacts.
+    ///
+    /// # Arguments
+    /// * `settings` - The new solver settings, used from the next physics step on
+    pub fn set_solver_settings(&self, settings: SolverSettings) {
+        match self.worlds.read() {
+            Ok(worlds) => {
+                for boson in worlds.bosons() {
+                    match boson.read() {
+                        Ok(boson) => boson.set_solver_settings(settings),
+                        Err(err) => error!("Failed to set solver settings: {}", err),
+                    }
+                }
+            }
+            Err(err) => error!("Failed to set solver settings: {}", err),
+        }
+    }
+
+    /// Returns the counters and timings of the last physics step of the active world.
+    ///
+    /// # Returns
+    /// The stats, or empty stats if the physics engine could not be read
+    pub fn physics_stats(&self) -> PhysicsStats {
+        match self.boson().read() {
+            Ok(boson) => boson.stats(),
+            Err(err) => {
+                error!("Failed to read physics stats: {}", err);
+                PhysicsStats::default()
+            }
+        }
+    }
+
+    /// Returns the counts of the live GPU objects and the memory they hold,
+    /// see `GpuController::stats`.
+    ///
+    /// Memory is only counted with the `gpu_counters` feature.
+    pub fn gpu_stats(&self) -> GpuStats {
+        self.gpu_controller.stats()
+    }
+
+    /// Casts a ray against the bodies of the entities of the active world,
+    /// such as for mouse picking.
+    ///
+    /// # Arguments
+    /// * `origin` - Start of the ray in world space
+    /// * `direction` - Direction of the ray in world space, doesn't need to be normalized
+    /// * `max_distance` - How far from the origin bodies are hit
+    /// * `filter` - Returns whether the body of an entity can be hit
+    ///
+    /// # Returns
+    /// The closest hit, or `None` if the ray hits nothing within `max_distance`
+    pub fn raycast(
+        &self,
+        origin: Vector3<f32>,
+        direction: Vector3<f32>,
+        max_distance: f32,
+        filter: impl Fn(Entity) -> bool,
+    ) -> Option<RaycastHit> {
+        physics::raycast(
+            &self.compound(),
+            &self.boson(),
+            origin,
+            direction,
+            max_distance,
+            filter,
+        )
+    }
+
+    /// Creates a new empty world with its own physics.
+    ///
+    /// New worlds start in the background with `WorldCadence::Paused`, fill
+    /// them with `with_world` and switch to them with `set_active_world`.
+    ///
+    /// # Returns
+    /// The id of the new world, or an error if the worlds lock is poisoned
+    pub fn create_world(&self) -> Result<WorldId> {
+        match self.worlds.write() {
+            Ok(mut worlds) => Ok(worlds.create()),
+            Err(err) => Err(anyhow!("Worlds lock poisoned: {}", err)),
+        }
+    }
+
+    /// Removes a world and everything in it.
+    ///
+    /// # Arguments
+    /// * `id` - The world to remove, must not be the active world
+    pub fn remove_world(&self, id: WorldId) -> Result<()> {
+        match self.worlds.write() {
+            Ok(mut worlds) => worlds.remove(id),
+            Err(err) => Err(anyhow!("Worlds lock poisoned: {}", err)),
+        }
+    }
+
+    /// Returns the world that is rendered and passed to the `IsotopeState`
+    pub fn active_world(&self) -> WorldId {
+        self.worlds
+            .read()
+            .map(|worlds| worlds.active_id())
+            .unwrap_or(WorldId::MAIN)
+    }
+
+    /// Makes a world the one that is rendered, receives input and is passed
+    /// to the `IsotopeState`.
+    ///
+    /// The previously active world continues at its own `WorldCadence`.
+    ///
+    /// # Arguments
+    /// * `id` - The world to switch to
+    pub fn set_active_world(&self, id: WorldId) -> Result<()> {
+        match self.worlds.write() {
+            Ok(mut worlds) => worlds.set_active(id),
+            Err(err) => Err(anyhow!("Worlds lock poisoned: {}", err)),
+        }
+    }
+
+    /// Sets how often a world is simulated while it is not active.
+    ///
+    /// # Arguments
+    /// * `id` - The world to change
+    /// * `cadence` - How often the world is simulated in the background
+    pub fn set_world_cadence(&self, id: WorldId, cadence: WorldCadence) -> Result<()> {
+        match self.worlds.write() {
+            Ok(mut worlds) => worlds.set_cadence(id, cadence),
+            Err(err) => Err(anyhow!("Worlds lock poisoned: {}", err)),
+        }
+    }
+
+    /// Provides access to the entities of any world through a callback.
+    ///
+    /// # Arguments
+    /// * `id` - The world to access
+    /// * `callback` - Function that receives the entities of the world
+    ///
+    /// # Returns
+    /// The return value of the callback function, or an error if the world does not exist
+    ///
+    /// # Example
+    /// ```ignore
+    /// let menu = isotope.create_world()?;
+    /// isotope.with_world(menu, |compound| {
+    ///     compound.spawn((Transform3D::default(),));
+    /// })?;
+    /// isotope.set_active_world(menu)?;
+    /// ```
+    pub fn with_world<F, R>(&self, id: WorldId, callback: F) -> Result<R>
+    where
+        F: FnOnce(&Compound) -> R,
+    {
+        let compound = match self.worlds.read() {
+            Ok(worlds) => worlds.get(id)?.compound.clone(),
+            Err(err) => return Err(anyhow!("Worlds lock poisoned: {}", err)),
+        };
+
+        Ok(callback(&compound))
+    }
+
+    /// Moves an entity with all of its molecules from one world to another,
+    /// for example to stream the player between levels.
+    ///
+    /// A physics body of the entity is moved to the physics of the new world.
+    ///
+    /// # Arguments
+    /// * `entity` - The entity to move
+    /// * `from` - The world the entity is in
+    /// * `to` - The world to move the entity to
+    ///
+    /// # Returns
+    /// The ID of the entity in the new world, or an error if either world does not exist
+    ///
+    /// # Example
+    /// ```ignore
+    /// let player = isotope.move_entity(player, WorldId::MAIN, level_two)?;
+    /// isotope.set_active_world(level_two)?;
+    /// ```
+    pub fn move_entity(&self, entity: Entity, from: WorldId, to: WorldId) -> Result<Entity> {
+        let ((from_compound, from_boson), (to_compound, to_boson)) = match self.worlds.read() {
+            Ok(worlds) => {
+                let from = worlds.get(from)?;
+                let to = worlds.get(to)?;
+
+                (
+                    (from.compound.clone(), from.boson.clone()),
+                    (to.compound.clone(), to.boson.clone()),
+                )
+            }
+            Err(err) => return Err(anyhow!("Worlds lock poisoned: {}", err)),
+        };
+
+        let mut boson_object = None;
+        from_compound.iter_mol(|boson_entity, object: &BosonObject| {
+            if boson_entity == entity {
+                boson_object = Some(object.clone());
+            }
+        });
+
+        // The body already carries its `BosonCompliant` marker, so it is
+        // handed to the new physics here instead of by the state thread
+        if let Some(boson_object) = &boson_object {
+            match from_boson.write() {
+                Ok(mut boson) => boson.remove_object(boson_object),
+                Err(err) => return Err(anyhow!("Boson lock poisoned: {}", err)),
+            }
+        }
+
+        let moved = from_compound.move_entity(entity, &to_compound);
+
+        if let Some(boson_object) = &boson_object {
+            match to_boson.write() {
+                Ok(mut boson) => _ = boson.add_object(boson_object),
+                Err(err) => return Err(anyhow!("Boson lock poisoned: {}", err)),
+            }
+        }
+
+        info!("Moved entity {} from {:?} to {:?}", entity, from, to);
+        Ok(moved)
+    }
+
+    /// Moves every entity of a world into another one with their physics
+    /// bodies, such as a level that was streamed in on a loading world.
+    ///
+    /// The emptied world is kept, remove it with `remove_world` once it is
+    /// no longer needed or load the next level into it.
+    ///
+    /// # Arguments
+    /// * `from` - The world to empty
+    /// * `into` - The world to move the entities to
+    ///
+    /// # Returns
+    /// The ID of every moved entity in `into` by its ID in `from`, to remap
+    /// molecules that refer to other entities, or an error if either world
+    /// does not exist
+    ///
+    /// # Example
+    /// ```ignore
+    /// let loading = isotope.create_world()?;
+    /// isotope.with_world(loading, |compound| {
+    ///     compound.spawn((Transform::default(), Light::default()));
+    /// })?;
+    ///
+    /// // Once the level is loaded
+    /// isotope.merge_world(loading, WorldId::MAIN)?;
+    /// isotope.remove_world(loading)?;
+    /// ```
+    pub fn merge_world(&self, from: WorldId, into: WorldId) -> Result<HashMap<Entity, Entity>> {
+        if from == into {
+            return Err(anyhow!("Cannot merge world {:?} into itself", from));
+        }
+
+        let ((from_compound, from_boson), (into_compound, into_boson)) = match self.worlds.read() {
+            Ok(worlds) => {
+                let from = worlds.get(from)?;
+                let into = worlds.get(into)?;
+
+                (
+                    (from.compound.clone(), from.boson.clone()),
+                    (into.compound.clone(), into.boson.clone()),
+                )
+            }
+            Err(err) => return Err(anyhow!("Worlds lock poisoned: {}", err)),
+        };
+
+        let mut boson_objects = Vec::new();
+        from_compound.iter_mol(|_entity, object: &BosonObject| {
+            boson_objects.push(object.clone());
+        });
+
+        // The bodies carry their `BosonCompliant` marker, the same as when moving one entity
+        match from_boson.write() {
+            Ok(mut boson) => {
+                for boson_object in boson_objects.iter() {
+                    boson.remove_object(boson_object);
+                }
+            }
+            Err(err) => return Err(anyhow!("Boson lock poisoned: {}", err)),
+        }
+
+        let moved = from_compound.move_all_entities(&into_compound);
+
+        match into_boson.write() {
+            Ok(mut boson) => {
+                for boson_object in boson_objects.iter() {
+                    _ = boson.add_object(boson_object);
+                }
+            }
+            Err(err) => return Err(anyhow!("Boson lock poisoned: {}", err)),
+        }
+
+        info!(
+            "Merged {} entities from {:?} into {:?}",
+            moved.len(),
+            from,
+            into
+        );
+        Ok(moved)
+    }
+
+    /// Subscribes a hook to the moments of the engine loop, such as the start
+    /// of a frame or a world stepping its physics.
+    ///
+    /// Hooks are called on the thread that reaches the moment, frame and window
+    /// events on the render loop and physics events on the state thread, with
+    /// the entities of the world the event belongs to.
+    ///
+    /// # Arguments
+    /// * `hook` - Function called with the entities, assets and event
+    ///
+    /// # Returns
+    /// An id that removes the hook when passed to `remove_lifecycle_hook`
+    ///
+    /// # Example
+    /// ```ignore
+    /// isotope.add_lifecycle_hook(|compound, _assets, event| {
+    ///     if let LifecycleEvent::PostPhysics { .. } = event {
+    ///         compound.iter_mol(|_entity, transform: &Transform3D| {
+    ///             // Check for entities that fell out of the world
+    ///         });
+    ///     }
+    /// });
+    /// ```
+    pub fn add_lifecycle_hook<F>(&self, hook: F) -> LifecycleHookId
+    where
+        F: Fn(&Compound, &AssetServer, &LifecycleEvent) + Send + Sync + 'static,
+    {
+        self.lifecycle_hooks.add(hook)
+    }
+
+    /// Unsubscribes a hook added with `add_lifecycle_hook`.
+    ///
+    /// # Returns
+    /// `true` if the hook existed
+    pub fn remove_lifecycle_hook(&self, id: LifecycleHookId) -> bool {
+        self.lifecycle_hooks.remove(id)
+    }
+
+    /// Adds a system that every simulated world runs each tick.
+    ///
+    /// Systems that don't conflict with each other in the molecules they read
+    /// and write run in parallel. They run after the engine systems unless
+    /// they are ordered before one of them, such as `PRE_PHYSICS_SYSTEM`.
+    ///
+    /// # Arguments
+    /// * `system` - The system, replacing any system with the same name
+    ///
+    /// # Returns
+    /// An error without adding the system if its ordering forms a cycle
+    ///
+    /// # Example
+    /// ```ignore
+    /// isotope.add_system(
+    ///     System::new("spin", |compound, context: &SystemContext| {
+    ///         compound.iter_mut_duo(|_entity, spinner: &mut Spinner, transform: &mut Transform3D| {
+    ///             transform.rotation(|rotation| *rotation = *rotation * spinner.step(context.delta_t));
+    ///         });
+    ///     })
+    ///     .writes::<Transform3D>()
+    ///     .reads::<Spinner>()
+    ///     .before(PRE_PHYSICS_SYSTEM),
+    /// )?;
+    /// ```
+    pub fn add_system(&self, system: IsotopeSystem) -> Result<()> {
+        let mut schedule = self
+            .schedule
+            .lock()
+            .map_err(|err| anyhow!("Failed to add system: {}", err))?;
+
+        let name = system.name().to_string();
+        schedule.add_system(system);
+
+        if let Err(err) = schedule.batches() {
+            schedule.remove_system(&name);
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// Removes a system added with `add_system`.
+    ///
+    /// # Returns
+    /// `true` if the system existed
+    pub fn remove_system(&self, name: &str) -> bool {
+        match self.schedule.lock() {
+            Ok(mut schedule) => schedule.remove_system(name),
+            Err(err) => {
+                error!("Failed to remove system: {}", err);
+                false
+            }
+        }
+    }
+
+    /// Inserts a custom pass into the frame of every camera.
+    ///
+    /// The pass records into the camera's command encoder at the stage,
+    /// with the color and depth views of the frame and the camera's bind
+    /// group, so effects can be added without changing the renderer.
+    ///
+    /// # Arguments
+    /// * `stage` - The point in the frame the pass runs at
+    /// * `hook` - Records the pass, called once per camera every frame
+    ///
+    /// # Returns
+    /// An id that removes the pass when passed to `remove_render_pass`
+    ///
+    /// # Example
+    /// ```ignore
+    /// isotope.add_render_pass(RenderStage::AfterOpaque, move |ctx, encoder| {
+    ///     let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+    ///         label: Some("Outline Pass"),
+    ///         color_attachments: &[Some(RenderPassColorAttachment {
+    ///             view: ctx.color_view,
+    ///             resolve_target: None,
+    ///             ops: Operations { load: LoadOp::Load, store: StoreOp::Store },
+    ///         })],
+    ///         ..Default::default()
+    ///     });
+    ///
+    ///     render_pass.set_pipeline(&outline_pipeline);
+    ///     render_pass.set_bind_group(0, ctx.camera_bind_group, &[]);
+    ///     render_pass.draw(0..3, 0..1);
+    /// });
+    /// ```
+    pub fn add_render_pass<F>(&self, stage: RenderStage, hook: F) -> RenderPassHookId
+    where
+        F: Fn(&RenderPassContext, &mut CommandEncoder) + Send + Sync + 'static,
+    {
+        self.photon.add_pass(stage, hook)
+    }
+
+    /// Removes a pass added with `add_render_pass`.
+    ///
+    /// # Returns
+    /// `true` if the pass existed
+    pub fn remove_render_pass(&self, id: RenderPassHookId) -> bool {
+        self.photon.remove_pass(id)
+    }
+
+    /// Sets where mouse movement comes from and how it is scaled and smoothed
+    /// before it reaches `IsotopeState::mouse_is_moved`.
+    ///
+    /// # Arguments
+    /// * `settings` - The input mode, sensitivity and smoothing of the mouse
+    ///
+    /// # Example
+    /// ```ignore
+    /// isotope.set_mouse_settings(MouseSettings {
+    ///     mode: MouseInputMode::Raw,
+    ///     sensitivity: 0.5,
+    ///     smoothing: 0.3,
+    /// });
+    /// ```
+    pub fn set_mouse_settings(&self, settings: MouseSettings) {
+        match self.mouse_filter.write() {
+            Ok(mut mouse_filter) => mouse_filter.set_settings(settings),
+            Err(err) => error!("Failed to set mouse settings: {}", err),
+        }
+    }
+
+    /// Passes input to the action maps of the active world
+    fn update_action_maps<F>(&self, mut callback: F)
+    where
+        F: FnMut(&mut ActionMap) + Send + Sync,
+    {
+        self.compound()
+            .iter_mut_mol(|_entity, action_map: &mut ActionMap| callback(action_map));
+    }
+
+    /// Returns the time of an input arriving now, in seconds since the
+    /// engine started and since the start of the last tick
+    fn input_time(&self) -> (f32, Duration) {
+        let elapsed = self.time.elapsed();
+        let tick_start = self
+            .tick_start
+            .read()
+            .map(|tick_start| *tick_start)
+            .unwrap_or_default();
+
+        (elapsed.as_secs_f32(), elapsed.saturating_sub(tick_start))
+    }
+
+    /// Passes filtered mouse movement to the state and the action maps
+    fn mouse_moved(&self, delta: (f64, f64)) {
+        let (_, offset) = self.input_time();
+        self.update_action_maps(|action_map| action_map.mouse_moved(delta, offset));
+
+        self.state
+            .write()
+            .map(|mut state| {
+                state.mouse_is_moved(
+                    &self.compound(),
+                    &self.asset_server,
+                    delta,
+                    self.time.elapsed().as_secs_f32(),
+                );
+            })
+            .unwrap_or_else(|err| {
+                warn!(
+                    "Failed to update game state with mouse movement: {} continuing...",
+                    err
+                );
+            });
+    }
+
+    /// Sets what the engine does while the window is unfocused, minimized or hidden.
+    ///
+    /// Games that want to pause themselves can listen for
+    /// `LifecycleEvent::EnteredBackground` with `add_lifecycle_hook`.
+    ///
+    /// # Arguments
+    /// * `policy` - The frame rate and simulation behavior in the background
+    pub fn set_background_policy(&self, policy: BackgroundPolicy) {
+        match self.background_policy.write() {
+            Ok(mut background_policy) => *background_policy = policy,
+            Err(err) => error!("Failed to set background policy: {}", err),
+        }
+
+        self.apply_background_policy();
+    }
+
+    /// Time between frames while in the background, if they are throttled
+    fn background_frame_interval(&self) -> Option<Duration> {
+        if !self.in_background {
+            return None;
+        }
+
+        self.background_policy
+            .read()
+            .ok()
+            .and_then(|policy| policy.frame_interval())
+    }
+
+    /// Pauses or resumes the simulation and audio to match the policy and
+    /// whether the window is in the background
+    fn apply_background_policy(&self) {
+        let policy = self.background_policy.read().map(|policy| *policy);
+        let pause =
+            self.in_background && policy.as_ref().is_ok_and(|policy| policy.pause_simulation);
+        let pause_audio =
+            self.in_background && policy.as_ref().is_ok_and(|policy| policy.pause_audio);
+
+        _ = self.background_paused.write().map(|mut paused| {
+            *paused = pause;
+        });
+        self.update_state_pause();
+
+        match self.worlds.write() {
+            Ok(mut worlds) => worlds.set_suspended(pause),
+            Err(err) => error!("Failed to pause world physics: {}", err),
+        }
+
+        self.pause_audio(pause_audio);
+    }
+
+    /// Pauses or resumes the voice chat of every world
+    #[cfg(feature = "voice")]
+    fn pause_audio(&self, pause: bool) {
+        let compounds = match self.worlds.read() {
+            Ok(worlds) => worlds.compounds(),
+            Err(err) => {
+                error!("Failed to pause audio: {}", err);
+                return;
+            }
+        };
+
+        for compound in compounds {
+            compound.resource_mut::<network::VoiceChat, _>(|voice| voice.set_paused(pause));
+        }
+    }
+
+    #[cfg(not(feature = "voice"))]
+    fn pause_audio(&self, _pause: bool) {}
+
+    /// Pauses the state thread while the application is suspended or paused
+    /// in the background, resuming it otherwise
+    fn update_state_pause(&self) {
+        let paused = self.paused.read().is_ok_and(|paused| *paused)
+            || self.background_paused.read().is_ok_and(|paused| *paused);
+
+        self.state_thread.0.set_paused(paused);
+    }
+
+    /// Enters or leaves the background after the focus or visibility of the window changed
+    fn update_background(&mut self) {
+        let in_background = !self.focused || self.occluded;
+        if in_background == self.in_background {
+            return;
+        }
+
+        self.in_background = in_background;
+        self.apply_background_policy();
+
+        if in_background {
+            info!("Isotope entered the background");
+            self.fire_lifecycle_event(LifecycleEvent::EnteredBackground);
+        } else {
+            info!("Isotope left the background");
+            self.fire_lifecycle_event(LifecycleEvent::LeftBackground);
+        }
+    }
+
+    /// Fires a lifecycle event with the entities of the active world
+    fn fire_lifecycle_event(&self, event: LifecycleEvent) {
+        self.lifecycle_hooks
+            .fire(&self.compound(), &self.asset_server, event);
+    }
+
+    /// Returns the entities of the active world
+    pub(crate) fn compound(&self) -> Arc<Compound> {
+        match self.worlds.read() {
+            Ok(worlds) => worlds.active().compound.clone(),
+            Err(err) => err.into_inner().active().compound.clone(),
+        }
+    }
+
+    /// Returns the entities of every world
+    pub(crate) fn compounds(&self) -> Vec<Arc<Compound>> {
+        match self.worlds.read() {
+            Ok(worlds) => worlds.compounds(),
+            Err(err) => err.into_inner().compounds(),
+        }
+    }
+
+    /// Returns the physics engine of the active world
+    pub(crate) fn boson(&self) -> Arc<RwLock<Boson>> {
+        match self.worlds.read() {
+            Ok(worlds) => worlds.active().boson.clone(),
+            Err(err) => err.into_inner().active().boson.clone(),
+        }
+    }
+
+    /// Provides read access to the concrete game state through a callback.
+    ///
+    /// # Arguments
+    /// * `callback` - Function that receives a reference to the state
+    ///
+    /// # Returns
+    /// The return value of the callback function, or an error if the state lock is poisoned
+    pub fn read_state<F, R>(&self, callback: F) -> Result<R>
+    where
+        F: FnOnce(&S) -> R,
+    {
+        match self.state.read() {
+            Ok(state) => Ok(callback(&state)),
+            Err(err) => Err(anyhow!("State lock poisoned: {}", err)),
+        }
+    }
+
+    /// Provides mutable access to the concrete game state through a callback.
+    ///
+    /// # Arguments
+    /// * `callback` - Function that receives a mutable reference to the state
+    ///
+    /// # Returns
+    /// The return value of the callback function, or an error if the state lock is poisoned
+    pub fn modify_state<F, R>(&self, callback: F) -> Result<R>
+    where
+        F: FnOnce(&mut S) -> R,
+    {
+        match self.state.write() {
+            Ok(mut state) => Ok(callback(&mut state)),
+            Err(err) => Err(anyhow!("State lock poisoned: {}", err)),
+        }
+    }
 }
 
-pub struct IsotopeApplication {
+pub struct IsotopeApplication<S: IsotopeState> {
     window: Option<RenderingWindow>,
-    isotope: Isotope,
+    isotope: Isotope<S>,
+    // The control flow of the event loop before frames were throttled in the background
+    foreground_control_flow: Option<ControlFlow>,
 }
 
-impl IsotopeApplication {
-    pub fn new<I>(state: I) -> Result<Self>
-    where
-        I: IsotopeState,
-    {
+/// An application whose state is only known through the `IsotopeState` trait,
+/// useful for plugins and tools that load states dynamically.
+pub type DynIsotopeApplication = IsotopeApplication<Box<dyn IsotopeState>>;
+
+impl<S: IsotopeState> IsotopeApplication<S> {
+    pub fn new(state: S) -> Result<Self> {
         info!("Creating Gpu Controller");
         let gpu_controller = block_on(GpuController::new(
             Some(Features::MAPPABLE_PRIMARY_BUFFERS),
             None,
             Some(SurfaceConfiguration {
-                usage: TextureUsages::RENDER_ATTACHMENT,
+                // Screenshots copy from the surface
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
                 format: TextureFormat::Rgba8UnormSrgb,
                 width: 1,
                 height: 1,
@@ -251,11 +1116,209 @@ impl IsotopeApplication {
         Ok(Self {
             window: None,
             isotope: Isotope::new(gpu_controller, state)?,
+            foreground_control_flow: None,
         })
     }
+
+    /// Sets the timestep of the fixed update.
+    ///
+    /// See [`Isotope::set_fixed_timestep`]
+    pub fn set_fixed_timestep(&self, fixed_timestep: Duration) {
+        self.isotope.set_fixed_timestep(fixed_timestep);
+    }
+
+    /// Enables or disables the physics debug overlay.
+    ///
+    /// See [`Isotope::set_physics_debug`]
+    pub fn set_physics_debug(&self, enabled: bool) {
+        self.isotope.set_physics_debug(enabled);
+    }
+
+    /// Binds the key that captures a frame in a graphics debugger.
+    ///
+    /// See [`Isotope::set_frame_capture_key`]
+    pub fn set_frame_capture_key(&self, key: Option<KeyCode>) {
+        self.isotope.set_frame_capture_key(key);
+    }
+
+    /// Changes how the physics engine resolves contacts.
+    ///
+    /// See [`Isotope::set_solver_settings`]
+    pub fn set_solver_settings(&self, settings: SolverSettings) {
+        self.isotope.set_solver_settings(settings);
+    }
+
+    /// Returns the counters and timings of the last physics step.
+    ///
+    /// See [`Isotope::physics_stats`]
+    pub fn physics_stats(&self) -> PhysicsStats {
+        self.isotope.physics_stats()
+    }
+
+    /// Returns the counts of the live GPU objects.
+    ///
+    /// See [`Isotope::gpu_stats`]
+    pub fn gpu_stats(&self) -> GpuStats {
+        self.isotope.gpu_stats()
+    }
+
+    /// Casts a ray against the bodies of the entities of the active world.
+    ///
+    /// See [`Isotope::raycast`]
+    pub fn raycast(
+        &self,
+        origin: Vector3<f32>,
+        direction: Vector3<f32>,
+        max_distance: f32,
+        filter: impl Fn(Entity) -> bool,
+    ) -> Option<RaycastHit> {
+        self.isotope
+            .raycast(origin, direction, max_distance, filter)
+    }
+
+    /// Creates a new empty world.
+    ///
+    /// See [`Isotope::create_world`]
+    pub fn create_world(&self) -> Result<WorldId> {
+        self.isotope.create_world()
+    }
+
+    /// Removes a world and everything in it.
+    ///
+    /// See [`Isotope::remove_world`]
+    pub fn remove_world(&self, id: WorldId) -> Result<()> {
+        self.isotope.remove_world(id)
+    }
+
+    /// Returns the world that is rendered and passed to the `IsotopeState`.
+    ///
+    /// See [`Isotope::active_world`]
+    pub fn active_world(&self) -> WorldId {
+        self.isotope.active_world()
+    }
+
+    /// Switches the active world.
+    ///
+    /// See [`Isotope::set_active_world`]
+    pub fn set_active_world(&self, id: WorldId) -> Result<()> {
+        self.isotope.set_active_world(id)
+    }
+
+    /// Sets how often a world is simulated while it is not active.
+    ///
+    /// See [`Isotope::set_world_cadence`]
+    pub fn set_world_cadence(&self, id: WorldId, cadence: WorldCadence) -> Result<()> {
+        self.isotope.set_world_cadence(id, cadence)
+    }
+
+    /// Moves an entity with all of its molecules from one world to another.
+    ///
+    /// See [`Isotope::move_entity`]
+    pub fn move_entity(&self, entity: Entity, from: WorldId, to: WorldId) -> Result<Entity> {
+        self.isotope.move_entity(entity, from, to)
+    }
+
+    /// Moves every entity of a world into another one with their physics bodies.
+    ///
+    /// See [`Isotope::merge_world`]
+    pub fn merge_world(&self, from: WorldId, into: WorldId) -> Result<HashMap<Entity, Entity>> {
+        self.isotope.merge_world(from, into)
+    }
+
+    /// Sets the input mode, sensitivity and smoothing of the mouse.
+    ///
+    /// See [`Isotope::set_mouse_settings`]
+    pub fn set_mouse_settings(&self, settings: MouseSettings) {
+        self.isotope.set_mouse_settings(settings);
+    }
+
+    /// Sets what the engine does while the window is in the background.
+    ///
+    /// See [`Isotope::set_background_policy`]
+    pub fn set_background_policy(&self, policy: BackgroundPolicy) {
+        self.isotope.set_background_policy(policy);
+    }
+
+    /// Subscribes a hook to the moments of the engine loop.
+    ///
+    /// See [`Isotope::add_lifecycle_hook`]
+    pub fn add_lifecycle_hook<F>(&self, hook: F) -> LifecycleHookId
+    where
+        F: Fn(&Compound, &AssetServer, &LifecycleEvent) + Send + Sync + 'static,
+    {
+        self.isotope.add_lifecycle_hook(hook)
+    }
+
+    /// Unsubscribes a lifecycle hook.
+    ///
+    /// See [`Isotope::remove_lifecycle_hook`]
+    pub fn remove_lifecycle_hook(&self, id: LifecycleHookId) -> bool {
+        self.isotope.remove_lifecycle_hook(id)
+    }
+
+    /// Adds a system that every simulated world runs each tick.
+    ///
+    /// See [`Isotope::add_system`]
+    pub fn add_system(&self, system: IsotopeSystem) -> Result<()> {
+        self.isotope.add_system(system)
+    }
+
+    /// Removes a system.
+    ///
+    /// See [`Isotope::remove_system`]
+    pub fn remove_system(&self, name: &str) -> bool {
+        self.isotope.remove_system(name)
+    }
+
+    /// Inserts a custom pass into the frame of every camera.
+    ///
+    /// See [`Isotope::add_render_pass`]
+    pub fn add_render_pass<F>(&self, stage: RenderStage, hook: F) -> RenderPassHookId
+    where
+        F: Fn(&RenderPassContext, &mut CommandEncoder) + Send + Sync + 'static,
+    {
+        self.isotope.add_render_pass(stage, hook)
+    }
+
+    /// Removes a custom render pass.
+    ///
+    /// See [`Isotope::remove_render_pass`]
+    pub fn remove_render_pass(&self, id: RenderPassHookId) -> bool {
+        self.isotope.remove_render_pass(id)
+    }
+
+    /// Provides access to the entities of any world through a callback.
+    ///
+    /// See [`Isotope::with_world`]
+    pub fn with_world<F, R>(&self, id: WorldId, callback: F) -> Result<R>
+    where
+        F: FnOnce(&Compound) -> R,
+    {
+        self.isotope.with_world(id, callback)
+    }
+
+    /// Provides read access to the concrete game state through a callback.
+    ///
+    /// See [`Isotope::read_state`]
+    pub fn read_state<F, R>(&self, callback: F) -> Result<R>
+    where
+        F: FnOnce(&S) -> R,
+    {
+        self.isotope.read_state(callback)
+    }
+
+    /// Provides mutable access to the concrete game state through a callback.
+    ///
+    /// See [`Isotope::modify_state`]
+    pub fn modify_state<F, R>(&self, callback: F) -> Result<R>
+    where
+        F: FnOnce(&mut S) -> R,
+    {
+        self.isotope.modify_state(callback)
+    }
 }
 
-impl ApplicationHandler for IsotopeApplication {
+impl<S: IsotopeState> ApplicationHandler for IsotopeApplication<S> {
     fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
         info!("Isotope Resumed");
 
@@ -269,10 +1332,26 @@ impl ApplicationHandler for IsotopeApplication {
                 title: "Isotope".to_string(),
             },
         ) {
-            // Add the window controller to Isotope
-            self.isotope
-                .compound
-                .spawn((WindowController::new(rendering_window.window.clone()),));
+            // Point the window controllers of every world at the new window
+            for compound in self.isotope.compounds() {
+                attach_window(&compound, &rendering_window.window);
+            }
+
+            // The surface is new, so it needs to be configured before it is used
+            let size = rendering_window.window.inner_size();
+            if self
+                .isotope
+                .gpu_controller
+                .write_surface_config(|sc| {
+                    sc.width = size.width.max(1);
+                    sc.height = size.height.max(1);
+                })
+                .is_ok()
+            {
+                self.isotope
+                    .gpu_controller
+                    .configure_surface(&rendering_window.surface);
+            }
 
             self.window = Some(rendering_window);
             self.isotope.photon =
@@ -283,16 +1362,74 @@ impl ApplicationHandler for IsotopeApplication {
                         panic!();
                     }
                 };
+
+            // The new renderer starts without any lights or particle meshes
+            self.isotope.uploaded_light_layers = None;
+            self.isotope.particle_meshes.clear();
         }
 
-        _ = self.isotope.running.write().and_then(|mut running| {
-            *running = true;
-            Ok(())
+        self.isotope.state_thread.0.start();
+
+        // Coming back from the background
+        let was_paused = self
+            .isotope
+            .paused
+            .write()
+            .map(|mut paused| std::mem::replace(&mut *paused, false))
+            .unwrap_or(false);
+        self.isotope.update_state_pause();
+
+        if was_paused {
+            self.isotope
+                .state
+                .write()
+                .map(|mut state| {
+                    state.resumed(&self.isotope.compound(), &self.isotope.asset_server);
+                })
+                .unwrap_or_else(|err| {
+                    warn!("Failed to resume game state: {} continuing...", err);
+                });
+        }
+    }
+
+    fn suspended(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop) {
+        info!("Isotope Suspended");
+
+        // Mobile platforms destroy the native surface while in the background
+        self.window = None;
+
+        _ = self.isotope.paused.write().map(|mut paused| {
+            *paused = true;
         });
+        self.isotope.update_state_pause();
+
+        self.isotope
+            .state
+            .write()
+            .map(|mut state| {
+                state.suspended(&self.isotope.compound(), &self.isotope.asset_server);
+            })
+            .unwrap_or_else(|err| {
+                warn!("Failed to suspend game state: {} continuing...", err);
+            });
     }
 
-    fn about_to_wait(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop) {
+    fn about_to_wait(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
         if let Some(window) = self.window.as_ref() {
+            // Only render a few frames a second in the background
+            if let Some(interval) = self.isotope.background_frame_interval() {
+                let next_frame = self.isotope.last_frame_time + interval;
+
+                if Instant::now() < next_frame {
+                    self.foreground_control_flow
+                        .get_or_insert(event_loop.control_flow());
+                    event_loop.set_control_flow(ControlFlow::WaitUntil(next_frame));
+                    return;
+                }
+            } else if let Some(control_flow) = self.foreground_control_flow.take() {
+                event_loop.set_control_flow(control_flow);
+            }
+
             window.window.request_redraw();
         }
     }
@@ -309,39 +1446,131 @@ impl ApplicationHandler for IsotopeApplication {
                     WindowEvent::CloseRequested => {
                         info!("Shutting Down Isotope...");
 
-                        _ = self.isotope.running.write().and_then(|mut running| {
-                            *running = false;
-                            Ok(())
-                        });
+                        self.isotope.state_thread.0.stop();
 
                         event_loop.exit();
                     }
                     WindowEvent::RedrawRequested => {
                         if let Ok(surface_texture) = window.surface.get_current_texture() {
-                            // Update the lights if there are any modified lights
+                            // Captures have to start before any command of the frame is recorded
+                            self.isotope.frame_capture.begin_frame(
+                                &self.isotope.compound(),
+                                &self.isotope.gpu_controller,
+                            );
+
+                            let now = Instant::now();
+                            let dt = now
+                                .duration_since(self.isotope.last_frame_time)
+                                .as_secs_f32();
+                            self.isotope.last_frame_time = now;
+
+                            // Time every shader reads from the frame constants
+                            self.isotope
+                                .photon
+                                .begin_frame(self.isotope.time.elapsed().as_secs_f32(), dt);
+
+                            // Per frame state update
                             {
-                                let mut lights_changed = false;
+                                let t = self.isotope.time.elapsed().as_secs_f32();
+
                                 self.isotope
-                                    .compound
-                                    .iter_mol_mod(|_entity, _light: &Light| {
-                                        lights_changed = true;
-                                        return;
+                                    .fire_lifecycle_event(LifecycleEvent::FrameStart {
+                                        delta_t: dt,
+                                        t,
                                     });
 
-                                if lights_changed {
-                                    let mut lights = Vec::new();
-                                    self.isotope.compound.iter_mol(|_entity, light: &Light| {
-                                        lights.push(light.clone());
+                                self.isotope
+                                    .state
+                                    .write()
+                                    .map(|mut state| {
+                                        state.frame_update(
+                                            &self.isotope.compound(),
+                                            &self.isotope.asset_server,
+                                            dt,
+                                            t,
+                                        );
+                                    })
+                                    .unwrap_or_else(|err| {
+                                        warn!("Failed to run frame update: {} continuing...", err);
                                     });
-                                    self.isotope.photon.update_lights(&lights);
+                            }
+
+                            // Catch the newly active world up with the window after a switch
+                            let active_world = self.isotope.active_world();
+                            let world_changed = self.isotope.rendered_world != Some(active_world);
+                            if world_changed {
+                                attach_window(&self.isotope.compound(), &window.window);
+                                update_camera_aspects(
+                                    &self.isotope.compound(),
+                                    &self.isotope.gpu_controller,
+                                );
+                                self.isotope.rendered_world = Some(active_world);
+                            }
+
+                            // Apply the lights, cameras and model layers that changed since the last frame
+                            {
+                                let lights_changed = self.isotope.render_scene.update(
+                                    active_world,
+                                    &self.isotope.compound(),
+                                    world_changed,
+                                );
+
+                                if lights_changed {
+                                    self.isotope.uploaded_light_layers = None;
+                                }
+
+                                // Only the lights in the budget cast shadows, fading as they go in and out of it
+                                if update_light_budget(
+                                    &self.isotope.compound(),
+                                    &mut self.isotope.render_scene.lights,
+                                    dt,
+                                ) {
+                                    self.isotope.uploaded_light_layers = None;
                                 }
+
+                                // Probes bounce every light, whatever layers the cameras see
+                                let lights = self
+                                    .isotope
+                                    .render_scene
+                                    .lights
+                                    .iter()
+                                    .map(|(_, light, _)| *light)
+                                    .collect::<Vec<Light>>();
+
+                                update_light_probes(
+                                    &self.isotope.compound(),
+                                    &lights,
+                                    lights_changed,
+                                    world_changed,
+                                    &mut self.isotope.photon,
+                                );
                             }
 
+                            // Fog the lights scatter through
+                            update_fog(&self.isotope.compound(), &mut self.isotope.photon);
+
+                            // GPU particle emitters and the colliders they bounce off of
+                            update_particles(
+                                &self.isotope.compound(),
+                                &mut self.isotope.photon,
+                                &self.isotope.asset_server,
+                                &mut self.isotope.particle_meshes,
+                            );
+
+                            // Ribbons through where entities with a trail have been
+                            update_trails(&self.isotope.compound(), &mut self.isotope.photon, dt);
+
+                            // Heat haze and shockwaves bending the scene
+                            update_distortion(&self.isotope.compound(), &mut self.isotope.photon);
+
+                            // Players and selected units seen through walls
+                            update_silhouettes(&self.isotope.compound(), &mut self.isotope.photon);
+
                             // Run the instancer on any objects that have an instancer
                             {
                                 let t = self.isotope.time.elapsed().as_secs_f32();
 
-                                self.isotope.compound.iter_mut_duo(
+                                self.isotope.compound().iter_mut_duo(
                                     |_entity, model: &mut Model, instancer: &mut Instancer| {
                                         if let Err(err) = model.apply_instancer(instancer, 0.0, t) {
                                             error!("Failed To Apply Instancer: {}", err);
@@ -350,9 +1579,22 @@ impl ApplicationHandler for IsotopeApplication {
                                 )
                             }
 
+                            // Keep UI canvases in sync with the window
+                            {
+                                let size = window.window.inner_size();
+                                update_canvases(
+                                    &self.isotope.compound(),
+                                    (size.width, size.height),
+                                    window.window.scale_factor(),
+                                );
+                            }
+
+                            // Create and update cameras from their descriptors
+                            build_cameras(&self.isotope.compound(), &self.isotope.asset_server);
+
                             // Update the camera if there are any modifications
                             {
-                                self.isotope.compound.iter_mut_duo_mod(
+                                self.isotope.compound().iter_mut_duo_mod(
                                     |_entity, transform: &mut Transform3D, camera: &mut Camera| {
                                         match camera {
                                             Camera::PerspectiveCamera3D(camera) => {
@@ -374,36 +1616,188 @@ impl ApplicationHandler for IsotopeApplication {
                             // Update the model with the transform if it has been modified
                             {
                                 // Update Modified Transforms
-                                self.isotope.compound.iter_mut_duo_mod(
+                                self.isotope.compound().iter_mut_duo_mod(
                                     |_entity, transform: &mut Transform3D, model: &mut Model| {
                                         model.set_transform(transform);
                                     },
                                 );
 
                                 // Update All BosonCompliant Objects
-                                self.isotope.compound.iter_mut_trio_unmod(
+                                self.isotope.compound().iter_mut_trio_unmod(
                                     |_entity, transform: &mut Transform3D, model: &mut Model, _boson_compliant: &mut BosonCompliant| {
                                         model.set_transform(transform);
                                     }
                                 );
+
+                                // Models that stopped moving stop drawing motion vectors
+                                self.isotope.compound().iter_mut_mol_unmod(
+                                    |_entity, model: &mut Model| model.settle_motion(),
+                                );
+                            }
+
+                            // Upload any per entity material overrides that changed
+                            apply_material_overrides(
+                                &self.isotope.compound(),
+                                &self.isotope.asset_server,
+                            );
+
+                            // Rebuild the debug overlay lines
+                            {
+                                let mut gizmos = collect_gizmos(&self.isotope.compound());
+
+                                if self.isotope.physics_debug.read().is_ok_and(|debug| *debug)
+                                    && let Ok(boson) = self.isotope.boson().read()
+                                {
+                                    let mut debugger = GizmoDebugger(gizmos);
+                                    boson.debug_draw(&mut debugger);
+                                    gizmos = debugger.0;
+                                }
+
+                                self.isotope.photon.update_gizmos(&gizmos);
+                            }
+
+                            // Cameras render in order so overlay cameras draw over the ones before them
+                            let camera_entities = self.isotope.render_scene.camera_order();
+
+                            // Particles collide with the G-buffer the last camera left behind
+                            if let Some(last_camera) = camera_entities.last() {
+                                self.isotope.compound().iter_mol(|entity, camera: &Camera| {
+                                    if entity == *last_camera {
+                                        self.isotope.photon.simulate_particles(camera, dt);
+                                    }
+                                });
                             }
 
                             // Render to the display
-                            self.isotope.compound.iter_mol(|_entity, camera: &Camera| {
-                                self.isotope.photon.render(
-                                    camera,
-                                    &surface_texture.texture,
-                                    |render_pass| {
-                                        // Temp
-                                        self.isotope.compound.iter_mol(|_entity, model: &Model| {
-                                            model.render(render_pass);
-                                        });
-                                    },
-                                );
-                            });
+                            for camera_entity in camera_entities {
+                                let Some(link) = self
+                                    .isotope
+                                    .render_scene
+                                    .cameras
+                                    .get(&camera_entity)
+                                    .copied()
+                                else {
+                                    continue;
+                                };
+
+                                self.isotope.compound().iter_mol(|entity, camera: &Camera| {
+                                    if entity != camera_entity {
+                                        return;
+                                    }
+
+                                    let layers = link.layers;
+
+                                    // Only upload the lights again if this camera sees a different set
+                                    if self.isotope.uploaded_light_layers != Some(layers) {
+                                        let lights = self
+                                            .isotope
+                                            .render_scene
+                                            .lights
+                                            .iter()
+                                            .filter(|(_, _, light_layers)| {
+                                                light_layers.intersects(&layers)
+                                            })
+                                            .map(|(_, light, _)| *light)
+                                            .collect::<Vec<Light>>();
+
+                                        self.isotope.photon.update_lights(&lights);
+                                        self.isotope.uploaded_light_layers = Some(layers);
+                                    }
+
+                                    let model_layers = &self.isotope.render_scene.model_layers;
+                                    let visible_to_camera = |entity: &Entity| {
+                                        model_layers
+                                            .get(entity)
+                                            .copied()
+                                            .unwrap_or_default()
+                                            .intersects(&layers)
+                                    };
+
+                                    // Models on the viewmodel layer only draw in the viewmodel pass of the camera
+                                    let view_model_layers = link.view_model;
+                                    let in_view_model = |entity: &Entity| {
+                                        view_model_layers.is_some_and(|view_model_layers| {
+                                            model_layers
+                                                .get(entity)
+                                                .copied()
+                                                .unwrap_or_default()
+                                                .intersects(&view_model_layers)
+                                        })
+                                    };
+
+                                    // Frustum cull every instance on the GPU, the geometry pass draws the survivors
+                                    self.isotope.photon.cull(camera, |compute_pass| {
+                                        self.isotope
+                                            .compound()
+                                            .iter_without_mol::<Discarded, _, _>(
+                                                |entity, model: &Model| {
+                                                    if visible_to_camera(&entity)
+                                                        || in_view_model(&entity)
+                                                    {
+                                                        model.cull(compute_pass);
+                                                    }
+                                                },
+                                            );
+                                    });
+
+                                    self.isotope.photon.render(
+                                        camera,
+                                        &surface_texture.texture,
+                                        |render_pass, pass, encoding| {
+                                            // Temp
+                                            self.isotope
+                                                .compound()
+                                                .iter_without_mol::<Discarded, _, _>(
+                                                    |entity, model: &Model| {
+                                                        let in_world = visible_to_camera(&entity)
+                                                            && !in_view_model(&entity);
+
+                                                        let draws = match pass {
+                                                            GeometryPass::Surfaces => in_world,
+                                                            GeometryPass::ViewModel => {
+                                                                in_view_model(&entity)
+                                                            }
+                                                            GeometryPass::Motion => {
+                                                                in_world && model.has_motion()
+                                                            }
+                                                            GeometryPass::Silhouettes => {
+                                                                in_world
+                                                                    && model.silhouette().is_some()
+                                                            }
+                                                        };
+
+                                                        if draws {
+                                                            model.render(render_pass, encoding);
+                                                        }
+                                                    },
+                                                );
+                                        },
+                                    );
+                                });
+                            }
+
+                            // Motion blur of the next frame is measured from this one
+                            self.isotope.compound().iter_mut_mol_unmod(
+                                |_entity, camera: &mut Camera| camera.end_frame(),
+                            );
+
+                            take_screenshots(
+                                &self.isotope.compound(),
+                                &self.isotope.gpu_controller,
+                                &surface_texture.texture,
+                            );
 
                             // Display on the surface
                             surface_texture.present();
+                            self.isotope.gpu_controller.end_frame();
+
+                            self.isotope
+                                .frame_capture
+                                .end_frame(&self.isotope.gpu_controller);
+
+                            self.isotope.fire_lifecycle_event(LifecycleEvent::FrameEnd {
+                                t: self.isotope.time.elapsed().as_secs_f32(),
+                            });
                         }
                     }
                     WindowEvent::Resized(new_size) => {
@@ -428,21 +1822,37 @@ impl ApplicationHandler for IsotopeApplication {
                             .photon
                             .resize((new_size.width, new_size.height));
 
+                        update_camera_aspects(
+                            &self.isotope.compound(),
+                            &self.isotope.gpu_controller,
+                        );
+
                         self.isotope
-                            .compound
-                            .iter_mut_mol(|_entity, camera: &mut Camera| match camera {
-                                Camera::PerspectiveCamera3D(camera) => {
-                                    camera.aspect(|aspect| {
-                                        *aspect = self.isotope.gpu_controller.read_surface_config(|sc| {
-                                            sc.width as f32 / sc.height as f32
-                                        }).unwrap_or_else(|err| {
-                                            warn!("Reading Surface Configuration Failed: {}, Continuing with aspect ration of 1.0...", err);
-                                            1.0
-                                        });
-                                    });
-                                }
+                            .fire_lifecycle_event(LifecycleEvent::SurfaceResized {
+                                width: new_size.width,
+                                height: new_size.height,
                             });
                     }
+                    WindowEvent::Focused(focused) => {
+                        self.isotope.fire_lifecycle_event(if focused {
+                            LifecycleEvent::FocusGained
+                        } else {
+                            LifecycleEvent::FocusLost
+                        });
+
+                        // Releases aren't seen while unfocused, don't leave inputs held
+                        if !focused {
+                            self.isotope
+                                .update_action_maps(|action_map| action_map.release_all());
+                        }
+
+                        self.isotope.focused = focused;
+                        self.isotope.update_background();
+                    }
+                    WindowEvent::Occluded(occluded) => {
+                        self.isotope.occluded = occluded;
+                        self.isotope.update_background();
+                    }
                     WindowEvent::KeyboardInput { event, .. } => match event {
                         KeyEvent {
                             physical_key,
@@ -451,14 +1861,27 @@ impl ApplicationHandler for IsotopeApplication {
                         } => match state {
                             ElementState::Pressed => match physical_key {
                                 winit::keyboard::PhysicalKey::Code(code) => {
-                                    self.isotope.state.write().and_then(|mut state| {
+                                    let (t, offset) = self.isotope.input_time();
+                                    self.isotope.update_action_maps(|action_map| {
+                                        action_map.press(code.into(), t, offset);
+                                    });
+
+                                    if self
+                                        .isotope
+                                        .frame_capture_key
+                                        .read()
+                                        .is_ok_and(|key| *key == Some(code))
+                                    {
+                                        self.isotope.frame_capture.request(FrameCapture::new());
+                                    }
+
+                                    self.isotope.state.write().map(|mut state| {
                                         state.key_is_pressed(
-                                            &self.isotope.compound,
+                                            &self.isotope.compound(),
                                             &self.isotope.asset_server,
                                             code,
                                             self.isotope.time.elapsed().as_secs_f32(),
                                         );
-                                        Ok(())
                                     }).unwrap_or_else(|err| {
                                         warn!("Failed to update game state with key: {} continuing...", err);
                                     });
@@ -467,14 +1890,18 @@ impl ApplicationHandler for IsotopeApplication {
                             },
                             ElementState::Released => match physical_key {
                                 winit::keyboard::PhysicalKey::Code(code) => {
-                                    self.isotope.state.write().and_then(|mut state| {
+                                    let (_, offset) = self.isotope.input_time();
+                                    self.isotope.update_action_maps(|action_map| {
+                                        action_map.release(code.into(), offset);
+                                    });
+
+                                    self.isotope.state.write().map(|mut state| {
                                         state.key_is_released(
-                                            &self.isotope.compound,
+                                            &self.isotope.compound(),
                                             &self.isotope.asset_server,
                                             code,
                                             self.isotope.time.elapsed().as_secs_f32(),
                                         );
-                                        Ok(())
                                     }).unwrap_or_else(|err| {
                                         warn!("Failed to update game state with key: {} continuing...", err);
                                     });
@@ -483,15 +1910,70 @@ impl ApplicationHandler for IsotopeApplication {
                             },
                         },
                     },
+                    WindowEvent::ModifiersChanged(modifiers) => {
+                        self.isotope.update_action_maps(|action_map| {
+                            action_map.set_modifiers(modifiers.state().into());
+                        });
+                    }
+                    WindowEvent::MouseInput { state, button, .. } => {
+                        let (t, offset) = self.isotope.input_time();
+                        self.isotope.update_action_maps(|action_map| match state {
+                            ElementState::Pressed => action_map.press(button.into(), t, offset),
+                            ElementState::Released => action_map.release(button.into(), offset),
+                        });
+                    }
+                    WindowEvent::Touch(touch) => {
+                        self.isotope
+                            .state
+                            .write()
+                            .map(|mut state| {
+                                state.pointer_event(
+                                    &self.isotope.compound(),
+                                    &self.isotope.asset_server,
+                                    touch.into(),
+                                    self.isotope.time.elapsed().as_secs_f32(),
+                                );
+                            })
+                            .unwrap_or_else(|err| {
+                                warn!(
+                                    "Failed to update game state with touch: {} continuing...",
+                                    err
+                                );
+                            });
+                    }
                     WindowEvent::CursorMoved { position, .. } => {
-                        self.isotope.state.write().and_then(|mut state| {
+                        let delta =
+                            self.isotope
+                                .mouse_filter
+                                .write()
+                                .ok()
+                                .and_then(|mut mouse_filter| {
+                                    mouse_filter
+                                        .cursor_moved(position.into(), window.window.scale_factor())
+                                });
+
+                        // Hold the cursor in place where it can't be locked
+                        self.isotope.compound().iter_mol(
+                            |_entity, window_controller: &WindowController| {
+                                if let Some(center) = window_controller.recenter(Some(position))
+                                    && let Ok(mut mouse_filter) = self.isotope.mouse_filter.write()
+                                {
+                                    mouse_filter.warp_cursor(center.into());
+                                }
+                            },
+                        );
+
+                        if let Some(delta) = delta {
+                            self.isotope.mouse_moved(delta);
+                        }
+
+                        self.isotope.state.write().map(|mut state| {
                             state.cursor_moved(
-                                &self.isotope.compound,
+                                &self.isotope.compound(),
                                 &self.isotope.asset_server,
                                 position.into(),
                                 self.isotope.time.elapsed().as_secs_f32(),
                             );
-                            Ok(())
                         }).unwrap_or_else(|err| {
                             warn!("Failed to update game state with cursor position: {} continuing...", err);
                         });
@@ -510,26 +1992,37 @@ impl ApplicationHandler for IsotopeApplication {
     ) {
         match event {
             DeviceEvent::MouseMotion { delta } => {
-                self.isotope
-                    .state
+                let delta = self
+                    .isotope
+                    .mouse_filter
                     .write()
-                    .and_then(|mut state| {
-                        state.mouse_is_moved(
-                            &self.isotope.compound,
-                            &self.isotope.asset_server,
-                            delta,
-                            self.isotope.time.elapsed().as_secs_f32(),
-                        );
-                        Ok(())
-                    })
+                    .ok()
+                    .and_then(|mut mouse_filter| mouse_filter.device_motion(delta));
+
+                if let Some(delta) = delta {
+                    self.isotope.mouse_moved(delta);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Matches the aspect ratio of every camera in a world to the surface
+fn update_camera_aspects(compound: &Compound, gpu_controller: &GpuController) {
+    compound.iter_mut_mol(|_entity, camera: &mut Camera| match camera {
+        Camera::PerspectiveCamera3D(camera) => {
+            camera.aspect(|aspect| {
+                *aspect = gpu_controller
+                    .read_surface_config(|sc| sc.width as f32 / sc.height as f32)
                     .unwrap_or_else(|err| {
                         warn!(
-                            "Failed to update game state with mouse movement: {} continuing...",
+                            "Reading Surface Configuration Failed: {}, Continuing with aspect ration of 1.0...",
                             err
                         );
+                        1.0
                     });
-            }
-            _ => {}
+            });
         }
-    }
+    });
 }
@@ -0,0 +1,128 @@
+use std::{
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use compound::Compound;
+use log::error;
+
+use crate::{asset_server::AssetServer, world::WorldId};
+
+/// A moment in the engine loop that hooks can subscribe to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LifecycleEvent {
+    /// A frame is about to be rendered, fired before `IsotopeState::frame_update`
+    FrameStart { delta_t: f32, t: f32 },
+    /// A frame was presented to the window
+    FrameEnd { t: f32 },
+    /// A world is about to send its changes to its physics
+    PrePhysics { world: WorldId, delta_t: f32 },
+    /// A world has read back the results of its physics
+    PostPhysics { world: WorldId, delta_t: f32 },
+    /// The window surface changed size, in physical pixels
+    SurfaceResized { width: u32, height: u32 },
+    /// The window became the focused window
+    FocusGained,
+    /// The window is no longer the focused window
+    FocusLost,
+    /// The window lost focus or was hidden, see `BackgroundPolicy`
+    EnteredBackground,
+    /// The window is focused and visible again
+    LeftBackground,
+}
+
+/// What the engine does while the window is unfocused, minimized or hidden.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackgroundPolicy {
+    /// Frames rendered per second in the background, `None` renders as fast as in the foreground
+    pub frame_rate: Option<f32>,
+    /// Stops state updates and the physics of every world in the background
+    pub pause_simulation: bool,
+    /// Silences the `VoiceChat` of every world and stops sending voice in
+    /// the background, only has an effect with the `voice` feature
+    pub pause_audio: bool,
+}
+
+impl Default for BackgroundPolicy {
+    fn default() -> Self {
+        Self {
+            frame_rate: Some(4.0),
+            pause_simulation: false,
+            pause_audio: false,
+        }
+    }
+}
+
+impl BackgroundPolicy {
+    /// Time between frames in the background, if they are throttled
+    pub(crate) fn frame_interval(&self) -> Option<Duration> {
+        self.frame_rate
+            .filter(|frame_rate| *frame_rate > 0.0)
+            .map(|frame_rate| Duration::from_secs_f32(1.0 / frame_rate))
+    }
+}
+
+/// Identifies a hook added with `Isotope::add_lifecycle_hook` so it can be removed again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LifecycleHookId(u64);
+
+type LifecycleHook = Arc<dyn Fn(&Compound, &AssetServer, &LifecycleEvent) + Send + Sync>;
+
+/// Every hook subscribed to the engine lifecycle.
+///
+/// Shared between the render loop and the state thread, events are fired
+/// from whichever thread reaches the moment.
+#[derive(Default)]
+pub(crate) struct LifecycleHooks {
+    hooks: RwLock<Vec<(LifecycleHookId, LifecycleHook)>>,
+    next_id: AtomicU64,
+}
+
+impl LifecycleHooks {
+    pub(crate) fn add<F>(&self, hook: F) -> LifecycleHookId
+    where
+        F: Fn(&Compound, &AssetServer, &LifecycleEvent) + Send + Sync + 'static,
+    {
+        let id = LifecycleHookId(self.next_id.fetch_add(1, Ordering::Relaxed));
+
+        match self.hooks.write() {
+            Ok(mut hooks) => hooks.push((id, Arc::new(hook))),
+            Err(err) => error!("Failed to add lifecycle hook: {}", err),
+        }
+
+        id
+    }
+
+    pub(crate) fn remove(&self, id: LifecycleHookId) -> bool {
+        match self.hooks.write() {
+            Ok(mut hooks) => {
+                let count = hooks.len();
+                hooks.retain(|(hook_id, _)| *hook_id != id);
+                hooks.len() != count
+            }
+            Err(err) => {
+                error!("Failed to remove lifecycle hook: {}", err);
+                false
+            }
+        }
+    }
+
+    /// Calls every hook with the event in the order they were added
+    pub(crate) fn fire(&self, compound: &Compound, assets: &AssetServer, event: LifecycleEvent) {
+        // Released before calling so hooks can add or remove hooks
+        let hooks: Vec<LifecycleHook> = match self.hooks.read() {
+            Ok(hooks) => hooks.iter().map(|(_, hook)| hook.clone()).collect(),
+            Err(err) => {
+                error!("Failed to fire lifecycle event: {}", err);
+                return;
+            }
+        };
+
+        for hook in hooks {
+            hook(compound, assets, &event);
+        }
+    }
+}
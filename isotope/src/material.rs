@@ -1,6 +1,5 @@
 use std::{
-    fs::File,
-    io::{BufRead, BufReader},
+    io::{BufRead, Cursor},
     path::Path,
     sync::Arc,
 };
@@ -8,7 +7,7 @@ use std::{
 use anyhow::{Result, anyhow};
 use gpu_controller::{
     BindGroup, BindGroupDescriptor, BindGroupEntry, BindingResource, Buffer, BufferDescriptor,
-    BufferUsages, GpuController, TextureViewDescriptor,
+    BufferInitDescriptor, BufferUsages, GpuController, TextureViewDescriptor,
 };
 use log::{debug, error, info};
 use matter_vault::SharedMatter;
@@ -18,6 +17,9 @@ use crate::{asset_server::AssetServer, texture::IsotopeTexture};
 const FALSE: u32 = 0;
 const TRUE: u32 = 1;
 
+// Label the error material is shared under
+const ERROR_MATERIAL_LABEL: &str = "isotope/error_material";
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct MaterialProperties {
@@ -53,25 +55,94 @@ pub struct Material {
     pub properties: MaterialProperties,
     properties_buffer: Buffer,
     pub label: String,
+    /// What the material is made of, set by the `surface` statement of the
+    /// material library, see `SurfaceType`
+    pub surface: Option<String>,
 
     gpu_controller: Arc<GpuController>,
     texture: Option<SharedMatter<IsotopeTexture>>,
     pub(crate) bind_group: BindGroup,
 }
 
+impl Material {
+    /// Returns the material used in place of materials that are missing.
+    ///
+    /// The material is the default error color with the fallback checker as
+    /// its texture, it is created the first time it is needed.
+    pub(crate) fn shared_error(asset_server: &AssetServer) -> Result<SharedMatter<Material>> {
+        if let Ok(material) = asset_server.asset_manager.share(ERROR_MATERIAL_LABEL) {
+            return Ok(material);
+        }
+
+        let properties = MaterialProperties {
+            texture: TRUE,
+            ..Default::default()
+        };
+
+        let properties_buffer =
+            asset_server
+                .gpu_controller
+                .create_buffer_init(&BufferInitDescriptor {
+                    label: Some("Error material properties"),
+                    contents: bytemuck::cast_slice(&[properties]),
+                    usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+                });
+
+        let texture = IsotopeTexture::shared_fallback(asset_server)?;
+
+        let bind_group = asset_server.gpu_controller.read_layouts(|layouts| {
+            texture.read(|texture| {
+                asset_server
+                    .gpu_controller
+                    .create_bind_group(&BindGroupDescriptor {
+                        label: Some("Error material bind group"),
+                        layout: &layouts["Material"],
+                        entries: &[
+                            BindGroupEntry {
+                                binding: 0,
+                                resource: properties_buffer.as_entire_binding(),
+                            },
+                            BindGroupEntry {
+                                binding: 1,
+                                resource: BindingResource::TextureView(&texture.view),
+                            },
+                            BindGroupEntry {
+                                binding: 2,
+                                resource: BindingResource::Sampler(&texture.sampler),
+                            },
+                        ],
+                    })
+            })
+        })?;
+
+        asset_server.asset_manager.add(
+            ERROR_MATERIAL_LABEL,
+            Material {
+                gpu_controller: asset_server.gpu_controller.clone(),
+                label: ERROR_MATERIAL_LABEL.to_string(),
+                surface: None,
+                properties,
+                texture: Some(texture),
+                properties_buffer,
+                bind_group,
+            },
+        )
+    }
+}
+
 pub fn load_materials<P>(path: P, asset_server: &AssetServer) -> Result<Vec<SharedMatter<Material>>>
 where
     P: AsRef<Path>,
 {
     info!("Loading Materials From Path: {:#?}", path.as_ref());
 
-    let file = File::open(path.as_ref())?;
+    let file = asset_server.read_asset(path.as_ref())?;
 
     let mut materials: Vec<SharedMatter<Material>> = Vec::new();
     let mut current_material: Option<Material> = None;
     let mut label_exists = false;
 
-    for line in BufReader::new(file).lines().map_while(Result::ok) {
+    for line in Cursor::new(file).lines().map_while(Result::ok) {
         let tokens = line.split_whitespace().collect::<Vec<_>>();
 
         if tokens.is_empty() {
@@ -148,6 +219,7 @@ where
                         current_material = Some(Material {
                             gpu_controller: asset_server.gpu_controller.clone(),
                             label: label.clone(),
+                            surface: None,
                             properties: MaterialProperties::default(),
                             texture: None,
                             properties_buffer,
@@ -223,6 +295,13 @@ where
                         .properties
                         .illum = tokens[1].parse::<u32>()?;
                 }
+                // Surface type, not part of the MTL format
+                "surface" => {
+                    current_material
+                        .as_mut()
+                        .ok_or_else(|| anyhow!("No Current Material"))?
+                        .surface = tokens.get(1).map(|surface| surface.to_string());
+                }
                 // Texture
                 "map_Kd" => {
                     let material_path = path
@@ -237,21 +316,12 @@ where
                     debug!("Searching in path: {:#?}", material_path);
 
                     if let Some(current_material) = current_material.as_mut() {
-                        current_material.texture = Some(
-                            asset_server
-                                .asset_manager
-                                .share(tokens[1].to_string())
-                                .or_else(|_err| {
-                                    asset_server.asset_manager.add(
-                                        tokens[1].to_string(),
-                                        IsotopeTexture::new_from_path(material_path, asset_server)
-                                            .map_err(|err| {
-                                                error!("Failed to Load Texture: {:#?}", err);
-                                                err
-                                            })?,
-                                    )
-                                })?,
-                        );
+                        // Missing textures show the checker instead of failing the material
+                        current_material.texture =
+                            Some(asset_server.shared_texture(&material_path).or_else(|err| {
+                                asset_server.report_fallback(&material_path, &err);
+                                IsotopeTexture::shared_fallback(asset_server)
+                            })?);
 
                         current_material.properties.texture = TRUE;
 
@@ -365,6 +435,7 @@ where
                         current_material = Some(Material {
                             gpu_controller: asset_server.gpu_controller.clone(),
                             label: label.clone(),
+                            surface: None,
                             properties: MaterialProperties::default(),
                             texture: None,
                             properties_buffer,
@@ -391,3 +462,35 @@ where
 
     Ok(materials)
 }
+
+/// Loads the first material of an MTL file, for meshes generated at runtime.
+///
+/// A missing file or one without materials is reported as a fallback and
+/// the error material is used instead.
+///
+/// # Arguments
+/// * `path` - Path to the MTL file, `None` to use the error material
+/// * `asset_server` - The asset server to read the file through
+///
+/// # Returns
+/// The material, or an error only if the error material could not be created
+pub(crate) fn load_first_material(
+    path: Option<&str>,
+    asset_server: &AssetServer,
+) -> Result<SharedMatter<Material>> {
+    let Some(path) = path else {
+        return Material::shared_error(asset_server);
+    };
+
+    match load_materials(path, asset_server) {
+        Ok(materials) if !materials.is_empty() => Ok(materials[0].clone()),
+        Ok(_) => {
+            asset_server.report_fallback(path, &anyhow!("No materials in file"));
+            Material::shared_error(asset_server)
+        }
+        Err(err) => {
+            asset_server.report_fallback(path, &err);
+            Material::shared_error(asset_server)
+        }
+    }
+}
@@ -1,30 +1,73 @@
 use std::{
-    fs::File,
-    io::{BufRead, BufReader},
+    io::{BufRead, Cursor},
     ops::Range,
     path::Path,
-    sync::Arc,
+    sync::{Arc, OnceLock},
 };
 
 use anyhow::{Result, anyhow};
 use cgmath::{Matrix4, Quaternion, SquareMatrix, Vector3, Zero};
 use gpu_controller::{
-    BindGroup, BindGroupDescriptor, BindGroupEntry, Buffer, BufferDescriptor, BufferInitDescriptor,
-    BufferUsages, ComputePassDescriptor, GpuController, INSTANCE_BUFFER_INDEX, Instance,
-    MaintainBase, MapMode, Mesh, RenderPass, Vertex,
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindingResource, Bounds, Buffer,
+    BufferDescriptor, BufferInitDescriptor, BufferUsages, ComputePass, ComputePassDescriptor,
+    DrawIndexedIndirectArgs, GpuController, INSTANCE_BUFFER_INDEX, Instance, MaintainBase, MapMode,
+    Mesh, PerFrame, RenderPass, Vertex, VertexEncoding,
 };
 use isotope_utils::compute_work_group_count;
-use log::{debug, info};
+use log::{debug, error, info};
 use matter_vault::SharedMatter;
-use photon::{MATERIALS_BIND_GROUP, renderer::GLOBAL_TRANSFORM_BIND_GROUP};
+use photon::{
+    MATERIALS_BIND_GROUP,
+    renderer::{
+        GLOBAL_TRANSFORM_BIND_GROUP,
+        culling::{CULLING_BIND_GROUP, CULLING_WORKGROUP_SIZE, CullInfo},
+    },
+};
 
 use crate::{
-    Instancer, InstancerKind, Transform3D,
-    asset_server::AssetServer,
+    Instancer, InstancerKind, MaterialOverride, SurfaceType, Transform3D,
+    asset_server::{AssetServer, asset_key},
     material::{Material, load_materials},
+    texture::IsotopeTexture,
 };
 
 const INSTANCE_SIZE: u64 = std::mem::size_of::<Instance>() as u64;
+const DRAW_ARGS_SIZE: u64 = std::mem::size_of::<DrawIndexedIndirectArgs>() as u64;
+
+const FALSE: u32 = 0;
+const TRUE: u32 = 1;
+
+// Per object material overrides as laid out in the geometry shader
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct MaterialOverrideUniform {
+    tint: [f32; 4],
+    emissive: [f32; 4],
+    silhouette: [f32; 4],
+    texture: u32,
+    _padding: [u32; 3],
+}
+
+impl From<&MaterialOverride> for MaterialOverrideUniform {
+    fn from(material_override: &MaterialOverride) -> Self {
+        let [r, g, b] = material_override.emissive_color;
+
+        Self {
+            tint: material_override.tint,
+            emissive: [r, g, b, material_override.emissive_strength],
+            silhouette: [0.0; 4],
+            texture: if material_override.texture.is_some() {
+                TRUE
+            } else {
+                FALSE
+            },
+            _padding: [0; 3],
+        }
+    }
+}
+
+// Label the placeholder cube mesh is shared under
+const PLACEHOLDER_LABEL: &str = "isotope/placeholder_cube";
 
 type Position = [f32; 3];
 type Normal = [f32; 3];
@@ -34,13 +77,37 @@ pub struct Model {
     gpu_controller: Arc<GpuController>,
     meshes: Vec<(Option<usize>, SharedMatter<Mesh>)>,
     materials: Vec<SharedMatter<Material>>,
+    bounds: Bounds,
+    // Computed the first time a collider asks for it
+    hull_points: OnceLock<Vec<[f32; 3]>>,
 
     global_transform_bind_group: BindGroup,
+    // Holds the transform of this frame followed by the one of the frame before
     global_transformation_buffer: Buffer,
+    transform: Transform3D,
+    previous_transform: Transform3D,
+    // Set when the transform was given this frame
+    moved: bool,
+    // Set while the two transforms in the buffer differ
+    has_motion: bool,
+    material_override_buffer: Buffer,
+    // Color the model is tinted where it is hidden, when it draws a silhouette
+    silhouette: Option<[f32; 4]>,
+    // Shared by every model, bound while there is no override texture
+    empty_texture: SharedMatter<IsotopeTexture>,
     instance_buffer: Buffer,
     num_instances: u32,
 
-    instance_staging_buffer: Buffer,
+    // Mapped to change the instances on the CPU, one per frame in flight
+    instance_staging_buffers: PerFrame<Buffer>,
+
+    // Filled by the culling pass with the instances the camera can see
+    visible_instance_buffer: Buffer,
+    // One set of indirect draw arguments per mesh
+    draw_args_buffer: Buffer,
+    // Arguments the draws start every culling pass with, before any instance is visible
+    initial_draw_args: Vec<u8>,
+    culling_bind_group: BindGroup,
 }
 
 impl Model {
@@ -55,10 +122,12 @@ impl Model {
         debug!("Full Path {:#?}", path.as_ref());
 
         info!("Retriving wavefrom from {:#?}", path.as_ref());
-        let file = File::open(&path)?;
+        let file = asset_server.read_asset(&path)?;
+
+        let import_settings = asset_server.import_settings(&path);
 
         // Read the obj file line by line
-        let lines = BufReader::new(file).lines();
+        let lines = Cursor::new(file).lines();
 
         let mut current_mesh: Option<Mesh> = None;
         let mut current_material_index: Option<usize> = None;
@@ -84,18 +153,24 @@ impl Model {
                 match tokens[0] {
                     "o" => {
                         if let Some(mut mesh) = current_mesh.take() {
-                            mesh.buffer(asset_server.gpu_controller.clone());
-                            let mesh_label = mesh.label().clone();
+                            if import_settings.mesh.quantize {
+                                mesh.buffer_quantized(asset_server.gpu_controller.clone());
+                            } else {
+                                mesh.buffer(asset_server.gpu_controller.clone());
+                            }
                             meshes.push((
                                 current_material_index,
-                                asset_server.asset_manager.add(mesh_label, mesh)?,
+                                asset_server
+                                    .asset_manager
+                                    .add(mesh_key(&path, mesh.label()), mesh)?,
                             ));
                         }
 
                         let label = tokens[1].to_string();
 
-                        // If the mesh is already shared, add it to the list of meshes
-                        if let Ok(mesh) = asset_server.asset_manager.share(&label) {
+                        // If the mesh of this file is already shared, add it to the list of meshes
+                        if let Ok(mesh) = asset_server.asset_manager.share(mesh_key(&path, &label))
+                        {
                             debug!("Mesh already exists: {}", label);
                             meshes.push((current_material_index, mesh));
                             label_exists = true;
@@ -105,16 +180,17 @@ impl Model {
                                 label: label.clone(),
                                 vertices: Vec::new(),
                                 indices: Vec::new(),
+                                collision_geometry: import_settings.mesh.collision_geometry,
                             });
                         }
                     }
                     // Vertex Position
                     "v" => {
-                        positions.push([
+                        positions.push(import_settings.mesh.convert_position([
                             tokens[1].parse()?,
                             tokens[2].parse()?,
                             tokens[3].parse()?,
-                        ]);
+                        ]));
                     }
                     // Vertex UV
                     "vt" => {
@@ -122,7 +198,11 @@ impl Model {
                     }
                     // Vertex Normal
                     "vn" => {
-                        normals.push([tokens[1].parse()?, tokens[2].parse()?, tokens[3].parse()?]);
+                        normals.push(import_settings.mesh.convert_normal([
+                            tokens[1].parse()?,
+                            tokens[2].parse()?,
+                            tokens[3].parse()?,
+                        ]));
                     }
                     // Connecting Face
                     "f" => {
@@ -161,18 +241,19 @@ impl Model {
                             .ok_or(anyhow!("Obj Path is invalid"))?
                             .join(tokens[1]);
 
-                        materials = load_materials(&path_to_material, asset_server)?;
+                        // Meshes of a missing library fall back to the error material
+                        materials =
+                            load_materials(&path_to_material, asset_server).unwrap_or_else(|err| {
+                                asset_server.report_fallback(&path_to_material, &err);
+                                Vec::new()
+                            });
                     }
                     "usemtl" => {
-                        let material_name = tokens[1].to_string();
-                        current_material_index =
-                            materials.iter().enumerate().find_map(|(index, material)| {
-                                if material.read(|m| m.label == material_name) {
-                                    Some(index)
-                                } else {
-                                    None
-                                }
-                            });
+                        current_material_index = find_material(
+                            import_settings.assigned_material(tokens[1]),
+                            &mut materials,
+                            asset_server,
+                        );
                     }
                     _ => {}
                 }
@@ -183,8 +264,9 @@ impl Model {
 
                         let label = tokens[1].to_string();
 
-                        // If the mesh is already shared, add it to the list of meshes
-                        if let Ok(mesh) = asset_server.asset_manager.share(&label) {
+                        // If the mesh of this file is already shared, add it to the list of meshes
+                        if let Ok(mesh) = asset_server.asset_manager.share(mesh_key(&path, &label))
+                        {
                             debug!("Mesh already exists: {}", label);
                             meshes.push((current_material_index, mesh));
                             label_exists = true;
@@ -194,6 +276,7 @@ impl Model {
                                 label: label.clone(),
                                 vertices: Vec::new(),
                                 indices: Vec::new(),
+                                collision_geometry: import_settings.mesh.collision_geometry,
                             });
                         }
                     }
@@ -204,18 +287,19 @@ impl Model {
                             .ok_or(anyhow!("Obj Path is invalid"))?
                             .join(tokens[1]);
 
-                        materials = load_materials(&path_to_material, asset_server)?;
+                        // Meshes of a missing library fall back to the error material
+                        materials =
+                            load_materials(&path_to_material, asset_server).unwrap_or_else(|err| {
+                                asset_server.report_fallback(&path_to_material, &err);
+                                Vec::new()
+                            });
                     }
                     "usemtl" => {
-                        let material_name = tokens[1].to_string();
-                        current_material_index =
-                            materials.iter().enumerate().find_map(|(index, material)| {
-                                if material.read(|m| m.label == material_name) {
-                                    Some(index)
-                                } else {
-                                    None
-                                }
-                            });
+                        current_material_index = find_material(
+                            import_settings.assigned_material(tokens[1]),
+                            &mut materials,
+                            asset_server,
+                        );
                     }
                     _ => {}
                 }
@@ -223,14 +307,119 @@ impl Model {
         }
 
         if let Some(mut mesh) = current_mesh.take() {
-            mesh.buffer(asset_server.gpu_controller.clone());
-            let mesh_label = mesh.label().clone();
+            if import_settings.mesh.quantize {
+                mesh.buffer_quantized(asset_server.gpu_controller.clone());
+            } else {
+                mesh.buffer(asset_server.gpu_controller.clone());
+            }
             meshes.push((
                 current_material_index,
-                asset_server.asset_manager.add(mesh_label, mesh)?,
+                asset_server
+                    .asset_manager
+                    .add(mesh_key(&path, mesh.label()), mesh)?,
             ));
         }
 
+        Self::from_meshes(meshes, materials, asset_server, instances)
+    }
+
+    /// Creates a model directly from CPU geometry instead of a file on disk.
+    ///
+    /// The mesh is buffered immediately and registered with the asset server
+    /// under `label`, so models created with the same label share the mesh.
+    ///
+    /// # Arguments
+    /// * `label` - The label to register the mesh under
+    /// * `vertices` - The vertices of the mesh
+    /// * `indices` - The triangle indices of the mesh
+    /// * `material` - The material to render the mesh with
+    /// * `asset_server` - The asset server used to buffer and share the mesh
+    /// * `instances` - The instances to draw, `None` for a single instance
+    pub(crate) fn from_geometry(
+        label: String,
+        vertices: &[Vertex],
+        indices: &[u32],
+        material: SharedMatter<Material>,
+        asset_server: &AssetServer,
+        instances: Option<&[Instance]>,
+    ) -> Result<Self> {
+        let mesh = match asset_server.asset_manager.share(&label) {
+            Ok(mesh) => mesh,
+            Err(_) => {
+                let mesh = Mesh::new(
+                    asset_server.gpu_controller.clone(),
+                    label.clone(),
+                    vertices,
+                    indices,
+                );
+                asset_server.asset_manager.add(label, mesh)?
+            }
+        };
+
+        Self::from_meshes(
+            vec![(Some(0), mesh)],
+            vec![material],
+            asset_server,
+            instances,
+        )
+    }
+
+    /// Loads a model like `from_obj`, replacing it with the placeholder if it fails.
+    ///
+    /// The failure is logged and recorded in `AssetServer::failed_loads`, so a
+    /// missing or broken file shows up as a checkered cube instead of stopping
+    /// the game.
+    ///
+    /// # Returns
+    /// The model, or an error only if the placeholder could not be created either
+    pub fn from_obj_or_placeholder<P>(
+        path: P,
+        asset_server: &AssetServer,
+        instances: Option<&[Instance]>,
+    ) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_obj(&path, asset_server, instances).or_else(|err| {
+            asset_server.report_fallback(&path, &err);
+            Self::placeholder(asset_server, instances)
+        })
+    }
+
+    /// Creates a unit cube with the error material, the stand in for models that failed to load
+    pub fn placeholder(asset_server: &AssetServer, instances: Option<&[Instance]>) -> Result<Self> {
+        let (vertices, indices) = placeholder_cube();
+
+        Self::from_geometry(
+            PLACEHOLDER_LABEL.to_string(),
+            &vertices,
+            &indices,
+            Material::shared_error(asset_server)?,
+            asset_server,
+            instances,
+        )
+    }
+
+    /// Creates a model that draws already buffered meshes.
+    ///
+    /// # Arguments
+    /// * `meshes` - Every mesh with the index of its material in `materials`
+    /// * `materials` - The materials of the meshes
+    /// * `asset_server` - The asset server the model is created with
+    /// * `instances` - The instances to draw, `None` for a single instance
+    pub(crate) fn from_meshes(
+        meshes: Vec<(Option<usize>, SharedMatter<Mesh>)>,
+        materials: Vec<SharedMatter<Material>>,
+        asset_server: &AssetServer,
+        instances: Option<&[Instance]>,
+    ) -> Result<Self> {
+        // Combined bounds of every mesh in the model
+        let bounds = meshes
+            .iter()
+            .map(|(_, mesh)| mesh.read(|mesh| mesh.bounds()))
+            .reduce(|bounds, mesh_bounds| bounds.union(&mesh_bounds))
+            .unwrap_or_default();
+
         // Create the instance buffer for the model
         let (instance_buffer, num_instances) = if let Some(instances) = instances {
             (
@@ -253,6 +442,7 @@ impl Model {
                     .create_buffer_init(&BufferInitDescriptor {
                         label: Some("Model Instance Buffer"),
                         usage: BufferUsages::VERTEX
+                            | BufferUsages::STORAGE
                             | BufferUsages::COPY_DST
                             | BufferUsages::COPY_SRC,
                         contents: bytemuck::cast_slice(&[Instance::new(
@@ -265,7 +455,7 @@ impl Model {
             )
         };
 
-        let instance_staging_buffer =
+        let instance_staging_buffers = PerFrame::new(|_| {
             asset_server
                 .gpu_controller
                 .create_buffer(&BufferDescriptor {
@@ -276,7 +466,8 @@ impl Model {
                         | BufferUsages::COPY_SRC
                         | BufferUsages::COPY_DST,
                     mapped_at_creation: false,
-                });
+                })
+        });
 
         let global_transformation_buffer =
             asset_server
@@ -284,19 +475,109 @@ impl Model {
                 .create_buffer_init(&BufferInitDescriptor {
                     label: Some("Global Transformation Buffer"),
                     usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
-                    contents: bytemuck::cast_slice(&[Transform3D::default()]),
+                    contents: bytemuck::cast_slice(&[Transform3D::default(); 2]),
+                });
+
+        let material_override_buffer =
+            asset_server
+                .gpu_controller
+                .create_buffer_init(&BufferInitDescriptor {
+                    label: Some("Material Override Buffer"),
+                    usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+                    contents: bytemuck::cast_slice(&[MaterialOverrideUniform::from(
+                        &MaterialOverride::default(),
+                    )]),
                 });
 
-        let global_transform_bind_group = asset_server.gpu_controller.read_layouts(|layouts| {
+        // Bound in place of the override texture while there is none
+        let empty_texture = IsotopeTexture::shared_empty(asset_server)?;
+
+        let global_transform_bind_group = empty_texture.read(|empty_texture| {
+            Self::create_global_transform_bind_group(
+                &asset_server.gpu_controller,
+                &global_transformation_buffer,
+                &material_override_buffer,
+                empty_texture,
+            )
+        })?;
+
+        let visible_instance_buffer =
+            asset_server
+                .gpu_controller
+                .create_buffer(&BufferDescriptor {
+                    label: Some("Model Visible Instance Buffer"),
+                    size: num_instances as u64 * INSTANCE_SIZE,
+                    usage: BufferUsages::VERTEX | BufferUsages::STORAGE,
+                    mapped_at_creation: false,
+                });
+
+        let initial_draw_args = meshes
+            .iter()
+            .flat_map(|(_, mesh)| {
+                DrawIndexedIndirectArgs {
+                    index_count: mesh.read(|mesh| mesh.num_indices()),
+                    instance_count: 0,
+                    first_index: 0,
+                    base_vertex: 0,
+                    first_instance: 0,
+                }
+                .as_bytes()
+                .to_vec()
+            })
+            .collect::<Vec<u8>>();
+
+        let draw_args_buffer =
+            asset_server
+                .gpu_controller
+                .create_buffer_init(&BufferInitDescriptor {
+                    label: Some("Model Draw Arguments Buffer"),
+                    usage: BufferUsages::INDIRECT | BufferUsages::STORAGE | BufferUsages::COPY_DST,
+                    contents: &initial_draw_args,
+                });
+
+        let cull_info = CullInfo::new(
+            bounds.center,
+            bounds.radius,
+            num_instances,
+            meshes.len() as u32,
+        );
+        let cull_info_buffer =
+            asset_server
+                .gpu_controller
+                .create_buffer_init(&BufferInitDescriptor {
+                    label: Some("Model Cull Info Buffer"),
+                    usage: BufferUsages::UNIFORM,
+                    contents: bytemuck::cast_slice(&[cull_info]),
+                });
+
+        let culling_bind_group = asset_server.gpu_controller.read_layouts(|layouts| {
             asset_server
                 .gpu_controller
                 .create_bind_group(&BindGroupDescriptor {
-                    label: Some("Global Transform Bind Group"),
-                    layout: &layouts["Global Transform"],
-                    entries: &[BindGroupEntry {
-                        binding: 0,
-                        resource: global_transformation_buffer.as_entire_binding(),
-                    }],
+                    label: Some("Model Culling Bind Group"),
+                    layout: &layouts["Culling"],
+                    entries: &[
+                        BindGroupEntry {
+                            binding: 0,
+                            resource: global_transformation_buffer.as_entire_binding(),
+                        },
+                        BindGroupEntry {
+                            binding: 1,
+                            resource: cull_info_buffer.as_entire_binding(),
+                        },
+                        BindGroupEntry {
+                            binding: 2,
+                            resource: instance_buffer.as_entire_binding(),
+                        },
+                        BindGroupEntry {
+                            binding: 3,
+                            resource: visible_instance_buffer.as_entire_binding(),
+                        },
+                        BindGroupEntry {
+                            binding: 4,
+                            resource: draw_args_buffer.as_entire_binding(),
+                        },
+                    ],
                 })
         })?;
 
@@ -304,17 +585,85 @@ impl Model {
             gpu_controller: asset_server.gpu_controller.clone(),
             meshes,
             materials,
+            bounds,
+            hull_points: OnceLock::new(),
             instance_buffer,
             global_transform_bind_group,
             global_transformation_buffer,
+            transform: Transform3D::default(),
+            previous_transform: Transform3D::default(),
+            moved: false,
+            has_motion: false,
+            material_override_buffer,
+            silhouette: None,
+            empty_texture,
             num_instances,
-            instance_staging_buffer,
+            instance_staging_buffers,
+            visible_instance_buffer,
+            draw_args_buffer,
+            initial_draw_args,
+            culling_bind_group,
         })
     }
 
-    pub fn render(&self, render_pass: &mut RenderPass) {
-        for (material_index, mesh) in self.meshes.iter() {
+    fn create_global_transform_bind_group(
+        gpu_controller: &GpuController,
+        global_transformation_buffer: &Buffer,
+        material_override_buffer: &Buffer,
+        override_texture: &IsotopeTexture,
+    ) -> Result<BindGroup> {
+        gpu_controller.read_layouts(|layouts| {
+            gpu_controller.create_bind_group(&BindGroupDescriptor {
+                label: Some("Global Transform Bind Group"),
+                layout: &layouts["Global Transform"],
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: global_transformation_buffer.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: material_override_buffer.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: BindingResource::TextureView(&override_texture.view),
+                    },
+                    BindGroupEntry {
+                        binding: 3,
+                        resource: BindingResource::Sampler(&override_texture.sampler),
+                    },
+                ],
+            })
+        })
+    }
+
+    /// Culls the instances of the model in a culling pass.
+    ///
+    /// Must run before `render` for the same camera, `render` draws only the
+    /// instances the last culling pass found visible.
+    pub fn cull(&self, compute_pass: &mut ComputePass) {
+        // Visible instances are counted up from zero every pass
+        self.gpu_controller
+            .write_buffer(&self.draw_args_buffer, 0, &self.initial_draw_args);
+
+        compute_pass.set_bind_group(CULLING_BIND_GROUP, &self.culling_bind_group, &[]);
+        compute_pass.dispatch_workgroups(
+            compute_work_group_count(self.num_instances, CULLING_WORKGROUP_SIZE),
+            1,
+            1,
+        );
+    }
+
+    /// Draws the meshes of the model stored with the encoding, the pipeline
+    /// of that encoding must be set on the render pass.
+    pub fn render(&self, render_pass: &mut RenderPass, encoding: VertexEncoding) {
+        for (draw_index, (material_index, mesh)) in self.meshes.iter().enumerate() {
             mesh.read(|mesh| {
+                if mesh.encoding() != encoding {
+                    return;
+                }
+
                 if let Some(material_index) = material_index.as_ref() {
                     self.materials[*material_index].read(|material| {
                         render_pass.set_bind_group(MATERIALS_BIND_GROUP, &material.bind_group, &[]);
@@ -327,20 +676,195 @@ impl Model {
                     &[],
                 );
 
-                render_pass
-                    .set_vertex_buffer(INSTANCE_BUFFER_INDEX, self.instance_buffer.slice(..));
+                render_pass.set_vertex_buffer(
+                    INSTANCE_BUFFER_INDEX,
+                    self.visible_instance_buffer.slice(..),
+                );
 
-                mesh.render(render_pass, self.num_instances);
+                mesh.render_indirect(
+                    render_pass,
+                    &self.draw_args_buffer,
+                    draw_index as u64 * DRAW_ARGS_SIZE,
+                );
             });
         }
     }
 
-    pub(crate) fn set_transform(&self, transform: &Transform3D) {
+    /// Returns the bounds of the model in its local space.
+    ///
+    /// The bounds cover every mesh of the model, they are computed once when
+    /// the model is loaded and do not include instance offsets.
+    pub fn bounds(&self) -> Bounds {
+        self.bounds
+    }
+
+    /// Returns points in the model's local space whose convex hull is the
+    /// hull of the model, used to build colliders.
+    ///
+    /// The points are computed the first time they are asked for. Meshes
+    /// imported without `collision_geometry` add the corners of their bounds.
+    pub fn hull_points(&self) -> &[[f32; 3]] {
+        self.hull_points.get_or_init(|| {
+            // The hull of the union of every mesh's hull points is the hull of the model
+            self.meshes
+                .iter()
+                .flat_map(|(_, mesh)| mesh.read(|mesh| mesh.hull_points()))
+                .collect()
+        })
+    }
+
+    /// Gives the triangles of every mesh of the model in its local space,
+    /// such as to build a `TriMesh` collider from them.
+    ///
+    /// Only meshes imported with `collision_geometry` keep their triangles
+    /// once they are buffered.
+    ///
+    /// # Arguments
+    /// * `callback` - Called with the positions and three indices per triangle
+    ///
+    /// # Returns
+    /// The result of the callback, or an error if a mesh has no triangles on the CPU
+    ///
+    /// # Example
+    /// ```ignore
+    /// let floor = model.triangles(|positions, indices| TriMesh::new(positions, indices))?;
+    /// let ground = RigidBody::new(0.0, ColliderBuilder::TriMesh(Arc::new(floor)));
+    /// ```
+    pub fn triangles<F, R>(&self, callback: F) -> Result<R>
+    where
+        F: FnOnce(&[[f32; 3]], &[u32]) -> R,
+    {
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+
+        for (_, mesh) in self.meshes.iter() {
+            mesh.read(|mesh| {
+                mesh.triangles(|mesh_positions, mesh_indices| {
+                    let offset = positions.len() as u32;
+                    positions.extend_from_slice(mesh_positions);
+                    indices.extend(mesh_indices.iter().map(|index| index + offset));
+                })
+            })?;
+        }
+
+        Ok(callback(&positions, &indices))
+    }
+
+    /// Returns the first material of the model, if it has one
+    pub(crate) fn material(&self) -> Option<SharedMatter<Material>> {
+        self.materials.first().cloned()
+    }
+
+    /// Returns the surface of the first material of the model that has one
+    pub fn surface(&self) -> Option<SurfaceType> {
+        self.materials.iter().find_map(|material| {
+            material.read(|material| material.surface.clone().map(SurfaceType::new))
+        })
+    }
+
+    pub(crate) fn set_transform(&mut self, transform: &Transform3D) {
+        // The transform of the last frame is kept for the motion vectors
         self.gpu_controller.write_buffer(
             &self.global_transformation_buffer,
             0,
-            bytemuck::cast_slice(&[*transform]),
+            bytemuck::cast_slice(&[*transform, self.previous_transform]),
         );
+
+        self.transform = *transform;
+        self.moved = true;
+    }
+
+    /// Ends the frame for the motion vectors of the model, call after every `set_transform`.
+    ///
+    /// A model that didn't move this frame has its previous transform caught
+    /// up with the current one, so it stops drawing motion vectors.
+    pub(crate) fn settle_motion(&mut self) {
+        let moved = self.moved
+            && bytemuck::bytes_of(&self.transform) != bytemuck::bytes_of(&self.previous_transform);
+
+        if !moved && self.has_motion {
+            self.gpu_controller.write_buffer(
+                &self.global_transformation_buffer,
+                0,
+                bytemuck::cast_slice(&[self.transform; 2]),
+            );
+        }
+
+        self.has_motion = moved;
+        self.moved = false;
+        self.previous_transform = self.transform;
+    }
+
+    /// Returns whether the model moved since the last frame and draws in the motion pass
+    pub fn has_motion(&self) -> bool {
+        self.has_motion
+    }
+
+    /// Uploads the per object material overrides of the model.
+    ///
+    /// The override texture is loaded through the asset server, so entities
+    /// overriding with the same texture share it.
+    ///
+    /// # Arguments
+    /// * `material_override` - The overrides to apply to every material of the model
+    /// * `asset_server` - The asset server used to load the override texture
+    pub(crate) fn set_material_override(
+        &mut self,
+        material_override: &MaterialOverride,
+        asset_server: &AssetServer,
+    ) -> Result<()> {
+        let override_texture = match material_override.texture.as_ref() {
+            Some(path) => Some(asset_server.shared_texture(path)?),
+            None => None,
+        };
+
+        self.global_transform_bind_group = match override_texture.as_ref() {
+            Some(texture) => texture.read(|texture| {
+                Self::create_global_transform_bind_group(
+                    &self.gpu_controller,
+                    &self.global_transformation_buffer,
+                    &self.material_override_buffer,
+                    texture,
+                )
+            })?,
+            None => self.empty_texture.read(|empty_texture| {
+                Self::create_global_transform_bind_group(
+                    &self.gpu_controller,
+                    &self.global_transformation_buffer,
+                    &self.material_override_buffer,
+                    empty_texture,
+                )
+            })?,
+        };
+
+        // The silhouette shares the buffer, keep it when the overrides change
+        let mut uniform = MaterialOverrideUniform::from(material_override);
+        uniform.silhouette = self.silhouette.unwrap_or_default();
+
+        self.gpu_controller.write_buffer(
+            &self.material_override_buffer,
+            0,
+            bytemuck::cast_slice(&[uniform]),
+        );
+
+        Ok(())
+    }
+
+    /// Returns the color of the model's silhouette, if it draws one
+    pub fn silhouette(&self) -> Option<[f32; 4]> {
+        self.silhouette
+    }
+
+    /// Sets the color the model is tinted with where it is hidden behind
+    /// other geometry, `None` stops it from drawing in the silhouette pass
+    pub(crate) fn set_silhouette(&mut self, color: Option<[f32; 4]>) {
+        self.gpu_controller.write_buffer(
+            &self.material_override_buffer,
+            std::mem::offset_of!(MaterialOverrideUniform, silhouette) as u64,
+            bytemuck::cast_slice(&[color.unwrap_or_default()]),
+        );
+
+        self.silhouette = color;
     }
 
     pub fn modify_instances<F>(&self, range: Option<Range<u64>>, callback: F) -> Result<()>
@@ -349,6 +873,7 @@ impl Model {
     {
         let range = range.unwrap_or(0..self.num_instances as u64);
         let byte_range = (range.start * INSTANCE_SIZE)..(range.end * INSTANCE_SIZE);
+        let instance_staging_buffer = self.instance_staging_buffers.current(&self.gpu_controller);
 
         // Copy the instance buffer to mappable buffer
         let mut encoder = self
@@ -357,7 +882,7 @@ impl Model {
         encoder.copy_buffer_to_buffer(
             &self.instance_buffer,
             byte_range.start,
-            &self.instance_staging_buffer,
+            instance_staging_buffer,
             byte_range.start,
             byte_range.end - byte_range.start,
         );
@@ -365,7 +890,7 @@ impl Model {
         self.gpu_controller.poll(MaintainBase::Wait)?;
 
         // Map the buffer for the CPU to read
-        let buffer_slice = self.instance_staging_buffer.slice(byte_range.clone());
+        let buffer_slice = instance_staging_buffer.slice(byte_range.clone());
         buffer_slice.map_async(MapMode::Write, |_| {});
         self.gpu_controller.poll(MaintainBase::Wait)?;
 
@@ -375,14 +900,14 @@ impl Model {
             callback(instances);
         }
 
-        self.instance_staging_buffer.unmap();
+        instance_staging_buffer.unmap();
 
         // Copy the data back
         let mut encoder = self
             .gpu_controller
             .create_command_encoder("Instance Update Copy Back");
         encoder.copy_buffer_to_buffer(
-            &self.instance_staging_buffer,
+            instance_staging_buffer,
             byte_range.start,
             &self.instance_buffer,
             byte_range.start,
@@ -404,6 +929,8 @@ impl Model {
                     .clone()
                     .unwrap_or_else(|| 0..self.num_instances as u64);
                 let byte_range = (range.start * INSTANCE_SIZE)..(range.end * INSTANCE_SIZE);
+                let instance_staging_buffer =
+                    self.instance_staging_buffers.current(&self.gpu_controller);
 
                 // Copy the instance buffer to mappable buffer
                 let mut encoder = self
@@ -412,7 +939,7 @@ impl Model {
                 encoder.copy_buffer_to_buffer(
                     &self.instance_buffer,
                     byte_range.start,
-                    &self.instance_staging_buffer,
+                    instance_staging_buffer,
                     byte_range.start,
                     byte_range.end - byte_range.start,
                 );
@@ -420,7 +947,7 @@ impl Model {
                 self.gpu_controller.poll(MaintainBase::Wait)?;
 
                 // Map the buffer for the CPU to read
-                let buffer_slice = self.instance_staging_buffer.slice(byte_range.clone());
+                let buffer_slice = instance_staging_buffer.slice(byte_range.clone());
                 buffer_slice.map_async(MapMode::Write, |_| {});
                 self.gpu_controller.poll(MaintainBase::Wait)?;
 
@@ -430,14 +957,14 @@ impl Model {
                     serial_modifier(instances, dt, t);
                 }
 
-                self.instance_staging_buffer.unmap();
+                instance_staging_buffer.unmap();
 
                 // Copy the data back
                 let mut encoder = self
                     .gpu_controller
                     .create_command_encoder("Instance Update Copy Back");
                 encoder.copy_buffer_to_buffer(
-                    &self.instance_staging_buffer,
+                    instance_staging_buffer,
                     byte_range.start,
                     &self.instance_buffer,
                     byte_range.start,
@@ -480,3 +1007,164 @@ impl Model {
         }
     }
 }
+
+/// Finds the index of a material by name.
+///
+/// Materials that are not part of the model's material library but are
+/// already shared with the asset server, such as ones assigned through import
+/// settings, are added to `materials`.
+/// Returns the label a mesh of an OBJ file is shared under, objects with the
+/// same name in different files are different meshes
+fn mesh_key<P>(path: P, object: &str) -> String
+where
+    P: AsRef<Path>,
+{
+    format!("{}#{}", asset_key(path), object)
+}
+
+fn find_material(
+    material_name: &str,
+    materials: &mut Vec<SharedMatter<Material>>,
+    asset_server: &AssetServer,
+) -> Option<usize> {
+    materials
+        .iter()
+        .position(|material| material.read(|m| m.label == material_name))
+        .or_else(|| {
+            let material = asset_server
+                .asset_manager
+                .share(material_name)
+                .or_else(|_| {
+                    error!(
+                        "Material {} not found, using the error material",
+                        material_name
+                    );
+                    Material::shared_error(asset_server)
+                })
+                .ok()?;
+            materials.push(material);
+            Some(materials.len() - 1)
+        })
+}
+
+/// Returns the vertices and indices of a unit cube centered on the origin,
+/// every face has its own vertices so the normals and UVs stay flat
+fn placeholder_cube() -> (Vec<Vertex>, Vec<u32>) {
+    // The normal of every face and two axes along it, `u` cross `v` is the normal
+    const FACES: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+        ([1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]),
+        ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+        ([0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]),
+        ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+        ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        ([0.0, 0.0, -1.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]),
+    ];
+
+    let mut vertices = Vec::with_capacity(24);
+    let mut indices = Vec::with_capacity(36);
+
+    for (normal, u, v) in FACES {
+        let first = vertices.len() as u32;
+
+        // Counter clockwise seen from outside the cube
+        for (du, dv) in [(-0.5, -0.5), (0.5, -0.5), (0.5, 0.5), (-0.5, 0.5)] {
+            vertices.push(Vertex {
+                position: [0, 1, 2].map(|axis| normal[axis] * 0.5 + u[axis] * du + v[axis] * dv),
+                uv_coord: [du + 0.5, 0.5 - dv],
+                normal_vec: normal,
+            });
+        }
+
+        indices.extend([0, 1, 2, 0, 2, 3].map(|index| first + index));
+    }
+
+    (vertices, indices)
+}
+
+/// Reads the geometry of every object in an OBJ file into a single CPU mesh.
+///
+/// Faces are fan triangulated and missing UVs or normals default to zero.
+/// Nothing is buffered or shared with the asset server, this is meant for
+/// systems that need to process the geometry on the CPU. The mesh import
+/// settings of the file are applied so the geometry matches its `Model`.
+///
+/// # Arguments
+/// * `path` - Path to the OBJ file
+/// * `asset_server` - The asset server to read the file through
+///
+/// # Returns
+/// The vertices and triangle indices of the merged mesh
+pub(crate) fn read_obj_geometry<P>(
+    path: P,
+    asset_server: &AssetServer,
+) -> Result<(Vec<Vertex>, Vec<u32>)>
+where
+    P: AsRef<Path>,
+{
+    let file = asset_server.read_asset(&path)?;
+    let lines = Cursor::new(file).lines();
+
+    let import_settings = asset_server.import_settings(&path);
+
+    let mut positions: Vec<Position> = Vec::new();
+    let mut normals: Vec<Normal> = Vec::new();
+    let mut uvs: Vec<UV> = Vec::new();
+
+    let mut vertices: Vec<Vertex> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+
+    for line in lines.map_while(Result::ok) {
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+
+        if tokens.is_empty() {
+            continue;
+        }
+
+        match tokens[0] {
+            "v" => {
+                positions.push(import_settings.mesh.convert_position([
+                    tokens[1].parse()?,
+                    tokens[2].parse()?,
+                    tokens[3].parse()?,
+                ]));
+            }
+            "vt" => {
+                uvs.push([tokens[1].parse()?, tokens[2].parse()?]);
+            }
+            "vn" => {
+                normals.push(import_settings.mesh.convert_normal([
+                    tokens[1].parse()?,
+                    tokens[2].parse()?,
+                    tokens[3].parse()?,
+                ]));
+            }
+            "f" => {
+                let base = vertices.len() as u32;
+
+                for vertex_info in tokens[1..].iter() {
+                    let vertex_tokens = vertex_info.split('/').collect::<Vec<_>>();
+                    let lookup = |slot: usize| -> Option<usize> {
+                        vertex_tokens
+                            .get(slot)
+                            .and_then(|token| token.parse::<usize>().ok())
+                            .map(|index| index - 1)
+                    };
+
+                    vertices.push(Vertex {
+                        position: positions
+                            [lookup(0).ok_or(anyhow!("Face is missing a position"))?],
+                        uv_coord: lookup(1).map(|index| uvs[index]).unwrap_or_default(),
+                        normal_vec: lookup(2).map(|index| normals[index]).unwrap_or_default(),
+                    });
+                }
+
+                for i in 1..(tokens.len() as u32).saturating_sub(2) {
+                    indices.extend_from_slice(&[base, base + i, base + i + 1]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok((vertices, indices))
+}
@@ -0,0 +1,724 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use anyhow::{Result, anyhow};
+use boson::{BodyTransform, BosonObject};
+use cgmath::Vector3;
+use compound::{Compound, Entity};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use super::{
+    channel::ChannelId,
+    replication::{
+        DeltaDecoder, DeltaEncoder, NetworkRelevancy, NetworkVelocity, NetworkViewer, Quantization,
+        ReplicationSettings, SnapshotPart,
+    },
+    session::{HOST_PEER, NetworkRole, NetworkState, PeerId},
+    transport::MAX_PACKET_SIZE,
+};
+use crate::Transform3D;
+
+/// Channel clients send their input commands and snapshot resync requests on
+/// reliably, and acknowledge snapshots on unreliably
+pub const INPUT_CHANNEL: ChannelId = ChannelId::MAX - 1;
+
+/// Unreliable channel the host sends the state of its bodies on
+pub const SNAPSHOT_CHANNEL: ChannelId = ChannelId::MAX - 2;
+
+/// Default number of ticks of body positions kept for lag compensation
+pub const DEFAULT_AUTHORITY_HISTORY: usize = 64;
+
+/// Default number of input commands buffered per client
+pub const DEFAULT_INPUT_BUFFER: usize = 32;
+
+// Bytes of bodies packed into one snapshot packet, leaving room for the
+// headers and the body that goes over
+const SNAPSHOT_BUDGET: usize = MAX_PACKET_SIZE - 160;
+
+/// Links an entity of the host to the entity of every client that stands for
+/// the same thing, set by the game when it spawns them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NetworkId(pub u32);
+
+/// An input command of a player, buffered by the host until the game takes it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputCommand {
+    pub peer: PeerId,
+    /// Numbered from 0 by every player, commands are taken in this order
+    pub sequence: u32,
+    /// Tick of the host the player was seeing when it sent the command, what
+    /// hits of the command are checked against
+    pub view_tick: u64,
+    pub data: Vec<u8>,
+}
+
+impl InputCommand {
+    /// Decodes the input sent with `PhysicsAuthority::send_input`
+    pub fn decode<I: DeserializeOwned>(&self) -> Result<I> {
+        Ok(bincode::deserialize(&self.data)?)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct InputPacket {
+    sequence: u32,
+    view_tick: u64,
+    data: Vec<u8>,
+}
+
+// What clients send the host
+#[derive(Debug, Serialize, Deserialize)]
+enum ClientPacket {
+    Input(InputPacket),
+    /// The client got every part of the snapshot of a tick, so the host
+    /// takes deltas from it
+    Acknowledge(u64),
+    /// The client couldn't decode a snapshot and threw away its bodies, so
+    /// the host sends every body again
+    Resync,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SnapshotPacket {
+    part: SnapshotPart,
+    /// Last command of the receiving client the host took
+    acknowledged: Option<u32>,
+    quantization: Quantization,
+    count: u32,
+    /// Bitpacked deltas from the bodies of the baseline
+    bodies: Vec<u8>,
+}
+
+struct HistoryFrame {
+    tick: u64,
+    bodies: Vec<(Entity, BosonObject, BodyTransform)>,
+}
+
+/// Server authoritative physics, where only the host simulates.
+///
+/// Inserting the authority as a resource of a world with a `NetworkState`
+/// turns it on. While the world is a client its physics is paused and the
+/// entities with a `NetworkId` are moved to where the host says they are.
+/// Players send their input with `send_input` instead of applying it, and the
+/// host takes the commands of every player with `drain_inputs` in the order
+/// they were sent. The host keeps where its bodies were for the last ticks, so
+/// hits are checked against what the shooter saw with `raycast_at`.
+///
+/// Snapshots are sent unreliably and only hold the bodies that moved since the
+/// last one a client acknowledged, quantized and bitpacked as the
+/// `ReplicationSettings` say. A lost snapshot is never resent, the next one
+/// carries what it would have. Bodies too far from the `NetworkViewer` of a
+/// player or whose `NetworkRelevancy` leaves the player out aren't sent to it
+/// at all.
+///
+/// Offline and on the host, input is buffered for the local player so the same
+/// game code runs in every role.
+///
+/// # Example
+/// ```ignore
+/// compound.insert_resource(
+///     PhysicsAuthority::new()
+///         .with_history(32)
+///         .with_replication(ReplicationSettings {
+///             relevancy_distance: Some(150.0),
+///             ..Default::default()
+///         }),
+/// );
+/// compound.spawn((NetworkId(1), Transform3D::default(), RigidBody::new(1.0, ColliderBuilder::Cube)));
+/// compound.spawn((NetworkId(2), NetworkViewer { peer }, NetworkVelocity::default(), player_transform));
+///
+/// // Every player, in IsotopeState::update
+/// compound.resource_mut::<PhysicsAuthority, _>(|authority| {
+///     authority.send_input(&PlayerInput { fire: true, aim })
+/// });
+///
+/// // The host, in IsotopeState::update
+/// compound.resource_mut::<PhysicsAuthority, _>(|authority| {
+///     for command in authority.drain_inputs() {
+///         let input: PlayerInput = command.decode()?;
+///         if input.fire {
+///             let hit = authority.raycast_at(command.view_tick, eye, eye + input.aim * 100.0, &[shooter]);
+///         }
+///     }
+/// });
+/// ```
+pub struct PhysicsAuthority {
+    history_len: usize,
+    input_buffer: usize,
+    snapshot_interval: u64,
+    replication: ReplicationSettings,
+    role: NetworkRole,
+    local_peer: PeerId,
+    tick: u64,
+
+    // Client
+    next_sequence: u32,
+    outgoing: Vec<InputPacket>,
+    unacknowledged: VecDeque<(u32, Vec<u8>)>,
+    server_tick: Option<u64>,
+    snapshots: Vec<SnapshotPacket>,
+    decoder: DeltaDecoder,
+    // Tick of the last complete snapshot, until the host is told
+    acknowledge: Option<u64>,
+    // Set when a snapshot couldn't be decoded, until a full one arrives
+    resyncing: bool,
+    request_resync: bool,
+
+    // Host
+    inputs: HashMap<PeerId, BTreeMap<u32, InputCommand>>,
+    taken: HashMap<PeerId, u32>,
+    history: VecDeque<HistoryFrame>,
+    encoder: DeltaEncoder,
+    snapshot_bytes: usize,
+}
+
+impl Default for PhysicsAuthority {
+    fn default() -> Self {
+        Self {
+            history_len: DEFAULT_AUTHORITY_HISTORY,
+            input_buffer: DEFAULT_INPUT_BUFFER,
+            snapshot_interval: 1,
+            replication: ReplicationSettings::default(),
+            role: NetworkRole::Offline,
+            local_peer: HOST_PEER,
+            tick: 0,
+            next_sequence: 0,
+            outgoing: Vec::new(),
+            unacknowledged: VecDeque::new(),
+            server_tick: None,
+            snapshots: Vec::new(),
+            decoder: DeltaDecoder::default(),
+            acknowledge: None,
+            resyncing: false,
+            request_resync: false,
+            inputs: HashMap::new(),
+            taken: HashMap::new(),
+            history: VecDeque::new(),
+            encoder: DeltaEncoder::default(),
+            snapshot_bytes: 0,
+        }
+    }
+}
+
+impl PhysicsAuthority {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how many ticks of body positions the host keeps, how far back
+    /// hits of laggy players are checked
+    pub fn with_history(mut self, ticks: usize) -> Self {
+        self.history_len = ticks.max(1);
+        self
+    }
+
+    /// Sets how many commands of each player the host buffers, older ones are
+    /// dropped when a player sends more than the game takes
+    pub fn with_input_buffer(mut self, commands: usize) -> Self {
+        self.input_buffer = commands.max(1);
+        self
+    }
+
+    /// Sets how many ticks pass between snapshots the host sends
+    pub fn with_snapshot_interval(mut self, ticks: u64) -> Self {
+        self.snapshot_interval = ticks.max(1);
+        self
+    }
+
+    /// Sets how the host compresses snapshots and which bodies it sends each player
+    pub fn with_replication(mut self, settings: ReplicationSettings) -> Self {
+        self.replication = settings;
+        self
+    }
+
+    pub fn replication(&self) -> &ReplicationSettings {
+        &self.replication
+    }
+
+    /// Changes how snapshots are compressed, clients are sent every body again
+    pub fn set_replication(&mut self, settings: ReplicationSettings) {
+        self.replication = settings;
+        self.encoder = DeltaEncoder::default();
+    }
+
+    /// Returns the bytes of bodies the host sent in its last snapshot to every client
+    pub fn snapshot_bytes(&self) -> usize {
+        self.snapshot_bytes
+    }
+
+    /// Returns whether the host is sending a body to this client, bodies that
+    /// aren't relevant to the player stay where they were last sent
+    pub fn is_replicated(&self, id: NetworkId) -> bool {
+        self.role != NetworkRole::Client || self.decoder.contains(id.0)
+    }
+
+    /// Returns whether the world simulates its own physics
+    pub fn is_authoritative(&self) -> bool {
+        self.role != NetworkRole::Client
+    }
+
+    /// Returns the number of ticks the host has simulated, or the tick of the
+    /// last snapshot on a client
+    pub fn tick(&self) -> u64 {
+        match self.role {
+            NetworkRole::Client => self.server_tick.unwrap_or_default(),
+            _ => self.tick,
+        }
+    }
+
+    /// Sends an input command of the local player to the host.
+    ///
+    /// # Arguments
+    /// * `input` - The input, encoded with bincode
+    ///
+    /// # Returns
+    /// The sequence number of the command
+    pub fn send_input<I: Serialize>(&mut self, input: &I) -> Result<u32> {
+        let data = bincode::serialize(input)?;
+        let sequence = self.next_sequence;
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+
+        if self.role == NetworkRole::Client {
+            self.unacknowledged.push_back((sequence, data.clone()));
+            self.outgoing.push(InputPacket {
+                sequence,
+                view_tick: self.tick(),
+                data,
+            });
+        } else {
+            self.buffer(InputCommand {
+                peer: self.local_peer,
+                sequence,
+                view_tick: self.tick,
+                data,
+            });
+        }
+
+        Ok(sequence)
+    }
+
+    /// Returns the commands of the local player the host hasn't taken yet,
+    /// oldest first, to predict their outcome on top of the last snapshot
+    pub fn unacknowledged_inputs(&self) -> impl Iterator<Item = (u32, &[u8])> {
+        self.unacknowledged
+            .iter()
+            .map(|(sequence, data)| (*sequence, data.as_slice()))
+    }
+
+    /// Takes the buffered commands of every player, each player's in the order they were sent
+    pub fn drain_inputs(&mut self) -> Vec<InputCommand> {
+        let mut commands = Vec::new();
+
+        for (peer, buffered) in self.inputs.iter_mut() {
+            if let Some((sequence, _)) = buffered.last_key_value() {
+                self.taken.insert(*peer, *sequence);
+            }
+
+            commands.extend(std::mem::take(buffered).into_values());
+        }
+
+        commands
+    }
+
+    /// Returns where the model of an entity was at a tick of the host
+    pub fn transform_at(&self, entity: Entity, tick: u64) -> Option<BodyTransform> {
+        self.frame_at(tick)?
+            .bodies
+            .iter()
+            .find(|(body, _, _)| *body == entity)
+            .map(|(_, _, transform)| *transform)
+    }
+
+    /// Casts a line against the bodies of the host as they were at a tick.
+    ///
+    /// Ticks older than the history are checked against the oldest kept one.
+    ///
+    /// # Arguments
+    /// * `tick` - Tick to rewind to, usually the `view_tick` of a command
+    /// * `from` - Start of the line in world space
+    /// * `to` - End of the line in world space
+    /// * `ignore` - Entities that can't be hit, such as the shooter
+    ///
+    /// # Returns
+    /// The first entity hit and how far along the line from 0 to 1 it was hit
+    pub fn raycast_at(
+        &self,
+        tick: u64,
+        from: Vector3<f64>,
+        to: Vector3<f64>,
+        ignore: &[Entity],
+    ) -> Option<(Entity, f64)> {
+        self.frame_at(tick)?
+            .bodies
+            .iter()
+            .filter(|(entity, _, _)| !ignore.contains(entity))
+            .filter_map(|(entity, object, transform)| {
+                object
+                    .ray_distance_at(transform, from, to)
+                    .map(|t| (*entity, t))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+    }
+
+    fn frame_at(&self, tick: u64) -> Option<&HistoryFrame> {
+        self.history
+            .iter()
+            .rev()
+            .find(|frame| frame.tick <= tick)
+            .or(self.history.front())
+    }
+
+    fn buffer(&mut self, command: InputCommand) {
+        // Commands that were already taken are resends or arrived too late
+        if self
+            .taken
+            .get(&command.peer)
+            .is_some_and(|taken| is_not_after(command.sequence, *taken))
+        {
+            return;
+        }
+
+        let buffered = self.inputs.entry(command.peer).or_default();
+        buffered.insert(command.sequence, command);
+
+        while buffered.len() > self.input_buffer {
+            if let Some((sequence, command)) = buffered.pop_first() {
+                debug!(
+                    "Dropped input {} of peer {}, the buffer is full",
+                    sequence, command.peer
+                );
+            }
+        }
+    }
+
+    /// Exchanges commands and snapshots with the session
+    fn update_session(&mut self, network: &mut NetworkState) {
+        let role = network.role();
+        if role != self.role {
+            debug!("Physics authority is now {:?}", role);
+            self.role = role;
+            self.server_tick = None;
+            self.unacknowledged.clear();
+            self.inputs.clear();
+            self.taken.clear();
+            self.decoder.clear();
+            self.acknowledge = None;
+            self.resyncing = false;
+            self.request_resync = false;
+            self.encoder = DeltaEncoder::default();
+        }
+        self.local_peer = network.local_peer().unwrap_or(HOST_PEER);
+
+        match role {
+            NetworkRole::Client => {
+                for packet in std::mem::take(&mut self.outgoing) {
+                    let sequence = packet.sequence;
+                    match bincode::serialize(&ClientPacket::Input(packet)) {
+                        Ok(bytes) => {
+                            if let Err(err) = network.send_reliable(HOST_PEER, INPUT_CHANNEL, bytes)
+                            {
+                                warn!("Failed to send input {}: {}", sequence, err);
+                            }
+                        }
+                        Err(err) => warn!("Failed to encode input {}: {}", sequence, err),
+                    }
+                }
+
+                // Lost acknowledgements only keep deltas larger for a while
+                if let Some(tick) = self.acknowledge.take() {
+                    let result = bincode::serialize(&ClientPacket::Acknowledge(tick))
+                        .map_err(|err| anyhow!(err))
+                        .and_then(|bytes| network.send_unreliable(HOST_PEER, INPUT_CHANNEL, bytes));
+                    if let Err(err) = result {
+                        debug!("Failed to acknowledge snapshot {}: {}", tick, err);
+                    }
+                }
+
+                if self.request_resync {
+                    let result = bincode::serialize(&ClientPacket::Resync)
+                        .map_err(|err| anyhow!(err))
+                        .and_then(|bytes| network.send_reliable(HOST_PEER, INPUT_CHANNEL, bytes));
+                    match result {
+                        Ok(()) => self.request_resync = false,
+                        Err(err) => warn!("Failed to ask the host for a full snapshot: {}", err),
+                    }
+                }
+
+                for (_, bytes) in network.drain_channel(SNAPSHOT_CHANNEL) {
+                    match bincode::deserialize::<SnapshotPacket>(&bytes) {
+                        Ok(snapshot) => self.snapshots.push(snapshot),
+                        Err(err) => debug!("Dropped invalid snapshot: {}", err),
+                    }
+                }
+            }
+            NetworkRole::Host => {
+                for (peer, bytes) in network.drain_channel(INPUT_CHANNEL) {
+                    match bincode::deserialize::<ClientPacket>(&bytes) {
+                        Ok(ClientPacket::Input(InputPacket {
+                            sequence,
+                            view_tick,
+                            data,
+                        })) => self.buffer(InputCommand {
+                            peer,
+                            sequence,
+                            // Clients can't see ticks that haven't happened
+                            view_tick: view_tick.min(self.tick),
+                            data,
+                        }),
+                        Ok(ClientPacket::Acknowledge(tick)) => self.encoder.acknowledge(peer, tick),
+                        Ok(ClientPacket::Resync) => {
+                            debug!("Peer {} asked for a full snapshot", peer);
+                            self.encoder.reset_peer(peer);
+                        }
+                        Err(err) => debug!("Dropped invalid input from peer {}: {}", peer, err),
+                    }
+                }
+            }
+            NetworkRole::Offline | NetworkRole::Connecting => {
+                self.outgoing.clear();
+            }
+        }
+    }
+
+    /// Moves the entities of a client to the last snapshot of the host
+    fn apply_snapshots(&mut self, compound: &Compound) {
+        let mut latest = HashMap::new();
+
+        for snapshot in std::mem::take(&mut self.snapshots) {
+            let part = snapshot.part;
+            self.server_tick = Some(
+                self.server_tick
+                    .map_or(part.tick, |tick| tick.max(part.tick)),
+            );
+
+            if let Some(acknowledged) = snapshot.acknowledged {
+                self.unacknowledged
+                    .retain(|(sequence, _)| !is_not_after(*sequence, acknowledged));
+            }
+
+            // Deltas sent before the host got the resync request are of
+            // bodies that were thrown away
+            if self.resyncing && part.baseline.is_some() {
+                continue;
+            }
+            self.resyncing = false;
+
+            let decoded = snapshot.quantization.validate().and_then(|()| {
+                self.decoder.decode(
+                    part,
+                    &snapshot.bodies,
+                    snapshot.count,
+                    snapshot.quantization.rotation_bits(),
+                )
+            });
+            let bodies = match decoded {
+                Ok(Some((tick, bodies))) => {
+                    self.acknowledge = Some(tick);
+                    bodies
+                }
+                // Parts of a snapshot that isn't complete yet, or is out of date
+                Ok(None) => continue,
+                Err(err) => {
+                    // Later deltas would build on bodies that are now wrong
+                    warn!(
+                        "Failed to decode snapshot {}, asking for a full one: {}",
+                        part.tick, err
+                    );
+                    self.decoder.clear();
+                    self.acknowledge = None;
+                    self.resyncing = true;
+                    self.request_resync = true;
+                    continue;
+                }
+            };
+
+            for (id, body) in bodies {
+                match body {
+                    Some(body) => {
+                        latest.insert(id, snapshot.quantization.restore(&body));
+                    }
+                    None => {
+                        latest.remove(&id);
+                    }
+                }
+            }
+        }
+
+        if latest.is_empty() {
+            return;
+        }
+
+        // Marked as modified so the bodies are moved along in the physics sync
+        compound.iter_mut_duo(
+            |_entity, network_id: &mut NetworkId, transform: &mut Transform3D| {
+                if let Some((position, rotation, _)) = latest.get(&network_id.0) {
+                    *transform = Transform3D::new(*position, *rotation);
+                }
+            },
+        );
+
+        compound.iter_mut_duo(
+            |_entity, network_id: &mut NetworkId, velocity: &mut NetworkVelocity| {
+                if let Some((_, _, Some(sent))) = latest.get(&network_id.0) {
+                    *velocity = *sent;
+                }
+            },
+        );
+    }
+
+    /// Records where the bodies of the host are and sends them to every client
+    fn record(&mut self, compound: &Compound, network: Option<&mut NetworkState>) {
+        let tick = self.tick;
+        self.tick += 1;
+
+        let mut frame = HistoryFrame {
+            tick,
+            bodies: Vec::new(),
+        };
+        let mut velocities = HashMap::new();
+        compound.iter_duo(|entity, _network_id: &NetworkId, object: &BosonObject| {
+            let (_, current, body_velocities) = object.read_state();
+            frame.bodies.push((entity, object.clone(), current));
+
+            if self.replication.velocities {
+                velocities.insert(
+                    entity,
+                    NetworkVelocity {
+                        linear: body_velocities.linear.map(|v| v as f32),
+                        angular: body_velocities.angular.map(|v| v as f32),
+                    },
+                );
+            }
+        });
+
+        self.history.push_back(frame);
+        while self.history.len() > self.history_len {
+            self.history.pop_front();
+        }
+
+        let Some(network) = network.filter(|network| network.role() == NetworkRole::Host) else {
+            return;
+        };
+        if !tick.is_multiple_of(self.snapshot_interval) {
+            return;
+        }
+
+        let quantization = Quantization::new(&self.replication);
+        let mut bodies = Vec::new();
+        compound.iter_duo(|entity, network_id: &NetworkId, transform: &Transform3D| {
+            transform.get_position_and_rotation(|position, rotation| {
+                let quantized =
+                    quantization.quantize(*position, *rotation, velocities.get(&entity).copied());
+                bodies.push((entity, network_id.0, *position, quantized));
+            });
+        });
+        bodies.sort_unstable_by_key(|(_, id, _, _)| *id);
+
+        let mut relevancies = HashMap::new();
+        compound.iter_mol(|entity, relevancy: &NetworkRelevancy| {
+            relevancies.insert(entity, relevancy.clone());
+        });
+
+        let mut viewers = HashMap::new();
+        compound.iter_duo(|_entity, viewer: &NetworkViewer, transform: &Transform3D| {
+            transform.get_position(|position| {
+                viewers.insert(viewer.peer, *position);
+            });
+        });
+
+        let peers = network.connected_peers();
+        self.encoder.retain_peers(&peers);
+        self.snapshot_bytes = 0;
+
+        for peer in peers {
+            let relevant = bodies
+                .iter()
+                .filter(|(entity, _, position, _)| {
+                    NetworkRelevancy::is_relevant(
+                        relevancies.get(entity),
+                        self.replication.relevancy_distance,
+                        peer,
+                        *position,
+                        viewers.get(&peer).copied(),
+                    )
+                })
+                .map(|(_, id, _, body)| (*id, *body))
+                .collect::<Vec<_>>();
+
+            // Snapshots without changes are still sent to acknowledge input
+            let (baseline, chunks) = self.encoder.encode(
+                peer,
+                tick,
+                &relevant,
+                quantization.rotation_bits(),
+                SNAPSHOT_BUDGET,
+            );
+            let Ok(parts) = u16::try_from(chunks.len()) else {
+                warn!(
+                    "Snapshot {} for peer {} needs {} parts, not sent",
+                    tick,
+                    peer,
+                    chunks.len()
+                );
+                continue;
+            };
+
+            for (index, (count, chunk)) in (0..parts).zip(chunks) {
+                self.snapshot_bytes += chunk.len();
+
+                let packet = SnapshotPacket {
+                    part: SnapshotPart {
+                        tick,
+                        baseline,
+                        index,
+                        parts,
+                    },
+                    acknowledged: self.taken.get(&peer).copied(),
+                    quantization,
+                    count,
+                    bodies: chunk,
+                };
+
+                // A client that misses a part keeps taking deltas from its last baseline
+                let result = bincode::serialize(&packet)
+                    .map_err(|err| anyhow!(err))
+                    .and_then(|bytes| network.send_unreliable(peer, SNAPSHOT_CHANNEL, bytes));
+                if let Err(err) = result {
+                    warn!("Failed to send snapshot to peer {}: {}", peer, err);
+                    break;
+                }
+            }
+        }
+    }
+}
+// Whether a sequence number is the same as or older than another, allowing for wrapping
+fn is_not_after(sequence: u32, other: u32) -> bool {
+    other.wrapping_sub(sequence) < u32::MAX / 2
+}
+
+/// Sends the input of a client and applies the snapshots of the host if the
+/// world has a `PhysicsAuthority`, or buffers the input of every player on the host
+pub(crate) fn update_authority(compound: &Compound) {
+    compound.resource_mut::<PhysicsAuthority, _>(|authority| {
+        compound.resource_mut::<NetworkState, _>(|network| authority.update_session(network));
+        if !authority.is_authoritative() {
+            authority.apply_snapshots(compound);
+        }
+    });
+}
+
+/// Records the bodies of the host after physics and sends them to its clients
+pub(crate) fn record_authority(compound: &Compound) {
+    compound.resource_mut::<PhysicsAuthority, _>(|authority| {
+        if !authority.is_authoritative() {
+            return;
+        }
+
+        if compound
+            .resource_mut::<NetworkState, _>(|network| authority.record(compound, Some(network)))
+            .is_none()
+        {
+            authority.record(compound, None);
+        }
+    });
+}
@@ -0,0 +1,110 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, anyhow};
+
+/// Id of a reliable ordered channel, see `NetworkState::send_reliable`
+pub type ChannelId = u16;
+
+// Time before a message that wasn't acknowledged is sent again
+const RESEND_INTERVAL: Duration = Duration::from_millis(200);
+
+// Most messages of a channel in flight at once, the rest wait their turn
+const SEND_WINDOW: usize = 64;
+
+// Messages further ahead than this can't have been sent within the window
+const RECEIVE_WINDOW: u32 = 1024;
+
+// Most messages of a channel waiting to be acknowledged, a peer that falls
+// this far behind isn't keeping up
+pub(crate) const MAX_UNACKNOWLEDGED: usize = RECEIVE_WINDOW as usize;
+
+#[derive(Debug, Clone)]
+struct Unacknowledged {
+    sequence: u32,
+    data: Vec<u8>,
+    last_sent: Option<Instant>,
+}
+
+/// A reliable ordered channel with one peer.
+///
+/// Messages are numbered, resent until the peer acknowledges them and
+/// delivered in the order they were sent, holding back any that arrive early.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ReliableChannel {
+    next_sequence: u32,
+    unacknowledged: VecDeque<Unacknowledged>,
+    expected: u32,
+    early: BTreeMap<u32, Vec<u8>>,
+}
+
+impl ReliableChannel {
+    /// Queues a message to be sent
+    ///
+    /// # Returns
+    /// An error if too many messages are waiting to be acknowledged already
+    pub(crate) fn queue(&mut self, data: Vec<u8>) -> Result<()> {
+        if self.unacknowledged.len() >= MAX_UNACKNOWLEDGED {
+            return Err(anyhow!(
+                "{} messages are waiting to be acknowledged",
+                self.unacknowledged.len()
+            ));
+        }
+
+        self.unacknowledged.push_back(Unacknowledged {
+            sequence: self.next_sequence,
+            data,
+            last_sent: None,
+        });
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+
+        Ok(())
+    }
+
+    /// Returns the messages that have to be sent now, new ones and ones that
+    /// weren't acknowledged in time
+    pub(crate) fn due(&mut self, now: Instant) -> Vec<(u32, Vec<u8>)> {
+        self.unacknowledged
+            .iter_mut()
+            .take(SEND_WINDOW)
+            .filter(|message| {
+                message
+                    .last_sent
+                    .is_none_or(|last_sent| now.duration_since(last_sent) >= RESEND_INTERVAL)
+            })
+            .map(|message| {
+                message.last_sent = Some(now);
+                (message.sequence, message.data.clone())
+            })
+            .collect()
+    }
+
+    pub(crate) fn acknowledge(&mut self, sequence: u32) {
+        self.unacknowledged
+            .retain(|message| message.sequence != sequence);
+    }
+
+    /// Takes a received message
+    ///
+    /// # Returns
+    /// The messages that can be delivered in order now, none if the message
+    /// arrived early or was already delivered
+    pub(crate) fn receive(&mut self, sequence: u32, data: Vec<u8>) -> Vec<Vec<u8>> {
+        // Sequences wrap, anything behind the expected one is a duplicate
+        if sequence.wrapping_sub(self.expected) >= RECEIVE_WINDOW {
+            return Vec::new();
+        }
+
+        self.early.insert(sequence, data);
+
+        let mut delivered = Vec::new();
+        while let Some(data) = self.early.remove(&self.expected) {
+            delivered.push(data);
+            self.expected = self.expected.wrapping_add(1);
+        }
+
+        delivered
+    }
+}
@@ -0,0 +1,312 @@
+use std::collections::VecDeque;
+
+use anyhow::{Result, anyhow};
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    channel::ChannelId,
+    session::{HOST_PEER, NetworkEvent, NetworkRole, NetworkState, PeerId},
+};
+
+/// Reliable channel chat is sent on, games shouldn't use it for their own channels
+pub const CHAT_CHANNEL: ChannelId = ChannelId::MAX;
+
+/// Longest chat message in characters, longer ones are cut off
+pub const MAX_CHAT_LENGTH: usize = 256;
+
+/// A chat message sent by a player of the session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatMessage {
+    pub sender: PeerId,
+    /// Name of the sender when the message arrived
+    pub name: String,
+    pub text: String,
+}
+
+// What is sent on the chat channel, clients send to the host which passes
+// the message on to everyone else
+#[derive(Debug, Serialize, Deserialize)]
+struct ChatPacket {
+    sender: PeerId,
+    text: String,
+}
+
+impl NetworkState {
+    /// Sends a chat message to everyone in the session.
+    ///
+    /// Chat is built on a reliable channel like any channel a game defines, so
+    /// messages arrive in the order they were sent. The local player's own
+    /// messages are reported with `NetworkEvent::Chat` as well.
+    ///
+    /// # Arguments
+    /// * `text` - The message, cut off at `MAX_CHAT_LENGTH` characters
+    ///
+    /// # Returns
+    /// An error if the game isn't in a session
+    pub fn send_chat(&mut self, text: impl Into<String>) -> Result<()> {
+        let Some(sender) = self
+            .local_peer()
+            .filter(|_| matches!(self.role(), NetworkRole::Host | NetworkRole::Client))
+        else {
+            return Err(anyhow!("Can't chat without being in a session"));
+        };
+
+        let text = text
+            .into()
+            .trim()
+            .chars()
+            .take(MAX_CHAT_LENGTH)
+            .collect::<String>();
+        if text.is_empty() {
+            return Ok(());
+        }
+
+        let packet = bincode::serialize(&ChatPacket {
+            sender,
+            text: text.clone(),
+        })?;
+        match self.role() {
+            NetworkRole::Host => self.broadcast_reliable(CHAT_CHANNEL, packet)?,
+            _ => self.send_reliable(HOST_PEER, CHAT_CHANNEL, packet)?,
+        }
+
+        let name = self.player_name().to_string();
+        self.deliver_chat(ChatMessage { sender, name, text });
+
+        Ok(())
+    }
+
+    /// Takes the chat messages that arrived, passing the ones of clients on if
+    /// this is the host
+    pub(crate) fn update_chat(&mut self) {
+        for (peer, bytes) in self.drain_channel(CHAT_CHANNEL) {
+            let Ok(ChatPacket { sender, text }) = bincode::deserialize::<ChatPacket>(&bytes) else {
+                debug!("Dropped invalid chat message from peer {}", peer);
+                continue;
+            };
+
+            if self.role() != NetworkRole::Host {
+                self.deliver_chat(ChatMessage {
+                    sender,
+                    name: self.name_of(sender),
+                    text,
+                });
+                continue;
+            }
+
+            // Clients can't speak for someone else, so the host sends on who it came from
+            let text = text.chars().take(MAX_CHAT_LENGTH).collect::<String>();
+            if let Ok(packet) = bincode::serialize(&ChatPacket {
+                sender: peer,
+                text: text.clone(),
+            }) {
+                let others = self
+                    .peers()
+                    .iter()
+                    .map(|other| other.id)
+                    .filter(|other| *other != peer)
+                    .collect::<Vec<_>>();
+
+                for other in others {
+                    if let Err(err) = self.send_reliable(other, CHAT_CHANNEL, packet.clone()) {
+                        debug!("Failed to pass chat on to peer {}: {}", other, err);
+                    }
+                }
+            }
+
+            self.deliver_chat(ChatMessage {
+                sender: peer,
+                name: self.name_of(peer),
+                text,
+            });
+        }
+    }
+
+    fn name_of(&self, peer: PeerId) -> String {
+        if self.local_peer() == Some(peer) {
+            return self.player_name().to_string();
+        }
+
+        self.peer(peer)
+            .map(|peer| peer.name.clone())
+            .unwrap_or_else(|| format!("Player {}", peer))
+    }
+
+    fn deliver_chat(&mut self, message: ChatMessage) {
+        self.chat.push(message.clone());
+        self.events.push(NetworkEvent::Chat(message));
+    }
+}
+
+/// A line of a `ChatBox`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatLine {
+    pub name: String,
+    pub text: String,
+    /// Seconds since the engine started when the line arrived
+    pub t: f32,
+}
+
+/// Component holding what a chat UI shows, filled with the chat messages of
+/// the session of its world.
+///
+/// The chat box keeps the recent lines and the message being typed. While it is
+/// closed lines fade out after their lifetime, like the chat of most games.
+/// Submitted messages are sent by the engine on the next tick. How the chat box
+/// is drawn is up to the UI of the game.
+///
+/// # Example
+/// ```ignore
+/// compound.spawn((ChatBox::new().with_max_lines(8),));
+///
+/// // In IsotopeState::update
+/// compound.iter_mut_mol(|_entity, chat_box: &mut ChatBox| {
+///     if pressed_enter {
+///         if chat_box.is_open() {
+///             chat_box.submit();
+///         } else {
+///             chat_box.open();
+///         }
+///     }
+///     chat_box.type_text(&typed_text);
+///
+///     for (line, opacity) in chat_box.visible_lines(t) {
+///         // Draw "{line.name}: {line.text}" with the opacity
+///     }
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChatBox {
+    /// Most lines kept, older ones are dropped
+    pub max_lines: usize,
+    /// Seconds a line is shown while the chat box is closed
+    pub line_lifetime: f32,
+    /// Seconds a line takes to fade out at the end of its lifetime
+    pub fade_time: f32,
+
+    lines: VecDeque<ChatLine>,
+    draft: String,
+    open: bool,
+    submitted: Vec<String>,
+}
+
+impl Default for ChatBox {
+    fn default() -> Self {
+        Self {
+            max_lines: 50,
+            line_lifetime: 10.0,
+            fade_time: 1.0,
+            lines: VecDeque::new(),
+            draft: String::new(),
+            open: false,
+            submitted: Vec::new(),
+        }
+    }
+}
+
+impl ChatBox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_lines(mut self, max_lines: usize) -> Self {
+        self.max_lines = max_lines;
+        self
+    }
+
+    pub fn with_line_lifetime(mut self, line_lifetime: f32) -> Self {
+        self.line_lifetime = line_lifetime;
+        self
+    }
+
+    /// Opens the chat box for typing
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    /// Closes the chat box, throwing away the message being typed
+    pub fn close(&mut self) {
+        self.open = false;
+        self.draft.clear();
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Returns the message being typed
+    pub fn draft(&self) -> &str {
+        &self.draft
+    }
+
+    /// Adds typed text to the message while the chat box is open
+    pub fn type_text(&mut self, text: &str) {
+        if !self.open {
+            return;
+        }
+
+        let room = MAX_CHAT_LENGTH.saturating_sub(self.draft.chars().count());
+        self.draft
+            .extend(text.chars().filter(|c| !c.is_control()).take(room));
+    }
+
+    /// Removes the last character of the message being typed
+    pub fn backspace(&mut self) {
+        self.draft.pop();
+    }
+
+    /// Sends the message being typed and closes the chat box
+    pub fn submit(&mut self) {
+        let draft = std::mem::take(&mut self.draft);
+        if !draft.trim().is_empty() {
+            self.submitted.push(draft);
+        }
+
+        self.open = false;
+    }
+
+    /// Returns every kept line, oldest first
+    pub fn lines(&self) -> impl Iterator<Item = &ChatLine> {
+        self.lines.iter()
+    }
+
+    /// Returns the lines to show with their opacity, every line while the chat
+    /// box is open and the ones that haven't faded out while it is closed
+    ///
+    /// # Arguments
+    /// * `t` - Seconds since the engine started
+    pub fn visible_lines(&self, t: f32) -> impl Iterator<Item = (&ChatLine, f32)> {
+        self.lines.iter().filter_map(move |line| {
+            if self.open {
+                return Some((line, 1.0));
+            }
+
+            let remaining = self.line_lifetime - (t - line.t);
+            if remaining <= 0.0 {
+                return None;
+            }
+
+            Some((
+                line,
+                (remaining / self.fade_time.max(f32::EPSILON)).min(1.0),
+            ))
+        })
+    }
+
+    pub(crate) fn push(&mut self, message: &ChatMessage, t: f32) {
+        self.lines.push_back(ChatLine {
+            name: message.name.clone(),
+            text: message.text.clone(),
+            t,
+        });
+
+        while self.lines.len() > self.max_lines {
+            self.lines.pop_front();
+        }
+    }
+
+    pub(crate) fn take_submitted(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.submitted)
+    }
+}
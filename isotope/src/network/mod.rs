@@ -0,0 +1,17 @@
+pub use authority::*;
+pub use channel::ChannelId;
+pub use chat::*;
+pub use replication::*;
+pub use session::*;
+pub use transport::*;
+#[cfg(feature = "voice")]
+pub use voice::*;
+
+mod authority;
+mod channel;
+mod chat;
+mod replication;
+mod session;
+mod transport;
+#[cfg(feature = "voice")]
+mod voice;
@@ -0,0 +1,1192 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use anyhow::{Result, anyhow};
+use cgmath::{Quaternion, Vector3, Zero};
+use serde::{Deserialize, Serialize};
+
+use super::session::PeerId;
+
+// Bits a component of a rotation can be packed into
+const MIN_ROTATION_BITS: u8 = 4;
+const MAX_ROTATION_BITS: u8 = 16;
+
+/// How the host compresses the bodies it replicates.
+///
+/// Positions and velocities are rounded to steps of their precision and
+/// rotations to a number of bits per component. Only what changed since the
+/// last snapshot a client acknowledged is sent, bitpacked by how much it moved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplicationSettings {
+    /// Smallest step positions are sent with, in meters
+    pub position_precision: f32,
+    /// Smallest step velocities are sent with, in meters or radians per second
+    pub velocity_precision: f32,
+    /// Bits each of the three sent components of a rotation is packed into,
+    /// from 4 to 16
+    pub rotation_bits: u8,
+    /// Whether velocities are sent along with transforms, for `NetworkVelocity`
+    pub velocities: bool,
+    /// Distance from the `NetworkViewer` of a player past which bodies aren't
+    /// sent to that player, unless their `NetworkRelevancy` says otherwise
+    pub relevancy_distance: Option<f32>,
+}
+
+impl Default for ReplicationSettings {
+    fn default() -> Self {
+        Self {
+            position_precision: 0.001,
+            velocity_precision: 0.01,
+            rotation_bits: 12,
+            velocities: true,
+            relevancy_distance: None,
+        }
+    }
+}
+
+/// Component marking the entity a player sees the world from, what the
+/// relevancy of bodies is measured from for that player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkViewer {
+    pub peer: PeerId,
+}
+
+/// Component deciding which players an entity with a `NetworkId` is sent to,
+/// instead of the `relevancy_distance` of the `ReplicationSettings`.
+///
+/// Players without a `NetworkViewer` get every body that isn't `Only` sent to others.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetworkRelevancy {
+    /// Sent to every player
+    Always,
+    /// Sent to players whose viewer is within the distance
+    Within(f32),
+    /// Sent only to the players, such as the owner of an item
+    Only(Vec<PeerId>),
+}
+
+impl NetworkRelevancy {
+    pub(super) fn is_relevant(
+        relevancy: Option<&Self>,
+        default_distance: Option<f32>,
+        peer: PeerId,
+        position: Vector3<f32>,
+        viewer: Option<Vector3<f32>>,
+    ) -> bool {
+        let distance = match relevancy {
+            Some(Self::Always) => return true,
+            Some(Self::Only(peers)) => return peers.contains(&peer),
+            Some(Self::Within(distance)) => Some(*distance),
+            None => default_distance,
+        };
+
+        match (distance, viewer) {
+            (Some(distance), Some(viewer)) => {
+                let offset = position - viewer;
+                offset.x * offset.x + offset.y * offset.y + offset.z * offset.z
+                    <= distance * distance
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Component a client fills with the last velocities the host sent for an
+/// entity with a `NetworkId`, to extrapolate between snapshots.
+///
+/// The velocities are only sent if `ReplicationSettings::velocities` is set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NetworkVelocity {
+    pub linear: Vector3<f32>,
+    pub angular: Vector3<f32>,
+}
+
+impl Default for NetworkVelocity {
+    fn default() -> Self {
+        Self {
+            linear: Vector3::zero(),
+            angular: Vector3::zero(),
+        }
+    }
+}
+
+/// What the bodies of a snapshot are rounded with, sent along so clients
+/// don't need the settings of the host
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(super) struct Quantization {
+    position_precision: f32,
+    velocity_precision: f32,
+    rotation_bits: u8,
+}
+
+impl Quantization {
+    pub(super) fn new(settings: &ReplicationSettings) -> Self {
+        Self {
+            position_precision: settings.position_precision.max(f32::EPSILON),
+            velocity_precision: settings.velocity_precision.max(f32::EPSILON),
+            rotation_bits: settings
+                .rotation_bits
+                .clamp(MIN_ROTATION_BITS, MAX_ROTATION_BITS),
+        }
+    }
+
+    /// Checks a quantization sent by the host, which could be anything
+    pub(super) fn validate(&self) -> Result<()> {
+        if !(MIN_ROTATION_BITS..=MAX_ROTATION_BITS).contains(&self.rotation_bits) {
+            return Err(anyhow!(
+                "Rotation bits {} are outside of {} to {}",
+                self.rotation_bits,
+                MIN_ROTATION_BITS,
+                MAX_ROTATION_BITS
+            ));
+        }
+
+        for precision in [self.position_precision, self.velocity_precision] {
+            if !precision.is_finite() || precision < f32::EPSILON {
+                return Err(anyhow!("Invalid precision {}", precision));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn rotation_bits(&self) -> u8 {
+        self.rotation_bits
+    }
+
+    /// Rounds a body to what is sent
+    pub(super) fn quantize(
+        &self,
+        position: Vector3<f32>,
+        rotation: Quaternion<f32>,
+        velocity: Option<NetworkVelocity>,
+    ) -> QuantizedBody {
+        let step = |value: f32, precision: f32| {
+            (value / precision)
+                .round()
+                .clamp(i32::MIN as f32, i32::MAX as f32) as i32
+        };
+        let vector = |vector: Vector3<f32>, precision: f32| {
+            [
+                step(vector.x, precision),
+                step(vector.y, precision),
+                step(vector.z, precision),
+            ]
+        };
+
+        QuantizedBody {
+            position: vector(position, self.position_precision),
+            rotation: self.quantize_rotation(rotation),
+            velocity: velocity.map(|velocity| {
+                (
+                    vector(velocity.linear, self.velocity_precision),
+                    vector(velocity.angular, self.velocity_precision),
+                )
+            }),
+        }
+    }
+
+    /// Turns a sent body back into a position, rotation and velocity
+    pub(super) fn restore(
+        &self,
+        body: &QuantizedBody,
+    ) -> (Vector3<f32>, Quaternion<f32>, Option<NetworkVelocity>) {
+        let vector = |vector: [i32; 3], precision: f32| {
+            Vector3::new(
+                vector[0] as f32 * precision,
+                vector[1] as f32 * precision,
+                vector[2] as f32 * precision,
+            )
+        };
+
+        (
+            vector(body.position, self.position_precision),
+            self.restore_rotation(body.rotation),
+            body.velocity.map(|(linear, angular)| NetworkVelocity {
+                linear: vector(linear, self.velocity_precision),
+                angular: vector(angular, self.velocity_precision),
+            }),
+        )
+    }
+
+    // Smallest three, the largest component is left out and rebuilt from the others
+    fn quantize_rotation(&self, rotation: Quaternion<f32>) -> (u8, [u16; 3]) {
+        let components = [rotation.v.x, rotation.v.y, rotation.v.z, rotation.s];
+        let length = components.iter().map(|c| c * c).sum::<f32>().sqrt();
+        if length <= f32::EPSILON {
+            return self.quantize_rotation(Quaternion::new(1.0, 0.0, 0.0, 0.0));
+        }
+
+        let largest = (0..4)
+            .max_by(|a, b| components[*a].abs().total_cmp(&components[*b].abs()))
+            .unwrap_or(3);
+        // q and -q are the same rotation, so the left out one is always positive
+        let sign = components[largest].signum() / length;
+
+        let max = ((1 << self.rotation_bits) - 1) as f32;
+        let mut packed = [0; 3];
+        for (slot, index) in (0..4).filter(|index| *index != largest).enumerate() {
+            let unit = (components[index] * sign * std::f32::consts::SQRT_2 + 1.0) * 0.5;
+            packed[slot] = (unit.clamp(0.0, 1.0) * max).round() as u16;
+        }
+
+        (largest as u8, packed)
+    }
+
+    fn restore_rotation(&self, (largest, packed): (u8, [u16; 3])) -> Quaternion<f32> {
+        let max = ((1 << self.rotation_bits) - 1) as f32;
+        let mut components = [0.0; 4];
+        let mut sum = 0.0;
+
+        for (slot, index) in (0..4)
+            .filter(|index| *index != largest as usize)
+            .enumerate()
+        {
+            let component =
+                (packed[slot] as f32 / max * 2.0 - 1.0) * std::f32::consts::FRAC_1_SQRT_2;
+            components[index] = component;
+            sum += component * component;
+        }
+        components[(largest as usize).min(3)] = (1.0 - sum).max(0.0).sqrt();
+
+        Quaternion::new(components[3], components[0], components[1], components[2])
+    }
+}
+
+/// A body rounded to the steps it is sent in, what deltas are taken between
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(super) struct QuantizedBody {
+    position: [i32; 3],
+    rotation: (u8, [u16; 3]),
+    velocity: Option<([i32; 3], [i32; 3])>,
+}
+
+// Snapshots the host remembers sending a client until it acknowledges one,
+// deltas are only ever taken from one of these
+const SENT_SNAPSHOTS: usize = 32;
+
+// Complete snapshots a client keeps past the one the host last took deltas
+// from, more than the host remembers so whichever it picks is still there
+const KEPT_SNAPSHOTS: usize = 2 * SENT_SNAPSHOTS;
+
+// Snapshots a client waits on the missing parts of at once
+const PARTIAL_SNAPSHOTS: usize = 8;
+
+/// Which snapshot a part belongs to and which one its deltas are taken from.
+///
+/// Snapshots are sent unreliably, split in parts that each fit a packet. A
+/// client only takes a snapshot once every part of it arrived.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct SnapshotPart {
+    pub(super) tick: u64,
+    /// Tick of the snapshot the bodies are deltas of, `None` when every body
+    /// is sent in full
+    pub(super) baseline: Option<u64>,
+    pub(super) index: u16,
+    pub(super) parts: u16,
+}
+
+#[derive(Default)]
+struct PeerSnapshots {
+    // The last snapshot the client acknowledged, what deltas are taken from
+    acknowledged: Option<(u64, HashMap<u32, QuantizedBody>)>,
+    // Snapshots sent since that the client hasn't acknowledged yet
+    sent: VecDeque<(u64, HashMap<u32, QuantizedBody>)>,
+}
+
+/// The bodies a client acknowledged, so only what changed since is sent.
+///
+/// Snapshots can be lost, so deltas are taken from the last snapshot the
+/// client said it has rather than the last one it was sent.
+#[derive(Default)]
+pub(super) struct DeltaEncoder {
+    peers: HashMap<PeerId, PeerSnapshots>,
+}
+
+impl DeltaEncoder {
+    /// Forgets what was sent to players that left
+    pub(super) fn retain_peers(&mut self, peers: &[PeerId]) {
+        self.peers.retain(|peer, _| peers.contains(peer));
+    }
+
+    /// Forgets what was sent to a player, the next snapshot it gets is complete
+    pub(super) fn reset_peer(&mut self, peer: PeerId) {
+        self.peers.remove(&peer);
+    }
+
+    /// Takes a snapshot a player got every part of as its new baseline.
+    ///
+    /// Acknowledgements of snapshots older than the baseline or no longer
+    /// remembered are ignored.
+    pub(super) fn acknowledge(&mut self, peer: PeerId, tick: u64) {
+        let Some(snapshots) = self.peers.get_mut(&peer) else {
+            return;
+        };
+
+        let Some(index) = snapshots.sent.iter().position(|(sent, _)| *sent == tick) else {
+            return;
+        };
+
+        snapshots.acknowledged = snapshots.sent.remove(index);
+        snapshots.sent.retain(|(sent, _)| *sent > tick);
+    }
+
+    /// Packs the bodies relevant to a player into parts of at most `budget` bytes.
+    ///
+    /// # Arguments
+    /// * `tick` - The tick of the snapshot
+    /// * `bodies` - Every relevant body sorted by id
+    /// * `budget` - Most bytes in one part
+    ///
+    /// # Returns
+    /// The tick of the baseline and the parts with the number of bodies in
+    /// each, at least one so the player can acknowledge the snapshot
+    pub(super) fn encode(
+        &mut self,
+        peer: PeerId,
+        tick: u64,
+        bodies: &[(u32, QuantizedBody)],
+        rotation_bits: u8,
+        budget: usize,
+    ) -> (Option<u64>, Vec<(u32, Vec<u8>)>) {
+        let snapshots = self.peers.entry(peer).or_default();
+        let empty = HashMap::new();
+        let (baseline_tick, baseline) = match snapshots.acknowledged.as_ref() {
+            Some((tick, baseline)) => (Some(*tick), baseline),
+            None => (None, &empty),
+        };
+
+        let mut chunks = Vec::new();
+        let mut writer = BitWriter::default();
+        let mut count = 0;
+        let mut last_id = 0;
+
+        let mut flush = |writer: &mut BitWriter, count: &mut u32, last_id: &mut u32| {
+            if *count > 0 {
+                chunks.push((*count, std::mem::take(writer).finish()));
+            }
+            *count = 0;
+            *last_id = 0;
+        };
+
+        // Bodies that stopped being relevant are removed from the client
+        let mut removed = baseline
+            .keys()
+            .filter(|id| bodies.binary_search_by_key(*id, |(id, _)| *id).is_err())
+            .copied()
+            .collect::<Vec<_>>();
+        removed.sort_unstable();
+
+        for id in removed {
+            write_id(&mut writer, id, &mut last_id);
+            writer.write(1, 1);
+            count += 1;
+
+            if writer.len() >= budget {
+                flush(&mut writer, &mut count, &mut last_id);
+            }
+        }
+
+        for (id, body) in bodies {
+            let base = baseline.get(id);
+            if base == Some(body) {
+                continue;
+            }
+
+            write_id(&mut writer, *id, &mut last_id);
+            writer.write(0, 1);
+            write_body(&mut writer, body, base, rotation_bits);
+            count += 1;
+
+            if writer.len() >= budget {
+                flush(&mut writer, &mut count, &mut last_id);
+            }
+        }
+
+        flush(&mut writer, &mut count, &mut last_id);
+        if chunks.is_empty() {
+            chunks.push((0, Vec::new()));
+        }
+
+        snapshots
+            .sent
+            .push_back((tick, bodies.iter().copied().collect()));
+        while snapshots.sent.len() > SENT_SNAPSHOTS {
+            snapshots.sent.pop_front();
+        }
+
+        (baseline_tick, chunks)
+    }
+}
+
+/// Bodies of a snapshot by id, `None` for ones the host stopped sending
+pub(super) type BodyChanges = Vec<(u32, Option<QuantizedBody>)>;
+
+// A snapshot some parts of which haven't arrived yet
+struct PartialSnapshot {
+    baseline: Option<u64>,
+    received: Vec<bool>,
+    bodies: BodyChanges,
+}
+
+/// The snapshots a client got every part of, what the deltas of the host are applied to
+#[derive(Default)]
+pub(super) struct DeltaDecoder {
+    complete: BTreeMap<u64, HashMap<u32, QuantizedBody>>,
+    partial: BTreeMap<u64, PartialSnapshot>,
+    // Tick of the last complete snapshot
+    latest: Option<u64>,
+    // Tick of the newest baseline the host used, which it never goes back from
+    baseline: Option<u64>,
+}
+
+impl DeltaDecoder {
+    pub(super) fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Returns whether the host is sending a body to this client
+    pub(super) fn contains(&self, id: u32) -> bool {
+        self.latest
+            .and_then(|tick| self.complete.get(&tick))
+            .is_some_and(|bodies| bodies.contains_key(&id))
+    }
+
+    /// Unpacks a part of a snapshot of the host.
+    ///
+    /// Parts of snapshots older than the last complete one, or whose baseline
+    /// the host has since moved past, are ignored.
+    ///
+    /// # Returns
+    /// Once every part of a snapshot arrived, its tick with the bodies that
+    /// changed since the last complete snapshot, `None` for bodies the host
+    /// stopped sending. An error if the part is invalid or its baseline is
+    /// missing, after which the client needs a full snapshot.
+    pub(super) fn decode(
+        &mut self,
+        part: SnapshotPart,
+        bytes: &[u8],
+        count: u32,
+        rotation_bits: u8,
+    ) -> Result<Option<(u64, BodyChanges)>> {
+        if !(MIN_ROTATION_BITS..=MAX_ROTATION_BITS).contains(&rotation_bits) {
+            return Err(anyhow!("Rotation bits {} are out of range", rotation_bits));
+        }
+
+        if part.index >= part.parts {
+            return Err(anyhow!(
+                "Part {} of a snapshot with {} parts",
+                part.index,
+                part.parts
+            ));
+        }
+
+        if self.latest.is_some_and(|latest| part.tick <= latest)
+            || part
+                .baseline
+                .is_some_and(|tick| self.baseline.is_some_and(|baseline| tick < baseline))
+        {
+            return Ok(None);
+        }
+
+        let empty = HashMap::new();
+        let baseline = match part.baseline {
+            Some(tick) => self
+                .complete
+                .get(&tick)
+                .ok_or_else(|| anyhow!("Snapshot {} is a delta of missing {}", part.tick, tick))?,
+            None => &empty,
+        };
+
+        let mut reader = BitReader::new(bytes);
+        let mut bodies = Vec::with_capacity(count as usize);
+        let mut last_id = 0;
+
+        for _ in 0..count {
+            let id = read_id(&mut reader, &mut last_id)?;
+
+            if reader.read(1)? == 1 {
+                bodies.push((id, None));
+                continue;
+            }
+
+            bodies.push((
+                id,
+                Some(read_body(&mut reader, baseline, id, rotation_bits)?),
+            ));
+        }
+
+        let partial = self
+            .partial
+            .entry(part.tick)
+            .or_insert_with(|| PartialSnapshot {
+                baseline: part.baseline,
+                received: vec![false; part.parts as usize],
+                bodies: Vec::new(),
+            });
+
+        if partial.baseline != part.baseline || partial.received.len() != part.parts as usize {
+            return Err(anyhow!("Parts of snapshot {} don't match", part.tick));
+        }
+
+        if !std::mem::replace(&mut partial.received[part.index as usize], true) {
+            partial.bodies.append(&mut bodies);
+        }
+
+        if !partial.received.iter().all(|received| *received) {
+            while self.partial.len() > PARTIAL_SNAPSHOTS {
+                self.partial.pop_first();
+            }
+            return Ok(None);
+        }
+
+        let Some(partial) = self.partial.remove(&part.tick) else {
+            return Ok(None);
+        };
+
+        let mut snapshot = baseline.clone();
+        for (id, body) in partial.bodies {
+            match body {
+                Some(body) => snapshot.insert(id, body),
+                None => snapshot.remove(&id),
+            };
+        }
+
+        // What changed since the bodies were last moved
+        let previous = self.latest.and_then(|tick| self.complete.get(&tick));
+        let mut changed = snapshot
+            .iter()
+            .filter(|(id, body)| previous.and_then(|previous| previous.get(id)) != Some(body))
+            .map(|(id, body)| (*id, Some(*body)))
+            .collect::<Vec<_>>();
+        if let Some(previous) = previous {
+            changed.extend(
+                previous
+                    .keys()
+                    .filter(|id| !snapshot.contains_key(id))
+                    .map(|id| (*id, None)),
+            );
+        }
+
+        self.complete.insert(part.tick, snapshot);
+        self.latest = Some(part.tick);
+        self.partial.retain(|tick, _| *tick > part.tick);
+
+        // The host never takes deltas from older snapshots than its last baseline
+        if part.baseline.is_some() {
+            self.baseline = self.baseline.max(part.baseline);
+        }
+        let baseline = self.baseline;
+        self.complete
+            .retain(|tick, _| baseline.is_none_or(|baseline| *tick >= baseline));
+        while self.complete.len() > KEPT_SNAPSHOTS + 1 {
+            // Never the baseline, which is kept alongside the newest snapshots
+            let Some(oldest) = self
+                .complete
+                .keys()
+                .copied()
+                .find(|tick| Some(*tick) != baseline)
+            else {
+                break;
+            };
+            self.complete.remove(&oldest);
+        }
+
+        Ok(Some((part.tick, changed)))
+    }
+}
+
+// Ids are sorted, so only the gap to the last one is sent
+fn write_id(writer: &mut BitWriter, id: u32, last_id: &mut u32) {
+    writer.write_varint(id.wrapping_sub(*last_id) as u64);
+    *last_id = id;
+}
+
+fn read_id(reader: &mut BitReader, last_id: &mut u32) -> Result<u32> {
+    let id = last_id.wrapping_add(reader.read_varint()? as u32);
+    *last_id = id;
+    Ok(id)
+}
+
+fn write_body(
+    writer: &mut BitWriter,
+    body: &QuantizedBody,
+    baseline: Option<&QuantizedBody>,
+    rotation_bits: u8,
+) {
+    // Without a baseline the client takes deltas from zero
+    writer.write(baseline.is_some() as u64, 1);
+    let baseline = baseline.copied().unwrap_or_default();
+
+    write_deltas(writer, body.position, baseline.position);
+
+    let rotated = body.rotation != baseline.rotation;
+    writer.write(rotated as u64, 1);
+    if rotated {
+        writer.write(body.rotation.0 as u64, 2);
+        for component in body.rotation.1 {
+            writer.write(component as u64, rotation_bits as u32);
+        }
+    }
+
+    writer.write(body.velocity.is_some() as u64, 1);
+    if let Some((linear, angular)) = body.velocity {
+        let (base_linear, base_angular) = baseline.velocity.unwrap_or_default();
+        write_deltas(writer, linear, base_linear);
+        write_deltas(writer, angular, base_angular);
+    }
+}
+
+fn read_body(
+    reader: &mut BitReader,
+    baselines: &HashMap<u32, QuantizedBody>,
+    id: u32,
+    rotation_bits: u8,
+) -> Result<QuantizedBody> {
+    let baseline = match reader.read(1)? {
+        1 => *baselines
+            .get(&id)
+            .ok_or_else(|| anyhow!("Delta for body {} without a baseline", id))?,
+        _ => QuantizedBody::default(),
+    };
+
+    let position = read_deltas(reader, baseline.position)?;
+
+    let rotation = match reader.read(1)? {
+        1 => {
+            let largest = reader.read(2)? as u8;
+            let mut packed = [0; 3];
+            for component in packed.iter_mut() {
+                *component = reader.read(rotation_bits as u32)? as u16;
+            }
+            (largest, packed)
+        }
+        _ => baseline.rotation,
+    };
+
+    let velocity = match reader.read(1)? {
+        1 => {
+            let (base_linear, base_angular) = baseline.velocity.unwrap_or_default();
+            Some((
+                read_deltas(reader, base_linear)?,
+                read_deltas(reader, base_angular)?,
+            ))
+        }
+        _ => None,
+    };
+
+    Ok(QuantizedBody {
+        position,
+        rotation,
+        velocity,
+    })
+}
+
+// Bits a delta is packed into, picked with two bits per component
+const DELTA_WIDTHS: [u32; 4] = [0, 6, 14, 32];
+
+fn write_deltas(writer: &mut BitWriter, values: [i32; 3], baseline: [i32; 3]) {
+    for (value, base) in values.into_iter().zip(baseline) {
+        let delta = value.wrapping_sub(base);
+        // Zigzag, so small steps either way take few bits
+        let zigzag = ((delta << 1) ^ (delta >> 31)) as u32;
+
+        let class = DELTA_WIDTHS
+            .iter()
+            .position(|width| *width == 32 || zigzag < 1 << width)
+            .unwrap_or(3);
+        writer.write(class as u64, 2);
+        writer.write(zigzag as u64, DELTA_WIDTHS[class]);
+    }
+}
+
+fn read_deltas(reader: &mut BitReader, baseline: [i32; 3]) -> Result<[i32; 3]> {
+    let mut values = baseline;
+
+    for value in values.iter_mut() {
+        let class = reader.read(2)? as usize;
+        let zigzag = reader.read(DELTA_WIDTHS[class])? as u32;
+        let delta = ((zigzag >> 1) as i32) ^ -((zigzag & 1) as i32);
+        *value = value.wrapping_add(delta);
+    }
+
+    Ok(values)
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    scratch: u64,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u64, bits: u32) {
+        if bits == 0 {
+            return;
+        }
+
+        let value = value & (u64::MAX >> (64 - bits));
+        for bit in (0..bits).rev() {
+            self.scratch = (self.scratch << 1) | ((value >> bit) & 1);
+            self.bits += 1;
+
+            if self.bits == 8 {
+                self.bytes.push(self.scratch as u8);
+                self.scratch = 0;
+                self.bits = 0;
+            }
+        }
+    }
+
+    // Seven bits at a time, with a bit saying whether more follow
+    fn write_varint(&mut self, mut value: u64) {
+        loop {
+            let more = value >= 1 << 7;
+            self.write(value & 0x7f, 7);
+            self.write(more as u64, 1);
+            value >>= 7;
+
+            if !more {
+                break;
+            }
+        }
+    }
+
+    /// Bytes written so far
+    fn len(&self) -> usize {
+        self.bytes.len() + (self.bits > 0) as usize
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push((self.scratch << (8 - self.bits)) as u8);
+        }
+
+        self.bytes
+    }
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn read(&mut self, bits: u32) -> Result<u64> {
+        let mut value = 0;
+
+        for _ in 0..bits {
+            let byte = self
+                .bytes
+                .get(self.position / 8)
+                .ok_or_else(|| anyhow!("Snapshot ended early"))?;
+            let bit = (byte >> (7 - self.position % 8)) & 1;
+            value = (value << 1) | bit as u64;
+            self.position += 1;
+        }
+
+        Ok(value)
+    }
+
+    fn read_varint(&mut self) -> Result<u64> {
+        let mut value = 0;
+
+        for shift in (0..64).step_by(7) {
+            value |= self.read(7)? << shift;
+            if self.read(1)? == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(anyhow!("Snapshot has an invalid number"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::InnerSpace;
+
+    use super::*;
+
+    const PEER: PeerId = 1;
+    const ROTATION_BITS: u8 = 12;
+    const BUDGET: usize = 1200;
+
+    // A 32 bit id gap, the removed bit and a body with every delta at 32 bits
+    const MAX_BODY_BYTES: usize = (40 + 1 + 1 + 102 + 1 + 2 + 48 + 1 + 204_usize).div_ceil(8);
+
+    fn body(quantization: &Quantization, x: f32) -> QuantizedBody {
+        quantization.quantize(
+            Vector3::new(x, 2.0, -3.0),
+            Quaternion::new(0.9, 0.1, -0.3, 0.2),
+            Some(NetworkVelocity {
+                linear: Vector3::new(1.0, 0.0, 0.5),
+                angular: Vector3::zero(),
+            }),
+        )
+    }
+
+    type Packet = (SnapshotPart, u32, Vec<u8>);
+
+    // Encodes the snapshot of a tick into the parts sent to the peer
+    fn encode(
+        encoder: &mut DeltaEncoder,
+        tick: u64,
+        bodies: &[(u32, QuantizedBody)],
+        budget: usize,
+    ) -> Vec<Packet> {
+        let (baseline, chunks) = encoder.encode(PEER, tick, bodies, ROTATION_BITS, budget);
+        let parts = chunks.len() as u16;
+
+        (0..parts)
+            .zip(chunks)
+            .map(|(index, (count, bytes))| {
+                let part = SnapshotPart {
+                    tick,
+                    baseline,
+                    index,
+                    parts,
+                };
+                (part, count, bytes)
+            })
+            .collect()
+    }
+
+    // Decodes parts in order, returning what the one completing a snapshot gave
+    fn decode_all(
+        decoder: &mut DeltaDecoder,
+        packets: &[Packet],
+    ) -> Option<(u64, HashMap<u32, Option<QuantizedBody>>)> {
+        let mut complete = None;
+
+        for (part, count, bytes) in packets {
+            let decoded = decoder
+                .decode(*part, bytes, *count, ROTATION_BITS)
+                .expect("Failed to decode part");
+
+            if let Some((tick, bodies)) = decoded {
+                assert!(complete.is_none(), "Snapshot completed twice");
+                complete = Some((tick, bodies.into_iter().collect()));
+            }
+        }
+
+        complete
+    }
+
+    #[test]
+    fn test_delta_round_trip() {
+        let quantization = Quantization::new(&ReplicationSettings::default());
+        let mut encoder = DeltaEncoder::default();
+        let mut decoder = DeltaDecoder::default();
+
+        // Without a baseline every body is sent in full
+        let first = vec![
+            (1, body(&quantization, 0.0)),
+            (5, body(&quantization, 10.0)),
+            (900, body(&quantization, -4.0)),
+        ];
+        let packets = encode(&mut encoder, 0, &first, BUDGET);
+        assert_eq!(packets[0].0.baseline, None);
+        let (tick, decoded) = decode_all(&mut decoder, &packets).unwrap();
+
+        assert_eq!(tick, 0);
+        assert_eq!(decoded.len(), 3);
+        for (id, body) in first.iter() {
+            assert_eq!(decoded[id], Some(*body));
+        }
+        encoder.acknowledge(PEER, 0);
+
+        // Against the baseline only the moved, added and removed bodies are sent
+        let second = vec![
+            (1, body(&quantization, 0.0)),
+            (5, body(&quantization, 10.25)),
+            (1000, body(&quantization, 7.0)),
+        ];
+        let packets = encode(&mut encoder, 1, &second, BUDGET);
+        assert_eq!(packets[0].0.baseline, Some(0));
+        let (_, decoded) = decode_all(&mut decoder, &packets).unwrap();
+
+        assert_eq!(decoded.len(), 3);
+        assert!(!decoded.contains_key(&1));
+        assert_eq!(decoded[&5], Some(second[1].1));
+        assert_eq!(decoded[&900], None);
+        assert_eq!(decoded[&1000], Some(second[2].1));
+        assert!(decoder.contains(1) && decoder.contains(5) && decoder.contains(1000));
+        assert!(!decoder.contains(900));
+
+        // Until the client acknowledges, deltas are still taken from the first
+        let packets = encode(&mut encoder, 2, &second, BUDGET);
+        assert_eq!(packets[0].1, 3);
+        let (_, decoded) = decode_all(&mut decoder, &packets).unwrap();
+        assert!(decoded.is_empty());
+
+        // Nothing changed since the acknowledged snapshot, an empty one is sent
+        encoder.acknowledge(PEER, 2);
+        let packets = encode(&mut encoder, 3, &second, BUDGET);
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].0.baseline, Some(2));
+        assert_eq!(packets[0].1, 0);
+        assert_eq!(
+            decode_all(&mut decoder, &packets).map(|(tick, bodies)| (tick, bodies.len())),
+            Some((3, 0))
+        );
+
+        // Old acknowledgements don't move the baseline back
+        encoder.acknowledge(PEER, 1);
+        let packets = encode(&mut encoder, 4, &second, BUDGET);
+        assert_eq!(packets[0].0.baseline, Some(2));
+
+        // A reset player gets every body in full again
+        encoder.reset_peer(PEER);
+        decoder.clear();
+        let packets = encode(&mut encoder, 5, &second, BUDGET);
+        assert_eq!(packets[0].0.baseline, None);
+        assert_eq!(decode_all(&mut decoder, &packets).unwrap().1.len(), 3);
+    }
+
+    #[test]
+    fn test_lost_parts() {
+        let quantization = Quantization::new(&ReplicationSettings::default());
+        let mut encoder = DeltaEncoder::default();
+        let mut decoder = DeltaDecoder::default();
+        let budget = 64;
+
+        let bodies = |offset: f32| {
+            (0..50)
+                .map(|id| (id, body(&quantization, id as f32 + offset)))
+                .collect::<Vec<_>>()
+        };
+
+        // Parts arriving in any order complete the snapshot with the last one
+        let mut packets = encode(&mut encoder, 0, &bodies(0.0), budget);
+        assert!(packets.len() > 2);
+        packets.reverse();
+        assert_eq!(decode_all(&mut decoder, &packets).unwrap().1.len(), 50);
+        encoder.acknowledge(PEER, 0);
+
+        // A snapshot missing a part is never taken or acknowledged
+        let mut lost = encode(&mut encoder, 1, &bodies(1.0), budget);
+        let late = lost.remove(1);
+        assert!(decode_all(&mut decoder, &lost).is_none());
+        assert!(decoder.contains(10));
+
+        // The next snapshot is still a delta of the acknowledged one
+        let packets = encode(&mut encoder, 2, &bodies(2.0), budget);
+        assert_eq!(packets[0].0.baseline, Some(0));
+        let (tick, decoded) = decode_all(&mut decoder, &packets).unwrap();
+        assert_eq!(tick, 2);
+        for (id, body) in bodies(2.0) {
+            assert_eq!(decoded[&id], Some(body));
+        }
+
+        // The lost part showing up late is out of date
+        assert!(decode_all(&mut decoder, &[late]).is_none());
+    }
+
+    #[test]
+    fn test_rotation_error_bound() {
+        // Deterministic spread of rotations, including the identity and
+        // rotations where a component is exactly the largest
+        let mut rotations = vec![
+            Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            Quaternion::new(0.0, 1.0, 0.0, 0.0),
+            Quaternion::new(0.5, 0.5, 0.5, 0.5),
+            Quaternion::new(-0.5, 0.5, -0.5, 0.5),
+        ];
+        let mut state: u32 = 0x1234_5678;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as f32 / u32::MAX as f32 * 2.0 - 1.0
+        };
+        for _ in 0..500 {
+            rotations.push(Quaternion::new(next(), next(), next(), next()).normalize());
+        }
+
+        for rotation_bits in 4..=16 {
+            let quantization = Quantization::new(&ReplicationSettings {
+                rotation_bits,
+                ..Default::default()
+            });
+
+            // Each sent component is off by at most half a step of the
+            // [-1/sqrt(2), 1/sqrt(2)] range, the rebuilt largest one by at
+            // most sqrt(2) times the sum of those, which bounds the angle
+            let max = ((1 << rotation_bits) - 1) as f32;
+            let component_error = std::f32::consts::FRAC_1_SQRT_2 / max;
+            let bound = 10.0 * component_error;
+
+            for rotation in rotations.iter() {
+                let body = quantization.quantize(Vector3::zero(), *rotation, None);
+                let (_, restored, _) = quantization.restore(&body);
+
+                // From the chord between the quaternions, acos loses the
+                // small angles of the higher bit counts to rounding
+                let restored = restored.normalize();
+                let restored = if rotation.dot(restored) < 0.0 {
+                    -restored
+                } else {
+                    restored
+                };
+                let angle = 4.0 * ((rotation - restored).magnitude() * 0.5).asin();
+                assert!(
+                    angle <= bound,
+                    "{} bits: {:?} came back {} radians off, more than {}",
+                    rotation_bits,
+                    rotation,
+                    angle,
+                    bound
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_zigzag_width_edges() {
+        // The largest deltas either way that still fit each width class
+        let cases: [(i32, u32); 11] = [
+            (0, 0),
+            (-1, 6),
+            (1, 6),
+            (31, 6),
+            (-32, 6),
+            (32, 14),
+            (-33, 14),
+            (8191, 14),
+            (-8192, 14),
+            (8192, 32),
+            (-8193, 32),
+        ];
+
+        for baseline in [0, 1_000_000, -77] {
+            for (delta, width) in cases {
+                let value = baseline + delta;
+                let mut writer = BitWriter::default();
+                write_deltas(&mut writer, [value; 3], [baseline; 3]);
+
+                let written = writer.bytes.len() as u32 * 8 + writer.bits;
+                assert_eq!(written, 3 * (2 + width), "delta {}", delta);
+
+                let bytes = writer.finish();
+                let read = read_deltas(&mut BitReader::new(&bytes), [baseline; 3])
+                    .expect("Failed to read deltas");
+                assert_eq!(read, [value; 3]);
+            }
+        }
+
+        // Deltas that overflow wrap around and still come back
+        let mut writer = BitWriter::default();
+        write_deltas(
+            &mut writer,
+            [i32::MAX, i32::MIN, 0],
+            [i32::MIN, i32::MAX, 0],
+        );
+        let bytes = writer.finish();
+        let read = read_deltas(&mut BitReader::new(&bytes), [i32::MIN, i32::MAX, 0])
+            .expect("Failed to read deltas");
+        assert_eq!(read, [i32::MAX, i32::MIN, 0]);
+    }
+
+    #[test]
+    fn test_chunks_split_at_budget() {
+        let quantization = Quantization::new(&ReplicationSettings::default());
+        let mut encoder = DeltaEncoder::default();
+        let mut decoder = DeltaDecoder::default();
+        let budget = 64;
+
+        let bodies = (0..200)
+            .map(|id| (id * 3, body(&quantization, id as f32 * 1.5)))
+            .collect::<Vec<_>>();
+        let packets = encode(&mut encoder, 0, &bodies, budget);
+
+        assert!(packets.len() > 1);
+        assert_eq!(packets.iter().map(|(_, count, _)| count).sum::<u32>(), 200);
+
+        // A chunk is closed by the first body that reaches the budget, so
+        // only the last one is smaller and none runs over by a whole body
+        for (index, (_, _, bytes)) in packets.iter().enumerate() {
+            assert!(index == packets.len() - 1 || bytes.len() >= budget);
+            assert!(bytes.len() < budget + MAX_BODY_BYTES);
+        }
+
+        let (_, decoded) = decode_all(&mut decoder, &packets).unwrap();
+        assert_eq!(decoded.len(), 200);
+        for (id, body) in bodies.iter() {
+            assert_eq!(decoded[id], Some(*body));
+        }
+        encoder.acknowledge(PEER, 0);
+
+        // Removing every body splits the same way
+        let packets = encode(&mut encoder, 1, &[], 4);
+        assert!(packets.len() > 1);
+        let (_, decoded) = decode_all(&mut decoder, &packets).unwrap();
+        assert_eq!(decoded.len(), 200);
+        assert!(decoded.values().all(Option::is_none));
+    }
+
+    #[test]
+    fn test_invalid_quantization_is_rejected() {
+        let quantization = Quantization::new(&ReplicationSettings::default());
+        assert!(quantization.validate().is_ok());
+
+        // Settings are clamped on the host
+        let clamped = Quantization::new(&ReplicationSettings {
+            rotation_bits: 64,
+            ..Default::default()
+        });
+        assert!(clamped.validate().is_ok());
+        assert_eq!(clamped.rotation_bits(), MAX_ROTATION_BITS);
+
+        // Anything a client receives is checked
+        for invalid in [
+            Quantization {
+                rotation_bits: 40,
+                ..quantization
+            },
+            Quantization {
+                rotation_bits: 0,
+                ..quantization
+            },
+            Quantization {
+                position_precision: 0.0,
+                ..quantization
+            },
+            Quantization {
+                velocity_precision: f32::NAN,
+                ..quantization
+            },
+        ] {
+            assert!(invalid.validate().is_err());
+        }
+
+        let mut encoder = DeltaEncoder::default();
+        let mut decoder = DeltaDecoder::default();
+        let packets = encode(&mut encoder, 0, &[(1, body(&quantization, 0.0))], BUDGET);
+        let (part, count, bytes) = &packets[0];
+        assert!(decoder.decode(*part, bytes, *count, 40).is_err());
+
+        // Parts past the end of a snapshot and deltas of snapshots the client
+        // doesn't have can't be taken either
+        let past_end = SnapshotPart { index: 1, ..*part };
+        assert!(
+            decoder
+                .decode(past_end, bytes, *count, ROTATION_BITS)
+                .is_err()
+        );
+        let unknown = SnapshotPart {
+            baseline: Some(7),
+            ..*part
+        };
+        assert!(
+            decoder
+                .decode(unknown, bytes, *count, ROTATION_BITS)
+                .is_err()
+        );
+        assert!(!decoder.contains(1));
+    }
+}
@@ -0,0 +1,1633 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, anyhow};
+use compound::Compound;
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    channel::{ChannelId, ReliableChannel},
+    chat::{ChatBox, ChatMessage},
+    transport::{MAX_PACKET_SIZE, Transport, bind_udp},
+};
+
+/// Port games are hosted on by default
+pub const DEFAULT_GAME_PORT: u16 = 27015;
+
+/// Port hosts listen for lobby searches on by default
+pub const DEFAULT_DISCOVERY_PORT: u16 = 27016;
+
+/// Id of the host in every session, clients are numbered from 1
+pub const HOST_PEER: PeerId = 0;
+
+// Every packet starts with this, anything else on the port is ignored
+const PROTOCOL_MAGIC: [u8; 4] = *b"ISO1";
+
+// How often a client asks the host to let it in until it answers
+const CONNECT_RETRY_INTERVAL: Duration = Duration::from_millis(250);
+
+// Bytes of a reliable packet that aren't the message, with some to spare
+const RELIABLE_HEADER_SIZE: usize = 32;
+
+// Packets sent to open the NAT of the host towards a client
+const HOLE_PUNCH_PACKETS: usize = 3;
+
+// Most messages waiting on a channel for gameplay to drain them, unreliable
+// messages past this are dropped and a peer whose reliable ones overflow it is
+const MAX_RECEIVED_MESSAGES: usize = 4096;
+
+/// Id of a peer within a session
+pub type PeerId = u32;
+
+/// How sessions are hosted, joined and kept alive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkSettings {
+    /// Only peers and lobbies of the same game can connect to each other
+    pub game_id: String,
+    /// Only peers with the same version can connect to each other
+    pub version: u32,
+    /// Port the game is hosted on
+    pub port: u16,
+    /// Port hosts answer lobby searches on
+    pub discovery_port: u16,
+    /// Most players in a session, including the host
+    pub max_players: usize,
+    /// Time between keepalives sent to peers that nothing else was sent to
+    pub keepalive_interval: Duration,
+    /// Time without hearing from a peer before it is dropped
+    pub timeout: Duration,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            game_id: String::from("isotope"),
+            version: 1,
+            port: DEFAULT_GAME_PORT,
+            discovery_port: DEFAULT_DISCOVERY_PORT,
+            max_players: 8,
+            keepalive_interval: Duration::from_secs(1),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// What part the local game plays in a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkRole {
+    Offline,
+    Host,
+    /// Waiting for the host to accept
+    Connecting,
+    Client,
+}
+
+/// A session found on the local network by `NetworkState::search_lobbies`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lobby {
+    pub name: String,
+    /// Address to `join`
+    pub address: SocketAddr,
+    pub players: usize,
+    pub max_players: usize,
+
+    last_seen: Instant,
+}
+
+/// Another player in the session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Peer {
+    pub id: PeerId,
+    pub name: String,
+
+    // Only peers the local game talks to directly have a connection
+    connection: Option<Connection>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Connection {
+    address: SocketAddr,
+    last_received: Instant,
+    last_sent: Instant,
+}
+
+impl Connection {
+    fn new(address: SocketAddr, now: Instant) -> Self {
+        Self {
+            address,
+            last_received: now,
+            last_sent: now,
+        }
+    }
+}
+
+/// Why a peer left or the local game left a session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// Left on purpose
+    Left,
+    /// Nothing was heard for longer than the timeout
+    TimedOut,
+    /// The host refused the connection
+    Rejected(String),
+    /// Too many reliable messages piled up, the peer or the game couldn't keep up
+    Overflowed,
+}
+
+/// What gameplay is told about the session.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetworkEvent {
+    /// A lobby answered a search, or changed since it last did
+    LobbyFound(Lobby),
+    /// The host accepted the local game into the session
+    Connected {
+        peer: PeerId,
+    },
+    /// The local game is no longer in a session
+    Disconnected {
+        reason: DisconnectReason,
+    },
+    PeerJoined {
+        peer: PeerId,
+        name: String,
+    },
+    PeerLeft {
+        peer: PeerId,
+        reason: DisconnectReason,
+    },
+    /// Data sent by a peer with `send` or `broadcast`
+    Received {
+        peer: PeerId,
+        data: Vec<u8>,
+    },
+    /// A chat message, including the ones sent by the local player
+    Chat(ChatMessage),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Packet {
+    Discover {
+        game_id: String,
+    },
+    LobbyInfo {
+        game_id: String,
+        name: String,
+        port: u16,
+        players: u32,
+        max_players: u32,
+        version: u32,
+    },
+    Connect {
+        game_id: String,
+        version: u32,
+        name: String,
+    },
+    Accept {
+        peer: PeerId,
+        peers: Vec<(PeerId, String)>,
+    },
+    Reject {
+        reason: String,
+    },
+    PeerJoined {
+        peer: PeerId,
+        name: String,
+    },
+    PeerLeft {
+        peer: PeerId,
+        timed_out: bool,
+    },
+    Keepalive,
+    Disconnect,
+    Data(Vec<u8>),
+    Reliable {
+        channel: ChannelId,
+        sequence: u32,
+        data: Vec<u8>,
+    },
+    Ack {
+        channel: ChannelId,
+        sequence: u32,
+    },
+    Unreliable {
+        channel: ChannelId,
+        data: Vec<u8>,
+    },
+}
+
+impl Packet {
+    fn encode(&self) -> Result<Vec<u8>> {
+        let mut bytes = PROTOCOL_MAGIC.to_vec();
+        bincode::serialize_into(&mut bytes, self)?;
+
+        if bytes.len() > MAX_PACKET_SIZE {
+            return Err(anyhow!(
+                "Packet of {} bytes is larger than {}",
+                bytes.len(),
+                MAX_PACKET_SIZE
+            ));
+        }
+
+        Ok(bytes)
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let payload = bytes.strip_prefix(&PROTOCOL_MAGIC)?;
+        bincode::deserialize(payload).ok()
+    }
+}
+
+/// Session management of the game, kept as a resource of the compound.
+///
+/// A game hosts a session with `host` or joins one with `join`, after finding
+/// it on the local network with `search_lobbies` or getting its address some
+/// other way. Joining checks that both sides run the same game and version,
+/// after which every peer is kept alive with keepalives and dropped when it
+/// isn't heard from within the timeout. Sessions are a star around the host:
+/// clients only talk to the host, which tells them who else is in the session.
+///
+/// Players behind a NAT that can't be traversed can connect through a relay
+/// with `host_through` and `join_through`. For the rest, `punch_hole` opens the
+/// NAT of the host towards a client that is about to join.
+///
+/// The engine updates the session every tick, what happens is queued as
+/// `NetworkEvent`s for gameplay to drain.
+///
+/// # Example
+/// ```ignore
+/// // In IsotopeState::init
+/// let mut network = NetworkState::new(NetworkSettings {
+///     game_id: String::from("my_game"),
+///     version: 3,
+///     ..Default::default()
+/// });
+/// network.search_lobbies()?;
+/// ecs.insert_resource(network);
+///
+/// // In IsotopeState::update
+/// ecs.resource_mut::<NetworkState, _>(|network| {
+///     for event in network.drain_events() {
+///         match event {
+///             NetworkEvent::LobbyFound(lobby) => {
+///                 network.join(lobby.address, "Player").ok();
+///             }
+///             NetworkEvent::Received { peer, data } => { /* Gameplay */ }
+///             _ => {}
+///         }
+///     }
+/// });
+/// ```
+pub struct NetworkState {
+    settings: NetworkSettings,
+    role: NetworkRole,
+    transport: Option<Arc<dyn Transport>>,
+    // Hosts answer lobby searches on this
+    discovery: Option<UdpSocket>,
+    // Lobby searches are answered on this
+    search: Option<UdpSocket>,
+    lobby_name: String,
+    player_name: String,
+    local_peer: Option<PeerId>,
+    peers: Vec<Peer>,
+    next_peer: PeerId,
+    lobbies: Vec<Lobby>,
+    // Where a client connects to and when it started to
+    host_address: Option<SocketAddr>,
+    connect_started: Instant,
+    last_connect_attempt: Option<Instant>,
+    // Reliable channels with every peer and the messages received on every channel
+    channels: HashMap<(PeerId, ChannelId), ReliableChannel>,
+    received: HashMap<ChannelId, VecDeque<(PeerId, Vec<u8>)>>,
+    // Chat messages since the last update, for chat boxes
+    pub(super) chat: Vec<ChatMessage>,
+    pub(super) events: Vec<NetworkEvent>,
+}
+
+impl NetworkState {
+    pub fn new(settings: NetworkSettings) -> Self {
+        Self {
+            settings,
+            role: NetworkRole::Offline,
+            transport: None,
+            discovery: None,
+            search: None,
+            lobby_name: String::new(),
+            player_name: String::new(),
+            local_peer: None,
+            peers: Vec::new(),
+            next_peer: HOST_PEER + 1,
+            lobbies: Vec::new(),
+            host_address: None,
+            connect_started: Instant::now(),
+            last_connect_attempt: None,
+            channels: HashMap::new(),
+            received: HashMap::new(),
+            chat: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn settings(&self) -> &NetworkSettings {
+        &self.settings
+    }
+
+    pub fn role(&self) -> NetworkRole {
+        self.role
+    }
+
+    /// Returns the id of the local game in the session
+    pub fn local_peer(&self) -> Option<PeerId> {
+        self.local_peer
+    }
+
+    /// Returns the other players in the session
+    pub fn peers(&self) -> &[Peer] {
+        &self.peers
+    }
+
+    pub fn peer(&self, id: PeerId) -> Option<&Peer> {
+        self.peers.iter().find(|peer| peer.id == id)
+    }
+
+    /// Returns the name of the local player in the session
+    pub fn player_name(&self) -> &str {
+        &self.player_name
+    }
+
+    /// Returns the lobbies found by `search_lobbies` that are still answering
+    pub fn lobbies(&self) -> &[Lobby] {
+        &self.lobbies
+    }
+
+    /// Hosts a session on the game port and answers lobby searches on the
+    /// local network.
+    ///
+    /// # Arguments
+    /// * `lobby_name` - Name shown in the lobby list of other players
+    /// * `player_name` - Name of the host in the session
+    ///
+    /// # Returns
+    /// An error if the game is already in a session or the port can't be bound
+    pub fn host(
+        &mut self,
+        lobby_name: impl Into<String>,
+        player_name: impl Into<String>,
+    ) -> Result<()> {
+        let transport = Arc::new(bind_udp(self.settings.port)?);
+        self.host_through(transport, lobby_name, player_name)?;
+
+        // The session still works without discovery, it just can't be found
+        match bind_udp(self.settings.discovery_port) {
+            Ok(discovery) => self.discovery = Some(discovery),
+            Err(err) => warn!("Failed to listen for lobby searches: {}", err),
+        }
+
+        Ok(())
+    }
+
+    /// Hosts a session through a relay or any other transport.
+    ///
+    /// See [`NetworkState::host`]
+    pub fn host_through(
+        &mut self,
+        transport: Arc<dyn Transport>,
+        lobby_name: impl Into<String>,
+        player_name: impl Into<String>,
+    ) -> Result<()> {
+        self.ensure_offline()?;
+
+        self.transport = Some(transport);
+        self.role = NetworkRole::Host;
+        self.lobby_name = lobby_name.into();
+        self.player_name = player_name.into();
+        self.local_peer = Some(HOST_PEER);
+        self.next_peer = HOST_PEER + 1;
+
+        info!("Hosting {}", self.lobby_name);
+        Ok(())
+    }
+
+    /// Joins the session hosted at an address.
+    ///
+    /// The game is connecting until the host accepts it, which is reported
+    /// with `NetworkEvent::Connected`, or rejects it or doesn't answer, which
+    /// is reported with `NetworkEvent::Disconnected`.
+    ///
+    /// # Arguments
+    /// * `address` - Address of the host, such as the address of a `Lobby`
+    /// * `player_name` - Name of the local player in the session
+    ///
+    /// # Returns
+    /// An error if the game is already in a session or no port can be bound
+    pub fn join(&mut self, address: SocketAddr, player_name: impl Into<String>) -> Result<()> {
+        let transport = Arc::new(bind_udp(0)?);
+        self.join_through(transport, address, player_name)
+    }
+
+    /// Joins a session through a relay or any other transport.
+    ///
+    /// See [`NetworkState::join`]
+    pub fn join_through(
+        &mut self,
+        transport: Arc<dyn Transport>,
+        address: SocketAddr,
+        player_name: impl Into<String>,
+    ) -> Result<()> {
+        self.ensure_offline()?;
+
+        self.transport = Some(transport);
+        self.role = NetworkRole::Connecting;
+        self.player_name = player_name.into();
+        self.host_address = Some(address);
+        self.connect_started = Instant::now();
+        self.last_connect_attempt = None;
+
+        info!("Joining session at {}", address);
+        Ok(())
+    }
+
+    /// Leaves the session, telling the other peers
+    pub fn disconnect(&mut self) {
+        if self.role == NetworkRole::Offline {
+            return;
+        }
+
+        for address in self.connected_addresses() {
+            self.send_packet(&Packet::Disconnect, address);
+        }
+
+        self.close(DisconnectReason::Left);
+    }
+
+    /// Asks every host on the local network for its lobby.
+    ///
+    /// Lobbies that answer are reported with `NetworkEvent::LobbyFound` and
+    /// listed by `lobbies` until they stop answering for longer than the timeout.
+    pub fn search_lobbies(&mut self) -> Result<()> {
+        if self.search.is_none() {
+            let search = bind_udp(0)?;
+            search.set_broadcast(true)?;
+            self.search = Some(search);
+        }
+
+        let packet = Packet::Discover {
+            game_id: self.settings.game_id.clone(),
+        }
+        .encode()?;
+
+        if let Some(search) = self.search.as_ref() {
+            search.send_to(
+                &packet,
+                SocketAddr::from((Ipv4Addr::BROADCAST, self.settings.discovery_port)),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Stops listening for answers to lobby searches and forgets found lobbies
+    pub fn stop_searching(&mut self) {
+        self.search = None;
+        self.lobbies.clear();
+    }
+
+    /// Sends a few packets to a client that is about to join, so the NAT of
+    /// the host lets the packets of the client in.
+    ///
+    /// The address of the client has to be learned some other way, such as
+    /// through a matchmaking server both sides are connected to.
+    pub fn punch_hole(&mut self, address: SocketAddr) -> Result<()> {
+        if self.role != NetworkRole::Host {
+            return Err(anyhow!("Only the host can open its NAT to clients"));
+        }
+
+        for _ in 0..HOLE_PUNCH_PACKETS {
+            self.send_packet(&Packet::Keepalive, address);
+        }
+
+        Ok(())
+    }
+
+    /// Sends data to a peer, clients can only send to the host.
+    ///
+    /// # Returns
+    /// An error if the peer isn't connected or the data doesn't fit in a packet
+    pub fn send(&mut self, peer: PeerId, data: Vec<u8>) -> Result<()> {
+        let address = self
+            .address_of(peer)
+            .ok_or_else(|| anyhow!("Peer {} is not connected", peer))?;
+
+        let packet = Packet::Data(data).encode()?;
+        self.send_bytes(&packet, address);
+
+        Ok(())
+    }
+
+    /// Sends data to every connected peer
+    pub fn broadcast(&mut self, data: Vec<u8>) -> Result<()> {
+        let packet = Packet::Data(data).encode()?;
+        for address in self.connected_addresses() {
+            self.send_bytes(&packet, address);
+        }
+
+        Ok(())
+    }
+
+    /// Sends a message to a peer on a reliable ordered channel.
+    ///
+    /// Messages of a channel arrive exactly once and in the order they were
+    /// sent, resent until the peer acknowledges them. Games define their own
+    /// channels by picking ids and taking what arrives with `drain_channel`,
+    /// the engine keeps the highest ids such as `CHAT_CHANNEL`. Clients can only
+    /// send to the host.
+    ///
+    /// # Arguments
+    /// * `peer` - The peer to send to
+    /// * `channel` - The channel to send on
+    /// * `data` - The message
+    ///
+    /// A peer that doesn't acknowledge the messages sent to it is dropped once
+    /// too many are waiting, the session is left if the peer is the host.
+    ///
+    /// # Returns
+    /// An error if the peer isn't connected, the message doesn't fit in a
+    /// packet or the peer was dropped
+    pub fn send_reliable(&mut self, peer: PeerId, channel: ChannelId, data: Vec<u8>) -> Result<()> {
+        if self.address_of(peer).is_none() {
+            return Err(anyhow!("Peer {} is not connected", peer));
+        }
+
+        if data.len() > MAX_PACKET_SIZE - RELIABLE_HEADER_SIZE {
+            return Err(anyhow!(
+                "Message of {} bytes doesn't fit in a packet",
+                data.len()
+            ));
+        }
+
+        let queued = self
+            .channels
+            .entry((peer, channel))
+            .or_default()
+            .queue(data);
+
+        if let Err(err) = queued {
+            self.overflow(peer);
+            return Err(anyhow!("Dropped peer {}: {}", peer, err));
+        }
+
+        Ok(())
+    }
+
+    /// Sends a message to every connected peer on a reliable ordered channel
+    pub fn broadcast_reliable(&mut self, channel: ChannelId, data: Vec<u8>) -> Result<()> {
+        for peer in self.connected_peers() {
+            self.send_reliable(peer, channel, data.clone())?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends a message to a peer on an unreliable channel.
+    ///
+    /// Messages are sent once, so they may be lost or arrive out of order, for
+    /// data that is out of date by the time it would be resent such as voice.
+    /// They are taken with `drain_channel` like reliable messages, so a
+    /// channel should only be used one way. Clients can only send to the host.
+    ///
+    /// # Returns
+    /// An error if the peer isn't connected or the message doesn't fit in a packet
+    pub fn send_unreliable(
+        &mut self,
+        peer: PeerId,
+        channel: ChannelId,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        let address = self
+            .address_of(peer)
+            .ok_or_else(|| anyhow!("Peer {} is not connected", peer))?;
+
+        let packet = Packet::Unreliable { channel, data }.encode()?;
+        self.send_bytes(&packet, address);
+
+        Ok(())
+    }
+
+    /// Sends a message to every connected peer on an unreliable channel
+    pub fn broadcast_unreliable(&mut self, channel: ChannelId, data: Vec<u8>) -> Result<()> {
+        let packet = Packet::Unreliable { channel, data }.encode()?;
+        for address in self.connected_addresses() {
+            self.send_bytes(&packet, address);
+        }
+
+        Ok(())
+    }
+
+    /// Takes the messages received on a channel, reliable ones in order
+    pub fn drain_channel(&mut self, channel: ChannelId) -> Vec<(PeerId, Vec<u8>)> {
+        self.received
+            .remove(&channel)
+            .map(Vec::from)
+            .unwrap_or_default()
+    }
+
+    /// Takes the events queued since the last time they were drained
+    pub fn drain_events(&mut self) -> Vec<NetworkEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Receives packets, keeps peers alive and drops the ones that timed out
+    pub(crate) fn update(&mut self) {
+        let now = Instant::now();
+
+        self.receive_discovery();
+        self.receive_lobbies(now);
+        self.receive(now);
+
+        match self.role {
+            NetworkRole::Connecting => self.retry_connect(now),
+            NetworkRole::Host | NetworkRole::Client => {
+                self.send_channels(now);
+                self.keep_alive(now);
+            }
+            NetworkRole::Offline => {}
+        }
+
+        let timeout = self.settings.timeout;
+        self.lobbies
+            .retain(|lobby| now.duration_since(lobby.last_seen) < timeout);
+    }
+
+    fn ensure_offline(&self) -> Result<()> {
+        if self.role != NetworkRole::Offline {
+            return Err(anyhow!("Already in a session"));
+        }
+
+        Ok(())
+    }
+
+    fn address_of(&self, peer: PeerId) -> Option<SocketAddr> {
+        self.peers
+            .iter()
+            .find(|other| other.id == peer)
+            .and_then(|other| other.connection)
+            .map(|connection| connection.address)
+    }
+
+    /// Returns the peers the local game talks to directly
+    pub(super) fn connected_peers(&self) -> Vec<PeerId> {
+        self.peers
+            .iter()
+            .filter(|peer| peer.connection.is_some())
+            .map(|peer| peer.id)
+            .collect()
+    }
+
+    fn connected_addresses(&self) -> Vec<SocketAddr> {
+        self.peers
+            .iter()
+            .filter_map(|peer| peer.connection.map(|connection| connection.address))
+            .collect()
+    }
+
+    fn send_packet(&mut self, packet: &Packet, address: SocketAddr) {
+        match packet.encode() {
+            Ok(bytes) => self.send_bytes(&bytes, address),
+            Err(err) => error!("Failed to encode packet: {}", err),
+        }
+    }
+
+    fn send_bytes(&mut self, bytes: &[u8], address: SocketAddr) {
+        let Some(transport) = self.transport.as_ref() else {
+            return;
+        };
+
+        if let Err(err) = transport.send_to(bytes, address) {
+            debug!("Failed to send packet to {}: {}", address, err);
+        }
+
+        if let Some(connection) = self
+            .peers
+            .iter_mut()
+            .filter_map(|peer| peer.connection.as_mut())
+            .find(|connection| connection.address == address)
+        {
+            connection.last_sent = Instant::now();
+        }
+    }
+
+    fn close(&mut self, reason: DisconnectReason) {
+        info!("Left session: {:?}", reason);
+
+        self.role = NetworkRole::Offline;
+        self.transport = None;
+        self.discovery = None;
+        self.local_peer = None;
+        self.peers.clear();
+        self.channels.clear();
+        self.host_address = None;
+        self.events.push(NetworkEvent::Disconnected { reason });
+    }
+
+    fn receive_discovery(&mut self) {
+        let Some(discovery) = self.discovery.as_ref() else {
+            return;
+        };
+
+        let mut buffer = [0; MAX_PACKET_SIZE];
+        while let Ok(Some((length, address))) = Transport::receive(discovery, &mut buffer) {
+            let Some(Packet::Discover { game_id }) = Packet::decode(&buffer[..length]) else {
+                continue;
+            };
+
+            if game_id != self.settings.game_id {
+                continue;
+            }
+
+            let info = Packet::LobbyInfo {
+                game_id,
+                name: self.lobby_name.clone(),
+                port: self.settings.port,
+                players: self.peers.len() as u32 + 1,
+                max_players: self.settings.max_players as u32,
+                version: self.settings.version,
+            };
+
+            match info.encode() {
+                Ok(bytes) => {
+                    if let Err(err) = Transport::send_to(discovery, &bytes, address) {
+                        debug!("Failed to answer lobby search of {}: {}", address, err);
+                    }
+                }
+                Err(err) => error!("Failed to encode lobby: {}", err),
+            }
+        }
+    }
+
+    fn receive_lobbies(&mut self, now: Instant) {
+        let Some(search) = self.search.as_ref() else {
+            return;
+        };
+
+        let mut buffer = [0; MAX_PACKET_SIZE];
+        while let Ok(Some((length, address))) = Transport::receive(search, &mut buffer) {
+            let Some(Packet::LobbyInfo {
+                game_id,
+                name,
+                port,
+                players,
+                max_players,
+                version,
+            }) = Packet::decode(&buffer[..length])
+            else {
+                continue;
+            };
+
+            // Lobbies of other versions can't be joined anyway
+            if game_id != self.settings.game_id || version != self.settings.version {
+                continue;
+            }
+
+            let lobby = Lobby {
+                name,
+                address: SocketAddr::new(address.ip(), port),
+                players: players as usize,
+                max_players: max_players as usize,
+                last_seen: now,
+            };
+
+            match self
+                .lobbies
+                .iter_mut()
+                .find(|found| found.address == lobby.address)
+            {
+                Some(found) => {
+                    let changed = found.name != lobby.name
+                        || found.players != lobby.players
+                        || found.max_players != lobby.max_players;
+                    *found = lobby.clone();
+
+                    if changed {
+                        self.events.push(NetworkEvent::LobbyFound(lobby));
+                    }
+                }
+                None => {
+                    debug!("Found lobby {} at {}", lobby.name, lobby.address);
+                    self.lobbies.push(lobby.clone());
+                    self.events.push(NetworkEvent::LobbyFound(lobby));
+                }
+            }
+        }
+    }
+
+    fn receive(&mut self, now: Instant) {
+        let mut buffer = [0; MAX_PACKET_SIZE];
+
+        loop {
+            let Some(transport) = self.transport.as_ref() else {
+                return;
+            };
+
+            let (length, address) = match transport.receive(&mut buffer) {
+                Ok(Some(received)) => received,
+                Ok(None) => return,
+                Err(err) => {
+                    debug!("Failed to receive packet: {}", err);
+                    return;
+                }
+            };
+
+            let Some(packet) = Packet::decode(&buffer[..length]) else {
+                continue;
+            };
+
+            if let Some(connection) = self
+                .peers
+                .iter_mut()
+                .filter_map(|peer| peer.connection.as_mut())
+                .find(|connection| connection.address == address)
+            {
+                connection.last_received = now;
+            }
+
+            match self.role {
+                NetworkRole::Host => self.receive_as_host(packet, address, now),
+                NetworkRole::Connecting | NetworkRole::Client => {
+                    // Clients only listen to their host
+                    if self.host_address == Some(address) {
+                        self.receive_as_client(packet, address, now);
+                    }
+                }
+                NetworkRole::Offline => return,
+            }
+        }
+    }
+
+    fn receive_as_host(&mut self, packet: Packet, address: SocketAddr, now: Instant) {
+        let sender = self
+            .peers
+            .iter()
+            .find(|peer| {
+                peer.connection
+                    .is_some_and(|connection| connection.address == address)
+            })
+            .map(|peer| peer.id);
+
+        match (packet, sender) {
+            (
+                Packet::Connect {
+                    game_id,
+                    version,
+                    name,
+                },
+                sender,
+            ) => self.accept(game_id, version, name, address, sender, now),
+            (Packet::Disconnect, Some(peer)) => self.drop_peer(peer, DisconnectReason::Left),
+            (Packet::Data(data), Some(peer)) => {
+                self.events.push(NetworkEvent::Received { peer, data });
+            }
+            (
+                Packet::Reliable {
+                    channel,
+                    sequence,
+                    data,
+                },
+                Some(peer),
+            ) => self.receive_reliable(peer, channel, sequence, data, address),
+            (Packet::Ack { channel, sequence }, Some(peer)) => {
+                if let Some(reliable) = self.channels.get_mut(&(peer, channel)) {
+                    reliable.acknowledge(sequence);
+                }
+            }
+            (Packet::Unreliable { channel, data }, Some(peer)) => {
+                self.receive_unreliable(peer, channel, data);
+            }
+            _ => {}
+        }
+    }
+
+    fn accept(
+        &mut self,
+        game_id: String,
+        version: u32,
+        name: String,
+        address: SocketAddr,
+        sender: Option<PeerId>,
+        now: Instant,
+    ) {
+        let rejection = if game_id != self.settings.game_id {
+            Some(String::from("Different game"))
+        } else if version != self.settings.version {
+            Some(format!(
+                "Version {} doesn't match host version {}",
+                version, self.settings.version
+            ))
+        } else if sender.is_none() && self.peers.len() + 1 >= self.settings.max_players {
+            Some(String::from("Lobby is full"))
+        } else {
+            None
+        };
+
+        if let Some(reason) = rejection {
+            info!("Rejected {} from {}: {}", name, address, reason);
+            self.send_packet(&Packet::Reject { reason }, address);
+            return;
+        }
+
+        // The accept was lost, so the client is asking again
+        let peer = match sender {
+            Some(peer) => peer,
+            None => {
+                let peer = self.next_peer;
+                self.next_peer += 1;
+
+                for other in self.connected_addresses() {
+                    self.send_packet(
+                        &Packet::PeerJoined {
+                            peer,
+                            name: name.clone(),
+                        },
+                        other,
+                    );
+                }
+
+                info!("{} joined as peer {}", name, peer);
+                self.peers.push(Peer {
+                    id: peer,
+                    name: name.clone(),
+                    connection: Some(Connection::new(address, now)),
+                });
+                self.events.push(NetworkEvent::PeerJoined { peer, name });
+
+                peer
+            }
+        };
+
+        let peers = std::iter::once((HOST_PEER, self.player_name.clone()))
+            .chain(
+                self.peers
+                    .iter()
+                    .filter(|other| other.id != peer)
+                    .map(|other| (other.id, other.name.clone())),
+            )
+            .collect();
+        self.send_packet(&Packet::Accept { peer, peers }, address);
+    }
+
+    fn receive_as_client(&mut self, packet: Packet, address: SocketAddr, now: Instant) {
+        match packet {
+            Packet::Accept { peer, peers } if self.role == NetworkRole::Connecting => {
+                self.role = NetworkRole::Client;
+                self.local_peer = Some(peer);
+                self.peers = peers
+                    .into_iter()
+                    .map(|(id, name)| Peer {
+                        id,
+                        name,
+                        connection: (id == HOST_PEER).then(|| Connection::new(address, now)),
+                    })
+                    .collect();
+
+                info!("Joined session as peer {}", peer);
+                self.events.push(NetworkEvent::Connected { peer });
+            }
+            Packet::Reject { reason } if self.role == NetworkRole::Connecting => {
+                self.close(DisconnectReason::Rejected(reason));
+            }
+            Packet::PeerJoined { peer, name } if self.peer(peer).is_none() => {
+                self.peers.push(Peer {
+                    id: peer,
+                    name: name.clone(),
+                    connection: None,
+                });
+                self.events.push(NetworkEvent::PeerJoined { peer, name });
+            }
+            Packet::PeerLeft { peer, timed_out } => {
+                let reason = if timed_out {
+                    DisconnectReason::TimedOut
+                } else {
+                    DisconnectReason::Left
+                };
+                self.drop_peer(peer, reason);
+            }
+            Packet::Disconnect => self.close(DisconnectReason::Left),
+            Packet::Data(data) if self.role == NetworkRole::Client => {
+                self.events.push(NetworkEvent::Received {
+                    peer: HOST_PEER,
+                    data,
+                });
+            }
+            Packet::Reliable {
+                channel,
+                sequence,
+                data,
+            } if self.role == NetworkRole::Client => {
+                self.receive_reliable(HOST_PEER, channel, sequence, data, address)
+            }
+            Packet::Ack { channel, sequence } => {
+                if let Some(reliable) = self.channels.get_mut(&(HOST_PEER, channel)) {
+                    reliable.acknowledge(sequence);
+                }
+            }
+            Packet::Unreliable { channel, data } if self.role == NetworkRole::Client => {
+                self.receive_unreliable(HOST_PEER, channel, data);
+            }
+            _ => {}
+        }
+    }
+
+    fn drop_peer(&mut self, peer: PeerId, reason: DisconnectReason) {
+        let Some(index) = self.peers.iter().position(|other| other.id == peer) else {
+            return;
+        };
+
+        let left = self.peers.remove(index);
+        info!("{} left: {:?}", left.name, reason);
+        self.channels.retain(|(other, _), _| *other != peer);
+
+        if self.role == NetworkRole::Host {
+            let timed_out = reason == DisconnectReason::TimedOut;
+            for address in self.connected_addresses() {
+                self.send_packet(&Packet::PeerLeft { peer, timed_out }, address);
+            }
+        }
+
+        self.events.push(NetworkEvent::PeerLeft { peer, reason });
+    }
+
+    fn receive_reliable(
+        &mut self,
+        peer: PeerId,
+        channel: ChannelId,
+        sequence: u32,
+        data: Vec<u8>,
+        address: SocketAddr,
+    ) {
+        // Duplicates are acknowledged too, the first acknowledgement may have been lost
+        self.send_packet(&Packet::Ack { channel, sequence }, address);
+
+        let delivered = self
+            .channels
+            .entry((peer, channel))
+            .or_default()
+            .receive(sequence, data);
+
+        let received = self.received.entry(channel).or_default();
+        if received.len() + delivered.len() > MAX_RECEIVED_MESSAGES {
+            warn!(
+                "Channel {} has more than {} messages waiting",
+                channel, MAX_RECEIVED_MESSAGES
+            );
+            self.overflow(peer);
+            return;
+        }
+
+        received.extend(delivered.into_iter().map(|data| (peer, data)));
+    }
+
+    fn receive_unreliable(&mut self, peer: PeerId, channel: ChannelId, data: Vec<u8>) {
+        let received = self.received.entry(channel).or_default();
+        if received.len() >= MAX_RECEIVED_MESSAGES {
+            debug!("Dropped unreliable message on full channel {}", channel);
+            return;
+        }
+
+        received.push_back((peer, data));
+    }
+
+    // Drops a peer that reliable messages piled up for, or leaves the session
+    // if it is the host
+    fn overflow(&mut self, peer: PeerId) {
+        warn!("Peer {} fell too far behind, dropping it", peer);
+
+        if let Some(address) = self.address_of(peer) {
+            self.send_packet(&Packet::Disconnect, address);
+        }
+
+        match self.role {
+            NetworkRole::Host => self.drop_peer(peer, DisconnectReason::Overflowed),
+            _ => self.close(DisconnectReason::Overflowed),
+        }
+    }
+
+    fn send_channels(&mut self, now: Instant) {
+        let mut due = Vec::new();
+        for ((peer, channel), reliable) in self.channels.iter_mut() {
+            for (sequence, data) in reliable.due(now) {
+                due.push((*peer, *channel, sequence, data));
+            }
+        }
+
+        for (peer, channel, sequence, data) in due {
+            if let Some(address) = self.address_of(peer) {
+                self.send_packet(
+                    &Packet::Reliable {
+                        channel,
+                        sequence,
+                        data,
+                    },
+                    address,
+                );
+            }
+        }
+    }
+
+    fn retry_connect(&mut self, now: Instant) {
+        let Some(address) = self.host_address else {
+            return;
+        };
+
+        if now.duration_since(self.connect_started) > self.settings.timeout {
+            warn!("Host at {} didn't answer", address);
+            self.close(DisconnectReason::TimedOut);
+            return;
+        }
+
+        if self
+            .last_connect_attempt
+            .is_some_and(|last| now.duration_since(last) < CONNECT_RETRY_INTERVAL)
+        {
+            return;
+        }
+
+        self.last_connect_attempt = Some(now);
+        self.send_packet(
+            &Packet::Connect {
+                game_id: self.settings.game_id.clone(),
+                version: self.settings.version,
+                name: self.player_name.clone(),
+            },
+            address,
+        );
+    }
+
+    fn keep_alive(&mut self, now: Instant) {
+        let timed_out = self
+            .peers
+            .iter()
+            .filter(|peer| {
+                peer.connection.is_some_and(|connection| {
+                    now.duration_since(connection.last_received) > self.settings.timeout
+                })
+            })
+            .map(|peer| peer.id)
+            .collect::<Vec<_>>();
+
+        for peer in timed_out {
+            if self.role == NetworkRole::Client && peer == HOST_PEER {
+                self.close(DisconnectReason::TimedOut);
+                return;
+            }
+
+            self.drop_peer(peer, DisconnectReason::TimedOut);
+        }
+
+        let idle = self
+            .peers
+            .iter()
+            .filter_map(|peer| peer.connection)
+            .filter(|connection| {
+                now.duration_since(connection.last_sent) >= self.settings.keepalive_interval
+            })
+            .map(|connection| connection.address)
+            .collect::<Vec<_>>();
+
+        for address in idle {
+            self.send_packet(&Packet::Keepalive, address);
+        }
+    }
+}
+
+impl Drop for NetworkState {
+    fn drop(&mut self) {
+        self.disconnect();
+    }
+}
+
+/// Updates the session of a world if it has one, sending what was typed in
+/// its chat boxes and showing the chat messages that arrived
+pub(crate) fn update_network(compound: &Compound, t: f32) {
+    let mut typed = Vec::new();
+    compound.iter_mut_mol_unmod(|_entity, chat_box: &mut ChatBox| {
+        typed.extend(chat_box.take_submitted());
+    });
+
+    let Some(messages) = compound.resource_mut::<NetworkState, _>(|network| {
+        for text in typed {
+            if let Err(err) = network.send_chat(text) {
+                warn!("Failed to send chat message: {}", err);
+            }
+        }
+
+        network.update();
+        network.update_chat();
+        std::mem::take(&mut network.chat)
+    }) else {
+        return;
+    };
+
+    if messages.is_empty() {
+        return;
+    }
+
+    compound.iter_mut_mol(|_entity, chat_box: &mut ChatBox| {
+        for message in messages.iter() {
+            chat_box.push(message, t);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            Mutex,
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+        },
+        thread,
+    };
+
+    use super::{super::channel::MAX_UNACKNOWLEDGED, *};
+
+    const HOST_ADDRESS: SocketAddr = SocketAddr::new(std::net::IpAddr::V4(Ipv4Addr::LOCALHOST), 1);
+    const CHANNEL: ChannelId = 7;
+
+    type Inboxes = Arc<Mutex<HashMap<SocketAddr, VecDeque<(Vec<u8>, SocketAddr)>>>>;
+
+    // One end of an in memory network that can lose packets
+    struct Loopback {
+        address: SocketAddr,
+        inboxes: Inboxes,
+        // Packets sent while offline and the next `drop_next` packets are lost
+        offline: AtomicBool,
+        drop_next: AtomicUsize,
+    }
+
+    impl Loopback {
+        fn new(inboxes: &Inboxes, port: u16) -> Arc<Self> {
+            Arc::new(Self {
+                address: SocketAddr::new(HOST_ADDRESS.ip(), port),
+                inboxes: inboxes.clone(),
+                offline: AtomicBool::new(false),
+                drop_next: AtomicUsize::new(0),
+            })
+        }
+    }
+
+    impl Transport for Loopback {
+        fn send_to(&self, bytes: &[u8], address: SocketAddr) -> Result<()> {
+            let dropped = self
+                .drop_next
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+                    count.checked_sub(1)
+                })
+                .is_ok();
+
+            if !dropped && !self.offline.load(Ordering::SeqCst) {
+                self.inboxes
+                    .lock()
+                    .unwrap()
+                    .entry(address)
+                    .or_default()
+                    .push_back((bytes.to_vec(), self.address));
+            }
+
+            Ok(())
+        }
+
+        fn receive(&self, buffer: &mut [u8]) -> Result<Option<(usize, SocketAddr)>> {
+            let received = self
+                .inboxes
+                .lock()
+                .unwrap()
+                .get_mut(&self.address)
+                .and_then(VecDeque::pop_front);
+
+            Ok(received.map(|(bytes, sender)| {
+                buffer[..bytes.len()].copy_from_slice(&bytes);
+                (bytes.len(), sender)
+            }))
+        }
+    }
+
+    fn settings() -> NetworkSettings {
+        NetworkSettings {
+            keepalive_interval: Duration::from_millis(10),
+            timeout: Duration::from_millis(200),
+            ..Default::default()
+        }
+    }
+
+    // Updates every peer a few times so packets travel back and forth
+    fn pump(states: &mut [&mut NetworkState]) {
+        for _ in 0..4 {
+            for state in states.iter_mut() {
+                state.update();
+            }
+        }
+    }
+
+    // A host and a client that joined it, with the events of joining drained
+    fn session() -> (NetworkState, Arc<Loopback>, NetworkState, Arc<Loopback>) {
+        let inboxes = Inboxes::default();
+        let host_transport = Loopback::new(&inboxes, HOST_ADDRESS.port());
+        let client_transport = Loopback::new(&inboxes, 2);
+
+        let mut host = NetworkState::new(settings());
+        host.host_through(host_transport.clone(), "Lobby", "Host")
+            .unwrap();
+        let mut client = NetworkState::new(settings());
+        client
+            .join_through(client_transport.clone(), HOST_ADDRESS, "Client")
+            .unwrap();
+
+        pump(&mut [&mut host, &mut client]);
+        assert_eq!(client.role(), NetworkRole::Client);
+        host.drain_events();
+        client.drain_events();
+
+        (host, host_transport, client, client_transport)
+    }
+
+    #[test]
+    fn test_connect() {
+        let inboxes = Inboxes::default();
+        let mut host = NetworkState::new(settings());
+        host.host_through(
+            Loopback::new(&inboxes, HOST_ADDRESS.port()),
+            "Lobby",
+            "Host",
+        )
+        .unwrap();
+
+        let mut first = NetworkState::new(settings());
+        first
+            .join_through(Loopback::new(&inboxes, 2), HOST_ADDRESS, "First")
+            .unwrap();
+        assert_eq!(first.role(), NetworkRole::Connecting);
+
+        pump(&mut [&mut host, &mut first]);
+
+        assert_eq!(first.role(), NetworkRole::Client);
+        assert_eq!(first.local_peer(), Some(1));
+        assert_eq!(
+            first.drain_events(),
+            vec![NetworkEvent::Connected { peer: 1 }]
+        );
+        assert_eq!(
+            host.drain_events(),
+            vec![NetworkEvent::PeerJoined {
+                peer: 1,
+                name: String::from("First"),
+            }]
+        );
+
+        // Clients that join later learn about the peers already there, and
+        // the peers already there learn about them
+        let mut second = NetworkState::new(settings());
+        second
+            .join_through(Loopback::new(&inboxes, 3), HOST_ADDRESS, "Second")
+            .unwrap();
+        pump(&mut [&mut host, &mut first, &mut second]);
+
+        assert_eq!(second.local_peer(), Some(2));
+        assert_eq!(second.peer(1).map(|peer| peer.name.as_str()), Some("First"));
+        assert_eq!(
+            second.peer(HOST_PEER).map(|peer| peer.name.as_str()),
+            Some("Host")
+        );
+        assert_eq!(
+            first.drain_events(),
+            vec![NetworkEvent::PeerJoined {
+                peer: 2,
+                name: String::from("Second"),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_connect_rejects_other_versions() {
+        let inboxes = Inboxes::default();
+        let mut host = NetworkState::new(settings());
+        host.host_through(
+            Loopback::new(&inboxes, HOST_ADDRESS.port()),
+            "Lobby",
+            "Host",
+        )
+        .unwrap();
+
+        let mut client = NetworkState::new(NetworkSettings {
+            version: 2,
+            ..settings()
+        });
+        client
+            .join_through(Loopback::new(&inboxes, 2), HOST_ADDRESS, "Client")
+            .unwrap();
+        pump(&mut [&mut host, &mut client]);
+
+        assert_eq!(client.role(), NetworkRole::Offline);
+        assert!(matches!(
+            client.drain_events().as_slice(),
+            [NetworkEvent::Disconnected {
+                reason: DisconnectReason::Rejected(_),
+            }]
+        ));
+        assert!(host.peers().is_empty());
+    }
+
+    #[test]
+    fn test_disconnect() {
+        let (mut host, _, mut client, _) = session();
+
+        client.disconnect();
+        assert_eq!(client.role(), NetworkRole::Offline);
+        assert_eq!(
+            client.drain_events(),
+            vec![NetworkEvent::Disconnected {
+                reason: DisconnectReason::Left,
+            }]
+        );
+
+        pump(&mut [&mut host]);
+        assert!(host.peers().is_empty());
+        assert_eq!(
+            host.drain_events(),
+            vec![NetworkEvent::PeerLeft {
+                peer: 1,
+                reason: DisconnectReason::Left,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_host_disconnect_ends_session() {
+        let (mut host, _, mut client, _) = session();
+
+        host.disconnect();
+        pump(&mut [&mut client]);
+
+        assert_eq!(client.role(), NetworkRole::Offline);
+        assert_eq!(
+            client.drain_events(),
+            vec![NetworkEvent::Disconnected {
+                reason: DisconnectReason::Left,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_timeout() {
+        let (mut host, host_transport, mut client, client_transport) = session();
+
+        // Keepalives keep quiet peers connected
+        for _ in 0..3 {
+            thread::sleep(settings().timeout / 4);
+            pump(&mut [&mut host, &mut client]);
+        }
+        assert!(host.drain_events().is_empty());
+        assert!(client.drain_events().is_empty());
+
+        host_transport.offline.store(true, Ordering::SeqCst);
+        client_transport.offline.store(true, Ordering::SeqCst);
+        thread::sleep(settings().timeout * 2);
+        pump(&mut [&mut host, &mut client]);
+
+        assert_eq!(
+            host.drain_events(),
+            vec![NetworkEvent::PeerLeft {
+                peer: 1,
+                reason: DisconnectReason::TimedOut,
+            }]
+        );
+        assert_eq!(client.role(), NetworkRole::Offline);
+        assert_eq!(
+            client.drain_events(),
+            vec![NetworkEvent::Disconnected {
+                reason: DisconnectReason::TimedOut,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_connect_timeout() {
+        let inboxes = Inboxes::default();
+        let mut client = NetworkState::new(settings());
+        client
+            .join_through(Loopback::new(&inboxes, 2), HOST_ADDRESS, "Client")
+            .unwrap();
+
+        pump(&mut [&mut client]);
+        assert_eq!(client.role(), NetworkRole::Connecting);
+
+        thread::sleep(settings().timeout * 2);
+        pump(&mut [&mut client]);
+
+        assert_eq!(client.role(), NetworkRole::Offline);
+        assert_eq!(
+            client.drain_events(),
+            vec![NetworkEvent::Disconnected {
+                reason: DisconnectReason::TimedOut,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_reliable_redelivery() {
+        let (mut host, host_transport, mut client, _) = session();
+        let messages = (0..3u8).map(|index| vec![index; 4]).collect::<Vec<_>>();
+
+        for message in messages.iter() {
+            host.send_reliable(1, CHANNEL, message.clone()).unwrap();
+        }
+
+        // The first message is lost, so the others are held back
+        host_transport.drop_next.store(1, Ordering::SeqCst);
+        pump(&mut [&mut host, &mut client]);
+        assert!(client.drain_channel(CHANNEL).is_empty());
+
+        // Until it is sent again
+        thread::sleep(Duration::from_millis(250));
+        pump(&mut [&mut host, &mut client]);
+        assert_eq!(
+            client.drain_channel(CHANNEL),
+            messages
+                .iter()
+                .map(|message| (HOST_PEER, message.clone()))
+                .collect::<Vec<_>>()
+        );
+
+        // Every message was acknowledged, so nothing arrives twice
+        thread::sleep(Duration::from_millis(250));
+        pump(&mut [&mut host, &mut client]);
+        assert!(client.drain_channel(CHANNEL).is_empty());
+    }
+
+    #[test]
+    fn test_reliable_redelivery_of_lost_acknowledgements() {
+        let (mut host, host_transport, mut client, _) = session();
+
+        client
+            .send_reliable(HOST_PEER, CHANNEL, vec![1, 2, 3])
+            .unwrap();
+
+        // The message arrives but its acknowledgement is lost
+        client.update();
+        host_transport.offline.store(true, Ordering::SeqCst);
+        host.update();
+        host_transport.offline.store(false, Ordering::SeqCst);
+        assert_eq!(host.drain_channel(CHANNEL), vec![(1, vec![1, 2, 3])]);
+
+        // The resent message is acknowledged but not delivered again
+        thread::sleep(Duration::from_millis(250));
+        pump(&mut [&mut host, &mut client]);
+        assert!(host.drain_channel(CHANNEL).is_empty());
+    }
+
+    #[test]
+    fn test_unacknowledged_overflow_drops_peer() {
+        let (mut host, _, mut client, client_transport) = session();
+
+        // The client stops acknowledging, so every message waits on the host
+        client_transport.offline.store(true, Ordering::SeqCst);
+        for index in 0..MAX_UNACKNOWLEDGED {
+            host.send_reliable(1, CHANNEL, vec![index as u8]).unwrap();
+        }
+        pump(&mut [&mut host, &mut client]);
+        assert!(host.drain_events().is_empty());
+
+        // One more than the channel holds and the client is dropped
+        assert!(host.send_reliable(1, CHANNEL, vec![0]).is_err());
+        assert!(host.peers().is_empty());
+        assert_eq!(
+            host.drain_events(),
+            vec![NetworkEvent::PeerLeft {
+                peer: 1,
+                reason: DisconnectReason::Overflowed,
+            }]
+        );
+
+        // And told so
+        pump(&mut [&mut client]);
+        assert_eq!(client.role(), NetworkRole::Offline);
+    }
+
+    #[test]
+    fn test_undrained_unreliable_messages_are_dropped() {
+        let (mut host, _, mut client, _) = session();
+
+        for index in 0..MAX_RECEIVED_MESSAGES + 10 {
+            host.send_unreliable(1, CHANNEL, vec![index as u8]).unwrap();
+        }
+        pump(&mut [&mut host, &mut client]);
+
+        // Only the oldest messages are kept and the client stays connected
+        let received = client.drain_channel(CHANNEL);
+        assert_eq!(received.len(), MAX_RECEIVED_MESSAGES);
+        assert_eq!(received[0], (HOST_PEER, vec![0]));
+        assert_eq!(client.role(), NetworkRole::Client);
+    }
+}
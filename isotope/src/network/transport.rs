@@ -0,0 +1,72 @@
+use std::{
+    io::ErrorKind,
+    net::{SocketAddr, UdpSocket},
+};
+
+use anyhow::Result;
+
+/// Largest packet the engine sends or receives, small enough to not be
+/// fragmented on most networks
+pub const MAX_PACKET_SIZE: usize = 1200;
+
+/// How packets get to other peers.
+///
+/// The engine sends over UDP by default. A relay for players whose NAT can't be
+/// traversed implements this to forward packets through its server, addressing
+/// peers by whatever addresses the relay hands out.
+///
+/// # Example
+/// ```ignore
+/// struct MyRelay { /* connection to the relay server */ }
+///
+/// impl Transport for MyRelay {
+///     fn send_to(&self, bytes: &[u8], address: SocketAddr) -> Result<()> {
+///         self.forward(address, bytes)
+///     }
+///
+///     fn receive(&self, buffer: &mut [u8]) -> Result<Option<(usize, SocketAddr)>> {
+///         self.poll_forwarded(buffer)
+///     }
+/// }
+///
+/// network.join_through(Arc::new(MyRelay::connect("relay.example.com:7000")?), host, "Player")?;
+/// ```
+pub trait Transport: Send + Sync {
+    /// Sends a packet without blocking
+    fn send_to(&self, bytes: &[u8], address: SocketAddr) -> Result<()>;
+
+    /// Receives a packet into `buffer` without blocking
+    ///
+    /// # Returns
+    /// The length and sender of the packet, none if no packet is waiting
+    fn receive(&self, buffer: &mut [u8]) -> Result<Option<(usize, SocketAddr)>>;
+}
+
+impl Transport for UdpSocket {
+    fn send_to(&self, bytes: &[u8], address: SocketAddr) -> Result<()> {
+        match UdpSocket::send_to(self, bytes, address) {
+            Ok(_) => Ok(()),
+            // A full send buffer drops the packet like the network would
+            Err(err) if err.kind() == ErrorKind::WouldBlock => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn receive(&self, buffer: &mut [u8]) -> Result<Option<(usize, SocketAddr)>> {
+        match self.recv_from(buffer) {
+            Ok(received) => Ok(Some(received)),
+            Err(err) if err.kind() == ErrorKind::WouldBlock => Ok(None),
+            // Windows reports an unreachable peer on the next receive
+            Err(err) if err.kind() == ErrorKind::ConnectionReset => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Binds a non blocking UDP socket on every interface
+pub(crate) fn bind_udp(port: u16) -> Result<UdpSocket> {
+    let socket = UdpSocket::bind(SocketAddr::from(([0, 0, 0, 0], port)))?;
+    socket.set_nonblocking(true)?;
+
+    Ok(socket)
+}
@@ -0,0 +1,813 @@
+use std::{
+    collections::{HashMap, VecDeque, hash_map::Entry},
+    f32::consts::FRAC_PI_4,
+    sync::{Arc, Mutex, mpsc},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, anyhow};
+use audiopus::{
+    Application, Bitrate, Channels, MutSignals, SampleRate,
+    coder::{Decoder, Encoder, GenericCtl},
+    packet::Packet,
+};
+use cgmath::{InnerSpace, Quaternion, Rotation, Vector3};
+use compound::Compound;
+use cpal::{
+    FromSample, Sample, SampleFormat, SizedSample, Stream, StreamConfig,
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+};
+use gluon::ThreadSignal;
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    channel::ChannelId,
+    session::{HOST_PEER, NetworkRole, NetworkState, PeerId},
+};
+use crate::{ActionMap, Transform3D};
+
+/// Unreliable channel voice is sent on
+pub const VOICE_CHANNEL: ChannelId = ChannelId::MAX - 3;
+
+/// Action that transmits voice while it is held, unless the settings name another
+pub const DEFAULT_PUSH_TO_TALK_ACTION: &str = "push_to_talk";
+
+// Opus runs at 48 kHz mono, 20 ms per frame
+const VOICE_SAMPLE_RATE: u32 = 48_000;
+const FRAME_SAMPLES: usize = 960;
+const MAX_FRAME_BYTES: usize = 512;
+
+// Frames a speaker buffers before playing, to ride out uneven arrival
+const JITTER_FRAMES: usize = 3;
+
+// Most audio kept waiting on either side, anything older is dropped
+const MAX_QUEUED_SAMPLES: usize = FRAME_SAMPLES * 16;
+
+// Lost frames that are concealed instead of treated as a new talk spurt
+const MAX_CONCEALED_FRAMES: u16 = 3;
+
+// Time a speaker counts as speaking after its last frame
+const SPEAKING_TIMEOUT: Duration = Duration::from_millis(250);
+
+// Voices quieter than this can't be heard and are always virtual
+const INAUDIBLE_GAIN: f32 = 1e-3;
+
+// How far the mixed gains of a voice move towards their target every output
+// sample, about 10 ms to fade in or out so voices don't click
+const GAIN_EASING: f32 = 1.0 / 480.0;
+
+/// Component marking the entity of a player, whose voice is played from its transform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoiceSpeaker {
+    pub peer: PeerId,
+}
+
+/// Component marking where the local player hears voices from, usually the
+/// camera or the player.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VoiceListener;
+
+/// How voice is sent and played.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoiceSettings {
+    /// Bits per second of encoded voice
+    pub bitrate: i32,
+    /// Volume of every voice from 0 to 1
+    pub volume: f32,
+    /// Distance voices are at full volume within
+    pub min_distance: f32,
+    /// Distance voices fade out at
+    pub max_distance: f32,
+    /// Action of the `ActionMap` that transmits while held, always
+    /// transmitting if none
+    pub push_to_talk: Option<String>,
+    /// Most voices mixed at once. The loudest voices where the listener is
+    /// are mixed, the rest are virtual: still tracked for `is_speaking`, but
+    /// neither decoded nor mixed until they are loud enough to be heard
+    pub max_audible_voices: usize,
+}
+
+impl Default for VoiceSettings {
+    fn default() -> Self {
+        Self {
+            bitrate: 24_000,
+            volume: 1.0,
+            min_distance: 2.0,
+            max_distance: 40.0,
+            push_to_talk: Some(String::from(DEFAULT_PUSH_TO_TALK_ACTION)),
+            max_audible_voices: 16,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct VoicePacket {
+    speaker: PeerId,
+    sequence: u16,
+    frame: Vec<u8>,
+}
+
+/// Voice of a speaker waiting to be played
+#[derive(Default)]
+struct VoiceStream {
+    samples: VecDeque<f32>,
+    playing: bool,
+    // Between the first two samples, for playing at another rate than 48 kHz
+    position: f32,
+    gains: (f32, f32),
+    // The gains mixed with, easing towards `gains` so changes don't click
+    mixed_gains: (f32, f32),
+    // Fades out and stops being mixed
+    is_virtual: bool,
+}
+
+/// The voices being played, shared with the output stream
+#[derive(Default)]
+struct Mixer {
+    streams: HashMap<PeerId, VoiceStream>,
+    // Plays silence and drops every voice that arrives
+    paused: bool,
+}
+
+impl Mixer {
+    /// Mixes the next frame of every voice into a left and right sample
+    ///
+    /// # Arguments
+    /// * `step` - Voice samples per output sample
+    fn next(&mut self, step: f32) -> (f32, f32) {
+        let mut mixed = (0.0, 0.0);
+
+        if self.paused {
+            for stream in self.streams.values_mut() {
+                stream.samples.clear();
+                stream.playing = false;
+                stream.mixed_gains = (0.0, 0.0);
+            }
+
+            return mixed;
+        }
+
+        for stream in self.streams.values_mut() {
+            let target = match stream.is_virtual {
+                true => (0.0, 0.0),
+                false => stream.gains,
+            };
+
+            // Faded out, the rest of a virtual voice is never heard
+            if stream.is_virtual
+                && (!stream.playing
+                    || stream.mixed_gains.0.max(stream.mixed_gains.1) <= INAUDIBLE_GAIN)
+            {
+                stream.samples.clear();
+                stream.playing = false;
+                stream.mixed_gains = (0.0, 0.0);
+                continue;
+            }
+
+            if !stream.playing {
+                stream.playing = stream.samples.len() >= FRAME_SAMPLES * JITTER_FRAMES;
+                continue;
+            }
+
+            let (Some(first), second) = (stream.samples.front(), stream.samples.get(1)) else {
+                // Ran dry, buffer up again before playing on
+                stream.playing = false;
+                continue;
+            };
+            let sample = first + (second.unwrap_or(first) - first) * stream.position;
+
+            stream.mixed_gains.0 += (target.0 - stream.mixed_gains.0) * GAIN_EASING;
+            stream.mixed_gains.1 += (target.1 - stream.mixed_gains.1) * GAIN_EASING;
+            mixed.0 += sample * stream.mixed_gains.0;
+            mixed.1 += sample * stream.mixed_gains.1;
+
+            stream.position += step;
+            while stream.position >= 1.0 {
+                stream.position -= 1.0;
+                stream.samples.pop_front();
+            }
+        }
+
+        (mixed.0.clamp(-1.0, 1.0), mixed.1.clamp(-1.0, 1.0))
+    }
+}
+
+/// Keeps the microphone and speaker streams open on a thread of their own,
+/// the streams can't be moved between threads on every platform
+struct AudioThread {
+    signal: Arc<ThreadSignal>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl AudioThread {
+    fn start(captured: Arc<Mutex<VecDeque<f32>>>, mixer: Arc<Mutex<Mixer>>) -> Result<Self> {
+        let signal = Arc::new(ThreadSignal::new());
+        let thread_signal = signal.clone();
+        let (ready_sender, ready) = mpsc::channel();
+
+        let handle = std::thread::spawn(move || {
+            let host = cpal::default_host();
+
+            let input = host
+                .default_input_device()
+                .ok_or_else(|| anyhow!("No microphone"))
+                .and_then(|device| open_input(&device, captured));
+            let input = match input {
+                Ok(input) => Some(input),
+                Err(err) => {
+                    warn!("Voice can't be sent: {}", err);
+                    None
+                }
+            };
+
+            let output = host
+                .default_output_device()
+                .ok_or_else(|| anyhow!("No speakers"))
+                .and_then(|device| open_output(&device, mixer));
+            let output = match output {
+                Ok(output) => output,
+                Err(err) => {
+                    _ = ready_sender.send(Err(err));
+                    return;
+                }
+            };
+            _ = ready_sender.send(Ok(()));
+
+            thread_signal.wait_for_stop();
+
+            drop(input);
+            drop(output);
+        });
+
+        ready
+            .recv()
+            .map_err(|_| anyhow!("Audio thread stopped while starting"))??;
+
+        Ok(Self {
+            signal,
+            handle: Some(handle),
+        })
+    }
+}
+
+impl Drop for AudioThread {
+    fn drop(&mut self) {
+        self.signal.stop();
+
+        if let Some(handle) = self.handle.take()
+            && handle.join().is_err()
+        {
+            error!("Audio thread panicked");
+        }
+    }
+}
+
+fn open_input(device: &cpal::Device, captured: Arc<Mutex<VecDeque<f32>>>) -> Result<Stream> {
+    let config = device.default_input_config()?;
+    let format = config.sample_format();
+    let config = config.config();
+
+    let stream = match format {
+        SampleFormat::F32 => build_input::<f32>(device, &config, captured)?,
+        SampleFormat::I16 => build_input::<i16>(device, &config, captured)?,
+        SampleFormat::U16 => build_input::<u16>(device, &config, captured)?,
+        SampleFormat::I32 => build_input::<i32>(device, &config, captured)?,
+        format => return Err(anyhow!("Unsupported microphone format {}", format)),
+    };
+    stream.play()?;
+
+    info!(
+        "Capturing voice at {} Hz with {} channels",
+        config.sample_rate.0, config.channels
+    );
+    Ok(stream)
+}
+
+fn build_input<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    captured: Arc<Mutex<VecDeque<f32>>>,
+) -> Result<Stream>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let channels = config.channels.max(1) as usize;
+    let step = config.sample_rate.0 as f32 / VOICE_SAMPLE_RATE as f32;
+    let mut position = 0.0;
+    let mut previous = 0.0;
+
+    Ok(device.build_input_stream(
+        config,
+        move |data: &[T], _| {
+            let Ok(mut captured) = captured.lock() else {
+                return;
+            };
+
+            // Down to mono, then to 48 kHz
+            for frame in data.chunks(channels) {
+                let sample = frame
+                    .iter()
+                    .map(|sample| f32::from_sample(*sample))
+                    .sum::<f32>()
+                    / frame.len() as f32;
+
+                while position < 1.0 {
+                    captured.push_back(previous + (sample - previous) * position);
+                    position += step;
+                }
+                position -= 1.0;
+                previous = sample;
+            }
+
+            while captured.len() > MAX_QUEUED_SAMPLES {
+                captured.pop_front();
+            }
+        },
+        |err| warn!("Microphone stream failed: {}", err),
+        None,
+    )?)
+}
+
+fn open_output(device: &cpal::Device, mixer: Arc<Mutex<Mixer>>) -> Result<Stream> {
+    let config = device.default_output_config()?;
+    let format = config.sample_format();
+    let config = config.config();
+
+    let stream = match format {
+        SampleFormat::F32 => build_output::<f32>(device, &config, mixer)?,
+        SampleFormat::I16 => build_output::<i16>(device, &config, mixer)?,
+        SampleFormat::U16 => build_output::<u16>(device, &config, mixer)?,
+        SampleFormat::I32 => build_output::<i32>(device, &config, mixer)?,
+        format => return Err(anyhow!("Unsupported speaker format {}", format)),
+    };
+    stream.play()?;
+
+    Ok(stream)
+}
+
+fn build_output<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    mixer: Arc<Mutex<Mixer>>,
+) -> Result<Stream>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let channels = config.channels.max(1) as usize;
+    let step = VOICE_SAMPLE_RATE as f32 / config.sample_rate.0 as f32;
+
+    Ok(device.build_output_stream(
+        config,
+        move |data: &mut [T], _| {
+            let Ok(mut mixer) = mixer.lock() else {
+                data.fill(T::EQUILIBRIUM);
+                return;
+            };
+
+            for frame in data.chunks_mut(channels) {
+                let (left, right) = mixer.next(step);
+
+                for (channel, sample) in frame.iter_mut().enumerate() {
+                    *sample = T::from_sample(match (channels, channel) {
+                        (1, _) => (left + right) * 0.5,
+                        (_, 0) => left,
+                        (_, 1) => right,
+                        _ => 0.0,
+                    });
+                }
+            }
+        },
+        |err| warn!("Speaker stream failed: {}", err),
+        None,
+    )?)
+}
+
+struct Speaker {
+    decoder: Decoder,
+    next_sequence: u16,
+    last_heard: Instant,
+    // Frames of a virtual speaker aren't decoded
+    is_virtual: bool,
+    // Frames were skipped while virtual, the decoder starts over once realized
+    skipped: bool,
+}
+
+// Opus state, only ever used through `&mut VoiceChat`
+struct Codecs {
+    encoder: Encoder,
+    speakers: HashMap<PeerId, Speaker>,
+}
+
+/// Voice chat with the other players of the session of a world.
+///
+/// While the push to talk action is held the microphone is encoded with Opus
+/// and sent on `VOICE_CHANNEL`, which the host passes on to every other
+/// player. Voices are played from the transform of the entity with the
+/// `VoiceSpeaker` of their player, as heard from the entity with the
+/// `VoiceListener`, and without position if the player has no entity.
+///
+/// Only built with the `voice` feature.
+///
+/// # Example
+/// ```ignore
+/// compound.insert_resource(VoiceChat::new(VoiceSettings::default())?);
+///
+/// action_map.bind(DEFAULT_PUSH_TO_TALK_ACTION, ActionBinding::press(KeyCode::KeyV));
+/// compound.spawn((VoiceListener, camera_transform));
+///
+/// // When a player joins
+/// compound.spawn((VoiceSpeaker { peer }, Transform3D::default(), player_model));
+/// ```
+pub struct VoiceChat {
+    settings: VoiceSettings,
+    captured: Arc<Mutex<VecDeque<f32>>>,
+    mixer: Arc<Mutex<Mixer>>,
+    codecs: Mutex<Codecs>,
+    muted: bool,
+    transmitting: bool,
+    next_sequence: u16,
+    // Dropped last so the streams stop before what they use goes away
+    _audio: AudioThread,
+}
+
+impl VoiceChat {
+    /// Opens the default microphone and speakers.
+    ///
+    /// # Returns
+    /// An error if there are no speakers or Opus can't be set up, a missing
+    /// microphone only keeps voice from being sent
+    pub fn new(settings: VoiceSettings) -> Result<Self> {
+        let mut encoder = Encoder::new(SampleRate::Hz48000, Channels::Mono, Application::Voip)?;
+        encoder.set_bitrate(Bitrate::BitsPerSecond(settings.bitrate))?;
+
+        let captured = Arc::new(Mutex::new(VecDeque::new()));
+        let mixer = Arc::new(Mutex::new(Mixer::default()));
+        let audio = AudioThread::start(captured.clone(), mixer.clone())?;
+
+        Ok(Self {
+            settings,
+            captured,
+            mixer,
+            codecs: Mutex::new(Codecs {
+                encoder,
+                speakers: HashMap::new(),
+            }),
+            muted: false,
+            transmitting: false,
+            next_sequence: 0,
+            _audio: audio,
+        })
+    }
+
+    pub fn settings(&self) -> &VoiceSettings {
+        &self.settings
+    }
+
+    /// Stops sending voice regardless of push to talk
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    /// Silences every voice and stops sending voice until resumed, used by
+    /// `BackgroundPolicy::pause_audio`
+    pub fn set_paused(&mut self, paused: bool) {
+        match self.mixer.lock() {
+            Ok(mut mixer) => mixer.paused = paused,
+            Err(err) => error!("Failed to pause voice: {}", err),
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.mixer.lock().is_ok_and(|mixer| mixer.paused)
+    }
+
+    /// Returns whether the local player's voice is being sent
+    pub fn is_transmitting(&self) -> bool {
+        self.transmitting
+    }
+
+    /// Returns whether a player's voice was heard just now, for speaking indicators
+    pub fn is_speaking(&mut self, peer: PeerId) -> bool {
+        self.codecs()
+            .speakers
+            .get(&peer)
+            .is_some_and(|speaker| speaker.last_heard.elapsed() < SPEAKING_TIMEOUT)
+    }
+
+    /// Returns whether a player's voice is virtual, tracked but too far, too
+    /// quiet or outranked by `max_audible_voices` louder ones to be mixed
+    pub fn is_virtual(&mut self, peer: PeerId) -> bool {
+        self.codecs()
+            .speakers
+            .get(&peer)
+            .is_some_and(|speaker| speaker.is_virtual)
+    }
+
+    fn codecs(&mut self) -> &mut Codecs {
+        match self.codecs.get_mut() {
+            Ok(codecs) => codecs,
+            Err(err) => err.into_inner(),
+        }
+    }
+
+    /// Encodes and sends the captured voice if the local player is talking
+    fn send(&mut self, network: &mut NetworkState, talking: bool) {
+        let frames = match self.captured.lock() {
+            Ok(mut captured) => {
+                let available = captured.len() - captured.len() % FRAME_SAMPLES;
+                captured.drain(..available).collect::<Vec<_>>()
+            }
+            Err(err) => {
+                error!("Voice capture poisoned: {}", err);
+                return;
+            }
+        };
+
+        let Some(speaker) = network.local_peer() else {
+            return;
+        };
+
+        self.transmitting = talking && !self.muted && !self.is_paused();
+        if !self.transmitting {
+            return;
+        }
+
+        let mut encoded = [0; MAX_FRAME_BYTES];
+        for frame in frames.chunks_exact(FRAME_SAMPLES) {
+            let length = match self.codecs().encoder.encode_float(frame, &mut encoded) {
+                Ok(length) => length,
+                Err(err) => {
+                    warn!("Failed to encode voice: {}", err);
+                    continue;
+                }
+            };
+
+            let packet = VoicePacket {
+                speaker,
+                sequence: self.next_sequence,
+                frame: encoded[..length].to_vec(),
+            };
+            self.next_sequence = self.next_sequence.wrapping_add(1);
+
+            let result = bincode::serialize(&packet)
+                .map_err(|err| anyhow!(err))
+                .and_then(|bytes| match network.role() {
+                    NetworkRole::Host => network.broadcast_unreliable(VOICE_CHANNEL, bytes),
+                    _ => network.send_unreliable(HOST_PEER, VOICE_CHANNEL, bytes),
+                });
+            if let Err(err) = result {
+                debug!("Failed to send voice: {}", err);
+            }
+        }
+    }
+
+    /// Decodes the voice that arrived, passing it on to the other clients if this is the host
+    fn receive(&mut self, network: &mut NetworkState) {
+        let host = network.role() == NetworkRole::Host;
+        let local = network.local_peer();
+
+        for (peer, bytes) in network.drain_channel(VOICE_CHANNEL) {
+            let Ok(mut packet) = bincode::deserialize::<VoicePacket>(&bytes) else {
+                debug!("Dropped invalid voice from peer {}", peer);
+                continue;
+            };
+
+            if host {
+                // Clients can't speak for someone else
+                packet.speaker = peer;
+
+                if let Ok(bytes) = bincode::serialize(&packet) {
+                    for other in network.connected_peers() {
+                        if other != peer {
+                            _ = network.send_unreliable(other, VOICE_CHANNEL, bytes.clone());
+                        }
+                    }
+                }
+            }
+
+            if Some(packet.speaker) != local {
+                self.play(packet);
+            }
+        }
+    }
+
+    fn play(&mut self, packet: VoicePacket) {
+        let speaker = match self.codecs().speakers.entry(packet.speaker) {
+            Entry::Occupied(speaker) => speaker.into_mut(),
+            Entry::Vacant(entry) => match Decoder::new(SampleRate::Hz48000, Channels::Mono) {
+                Ok(decoder) => entry.insert(Speaker {
+                    decoder,
+                    next_sequence: packet.sequence,
+                    last_heard: Instant::now(),
+                    is_virtual: false,
+                    skipped: false,
+                }),
+                Err(err) => {
+                    warn!("Failed to create voice decoder: {}", err);
+                    return;
+                }
+            },
+        };
+
+        // Virtual voices are only kept track of, not decoded
+        if speaker.is_virtual {
+            speaker.next_sequence = packet.sequence.wrapping_add(1);
+            speaker.last_heard = Instant::now();
+            speaker.skipped = true;
+            return;
+        }
+
+        // Realized after skipping frames, start decoding afresh from this one
+        if speaker.skipped {
+            if let Err(err) = speaker.decoder.reset_state() {
+                debug!("Failed to reset voice decoder: {}", err);
+            }
+            speaker.next_sequence = packet.sequence;
+            speaker.skipped = false;
+        }
+
+        // Late frames were already concealed
+        let missing = packet.sequence.wrapping_sub(speaker.next_sequence);
+        if missing > u16::MAX / 2 {
+            return;
+        }
+
+        let mut decoded = Vec::new();
+        let mut buffer = vec![0.0; FRAME_SAMPLES * 6];
+
+        if missing <= MAX_CONCEALED_FRAMES {
+            for _ in 0..missing {
+                if let Ok(signals) = MutSignals::try_from(&mut buffer[..FRAME_SAMPLES])
+                    && let Ok(length) = speaker.decoder.decode_float(None, signals, false)
+                {
+                    decoded.extend_from_slice(&buffer[..length]);
+                }
+            }
+        }
+
+        let result = Packet::try_from(packet.frame.as_slice()).and_then(|frame| {
+            MutSignals::try_from(&mut buffer[..])
+                .and_then(|signals| speaker.decoder.decode_float(Some(frame), signals, false))
+        });
+        match result {
+            Ok(length) => decoded.extend_from_slice(&buffer[..length]),
+            Err(err) => debug!("Failed to decode voice of peer {}: {}", packet.speaker, err),
+        }
+
+        speaker.next_sequence = packet.sequence.wrapping_add(1);
+        speaker.last_heard = Instant::now();
+
+        match self.mixer.lock() {
+            Ok(mut mixer) => {
+                let stream = mixer.streams.entry(packet.speaker).or_default();
+                stream.samples.extend(decoded);
+                while stream.samples.len() > MAX_QUEUED_SAMPLES {
+                    stream.samples.pop_front();
+                }
+            }
+            Err(err) => error!("Voice mixer poisoned: {}", err),
+        }
+    }
+
+    /// Pans and attenuates every voice by where its speaker is, then mixes
+    /// only the loudest `max_audible_voices` and makes the rest virtual
+    fn position(
+        &mut self,
+        network: &NetworkState,
+        listener: Option<(Vector3<f32>, Quaternion<f32>)>,
+        speakers: &HashMap<PeerId, Vector3<f32>>,
+    ) {
+        let mixer = self.mixer.clone();
+        let Ok(mut mixer) = mixer.lock() else {
+            return;
+        };
+
+        // Players that left aren't heard anymore
+        mixer
+            .streams
+            .retain(|peer, _| network.peer(*peer).is_some() || *peer == HOST_PEER);
+
+        // Rank the voices by how loud they are where the listener is, with
+        // the players speaking right now before the silent ones
+        let settings = self.settings.clone();
+        let mut ranked = self
+            .codecs()
+            .speakers
+            .iter()
+            .map(|(peer, speaker)| {
+                let gains = voice_gains(&settings, listener, speakers.get(peer));
+                let speaking = speaker.last_heard.elapsed() < SPEAKING_TIMEOUT;
+
+                (*peer, gains, speaking)
+            })
+            .collect::<Vec<_>>();
+        ranked.sort_by(|(_, a, a_speaking), (_, b, b_speaking)| {
+            b_speaking
+                .cmp(a_speaking)
+                .then(b.0.max(b.1).total_cmp(&a.0.max(a.1)))
+        });
+
+        for (rank, (peer, gains, _)) in ranked.into_iter().enumerate() {
+            let audible =
+                rank < settings.max_audible_voices && gains.0.max(gains.1) > INAUDIBLE_GAIN;
+
+            if let Some(speaker) = self.codecs().speakers.get_mut(&peer) {
+                speaker.is_virtual = !audible;
+            }
+
+            if let Some(stream) = mixer.streams.get_mut(&peer) {
+                stream.gains = gains;
+                stream.is_virtual = !audible;
+            }
+        }
+    }
+}
+
+/// Returns the left and right gain of a voice
+///
+/// # Arguments
+/// * `settings` - The volume and distances voices are heard within
+/// * `listener` - Where the voice is heard from, if anywhere
+/// * `mouth` - Where the voice is played from, if anywhere
+fn voice_gains(
+    settings: &VoiceSettings,
+    listener: Option<(Vector3<f32>, Quaternion<f32>)>,
+    mouth: Option<&Vector3<f32>>,
+) -> (f32, f32) {
+    match (listener, mouth) {
+        (Some((ear, rotation)), Some(mouth)) => {
+            let offset = mouth - ear;
+            let distance = offset.magnitude();
+
+            let falloff = (settings.max_distance - distance)
+                / (settings.max_distance - settings.min_distance).max(f32::EPSILON);
+            let gain = settings.volume * falloff.clamp(0.0, 1.0);
+
+            let right = rotation.rotate_vector(Vector3::unit_x());
+            let pan = match distance > f32::EPSILON {
+                true => offset.dot(right) / distance,
+                false => 0.0,
+            };
+
+            // Equal power, so voices keep their loudness as they move across
+            let angle = (pan + 1.0) * FRAC_PI_4;
+            (gain * angle.cos(), gain * angle.sin())
+        }
+        _ => {
+            let gain = settings.volume * FRAC_PI_4.cos();
+            (gain, gain)
+        }
+    }
+}
+
+/// Sends and plays the voice of the session of a world if it has `VoiceChat`
+pub(crate) fn update_voice(compound: &Compound) {
+    if !compound.has_resource::<VoiceChat>() {
+        return;
+    }
+
+    let push_to_talk = compound
+        .resource::<VoiceChat, _>(|voice| voice.settings.push_to_talk.clone())
+        .flatten();
+    let mut talking = push_to_talk.is_none();
+    if let Some(action) = push_to_talk {
+        compound.iter_mol(|_entity, action_map: &ActionMap| {
+            talking |= action_map.is_active(&action);
+        });
+    }
+
+    let mut listener = None;
+    compound.iter_duo(
+        |_entity, _listener: &VoiceListener, transform: &Transform3D| {
+            listener = transform
+                .get_position_and_rotation(|position, rotation| Some((*position, *rotation)));
+        },
+    );
+
+    let mut speakers = HashMap::new();
+    compound.iter_duo(|_entity, speaker: &VoiceSpeaker, transform: &Transform3D| {
+        transform.get_position(|position| {
+            speakers.insert(speaker.peer, *position);
+        });
+    });
+
+    compound.resource_mut::<VoiceChat, _>(|voice| {
+        compound.resource_mut::<NetworkState, _>(|network| {
+            if !matches!(network.role(), NetworkRole::Host | NetworkRole::Client) {
+                voice.transmitting = false;
+                return;
+            }
+
+            voice.send(network, talking);
+            voice.receive(network);
+            voice.position(network, listener, &speakers);
+        });
+    });
+}
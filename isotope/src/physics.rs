@@ -1,13 +1,16 @@
-use boson::{BosonBody, BosonObject, RigidBody};
-use cgmath::Vector3;
+use std::{collections::HashMap, sync::RwLock};
 
-use crate::Transform3D;
+use boson::{Boson, BosonBody, BosonDebugger, BosonObject, CastShape, Impact};
+use cgmath::{Quaternion, Vector3};
+use compound::{Compound, Entity};
+use log::{error, info, warn};
+use photon::GizmoVertex;
+
+use crate::{BosonCompliant, Discarded, Model, Transform3D};
 
 pub trait BosonCompat {
     fn write_transform(&self, transform: &Transform3D);
-    fn read_position<F, R>(&self, callback: F) -> R
-    where
-        F: FnOnce(&Vector3<f64>) -> R;
+    fn read_transform(&self, transform: &mut Transform3D);
 }
 
 impl BosonCompat for BosonObject {
@@ -18,19 +21,287 @@ impl BosonCompat for BosonObject {
                 point_mass.position.y = pos.y as f64;
                 point_mass.position.z = pos.z as f64;
             }),
+            BosonBody::RigidBody(rigid_body) => transform.get_position_and_rotation(|pos, rot| {
+                rigid_body.orientation = rot.cast().unwrap_or(rigid_body.orientation);
+                rigid_body.set_origin(pos.cast().unwrap_or(rigid_body.origin()));
+            }),
             _ => {}
         });
+
+        // The body was moved outside of a step, so do not interpolate the move
+        self.reset_state();
     }
 
-    fn read_position<F, R>(&self, callback: F) -> R
-    where
-        F: FnOnce(&Vector3<f64>) -> R,
-    {
+    fn read_transform(&self, transform: &mut Transform3D) {
         self.read_body(|body| match body {
-            BosonBody::PointMass(point_mass) => callback(&point_mass.position),
-            _ => {
-                todo!()
+            BosonBody::PointMass(point_mass) => transform.position(|pos| {
+                pos.x = point_mass.position.x as f32;
+                pos.y = point_mass.position.y as f32;
+                pos.z = point_mass.position.z as f32;
+            }),
+            BosonBody::RigidBody(rigid_body) => transform.position_and_rotation(|pos, rot| {
+                let origin: Vector3<f64> = rigid_body.origin();
+                *pos = origin.cast().unwrap_or(*pos);
+
+                let orientation: Quaternion<f64> = rigid_body.orientation;
+                *rot = orientation.cast().unwrap_or(*rot);
+            }),
+            _ => {}
+        });
+    }
+}
+
+/// Prepares bodies that have not been added to Boson yet.
+///
+/// Bodies whose colliders are built from a model get their collider from the
+/// `Model` on the same entity, then every new body is moved to the entity's
+/// transform so it starts where the entity was spawned.
+pub(crate) fn prepare_new_bodies(compound: &Compound) {
+    compound.iter_without_duo::<BosonCompliant, _, _, _>(
+        |_entity, boson_object: &BosonObject, model: &Model| {
+            boson_object.modify_body(|body| {
+                if let BosonBody::RigidBody(rigid_body) = body
+                    && rigid_body.needs_model_geometry()
+                {
+                    if rigid_body.collider_builder().needs_model_triangles() {
+                        info!("Building triangle mesh collider from model");
+                        if let Err(err) = model.triangles(|positions, indices| {
+                            rigid_body.build_collider_from_mesh(positions, indices)
+                        }) {
+                            // The body is given an empty mesh so this isn't retried every tick
+                            warn!("Failed to build triangle mesh collider: {}", err);
+                            rigid_body.build_collider_from_mesh(&[], &[]);
+                        }
+                    } else {
+                        info!("Building collider from model");
+                        rigid_body.build_collider_from_points(model.hull_points());
+                    }
+                }
+            });
+        },
+    );
+
+    compound.iter_without_duo::<BosonCompliant, _, _, _>(
+        |_entity, boson_object: &BosonObject, transform: &Transform3D| {
+            boson_object.write_transform(transform);
+        },
+    );
+}
+
+/// Adds new bodies to Boson and syncs transforms with it.
+///
+/// Transforms that changed since the last sync are written to their bodies
+/// first, then every transform is read back from the results of physics.
+pub(crate) fn sync_physics(compound: &Compound, boson: &RwLock<Boson>) {
+    // Add any new boson objects
+    {
+        prepare_new_bodies(compound);
+
+        let mut to_add_as_boson_compliant: Vec<Entity> = Vec::new();
+
+        compound.iter_without_mol_mod::<BosonCompliant, _, _>(
+            |entity, boson_object: &BosonObject| {
+                if let Ok(mut boson) = boson.write() {
+                    info!("Adding Boson Object");
+                    boson.add_object(boson_object);
+                    to_add_as_boson_compliant.push(entity);
+                }
+            },
+        );
+
+        for entity in to_add_as_boson_compliant.into_iter() {
+            compound.add_molecule(entity, BosonCompliant);
+            info!("Added Boson Object");
+        }
+    }
+
+    // Update boson objects with any changed transforms first
+    {
+        compound.iter_mut_duo_mod(
+            |_entity, transform: &mut Transform3D, boson_object: &mut BosonObject| {
+                boson_object.write_transform(transform);
+            },
+        );
+    }
+
+    // Update transforms with the new boson values
+    {
+        // Unmodified so the transform update is not triggered at the next goaround
+        compound.iter_mut_duo_unmod(
+            |_entity, transform: &mut Transform3D, boson_object: &mut BosonObject| {
+                boson_object.read_transform(transform);
+            },
+        );
+    }
+}
+
+/// Despawns every discarded entity, dropping its model and removing its body
+/// from physics if it is still there
+pub(crate) fn despawn_discarded(compound: &Compound, boson: &RwLock<Boson>) {
+    let mut discarded = Vec::new();
+    compound.iter_mol(|entity, _discarded: &Discarded| discarded.push(entity));
+
+    if discarded.is_empty() {
+        return;
+    }
+
+    let mut bodies = Vec::new();
+    compound.iter_mol(|entity, boson_object: &BosonObject| {
+        if discarded.contains(&entity) {
+            bodies.push(boson_object.clone());
+        }
+    });
+
+    if !bodies.is_empty() {
+        match boson.write() {
+            Ok(mut boson) => {
+                for body in bodies.iter() {
+                    boson.remove_object(body);
+                }
             }
-        })
+            Err(err) => error!("Failed to remove discarded bodies from boson: {}", err),
+        }
+    }
+
+    for entity in discarded {
+        compound.despawn(entity);
+    }
+}
+
+/// Resource with the impacts of the last physics step, drained from Boson
+/// once so every system reacting to impacts sees all of them.
+#[derive(Default)]
+pub(crate) struct PhysicsImpacts(pub(crate) Vec<Impact>);
+
+/// Replaces the impacts of the world with the ones physics found since the last tick
+pub(crate) fn drain_impacts(compound: &Compound, boson: &RwLock<Boson>) {
+    let impacts = match boson.read() {
+        Ok(boson) => boson.drain_impacts(),
+        Err(err) => {
+            error!("Failed to read impacts: {}", err);
+            Vec::new()
+        }
+    };
+
+    compound.insert_resource(PhysicsImpacts(impacts));
+}
+
+/// Where a ray cast with `SystemContext::raycast` or `Isotope::raycast`, or a
+/// shape cast with `SystemContext::shape_cast`, first hit the body of an entity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RaycastHit {
+    /// The entity whose `BosonObject` was hit
+    pub entity: Entity,
+    /// Where the ray or shape touched the collider in world space
+    pub point: Vector3<f32>,
+    /// Unit normal of the collider's surface at `point` in world space
+    pub normal: Vector3<f32>,
+    /// How far the ray or shape travelled from its origin before it hit
+    pub distance: f32,
+}
+
+/// Casts a ray against the bodies of the entities of a compound, see `Boson::raycast`.
+///
+/// Bodies that don't belong to an entity of the compound are never hit.
+pub(crate) fn raycast(
+    compound: &Compound,
+    boson: &RwLock<Boson>,
+    origin: Vector3<f32>,
+    direction: Vector3<f32>,
+    max_distance: f32,
+    filter: impl Fn(Entity) -> bool,
+) -> Option<RaycastHit> {
+    let entities = body_entities(compound);
+    let boson = match boson.read() {
+        Ok(boson) => boson,
+        Err(err) => {
+            error!("Failed to read physics for raycast: {}", err);
+            return None;
+        }
+    };
+
+    let hit = boson.raycast(
+        origin.cast()?,
+        direction.cast()?,
+        max_distance as f64,
+        |object| {
+            entities
+                .get(&object.id())
+                .is_some_and(|entity| filter(*entity))
+        },
+    )?;
+
+    Some(RaycastHit {
+        entity: *entities.get(&hit.object.id())?,
+        point: hit.point.cast()?,
+        normal: hit.normal.cast()?,
+        distance: hit.t as f32,
+    })
+}
+
+/// Sweeps a shape against the bodies of the entities of a compound, see `Boson::shape_cast`.
+///
+/// Bodies that don't belong to an entity of the compound are never hit.
+pub(crate) fn shape_cast(
+    compound: &Compound,
+    boson: &RwLock<Boson>,
+    shape: &CastShape,
+    start: &Transform3D,
+    direction: Vector3<f32>,
+    max_distance: f32,
+    filter: impl Fn(Entity) -> bool,
+) -> Option<RaycastHit> {
+    let entities = body_entities(compound);
+    let boson = match boson.read() {
+        Ok(boson) => boson,
+        Err(err) => {
+            error!("Failed to read physics for shape cast: {}", err);
+            return None;
+        }
+    };
+
+    let (origin, rotation) =
+        start.get_position_and_rotation(|position, rotation| (*position, *rotation));
+    let hit = boson.shape_cast(
+        shape,
+        origin.cast()?,
+        rotation.cast()?,
+        direction.cast()?,
+        max_distance as f64,
+        |object| {
+            entities
+                .get(&object.id())
+                .is_some_and(|entity| filter(*entity))
+        },
+    )?;
+
+    Some(RaycastHit {
+        entity: *entities.get(&hit.object.id())?,
+        point: hit.point.cast()?,
+        normal: hit.normal.cast()?,
+        distance: hit.t as f32,
+    })
+}
+
+/// Maps the id of every body to the entity it belongs to
+fn body_entities(compound: &Compound) -> HashMap<usize, Entity> {
+    let mut entities = HashMap::new();
+    compound.iter_mol(|entity, boson_object: &BosonObject| {
+        entities.insert(boson_object.id(), entity);
+    });
+    entities
+}
+
+/// Collects the debug lines of Boson as gizmo vertices for Photon to draw
+pub(crate) struct GizmoDebugger(pub(crate) Vec<GizmoVertex>);
+
+impl BosonDebugger for GizmoDebugger {
+    fn draw_line(&mut self, start: Vector3<f64>, end: Vector3<f64>, color: [f32; 4]) {
+        for point in [start, end] {
+            self.0.push(GizmoVertex::new(
+                [point.x as f32, point.y as f32, point.z as f32],
+                color,
+            ));
+        }
     }
 }
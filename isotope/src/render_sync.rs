@@ -0,0 +1,447 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+    },
+};
+
+use compound::{Added, Changed, Compound, Entity, With, Without};
+use photon::Light;
+
+use crate::{Camera, Camera3DDescriptor, Discarded, Model, RenderLayers, world::WorldId};
+
+// Updates a stream holds before the renderer has to read the compound again
+const RENDER_UPDATE_CAPACITY: usize = 1024;
+
+/// How the renderer draws with a camera
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct CameraLink {
+    /// Cameras render in order so overlay cameras draw over the ones before them
+    pub(crate) order: i32,
+    /// The layers of the models the camera draws
+    pub(crate) layers: RenderLayers,
+    /// The layers only drawn in the viewmodel pass of the camera
+    pub(crate) view_model: Option<RenderLayers>,
+}
+
+// A change to an entity the renderer caches, `None` once it is no longer drawn
+struct RenderUpdate<T> {
+    world: WorldId,
+    entity: Entity,
+    value: Option<T>,
+}
+
+// The sending end of a stream and what the renderer was last sent on it
+struct Stream<T> {
+    sender: SyncSender<RenderUpdate<T>>,
+    sent: HashMap<Entity, T>,
+    // The counts the stream was last checked with, a change in them means
+    // something was removed
+    counts: Vec<usize>,
+}
+
+impl<T: Copy> Stream<T> {
+    fn new(sender: SyncSender<RenderUpdate<T>>) -> Self {
+        Self {
+            sender,
+            sent: HashMap::new(),
+            counts: Vec::new(),
+        }
+    }
+
+    // Returns whether anything changed since the stream was last checked
+    fn dirty(&mut self, changes: usize, counts: Vec<usize>) -> bool {
+        let dirty = changes > 0 || counts != self.counts;
+        self.counts = counts;
+        dirty
+    }
+
+    // Sends every entity whose value differs from what was last sent.
+    //
+    // Returns false if the stream was full, the renderer reads the compound
+    // again in that case so the rest is only remembered as sent
+    fn send(
+        &mut self,
+        world: WorldId,
+        current: HashMap<Entity, T>,
+        same: impl Fn(&T, &T) -> bool,
+    ) -> bool {
+        let mut updates = current
+            .iter()
+            .filter(|(entity, value)| self.sent.get(entity).is_none_or(|sent| !same(sent, value)))
+            .map(|(entity, value)| (*entity, Some(*value)))
+            .collect::<Vec<_>>();
+        updates.extend(
+            self.sent
+                .keys()
+                .filter(|entity| !current.contains_key(entity))
+                .map(|entity| (*entity, None)),
+        );
+        self.sent = current;
+
+        updates.into_iter().all(|(entity, value)| {
+            !matches!(
+                self.sender.try_send(RenderUpdate {
+                    world,
+                    entity,
+                    value,
+                }),
+                Err(TrySendError::Full(_))
+            )
+        })
+    }
+}
+
+/// Streams the lights, cameras and model layers of the active world that
+/// changed to the renderer, so it doesn't scan the compound every frame.
+///
+/// Run by the state thread after every tick. Finding the changes still
+/// scans the storages, but off the thread that renders.
+pub(crate) struct RenderSync {
+    // The world the streams are about
+    world: Option<WorldId>,
+    // The change tick of the last check
+    since: u64,
+    lights: Stream<(Light, RenderLayers)>,
+    cameras: Stream<CameraLink>,
+    instances: Stream<RenderLayers>,
+    resync: Arc<AtomicBool>,
+}
+
+impl RenderSync {
+    /// Streams what changed in the active world since the last tick.
+    ///
+    /// # Arguments
+    /// * `world` - The active world
+    /// * `compound` - The entities of the active world
+    pub(crate) fn push(&mut self, world: WorldId, compound: &Compound) {
+        let now = compound.advance_change_tick();
+        let since = std::mem::replace(&mut self.since, now);
+
+        let (light_changes, light_counts) = light_changes(compound, since);
+        let (camera_changes, camera_counts) = camera_changes(compound, since);
+        let (instance_changes, instance_counts) = instance_changes(compound, since);
+
+        // The renderer reads a newly active world whole, only the changes after it are streamed
+        if self.world != Some(world) {
+            self.world = Some(world);
+            self.lights.dirty(0, light_counts);
+            self.lights.sent = scan_lights(compound);
+            self.cameras.dirty(0, camera_counts);
+            self.cameras.sent = scan_cameras(compound);
+            self.instances.dirty(0, instance_counts);
+            self.instances.sent = scan_instances(compound);
+
+            self.resync.store(true, Ordering::Release);
+            return;
+        }
+
+        let mut sent = true;
+        if self.lights.dirty(light_changes, light_counts) {
+            sent &= self.lights.send(
+                world,
+                scan_lights(compound),
+                |(a, a_layers), (b, b_layers)| {
+                    bytemuck::bytes_of(a) == bytemuck::bytes_of(b) && a_layers == b_layers
+                },
+            );
+        }
+
+        if self.cameras.dirty(camera_changes, camera_counts) {
+            sent &= self
+                .cameras
+                .send(world, scan_cameras(compound), PartialEq::eq);
+        }
+
+        if self.instances.dirty(instance_changes, instance_counts) {
+            sent &= self
+                .instances
+                .send(world, scan_instances(compound), PartialEq::eq);
+        }
+
+        if !sent {
+            self.resync.store(true, Ordering::Release);
+        }
+    }
+}
+
+/// The lights, cameras and model layers of the rendered world, kept up to
+/// date with the changes `RenderSync` streams.
+pub(crate) struct RenderScene {
+    // Only taken through `&mut self`, the mutexes keep the renderer `Sync`
+    light_updates: Mutex<Receiver<RenderUpdate<(Light, RenderLayers)>>>,
+    camera_updates: Mutex<Receiver<RenderUpdate<CameraLink>>>,
+    instance_updates: Mutex<Receiver<RenderUpdate<RenderLayers>>>,
+    // Set when the streams can't be trusted and the compound has to be read again
+    resync: Arc<AtomicBool>,
+
+    /// Every light with the layers it lights
+    pub(crate) lights: Vec<(Entity, Light, RenderLayers)>,
+    light_indices: HashMap<Entity, usize>,
+    /// Every camera that renders
+    pub(crate) cameras: HashMap<Entity, CameraLink>,
+    /// The layers of every model that has them, models without them are on the default layers
+    pub(crate) model_layers: HashMap<Entity, RenderLayers>,
+}
+
+impl RenderScene {
+    /// Applies the changes streamed since the last frame, or reads the
+    /// compound again if the world changed or a stream was full.
+    ///
+    /// # Arguments
+    /// * `world` - The world being rendered
+    /// * `compound` - The entities of the world being rendered
+    /// * `world_changed` - Whether a different world was rendered last frame
+    ///
+    /// # Returns
+    /// Whether any light changed
+    pub(crate) fn update(
+        &mut self,
+        world: WorldId,
+        compound: &Compound,
+        world_changed: bool,
+    ) -> bool {
+        let resync = self.resync.swap(false, Ordering::Acquire) || world_changed;
+
+        // Updates from before a resync are already in the compound
+        let mut lights_changed = false;
+        for update in updates(&mut self.light_updates) {
+            if resync || update.world != world {
+                continue;
+            }
+
+            lights_changed = true;
+            match (update.value, self.light_indices.get(&update.entity)) {
+                (Some((light, layers)), Some(&index)) => {
+                    self.lights[index] = (update.entity, light, layers)
+                }
+                (Some((light, layers)), None) => {
+                    self.light_indices.insert(update.entity, self.lights.len());
+                    self.lights.push((update.entity, light, layers));
+                }
+                (None, Some(&index)) => {
+                    self.lights.swap_remove(index);
+                    self.light_indices.remove(&update.entity);
+                    if let Some((moved, _, _)) = self.lights.get(index) {
+                        self.light_indices.insert(*moved, index);
+                    }
+                }
+                (None, None) => {}
+            }
+        }
+
+        for update in updates(&mut self.camera_updates) {
+            if !resync && update.world == world {
+                apply(&mut self.cameras, update);
+            }
+        }
+
+        for update in updates(&mut self.instance_updates) {
+            if !resync && update.world == world {
+                apply(&mut self.model_layers, update);
+            }
+        }
+
+        if resync {
+            self.lights = scan_lights(compound)
+                .into_iter()
+                .map(|(entity, (light, layers))| (entity, light, layers))
+                .collect();
+            self.light_indices = self
+                .lights
+                .iter()
+                .enumerate()
+                .map(|(index, (entity, _, _))| (*entity, index))
+                .collect();
+            self.cameras = scan_cameras(compound);
+            self.model_layers = scan_instances(compound);
+        }
+
+        lights_changed || resync
+    }
+
+    /// Returns the cameras in the order they render
+    pub(crate) fn camera_order(&self) -> Vec<Entity> {
+        let mut cameras = self
+            .cameras
+            .iter()
+            .map(|(entity, link)| (link.order, *entity))
+            .collect::<Vec<_>>();
+        cameras.sort();
+
+        cameras.into_iter().map(|(_, entity)| entity).collect()
+    }
+}
+
+// Takes every update waiting in a stream
+fn updates<T>(receiver: &mut Mutex<Receiver<RenderUpdate<T>>>) -> Vec<RenderUpdate<T>> {
+    receiver
+        .get_mut()
+        .unwrap_or_else(PoisonError::into_inner)
+        .try_iter()
+        .collect()
+}
+
+fn apply<T>(values: &mut HashMap<Entity, T>, update: RenderUpdate<T>) {
+    match update.value {
+        Some(value) => _ = values.insert(update.entity, value),
+        None => _ = values.remove(&update.entity),
+    }
+}
+
+/// Creates the streams between the state thread and the renderer
+pub(crate) fn render_streams() -> (RenderSync, RenderScene) {
+    let (light_sender, light_updates) = mpsc::sync_channel(RENDER_UPDATE_CAPACITY);
+    let (camera_sender, camera_updates) = mpsc::sync_channel(RENDER_UPDATE_CAPACITY);
+    let (instance_sender, instance_updates) = mpsc::sync_channel(RENDER_UPDATE_CAPACITY);
+    let resync = Arc::new(AtomicBool::new(false));
+
+    (
+        RenderSync {
+            world: None,
+            since: 0,
+            lights: Stream::new(light_sender),
+            cameras: Stream::new(camera_sender),
+            instances: Stream::new(instance_sender),
+            resync: resync.clone(),
+        },
+        RenderScene {
+            light_updates: Mutex::new(light_updates),
+            camera_updates: Mutex::new(camera_updates),
+            instance_updates: Mutex::new(instance_updates),
+            resync,
+            lights: Vec::new(),
+            light_indices: HashMap::new(),
+            cameras: HashMap::new(),
+            model_layers: HashMap::new(),
+        },
+    )
+}
+
+// How many lights changed since a tick, and the counts that change when
+// a light or its layers are removed
+fn light_changes(compound: &Compound, since: u64) -> (usize, Vec<usize>) {
+    let changes = compound
+        .query::<(&Light,), Changed<Light>>()
+        .since(since)
+        .count()
+        + compound
+            .query::<(&RenderLayers,), (With<Light>, Changed<RenderLayers>)>()
+            .since(since)
+            .count();
+
+    let counts = vec![
+        compound.query::<(&Light,), ()>().count(),
+        compound.query::<(&Light, &RenderLayers), ()>().count(),
+    ];
+
+    (changes, counts)
+}
+
+// How many cameras changed since a tick, and the counts that change when a
+// camera or what it renders with is removed
+fn camera_changes(compound: &Compound, since: u64) -> (usize, Vec<usize>) {
+    // Cameras are written every frame as they move, they only change how they render when added
+    let changes = compound
+        .query::<(&Camera,), Added<Camera>>()
+        .since(since)
+        .count()
+        + compound
+            .query::<(&Camera3DDescriptor,), (With<Camera>, Changed<Camera3DDescriptor>)>()
+            .since(since)
+            .count()
+        + compound
+            .query::<(&RenderLayers,), (With<Camera>, Changed<RenderLayers>)>()
+            .since(since)
+            .count();
+
+    let counts = vec![
+        compound.query::<(&Camera,), Without<Discarded>>().count(),
+        compound
+            .query::<(&Camera, &Camera3DDescriptor), ()>()
+            .count(),
+        compound.query::<(&Camera, &RenderLayers), ()>().count(),
+    ];
+
+    (changes, counts)
+}
+
+// How many model layers changed since a tick, and the count that changes
+// when one is removed
+fn instance_changes(compound: &Compound, since: u64) -> (usize, Vec<usize>) {
+    let changes = compound
+        .query::<(&RenderLayers,), (With<Model>, Changed<RenderLayers>)>()
+        .since(since)
+        .count();
+
+    (
+        changes,
+        vec![compound.query::<(&Model, &RenderLayers), ()>().count()],
+    )
+}
+
+fn scan_lights(compound: &Compound) -> HashMap<Entity, (Light, RenderLayers)> {
+    let mut light_layers = HashMap::new();
+    compound.iter_duo(|entity, _light: &Light, layers: &RenderLayers| {
+        light_layers.insert(entity, *layers);
+    });
+
+    let mut lights = HashMap::new();
+    compound.iter_mol(|entity, light: &Light| {
+        lights.insert(
+            entity,
+            (
+                *light,
+                light_layers.get(&entity).copied().unwrap_or_default(),
+            ),
+        );
+    });
+
+    lights
+}
+
+fn scan_cameras(compound: &Compound) -> HashMap<Entity, CameraLink> {
+    let mut camera_layers = HashMap::new();
+    compound.iter_duo(|entity, _camera: &Camera, layers: &RenderLayers| {
+        camera_layers.insert(entity, *layers);
+    });
+
+    let mut descriptors = HashMap::new();
+    compound.iter_duo(
+        |entity, _camera: &Camera, descriptor: &Camera3DDescriptor| {
+            descriptors.insert(
+                entity,
+                (
+                    descriptor.order,
+                    descriptor.view_model.map(|view_model| view_model.layers()),
+                ),
+            );
+        },
+    );
+
+    let mut cameras = HashMap::new();
+    compound.iter_without_mol::<Discarded, _, _>(|entity, _camera: &Camera| {
+        let (order, view_model) = descriptors.get(&entity).copied().unwrap_or_default();
+        cameras.insert(
+            entity,
+            CameraLink {
+                order,
+                layers: camera_layers.get(&entity).copied().unwrap_or_default(),
+                view_model,
+            },
+        );
+    });
+
+    cameras
+}
+
+fn scan_instances(compound: &Compound) -> HashMap<Entity, RenderLayers> {
+    let mut model_layers = HashMap::new();
+    compound.iter_duo(|entity, _model: &Model, layers: &RenderLayers| {
+        model_layers.insert(entity, *layers);
+    });
+
+    model_layers
+}
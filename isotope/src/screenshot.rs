@@ -0,0 +1,168 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+use compound::Compound;
+use gpu_controller::{
+    BufferDescriptor, BufferUsages, Extent3d, GpuController, MaintainBase, MapMode, Origin3d,
+    TexelCopyBufferInfo, TexelCopyBufferLayout, TexelCopyTextureInfo, Texture, TextureAspect,
+    TextureFormat,
+};
+use image::RgbaImage;
+use log::{error, info};
+
+// Rows of a texture copied into a buffer have to be aligned to this
+const COPY_BYTES_PER_ROW_ALIGNMENT: u32 = 256;
+
+/// A capture of the window saved to an image file.
+///
+/// The capture is taken at the end of the next frame, after every camera and
+/// overlay has drawn, and written on a background thread. The format is
+/// picked from the extension of the path.
+///
+/// # Example
+/// ```ignore
+/// Screenshot::new("screenshots/sunset.png").capture(compound);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Screenshot {
+    path: PathBuf,
+}
+
+impl Screenshot {
+    pub fn new<P>(path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Queues the screenshot to be taken at the end of the next frame that
+    /// renders the compound
+    pub fn capture(self, compound: &Compound) {
+        let mut screenshot = Some(self);
+        compound.resource_mut::<PendingScreenshots, _>(|screenshots| {
+            if let Some(screenshot) = screenshot.take() {
+                screenshots.0.push(screenshot);
+            }
+        });
+
+        if let Some(screenshot) = screenshot {
+            compound.insert_resource(PendingScreenshots(vec![screenshot]));
+        }
+    }
+}
+
+// Screenshots requested since the last frame
+pub(crate) struct PendingScreenshots(Vec<Screenshot>);
+
+/// Takes the screenshots queued in a compound from the frame that was rendered
+/// to `texture`.
+pub(crate) fn take_screenshots(
+    compound: &Compound,
+    gpu_controller: &GpuController,
+    texture: &Texture,
+) {
+    let Some(PendingScreenshots(screenshots)) = compound.remove_resource::<PendingScreenshots>()
+    else {
+        return;
+    };
+
+    let image = match read_texture(gpu_controller, texture) {
+        Ok(image) => image,
+        Err(err) => {
+            error!("Failed to capture screenshot: {}", err);
+            return;
+        }
+    };
+
+    // Encoding is slow enough to hitch a frame, so it is done in the background
+    gluon::spawn(move || {
+        for screenshot in screenshots {
+            match image.save(&screenshot.path) {
+                Ok(()) => info!("Saved screenshot to {:#?}", screenshot.path),
+                Err(err) => error!(
+                    "Failed to save screenshot to {:#?}: {}",
+                    screenshot.path, err
+                ),
+            }
+        }
+    });
+}
+
+/// Copies an 8 bit RGBA texture back to the CPU
+fn read_texture(gpu_controller: &GpuController, texture: &Texture) -> Result<RgbaImage> {
+    let width = texture.width();
+    let height = texture.height();
+    if texture.format().block_copy_size(None) != Some(4) {
+        return Err(anyhow!(
+            "Can't capture texture with format {:?}",
+            texture.format()
+        ));
+    }
+
+    let unpadded_bytes_per_row = width * 4;
+    let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT)
+        * COPY_BYTES_PER_ROW_ALIGNMENT;
+
+    let buffer = gpu_controller.create_buffer(&BufferDescriptor {
+        label: Some("Screenshot Buffer"),
+        size: (padded_bytes_per_row * height) as u64,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = gpu_controller.create_command_encoder("Screenshot Copy");
+    encoder.copy_texture_to_buffer(
+        TexelCopyTextureInfo {
+            texture,
+            mip_level: 0,
+            origin: Origin3d::ZERO,
+            aspect: TextureAspect::All,
+        },
+        TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(height),
+            },
+        },
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+    gpu_controller.submit(encoder);
+
+    let buffer_slice = buffer.slice(..);
+    buffer_slice.map_async(MapMode::Read, |_| {});
+    gpu_controller.poll(MaintainBase::Wait)?;
+
+    let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+    {
+        let mapped_data = buffer_slice.get_mapped_range();
+        for row in mapped_data.chunks(padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+        }
+    }
+    buffer.unmap();
+
+    if matches!(
+        texture.format(),
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb
+    ) {
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+
+    RgbaImage::from_raw(width, height, pixels)
+        .ok_or_else(|| anyhow!("Screenshot has the wrong size"))
+}
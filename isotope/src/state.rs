@@ -1,7 +1,7 @@
 use compound::Compound;
 use winit::keyboard::KeyCode;
 
-use crate::asset_server::AssetServer;
+use crate::{asset_server::AssetServer, input::PointerEvent};
 
 #[allow(unused_variables)]
 pub trait IsotopeState: Send + Sync + 'static {
@@ -9,6 +9,12 @@ pub trait IsotopeState: Send + Sync + 'static {
 
     fn update(&mut self, ecs: &Compound, assets: &AssetServer, delta_t: f32, t: f32) {}
 
+    // Called at a fixed cadence from the state thread, delta_t is always the fixed timestep
+    fn fixed_update(&mut self, ecs: &Compound, assets: &AssetServer, delta_t: f32, t: f32) {}
+
+    // Called once for every frame rendered, before rendering
+    fn frame_update(&mut self, ecs: &Compound, assets: &AssetServer, delta_t: f32, t: f32) {}
+
     fn key_is_pressed(&mut self, ecs: &Compound, assets: &AssetServer, key: KeyCode, t: f32) {}
 
     fn key_is_released(&mut self, ecs: &Compound, assets: &AssetServer, key: KeyCode, t: f32) {}
@@ -23,6 +29,81 @@ pub trait IsotopeState: Send + Sync + 'static {
     ) {
     }
 
+    // Window Event, touch input arrives as pointer events
+    fn pointer_event(
+        &mut self,
+        ecs: &Compound,
+        assets: &AssetServer,
+        pointer: PointerEvent,
+        t: f32,
+    ) {
+    }
+
     // Device event
     fn mouse_is_moved(&mut self, ecs: &Compound, assets: &AssetServer, delta: (f64, f64), t: f32) {}
+
+    // The application was sent to the background, the surface is gone until resumed
+    fn suspended(&mut self, ecs: &Compound, assets: &AssetServer) {}
+
+    // The application came back to the foreground with a new surface
+    fn resumed(&mut self, ecs: &Compound, assets: &AssetServer) {}
+}
+
+// Lets states that are only known at runtime (plugins, tools) drive the engine
+impl IsotopeState for Box<dyn IsotopeState> {
+    fn init(&mut self, ecs: &Compound, assets: &AssetServer) {
+        (**self).init(ecs, assets)
+    }
+
+    fn update(&mut self, ecs: &Compound, assets: &AssetServer, delta_t: f32, t: f32) {
+        (**self).update(ecs, assets, delta_t, t)
+    }
+
+    fn fixed_update(&mut self, ecs: &Compound, assets: &AssetServer, delta_t: f32, t: f32) {
+        (**self).fixed_update(ecs, assets, delta_t, t)
+    }
+
+    fn frame_update(&mut self, ecs: &Compound, assets: &AssetServer, delta_t: f32, t: f32) {
+        (**self).frame_update(ecs, assets, delta_t, t)
+    }
+
+    fn key_is_pressed(&mut self, ecs: &Compound, assets: &AssetServer, key: KeyCode, t: f32) {
+        (**self).key_is_pressed(ecs, assets, key, t)
+    }
+
+    fn key_is_released(&mut self, ecs: &Compound, assets: &AssetServer, key: KeyCode, t: f32) {
+        (**self).key_is_released(ecs, assets, key, t)
+    }
+
+    fn cursor_moved(
+        &mut self,
+        ecs: &Compound,
+        assets: &AssetServer,
+        cursor_position: (f32, f32),
+        t: f32,
+    ) {
+        (**self).cursor_moved(ecs, assets, cursor_position, t)
+    }
+
+    fn pointer_event(
+        &mut self,
+        ecs: &Compound,
+        assets: &AssetServer,
+        pointer: PointerEvent,
+        t: f32,
+    ) {
+        (**self).pointer_event(ecs, assets, pointer, t)
+    }
+
+    fn mouse_is_moved(&mut self, ecs: &Compound, assets: &AssetServer, delta: (f64, f64), t: f32) {
+        (**self).mouse_is_moved(ecs, assets, delta, t)
+    }
+
+    fn suspended(&mut self, ecs: &Compound, assets: &AssetServer) {
+        (**self).suspended(ecs, assets)
+    }
+
+    fn resumed(&mut self, ecs: &Compound, assets: &AssetServer) {
+        (**self).resumed(ecs, assets)
+    }
 }
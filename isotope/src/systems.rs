@@ -0,0 +1,536 @@
+use std::sync::{Arc, RwLock};
+
+use boson::{Boson, BosonObject, CastShape, Joint, JointId};
+use cgmath::Vector3;
+use compound::{Compound, Entity, Schedule, System};
+use log::error;
+
+use crate::{
+    Animator, AssetServer, AttachedTo, BosonCompliant, Camera3DDescriptor, CrowdAgent,
+    CrowdSettings, Debris, Discarded, Equipment, Equipped, Fracturable, Interactable, Interactor,
+    Inventory, ItemDatabase, LifecycleEvent, Model, PendingFootsteps, PendingModel, PendingSounds,
+    Perceivable, Perceiver, Pickup, Sockets, SpatialHash, SpatialHashed, SplineMesh, SurfaceEffect,
+    SurfaceHits, SurfaceResponses, SurfaceType, TimeDilation, TimeScale, Transform3D,
+    TriggerEntities, TriggerEvents, VisualEffect, VoxelTerrain,
+    elements::{
+        damage_fracturables, expire_debris, load_pending_models, shatter_fracturables,
+        update_animators, update_attachments, update_crowds, update_interactions,
+        update_inventories, update_perception, update_spatial_hash, update_spline_meshes,
+        update_surfaces, update_time_dilation, update_triggers, update_voxels,
+    },
+    lifecycle::LifecycleHooks,
+    network::{
+        ChatBox, NetworkId, NetworkRelevancy, NetworkState, NetworkVelocity, NetworkViewer,
+        PhysicsAuthority, record_authority, update_authority, update_network,
+    },
+    physics::{
+        PhysicsImpacts, RaycastHit, despawn_discarded, drain_impacts, raycast, shape_cast,
+        sync_physics,
+    },
+    world::WorldId,
+};
+
+/// Fires `LifecycleEvent::PrePhysics`, systems that change what physics
+/// simulates should run before it
+pub const PRE_PHYSICS_SYSTEM: &str = "isotope::pre_physics";
+
+/// Adds new bodies to physics and syncs them with their transforms
+pub const PHYSICS_SYNC_SYSTEM: &str = "isotope::physics_sync";
+
+/// Fires `LifecycleEvent::PostPhysics`, the last engine system of a tick
+pub const POST_PHYSICS_SYSTEM: &str = "isotope::post_physics";
+
+/// A system run on every world that is simulated, see `Isotope::add_system`
+pub type IsotopeSystem = System<SystemContext>;
+
+/// What a system is running for, passed to every `IsotopeSystem`.
+pub struct SystemContext {
+    /// The world the system is running on
+    pub world: WorldId,
+    /// Seconds of game time since the world was last simulated, scaled by its `Time`
+    pub delta_t: f32,
+    /// Seconds since the engine started
+    pub t: f32,
+
+    pub(crate) assets: Arc<AssetServer>,
+    pub(crate) boson: Arc<RwLock<Boson>>,
+    pub(crate) lifecycle_hooks: Arc<LifecycleHooks>,
+}
+
+impl SystemContext {
+    pub fn assets(&self) -> &AssetServer {
+        &self.assets
+    }
+
+    /// Casts a ray against the bodies of the entities of the world the system
+    /// is running on, such as for shooting or ground checks.
+    ///
+    /// # Arguments
+    /// * `compound` - The entities of the world, as passed to the system
+    /// * `origin` - Start of the ray in world space
+    /// * `direction` - Direction of the ray in world space, doesn't need to be normalized
+    /// * `max_distance` - How far from the origin bodies are hit
+    /// * `filter` - Returns whether the body of an entity can be hit
+    ///
+    /// # Returns
+    /// The closest hit, or `None` if the ray hits nothing within `max_distance`
+    ///
+    /// # Example
+    /// ```ignore
+    /// // Snap to the ground under the player, ignoring the player's own body
+    /// if let Some(hit) = context.raycast(compound, feet, -Vector3::unit_y(), 2.0, |entity| entity != player) {
+    ///     transform.position(|position| *position = hit.point);
+    /// }
+    /// ```
+    pub fn raycast(
+        &self,
+        compound: &Compound,
+        origin: Vector3<f32>,
+        direction: Vector3<f32>,
+        max_distance: f32,
+        filter: impl Fn(Entity) -> bool,
+    ) -> Option<RaycastHit> {
+        raycast(
+            compound,
+            &self.boson,
+            origin,
+            direction,
+            max_distance,
+            filter,
+        )
+    }
+
+    /// Sweeps a shape against the bodies of the entities of the world the
+    /// system is running on, such as to move a character or a projectile
+    /// without it passing through thin walls.
+    ///
+    /// # Arguments
+    /// * `compound` - The entities of the world, as passed to the system
+    /// * `shape` - The shape to sweep
+    /// * `start` - Where the center of the shape starts and its rotation,
+    ///   which stays the same along the sweep
+    /// * `direction` - Direction the shape moves in world space, doesn't need to be normalized
+    /// * `max_distance` - How far the shape moves
+    /// * `filter` - Returns whether the body of an entity can be hit
+    ///
+    /// # Returns
+    /// The first hit along the sweep, or `None` if the shape moves
+    /// `max_distance` without touching anything
+    ///
+    /// # Example
+    /// ```ignore
+    /// let capsule = CastShape::Capsule { radius: 0.4, half_height: 0.5 };
+    /// let step = velocity.magnitude() * context.delta_t;
+    /// let moved = context
+    ///     .shape_cast(compound, &capsule, transform, velocity, step, |entity| entity != player)
+    ///     .map_or(step, |hit| hit.distance);
+    /// ```
+    pub fn shape_cast(
+        &self,
+        compound: &Compound,
+        shape: &CastShape,
+        start: &Transform3D,
+        direction: Vector3<f32>,
+        max_distance: f32,
+        filter: impl Fn(Entity) -> bool,
+    ) -> Option<RaycastHit> {
+        shape_cast(
+            compound,
+            &self.boson,
+            shape,
+            start,
+            direction,
+            max_distance,
+            filter,
+        )
+    }
+
+    /// Adds a joint between the bodies of two entities of the world the
+    /// system is running on, such as to hang a door or mount a turret.
+    ///
+    /// The joint is removed along with either body.
+    ///
+    /// # Arguments
+    /// * `joint` - The joint, see `Joint`
+    ///
+    /// # Returns
+    /// The id of the joint, or `None` if physics couldn't be reached
+    ///
+    /// # Example
+    /// ```ignore
+    /// let wheel_joint = Joint::hinge(&chassis, &wheel, axle, Vector3::unit_x())
+    ///     .with_motor(JointMotor::Velocity { speed: 0.0, max_force: 200.0 });
+    /// let wheel_joint = context.add_joint(wheel_joint);
+    /// ```
+    pub fn add_joint(&self, joint: Joint) -> Option<JointId> {
+        match self.boson.read() {
+            Ok(boson) => Some(boson.add_joint(joint)),
+            Err(err) => {
+                error!("Failed to read physics to add joint: {}", err);
+                None
+            }
+        }
+    }
+
+    /// Changes a joint, such as the target of its motor, see `Boson::modify_joint`
+    ///
+    /// # Returns
+    /// The result of the callback, or `None` if the joint was removed
+    pub fn modify_joint<F, R>(&self, id: JointId, callback: F) -> Option<R>
+    where
+        F: FnOnce(&mut Joint) -> R,
+    {
+        match self.boson.read() {
+            Ok(boson) => boson.modify_joint(id, callback),
+            Err(err) => {
+                error!("Failed to read physics to modify joint: {}", err);
+                None
+            }
+        }
+    }
+
+    /// Removes a joint, letting its bodies move freely
+    ///
+    /// # Returns
+    /// The joint, or `None` if it was already removed
+    pub fn remove_joint(&self, id: JointId) -> Option<Joint> {
+        match self.boson.read() {
+            Ok(boson) => boson.remove_joint(id),
+            Err(err) => {
+                error!("Failed to read physics to remove joint: {}", err);
+                None
+            }
+        }
+    }
+}
+
+/// Creates the schedule with the systems of the engine, which every world
+/// runs each tick it is simulated.
+///
+/// Systems added with `Isotope::add_system` run after the engine systems
+/// unless they are ordered before one of them.
+pub(crate) fn engine_schedule() -> Schedule<SystemContext> {
+    let mut schedule = Schedule::new();
+
+    schedule
+        .add_system(
+            System::new(
+                "isotope::load_pending_models",
+                |compound, context: &SystemContext| load_pending_models(compound, &context.assets),
+            )
+            .writes::<PendingModel>()
+            .writes::<Model>()
+            .before(PRE_PHYSICS_SYSTEM),
+        )
+        .add_system(
+            System::new(
+                "isotope::shatter_fracturables",
+                |compound, context: &SystemContext| {
+                    shatter_fracturables(compound, &context.assets, &context.boson)
+                },
+            )
+            .writes::<Fracturable>()
+            .writes::<Transform3D>()
+            .writes::<Model>()
+            .writes::<BosonObject>()
+            .writes::<Debris>()
+            .writes::<Discarded>()
+            .before(PRE_PHYSICS_SYSTEM),
+        )
+        .add_system(
+            System::new(
+                "isotope::expire_debris",
+                |compound, context: &SystemContext| {
+                    expire_debris(compound, &context.boson, context.delta_t)
+                },
+            )
+            .writes::<Debris>()
+            .writes::<Discarded>()
+            .reads::<BosonObject>()
+            .after("isotope::shatter_fracturables")
+            .before(PRE_PHYSICS_SYSTEM),
+        )
+        .add_system(
+            System::new(
+                "isotope::update_spline_meshes",
+                |compound, context: &SystemContext| {
+                    update_spline_meshes(compound, &context.assets, &context.boson)
+                },
+            )
+            .writes::<SplineMesh>()
+            .writes::<Model>()
+            .writes::<Transform3D>()
+            .writes::<BosonObject>()
+            .writes::<Discarded>()
+            .reads::<Camera3DDescriptor>()
+            .before(PRE_PHYSICS_SYSTEM),
+        )
+        .add_system(
+            System::new(
+                "isotope::update_voxels",
+                |compound, context: &SystemContext| {
+                    update_voxels(compound, &context.assets, &context.boson)
+                },
+            )
+            .writes::<VoxelTerrain>()
+            .writes::<Model>()
+            .writes::<Transform3D>()
+            .writes::<BosonObject>()
+            .writes::<Discarded>()
+            .before(PRE_PHYSICS_SYSTEM),
+        )
+        .add_system(
+            System::new(
+                "isotope::update_crowds",
+                |compound, context: &SystemContext| update_crowds(compound, context.delta_t),
+            )
+            .writes::<CrowdAgent>()
+            .writes::<Transform3D>()
+            .reads::<BosonObject>()
+            .reads::<CrowdSettings>()
+            .before(PRE_PHYSICS_SYSTEM),
+        )
+        .add_system(
+            System::new(
+                "isotope::update_inventories",
+                |compound, context: &SystemContext| update_inventories(compound, context.delta_t),
+            )
+            .writes::<Inventory>()
+            .writes::<Pickup>()
+            .writes::<Equipment>()
+            .writes::<Equipped>()
+            .writes::<Transform3D>()
+            .writes::<PendingModel>()
+            .writes::<Discarded>()
+            .reads::<ItemDatabase>()
+            .before(PRE_PHYSICS_SYSTEM),
+        )
+        .add_system(
+            System::new(
+                "isotope::update_network",
+                |compound, context: &SystemContext| update_network(compound, context.t),
+            )
+            .writes::<NetworkState>()
+            .writes::<ChatBox>()
+            .before(PRE_PHYSICS_SYSTEM),
+        )
+        .add_system(
+            System::new(
+                "isotope::update_authority",
+                |compound, _context: &SystemContext| update_authority(compound),
+            )
+            .writes::<PhysicsAuthority>()
+            .writes::<NetworkState>()
+            .writes::<Transform3D>()
+            .writes::<NetworkVelocity>()
+            .reads::<NetworkId>()
+            .after("isotope::update_network")
+            .before(PRE_PHYSICS_SYSTEM),
+        )
+        .add_system(
+            System::new(
+                "isotope::despawn_discarded",
+                |compound, context: &SystemContext| despawn_discarded(compound, &context.boson),
+            )
+            .exclusive()
+            .after("isotope::shatter_fracturables")
+            .after("isotope::expire_debris")
+            .after("isotope::update_spline_meshes")
+            .after("isotope::update_voxels")
+            .after("isotope::update_inventories")
+            .before(PRE_PHYSICS_SYSTEM),
+        )
+        .add_system(
+            System::new(PRE_PHYSICS_SYSTEM, |compound, context: &SystemContext| {
+                context.lifecycle_hooks.fire(
+                    compound,
+                    &context.assets,
+                    LifecycleEvent::PrePhysics {
+                        world: context.world,
+                        delta_t: context.delta_t,
+                    },
+                );
+            })
+            .exclusive(),
+        )
+        .add_system(
+            System::new(PHYSICS_SYNC_SYSTEM, |compound, context: &SystemContext| {
+                sync_physics(compound, &context.boson)
+            })
+            .writes::<Transform3D>()
+            .writes::<BosonObject>()
+            .writes::<BosonCompliant>()
+            .reads::<Model>()
+            .after(PRE_PHYSICS_SYSTEM),
+        )
+        .add_system(
+            System::new(
+                "isotope::update_spatial_hash",
+                |compound, _context: &SystemContext| update_spatial_hash(compound),
+            )
+            .writes::<SpatialHash>()
+            .reads::<Transform3D>()
+            .reads::<SpatialHashed>()
+            .after(PHYSICS_SYNC_SYSTEM),
+        )
+        .add_system(
+            System::new(
+                "isotope::update_perception",
+                |compound, context: &SystemContext| {
+                    update_perception(compound, &context.boson, context.delta_t)
+                },
+            )
+            .writes::<Perceiver>()
+            .writes::<PendingSounds>()
+            .reads::<Transform3D>()
+            .reads::<Perceivable>()
+            .reads::<BosonObject>()
+            .after(PHYSICS_SYNC_SYSTEM),
+        )
+        .add_system(
+            System::new(
+                "isotope::drain_impacts",
+                |compound, context: &SystemContext| drain_impacts(compound, &context.boson),
+            )
+            .writes::<PhysicsImpacts>()
+            .after(PHYSICS_SYNC_SYSTEM),
+        )
+        .add_system(
+            System::new(
+                "isotope::damage_fracturables",
+                |compound, _context: &SystemContext| damage_fracturables(compound),
+            )
+            .writes::<Fracturable>()
+            .reads::<BosonObject>()
+            .reads::<PhysicsImpacts>()
+            .after("isotope::drain_impacts"),
+        )
+        .add_system(
+            System::new(
+                "isotope::update_surfaces",
+                |compound, context: &SystemContext| update_surfaces(compound, context.delta_t),
+            )
+            .writes::<SurfaceEffect>()
+            .writes::<VisualEffect>()
+            .writes::<Transform3D>()
+            .writes::<SurfaceHits>()
+            .writes::<PendingFootsteps>()
+            .writes::<PendingSounds>()
+            .reads::<SurfaceResponses>()
+            .reads::<SurfaceType>()
+            .reads::<Model>()
+            .reads::<BosonObject>()
+            .reads::<PhysicsImpacts>()
+            .after("isotope::drain_impacts")
+            .before("isotope::update_perception"),
+        )
+        .add_system(
+            System::new(
+                "isotope::update_triggers",
+                |compound, context: &SystemContext| update_triggers(compound, &context.boson),
+            )
+            .writes::<TriggerEvents>()
+            .writes::<TriggerEntities>()
+            .reads::<BosonObject>()
+            .after(PHYSICS_SYNC_SYSTEM),
+        )
+        .add_system(
+            System::new(
+                "isotope::update_interactions",
+                |compound, context: &SystemContext| update_interactions(compound, &context.boson),
+            )
+            .writes::<Interactor>()
+            .writes::<Interactable>()
+            .reads::<Transform3D>()
+            .reads::<BosonObject>()
+            .after(PHYSICS_SYNC_SYSTEM),
+        )
+        .add_system(
+            System::new(
+                "isotope::record_authority",
+                |compound, _context: &SystemContext| record_authority(compound),
+            )
+            .writes::<PhysicsAuthority>()
+            .writes::<NetworkState>()
+            .reads::<NetworkId>()
+            .reads::<BosonObject>()
+            .reads::<Transform3D>()
+            .reads::<NetworkRelevancy>()
+            .reads::<NetworkViewer>()
+            .after(PHYSICS_SYNC_SYSTEM),
+        )
+        .add_system(
+            System::new(
+                "isotope::update_time_dilation",
+                |compound, _context: &SystemContext| update_time_dilation(compound),
+            )
+            .writes::<TimeScale>()
+            .reads::<TimeDilation>()
+            .reads::<Transform3D>()
+            .reads::<BosonObject>()
+            .before(PRE_PHYSICS_SYSTEM),
+        )
+        .add_system(
+            System::new(
+                "isotope::update_animators",
+                |compound, context: &SystemContext| update_animators(compound, context.delta_t),
+            )
+            .writes::<Animator>()
+            .reads::<TimeScale>()
+            .after(PRE_PHYSICS_SYSTEM),
+        )
+        .add_system(
+            System::new(
+                "isotope::update_attachments",
+                |compound, _context: &SystemContext| update_attachments(compound),
+            )
+            .writes::<Transform3D>()
+            .reads::<AttachedTo>()
+            .reads::<Sockets>()
+            .after("isotope::update_animators")
+            .after("isotope::update_surfaces")
+            .after(PHYSICS_SYNC_SYSTEM)
+            .before("isotope::update_spatial_hash")
+            .before("isotope::update_perception")
+            .before("isotope::update_interactions")
+            .before("isotope::record_authority"),
+        )
+        .add_system(
+            System::new(POST_PHYSICS_SYSTEM, |compound, context: &SystemContext| {
+                context.lifecycle_hooks.fire(
+                    compound,
+                    &context.assets,
+                    LifecycleEvent::PostPhysics {
+                        world: context.world,
+                        delta_t: context.delta_t,
+                    },
+                );
+            })
+            .after("isotope::update_spatial_hash")
+            .after("isotope::update_perception")
+            .after("isotope::update_interactions")
+            .after("isotope::update_triggers")
+            .after("isotope::update_animators")
+            .after("isotope::update_attachments")
+            .after("isotope::record_authority")
+            .exclusive(),
+        );
+
+    #[cfg(feature = "voice")]
+    schedule.add_system(
+        System::new(
+            "isotope::update_voice",
+            |compound, _context: &SystemContext| crate::network::update_voice(compound),
+        )
+        .writes::<crate::network::VoiceChat>()
+        .writes::<NetworkState>()
+        .reads::<crate::ActionMap>()
+        .reads::<crate::network::VoiceSpeaker>()
+        .reads::<crate::network::VoiceListener>()
+        .reads::<Transform3D>()
+        .after("isotope::update_network")
+        .before(PRE_PHYSICS_SYSTEM),
+    );
+
+    schedule
+}
@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::{io::Cursor, path::Path};
 
 use anyhow::{Result, anyhow};
 use gpu_controller::{
@@ -7,12 +7,23 @@ use gpu_controller::{
     TextureViewDescriptor,
 };
 use image::ImageReader;
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
+use matter_vault::SharedMatter;
 
-use crate::asset_server::AssetServer;
+use crate::{TextureCompression, asset_server::AssetServer};
 
 const ROW_SIZE: u32 = std::mem::size_of::<f32>() as u32;
 
+// Label the fallback texture is shared under
+const FALLBACK_LABEL: &str = "isotope/fallback_texture";
+
+// Label the empty texture models bind without an override texture is shared under
+const EMPTY_LABEL: &str = "isotope/empty_texture";
+
+// Texels per side of the fallback checker
+const FALLBACK_SIZE: u32 = 8;
+const FALLBACK_COLORS: [[u8; 4]; 2] = [[255, 0, 255, 255], [0, 0, 0, 255]];
+
 pub(crate) struct IsotopeTexture {
     pub texture: Texture,
     pub view: TextureView,
@@ -63,14 +74,108 @@ impl IsotopeTexture {
         }
     }
 
+    /// Creates the magenta and black checker used in place of textures that failed to load
+    pub fn new_fallback(asset_server: &AssetServer) -> Self {
+        info!("Creating Fallback Texture");
+
+        let size = Extent3d {
+            width: FALLBACK_SIZE,
+            height: FALLBACK_SIZE,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = asset_server
+            .gpu_controller
+            .create_texture(&TextureDescriptor {
+                label: Some("Photon Fallback Texture"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Rgba8UnormSrgb,
+                usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+                view_formats: &[],
+            });
+
+        let texels = (0..FALLBACK_SIZE * FALLBACK_SIZE)
+            .flat_map(|index| {
+                let (x, y) = (index % FALLBACK_SIZE, index / FALLBACK_SIZE);
+                FALLBACK_COLORS[((x + y) % 2) as usize]
+            })
+            .collect::<Vec<u8>>();
+
+        asset_server.gpu_controller.write_texture(
+            &texture,
+            &texels,
+            TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(ROW_SIZE * FALLBACK_SIZE),
+                rows_per_image: Some(FALLBACK_SIZE),
+            },
+            size,
+        );
+
+        let view = texture.create_view(&TextureViewDescriptor::default());
+
+        // Repeated and unfiltered so the checker stays sharp on any mesh
+        let sampler = asset_server
+            .gpu_controller
+            .create_sampler(&SamplerDescriptor {
+                address_mode_u: AddressMode::Repeat,
+                address_mode_v: AddressMode::Repeat,
+                address_mode_w: AddressMode::Repeat,
+                mag_filter: FilterMode::Nearest,
+                min_filter: FilterMode::Nearest,
+                mipmap_filter: FilterMode::Nearest,
+                ..Default::default()
+            });
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    /// Returns the empty texture, created the first time it is needed
+    pub(crate) fn shared_empty(asset_server: &AssetServer) -> Result<SharedMatter<Self>> {
+        asset_server.asset_manager.share(EMPTY_LABEL).or_else(|_| {
+            asset_server
+                .asset_manager
+                .add(EMPTY_LABEL, Self::new_empty(asset_server))
+        })
+    }
+
+    /// Returns the fallback texture, created the first time it is needed
+    pub(crate) fn shared_fallback(asset_server: &AssetServer) -> Result<SharedMatter<Self>> {
+        asset_server
+            .asset_manager
+            .share(FALLBACK_LABEL)
+            .or_else(|_| {
+                asset_server
+                    .asset_manager
+                    .add(FALLBACK_LABEL, Self::new_fallback(asset_server))
+            })
+    }
+
     pub fn new_from_path<P>(path: P, asset_server: &AssetServer) -> Result<Self>
     where
         P: AsRef<Path>,
     {
         info!("Loading Texture: {:#?}", path.as_ref());
 
-        let img_reader = ImageReader::open(path.as_ref())?;
-        let dimensions = img_reader.into_dimensions()?;
+        let import_settings = asset_server.import_settings(path.as_ref()).texture;
+        if import_settings.compression != TextureCompression::None {
+            warn!(
+                "Texture compression is not supported yet, importing {:#?} uncompressed",
+                path.as_ref()
+            );
+        }
+
+        let bytes = asset_server.read_asset(path.as_ref())?;
+        let dimensions = ImageReader::new(Cursor::new(&bytes))
+            .with_guessed_format()?
+            .into_dimensions()?;
 
         let size = Extent3d {
             width: dimensions.0,
@@ -93,7 +198,11 @@ impl IsotopeTexture {
                 mip_level_count: 1,
                 sample_count: 1,
                 dimension: TextureDimension::D2,
-                format: TextureFormat::Rgba8UnormSrgb,
+                format: if import_settings.srgb {
+                    TextureFormat::Rgba8UnormSrgb
+                } else {
+                    TextureFormat::Rgba8Unorm
+                },
                 usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
                 view_formats: &[],
             });
@@ -113,12 +222,12 @@ impl IsotopeTexture {
                 ..Default::default()
             });
 
-        if let Some(path) = path.as_ref().to_str() {
+        {
             let texture_clone = texture.clone();
-            let path_clone = path.to_string();
             let gpu_controller_clone = asset_server.gpu_controller.clone();
-            std::thread::spawn(move || {
-                _ = ImageReader::open(&path_clone)
+            gluon::spawn(move || {
+                _ = ImageReader::new(Cursor::new(bytes))
+                    .with_guessed_format()
                     .and_then(|img| {
                         _ = img
                             .decode()
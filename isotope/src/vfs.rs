@@ -0,0 +1,366 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+use anyhow::{Result, anyhow};
+use log::{debug, error, info};
+
+/// Separates the name of a mount from the path within it (`game://models/crate.obj`)
+const SCHEME_SEPARATOR: &str = "://";
+
+const PACK_MAGIC: &[u8; 8] = b"ISOPACK\0";
+const PACK_VERSION: u32 = 1;
+
+/// A source of asset files that can be mounted in the `Vfs`.
+///
+/// Paths given to a mount are relative to the prefix it is mounted under
+/// and always `/` separated.
+pub trait VfsMount: Send + Sync {
+    fn read(&self, path: &str) -> Result<Vec<u8>>;
+
+    fn exists(&self, path: &str) -> bool;
+
+    /// Writes a file, mounts are read only unless they implement this
+    fn write(&self, path: &str, data: &[u8]) -> Result<()> {
+        _ = data;
+        Err(anyhow!("Cannot write {}, the mount is read only", path))
+    }
+}
+
+/// Files of a directory on disk, the only mount that can be written to
+pub struct DirectoryMount {
+    root: PathBuf,
+}
+
+impl DirectoryMount {
+    pub fn new<P>(root: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl VfsMount for DirectoryMount {
+    fn read(&self, path: &str) -> Result<Vec<u8>> {
+        Ok(fs::read(self.root.join(path))?)
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.root.join(path).exists()
+    }
+
+    fn write(&self, path: &str, data: &[u8]) -> Result<()> {
+        Ok(fs::write(self.root.join(path), data)?)
+    }
+}
+
+/// Files compiled into the executable, for builds that can't read from disk.
+///
+/// # Example
+/// ```ignore
+/// let embedded = EmbeddedMount::new()
+///     .file("models/crate.obj", include_bytes!("../assets/models/crate.obj"))
+///     .file("models/crate.mtl", include_bytes!("../assets/models/crate.mtl"));
+///
+/// assets.mount("game://", embedded)?;
+/// ```
+#[derive(Default)]
+pub struct EmbeddedMount {
+    files: HashMap<String, &'static [u8]>,
+}
+
+impl EmbeddedMount {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn file(mut self, path: impl Into<String>, data: &'static [u8]) -> Self {
+        self.files.insert(path.into(), data);
+        self
+    }
+}
+
+impl VfsMount for EmbeddedMount {
+    fn read(&self, path: &str) -> Result<Vec<u8>> {
+        self.files
+            .get(path)
+            .map(|data| data.to_vec())
+            .ok_or_else(|| anyhow!("{} is not embedded", path))
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.files.contains_key(path)
+    }
+}
+
+/// Files packed into a single archive, for shipped builds.
+///
+/// Archives are written with `PackMount::write_pack` and read fully into
+/// memory when they are mounted.
+pub struct PackMount {
+    files: HashMap<String, Vec<u8>>,
+}
+
+impl PackMount {
+    /// Reads an archive from disk
+    pub fn open<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        info!("Opening asset pack: {:#?}", path.as_ref());
+        Self::from_bytes(&fs::read(path.as_ref())?)
+    }
+
+    /// Reads an archive from memory, such as one included with `include_bytes!`
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self> {
+        let mut magic = [0; 8];
+        bytes.read_exact(&mut magic)?;
+        if &magic != PACK_MAGIC {
+            return Err(anyhow!("Not an asset pack"));
+        }
+
+        let version = read_u32(&mut bytes)?;
+        if version > PACK_VERSION {
+            return Err(anyhow!(
+                "Asset pack has version {}, newer than {}",
+                version,
+                PACK_VERSION
+            ));
+        }
+
+        let count = read_u32(&mut bytes)?;
+        let mut files = HashMap::with_capacity(count as usize);
+        for _ in 0..count {
+            let path_length = read_u32(&mut bytes)? as usize;
+            let path = String::from_utf8(take(&mut bytes, path_length)?.to_vec())?;
+
+            let data_length = read_u64(&mut bytes)? as usize;
+            let data = take(&mut bytes, data_length)?.to_vec();
+
+            files.insert(path, data);
+        }
+
+        Ok(Self { files })
+    }
+
+    /// Writes files into an archive that can be mounted with `PackMount`.
+    ///
+    /// # Arguments
+    /// * `files` - Paths of the files within the archive and their contents
+    /// * `writer` - Where to write the archive to
+    pub fn write_pack<W>(files: &[(String, Vec<u8>)], mut writer: W) -> Result<()>
+    where
+        W: Write,
+    {
+        writer.write_all(PACK_MAGIC)?;
+        writer.write_all(&PACK_VERSION.to_le_bytes())?;
+        writer.write_all(&(files.len() as u32).to_le_bytes())?;
+
+        for (path, data) in files {
+            writer.write_all(&(path.len() as u32).to_le_bytes())?;
+            writer.write_all(path.as_bytes())?;
+            writer.write_all(&(data.len() as u64).to_le_bytes())?;
+            writer.write_all(data)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl VfsMount for PackMount {
+    fn read(&self, path: &str) -> Result<Vec<u8>> {
+        self.files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| anyhow!("{} is not in the pack", path))
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.files.contains_key(path)
+    }
+}
+
+fn take<'a>(bytes: &mut &'a [u8], length: usize) -> Result<&'a [u8]> {
+    if bytes.len() < length {
+        return Err(anyhow!("Asset pack is truncated"));
+    }
+
+    let (taken, rest) = bytes.split_at(length);
+    *bytes = rest;
+    Ok(taken)
+}
+
+fn read_u32(bytes: &mut &[u8]) -> Result<u32> {
+    let mut value = [0; 4];
+    bytes.read_exact(&mut value)?;
+    Ok(u32::from_le_bytes(value))
+}
+
+fn read_u64(bytes: &mut &[u8]) -> Result<u64> {
+    let mut value = [0; 8];
+    bytes.read_exact(&mut value)?;
+    Ok(u64::from_le_bytes(value))
+}
+
+struct Mount {
+    prefix: String,
+    source: Box<dyn VfsMount>,
+}
+
+/// The file system every asset is loaded through.
+///
+/// Sources are mounted under prefixes like `game://` or `game://models/`,
+/// a path is read from the mount with the longest prefix that has the file,
+/// and mounts added later shadow earlier ones with the same prefix. That
+/// lets the same asset paths load from a directory during development and
+/// from a pack or embedded files in shipped builds.
+///
+/// Paths without a `scheme://` prefix are read from disk as they are.
+#[derive(Default)]
+pub struct Vfs {
+    mounts: RwLock<Vec<Mount>>,
+}
+
+impl Vfs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mounts a source under a prefix, which must start with a `scheme://`
+    pub fn mount<M>(&self, prefix: &str, source: M) -> Result<()>
+    where
+        M: VfsMount + 'static,
+    {
+        if !prefix.contains(SCHEME_SEPARATOR) {
+            return Err(anyhow!("Mount prefix {} has no scheme://", prefix));
+        }
+
+        let mut mounts = self
+            .mounts
+            .write()
+            .map_err(|err| anyhow!("Failed to mount {}: {}", prefix, err))?;
+
+        mounts.push(Mount {
+            prefix: prefix.to_string(),
+            source: Box::new(source),
+        });
+
+        info!("Mounted {}", prefix);
+        Ok(())
+    }
+
+    /// Removes the mount added last under a prefix
+    pub fn unmount(&self, prefix: &str) -> bool {
+        match self.mounts.write() {
+            Ok(mut mounts) => match mounts.iter().rposition(|mount| mount.prefix == prefix) {
+                Some(index) => {
+                    mounts.remove(index);
+                    true
+                }
+                None => false,
+            },
+            Err(err) => {
+                error!("Failed to unmount {}: {}", prefix, err);
+                false
+            }
+        }
+    }
+
+    /// Reads a file, see `Vfs` for how paths are resolved
+    pub fn read<P>(&self, path: P) -> Result<Vec<u8>>
+    where
+        P: AsRef<Path>,
+    {
+        let path = normalize(path.as_ref());
+        if !path.contains(SCHEME_SEPARATOR) {
+            return Ok(fs::read(&path)?);
+        }
+
+        self.with_mount(&path, |source, relative| {
+            source.exists(relative).then(|| source.read(relative))
+        })?
+        .unwrap_or_else(|| Err(anyhow!("No mount has {}", path)))
+    }
+
+    pub fn read_to_string<P>(&self, path: P) -> Result<String>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(String::from_utf8(self.read(path)?)?)
+    }
+
+    pub fn exists<P>(&self, path: P) -> bool
+    where
+        P: AsRef<Path>,
+    {
+        let path = normalize(path.as_ref());
+        if !path.contains(SCHEME_SEPARATOR) {
+            return Path::new(&path).exists();
+        }
+
+        self.with_mount(&path, |source, relative| {
+            source.exists(relative).then_some(())
+        })
+        .is_ok_and(|found| found.is_some())
+    }
+
+    /// Writes a file to the mount with the longest prefix of the path
+    pub fn write<P>(&self, path: P, data: &[u8]) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let path = normalize(path.as_ref());
+        if !path.contains(SCHEME_SEPARATOR) {
+            return Ok(fs::write(&path, data)?);
+        }
+
+        self.with_mount(&path, |source, relative| Some(source.write(relative, data)))?
+            .unwrap_or_else(|| Err(anyhow!("No mount for {}", path)))
+    }
+
+    /// Calls the callback with the mounts a path is under, longest prefix
+    /// first and then most recent first, until it returns `Some`
+    fn with_mount<F, R>(&self, path: &str, mut callback: F) -> Result<Option<R>>
+    where
+        F: FnMut(&dyn VfsMount, &str) -> Option<R>,
+    {
+        let mounts = self
+            .mounts
+            .read()
+            .map_err(|err| anyhow!("Failed to read mounts: {}", err))?;
+
+        let mut candidates = mounts
+            .iter()
+            .enumerate()
+            .filter(|(_, mount)| path.starts_with(&mount.prefix))
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|(index, mount)| {
+            (
+                std::cmp::Reverse(mount.prefix.len()),
+                std::cmp::Reverse(*index),
+            )
+        });
+
+        for (_, mount) in candidates {
+            let relative = path[mount.prefix.len()..].trim_start_matches('/');
+            if let Some(result) = callback(mount.source.as_ref(), relative) {
+                debug!("Resolved {} in {}", path, mount.prefix);
+                return Ok(Some(result));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// Joining paths can mix in the separators of the platform, mounts only use `/`
+fn normalize(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
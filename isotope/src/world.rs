@@ -0,0 +1,419 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Instant,
+};
+
+use anyhow::{Result, anyhow};
+use boson::{BOSON_DEFAULT_TICKRATE, Boson};
+use compound::Compound;
+use log::{error, info};
+
+use crate::PhysicsAuthority;
+
+/// Identifies a world of an Isotope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WorldId(u32);
+
+impl WorldId {
+    /// The world Isotope starts with, passed to `IsotopeState::init`
+    pub const MAIN: WorldId = WorldId(0);
+}
+
+/// How often a world is simulated while it is not the active world.
+///
+/// The active world is always simulated every tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WorldCadence {
+    /// Time stops for the world until it is active again
+    #[default]
+    Paused,
+    /// The world is simulated once every `n` ticks, with larger steps so
+    /// time passes at the same speed
+    Reduced(u32),
+    /// The world is simulated as often as the active world
+    Full,
+}
+
+/// Resource that pauses the game in the world it is inserted into.
+///
+/// Paused worlds don't simulate physics or run systems, and the active world
+/// only runs `IsotopeState::update` so menus and photo mode keep working.
+/// Time spent paused is not counted towards the next step of the world.
+///
+/// # Example
+/// ```ignore
+/// // Open the pause menu
+/// compound.insert_resource(GamePaused);
+///
+/// // In IsotopeState::update
+/// if ecs.has_resource::<GamePaused>() {
+///     return;
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GamePaused;
+
+/// Resource with how fast time passes in the world it is inserted into.
+///
+/// Every world starts with one. Its scale speeds up or slows down the whole
+/// world, the physics, systems, animators and particles, on top of the
+/// `TimeDilation` regions inside it. `IsotopeState::update` is still passed
+/// real time so menus keep working in slow motion.
+///
+/// # Example
+/// ```ignore
+/// // Bullet time
+/// compound.resource_mut(|time: &mut Time| time.scale = 0.2);
+///
+/// // In a system, how long the world was simulated for
+/// let elapsed = compound.resource(|time: &Time| time.elapsed()).unwrap_or_default();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Time {
+    /// How fast time passes, 1 is normal speed and 0 stops time without pausing the game
+    pub scale: f32,
+
+    delta_t: f32,
+    unscaled_delta_t: f32,
+    elapsed: f32,
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            delta_t: 0.0,
+            unscaled_delta_t: 0.0,
+            elapsed: 0.0,
+        }
+    }
+}
+
+impl Time {
+    /// Returns the seconds of game time the last tick of the world covered,
+    /// the same `SystemContext::delta_t` systems are passed
+    pub fn delta_t(&self) -> f32 {
+        self.delta_t
+    }
+
+    /// Returns the real seconds the last tick of the world covered
+    pub fn unscaled_delta_t(&self) -> f32 {
+        self.unscaled_delta_t
+    }
+
+    /// Returns the seconds of game time the world has been simulated for
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    /// Counts a tick of the world
+    ///
+    /// # Returns
+    /// The seconds of game time the tick covers
+    fn advance(&mut self, unscaled_delta_t: f32) -> f32 {
+        self.unscaled_delta_t = unscaled_delta_t;
+        self.delta_t = unscaled_delta_t * self.scale.max(0.0);
+        self.elapsed += self.delta_t;
+
+        self.delta_t
+    }
+}
+
+/// An independent set of entities with its own physics.
+pub(crate) struct World {
+    pub(crate) compound: Arc<Compound>,
+    pub(crate) boson: Arc<RwLock<Boson>>,
+    cadence: WorldCadence,
+    ticks_since_update: u32,
+    last_update: Instant,
+    // Whether the world had `GamePaused` when it last ticked
+    game_paused: bool,
+    // Whether the physics of the world was simulated by a host when it last ticked
+    remote_physics: bool,
+    // The scale of `Time` when the world last ticked
+    time_scale: f32,
+}
+
+impl World {
+    fn new() -> Self {
+        let compound = Compound::new();
+        compound.insert_resource(Time::default());
+
+        Self {
+            compound: Arc::new(compound),
+            boson: Arc::new(RwLock::new(Boson::new())),
+            cadence: WorldCadence::default(),
+            ticks_since_update: 0,
+            last_update: Instant::now(),
+            game_paused: false,
+            remote_physics: false,
+            time_scale: 1.0,
+        }
+    }
+
+    /// Makes the physics of the world match how often it is simulated
+    fn apply_cadence(&self, active: bool) {
+        if self.game_paused || self.remote_physics {
+            self.suspend();
+            return;
+        }
+
+        let cadence = if active {
+            WorldCadence::Full
+        } else {
+            self.cadence
+        };
+
+        match self.boson.read() {
+            Ok(boson) => match cadence {
+                WorldCadence::Paused => boson.set_paused(true),
+                WorldCadence::Reduced(ticks) => {
+                    boson.set_tickrate(BOSON_DEFAULT_TICKRATE * ticks.max(1));
+                    boson.set_paused(false);
+                }
+                WorldCadence::Full => {
+                    boson.set_tickrate(BOSON_DEFAULT_TICKRATE);
+                    boson.set_paused(false);
+                }
+            },
+            Err(err) => error!("Failed to update world physics: {}", err),
+        }
+    }
+
+    /// Pauses the physics of the world regardless of its cadence
+    fn suspend(&self) {
+        match self.boson.read() {
+            Ok(boson) => boson.set_paused(true),
+            Err(err) => error!("Failed to suspend world physics: {}", err),
+        }
+    }
+}
+
+/// A world that is due to be simulated this tick
+pub(crate) struct WorldTick {
+    pub(crate) id: WorldId,
+    pub(crate) compound: Arc<Compound>,
+    pub(crate) boson: Arc<RwLock<Boson>>,
+    pub(crate) active: bool,
+    /// Whether the game is paused in the world, only the active world ticks while paused
+    pub(crate) paused: bool,
+    /// Seconds of game time since the world was last simulated
+    pub(crate) delta_t: f32,
+}
+
+/// Every world of an Isotope and which of them is active.
+pub(crate) struct Worlds {
+    worlds: HashMap<WorldId, World>,
+    active: WorldId,
+    next_id: u32,
+    // Physics of every world is paused while the application is in the background
+    suspended: bool,
+}
+
+impl Worlds {
+    /// Creates the worlds with only the main world, which is active
+    pub(crate) fn new() -> Self {
+        let main = World::new();
+        main.apply_cadence(true);
+
+        Self {
+            worlds: HashMap::from([(WorldId::MAIN, main)]),
+            active: WorldId::MAIN,
+            next_id: 1,
+            suspended: false,
+        }
+    }
+
+    pub(crate) fn create(&mut self) -> WorldId {
+        let id = WorldId(self.next_id);
+        self.next_id += 1;
+
+        let world = World::new();
+        world.apply_cadence(false);
+        if self.suspended {
+            world.suspend();
+        }
+        self.worlds.insert(id, world);
+
+        info!("Created world {:?}", id);
+        id
+    }
+
+    pub(crate) fn remove(&mut self, id: WorldId) -> Result<()> {
+        if id == self.active {
+            return Err(anyhow!("Cannot remove the active world {:?}", id));
+        }
+
+        self.worlds
+            .remove(&id)
+            .map(|_| info!("Removed world {:?}", id))
+            .ok_or_else(|| anyhow!("World {:?} does not exist", id))
+    }
+
+    pub(crate) fn get(&self, id: WorldId) -> Result<&World> {
+        self.worlds
+            .get(&id)
+            .ok_or_else(|| anyhow!("World {:?} does not exist", id))
+    }
+
+    pub(crate) fn active_id(&self) -> WorldId {
+        self.active
+    }
+
+    pub(crate) fn active(&self) -> &World {
+        // The active world can never be removed
+        &self.worlds[&self.active]
+    }
+
+    pub(crate) fn set_active(&mut self, id: WorldId) -> Result<()> {
+        let world = self.get(id)?;
+
+        if !self.suspended {
+            world.apply_cadence(true);
+
+            if let Ok(previous) = self.get(self.active)
+                && self.active != id
+            {
+                previous.apply_cadence(false);
+            }
+        }
+
+        info!("Switched to world {:?}", id);
+        self.active = id;
+        Ok(())
+    }
+
+    pub(crate) fn set_cadence(&mut self, id: WorldId, cadence: WorldCadence) -> Result<()> {
+        let active = self.active == id;
+        let world = self
+            .worlds
+            .get_mut(&id)
+            .ok_or_else(|| anyhow!("World {:?} does not exist", id))?;
+
+        world.cadence = cadence;
+        if !self.suspended {
+            world.apply_cadence(active);
+        }
+        Ok(())
+    }
+
+    /// Pauses the physics of every world, or restores each world to its cadence
+    pub(crate) fn set_suspended(&mut self, suspended: bool) {
+        if self.suspended == suspended {
+            return;
+        }
+
+        self.suspended = suspended;
+        for (id, world) in self.worlds.iter() {
+            if suspended {
+                world.suspend();
+            } else {
+                world.apply_cadence(*id == self.active);
+            }
+        }
+    }
+
+    /// Returns every world to simulate this tick and counts the tick for the others
+    pub(crate) fn tick(&mut self) -> Vec<WorldTick> {
+        let now = Instant::now();
+        let active = self.active;
+        let suspended = self.suspended;
+
+        self.worlds
+            .iter_mut()
+            .filter_map(|(id, world)| {
+                world.ticks_since_update += 1;
+
+                let paused = world.compound.has_resource::<GamePaused>();
+                if paused != world.game_paused {
+                    info!(
+                        "{} world {:?}",
+                        if paused { "Paused" } else { "Unpaused" },
+                        id
+                    );
+
+                    world.game_paused = paused;
+                    if !suspended {
+                        world.apply_cadence(*id == active);
+                    }
+                }
+
+                // Clients only show what the host simulated
+                let remote_physics = world
+                    .compound
+                    .resource::<PhysicsAuthority, _>(|authority| !authority.is_authoritative())
+                    .unwrap_or(false);
+                if remote_physics != world.remote_physics {
+                    world.remote_physics = remote_physics;
+                    if !suspended {
+                        world.apply_cadence(*id == active);
+                    }
+                }
+
+                // Physics keeps its own time, so it is told when the world slows down
+                let time_scale = world
+                    .compound
+                    .resource::<Time, _>(|time| time.scale)
+                    .unwrap_or(1.0);
+                if time_scale != world.time_scale {
+                    world.time_scale = time_scale;
+                    match world.boson.read() {
+                        Ok(boson) => boson.set_time_scale(time_scale.max(0.0) as f64),
+                        Err(err) => error!("Failed to scale world physics: {}", err),
+                    }
+                }
+
+                let due = *id == active
+                    || (!paused
+                        && match world.cadence {
+                            WorldCadence::Paused => false,
+                            WorldCadence::Reduced(ticks) => world.ticks_since_update >= ticks,
+                            WorldCadence::Full => true,
+                        });
+
+                // Paused worlds don't count the time spent paused
+                if !due {
+                    if paused || world.cadence == WorldCadence::Paused {
+                        world.last_update = now;
+                    }
+
+                    return None;
+                }
+
+                let delta_t = now.duration_since(world.last_update).as_secs_f32();
+                let delta_t = world
+                    .compound
+                    .resource_mut::<Time, _>(|time| time.advance(delta_t))
+                    .unwrap_or(delta_t);
+                world.ticks_since_update = 0;
+                world.last_update = now;
+
+                Some(WorldTick {
+                    id: *id,
+                    compound: world.compound.clone(),
+                    boson: world.boson.clone(),
+                    active: *id == active,
+                    paused,
+                    delta_t,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the physics engine of every world
+    pub(crate) fn bosons(&self) -> Vec<Arc<RwLock<Boson>>> {
+        self.worlds
+            .values()
+            .map(|world| world.boson.clone())
+            .collect()
+    }
+
+    /// Returns the entities of every world
+    pub(crate) fn compounds(&self) -> Vec<Arc<Compound>> {
+        self.worlds
+            .values()
+            .map(|world| world.compound.clone())
+            .collect()
+    }
+}
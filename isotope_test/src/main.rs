@@ -66,7 +66,7 @@ impl IsotopeState for GameState {
         ),));
 
         ecs.spawn((
-            Camera::perspective_3d_default(assets),
+            Camera3DDescriptor::default(),
             Transform3D::new(
                 Vector3::new(0.0, 0.0, 10.0),
                 Quaternion::from_axis_angle(Vector3::unit_y(), Deg(180.0)),
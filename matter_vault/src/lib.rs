@@ -54,6 +54,22 @@ impl<T> Clone for SharedMatter<T> {
     }
 }
 
+/// How many values of a type a vault holds and how many handles share them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatterSharing {
+    /// Values stored in the vault
+    pub unique: usize,
+    /// Handles to the values held outside the vault
+    pub handles: usize,
+}
+
+impl MatterSharing {
+    /// Returns how many handles reuse a value instead of holding their own copy
+    pub fn reused(&self) -> usize {
+        self.handles.saturating_sub(self.unique)
+    }
+}
+
 pub struct MatterVault {
     matter: RwLock<HashMap<TypeId, HashMap<String, Box<dyn Any>>>>,
 }
@@ -156,6 +172,31 @@ impl MatterVault {
 
         Ok(shared_matter)
     }
+
+    /// Counts the values of a type and the handles sharing them, such as to
+    /// check that models loading the same files share their GPU resources.
+    ///
+    /// # Returns
+    /// The sharing of the values of the type, empty if none were added
+    pub fn sharing<T: 'static>(&self) -> MatterSharing {
+        let map = match self.matter.read() {
+            Ok(map) => map,
+            Err(poisoned) => {
+                warn!("Matter Manager has been poisoned, recovering...");
+                poisoned.into_inner()
+            }
+        };
+
+        map.get(&TypeId::of::<T>())
+            .into_iter()
+            .flat_map(|matter| matter.values())
+            .filter_map(|matter| matter.downcast_ref::<SharedMatter<T>>())
+            .fold(MatterSharing::default(), |sharing, matter| MatterSharing {
+                unique: sharing.unique + 1,
+                // The vault holds one of the references itself
+                handles: sharing.handles + Arc::strong_count(&matter.0) - 1,
+            })
+    }
 }
 
 #[cfg(test)]
@@ -221,4 +262,25 @@ mod test {
             println!("Shared Second Number: {}", number);
         });
     }
+
+    #[test]
+    fn test_matter_vault_sharing() {
+        let matter_vault = MatterVault::new();
+
+        assert_eq!(matter_vault.sharing::<u32>(), MatterSharing::default());
+
+        let first_number = matter_vault.add("first_number", 10u32).unwrap();
+        let _second_number = matter_vault.add("second_number", 11u32).unwrap();
+        let shared_first_number: SharedMatter<u32> = matter_vault.share("first_number").unwrap();
+        _ = matter_vault.add("name", String::from("matter"));
+
+        let sharing = matter_vault.sharing::<u32>();
+        assert_eq!(sharing.unique, 2);
+        assert_eq!(sharing.handles, 3);
+        assert_eq!(sharing.reused(), 1);
+
+        drop(first_number);
+        drop(shared_first_number);
+        assert_eq!(matter_vault.sharing::<u32>().handles, 1);
+    }
 }
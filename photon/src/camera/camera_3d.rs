@@ -1,12 +1,19 @@
 use std::sync::Arc;
 
-use cgmath::{Deg, InnerSpace, Matrix4, Point3, Vector3, perspective};
+use cgmath::{Deg, InnerSpace, Matrix4, Point3, SquareMatrix, Vector3, perspective};
 use gpu_controller::{
-    BindGroup, BindGroupDescriptor, BindGroupEntry, Buffer, BufferInitDescriptor, BufferUsages,
+    BindGroup, BindGroupDescriptor, BindGroupEntry, Buffer, BufferDescriptor, BufferUsages, Color,
     GpuController,
 };
 
-use super::{CAMERA_BIND_GROUP_LAYOUT_DESCRIPTOR, OPENGL_TO_WGPU_MATIX, PhotonCamera};
+use crate::renderer::{
+    frame::{FRAME_CONSTANTS_OFFSET, FrameConstants},
+    lens::{DepthOfField, MotionBlur},
+};
+
+use super::{
+    CAMERA_BIND_GROUP_LAYOUT_DESCRIPTOR, OPENGL_TO_WGPU_MATIX, PhotonCamera, ViewModelProjection,
+};
 
 // Clamping constants
 const FOVY_CLAMP: (f32, f32) = (0.1, 179.9);
@@ -17,8 +24,16 @@ const MAX_UP_DOT: f32 = 0.99;
 pub struct PerspectiveCam3DUniform {
     view_position: [f32; 4],
     view_projection: [[f32; 4]; 4],
+    // The view projection of the last frame, for motion vectors
+    previous_view_projection: [[f32; 4]; 4],
+    // The view projection of the viewmodel, the same as the camera's without one
+    view_model_view_projection: [[f32; 4]; 4],
+    inverse_view_projection: [[f32; 4]; 4],
 }
 
+// The frame constants follow the matrices in the same buffer
+const _: () = assert!(size_of::<PerspectiveCam3DUniform>() as u64 == FRAME_CONSTANTS_OFFSET);
+
 pub struct PerspectiveCamera3D {
     eye: Point3<f32>,
     target: Vector3<f32>,
@@ -29,6 +44,12 @@ pub struct PerspectiveCamera3D {
     znear: f32,
     zfar: f32,
 
+    clear_color: Color,
+    clears: bool,
+    depth_of_field: Option<DepthOfField>,
+    motion_blur: Option<MotionBlur>,
+    view_model: Option<ViewModelProjection>,
+
     camera_uniform: PerspectiveCam3DUniform,
     buffer: Buffer,
     gpu_controller: Arc<GpuController>,
@@ -55,13 +76,18 @@ impl PerspectiveCamera3D {
         let camera_uniform = PerspectiveCam3DUniform {
             view_position: eye.to_homogeneous().into(),
             view_projection: view_proj.into(),
+            previous_view_projection: view_proj.into(),
+            view_model_view_projection: view_proj.into(),
+            inverse_view_projection: view_proj.invert().unwrap_or(Matrix4::identity()).into(),
         };
 
-        let buffer = gpu_controller.create_buffer_init(&BufferInitDescriptor {
+        let buffer = gpu_controller.create_buffer(&BufferDescriptor {
             label: Some("Perspective 3D Camera Buffer"),
-            contents: bytemuck::cast_slice(&[camera_uniform]),
+            size: FRAME_CONSTANTS_OFFSET + size_of::<FrameConstants>() as u64,
             usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
         });
+        gpu_controller.write_buffer(&buffer, 0, bytemuck::cast_slice(&[camera_uniform]));
 
         let bind_group_layout =
             gpu_controller.create_bind_group_layout(&CAMERA_BIND_GROUP_LAYOUT_DESCRIPTOR);
@@ -83,6 +109,11 @@ impl PerspectiveCamera3D {
             fovy,
             znear,
             zfar,
+            clear_color: Color::BLACK,
+            clears: true,
+            depth_of_field: None,
+            motion_blur: None,
+            view_model: None,
             camera_uniform,
             buffer,
             gpu_controller,
@@ -97,9 +128,26 @@ impl PerspectiveCamera3D {
 
         let view_proj = OPENGL_TO_WGPU_MATIX * proj * view;
 
+        let view_model_view_proj = match self.view_model {
+            Some(view_model) => {
+                let proj = perspective(
+                    Deg(view_model.fovy.clamp(FOVY_CLAMP.0, FOVY_CLAMP.1)),
+                    self.aspect,
+                    view_model.near,
+                    view_model.far,
+                );
+
+                OPENGL_TO_WGPU_MATIX * proj * view
+            }
+            None => view_proj,
+        };
+
         self.camera_uniform = PerspectiveCam3DUniform {
             view_position: self.eye.to_homogeneous().into(),
             view_projection: view_proj.into(),
+            previous_view_projection: self.camera_uniform.previous_view_projection,
+            view_model_view_projection: view_model_view_proj.into(),
+            inverse_view_projection: view_proj.invert().unwrap_or(Matrix4::identity()).into(),
         };
 
         self.gpu_controller.write_buffer(
@@ -198,6 +246,83 @@ impl PerspectiveCamera3D {
         self.update();
     }
 
+    /// Provides mutable access to the color the camera clears its output to.
+    ///
+    /// # Arguments
+    /// * `callback` - A closure that receives a mutable reference to the clear color
+    pub fn clear_color<F>(&mut self, callback: F)
+    where
+        F: FnOnce(&mut Color),
+    {
+        callback(&mut self.clear_color);
+    }
+
+    /// Provides mutable access to whether the camera clears its output before rendering.
+    ///
+    /// Cameras that don't clear draw over the output of the cameras rendered before them.
+    ///
+    /// # Arguments
+    /// * `callback` - A closure that receives a mutable reference to the clear flag
+    pub fn clears<F>(&mut self, callback: F)
+    where
+        F: FnOnce(&mut bool),
+    {
+        callback(&mut self.clears);
+    }
+
+    /// Provides mutable access to the depth of field of the camera, `None` keeps everything in focus.
+    ///
+    /// # Arguments
+    /// * `callback` - A closure that receives a mutable reference to the depth of field
+    pub fn depth_of_field<F>(&mut self, callback: F)
+    where
+        F: FnOnce(&mut Option<DepthOfField>),
+    {
+        callback(&mut self.depth_of_field);
+    }
+
+    /// Provides mutable access to the motion blur of the camera, `None` turns it off.
+    ///
+    /// # Arguments
+    /// * `callback` - A closure that receives a mutable reference to the motion blur
+    pub fn motion_blur<F>(&mut self, callback: F)
+    where
+        F: FnOnce(&mut Option<MotionBlur>),
+    {
+        callback(&mut self.motion_blur);
+    }
+
+    /// Provides mutable access to the projection of the camera's viewmodel,
+    /// `None` draws no viewmodel.
+    ///
+    /// # Arguments
+    /// * `callback` - A closure that receives a mutable reference to the viewmodel projection
+    pub fn view_model<F>(&mut self, callback: F)
+    where
+        F: FnOnce(&mut Option<ViewModelProjection>),
+    {
+        callback(&mut self.view_model);
+        self.update();
+    }
+
+    /// Ends the frame for the motion vectors of the camera.
+    ///
+    /// Call once every camera rendered the frame, the view projection of the
+    /// frame becomes the one motion is measured from in the next.
+    pub fn end_frame(&mut self) {
+        if self.camera_uniform.previous_view_projection == self.camera_uniform.view_projection {
+            return;
+        }
+
+        self.camera_uniform.previous_view_projection = self.camera_uniform.view_projection;
+
+        self.gpu_controller.write_buffer(
+            &self.buffer,
+            0,
+            bytemuck::cast_slice(&[self.camera_uniform]),
+        );
+    }
+
     /// Provides mutable access to all camera parameters at once.
     ///
     /// # Arguments
@@ -232,4 +357,32 @@ impl PhotonCamera for PerspectiveCamera3D {
     fn bind_group(&self) -> &BindGroup {
         &self.bind_group
     }
+
+    fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    fn clear_color(&self) -> Color {
+        self.clear_color
+    }
+
+    fn clears(&self) -> bool {
+        self.clears
+    }
+
+    fn depth_of_field(&self) -> Option<DepthOfField> {
+        self.depth_of_field
+    }
+
+    fn motion_blur(&self) -> Option<MotionBlur> {
+        self.motion_blur
+    }
+
+    fn view_model(&self) -> Option<ViewModelProjection> {
+        self.view_model
+    }
+
+    fn position(&self) -> [f32; 3] {
+        self.eye.into()
+    }
 }
@@ -1,10 +1,12 @@
 pub use camera_3d::PerspectiveCamera3D;
 use cgmath::Matrix4;
 use gpu_controller::{
-    BindGroup, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType, BufferBindingType,
-    ShaderStages,
+    BindGroup, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType, Buffer,
+    BufferBindingType, Color, ShaderStages,
 };
 
+use crate::renderer::lens::{DepthOfField, MotionBlur};
+
 mod camera_3d;
 
 pub const OPENGL_TO_WGPU_MATIX: Matrix4<f32> = Matrix4::new(
@@ -16,7 +18,7 @@ pub const CAMERA_BIND_GROUP_LAYOUT_DESCRIPTOR: BindGroupLayoutDescriptor =
         label: Some("Camera Bind Group Layout Descriptor"),
         entries: &[BindGroupLayoutEntry {
             binding: 0,
-            visibility: ShaderStages::VERTEX_FRAGMENT,
+            visibility: ShaderStages::VERTEX_FRAGMENT.union(ShaderStages::COMPUTE),
             ty: BindingType::Buffer {
                 ty: BufferBindingType::Uniform,
                 has_dynamic_offset: false,
@@ -28,4 +30,65 @@ pub const CAMERA_BIND_GROUP_LAYOUT_DESCRIPTOR: BindGroupLayoutDescriptor =
 
 pub trait PhotonCamera {
     fn bind_group(&self) -> &BindGroup;
+
+    // The uniform buffer bound at binding 0 of the bind group, the renderer
+    // writes the frame constants into it at `FRAME_CONSTANTS_OFFSET`
+    fn buffer(&self) -> &Buffer;
+
+    // Color the output is cleared to where no geometry was drawn
+    fn clear_color(&self) -> Color {
+        Color::BLACK
+    }
+
+    // Whether the output is cleared before rendering, overlay cameras keep
+    // what the cameras before them drew
+    fn clears(&self) -> bool {
+        true
+    }
+
+    // Blurs what is out of focus at the end of the frame
+    fn depth_of_field(&self) -> Option<DepthOfField> {
+        None
+    }
+
+    // Blurs what moved since the last frame at the end of the frame
+    fn motion_blur(&self) -> Option<MotionBlur> {
+        None
+    }
+
+    // Projection of the geometry drawn in `GeometryPass::ViewModel`, the
+    // pass only runs for cameras with one
+    fn view_model(&self) -> Option<ViewModelProjection> {
+        None
+    }
+
+    // Where the camera is in world space, blended draws are sorted back to front from it
+    fn position(&self) -> [f32; 3] {
+        [0.0; 3]
+    }
+}
+
+/// Projection a first person camera draws its viewmodel with, the weapon and
+/// hands in front of the camera.
+///
+/// The viewmodel is drawn in front of everything else the camera sees, so it
+/// never clips into walls however close the camera gets to them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewModelProjection {
+    /// Vertical field of view in degrees, independent of the field of view of the world
+    pub fovy: f32,
+    /// Distance to the near clipping plane
+    pub near: f32,
+    /// Distance to the far clipping plane
+    pub far: f32,
+}
+
+impl Default for ViewModelProjection {
+    fn default() -> Self {
+        Self {
+            fovy: 55.0,
+            near: 0.01,
+            far: 10.0,
+        }
+    }
 }
@@ -1,5 +1,5 @@
 pub use photon_lighting::Light;
-pub use renderer::MATERIALS_BIND_GROUP;
+pub use renderer::{MATERIALS_BIND_GROUP, gizmos::GizmoVertex};
 
 pub mod camera;
 pub mod photon_lighting;
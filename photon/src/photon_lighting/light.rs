@@ -6,7 +6,10 @@ type Color = [f32; 3];
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Default)]
 pub struct Light {
     pub position: Position,
-    _padding: f32,
+    /// How much of the light's shadow is drawn, from 0 for none to 1 for all
+    /// of it. Shadows are marched in screen space for every lit pixel, so
+    /// only a few lights should have one at a time
+    pub shadow: f32,
     pub normal: Normal,
     _padding_2: f32,
     pub color: Color,
@@ -22,7 +25,7 @@ impl Light {
     {
         Self {
             position: position.into(),
-            _padding: 0.0,
+            shadow: 0.0,
             normal: direction.into(),
             _padding_2: 0.0,
             color: color.into(),
@@ -7,16 +7,23 @@ use gpu_controller::{
 };
 pub use light::Light;
 use log::{debug, warn};
+pub use probes::{IrradianceProbe, ProbeGridInfo};
 
 // TODO: Change the size of later
 const MAX_LIGHTS: usize = 1024;
 
 pub mod light;
+pub mod probes;
+
+const PROBE_SIZE: u64 = std::mem::size_of::<IrradianceProbe>() as u64;
 
 pub struct LightsManager {
     gpu_controller: Arc<GpuController>,
     lights_buffer: Buffer,
     num_lights_buffer: Buffer,
+    probes_buffer: Buffer,
+    probe_capacity: usize,
+    probe_grid_buffer: Buffer,
     pub bind_group: BindGroup,
 
     lights: [Light; MAX_LIGHTS],
@@ -38,8 +45,52 @@ impl LightsManager {
             usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
         });
 
-        // Create the bind group
-        let bind_group = gpu_controller.read_layouts(|layouts| {
+        // Holds at least one probe, empty storage buffers can't be bound
+        let probes_buffer = Self::create_probes_buffer(&gpu_controller, 1);
+
+        let probe_grid_buffer = gpu_controller.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Probe Grid Buffer"),
+            contents: bytemuck::cast_slice(&[ProbeGridInfo::disabled()]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let bind_group = Self::create_bind_group(
+            &gpu_controller,
+            &lights_buffer,
+            &num_lights_buffer,
+            &probes_buffer,
+            &probe_grid_buffer,
+        )?;
+
+        Ok(Self {
+            gpu_controller,
+            lights_buffer,
+            num_lights_buffer,
+            probes_buffer,
+            probe_capacity: 1,
+            probe_grid_buffer,
+            bind_group,
+            lights: [Light::default(); MAX_LIGHTS],
+            num_lights: 0,
+        })
+    }
+
+    fn create_probes_buffer(gpu_controller: &GpuController, capacity: usize) -> Buffer {
+        gpu_controller.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Light Probes Buffer"),
+            contents: bytemuck::cast_slice(&vec![IrradianceProbe::default(); capacity]),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        })
+    }
+
+    fn create_bind_group(
+        gpu_controller: &GpuController,
+        lights_buffer: &Buffer,
+        num_lights_buffer: &Buffer,
+        probes_buffer: &Buffer,
+        probe_grid_buffer: &Buffer,
+    ) -> Result<BindGroup> {
+        gpu_controller.read_layouts(|layouts| {
             gpu_controller.create_bind_group(&BindGroupDescriptor {
                 label: Some("Lighting Bind Group"),
                 layout: &layouts["Lights"],
@@ -54,20 +105,94 @@ impl LightsManager {
                         binding: 1,
                         resource: num_lights_buffer.as_entire_binding(),
                     },
+                    // Light Probes
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: probes_buffer.as_entire_binding(),
+                    },
+                    // Probe Grid
+                    BindGroupEntry {
+                        binding: 3,
+                        resource: probe_grid_buffer.as_entire_binding(),
+                    },
                 ],
             })
-        })?;
-
-        Ok(Self {
-            gpu_controller,
-            lights_buffer,
-            num_lights_buffer,
-            bind_group,
-            lights: [Light::default(); MAX_LIGHTS],
-            num_lights: 0,
         })
     }
 
+    /// Replaces the probe grid and every probe in it
+    ///
+    /// # Arguments
+    /// * `grid` - The placement of the probes
+    /// * `probes` - Every probe of the grid, ordered along X, then Y, then Z
+    pub fn set_light_probes(&mut self, grid: ProbeGridInfo, probes: &[IrradianceProbe]) {
+        if probes.len() != grid.num_probes() {
+            warn!(
+                "Probe grid needs {} probes but {} were given",
+                grid.num_probes(),
+                probes.len()
+            );
+            self.clear_light_probes();
+            return;
+        }
+
+        if probes.len() > self.probe_capacity {
+            self.probe_capacity = probes.len().next_power_of_two();
+            self.probes_buffer =
+                Self::create_probes_buffer(&self.gpu_controller, self.probe_capacity);
+
+            match Self::create_bind_group(
+                &self.gpu_controller,
+                &self.lights_buffer,
+                &self.num_lights_buffer,
+                &self.probes_buffer,
+                &self.probe_grid_buffer,
+            ) {
+                Ok(bind_group) => self.bind_group = bind_group,
+                Err(err) => {
+                    warn!("Failed to grow the light probe buffer: {}", err);
+                    return;
+                }
+            }
+        }
+
+        self.update_light_probes(0, probes);
+        self.gpu_controller
+            .write_buffer(&self.probe_grid_buffer, 0, bytemuck::cast_slice(&[grid]));
+    }
+
+    /// Uploads some of the probes of the current grid
+    ///
+    /// # Arguments
+    /// * `first` - The index of the first probe to replace
+    /// * `probes` - The new probes
+    pub fn update_light_probes(&mut self, first: usize, probes: &[IrradianceProbe]) {
+        if first + probes.len() > self.probe_capacity {
+            warn!(
+                "Light probes {} past the end of the probe grid",
+                first + probes.len()
+            );
+            return;
+        }
+
+        if !probes.is_empty() {
+            self.gpu_controller.write_buffer(
+                &self.probes_buffer,
+                first as u64 * PROBE_SIZE,
+                bytemuck::cast_slice(probes),
+            );
+        }
+    }
+
+    /// Removes the probe grid, surfaces go back to a constant ambient term
+    pub fn clear_light_probes(&mut self) {
+        self.gpu_controller.write_buffer(
+            &self.probe_grid_buffer,
+            0,
+            bytemuck::cast_slice(&[ProbeGridInfo::disabled()]),
+        );
+    }
+
     // Copys the light to the buffer
     // pub fn add_light(&mut self, light: &Light) {
     //     if self.num_lights < MAX_LIGHTS as u32 {
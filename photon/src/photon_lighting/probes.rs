@@ -0,0 +1,176 @@
+use super::light::Light;
+
+type Position = [f32; 3];
+type Color = [f32; 3];
+
+// Axis directions of the faces of a probe, in the order they are stored
+const FACE_DIRECTIONS: [[f32; 3]; 6] = [
+    [1.0, 0.0, 0.0],
+    [-1.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0],
+    [0.0, -1.0, 0.0],
+    [0.0, 0.0, 1.0],
+    [0.0, 0.0, -1.0],
+];
+
+// Share of the bounce light that arrives evenly from every direction
+const BOUNCE_SCATTER: f32 = 0.25;
+
+/// Light arriving at a point from every direction, stored as an ambient cube.
+///
+/// Each of the six faces holds the irradiance arriving from one axis
+/// direction, surfaces blend the faces their normal points towards.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct IrradianceProbe {
+    // +X, -X, +Y, -Y, +Z, -Z, the fourth component is unused
+    faces: [[f32; 4]; 6],
+}
+
+impl Default for IrradianceProbe {
+    fn default() -> Self {
+        Self::uniform([0.0; 3])
+    }
+}
+
+impl IrradianceProbe {
+    /// Creates a probe from the irradiance arriving from +X, -X, +Y, -Y, +Z and -Z
+    pub fn new(faces: [Color; 6]) -> Self {
+        Self {
+            faces: faces.map(|[r, g, b]| [r, g, b, 0.0]),
+        }
+    }
+
+    /// Creates a probe with the same irradiance from every direction
+    pub fn uniform(color: Color) -> Self {
+        Self::new([color; 6])
+    }
+
+    /// Estimates the bounce light at a point from the lights around it.
+    ///
+    /// Light reaching the point is assumed to be reflected by the surfaces
+    /// around it, so it arrives mostly from the side facing away from each
+    /// light, the way a lit floor lights the underside of things above it.
+    /// Nothing is occluded, a baked probe should be used where that matters.
+    ///
+    /// # Arguments
+    /// * `position` - The position of the probe in world space
+    /// * `lights` - The lights that bounce light onto the probe
+    /// * `bounce` - The share of the light reflected by the surroundings
+    /// * `sky_color` - Irradiance arriving from every direction regardless of the lights
+    pub fn from_lights(
+        position: Position,
+        lights: &[Light],
+        bounce: f32,
+        sky_color: Color,
+    ) -> Self {
+        let mut faces = [sky_color; 6];
+
+        for light in lights {
+            let to_light = [0, 1, 2].map(|axis| light.position[axis] - position[axis]);
+            let distance_squared = to_light.iter().map(|v| v * v).sum::<f32>();
+            let distance = distance_squared.sqrt();
+
+            let direction = if distance > f32::EPSILON {
+                to_light.map(|v| v / distance)
+            } else {
+                [0.0, 1.0, 0.0]
+            };
+
+            // Falls off with distance so probes near a light pick up more of it
+            let strength = light.intensity * bounce / (1.0 + distance_squared);
+
+            for (face, face_direction) in faces.iter_mut().zip(FACE_DIRECTIONS.iter()) {
+                // The reflecting surfaces are on the far side from the light
+                let facing = -(0..3)
+                    .map(|axis| direction[axis] * face_direction[axis])
+                    .sum::<f32>();
+                let amount = strength * (facing.max(0.0) + BOUNCE_SCATTER);
+
+                for (value, light_value) in face.iter_mut().zip(light.color.iter()) {
+                    *value += light_value * amount;
+                }
+            }
+        }
+
+        Self::new(faces)
+    }
+
+    /// Returns the irradiance arriving from +X, -X, +Y, -Y, +Z and -Z
+    pub fn faces(&self) -> [Color; 6] {
+        self.faces.map(|[r, g, b, _]| [r, g, b])
+    }
+
+    /// Returns the irradiance reaching a surface facing `normal`
+    pub fn irradiance(&self, normal: [f32; 3]) -> Color {
+        let faces = self.faces();
+        let mut color = [0.0; 3];
+
+        for axis in 0..3 {
+            let weight = normal[axis] * normal[axis];
+            let face = if normal[axis] >= 0.0 {
+                faces[axis * 2]
+            } else {
+                faces[axis * 2 + 1]
+            };
+
+            for (value, face_value) in color.iter_mut().zip(face.iter()) {
+                *value += face_value * weight;
+            }
+        }
+
+        color
+    }
+}
+
+/// Placement of the probe grid as laid out in the lighting shader
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ProbeGridInfo {
+    origin: Position,
+    enabled: u32,
+    spacing: [f32; 3],
+    _padding: f32,
+    counts: [u32; 3],
+    _padding_2: u32,
+}
+
+impl Default for ProbeGridInfo {
+    fn default() -> Self {
+        Self::disabled()
+    }
+}
+
+impl ProbeGridInfo {
+    /// # Arguments
+    /// * `origin` - The position of the first probe
+    /// * `spacing` - The distance between neighbouring probes along each axis
+    /// * `counts` - The number of probes along each axis
+    pub fn new(origin: Position, spacing: [f32; 3], counts: [u32; 3]) -> Self {
+        Self {
+            origin,
+            enabled: 1,
+            spacing,
+            _padding: 0.0,
+            counts,
+            _padding_2: 0,
+        }
+    }
+
+    /// No grid, surfaces fall back to a constant ambient term
+    pub fn disabled() -> Self {
+        Self {
+            origin: [0.0; 3],
+            enabled: 0,
+            spacing: [1.0; 3],
+            _padding: 0.0,
+            counts: [0; 3],
+            _padding_2: 0,
+        }
+    }
+
+    /// Returns the number of probes in the grid
+    pub fn num_probes(&self) -> usize {
+        self.counts.iter().map(|count| *count as usize).product()
+    }
+}
@@ -0,0 +1,117 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use gpu_controller::{
+    ComputePass, ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor, GpuController,
+    PipelineCompilationOptions, PipelineLayoutDescriptor,
+};
+
+use crate::camera::PhotonCamera;
+
+use super::CAMERA_BIND_GROUP;
+use super::frame::create_frame_shader;
+
+/// Bind group of the object being culled, laid out as the "Culling" layout
+pub const CULLING_BIND_GROUP: u32 = 1;
+
+/// Number of instances each workgroup of the culling shader tests
+pub const CULLING_WORKGROUP_SIZE: u32 = 64;
+
+/// Per object culling data as laid out in the culling shader.
+///
+/// The object's transform is read from its global transform buffer, so only
+/// the data that doesn't change every frame lives here.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CullInfo {
+    center: [f32; 3],
+    radius: f32,
+    num_instances: u32,
+    num_draws: u32,
+    _padding: [u32; 2],
+}
+
+impl CullInfo {
+    /// # Arguments
+    /// * `center` - Center of the object's bounding sphere in its local space
+    /// * `radius` - Radius of the object's bounding sphere
+    /// * `num_instances` - Number of instances of the object to test
+    /// * `num_draws` - Number of indirect draws that render the visible instances
+    pub fn new(center: [f32; 3], radius: f32, num_instances: u32, num_draws: u32) -> Self {
+        Self {
+            center,
+            radius,
+            num_instances,
+            num_draws,
+            _padding: [0; 2],
+        }
+    }
+}
+
+/// Tests instances against the camera frustum on the GPU.
+///
+/// Every object dispatches the culling shader over its instances, the
+/// instances inside the frustum are compacted into the object's visible
+/// instance buffer and counted into its indirect draw arguments, so the
+/// geometry pass draws them without the CPU knowing what is visible.
+pub(crate) struct GpuCuller {
+    gpu_controller: Arc<GpuController>,
+    pipeline: ComputePipeline,
+}
+
+impl GpuCuller {
+    pub(crate) fn new(gpu_controller: Arc<GpuController>) -> Result<Self> {
+        let pipeline_layout = gpu_controller.read_layouts(|layouts| {
+            gpu_controller.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("Culling Pipeline Layout"),
+                bind_group_layouts: &[&layouts["Camera"], &layouts["Culling"]],
+                push_constant_ranges: &[],
+            })
+        })?;
+
+        let shader_module = create_frame_shader(&gpu_controller, include_str!("shaders/cull.wgsl"));
+
+        let pipeline = gpu_controller.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("Culling Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader_module,
+            entry_point: Some("cs_main"),
+            compilation_options: PipelineCompilationOptions::default(),
+            cache: None,
+        });
+
+        Ok(Self {
+            gpu_controller,
+            pipeline,
+        })
+    }
+
+    /// Runs the culling pass of a camera
+    ///
+    /// # Arguments
+    /// * `camera` - The camera whose frustum instances are tested against
+    /// * `cull_callback` - Binds and dispatches every object to cull
+    pub(crate) fn cull<C, F>(&self, camera: &C, cull_callback: F)
+    where
+        C: PhotonCamera,
+        F: FnOnce(&mut ComputePass),
+    {
+        let mut encoder = self
+            .gpu_controller
+            .create_command_encoder("Culling Encoder");
+
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("Culling Pass"),
+                timestamp_writes: None,
+            });
+
+            compute_pass.set_pipeline(&self.pipeline);
+            compute_pass.set_bind_group(CAMERA_BIND_GROUP, camera.bind_group(), &[]);
+
+            cull_callback(&mut compute_pass);
+        }
+
+        self.gpu_controller.submit(encoder);
+    }
+}
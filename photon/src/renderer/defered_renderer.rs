@@ -1,22 +1,26 @@
 use std::collections::HashMap;
 use std::{ops::Mul, sync::Arc};
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use cgmath::{Matrix4, Vector3, Zero};
 use gpu_controller::{
     BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
     BindGroupLayoutEntry, BindingResource, BindingType, BlendComponent, BlendFactor,
     BlendOperation, BlendState, Buffer, BufferInitDescriptor, BufferUsages, Color,
-    ColorTargetState, ColorWrites, CompareFunction, DepthBiasState, DepthStencilState, Extent3d,
-    Face, FragmentState, FrontFace, IndexFormat, LoadOp, MultisampleState, Operations,
-    PipelineCompilationOptions, PipelineLayoutDescriptor, PolygonMode, PrimitiveState,
-    PrimitiveTopology, RenderPass, RenderPassColorAttachment, RenderPassDepthStencilAttachment,
-    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, Sampler, SamplerBindingType,
-    SamplerDescriptor, ShaderStages, StencilState, StoreOp, Texture, TextureDescriptor,
-    TextureDimension, TextureFormat, TextureSampleType, TextureUsages, TextureViewDescriptor,
-    TextureViewDimension, VertexState,
+    ColorTargetState, ColorWrites, CommandEncoder, CompareFunction, DepthBiasState,
+    DepthStencilState, Extent3d, Face, FragmentState, FrontFace, IndexFormat, LoadOp,
+    MultisampleState, Operations, PipelineCompilationOptions, PipelineLayout,
+    PipelineLayoutDescriptor, PolygonMode, PrimitiveState, PrimitiveTopology, RenderPass,
+    RenderPassColorAttachment, RenderPassDepthStencilAttachment, RenderPassDescriptor,
+    RenderPipeline, RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor,
+    ShaderModule, ShaderStages, StencilState, StoreOp, Texture, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureSampleType, TextureUsages, TextureView,
+    TextureViewDescriptor, TextureViewDimension, VertexBufferLayout, VertexState,
+};
+use gpu_controller::{
+    BufferBindingType, Buffered, GpuController, Instance, PipelineCache,
+    QUANTIZATION_BIND_GROUP_LAYOUT_DESCRIPTOR, QuantizedVertex, Vertex, VertexEncoding,
 };
-use gpu_controller::{BufferBindingType, Buffered, GpuController, Instance, Vertex};
 use log::error;
 
 // TEMP
@@ -59,7 +63,19 @@ const MATERIAL_BIND_GROUP_LAYOUT_DESCRIPTOR: BindGroupLayoutDescriptor =
 use crate::camera::{CAMERA_BIND_GROUP_LAYOUT_DESCRIPTOR, PhotonCamera};
 use crate::photon_lighting::LightsManager;
 
+use super::culling::GpuCuller;
+use super::distortion::{DISTORTION_FORMAT, DistortionRenderer};
+use super::frame::{FRAME_CONSTANTS_OFFSET, FrameConstants, create_frame_shader};
+use super::gizmos::GizmoRenderer;
+use super::hooks::{RenderPassContext, RenderPassHooks, RenderStage};
+use super::lens::{LensRenderer, MOTION_FORMAT};
+use super::particles::ParticleRenderer;
+use super::silhouette::SilhouetteRenderer;
+use super::trails::TrailRenderer;
+use super::volumetrics::VolumetricRenderer;
+
 use super::CAMERA_BIND_GROUP;
+use super::GeometryPass;
 use super::LIGHTS_BIND_GROUP;
 
 pub const ALBEDO_BINDING: u32 = 0;
@@ -72,11 +88,23 @@ const G_BUFFER_BIND_GROUP: u32 = 2; // TODO: Lights are 1
 
 pub struct DeferedRenderer3D {
     gpu_controller: Arc<GpuController>,
-    geometry_render_pipeline: RenderPipeline,
+    geometry_pipelines: PipelineCache<(GeometryPass, VertexEncoding)>,
+    error_pipelines: PipelineCache<VertexEncoding>,
     lighting_render_pipeline: RenderPipeline,
     depth_texture: Texture,
+    color_format: TextureFormat,
 
+    pub(crate) frame_constants: FrameConstants,
     pub(crate) lights_manager: LightsManager,
+    pub(crate) gizmo_renderer: GizmoRenderer,
+    pub(crate) culler: GpuCuller,
+    pub(crate) volumetric_renderer: VolumetricRenderer,
+    pub(crate) particle_renderer: ParticleRenderer,
+    pub(crate) trail_renderer: TrailRenderer,
+    pub(crate) distortion_renderer: DistortionRenderer,
+    pub(crate) silhouette_renderer: SilhouetteRenderer,
+    pub(crate) lens_renderer: LensRenderer,
+    pub(crate) render_hooks: RenderPassHooks,
 
     // G-buffer textures
     albedo_texture: Texture,
@@ -199,6 +227,13 @@ impl DeferedRenderer3D {
         })?;
 
         let lights_manager = LightsManager::new(gpu_controller.clone())?;
+        let gizmo_renderer = GizmoRenderer::new(gpu_controller.clone())?;
+        let culler = GpuCuller::new(gpu_controller.clone())?;
+        let volumetric_renderer = VolumetricRenderer::new(gpu_controller.clone())?;
+        let particle_renderer = ParticleRenderer::new(gpu_controller.clone())?;
+        let trail_renderer = TrailRenderer::new(gpu_controller.clone())?;
+        let distortion_renderer = DistortionRenderer::new(gpu_controller.clone())?;
+        let lens_renderer = LensRenderer::new(gpu_controller.clone())?;
 
         let geometry_pipeline_layout = gpu_controller.read_layouts(|layouts| {
             gpu_controller.create_pipeline_layout(&PipelineLayoutDescriptor {
@@ -213,6 +248,23 @@ impl DeferedRenderer3D {
             })
         })?;
 
+        // Quantized meshes also bind the box their positions are stored within
+        let quantization_bind_group_layout =
+            gpu_controller.create_bind_group_layout(&QUANTIZATION_BIND_GROUP_LAYOUT_DESCRIPTOR);
+
+        let quantized_pipeline_layout = gpu_controller.read_layouts(|layouts| {
+            gpu_controller.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("Quantized Geometry Pipeline Layout"),
+                bind_group_layouts: &[
+                    &layouts["Camera"],
+                    &layouts["Material"],
+                    &layouts["Global Transform"],
+                    &quantization_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            })
+        })?;
+
         let lighting_pipeline_layout = gpu_controller.read_layouts(|layouts| {
             gpu_controller.create_pipeline_layout(&PipelineLayoutDescriptor {
                 label: Some("Lighting Pipeline Layout"),
@@ -221,105 +273,117 @@ impl DeferedRenderer3D {
             })
         })?;
 
-        let geometry_shader_module =
-            gpu_controller.create_shader(include_str!("shaders/defered_3d_geom.wgsl"));
+        let geometry_shader_module = create_frame_shader(
+            &gpu_controller,
+            include_str!("shaders/defered_3d_geom.wgsl"),
+        );
 
-        let lighting_shader_module =
-            gpu_controller.create_shader(include_str!("shaders/defered_3d_light.wgsl"));
+        let lighting_shader_module = create_frame_shader(
+            &gpu_controller,
+            include_str!("shaders/defered_3d_light.wgsl"),
+        );
 
-        let geometry_render_pipeline =
-            gpu_controller.create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("Defered Renderer Geometry Pipeline"),
-                cache: None,
-                multiview: None,
-                layout: Some(&geometry_pipeline_layout),
-                vertex: VertexState {
-                    module: &geometry_shader_module,
-                    entry_point: Some("vs_main"),
-                    buffers: &[Vertex::desc(), Instance::desc()],
-                    compilation_options: PipelineCompilationOptions::default(),
-                },
-                fragment: Some(FragmentState {
-                    module: &geometry_shader_module,
-                    entry_point: Some("fs_main"),
-                    targets: &[
-                        // Albedo
-                        Some(ColorTargetState {
-                            format: TextureFormat::Rgba8UnormSrgb,
-                            blend: Some(BlendState {
-                                color: BlendComponent {
-                                    src_factor: BlendFactor::SrcAlpha,
-                                    dst_factor: BlendFactor::OneMinusSrcAlpha,
-                                    operation: BlendOperation::Add,
-                                },
-                                alpha: BlendComponent::OVER,
-                            }),
-                            write_mask: ColorWrites::ALL,
-                        }),
-                        // Position
-                        Some(ColorTargetState {
-                            format: TextureFormat::Rgba16Float,
-                            blend: Some(BlendState {
-                                color: BlendComponent {
-                                    src_factor: BlendFactor::SrcAlpha,
-                                    dst_factor: BlendFactor::OneMinusSrcAlpha,
-                                    operation: BlendOperation::Add,
-                                },
-                                alpha: BlendComponent::OVER,
-                            }),
-                            write_mask: ColorWrites::ALL,
-                        }),
-                        // Normals
-                        Some(ColorTargetState {
-                            format: TextureFormat::Rgba16Float,
-                            blend: Some(BlendState {
-                                color: BlendComponent {
-                                    src_factor: BlendFactor::SrcAlpha,
-                                    dst_factor: BlendFactor::OneMinusSrcAlpha,
-                                    operation: BlendOperation::Add,
-                                },
-                                alpha: BlendComponent::OVER,
-                            }),
-                            write_mask: ColorWrites::ALL,
-                        }),
-                        // Material
-                        Some(ColorTargetState {
-                            format: TextureFormat::Rgba8Unorm,
-                            blend: Some(BlendState {
-                                color: BlendComponent {
-                                    src_factor: BlendFactor::SrcAlpha,
-                                    dst_factor: BlendFactor::OneMinusSrcAlpha,
-                                    operation: BlendOperation::Add,
-                                },
-                                alpha: BlendComponent::OVER,
-                            }),
-                            write_mask: ColorWrites::ALL,
-                        }),
-                    ],
-                    compilation_options: PipelineCompilationOptions::default(),
-                }),
-                primitive: PrimitiveState {
-                    topology: PrimitiveTopology::TriangleList,
-                    strip_index_format: None,
-                    front_face: FrontFace::Ccw,
-                    cull_mode: Some(Face::Back),
-                    polygon_mode: PolygonMode::Fill,
-                    unclipped_depth: false,
-                    conservative: false,
+        let silhouette_renderer = SilhouetteRenderer::new(
+            gpu_controller.clone(),
+            &geometry_shader_module,
+            (&geometry_pipeline_layout, &quantized_pipeline_layout),
+            gpu_controller.read_surface_config(|config| config.format)?,
+            &depth_texture,
+        )?;
+
+        // Surfaces whose variant fails to compile are drawn with the error shader instead
+        let error_pipelines = {
+            let gpu_controller = gpu_controller.clone();
+            let geometry_pipeline_layout = geometry_pipeline_layout.clone();
+            let quantized_pipeline_layout = quantized_pipeline_layout.clone();
+            let geometry_shader_module = geometry_shader_module.clone();
+
+            PipelineCache::new(
+                "Defered Renderer Error",
+                move |&encoding: &VertexEncoding| {
+                    let (layout, vertex_buffer, entry_point) = match encoding {
+                        VertexEncoding::Full => {
+                            (&geometry_pipeline_layout, Vertex::desc(), "vs_main")
+                        }
+                        VertexEncoding::Quantized => (
+                            &quantized_pipeline_layout,
+                            QuantizedVertex::desc(),
+                            "vs_quantized",
+                        ),
+                    };
+
+                    Ok(create_geometry_pipeline(
+                        &gpu_controller,
+                        &geometry_shader_module,
+                        &format!("Defered Renderer {:?} Error Pipeline", encoding),
+                        layout,
+                        (entry_point, "fs_error"),
+                        vertex_buffer,
+                    ))
                 },
-                depth_stencil: Some(DepthStencilState {
-                    format: TextureFormat::Depth32Float,
-                    depth_write_enabled: true,
-                    depth_compare: CompareFunction::Less,
-                    stencil: StencilState::default(),
-                    bias: DepthBiasState::default(),
-                }),
-                multisample: MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
+            )
+        };
+
+        // Every pass and vertex encoding of the geometry has its own variant
+        let geometry_pipelines = {
+            let gpu_controller = gpu_controller.clone();
+
+            PipelineCache::new(
+                "Defered Renderer Geometry",
+                move |&(pass, encoding): &(GeometryPass, VertexEncoding)| {
+                    let (layout, vertex_buffer) = match encoding {
+                        VertexEncoding::Full => (&geometry_pipeline_layout, Vertex::desc()),
+                        VertexEncoding::Quantized => {
+                            (&quantized_pipeline_layout, QuantizedVertex::desc())
+                        }
+                    };
+
+                    let label = format!("Defered Renderer {:?} {:?} Pipeline", encoding, pass);
+
+                    let create_pipeline = match pass {
+                        GeometryPass::Surfaces | GeometryPass::ViewModel => {
+                            create_geometry_pipeline
+                        }
+                        GeometryPass::Motion => create_motion_pipeline,
+                        // Drawn with the stencil pipelines of the silhouette renderer instead
+                        GeometryPass::Silhouettes => {
+                            return Err(anyhow!("The {:?} pass has no geometry pipeline", pass));
+                        }
+                    };
+
+                    let entry_points = match (pass, encoding) {
+                        (GeometryPass::Surfaces, VertexEncoding::Full) => ("vs_main", "fs_main"),
+                        (GeometryPass::Surfaces, VertexEncoding::Quantized) => {
+                            ("vs_quantized", "fs_main")
+                        }
+                        (GeometryPass::ViewModel, VertexEncoding::Full) => {
+                            ("vs_view_model", "fs_main")
+                        }
+                        (GeometryPass::ViewModel, VertexEncoding::Quantized) => {
+                            ("vs_view_model_quantized", "fs_main")
+                        }
+                        (GeometryPass::Motion, VertexEncoding::Full) => ("vs_motion", "fs_motion"),
+                        (GeometryPass::Motion, VertexEncoding::Quantized) => {
+                            ("vs_motion_quantized", "fs_motion")
+                        }
+                        (GeometryPass::Silhouettes, _) => unreachable!(),
+                    };
+
+                    Ok(create_pipeline(
+                        &gpu_controller,
+                        &geometry_shader_module,
+                        &label,
+                        layout,
+                        entry_points,
+                        vertex_buffer,
+                    ))
                 },
-            });
+            )
+        };
+
+        // Full precision surfaces are drawn by almost every frame, the other
+        // variants compile the first time a quantized mesh or motion blur shows up
+        geometry_pipelines.warm_up([(GeometryPass::Surfaces, VertexEncoding::Full)])?;
 
         let lighting_render_pipeline =
             gpu_controller.create_render_pipeline(&RenderPipelineDescriptor {
@@ -394,6 +458,8 @@ impl DeferedRenderer3D {
             contents: bytemuck::cast_slice(&[0, 1, 2]),
         });
 
+        let color_format = gpu_controller.read_surface_config(|config| config.format)?;
+
         // Clone for resizing later if needed
         let g_buffer_bind_group_layout =
             gpu_controller.read_layouts(|layouts| layouts["G-Buffer"].clone())?;
@@ -404,35 +470,140 @@ impl DeferedRenderer3D {
             normal_texture,
             material_texture,
             depth_texture,
-            geometry_render_pipeline,
+            color_format,
+            geometry_pipelines,
+            error_pipelines,
             lighting_render_pipeline,
             g_buffer_bind_group_layout,
             g_buffer_bind_group,
             g_buffer_sampler,
+            frame_constants: FrameConstants::new((texture_size.width, texture_size.height)),
             lights_manager,
+            gizmo_renderer,
+            culler,
+            volumetric_renderer,
+            particle_renderer,
+            trail_renderer,
+            distortion_renderer,
+            silhouette_renderer,
+            lens_renderer,
+            render_hooks: RenderPassHooks::default(),
             gpu_controller,
             instance_buffer,
             index_buffer,
         })
     }
 
+    /// Runs the geometry callback once for every vertex encoding, with the
+    /// pipeline variant of the pass and encoding set
+    fn draw_geometry<F>(
+        &self,
+        render_pass: &mut RenderPass,
+        pass: GeometryPass,
+        geometry_callback: &mut F,
+    ) where
+        F: FnMut(&mut RenderPass, GeometryPass, VertexEncoding),
+    {
+        for encoding in [VertexEncoding::Full, VertexEncoding::Quantized] {
+            match self.geometry_pipelines.get(&(pass, encoding)) {
+                Ok(pipeline) => {
+                    render_pass.set_pipeline(&pipeline);
+                    geometry_callback(render_pass, pass, encoding);
+                }
+                Err(err) => {
+                    error!(
+                        "Failed to compile the {:?} {:?} pipeline: {}",
+                        encoding, pass, err
+                    );
+
+                    // Missing motion vectors only lose the blur, missing surfaces get the error shader
+                    if pass == GeometryPass::Motion {
+                        continue;
+                    }
+
+                    match self.error_pipelines.get(&encoding) {
+                        Ok(pipeline) => {
+                            render_pass.set_pipeline(&pipeline);
+                            geometry_callback(render_pass, pass, encoding);
+                        }
+                        Err(err) => error!(
+                            "Failed to compile the {:?} error pipeline: {}",
+                            encoding, err
+                        ),
+                    }
+                }
+            }
+        }
+    }
+
     pub(crate) fn render<C, F>(
         &self,
         camera: &C,
         output: &Texture,
-        geometry_callback: F,
+        mut geometry_callback: F,
     ) -> Result<()>
     where
         C: PhotonCamera,
-        F: FnOnce(&mut RenderPass),
+        F: FnMut(&mut RenderPass, GeometryPass, VertexEncoding),
     {
         // Get the output texture for the renderer
         let view = output.create_view(&TextureViewDescriptor::default());
 
+        // Cameras with lens effects light the scene into an intermediate texture first
+        let lens_enabled = LensRenderer::enabled(camera);
+        let post_view = if lens_enabled {
+            self.lens_renderer.scene_view()
+        } else {
+            output.create_view(&TextureViewDescriptor::default())
+        };
+
+        // Distorted scenes are lit into another one, then read back distorted into the view above
+        let distortion_enabled = self.distortion_renderer.has_sources()
+            || self.particle_renderer.has_distortion()
+            || self.render_hooks.has_stage(RenderStage::Distortion);
+        let scene_view = if distortion_enabled {
+            self.distortion_renderer.scene_view()
+        } else if lens_enabled {
+            self.lens_renderer.scene_view()
+        } else {
+            output.create_view(&TextureViewDescriptor::default())
+        };
+
+        let depth_view = self
+            .depth_texture
+            .create_view(&TextureViewDescriptor::default());
+
+        // Every pass of the camera reads the frame constants next to its matrices
+        self.gpu_controller.write_buffer(
+            camera.buffer(),
+            FRAME_CONSTANTS_OFFSET,
+            bytemuck::cast_slice(&[self.frame_constants]),
+        );
+
         let mut encoder = self
             .gpu_controller
             .create_command_encoder("Defered Render 3D Encoder");
 
+        // Records the custom passes of a stage over the color it left behind
+        let run_hooks = |stage, color_view: &TextureView, encoder: &mut CommandEncoder| {
+            self.render_hooks.run(
+                stage,
+                &RenderPassContext {
+                    gpu_controller: &self.gpu_controller,
+                    camera_bind_group: camera.bind_group(),
+                    g_buffer_bind_group: &self.g_buffer_bind_group,
+                    color_view,
+                    color_format: if stage == RenderStage::Distortion {
+                        DISTORTION_FORMAT
+                    } else {
+                        self.color_format
+                    },
+                    depth_view: &depth_view,
+                },
+                encoder,
+            );
+        };
+
         // Create views for G-Buffer
         let albedo_view = self
             .albedo_texture
@@ -488,9 +659,7 @@ impl DeferedRenderer3D {
                     }),
                 ],
                 depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
-                    view: &self
-                        .depth_texture
-                        .create_view(&TextureViewDescriptor::default()),
+                    view: &depth_view,
                     depth_ops: Some(Operations {
                         load: LoadOp::Clear(1.0),
                         store: StoreOp::Store,
@@ -501,8 +670,6 @@ impl DeferedRenderer3D {
                 timestamp_writes: None,
             });
 
-            render_pass.set_pipeline(&self.geometry_render_pipeline);
-
             // Set bind groups here
             render_pass.set_bind_group(CAMERA_BIND_GROUP, camera.bind_group(), &[]);
 
@@ -510,7 +677,56 @@ impl DeferedRenderer3D {
             // render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
 
             // Run render callback
-            geometry_callback(&mut render_pass);
+            self.draw_geometry(
+                &mut render_pass,
+                GeometryPass::Surfaces,
+                &mut geometry_callback,
+            );
+
+            // The viewmodel is squeezed into the front of the depth range, so
+            // it tests against the surfaces without ever being covered by them
+            if camera.view_model().is_some() {
+                self.draw_geometry(
+                    &mut render_pass,
+                    GeometryPass::ViewModel,
+                    &mut geometry_callback,
+                );
+            }
+        }
+
+        run_hooks(RenderStage::AfterGeometry, &scene_view, &mut encoder);
+
+        // Motion Pass
+        if camera.motion_blur().is_some() {
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("Defered 3D Motion Pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &self.lens_renderer.motion_view(),
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::TRANSPARENT),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                    view: &depth_view,
+                    depth_ops: Some(Operations {
+                        load: LoadOp::Load,
+                        store: StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+            render_pass.set_bind_group(CAMERA_BIND_GROUP, camera.bind_group(), &[]);
+
+            self.draw_geometry(
+                &mut render_pass,
+                GeometryPass::Motion,
+                &mut geometry_callback,
+            );
         }
 
         // Lighting Pass
@@ -518,10 +734,17 @@ impl DeferedRenderer3D {
             let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
                 label: Some("Defered 3D Lighting Pass"),
                 color_attachments: &[Some(RenderPassColorAttachment {
-                    view: &view,
+                    view: &scene_view,
                     resolve_target: None,
                     ops: Operations {
-                        load: LoadOp::Clear(Color::BLACK),
+                        load: if camera.clears() {
+                            LoadOp::Clear(camera.clear_color())
+                        } else if lens_enabled || distortion_enabled {
+                            // The lens and distortion stages blend over what the output already holds
+                            LoadOp::Clear(Color::TRANSPARENT)
+                        } else {
+                            LoadOp::Load
+                        },
                         store: StoreOp::Store,
                     },
                 })],
@@ -542,11 +765,83 @@ impl DeferedRenderer3D {
             render_pass.draw_indexed(0..3, 0, 0..1);
         }
 
+        run_hooks(RenderStage::AfterOpaque, &scene_view, &mut encoder);
+
+        // Volumetrics Pass
+        self.volumetric_renderer.render(
+            &mut encoder,
+            &scene_view,
+            camera,
+            &self.lights_manager.bind_group,
+            &self.g_buffer_bind_group,
+        );
+
+        // Transparent Pass
+        self.particle_renderer
+            .render(&mut encoder, &scene_view, &depth_view, camera);
+        self.trail_renderer
+            .render(&mut encoder, &scene_view, &depth_view, camera);
+
+        // Silhouette Pass
+        self.silhouette_renderer
+            .render(&mut encoder, &scene_view, camera, &mut geometry_callback);
+
+        // Distortion Pass
+        if distortion_enabled {
+            self.distortion_renderer
+                .render_sources(&mut encoder, &depth_view, camera);
+
+            let distortion_view = self.distortion_renderer.distortion_view();
+            self.particle_renderer.render_distortion(
+                &mut encoder,
+                &distortion_view,
+                &depth_view,
+                camera,
+            );
+            run_hooks(RenderStage::Distortion, &distortion_view, &mut encoder);
+
+            self.distortion_renderer
+                .composite(&mut encoder, &post_view, lens_enabled);
+        }
+
+        run_hooks(RenderStage::BeforePostProcess, &post_view, &mut encoder);
+
+        // Lens Pass
+        if lens_enabled {
+            self.lens_renderer
+                .render(&mut encoder, &view, camera, &self.g_buffer_bind_group);
+        }
+
+        run_hooks(RenderStage::AfterPostProcess, &view, &mut encoder);
+
+        // Gizmo Pass
+        self.gizmo_renderer.render(&mut encoder, &view, camera);
+
+        run_hooks(RenderStage::Overlay, &view, &mut encoder);
+
         self.gpu_controller.submit(encoder);
 
         Ok(())
     }
 
+    /// Moves the particles of every emitter along by a frame, colliding
+    /// them with what the camera drew last
+    pub(crate) fn simulate_particles<C>(&mut self, camera: &C, delta_t: f32)
+    where
+        C: PhotonCamera,
+    {
+        self.particle_renderer.simulate(
+            camera,
+            delta_t,
+            &self
+                .position_texture
+                .create_view(&TextureViewDescriptor::default()),
+            &self
+                .normal_texture
+                .create_view(&TextureViewDescriptor::default()),
+        );
+    }
+
     pub fn resize(&mut self, new_size: (u32, u32)) {
         let texture_size = self
             .gpu_controller
@@ -564,6 +859,9 @@ impl DeferedRenderer3D {
                 }
             });
 
+        self.frame_constants
+            .set_resolution((texture_size.width, texture_size.height));
+
         self.albedo_texture = self.gpu_controller.create_texture(&TextureDescriptor {
             label: Some("G-Buffer Albedo"),
             size: texture_size,
@@ -619,6 +917,11 @@ impl DeferedRenderer3D {
             view_formats: &[],
         });
 
+        self.lens_renderer.resize(texture_size);
+        self.distortion_renderer.resize(texture_size);
+        self.silhouette_renderer
+            .resize(texture_size, &self.depth_texture);
+
         self.g_buffer_bind_group = self.gpu_controller.create_bind_group(&BindGroupDescriptor {
             label: Some("G-Buffer Bind Group"),
             layout: &self.g_buffer_bind_group_layout,
@@ -663,3 +966,160 @@ impl DeferedRenderer3D {
         });
     }
 }
+
+fn create_geometry_pipeline(
+    gpu_controller: &GpuController,
+    shader_module: &ShaderModule,
+    label: &str,
+    layout: &PipelineLayout,
+    (vertex_entry_point, fragment_entry_point): (&str, &str),
+    vertex_buffer: VertexBufferLayout,
+) -> RenderPipeline {
+    gpu_controller.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some(label),
+        cache: None,
+        multiview: None,
+        layout: Some(layout),
+        vertex: VertexState {
+            module: shader_module,
+            entry_point: Some(vertex_entry_point),
+            buffers: &[vertex_buffer, Instance::desc()],
+            compilation_options: PipelineCompilationOptions::default(),
+        },
+        fragment: Some(FragmentState {
+            module: shader_module,
+            entry_point: Some(fragment_entry_point),
+            targets: &[
+                // Albedo
+                Some(ColorTargetState {
+                    format: TextureFormat::Rgba8UnormSrgb,
+                    blend: Some(BlendState {
+                        color: BlendComponent {
+                            src_factor: BlendFactor::SrcAlpha,
+                            dst_factor: BlendFactor::OneMinusSrcAlpha,
+                            operation: BlendOperation::Add,
+                        },
+                        alpha: BlendComponent::OVER,
+                    }),
+                    write_mask: ColorWrites::ALL,
+                }),
+                // Position
+                Some(ColorTargetState {
+                    format: TextureFormat::Rgba16Float,
+                    blend: Some(BlendState {
+                        color: BlendComponent {
+                            src_factor: BlendFactor::SrcAlpha,
+                            dst_factor: BlendFactor::OneMinusSrcAlpha,
+                            operation: BlendOperation::Add,
+                        },
+                        alpha: BlendComponent::OVER,
+                    }),
+                    write_mask: ColorWrites::ALL,
+                }),
+                // Normals
+                Some(ColorTargetState {
+                    format: TextureFormat::Rgba16Float,
+                    blend: Some(BlendState {
+                        color: BlendComponent {
+                            src_factor: BlendFactor::SrcAlpha,
+                            dst_factor: BlendFactor::OneMinusSrcAlpha,
+                            operation: BlendOperation::Add,
+                        },
+                        alpha: BlendComponent::OVER,
+                    }),
+                    write_mask: ColorWrites::ALL,
+                }),
+                // Material
+                Some(ColorTargetState {
+                    format: TextureFormat::Rgba8Unorm,
+                    blend: Some(BlendState {
+                        color: BlendComponent {
+                            src_factor: BlendFactor::SrcAlpha,
+                            dst_factor: BlendFactor::OneMinusSrcAlpha,
+                            operation: BlendOperation::Add,
+                        },
+                        alpha: BlendComponent::OVER,
+                    }),
+                    write_mask: ColorWrites::ALL,
+                }),
+            ],
+            compilation_options: PipelineCompilationOptions::default(),
+        }),
+        primitive: PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: FrontFace::Ccw,
+            cull_mode: Some(Face::Back),
+            polygon_mode: PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(DepthStencilState {
+            format: TextureFormat::Depth32Float,
+            depth_write_enabled: true,
+            depth_compare: CompareFunction::Less,
+            stencil: StencilState::default(),
+            bias: DepthBiasState::default(),
+        }),
+        multisample: MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+    })
+}
+
+/// Draws the objects that moved again over the geometry, writing their motion vectors
+fn create_motion_pipeline(
+    gpu_controller: &GpuController,
+    shader_module: &ShaderModule,
+    label: &str,
+    layout: &PipelineLayout,
+    (vertex_entry_point, fragment_entry_point): (&str, &str),
+    vertex_buffer: VertexBufferLayout,
+) -> RenderPipeline {
+    gpu_controller.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some(label),
+        cache: None,
+        multiview: None,
+        layout: Some(layout),
+        vertex: VertexState {
+            module: shader_module,
+            entry_point: Some(vertex_entry_point),
+            buffers: &[vertex_buffer, Instance::desc()],
+            compilation_options: PipelineCompilationOptions::default(),
+        },
+        fragment: Some(FragmentState {
+            module: shader_module,
+            entry_point: Some(fragment_entry_point),
+            targets: &[Some(ColorTargetState {
+                format: MOTION_FORMAT,
+                blend: None,
+                write_mask: ColorWrites::ALL,
+            })],
+            compilation_options: PipelineCompilationOptions::default(),
+        }),
+        primitive: PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: FrontFace::Ccw,
+            cull_mode: Some(Face::Back),
+            polygon_mode: PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        // Only the surfaces the geometry pass kept pass the depth test
+        depth_stencil: Some(DepthStencilState {
+            format: TextureFormat::Depth32Float,
+            depth_write_enabled: false,
+            depth_compare: CompareFunction::LessEqual,
+            stencil: StencilState::default(),
+            bias: DepthBiasState::default(),
+        }),
+        multisample: MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+    })
+}
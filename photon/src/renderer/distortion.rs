@@ -0,0 +1,510 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use gpu_controller::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BlendComponent, BlendFactor,
+    BlendOperation, BlendState, Buffer, BufferBindingType, BufferDescriptor, BufferUsages, Color,
+    ColorTargetState, ColorWrites, CommandEncoder, CompareFunction, DepthBiasState,
+    DepthStencilState, Extent3d, FilterMode, FragmentState, FrontFace, GpuController, LoadOp,
+    MultisampleState, Operations, PipelineCompilationOptions, PipelineLayoutDescriptor,
+    PolygonMode, PrimitiveState, PrimitiveTopology, RenderPassColorAttachment,
+    RenderPassDepthStencilAttachment, RenderPassDescriptor, RenderPipeline,
+    RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor, ShaderStages,
+    StencilState, StoreOp, Texture, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension,
+    VertexState,
+};
+use log::error;
+
+use crate::camera::PhotonCamera;
+
+use super::CAMERA_BIND_GROUP;
+use super::frame::create_frame_shader;
+
+const SOURCES_BIND_GROUP: u32 = 1;
+const COMPOSITE_BIND_GROUP: u32 = 0;
+
+/// Format of the distortion buffer, how far in texture coordinates the scene
+/// color of every pixel is read from
+pub const DISTORTION_FORMAT: TextureFormat = TextureFormat::Rg16Float;
+
+/// Most distortion sources drawn at once, the rest are ignored
+pub const MAX_DISTORTION_SOURCES: usize = 256;
+
+/// How a distortion source bends the scene behind it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DistortionKind {
+    /// Shimmer like the air above a fire or hot asphalt
+    HeatHaze {
+        /// How many ripples there are per unit
+        frequency: f32,
+        /// How fast the ripples move
+        speed: f32,
+    },
+    /// A ring pushing the scene outwards, like the blast of an explosion
+    Shockwave {
+        /// From 0 to 1, how far the ring has spread towards the radius
+        progress: f32,
+        /// From 0 to 1, how wide the ring is relative to the radius
+        thickness: f32,
+    },
+}
+
+/// A sphere that distorts the scene seen through it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DistortionSource {
+    pub position: [f32; 3],
+    pub radius: f32,
+    /// Largest offset of the scene in texture coordinates, such as 0.01
+    pub strength: f32,
+    pub kind: DistortionKind,
+}
+
+// As laid out in the distortion sources shader
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct SourceUniform {
+    position: [f32; 3],
+    radius: f32,
+    strength: f32,
+    kind: u32,
+    parameters: [f32; 2],
+}
+
+impl From<&DistortionSource> for SourceUniform {
+    fn from(source: &DistortionSource) -> Self {
+        let (kind, parameters) = match source.kind {
+            DistortionKind::HeatHaze { frequency, speed } => (0, [frequency, speed]),
+            DistortionKind::Shockwave {
+                progress,
+                thickness,
+            } => (1, [progress, thickness]),
+        };
+
+        Self {
+            position: source.position,
+            radius: source.radius,
+            strength: source.strength,
+            kind,
+            parameters,
+        }
+    }
+}
+
+/// Offsets where the scene color is read from, for heat haze, shockwaves and
+/// refraction-like effects.
+///
+/// Cameras light the scene into an intermediate texture while anything
+/// distorts it. Sources, distorting particles and custom passes add offsets
+/// into the distortion buffer, then the scene is copied to the output reading
+/// every pixel from where its offset points.
+pub(crate) struct DistortionRenderer {
+    gpu_controller: Arc<GpuController>,
+    source_pipeline: RenderPipeline,
+    composite_pipeline: RenderPipeline,
+    composite_layout: BindGroupLayout,
+    source_buffer: Buffer,
+    sources_bind_group: BindGroup,
+    sampler: Sampler,
+    scene_texture: Texture,
+    distortion_texture: Texture,
+    composite_bind_group: BindGroup,
+    source_count: u32,
+}
+
+impl DistortionRenderer {
+    pub(crate) fn new(gpu_controller: Arc<GpuController>) -> Result<Self> {
+        let sources_layout = gpu_controller.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Distortion Sources Bind Group Layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let texture_entry = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+                sample_type: TextureSampleType::Float { filterable: true },
+                view_dimension: TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+
+        let composite_layout =
+            gpu_controller.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("Distortion Composite Bind Group Layout"),
+                entries: &[
+                    texture_entry(0),
+                    texture_entry(1),
+                    BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let source_buffer = gpu_controller.create_buffer(&BufferDescriptor {
+            label: Some("Distortion Source Buffer"),
+            size: (MAX_DISTORTION_SOURCES * size_of::<SourceUniform>()) as u64,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let sources_bind_group = gpu_controller.create_bind_group(&BindGroupDescriptor {
+            label: Some("Distortion Sources Bind Group"),
+            layout: &sources_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: source_buffer.as_entire_binding(),
+            }],
+        });
+
+        let sampler = gpu_controller.create_sampler(&SamplerDescriptor {
+            label: Some("Distortion Sampler"),
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let source_pipeline_layout = gpu_controller.read_layouts(|layouts| {
+            gpu_controller.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("Distortion Source Pipeline Layout"),
+                bind_group_layouts: &[&layouts["Camera"], &sources_layout],
+                push_constant_ranges: &[],
+            })
+        })?;
+
+        let composite_pipeline_layout =
+            gpu_controller.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("Distortion Composite Pipeline Layout"),
+                bind_group_layouts: &[&composite_layout],
+                push_constant_ranges: &[],
+            });
+
+        let source_module = create_frame_shader(
+            &gpu_controller,
+            include_str!("shaders/distortion_sources.wgsl"),
+        );
+        let composite_module =
+            gpu_controller.create_shader(include_str!("shaders/distortion.wgsl"));
+        let format = gpu_controller.read_surface_config(|config| config.format)?;
+
+        let primitive = PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: FrontFace::Ccw,
+            cull_mode: None,
+            polygon_mode: PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        };
+        let multisample = MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        };
+
+        // Offsets of overlapping sources add up
+        let source_pipeline = gpu_controller.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Distortion Source Pipeline"),
+            cache: None,
+            multiview: None,
+            layout: Some(&source_pipeline_layout),
+            vertex: VertexState {
+                module: &source_module,
+                entry_point: Some("vs_source"),
+                buffers: &[],
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &source_module,
+                entry_point: Some("fs_source"),
+                targets: &[Some(ColorTargetState {
+                    format: DISTORTION_FORMAT,
+                    blend: Some(ADDITIVE_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            primitive,
+            depth_stencil: Some(DepthStencilState {
+                format: TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                depth_compare: CompareFunction::Less,
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default(),
+            }),
+            multisample,
+        });
+
+        // Drawn over the output the same way the lighting pass is, so cameras
+        // that don't clear still keep what was drawn before them
+        let composite_pipeline = gpu_controller.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Distortion Composite Pipeline"),
+            cache: None,
+            multiview: None,
+            layout: Some(&composite_pipeline_layout),
+            vertex: VertexState {
+                module: &composite_module,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &composite_module,
+                entry_point: Some("fs_main"),
+                targets: &[Some(ColorTargetState {
+                    format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            primitive,
+            depth_stencil: None,
+            multisample,
+        });
+
+        let size = gpu_controller.read_surface_config(|config| Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        })?;
+        let (scene_texture, distortion_texture) =
+            Self::create_textures(&gpu_controller, size, format);
+        let composite_bind_group = Self::create_composite_bind_group(
+            &gpu_controller,
+            &composite_layout,
+            &sampler,
+            &scene_texture,
+            &distortion_texture,
+        );
+
+        Ok(Self {
+            gpu_controller,
+            source_pipeline,
+            composite_pipeline,
+            composite_layout,
+            source_buffer,
+            sources_bind_group,
+            sampler,
+            scene_texture,
+            distortion_texture,
+            composite_bind_group,
+            source_count: 0,
+        })
+    }
+
+    fn create_textures(
+        gpu_controller: &GpuController,
+        size: Extent3d,
+        format: TextureFormat,
+    ) -> (Texture, Texture) {
+        let create_texture = |label, format| {
+            gpu_controller.create_texture(&TextureDescriptor {
+                label: Some(label),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+        };
+
+        (
+            create_texture("Distortion Scene", format),
+            create_texture("Distortion Offsets", DISTORTION_FORMAT),
+        )
+    }
+
+    fn create_composite_bind_group(
+        gpu_controller: &GpuController,
+        layout: &BindGroupLayout,
+        sampler: &Sampler,
+        scene_texture: &Texture,
+        distortion_texture: &Texture,
+    ) -> BindGroup {
+        gpu_controller.create_bind_group(&BindGroupDescriptor {
+            label: Some("Distortion Composite Bind Group"),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &scene_texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        &distortion_texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::Sampler(sampler),
+                },
+            ],
+        })
+    }
+
+    /// Replaces the distortion sources, only the first `MAX_DISTORTION_SOURCES` are drawn
+    ///
+    /// # Arguments
+    /// * `sources` - The spheres that distort the scene
+    pub(crate) fn set_sources(&mut self, sources: &[DistortionSource]) {
+        let sources = sources
+            .iter()
+            .take(MAX_DISTORTION_SOURCES)
+            .map(SourceUniform::from)
+            .collect::<Vec<_>>();
+
+        self.source_count = sources.len() as u32;
+        if !sources.is_empty() {
+            self.gpu_controller.write_buffer(
+                &self.source_buffer,
+                0,
+                bytemuck::cast_slice(&sources),
+            );
+        }
+    }
+
+    pub(crate) fn has_sources(&self) -> bool {
+        self.source_count > 0
+    }
+
+    /// Returns a view of the texture the scene is lit into while it is distorted
+    pub(crate) fn scene_view(&self) -> TextureView {
+        self.scene_texture
+            .create_view(&TextureViewDescriptor::default())
+    }
+
+    /// Returns a view of the distortion buffer, as `DISTORTION_FORMAT`
+    pub(crate) fn distortion_view(&self) -> TextureView {
+        self.distortion_texture
+            .create_view(&TextureViewDescriptor::default())
+    }
+
+    /// Clears the distortion buffer and adds the offsets of every source into it
+    pub(crate) fn render_sources<C>(
+        &self,
+        encoder: &mut CommandEncoder,
+        depth_view: &TextureView,
+        camera: &C,
+    ) where
+        C: PhotonCamera,
+    {
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Distortion Source Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &self.distortion_view(),
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::TRANSPARENT),
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        if self.source_count == 0 {
+            return;
+        }
+
+        render_pass.set_pipeline(&self.source_pipeline);
+        render_pass.set_bind_group(CAMERA_BIND_GROUP, camera.bind_group(), &[]);
+        render_pass.set_bind_group(SOURCES_BIND_GROUP, &self.sources_bind_group, &[]);
+        render_pass.draw(0..6, 0..self.source_count);
+    }
+
+    /// Copies the distorted scene into the view
+    ///
+    /// # Arguments
+    /// * `encoder` - The encoder of the camera's frame
+    /// * `view` - Where the scene continues to be drawn after distortion
+    /// * `clear` - Whether the view is cleared first instead of blended over
+    pub(crate) fn composite(&self, encoder: &mut CommandEncoder, view: &TextureView, clear: bool) {
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Distortion Composite Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: Operations {
+                    load: if clear {
+                        LoadOp::Clear(Color::TRANSPARENT)
+                    } else {
+                        LoadOp::Load
+                    },
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&self.composite_pipeline);
+        render_pass.set_bind_group(COMPOSITE_BIND_GROUP, &self.composite_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    pub(crate) fn resize(&mut self, size: Extent3d) {
+        let format = match self
+            .gpu_controller
+            .read_surface_config(|config| config.format)
+        {
+            Ok(format) => format,
+            Err(err) => {
+                error!("Failed to read surface config: {}", err);
+                return;
+            }
+        };
+
+        (self.scene_texture, self.distortion_texture) =
+            Self::create_textures(&self.gpu_controller, size, format);
+        self.composite_bind_group = Self::create_composite_bind_group(
+            &self.gpu_controller,
+            &self.composite_layout,
+            &self.sampler,
+            &self.scene_texture,
+            &self.distortion_texture,
+        );
+    }
+}
+
+/// Adds the offsets of everything drawn into the distortion buffer
+pub(crate) const ADDITIVE_BLENDING: BlendState = BlendState {
+    color: BlendComponent {
+        src_factor: BlendFactor::One,
+        dst_factor: BlendFactor::One,
+        operation: BlendOperation::Add,
+    },
+    alpha: BlendComponent {
+        src_factor: BlendFactor::One,
+        dst_factor: BlendFactor::One,
+        operation: BlendOperation::Add,
+    },
+};
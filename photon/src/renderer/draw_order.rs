@@ -0,0 +1,67 @@
+/// Where a blended draw, such as a trail or a particle emitter, goes among
+/// the other draws of its pass.
+///
+/// Draws are sorted by layer, then by key, and draws with the same layer and
+/// key from back to front so they blend over what is behind them. Draws
+/// without an order are on layer 0 with key 0, so they are only sorted by
+/// depth until an order is given. Isotope reads it as a component of entities
+/// with trails or particle emitters.
+///
+/// # Example
+/// ```ignore
+/// // Sparks always blend over the smoke they fly through
+/// compound.add_molecule(smoke, DrawOrder::default());
+/// compound.add_molecule(sparks, DrawOrder::layer(0).with_key(1));
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DrawOrder {
+    /// Draws on higher layers are drawn over the draws of lower ones
+    pub layer: i32,
+    /// Draws with a higher key are drawn over the draws with lower ones on the same layer
+    pub key: i32,
+}
+
+impl DrawOrder {
+    /// Creates an order on a layer, sorted by depth within it
+    pub const fn layer(layer: i32) -> Self {
+        Self { layer, key: 0 }
+    }
+
+    /// Returns the order with an explicit key within its layer
+    pub const fn with_key(self, key: i32) -> Self {
+        Self { key, ..self }
+    }
+}
+
+/// Sorts draws into the order they are drawn in.
+///
+/// # Arguments
+/// * `draws` - Every draw with its order and where it is in world space
+/// * `eye` - Position of the camera in world space
+///
+/// # Returns
+/// The draws, the one drawn first first
+pub(crate) fn sort_draws<'a, T, I>(draws: I, eye: [f32; 3]) -> Vec<&'a T>
+where
+    I: IntoIterator<Item = (DrawOrder, [f32; 3], &'a T)>,
+{
+    let distance = |position: [f32; 3]| {
+        (0..3)
+            .map(|axis| (position[axis] - eye[axis]).powi(2))
+            .sum::<f32>()
+    };
+
+    let mut draws = draws
+        .into_iter()
+        .map(|(order, position, draw)| (order, distance(position), draw))
+        .collect::<Vec<_>>();
+
+    // Farther draws first within an order
+    draws.sort_by(|(order_a, distance_a, _), (order_b, distance_b, _)| {
+        order_a
+            .cmp(order_b)
+            .then_with(|| distance_b.total_cmp(distance_a))
+    });
+
+    draws.into_iter().map(|(_, _, draw)| draw).collect()
+}
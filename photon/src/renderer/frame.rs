@@ -0,0 +1,86 @@
+use gpu_controller::{BufferAddress, GpuController, ShaderModule};
+
+use crate::renderer::{FRAME_CONSTANTS_WGSL, volumetrics::FogSettings};
+
+/// Where the renderer writes the `FrameConstants` in the buffer of a camera,
+/// right after the camera's own matrices
+pub const FRAME_CONSTANTS_OFFSET: BufferAddress = 272;
+
+// Frames the jitter sequence repeats after
+const JITTER_SEQUENCE_LENGTH: u32 = 8;
+
+/// The part of `FrameConstants` in WGSL that is the same for every camera of
+/// a frame
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct FrameConstants {
+    time: f32,
+    delta_t: f32,
+    frame_index: u32,
+    fog_enabled: u32,
+    resolution: [f32; 2],
+    jitter: [f32; 2],
+    fog_color: [f32; 3],
+    fog_density: f32,
+    fog_anisotropy: f32,
+    fog_max_distance: f32,
+    _padding: [f32; 2],
+}
+
+impl FrameConstants {
+    pub(crate) fn new(resolution: (u32, u32)) -> Self {
+        let mut frame_constants = Self::default();
+        frame_constants.set_resolution(resolution);
+        frame_constants
+    }
+
+    /// Starts the next frame, advancing the frame index and jitter
+    ///
+    /// # Arguments
+    /// * `time` - Seconds since the application started
+    /// * `delta_t` - Seconds since the last frame
+    pub(crate) fn begin_frame(&mut self, time: f32, delta_t: f32) {
+        self.time = time;
+        self.delta_t = delta_t;
+        self.frame_index = self.frame_index.wrapping_add(1);
+
+        let sample = self.frame_index % JITTER_SEQUENCE_LENGTH + 1;
+        self.jitter = [halton(sample, 2) - 0.5, halton(sample, 3) - 0.5];
+    }
+
+    pub(crate) fn set_resolution(&mut self, resolution: (u32, u32)) {
+        self.resolution = [resolution.0 as f32, resolution.1 as f32];
+    }
+
+    pub(crate) fn set_fog(&mut self, fog: Option<&FogSettings>) {
+        match fog {
+            Some(fog) => {
+                self.fog_enabled = 1;
+                self.fog_color = fog.color;
+                self.fog_density = fog.density;
+                self.fog_anisotropy = fog.anisotropy;
+                self.fog_max_distance = fog.max_distance;
+            }
+            None => self.fog_enabled = 0,
+        }
+    }
+}
+
+/// Compiles a built-in shader with the frame constants declared before it
+pub(crate) fn create_frame_shader(gpu_controller: &GpuController, source: &str) -> ShaderModule {
+    gpu_controller.create_shader(&format!("{}\n{}", FRAME_CONSTANTS_WGSL, source))
+}
+
+// Low discrepancy sequence the jitter walks through, from 0 to 1
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut fraction = 1.0;
+    let mut result = 0.0;
+
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+
+    result
+}
@@ -0,0 +1,191 @@
+use std::{mem, sync::Arc};
+
+use anyhow::Result;
+use gpu_controller::{
+    BlendState, Buffer, BufferAddress, BufferDescriptor, BufferUsages, Buffered, ColorTargetState,
+    ColorWrites, CommandEncoder, FragmentState, FrontFace, GpuController, LoadOp, MultisampleState,
+    Operations, PipelineCompilationOptions, PipelineLayoutDescriptor, PolygonMode, PrimitiveState,
+    PrimitiveTopology, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
+    RenderPipelineDescriptor, StoreOp, TextureView, VertexAttribute, VertexBufferLayout,
+    VertexFormat, VertexState, VertexStepMode,
+};
+
+use crate::camera::PhotonCamera;
+
+use super::CAMERA_BIND_GROUP;
+use super::frame::create_frame_shader;
+
+// Number of vertices the gizmo buffer starts with, it grows as needed
+const INITIAL_GIZMO_VERTICES: u64 = 1024;
+
+/// One end of a debug line drawn on top of the rendered scene.
+///
+/// Every two vertices form a line segment.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GizmoVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+impl GizmoVertex {
+    pub fn new(position: [f32; 3], color: [f32; 4]) -> Self {
+        Self { position, color }
+    }
+}
+
+impl Buffered for GizmoVertex {
+    fn desc() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: mem::size_of::<GizmoVertex>() as BufferAddress,
+            step_mode: VertexStepMode::Vertex,
+            attributes: &[
+                // Position
+                VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: VertexFormat::Float32x3,
+                },
+                // Color
+                VertexAttribute {
+                    offset: mem::size_of::<[f32; 3]>() as BufferAddress,
+                    shader_location: 1,
+                    format: VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+/// Draws debug lines as an overlay after the scene has been lit.
+pub(crate) struct GizmoRenderer {
+    gpu_controller: Arc<GpuController>,
+    pipeline: RenderPipeline,
+
+    vertex_buffer: Buffer,
+    capacity: u64,
+    num_vertices: u32,
+}
+
+impl GizmoRenderer {
+    pub(crate) fn new(gpu_controller: Arc<GpuController>) -> Result<Self> {
+        let pipeline_layout = gpu_controller.read_layouts(|layouts| {
+            gpu_controller.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("Gizmo Pipeline Layout"),
+                bind_group_layouts: &[&layouts["Camera"]],
+                push_constant_ranges: &[],
+            })
+        })?;
+
+        let shader_module =
+            create_frame_shader(&gpu_controller, include_str!("shaders/gizmos.wgsl"));
+
+        let pipeline = gpu_controller.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Gizmo Pipeline"),
+            cache: None,
+            multiview: None,
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader_module,
+                entry_point: Some("vs_main"),
+                buffers: &[GizmoVertex::desc()],
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &shader_module,
+                entry_point: Some("fs_main"),
+                targets: &[Some(ColorTargetState {
+                    format: gpu_controller.read_surface_config(|config| config.format)?,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::LineList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            // Gizmos are an overlay and are never hidden by geometry
+            depth_stencil: None,
+            multisample: MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        });
+
+        let vertex_buffer = Self::create_vertex_buffer(&gpu_controller, INITIAL_GIZMO_VERTICES);
+
+        Ok(Self {
+            gpu_controller,
+            pipeline,
+            vertex_buffer,
+            capacity: INITIAL_GIZMO_VERTICES,
+            num_vertices: 0,
+        })
+    }
+
+    fn create_vertex_buffer(gpu_controller: &GpuController, capacity: u64) -> Buffer {
+        gpu_controller.create_buffer(&BufferDescriptor {
+            label: Some("Gizmo Vertex Buffer"),
+            size: capacity * mem::size_of::<GizmoVertex>() as u64,
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Replaces the lines that are drawn every frame
+    pub(crate) fn update_gizmos(&mut self, vertices: &[GizmoVertex]) {
+        // Drop a dangling vertex so every line has two ends
+        let vertices = &vertices[..vertices.len() - vertices.len() % 2];
+
+        if vertices.len() as u64 > self.capacity {
+            self.capacity = (vertices.len() as u64).next_power_of_two();
+            self.vertex_buffer = Self::create_vertex_buffer(&self.gpu_controller, self.capacity);
+        }
+
+        if !vertices.is_empty() {
+            self.gpu_controller.write_buffer(
+                &self.vertex_buffer,
+                0,
+                bytemuck::cast_slice(vertices),
+            );
+        }
+
+        self.num_vertices = vertices.len() as u32;
+    }
+
+    pub(crate) fn render<C>(&self, encoder: &mut CommandEncoder, view: &TextureView, camera: &C)
+    where
+        C: PhotonCamera,
+    {
+        if self.num_vertices == 0 {
+            return;
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Gizmo Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(CAMERA_BIND_GROUP, camera.bind_group(), &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.num_vertices, 0..1);
+    }
+}
@@ -0,0 +1,127 @@
+use std::sync::{
+    Arc, RwLock,
+    atomic::{AtomicU64, Ordering},
+};
+
+use gpu_controller::{BindGroup, CommandEncoder, GpuController, TextureFormat, TextureView};
+use log::error;
+
+/// Points in the frame of every camera custom passes can be inserted at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RenderStage {
+    /// The G-buffer is filled and nothing is lit yet, the lighting pass
+    /// replaces the color of cameras that clear
+    AfterGeometry,
+    /// The opaque geometry is lit, before volumetrics
+    AfterOpaque,
+    /// Passes draw into the distortion buffer instead of the color, adding
+    /// how far in texture coordinates the scene is read from as
+    /// `DISTORTION_FORMAT`. Cameras only distort while this stage has a pass,
+    /// a distortion source exists or particles distort.
+    Distortion,
+    /// Volumetrics are done, before depth of field and motion blur
+    BeforePostProcess,
+    /// Depth of field and motion blur are done, before gizmos
+    AfterPostProcess,
+    /// Everything is drawn, right before the camera's frame is submitted
+    Overlay,
+}
+
+/// What a custom pass can read and draw to.
+///
+/// The views are of the textures the frame is being rendered to, passes
+/// load them to draw over what the stages before them drew.
+pub struct RenderPassContext<'a> {
+    pub gpu_controller: &'a GpuController,
+    /// The camera being rendered, laid out as the "Camera" layout. Bind it at
+    /// group 0 to read the `FRAME_CONSTANTS_WGSL` of the frame
+    pub camera_bind_group: &'a BindGroup,
+    /// The G-buffer of the camera, laid out as the "G-Buffer" layout
+    pub g_buffer_bind_group: &'a BindGroup,
+    /// The color the stage leaves behind, an intermediate texture before
+    /// the lens stages of cameras that have them, or the distortion buffer
+    /// in the `Distortion` stage
+    pub color_view: &'a TextureView,
+    pub color_format: TextureFormat,
+    /// Depth of the geometry, as `Depth32Float`
+    pub depth_view: &'a TextureView,
+}
+
+/// Identifies a pass added with `Renderer::add_pass` so it can be removed again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RenderPassHookId(u64);
+
+type RenderPassHook = Arc<dyn Fn(&RenderPassContext, &mut CommandEncoder) + Send + Sync>;
+
+/// Custom passes inserted into the frame by the user.
+#[derive(Default)]
+pub(crate) struct RenderPassHooks {
+    hooks: RwLock<Vec<(RenderPassHookId, RenderStage, RenderPassHook)>>,
+    next_id: AtomicU64,
+}
+
+impl RenderPassHooks {
+    pub(crate) fn add<F>(&self, stage: RenderStage, hook: F) -> RenderPassHookId
+    where
+        F: Fn(&RenderPassContext, &mut CommandEncoder) + Send + Sync + 'static,
+    {
+        let id = RenderPassHookId(self.next_id.fetch_add(1, Ordering::Relaxed));
+
+        match self.hooks.write() {
+            Ok(mut hooks) => hooks.push((id, stage, Arc::new(hook))),
+            Err(err) => error!("Failed to add render pass: {}", err),
+        }
+
+        id
+    }
+
+    /// Returns whether any pass was added to the stage
+    pub(crate) fn has_stage(&self, stage: RenderStage) -> bool {
+        match self.hooks.read() {
+            Ok(hooks) => hooks.iter().any(|(_, hook_stage, _)| *hook_stage == stage),
+            Err(err) => {
+                error!("Failed to read render passes: {}", err);
+                false
+            }
+        }
+    }
+
+    pub(crate) fn remove(&self, id: RenderPassHookId) -> bool {
+        match self.hooks.write() {
+            Ok(mut hooks) => {
+                let count = hooks.len();
+                hooks.retain(|(hook_id, _, _)| *hook_id != id);
+                hooks.len() != count
+            }
+            Err(err) => {
+                error!("Failed to remove render pass: {}", err);
+                false
+            }
+        }
+    }
+
+    /// Records the passes of a stage in the order they were added
+    pub(crate) fn run(
+        &self,
+        stage: RenderStage,
+        context: &RenderPassContext,
+        encoder: &mut CommandEncoder,
+    ) {
+        // Released before recording so passes can add or remove passes
+        let hooks: Vec<RenderPassHook> = match self.hooks.read() {
+            Ok(hooks) => hooks
+                .iter()
+                .filter(|(_, hook_stage, _)| *hook_stage == stage)
+                .map(|(_, _, hook)| hook.clone())
+                .collect(),
+            Err(err) => {
+                error!("Failed to run render passes: {}", err);
+                return;
+            }
+        };
+
+        for hook in hooks {
+            hook(context, encoder);
+        }
+    }
+}
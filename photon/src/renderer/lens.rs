@@ -0,0 +1,486 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use gpu_controller::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BlendComponent, BlendFactor,
+    BlendOperation, BlendState, Buffer, BufferBindingType, BufferInitDescriptor, BufferUsages,
+    Color, ColorTargetState, ColorWrites, CommandEncoder, Extent3d, FilterMode, FragmentState,
+    FrontFace, GpuController, LoadOp, MultisampleState, Operations, PipelineCompilationOptions,
+    PipelineLayoutDescriptor, PolygonMode, PrimitiveState, PrimitiveTopology,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    Sampler, SamplerBindingType, SamplerDescriptor, ShaderStages, StoreOp, Texture,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+    TextureView, TextureViewDescriptor, TextureViewDimension, VertexState,
+};
+use log::error;
+
+use crate::camera::PhotonCamera;
+
+use super::CAMERA_BIND_GROUP;
+use super::frame::create_frame_shader;
+
+const LENS_BIND_GROUP: u32 = 1;
+const G_BUFFER_BIND_GROUP: u32 = 2;
+
+/// Format of the motion vectors objects that moved write in the motion pass
+pub const MOTION_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// Blurs what is out of focus, the way a camera lens does.
+///
+/// Points blur more the further they are from the focus distance, reaching
+/// the largest blur `focus_range` in front of or behind it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepthOfField {
+    /// Distance from the camera that is in focus
+    pub focus_distance: f32,
+    /// Distance from the focus over which the blur grows to its largest
+    pub focus_range: f32,
+    /// Radius in pixels of the largest blur
+    pub max_radius: f32,
+}
+
+impl Default for DepthOfField {
+    fn default() -> Self {
+        Self {
+            focus_distance: 10.0,
+            focus_range: 20.0,
+            max_radius: 8.0,
+        }
+    }
+}
+
+/// Blurs what moves across the screen, from the camera moving or from objects moving.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MotionBlur {
+    /// Share of the frame the shutter is open for, scales the length of the blur
+    pub shutter: f32,
+    /// Samples taken along the motion of every pixel, at most 32
+    pub samples: u32,
+}
+
+impl Default for MotionBlur {
+    fn default() -> Self {
+        Self {
+            shutter: 0.5,
+            samples: 12,
+        }
+    }
+}
+
+// Lens effects of a camera as laid out in the lens shader
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct LensUniform {
+    focus_distance: f32,
+    focus_range: f32,
+    max_radius: f32,
+    depth_of_field: u32,
+    shutter: f32,
+    samples: u32,
+    motion_blur: u32,
+    _padding: u32,
+}
+
+impl LensUniform {
+    fn new(depth_of_field: Option<DepthOfField>, motion_blur: Option<MotionBlur>) -> Self {
+        let dof = depth_of_field.unwrap_or_default();
+        let blur = motion_blur.unwrap_or_default();
+
+        Self {
+            focus_distance: dof.focus_distance,
+            focus_range: dof.focus_range,
+            max_radius: dof.max_radius,
+            depth_of_field: depth_of_field.is_some() as u32,
+            shutter: blur.shutter,
+            samples: blur.samples,
+            motion_blur: motion_blur.is_some() as u32,
+            _padding: 0,
+        }
+    }
+}
+
+/// Depth of field and motion blur at the end of the frame.
+///
+/// Cameras with lens effects light the scene into an intermediate texture
+/// instead of their output. The depth of field stage gathers the pixels
+/// around every out of focus pixel into a second texture, then the motion
+/// blur stage samples along the motion of every pixel into the output.
+/// Either stage copies its input through when the camera turns it off.
+pub(crate) struct LensRenderer {
+    gpu_controller: Arc<GpuController>,
+    depth_of_field_pipeline: RenderPipeline,
+    motion_blur_pipeline: RenderPipeline,
+    lens_bind_group_layout: BindGroupLayout,
+    lens_buffer: Buffer,
+    sampler: Sampler,
+
+    scene_texture: Texture,
+    focused_texture: Texture,
+    motion_texture: Texture,
+    depth_of_field_bind_group: BindGroup,
+    motion_blur_bind_group: BindGroup,
+}
+
+impl LensRenderer {
+    pub(crate) fn new(gpu_controller: Arc<GpuController>) -> Result<Self> {
+        let lens_bind_group_layout =
+            gpu_controller.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("Lens Bind Group Layout"),
+                entries: &[
+                    // Source
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Texture {
+                            sample_type: TextureSampleType::Float { filterable: true },
+                            view_dimension: TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    // Motion vectors
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Texture {
+                            sample_type: TextureSampleType::Float { filterable: true },
+                            view_dimension: TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    // Sampler
+                    BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    // Lens
+                    BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let lens_buffer = gpu_controller.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Lens Buffer"),
+            contents: bytemuck::cast_slice(&[LensUniform::new(None, None)]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let sampler = gpu_controller.create_sampler(&SamplerDescriptor {
+            label: Some("Lens Sampler"),
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let pipeline_layout = gpu_controller.read_layouts(|layouts| {
+            gpu_controller.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("Lens Pipeline Layout"),
+                bind_group_layouts: &[
+                    &layouts["Camera"],
+                    &lens_bind_group_layout,
+                    &layouts["G-Buffer"],
+                ],
+                push_constant_ranges: &[],
+            })
+        })?;
+
+        let shader_module = create_frame_shader(&gpu_controller, include_str!("shaders/lens.wgsl"));
+        let format = gpu_controller.read_surface_config(|config| config.format)?;
+
+        let create_pipeline = |label, entry_point, blend| {
+            gpu_controller.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some(label),
+                cache: None,
+                multiview: None,
+                layout: Some(&pipeline_layout),
+                vertex: VertexState {
+                    module: &shader_module,
+                    entry_point: Some("vs_main"),
+                    buffers: &[],
+                    compilation_options: PipelineCompilationOptions::default(),
+                },
+                fragment: Some(FragmentState {
+                    module: &shader_module,
+                    entry_point: Some(entry_point),
+                    targets: &[Some(ColorTargetState {
+                        format,
+                        blend,
+                        write_mask: ColorWrites::ALL,
+                    })],
+                    compilation_options: PipelineCompilationOptions::default(),
+                }),
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: FrontFace::Ccw,
+                    cull_mode: None,
+                    polygon_mode: PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: None,
+                multisample: MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+            })
+        };
+
+        let depth_of_field_pipeline =
+            create_pipeline("Depth Of Field Pipeline", "fs_depth_of_field", None);
+
+        // Drawn over the output the same way the lighting pass is, so cameras
+        // that don't clear still keep what was drawn before them
+        let motion_blur_pipeline = create_pipeline(
+            "Motion Blur Pipeline",
+            "fs_motion_blur",
+            Some(BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::SrcAlpha,
+                    dst_factor: BlendFactor::OneMinusSrcAlpha,
+                    operation: BlendOperation::Add,
+                },
+                alpha: BlendComponent::OVER,
+            }),
+        );
+
+        let (scene_texture, focused_texture, motion_texture) = Self::create_textures(
+            &gpu_controller,
+            Self::surface_size(&gpu_controller)?,
+            format,
+        );
+
+        let (depth_of_field_bind_group, motion_blur_bind_group) = Self::create_bind_groups(
+            &gpu_controller,
+            &lens_bind_group_layout,
+            &lens_buffer,
+            &sampler,
+            &scene_texture,
+            &focused_texture,
+            &motion_texture,
+        );
+
+        Ok(Self {
+            gpu_controller,
+            depth_of_field_pipeline,
+            motion_blur_pipeline,
+            lens_bind_group_layout,
+            lens_buffer,
+            sampler,
+            scene_texture,
+            focused_texture,
+            motion_texture,
+            depth_of_field_bind_group,
+            motion_blur_bind_group,
+        })
+    }
+
+    fn surface_size(gpu_controller: &GpuController) -> Result<Extent3d> {
+        gpu_controller.read_surface_config(|config| Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        })
+    }
+
+    fn create_textures(
+        gpu_controller: &GpuController,
+        size: Extent3d,
+        format: TextureFormat,
+    ) -> (Texture, Texture, Texture) {
+        let create_texture = |label, format| {
+            gpu_controller.create_texture(&TextureDescriptor {
+                label: Some(label),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+        };
+
+        (
+            create_texture("Lens Scene", format),
+            create_texture("Lens Focused", format),
+            create_texture("Lens Motion", MOTION_FORMAT),
+        )
+    }
+
+    fn create_bind_groups(
+        gpu_controller: &GpuController,
+        layout: &BindGroupLayout,
+        lens_buffer: &Buffer,
+        sampler: &Sampler,
+        scene_texture: &Texture,
+        focused_texture: &Texture,
+        motion_texture: &Texture,
+    ) -> (BindGroup, BindGroup) {
+        let motion_view = motion_texture.create_view(&TextureViewDescriptor::default());
+
+        let create_bind_group = |label, source: &Texture| {
+            gpu_controller.create_bind_group(&BindGroupDescriptor {
+                label: Some(label),
+                layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(
+                            &source.create_view(&TextureViewDescriptor::default()),
+                        ),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::TextureView(&motion_view),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: BindingResource::Sampler(sampler),
+                    },
+                    BindGroupEntry {
+                        binding: 3,
+                        resource: lens_buffer.as_entire_binding(),
+                    },
+                ],
+            })
+        };
+
+        (
+            create_bind_group("Depth Of Field Bind Group", scene_texture),
+            create_bind_group("Motion Blur Bind Group", focused_texture),
+        )
+    }
+
+    /// Returns whether the camera renders through the lens stages
+    pub(crate) fn enabled<C>(camera: &C) -> bool
+    where
+        C: PhotonCamera,
+    {
+        camera.depth_of_field().is_some() || camera.motion_blur().is_some()
+    }
+
+    /// Returns a view of the texture the scene is lit into before the lens stages
+    pub(crate) fn scene_view(&self) -> TextureView {
+        self.scene_texture
+            .create_view(&TextureViewDescriptor::default())
+    }
+
+    /// Returns a view of the texture objects that moved write their motion vectors to
+    pub(crate) fn motion_view(&self) -> TextureView {
+        self.motion_texture
+            .create_view(&TextureViewDescriptor::default())
+    }
+
+    /// Runs the lens stages of a camera from the scene texture into the output
+    ///
+    /// # Arguments
+    /// * `encoder` - The encoder of the camera's frame
+    /// * `view` - The output of the camera
+    /// * `camera` - The camera whose lens effects are applied
+    /// * `g_buffer_bind_group` - The G-buffer the scene was lit from
+    pub(crate) fn render<C>(
+        &self,
+        encoder: &mut CommandEncoder,
+        view: &TextureView,
+        camera: &C,
+        g_buffer_bind_group: &BindGroup,
+    ) where
+        C: PhotonCamera,
+    {
+        self.gpu_controller.write_buffer(
+            &self.lens_buffer,
+            0,
+            bytemuck::cast_slice(&[LensUniform::new(
+                camera.depth_of_field(),
+                camera.motion_blur(),
+            )]),
+        );
+
+        let focused_view = self
+            .focused_texture
+            .create_view(&TextureViewDescriptor::default());
+
+        let stages = [
+            (
+                "Depth Of Field Pass",
+                &self.depth_of_field_pipeline,
+                &self.depth_of_field_bind_group,
+                &focused_view,
+                LoadOp::Clear(Color::TRANSPARENT),
+            ),
+            (
+                "Motion Blur Pass",
+                &self.motion_blur_pipeline,
+                &self.motion_blur_bind_group,
+                view,
+                LoadOp::Load,
+            ),
+        ];
+
+        for (label, pipeline, bind_group, target, load) in stages {
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some(label),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: Operations {
+                        load,
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(CAMERA_BIND_GROUP, camera.bind_group(), &[]);
+            render_pass.set_bind_group(LENS_BIND_GROUP, bind_group, &[]);
+            render_pass.set_bind_group(G_BUFFER_BIND_GROUP, g_buffer_bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+
+    pub(crate) fn resize(&mut self, size: Extent3d) {
+        let format = match self
+            .gpu_controller
+            .read_surface_config(|config| config.format)
+        {
+            Ok(format) => format,
+            Err(err) => {
+                error!("Failed to read surface config: {}", err);
+                return;
+            }
+        };
+
+        (
+            self.scene_texture,
+            self.focused_texture,
+            self.motion_texture,
+        ) = Self::create_textures(&self.gpu_controller, size, format);
+
+        (self.depth_of_field_bind_group, self.motion_blur_bind_group) = Self::create_bind_groups(
+            &self.gpu_controller,
+            &self.lens_bind_group_layout,
+            &self.lens_buffer,
+            &self.sampler,
+            &self.scene_texture,
+            &self.focused_texture,
+            &self.motion_texture,
+        );
+    }
+}
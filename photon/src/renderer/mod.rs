@@ -2,17 +2,69 @@ use std::{collections::HashMap, sync::Arc};
 
 use anyhow::Result;
 use defered_renderer::DeferedRenderer3D;
-use gpu_controller::{BindGroupLayout, GpuController, RenderPass, Texture};
+use gpu_controller::{
+    CommandEncoder, ComputePass, GpuController, RenderPass, Texture, Vertex, VertexEncoding,
+};
 
-use crate::{Light, camera::PhotonCamera};
+use crate::{
+    Light,
+    camera::PhotonCamera,
+    photon_lighting::{IrradianceProbe, ProbeGridInfo},
+    renderer::{
+        distortion::DistortionSource,
+        draw_order::DrawOrder,
+        gizmos::GizmoVertex,
+        hooks::{RenderPassContext, RenderPassHookId, RenderStage},
+        particles::{ParticleCollider, ParticleEmitterSettings, ParticleMeshId},
+        trails::{TrailPoint, TrailSettings},
+        volumetrics::FogSettings,
+    },
+};
 
+pub mod culling;
 pub mod defered_renderer;
+pub mod distortion;
+pub mod draw_order;
+pub mod frame;
+pub mod gizmos;
+pub mod hooks;
+pub mod lens;
+pub mod particles;
+pub mod silhouette;
+pub mod trails;
+pub mod volumetrics;
 
 const CAMERA_BIND_GROUP: u32 = 0;
 const LIGHTS_BIND_GROUP: u32 = 1;
 pub const MATERIALS_BIND_GROUP: u32 = 1;
 pub const GLOBAL_TRANSFORM_BIND_GROUP: u32 = 2;
 
+/// WGSL functions for sampling a `TextureArray`, prepend it to the source of
+/// custom shaders that splat terrain layers or draw tiles
+pub const TEXTURE_ARRAY_WGSL: &str = include_str!("shaders/texture_array.wgsl");
+
+/// WGSL declaration of the frame constants every pass binds at group 0,
+/// prepend it to the source of custom shaders to read the camera matrices,
+/// time, resolution or fog of the frame through `frame`
+pub const FRAME_CONSTANTS_WGSL: &str = include_str!("shaders/frame.wgsl");
+
+/// The passes the geometry of a camera is drawn in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GeometryPass {
+    /// Fills the G-buffer, every visible object draws here
+    Surfaces,
+    /// Adds the viewmodel of a first person camera to the G-buffer, in front
+    /// of the surfaces and with the camera's viewmodel projection. Only runs
+    /// for cameras with a `ViewModelProjection`, only the viewmodel draws here
+    ViewModel,
+    /// Writes the motion vectors of cameras with motion blur, only objects
+    /// that moved since the last frame need to draw here
+    Motion,
+    /// Tints the parts of objects hidden behind other geometry over the lit
+    /// scene, only objects with a silhouette need to draw here
+    Silhouettes,
+}
+
 pub enum Renderer {
     Defered3D(DeferedRenderer3D),
 }
@@ -22,6 +74,18 @@ impl Renderer {
         Ok(Self::Defered3D(DeferedRenderer3D::new(gpu_controller)?))
     }
 
+    /// Starts a frame, updating the time every pass reads from the frame
+    /// constants. Call once a frame before rendering any camera.
+    ///
+    /// # Arguments
+    /// * `time` - Seconds since the application started
+    /// * `delta_t` - Seconds since the last frame
+    pub fn begin_frame(&mut self, time: f32, delta_t: f32) {
+        match self {
+            Self::Defered3D(renderer) => renderer.frame_constants.begin_frame(time, delta_t),
+        }
+    }
+
     pub fn update_lights(&mut self, lights: &[Light]) {
         match self {
             Self::Defered3D(renderer) => {
@@ -30,10 +94,229 @@ impl Renderer {
         }
     }
 
+    /// Replaces the irradiance probe grid that lights surfaces in place of a constant ambient term
+    pub fn set_light_probes(&mut self, grid: ProbeGridInfo, probes: &[IrradianceProbe]) {
+        match self {
+            Self::Defered3D(renderer) => {
+                renderer.lights_manager.set_light_probes(grid, probes);
+            }
+        }
+    }
+
+    /// Replaces some of the probes of the current grid, starting at probe `first`
+    pub fn update_light_probes(&mut self, first: usize, probes: &[IrradianceProbe]) {
+        match self {
+            Self::Defered3D(renderer) => {
+                renderer.lights_manager.update_light_probes(first, probes);
+            }
+        }
+    }
+
+    /// Removes the irradiance probe grid
+    pub fn clear_light_probes(&mut self) {
+        match self {
+            Self::Defered3D(renderer) => {
+                renderer.lights_manager.clear_light_probes();
+            }
+        }
+    }
+
+    /// Sets the fog lights scatter through after lighting, `None` turns volumetrics off
+    pub fn set_fog(&mut self, fog: Option<FogSettings>) {
+        match self {
+            Self::Defered3D(renderer) => {
+                renderer.frame_constants.set_fog(fog.as_ref());
+                renderer.volumetric_renderer.set_fog(fog);
+            }
+        }
+    }
+
+    /// Adds a particle emitter or updates the one with the id and index
+    ///
+    /// # Arguments
+    /// * `id` - The owner of the emitter, such as an entity
+    /// * `index` - Which of the owner's emitters it is
+    /// * `settings` - How the emitter spawns and moves its particles
+    /// * `origin` - Where in the world particles are spawned
+    /// * `direction` - The direction particles are spawned towards
+    /// * `emitting` - Whether new particles are spawned, living ones die out either way
+    pub fn update_particle_emitter(
+        &mut self,
+        id: u64,
+        index: u32,
+        settings: &ParticleEmitterSettings,
+        origin: [f32; 3],
+        direction: [f32; 3],
+        emitting: bool,
+    ) {
+        match self {
+            Self::Defered3D(renderer) => {
+                renderer.particle_renderer.update_emitter(
+                    (id, index),
+                    settings,
+                    origin,
+                    direction,
+                    emitting,
+                );
+            }
+        }
+    }
+
+    /// Sets where the particles of an emitter are drawn among the other
+    /// emitters, emitters keep their order until it is set again
+    pub fn set_particle_emitter_order(&mut self, id: u64, index: u32, order: DrawOrder) {
+        match self {
+            Self::Defered3D(renderer) => renderer.particle_renderer.set_order((id, index), order),
+        }
+    }
+
+    /// Removes the particle emitters whose id and index the closure returns false for
+    pub fn retain_particle_emitters<F>(&mut self, keep: F)
+    where
+        F: FnMut(u64, u32) -> bool,
+    {
+        match self {
+            Self::Defered3D(renderer) => renderer.particle_renderer.retain_emitters(keep),
+        }
+    }
+
+    /// Uploads a mesh that particles of emitters with it in their settings are drawn as
+    pub fn add_particle_mesh(&mut self, vertices: &[Vertex], indices: &[u32]) -> ParticleMeshId {
+        match self {
+            Self::Defered3D(renderer) => renderer.particle_renderer.add_mesh(vertices, indices),
+        }
+    }
+
+    /// Replaces the shapes particles collide with when their emitter uses
+    /// `ParticleCollision::Colliders`, only the first `MAX_PARTICLE_COLLIDERS` are used
+    pub fn set_particle_colliders(&mut self, colliders: &[ParticleCollider]) {
+        match self {
+            Self::Defered3D(renderer) => renderer.particle_renderer.set_colliders(colliders),
+        }
+    }
+
+    /// Moves every particle along by a frame, call once per frame before rendering.
+    ///
+    /// Particles using `ParticleCollision::Depth` collide with what the camera
+    /// drew last.
+    pub fn simulate_particles<C>(&mut self, camera: &C, delta_t: f32)
+    where
+        C: PhotonCamera,
+    {
+        match self {
+            Self::Defered3D(renderer) => renderer.simulate_particles(camera, delta_t),
+        }
+    }
+
+    /// Adds a trail or replaces the points of the one with the id
+    ///
+    /// # Arguments
+    /// * `id` - Identifies the trail between frames
+    /// * `settings` - How the trail looks
+    /// * `points` - The points of the trail from oldest to newest
+    pub fn update_trail(&mut self, id: u64, settings: &TrailSettings, points: &[TrailPoint]) {
+        match self {
+            Self::Defered3D(renderer) => renderer.trail_renderer.update_trail(id, settings, points),
+        }
+    }
+
+    /// Sets where a trail is drawn among the other trails, trails keep
+    /// their order until it is set again
+    pub fn set_trail_order(&mut self, id: u64, order: DrawOrder) {
+        match self {
+            Self::Defered3D(renderer) => renderer.trail_renderer.set_order(id, order),
+        }
+    }
+
+    /// Removes the trails whose id the closure returns false for
+    pub fn retain_trails<F>(&mut self, keep: F)
+    where
+        F: FnMut(u64) -> bool,
+    {
+        match self {
+            Self::Defered3D(renderer) => renderer.trail_renderer.retain_trails(keep),
+        }
+    }
+
+    /// Replaces the spheres that distort the scene, for heat haze and shockwaves
+    ///
+    /// # Arguments
+    /// * `sources` - The distortion sources, only the first `MAX_DISTORTION_SOURCES` are drawn
+    pub fn set_distortion_sources(&mut self, sources: &[DistortionSource]) {
+        match self {
+            Self::Defered3D(renderer) => renderer.distortion_renderer.set_sources(sources),
+        }
+    }
+
+    /// Sets whether cameras run the silhouette pass, turn it on while any
+    /// object draws in `GeometryPass::Silhouettes`
+    pub fn set_silhouettes(&mut self, enabled: bool) {
+        match self {
+            Self::Defered3D(renderer) => renderer.silhouette_renderer.set_enabled(enabled),
+        }
+    }
+
+    /// Replaces the debug lines drawn on top of every camera's output
+    pub fn update_gizmos(&mut self, vertices: &[GizmoVertex]) {
+        match self {
+            Self::Defered3D(renderer) => {
+                renderer.gizmo_renderer.update_gizmos(vertices);
+            }
+        }
+    }
+
+    /// Inserts a custom pass into the frame of every camera.
+    ///
+    /// The pass records into the camera's encoder at the stage, after the
+    /// passes added to the same stage before it.
+    ///
+    /// # Arguments
+    /// * `stage` - The point in the frame the pass runs at
+    /// * `hook` - Records the pass with the views of the frame
+    ///
+    /// # Returns
+    /// An id that removes the pass when passed to `remove_pass`
+    pub fn add_pass<F>(&self, stage: RenderStage, hook: F) -> RenderPassHookId
+    where
+        F: Fn(&RenderPassContext, &mut CommandEncoder) + Send + Sync + 'static,
+    {
+        match self {
+            Self::Defered3D(renderer) => renderer.render_hooks.add(stage, hook),
+        }
+    }
+
+    /// Removes a pass added with `add_pass`, returning whether it existed
+    pub fn remove_pass(&self, id: RenderPassHookId) -> bool {
+        match self {
+            Self::Defered3D(renderer) => renderer.render_hooks.remove(id),
+        }
+    }
+
+    /// Culls the instances of every object against the camera before it renders.
+    ///
+    /// Objects bind their culling data at `CULLING_BIND_GROUP` and dispatch
+    /// over their instances, the geometry pass of the camera then draws only
+    /// the instances left visible.
+    pub fn cull<C, F>(&self, camera: &C, cull_callback: F)
+    where
+        C: PhotonCamera,
+        F: FnOnce(&mut ComputePass),
+    {
+        match self {
+            Self::Defered3D(renderer) => renderer.culler.cull(camera, cull_callback),
+        }
+    }
+
+    /// Renders a camera into the output.
+    ///
+    /// The geometry callback draws the objects the camera sees, it runs once
+    /// for every `GeometryPass` the camera needs and every `VertexEncoding`,
+    /// with the pipeline of that encoding set. Meshes are only drawn in the
+    /// run of their own encoding.
     pub fn render<C, G>(&self, camera: &C, output: &Texture, geometry_callback: G)
     where
         C: PhotonCamera,
-        G: FnOnce(&mut RenderPass),
+        G: FnMut(&mut RenderPass, GeometryPass, VertexEncoding),
     {
         match self {
             Self::Defered3D(renderer) => _ = renderer.render(camera, output, geometry_callback),
@@ -0,0 +1,935 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Result;
+use gpu_controller::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BlendComponent, BlendFactor,
+    BlendOperation, BlendState, Buffer, BufferBindingType, BufferDescriptor, BufferInitDescriptor,
+    BufferUsages, ColorTargetState, ColorWrites, CommandEncoder, CompareFunction,
+    ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor, DepthBiasState,
+    DepthStencilState, FragmentState, FrontFace, GpuController, LoadOp, MultisampleState,
+    Operations, PipelineCompilationOptions, PipelineLayoutDescriptor, PolygonMode, PrimitiveState,
+    PrimitiveTopology, RenderPassColorAttachment, RenderPassDepthStencilAttachment,
+    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, ShaderStages, StencilState,
+    StoreOp, TextureFormat, TextureSampleType, TextureView, TextureViewDimension, VertexState,
+};
+use gpu_controller::{Buffered, Face, IndexFormat, Vertex};
+
+use crate::camera::PhotonCamera;
+
+use super::CAMERA_BIND_GROUP;
+use super::distortion::{ADDITIVE_BLENDING, DISTORTION_FORMAT};
+use super::draw_order::{DrawOrder, sort_draws};
+use super::frame::create_frame_shader;
+
+const SURFACES_BIND_GROUP: u32 = 1;
+const EMITTER_BIND_GROUP: u32 = 2;
+const DRAW_BIND_GROUP: u32 = 1;
+
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Most colliders particles are tested against, the rest are ignored
+pub const MAX_PARTICLE_COLLIDERS: usize = 64;
+
+/// How the particles of an emitter collide with the world.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParticleCollision {
+    /// Particles pass through everything
+    None,
+    /// Particles collide with what the camera drew, read back from the
+    /// depth of the G-buffer. Cheap, but particles off screen or behind
+    /// other geometry pass through.
+    Depth {
+        /// How much of the speed into a surface is kept when bouncing
+        restitution: f32,
+        /// How much of the speed along a surface is lost when bouncing
+        friction: f32,
+        /// How far behind a surface a particle still collides with it,
+        /// particles further behind are thought to be behind the object
+        thickness: f32,
+        /// Kills particles that hit something instead of bouncing them
+        kill: bool,
+    },
+    /// Particles collide with the planes and spheres given with
+    /// `Renderer::set_particle_colliders`, wherever they are.
+    Colliders {
+        restitution: f32,
+        friction: f32,
+        kill: bool,
+    },
+}
+
+/// A shape particles with `ParticleCollision::Colliders` collide with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParticleCollider {
+    /// Everything behind the plane is solid
+    Plane {
+        normal: [f32; 3],
+        distance: f32,
+    },
+    Sphere {
+        center: [f32; 3],
+        radius: f32,
+    },
+}
+
+/// A mesh added with `Renderer::add_particle_mesh` that particles can be drawn as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParticleMeshId(u32);
+
+/// How an emitter spawns and moves its particles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParticleEmitterSettings {
+    /// Most particles alive at once, the emitter's buffers are this big
+    pub max_particles: u32,
+    /// Particles spawned per second
+    pub rate: f32,
+    /// Angle in radians around the direction of the emitter particles are spawned within
+    pub spread: f32,
+    /// Smallest and largest speed particles are spawned with
+    pub speed: (f32, f32),
+    /// Shortest and longest seconds particles live for
+    pub lifetime: (f32, f32),
+    pub gravity: [f32; 3],
+    /// How much of their velocity particles lose per second
+    pub drag: f32,
+    /// Size of particles when they spawn and when they die
+    pub size: (f32, f32),
+    /// Color of particles when they spawn, blended to the end color over their life
+    pub start_color: [f32; 4],
+    pub end_color: [f32; 4],
+    pub collision: ParticleCollision,
+    /// The mesh every particle is drawn as, scaled by its size and turned
+    /// along its velocity, camera facing quads without one
+    pub mesh: Option<ParticleMeshId>,
+    /// How far particles offset the scene behind them in texture coordinates,
+    /// particles that distort are not drawn themselves
+    pub distortion: f32,
+    /// How fast time passes for the emitter and its particles, 1 is normal
+    /// speed and 0 freezes them
+    pub time_scale: f32,
+}
+
+impl Default for ParticleEmitterSettings {
+    fn default() -> Self {
+        Self {
+            max_particles: 1024,
+            rate: 64.0,
+            spread: 0.3,
+            speed: (1.0, 2.0),
+            lifetime: (1.0, 2.0),
+            gravity: [0.0, -9.81, 0.0],
+            drag: 0.1,
+            size: (0.1, 0.05),
+            start_color: [1.0; 4],
+            end_color: [1.0, 1.0, 1.0, 0.0],
+            collision: ParticleCollision::None,
+            mesh: None,
+            distortion: 0.0,
+            time_scale: 1.0,
+        }
+    }
+}
+
+// As laid out in the particle shaders
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct EmitterUniform {
+    origin: [f32; 3],
+    spawn_count: u32,
+    direction: [f32; 3],
+    spread: f32,
+    gravity: [f32; 3],
+    drag: f32,
+    start_color: [f32; 4],
+    end_color: [f32; 4],
+    speed: [f32; 2],
+    lifetime: [f32; 2],
+    size: [f32; 2],
+    delta_t: f32,
+    seed: u32,
+    collision: u32,
+    restitution: f32,
+    friction: f32,
+    kill_on_collision: u32,
+    collider_count: u32,
+    thickness: f32,
+    max_particles: u32,
+    radius: f32,
+    distortion: f32,
+    _padding: [u32; 3],
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct ColliderUniform {
+    shape: [f32; 4],
+    kind: u32,
+    _padding: [u32; 3],
+}
+
+impl From<&ParticleCollider> for ColliderUniform {
+    fn from(collider: &ParticleCollider) -> Self {
+        let (shape, kind) = match *collider {
+            ParticleCollider::Plane { normal, distance } => {
+                ([normal[0], normal[1], normal[2], distance], 0)
+            }
+            ParticleCollider::Sphere { center, radius } => {
+                ([center[0], center[1], center[2], radius], 1)
+            }
+        };
+
+        Self {
+            shape,
+            kind,
+            _padding: [0; 3],
+        }
+    }
+}
+
+// Position, age, velocity and lifetime of a particle
+const PARTICLE_SIZE: u64 = 32;
+
+struct ParticleMesh {
+    vertex_buffer: Buffer,
+    index_buffer: Buffer,
+    index_count: u32,
+}
+
+/// Identifies an emitter by its owner and which of the owner's emitters it is
+type EmitterKey = (u64, u32);
+
+/// The particles of an emitter and where they spawn this frame
+struct GpuEmitter {
+    settings: ParticleEmitterSettings,
+    uniform: EmitterUniform,
+    order: DrawOrder,
+    // Particles owed from earlier frames that spawned less than one
+    spawn_debt: f32,
+    emitter_buffer: Buffer,
+    spawned_buffer: Buffer,
+    simulate_bind_group: BindGroup,
+    draw_bind_group: BindGroup,
+}
+
+impl GpuEmitter {
+    fn distorts(&self) -> bool {
+        self.settings.distortion != 0.0
+    }
+}
+
+/// Simulates particles on the GPU and draws them as camera facing quads or
+/// instanced meshes.
+///
+/// Every emitter owns a pool of particles that lives on the GPU. Dead
+/// particles are spawned again at the emitter, living ones move with gravity
+/// and drag and collide as their `ParticleCollision` says, all in one
+/// dispatch per emitter every frame.
+pub(crate) struct ParticleRenderer {
+    gpu_controller: Arc<GpuController>,
+    simulate_pipeline: ComputePipeline,
+    draw_pipeline: RenderPipeline,
+    mesh_pipeline: RenderPipeline,
+    distortion_pipeline: RenderPipeline,
+    surfaces_layout: BindGroupLayout,
+    emitter_layout: BindGroupLayout,
+    draw_layout: BindGroupLayout,
+    collider_buffer: Buffer,
+    collider_count: u32,
+    emitters: HashMap<EmitterKey, GpuEmitter>,
+    meshes: Vec<ParticleMesh>,
+    frame: u32,
+}
+
+impl ParticleRenderer {
+    pub(crate) fn new(gpu_controller: Arc<GpuController>) -> Result<Self> {
+        let texture_entry = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Texture {
+                sample_type: TextureSampleType::Float { filterable: false },
+                view_dimension: TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let buffer_entry = |binding, visibility, ty| BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let surfaces_layout = gpu_controller.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Particle Surfaces Bind Group Layout"),
+            entries: &[texture_entry(0), texture_entry(1)],
+        });
+
+        let emitter_layout = gpu_controller.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Particle Emitter Bind Group Layout"),
+            entries: &[
+                buffer_entry(0, ShaderStages::COMPUTE, BufferBindingType::Uniform),
+                buffer_entry(
+                    1,
+                    ShaderStages::COMPUTE,
+                    BufferBindingType::Storage { read_only: false },
+                ),
+                buffer_entry(
+                    2,
+                    ShaderStages::COMPUTE,
+                    BufferBindingType::Storage { read_only: false },
+                ),
+                buffer_entry(
+                    3,
+                    ShaderStages::COMPUTE,
+                    BufferBindingType::Storage { read_only: true },
+                ),
+            ],
+        });
+
+        let draw_layout = gpu_controller.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Particle Draw Bind Group Layout"),
+            entries: &[
+                buffer_entry(0, ShaderStages::VERTEX, BufferBindingType::Uniform),
+                buffer_entry(
+                    1,
+                    ShaderStages::VERTEX,
+                    BufferBindingType::Storage { read_only: true },
+                ),
+            ],
+        });
+
+        let simulate_layout = gpu_controller.read_layouts(|layouts| {
+            gpu_controller.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("Particle Simulation Pipeline Layout"),
+                bind_group_layouts: &[&layouts["Camera"], &surfaces_layout, &emitter_layout],
+                push_constant_ranges: &[],
+            })
+        })?;
+
+        let draw_pipeline_layout = gpu_controller.read_layouts(|layouts| {
+            gpu_controller.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("Particle Draw Pipeline Layout"),
+                bind_group_layouts: &[&layouts["Camera"], &draw_layout],
+                push_constant_ranges: &[],
+            })
+        })?;
+
+        let simulate_module = create_frame_shader(
+            &gpu_controller,
+            include_str!("shaders/particle_simulation.wgsl"),
+        );
+        let draw_module =
+            create_frame_shader(&gpu_controller, include_str!("shaders/particles.wgsl"));
+
+        let simulate_pipeline =
+            gpu_controller.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some("Particle Simulation Pipeline"),
+                layout: Some(&simulate_layout),
+                module: &simulate_module,
+                entry_point: Some("cs_simulate"),
+                compilation_options: PipelineCompilationOptions::default(),
+                cache: None,
+            });
+
+        let draw_pipeline = gpu_controller.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Particle Draw Pipeline"),
+            cache: None,
+            multiview: None,
+            layout: Some(&draw_pipeline_layout),
+            vertex: VertexState {
+                module: &draw_module,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &draw_module,
+                entry_point: Some("fs_main"),
+                targets: &[Some(ColorTargetState {
+                    format: gpu_controller.read_surface_config(|config| config.format)?,
+                    blend: Some(BlendState {
+                        color: BlendComponent {
+                            src_factor: BlendFactor::SrcAlpha,
+                            dst_factor: BlendFactor::OneMinusSrcAlpha,
+                            operation: BlendOperation::Add,
+                        },
+                        alpha: BlendComponent::OVER,
+                    }),
+                    write_mask: ColorWrites::ALL,
+                })],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            // Hidden behind the geometry, without hiding each other
+            depth_stencil: Some(DepthStencilState {
+                format: TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                depth_compare: CompareFunction::Less,
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default(),
+            }),
+            multisample: MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        });
+
+        // Solid meshes hide each other, unlike the soft quads
+        let mesh_pipeline = gpu_controller.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Particle Mesh Pipeline"),
+            cache: None,
+            multiview: None,
+            layout: Some(&draw_pipeline_layout),
+            vertex: VertexState {
+                module: &draw_module,
+                entry_point: Some("vs_mesh"),
+                buffers: &[Vertex::desc()],
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &draw_module,
+                entry_point: Some("fs_mesh"),
+                targets: &[Some(ColorTargetState {
+                    format: gpu_controller.read_surface_config(|config| config.format)?,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: Some(Face::Back),
+                polygon_mode: PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(DepthStencilState {
+                format: TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: CompareFunction::Less,
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default(),
+            }),
+            multisample: MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        });
+
+        // Distorting particles add their offsets into the distortion buffer
+        let distortion_pipeline =
+            gpu_controller.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("Particle Distortion Pipeline"),
+                cache: None,
+                multiview: None,
+                layout: Some(&draw_pipeline_layout),
+                vertex: VertexState {
+                    module: &draw_module,
+                    entry_point: Some("vs_main"),
+                    buffers: &[],
+                    compilation_options: PipelineCompilationOptions::default(),
+                },
+                fragment: Some(FragmentState {
+                    module: &draw_module,
+                    entry_point: Some("fs_distortion"),
+                    targets: &[Some(ColorTargetState {
+                        format: DISTORTION_FORMAT,
+                        blend: Some(ADDITIVE_BLENDING),
+                        write_mask: ColorWrites::ALL,
+                    })],
+                    compilation_options: PipelineCompilationOptions::default(),
+                }),
+                primitive: PrimitiveState {
+                    topology: PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: FrontFace::Ccw,
+                    cull_mode: None,
+                    polygon_mode: PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: Some(DepthStencilState {
+                    format: TextureFormat::Depth32Float,
+                    depth_write_enabled: false,
+                    depth_compare: CompareFunction::Less,
+                    stencil: StencilState::default(),
+                    bias: DepthBiasState::default(),
+                }),
+                multisample: MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+            });
+
+        let collider_buffer = gpu_controller.create_buffer(&BufferDescriptor {
+            label: Some("Particle Collider Buffer"),
+            size: (MAX_PARTICLE_COLLIDERS * size_of::<ColliderUniform>()) as u64,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Ok(Self {
+            gpu_controller,
+            simulate_pipeline,
+            draw_pipeline,
+            mesh_pipeline,
+            distortion_pipeline,
+            surfaces_layout,
+            emitter_layout,
+            draw_layout,
+            collider_buffer,
+            collider_count: 0,
+            emitters: HashMap::new(),
+            meshes: Vec::new(),
+            frame: 0,
+        })
+    }
+
+    /// Replaces the shapes particles with `ParticleCollision::Colliders` collide with
+    pub(crate) fn set_colliders(&mut self, colliders: &[ParticleCollider]) {
+        let colliders = colliders
+            .iter()
+            .take(MAX_PARTICLE_COLLIDERS)
+            .map(ColliderUniform::from)
+            .collect::<Vec<_>>();
+
+        self.collider_count = colliders.len() as u32;
+        if !colliders.is_empty() {
+            self.gpu_controller.write_buffer(
+                &self.collider_buffer,
+                0,
+                bytemuck::cast_slice(&colliders),
+            );
+        }
+    }
+
+    /// Uploads a mesh particles can be drawn as, it stays until the renderer is dropped
+    pub(crate) fn add_mesh(&mut self, vertices: &[Vertex], indices: &[u32]) -> ParticleMeshId {
+        let vertex_buffer = self
+            .gpu_controller
+            .create_buffer_init(&BufferInitDescriptor {
+                label: Some("Particle Mesh Vertex Buffer"),
+                contents: bytemuck::cast_slice(vertices),
+                usage: BufferUsages::VERTEX,
+            });
+
+        let index_buffer = self
+            .gpu_controller
+            .create_buffer_init(&BufferInitDescriptor {
+                label: Some("Particle Mesh Index Buffer"),
+                contents: bytemuck::cast_slice(indices),
+                usage: BufferUsages::INDEX,
+            });
+
+        self.meshes.push(ParticleMesh {
+            vertex_buffer,
+            index_buffer,
+            index_count: indices.len() as u32,
+        });
+
+        ParticleMeshId(self.meshes.len() as u32 - 1)
+    }
+
+    /// Adds an emitter or moves an existing one, recreating its particles if
+    /// it needs a different number of them
+    pub(crate) fn update_emitter(
+        &mut self,
+        id: EmitterKey,
+        settings: &ParticleEmitterSettings,
+        origin: [f32; 3],
+        direction: [f32; 3],
+        emitting: bool,
+    ) {
+        let recreate = self
+            .emitters
+            .get(&id)
+            .is_none_or(|emitter| emitter.settings.max_particles != settings.max_particles);
+        if recreate {
+            let mut emitter = self.create_emitter(settings);
+            if let Some(previous) = self.emitters.get(&id) {
+                emitter.order = previous.order;
+            }
+            self.emitters.insert(id, emitter);
+        }
+
+        let Some(emitter) = self.emitters.get_mut(&id) else {
+            return;
+        };
+
+        let (collision, restitution, friction, thickness, kill) = match settings.collision {
+            ParticleCollision::None => (0, 0.0, 0.0, 0.0, false),
+            ParticleCollision::Depth {
+                restitution,
+                friction,
+                thickness,
+                kill,
+            } => (1, restitution, friction, thickness, kill),
+            ParticleCollision::Colliders {
+                restitution,
+                friction,
+                kill,
+            } => (2, restitution, friction, 0.0, kill),
+        };
+
+        emitter.settings = *settings;
+        emitter.uniform = EmitterUniform {
+            origin,
+            spawn_count: 0,
+            direction,
+            spread: settings.spread,
+            gravity: settings.gravity,
+            drag: settings.drag,
+            start_color: settings.start_color,
+            end_color: settings.end_color,
+            speed: [settings.speed.0, settings.speed.1],
+            lifetime: [settings.lifetime.0, settings.lifetime.1],
+            size: [settings.size.0, settings.size.1],
+            delta_t: 0.0,
+            seed: 0,
+            collision,
+            restitution,
+            friction,
+            kill_on_collision: kill as u32,
+            collider_count: 0,
+            thickness,
+            max_particles: settings.max_particles,
+            radius: settings.size.0.max(settings.size.1) * 0.25,
+            distortion: settings.distortion,
+            _padding: [0; 3],
+        };
+
+        if !emitting {
+            emitter.spawn_debt = -1.0;
+        }
+    }
+
+    /// Sets where the particles of an emitter are drawn among the other emitters, see `DrawOrder`
+    pub(crate) fn set_order(&mut self, id: EmitterKey, order: DrawOrder) {
+        if let Some(emitter) = self.emitters.get_mut(&id) {
+            emitter.order = order;
+        }
+    }
+
+    /// Removes the emitters whose owner and index the closure returns false for
+    pub(crate) fn retain_emitters<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(u64, u32) -> bool,
+    {
+        self.emitters.retain(|(id, index), _| keep(*id, *index));
+    }
+
+    fn create_emitter(&self, settings: &ParticleEmitterSettings) -> GpuEmitter {
+        let uniform: EmitterUniform = bytemuck::Zeroable::zeroed();
+
+        let emitter_buffer = self
+            .gpu_controller
+            .create_buffer_init(&BufferInitDescriptor {
+                label: Some("Particle Emitter Buffer"),
+                contents: bytemuck::cast_slice(&[uniform]),
+                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            });
+
+        // Zeroed particles have no lifetime left, so they all start dead
+        let particle_buffer = self.gpu_controller.create_buffer(&BufferDescriptor {
+            label: Some("Particle Buffer"),
+            size: settings.max_particles.max(1) as u64 * PARTICLE_SIZE,
+            usage: BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let spawned_buffer = self
+            .gpu_controller
+            .create_buffer_init(&BufferInitDescriptor {
+                label: Some("Particle Spawn Counter Buffer"),
+                contents: bytemuck::cast_slice(&[0u32]),
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            });
+
+        let simulate_bind_group = self.gpu_controller.create_bind_group(&BindGroupDescriptor {
+            label: Some("Particle Emitter Bind Group"),
+            layout: &self.emitter_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: emitter_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: particle_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: spawned_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: self.collider_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let draw_bind_group = self.gpu_controller.create_bind_group(&BindGroupDescriptor {
+            label: Some("Particle Draw Bind Group"),
+            layout: &self.draw_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: emitter_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: particle_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        GpuEmitter {
+            settings: *settings,
+            uniform,
+            order: DrawOrder::default(),
+            spawn_debt: 0.0,
+            emitter_buffer,
+            spawned_buffer,
+            simulate_bind_group,
+            draw_bind_group,
+        }
+    }
+
+    /// Moves every particle along by a frame
+    ///
+    /// # Arguments
+    /// * `camera` - The camera whose last frame particles with `ParticleCollision::Depth` collide with
+    /// * `delta_t` - Seconds since the last frame
+    /// * `position_view` - World positions of the G-buffer of the camera
+    /// * `normal_view` - World normals of the G-buffer of the camera
+    pub(crate) fn simulate<C>(
+        &mut self,
+        camera: &C,
+        delta_t: f32,
+        position_view: &TextureView,
+        normal_view: &TextureView,
+    ) where
+        C: PhotonCamera,
+    {
+        if self.emitters.is_empty() {
+            return;
+        }
+
+        self.frame = self.frame.wrapping_add(1);
+
+        for ((id, index), emitter) in self.emitters.iter_mut() {
+            let delta_t = delta_t * emitter.settings.time_scale.max(0.0);
+
+            // Stopped emitters let their particles die out
+            let spawn_count = if emitter.spawn_debt < 0.0 {
+                emitter.spawn_debt = 0.0;
+                0
+            } else {
+                emitter.spawn_debt += emitter.settings.rate.max(0.0) * delta_t;
+                let count = emitter.spawn_debt.floor();
+                emitter.spawn_debt -= count;
+                count as u32
+            };
+
+            emitter.uniform.spawn_count = spawn_count;
+            emitter.uniform.delta_t = delta_t;
+            emitter.uniform.seed =
+                self.frame ^ (*id as u32 ^ index.rotate_left(16)).wrapping_mul(0x9e3779b9);
+            emitter.uniform.collider_count = self.collider_count;
+
+            self.gpu_controller.write_buffer(
+                &emitter.emitter_buffer,
+                0,
+                bytemuck::cast_slice(&[emitter.uniform]),
+            );
+            self.gpu_controller.write_buffer(
+                &emitter.spawned_buffer,
+                0,
+                bytemuck::cast_slice(&[0u32]),
+            );
+        }
+
+        let surfaces_bind_group = self.gpu_controller.create_bind_group(&BindGroupDescriptor {
+            label: Some("Particle Surfaces Bind Group"),
+            layout: &self.surfaces_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(position_view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(normal_view),
+                },
+            ],
+        });
+
+        let mut encoder = self
+            .gpu_controller
+            .create_command_encoder("Particle Simulation Encoder");
+
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("Particle Simulation Pass"),
+                timestamp_writes: None,
+            });
+
+            compute_pass.set_pipeline(&self.simulate_pipeline);
+            compute_pass.set_bind_group(CAMERA_BIND_GROUP, camera.bind_group(), &[]);
+            compute_pass.set_bind_group(SURFACES_BIND_GROUP, &surfaces_bind_group, &[]);
+
+            for emitter in self.emitters.values() {
+                compute_pass.set_bind_group(EMITTER_BIND_GROUP, &emitter.simulate_bind_group, &[]);
+                compute_pass.dispatch_workgroups(
+                    emitter.settings.max_particles.div_ceil(WORKGROUP_SIZE),
+                    1,
+                    1,
+                );
+            }
+        }
+
+        self.gpu_controller.submit(encoder);
+    }
+
+    /// Returns whether any emitter draws into the distortion buffer
+    pub(crate) fn has_distortion(&self) -> bool {
+        self.emitters.values().any(GpuEmitter::distorts)
+    }
+
+    /// Adds the offsets of distorting particles into the distortion buffer
+    pub(crate) fn render_distortion<C>(
+        &self,
+        encoder: &mut CommandEncoder,
+        distortion_view: &TextureView,
+        depth_view: &TextureView,
+        camera: &C,
+    ) where
+        C: PhotonCamera,
+    {
+        if !self.has_distortion() {
+            return;
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Particle Distortion Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: distortion_view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&self.distortion_pipeline);
+        render_pass.set_bind_group(CAMERA_BIND_GROUP, camera.bind_group(), &[]);
+
+        for emitter in self.emitters.values().filter(|emitter| emitter.distorts()) {
+            render_pass.set_bind_group(DRAW_BIND_GROUP, &emitter.draw_bind_group, &[]);
+            render_pass.draw(0..6, 0..emitter.settings.max_particles);
+        }
+    }
+
+    /// Draws the particles of every emitter over the color in their
+    /// `DrawOrder`, hidden by the geometry
+    pub(crate) fn render<C>(
+        &self,
+        encoder: &mut CommandEncoder,
+        view: &TextureView,
+        depth_view: &TextureView,
+        camera: &C,
+    ) where
+        C: PhotonCamera,
+    {
+        if self.emitters.is_empty() {
+            return;
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Particle Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_bind_group(CAMERA_BIND_GROUP, camera.bind_group(), &[]);
+
+        let emitters = sort_draws(
+            self.emitters
+                .values()
+                .filter(|emitter| !emitter.distorts())
+                .map(|emitter| (emitter.order, emitter.uniform.origin, emitter)),
+            camera.position(),
+        );
+
+        // Meshes first so the soft quads blend over them
+        render_pass.set_pipeline(&self.mesh_pipeline);
+        for emitter in emitters.iter() {
+            let Some(mesh) = emitter
+                .settings
+                .mesh
+                .and_then(|ParticleMeshId(mesh)| self.meshes.get(mesh as usize))
+            else {
+                continue;
+            };
+
+            render_pass.set_bind_group(DRAW_BIND_GROUP, &emitter.draw_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+            render_pass.set_index_buffer(mesh.index_buffer.slice(..), IndexFormat::Uint32);
+            render_pass.draw_indexed(0..mesh.index_count, 0, 0..emitter.settings.max_particles);
+        }
+
+        render_pass.set_pipeline(&self.draw_pipeline);
+        for emitter in emitters
+            .iter()
+            .filter(|emitter| emitter.settings.mesh.is_none())
+        {
+            render_pass.set_bind_group(DRAW_BIND_GROUP, &emitter.draw_bind_group, &[]);
+            render_pass.draw(0..6, 0..emitter.settings.max_particles);
+        }
+    }
+}
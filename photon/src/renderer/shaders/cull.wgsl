@@ -0,0 +1,114 @@
+struct GlobalTransform {
+    position: vec3<f32>,
+    rotation: vec4<f32>,
+}
+
+struct CullInfo {
+    // Bounding sphere of the object in its local space
+    center: vec3<f32>,
+    radius: f32,
+    num_instances: u32,
+    num_draws: u32,
+}
+
+struct Instance {
+    position: vec3<f32>,
+    rotation: vec4<f32>,
+    scale: mat4x4<f32>,
+}
+
+// Matches the arguments of draw_indexed_indirect
+struct DrawIndexedArgs {
+    index_count: u32,
+    instance_count: atomic<u32>,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
+}
+
+@group(1) @binding(0)
+var<storage, read> global_transform: GlobalTransform;
+
+@group(1) @binding(1)
+var<uniform> cull_info: CullInfo;
+
+@group(1) @binding(2)
+var<storage, read> instances: array<Instance>;
+
+@group(1) @binding(3)
+var<storage, read_write> visible_instances: array<Instance>;
+
+@group(1) @binding(4)
+var<storage, read_write> draw_args: array<DrawIndexedArgs>;
+
+fn hamilton_prod(a: vec4<f32>, b: vec4<f32>) -> vec4<f32> {
+    return vec4<f32>(
+        a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+        a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+        a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+        a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z,
+    );
+}
+
+fn quat_conj(q: vec4<f32>) -> vec4<f32> {
+    return q * vec4<f32>(-1.0, -1.0, -1.0, 1.0);
+}
+
+fn quat_norm(q: vec4<f32>) -> vec4<f32> {
+    return q / length(q);
+}
+
+// Row of the view projection matrix, the frustum planes are built from them
+fn view_proj_row(row: u32) -> vec4<f32> {
+    let m = frame.view_proj;
+    return vec4<f32>(m[0][row], m[1][row], m[2][row], m[3][row]);
+}
+
+fn outside_plane(plane: vec4<f32>, center: vec3<f32>, radius: f32) -> bool {
+    return dot(plane.xyz, center) + plane.w < -radius * length(plane.xyz);
+}
+
+fn sphere_visible(center: vec3<f32>, radius: f32) -> bool {
+    let x = view_proj_row(0u);
+    let y = view_proj_row(1u);
+    let z = view_proj_row(2u);
+    let w = view_proj_row(3u);
+
+    // Clip space depth runs from 0 to w, so the near plane is the z row alone
+    return !(outside_plane(w + x, center, radius)
+        || outside_plane(w - x, center, radius)
+        || outside_plane(w + y, center, radius)
+        || outside_plane(w - y, center, radius)
+        || outside_plane(z, center, radius)
+        || outside_plane(w - z, center, radius));
+}
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let index = global_id.x;
+    if index >= cull_info.num_instances {
+        return;
+    }
+
+    let instance = instances[index];
+
+    // Place the bounding sphere the same way the geometry pass places vertices
+    let rotation = quat_norm(hamilton_prod(global_transform.rotation, instance.rotation));
+    let rotated = hamilton_prod(
+        hamilton_prod(rotation, vec4<f32>(cull_info.center, 0.0)),
+        quat_conj(rotation),
+    );
+    let center = rotated.xyz + instance.position + global_transform.position;
+
+    if !sphere_visible(center, cull_info.radius) {
+        return;
+    }
+
+    // Every draw of the object renders the same visible instances
+    let slot = atomicAdd(&draw_args[0].instance_count, 1u);
+    for (var draw = 1u; draw < cull_info.num_draws; draw++) {
+        atomicAdd(&draw_args[draw].instance_count, 1u);
+    }
+
+    visible_instances[slot] = instance;
+}
@@ -1,5 +1,5 @@
 struct VertexOutput {
-    @builtin(position) clip_position: vec4<f32>,
+    @builtin(position) @invariant clip_position: vec4<f32>,
     @location(0) uv_coords: vec2<f32>,
     @location(1) world_normal: vec3<f32>,
     @location(2) world_position: vec3<f32>,
@@ -18,6 +18,20 @@ struct VertexInput {
     @location(2) normal: vec3<f32>,
 }
 
+// A vertex of a quantized mesh, see `QuantizedVertex`
+struct QuantizedVertexInput {
+    // Normalized within the bounds of the mesh
+    @location(0) position: vec4<f32>,
+    @location(1) uv_coords: vec2<f32>,
+    // Octahedral encoded
+    @location(2) normal: vec2<f32>,
+}
+
+struct VertexQuantization {
+    offset: vec4<f32>,
+    scale: vec4<f32>,
+}
+
 struct InstanceInput {
     @location(3) position: vec3<f32>,
     @location(4) rotation: vec4<f32>,
@@ -28,11 +42,29 @@ struct GlobalTransform {
     rotation: vec4<f32>,
 }
 
-struct CameraUniform {
-    view_position: vec4<f32>,
-    view_proj: mat4x4<f32>,
+// The transform of the object this frame and the frame before
+struct ObjectTransforms {
+    current: GlobalTransform,
+    previous: GlobalTransform,
+}
+
+struct MotionOutput {
+    @builtin(position) @invariant clip_position: vec4<f32>,
+    @location(0) current_clip: vec4<f32>,
+    @location(1) previous_clip: vec4<f32>,
+}
+
+struct MaterialOverrides {
+    tint: vec4<f32>,
+    emissive: vec4<f32>,
+    // Color of the object where it is hidden behind other geometry
+    silhouette: vec4<f32>,
+    texture: u32,
 }
 
+// Fraction of the front of the depth range the viewmodel is drawn into
+const VIEW_MODEL_DEPTH_RANGE: f32 = 0.01;
+
 const FALSE: u32 = 0;
 const TRUE: u32 = 1;
 
@@ -48,9 +80,6 @@ struct MaterialProperties {
 }
 
 // Bind Groups
-@group(0) @binding(0)
-var<uniform> camera: CameraUniform;
-
 @group(1) @binding(0)
 var<storage> material_properties: MaterialProperties;
 
@@ -61,7 +90,20 @@ var material_texture: texture_2d<f32>;
 var material_sampler: sampler;
 
 @group(2) @binding(0)
-var<storage> global_transform: GlobalTransform;
+var<storage> transforms: ObjectTransforms;
+
+@group(2) @binding(1)
+var<storage> material_overrides: MaterialOverrides;
+
+@group(2) @binding(2)
+var override_texture: texture_2d<f32>;
+
+@group(2) @binding(3)
+var override_sampler: sampler;
+
+// Only bound by the quantized pipelines
+@group(3) @binding(0)
+var<uniform> quantization: VertexQuantization;
 
 fn hamilton_prod(a: vec4<f32>, b: vec4<f32>) -> vec4<f32> {
     return vec4<f32>(
@@ -80,15 +122,57 @@ fn quat_norm(q: vec4<f32>) -> vec4<f32> {
     return q / length(q);
 }
 
+// Places a vertex of an instance in the world with an object transform
+fn to_world(position: vec3<f32>, instance: InstanceInput, global_transform: GlobalTransform) -> vec3<f32> {
+    let rotation = quat_norm(hamilton_prod(global_transform.rotation, instance.rotation));
+    let rotated = hamilton_prod(
+        hamilton_prod(rotation, vec4<f32>(position, 0.0)),
+        quat_conj(rotation),
+    );
+
+    return rotated.xyz + instance.position + global_transform.position;
+}
+
+fn octahedral_decode(encoded: vec2<f32>) -> vec3<f32> {
+    var normal = vec3<f32>(encoded, 1.0 - abs(encoded.x) - abs(encoded.y));
+
+    // Unfolds the lower half of the octahedron
+    let t = max(-normal.z, 0.0);
+    normal.x -= t * select(-1.0, 1.0, normal.x >= 0.0);
+    normal.y -= t * select(-1.0, 1.0, normal.y >= 0.0);
+
+    return normalize(normal);
+}
+
+fn decode_vertex(model: QuantizedVertexInput) -> VertexInput {
+    var decoded: VertexInput;
+    decoded.position = quantization.offset.xyz + model.position.xyz * quantization.scale.xyz;
+    decoded.uv_coords = model.uv_coords;
+    decoded.normal = octahedral_decode(model.normal);
+
+    return decoded;
+}
+
 @vertex
 fn vs_main(
     model: VertexInput,
     instance: InstanceInput,
     @builtin(vertex_index) vertex_idx: u32,
 ) -> VertexOutput {
+    return surface_vertex(model, instance);
+}
+
+@vertex
+fn vs_quantized(model: QuantizedVertexInput, instance: InstanceInput) -> VertexOutput {
+    return surface_vertex(decode_vertex(model), instance);
+}
+
+fn surface_vertex(model: VertexInput, instance: InstanceInput) -> VertexOutput {
     var out: VertexOutput;
     out.uv_coords = model.uv_coords;
 
+    let global_transform = transforms.current;
+
     let combined_rotation = quat_norm(hamilton_prod(global_transform.rotation, instance.rotation));
 
     // Rotate the point first
@@ -115,31 +199,125 @@ fn vs_main(
     let world_position = vec4<f32>(rot.xyz + instance.position + global_transform.position, 1.0);
 
     out.world_position = world_position.xyz;
-    out.clip_position = camera.view_proj * world_position;
+    out.clip_position = frame.view_proj * world_position;
     // out.world_normal = model.normal;
 
     return out;
 }
 
+// The viewmodel of a first person camera, projected with its own field of
+// view and drawn in front of the rest of the surfaces
+@vertex
+fn vs_view_model(model: VertexInput, instance: InstanceInput) -> VertexOutput {
+    return view_model_vertex(model, instance);
+}
+
+@vertex
+fn vs_view_model_quantized(model: QuantizedVertexInput, instance: InstanceInput) -> VertexOutput {
+    return view_model_vertex(decode_vertex(model), instance);
+}
+
+fn view_model_vertex(model: VertexInput, instance: InstanceInput) -> VertexOutput {
+    // The world position is kept so the viewmodel is lit where it really is
+    var out = surface_vertex(model, instance);
+    out.clip_position = frame.view_model_view_proj * vec4<f32>(out.world_position, 1.0);
+
+    // Only the world right at the near plane is ever in front of the viewmodel
+    out.clip_position.z *= VIEW_MODEL_DEPTH_RANGE;
+
+    return out;
+}
+
+// Motion vectors of objects that moved, drawn after the geometry over the same depth
+@vertex
+fn vs_motion(model: VertexInput, instance: InstanceInput) -> MotionOutput {
+    return motion_vertex(model, instance);
+}
+
+@vertex
+fn vs_motion_quantized(model: QuantizedVertexInput, instance: InstanceInput) -> MotionOutput {
+    return motion_vertex(decode_vertex(model), instance);
+}
+
+fn motion_vertex(model: VertexInput, instance: InstanceInput) -> MotionOutput {
+    var out: MotionOutput;
+
+    // Same math as surface_vertex so the depth matches the geometry pass exactly
+    let current = vec4<f32>(to_world(model.position, instance, transforms.current), 1.0);
+    let previous = vec4<f32>(to_world(model.position, instance, transforms.previous), 1.0);
+
+    out.clip_position = frame.view_proj * current;
+    out.current_clip = out.clip_position;
+    out.previous_clip = frame.previous_view_proj * previous;
+
+    return out;
+}
+
+@fragment
+fn fs_motion(in: MotionOutput) -> @location(0) vec4<f32> {
+    let current = in.current_clip.xy / in.current_clip.w;
+    let previous = in.previous_clip.xy / in.previous_clip.w;
+
+    // Movement in texture coordinates since the last frame, w marks the pixel as written
+    return vec4<f32>((current - previous) * vec2<f32>(0.5, -0.5), 0.0, 1.0);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> FragmentOutput {
     var output: FragmentOutput;
 
     // Color of the object
     // output.albedo = vec4<f32>(1.0, 0.0, 1.0, 1.0);
-    if (material_properties.texture == TRUE) {
+    if (material_overrides.texture == TRUE) {
+        output.albedo = textureSample(override_texture, override_sampler, in.uv_coords);
+    } else if (material_properties.texture == TRUE) {
         output.albedo = textureSample(material_texture, material_sampler, in.uv_coords);
     } else {
         output.albedo = vec4<f32>(1.0, 1.0, 1.0, 1.0);
     }
 
+    // Per object tint on top of the shared material
+    output.albedo *= material_overrides.tint;
+
     // Position of the fragment
     output.position = vec4<f32>(in.world_position, 1.0);
 
     // Normals of the object
     output.normal = vec4<f32>(in.world_normal, 1.0);
 
-    output.material = vec4<f32>(0.5, 0.3, 1.0, 1.0);
+    // Emission is added by the lighting pass regardless of the lights
+    output.material = vec4<f32>(material_overrides.emissive.rgb * material_overrides.emissive.w, 1.0);
+
+    return output;
+}
+
+// Tints the parts of an object hidden behind other geometry, the depth and
+// stencil tests of the silhouette pass keep only the occluded fragments
+@fragment
+fn fs_silhouette(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Brighter towards the edges so the shape reads through walls
+    let view_direction = normalize(frame.view_position.xyz - in.world_position);
+    let rim = 1.0 - abs(dot(normalize(in.world_normal), view_direction));
+
+    let color = material_overrides.silhouette;
+    return vec4<f32>(color.rgb, color.a * mix(0.6, 1.0, rim));
+}
+
+// Drawn in place of surfaces whose pipeline failed to compile, a magenta
+// checker in world space so the broken objects stand out
+@fragment
+fn fs_error(in: VertexOutput) -> FragmentOutput {
+    var output: FragmentOutput;
+
+    let cell = vec3<i32>(floor(in.world_position * 4.0));
+    let checker = ((cell.x + cell.y + cell.z) & 1) == 1;
+    output.albedo = select(vec4<f32>(1.0, 0.0, 1.0, 1.0), vec4<f32>(0.0, 0.0, 0.0, 1.0), checker);
+
+    output.position = vec4<f32>(in.world_position, 1.0);
+    output.normal = vec4<f32>(in.world_normal, 1.0);
+
+    // Emissive so the checker shows even without lights
+    output.material = vec4<f32>(output.albedo.rgb, 1.0);
 
     return output;
 }
@@ -7,27 +7,40 @@ struct FragmentOutput {
     @location(0) color: vec4<f32>,
 }
 
-struct CameraUniform {
-    view_position: vec4<f32>,
-    view_proj: mat4x4<f32>,
-}
-
 struct Light {
     position: vec3<f32>,
+    // How much of the light's shadow is drawn, from 0 to 1
+    shadow: f32,
     normal: vec3<f32>,
     color: vec3<f32>,
     intensity: f32,
 }
 
-const CAMERA_BIND_GROUP: u32 = 0;
+// Irradiance from +X, -X, +Y, -Y, +Z and -Z
+struct IrradianceProbe {
+    faces: array<vec4<f32>, 6>,
+}
+
+struct ProbeGrid {
+    origin: vec3<f32>,
+    enabled: u32,
+    spacing: vec3<f32>,
+    counts: vec3<u32>,
+}
+
 const LIGHT_BIND_GROUP: u32 = 1;
 const G_BUFFER_BIND_GROUP: u32 = 2;
 
 const WHITE: vec3<f32> = vec3<f32>(1.0, 1.0, 1.0);
+const TRUE: u32 = 1;
 
-// Camera
-@group(CAMERA_BIND_GROUP) @binding(0)
-var<uniform> camera: CameraUniform;
+// Steps of the shadow march and how far from the surface it goes
+const SHADOW_STEPS: u32 = 16u;
+const SHADOW_MAX_DISTANCE: f32 = 4.0;
+// Surfaces closer than this to a step don't count as in front of it
+const SHADOW_BIAS: f32 = 0.05;
+// How deep behind a surface a step can be and still be hidden by it
+const SHADOW_THICKNESS: f32 = 0.5;
 
 // Lights
 @group(LIGHT_BIND_GROUP) @binding(0)
@@ -36,6 +49,12 @@ var<storage, read> lights: array<Light>;
 @group(LIGHT_BIND_GROUP) @binding(1)
 var<uniform> lights_len: u32;
 
+@group(LIGHT_BIND_GROUP) @binding(2)
+var<storage, read> light_probes: array<IrradianceProbe>;
+
+@group(LIGHT_BIND_GROUP) @binding(3)
+var<uniform> probe_grid: ProbeGrid;
+
 // G-Buffer (action XD)
 @group(G_BUFFER_BIND_GROUP) @binding(0)
 var albedo_texture: texture_2d<f32>;
@@ -52,6 +71,75 @@ var material: texture_2d<f32>;
 @group(G_BUFFER_BIND_GROUP) @binding(4)
 var g_buffer_sampler: sampler;
 
+// Blends the faces of a probe the normal points towards
+fn probe_irradiance(grid_position: vec3<u32>, normal: vec3<f32>) -> vec3<f32> {
+    let index = grid_position.x
+        + grid_position.y * probe_grid.counts.x
+        + grid_position.z * probe_grid.counts.x * probe_grid.counts.y;
+    let faces = light_probes[index].faces;
+
+    let weights = normal * normal;
+    let x = select(faces[1], faces[0], normal.x >= 0.0).rgb;
+    let y = select(faces[3], faces[2], normal.y >= 0.0).rgb;
+    let z = select(faces[5], faces[4], normal.z >= 0.0).rgb;
+
+    return x * weights.x + y * weights.y + z * weights.z;
+}
+
+// Trilinearly interpolates the eight probes around a point
+fn sample_light_probes(position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    let last = vec3<f32>(probe_grid.counts - vec3<u32>(1u));
+    let cell = clamp((position - probe_grid.origin) / probe_grid.spacing, vec3<f32>(0.0), last);
+
+    let low = vec3<u32>(floor(cell));
+    let high = min(low + vec3<u32>(1u), probe_grid.counts - vec3<u32>(1u));
+    let t = cell - floor(cell);
+
+    var result = vec3<f32>(0.0);
+    for (var corner: u32 = 0u; corner < 8u; corner++) {
+        let upper = vec3<bool>((corner & 1u) != 0u, (corner & 2u) != 0u, (corner & 4u) != 0u);
+        let grid_position = select(low, high, upper);
+        let weight = select(vec3<f32>(1.0) - t, t, upper);
+
+        result += probe_irradiance(grid_position, normal) * weight.x * weight.y * weight.z;
+    }
+
+    return result;
+}
+
+// Marches from a surface towards a light through the G-buffer, anything drawn
+// just in front of a step is taken to block the light
+fn light_visibility(surface_position: vec3<f32>, light_position: vec3<f32>) -> f32 {
+    let dimensions = vec2<f32>(textureDimensions(position_texture));
+    let to_light = light_position - surface_position;
+    let march = normalize(to_light) * min(length(to_light), SHADOW_MAX_DISTANCE);
+
+    for (var step: u32 = 1u; step <= SHADOW_STEPS; step++) {
+        let point = surface_position + march * (f32(step) / f32(SHADOW_STEPS));
+
+        let clip = frame.view_proj * vec4<f32>(point, 1.0);
+        if (clip.w <= 0.0) {
+            break;
+        }
+
+        let ndc = clip.xyz / clip.w;
+        if (any(abs(ndc.xy) > vec2<f32>(1.0))) {
+            break;
+        }
+
+        let uv = vec2<f32>(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+        let pixel = vec2<i32>(min(uv * dimensions, dimensions - vec2<f32>(1.0)));
+        let surface = textureLoad(position_texture, pixel, 0);
+
+        let depth = distance(frame.view_position.xyz, point) - distance(frame.view_position.xyz, surface.xyz);
+        if (surface.w != 0.0 && depth > SHADOW_BIAS && depth < SHADOW_THICKNESS) {
+            return 0.0;
+        }
+    }
+
+    return 1.0;
+}
+
 @vertex
 fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
     var output: VertexOutput;
@@ -72,10 +160,23 @@ fn fs_main(input: VertexOutput) -> FragmentOutput {
     let albedo = textureSample(albedo_texture, g_buffer_sampler, input.uv);
     let normal = textureSample(normal_texture, g_buffer_sampler, input.uv);
     let position = textureSample(position_texture, g_buffer_sampler, input.uv);
+    let emission = textureSample(material, g_buffer_sampler, input.uv);
+
+    // Nothing was drawn here, leave the camera's clear color
+    if (position.w == 0.0) {
+        discard;
+    }
 
     var result: vec3<f32> = vec3<f32>(0.0, 0.0, 0.0);
 
-    let ambient_strength = 0.01;
+    var ambient_strength = 0.01;
+
+    // Probes replace the constant ambient term with the bounce light around the surface
+    if (probe_grid.enabled == TRUE) {
+        ambient_strength = 0.0;
+        result += sample_light_probes(position.xyz, normalize(normal.xyz)) * albedo.rgb;
+    }
+
     let ambient_color = WHITE * ambient_strength;
 
     for (var i: u32 = 0; i < lights_len; i++) {
@@ -90,11 +191,18 @@ fn fs_main(input: VertexOutput) -> FragmentOutput {
 
         // Diffuse Lighting
         let diffuse_strength = max(dot(normal.xyz, light_dir), 0.0);
-        let diffuse_color = color.rgb * diffuse_strength * intensity;
+        var diffuse_color = color.rgb * diffuse_strength * intensity;
+
+        // Only lights in the shadow budget march, the rest are lit unshadowed
+        if (light.shadow > 0.0 && diffuse_strength > 0.0) {
+            diffuse_color *= mix(1.0, light_visibility(position.xyz, pos), light.shadow);
+        }
 
         result += (ambient_color + diffuse_color) * albedo.rgb;
     }
 
+    result += emission.rgb;
+
     output.color = vec4<f32>(result, 1.0);
 
     return output;
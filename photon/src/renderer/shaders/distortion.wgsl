@@ -0,0 +1,34 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var scene_texture: texture_2d<f32>;
+
+@group(0) @binding(1)
+var distortion_texture: texture_2d<f32>;
+
+@group(0) @binding(2)
+var distortion_sampler: sampler;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var output: VertexOutput;
+
+    // Create a full-screen triangle
+    let x = f32((vertex_index << 1) & 2);
+    let y = f32(vertex_index & 2);
+    output.position = vec4<f32>(x * 2.0 - 1.0, y * 2.0 - 1.0, 0.0, 1.0);
+    output.uv = vec2<f32>(x, 1.0 - y);
+
+    return output;
+}
+
+// Reads the scene from where the distortion buffer points
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let offset = textureSampleLevel(distortion_texture, distortion_sampler, input.uv, 0.0).xy;
+    let uv = clamp(input.uv + offset, vec2<f32>(0.0), vec2<f32>(1.0));
+    return textureSampleLevel(scene_texture, distortion_sampler, uv, 0.0);
+}
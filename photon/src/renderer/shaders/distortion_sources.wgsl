@@ -0,0 +1,89 @@
+struct DistortionSource {
+    position: vec3<f32>,
+    radius: f32,
+    strength: f32,
+    kind: u32,
+    // Frequency and speed of heat haze, progress and thickness of shockwaves
+    parameters: vec2<f32>,
+}
+
+const KIND_HEAT_HAZE: u32 = 0u;
+const KIND_SHOCKWAVE: u32 = 1u;
+
+@group(1) @binding(0)
+var<storage, read> sources: array<DistortionSource>;
+
+struct SourceOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) corner: vec2<f32>,
+    @location(1) world_position: vec3<f32>,
+    @location(2) @interpolate(flat) index: u32,
+}
+
+// Camera facing quads covering every source
+@vertex
+fn vs_source(
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) instance_index: u32,
+) -> SourceOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+
+    let source = sources[instance_index];
+    let corner = corners[vertex_index];
+
+    let to_camera = normalize(frame.view_position.xyz - source.position);
+    var helper = vec3<f32>(0.0, 1.0, 0.0);
+    if abs(to_camera.y) > 0.99 {
+        helper = vec3<f32>(1.0, 0.0, 0.0);
+    }
+    let right = normalize(cross(helper, to_camera));
+    let up = cross(to_camera, right);
+
+    let position = source.position + (right * corner.x + up * corner.y) * source.radius;
+
+    var output: SourceOutput;
+    output.clip_position = frame.view_proj * vec4<f32>(position, 1.0);
+    output.corner = corner;
+    output.world_position = position;
+    output.index = instance_index;
+    return output;
+}
+
+// Offsets of the scene color in texture coordinates, added over every source
+@fragment
+fn fs_source(in: SourceOutput) -> @location(0) vec4<f32> {
+    let source = sources[in.index];
+    let distance = length(in.corner);
+    if distance > 1.0 {
+        discard;
+    }
+
+    var offset = vec2<f32>(0.0);
+
+    if source.kind == KIND_HEAT_HAZE {
+        // Rising shimmer that fades towards the edge
+        let p = in.world_position * source.parameters.x;
+        let t = frame.time * source.parameters.y;
+        let wobble = vec2<f32>(
+            sin(p.y * 1.3 + t) + sin(p.x * 2.1 - t * 1.7),
+            cos(p.x * 1.7 + t * 0.9) + cos(p.z * 2.3 - t * 1.3),
+        ) * 0.25;
+        offset = wobble * (1.0 - smoothstep(0.5, 1.0, distance));
+    } else if source.kind == KIND_SHOCKWAVE {
+        // A ring pushing the scene outwards, weakening as it grows
+        let progress = source.parameters.x;
+        let thickness = max(source.parameters.y, 0.0001);
+        let band = 1.0 - smoothstep(0.0, thickness, abs(distance - progress));
+        let direction = in.corner / max(distance, 0.0001);
+        offset = vec2<f32>(direction.x, -direction.y) * band * (1.0 - progress);
+    }
+
+    return vec4<f32>(offset * source.strength, 0.0, 0.0);
+}
@@ -0,0 +1,31 @@
+// Constants of the frame and of the camera drawing it, bound at group 0
+// binding 0 of every pass. The camera writes the matrices, the renderer the
+// rest once a frame.
+
+struct FrameConstants {
+    view_position: vec4<f32>,
+    view_proj: mat4x4<f32>,
+    // The view projection of the last frame, for motion vectors
+    previous_view_proj: mat4x4<f32>,
+    // The view projection of the viewmodel, the same as view_proj without one
+    view_model_view_proj: mat4x4<f32>,
+    inverse_view_proj: mat4x4<f32>,
+    // Seconds since the renderer started and since the last frame
+    time: f32,
+    delta_t: f32,
+    frame_index: u32,
+    fog_enabled: u32,
+    // Size of the output in pixels
+    resolution: vec2<f32>,
+    // Sub-pixel offset of the frame in pixels, from -0.5 to 0.5, for temporal effects
+    jitter: vec2<f32>,
+    fog_color: vec3<f32>,
+    fog_density: f32,
+    fog_anisotropy: f32,
+    fog_max_distance: f32,
+}
+
+const FRAME_BIND_GROUP: u32 = 0;
+
+@group(FRAME_BIND_GROUP) @binding(0)
+var<uniform> frame: FrameConstants;
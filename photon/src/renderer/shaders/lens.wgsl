@@ -0,0 +1,162 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+struct Lens {
+    focus_distance: f32,
+    focus_range: f32,
+    max_radius: f32,
+    depth_of_field: u32,
+    shutter: f32,
+    samples: u32,
+    motion_blur: u32,
+}
+
+const LENS_BIND_GROUP: u32 = 1;
+const G_BUFFER_BIND_GROUP: u32 = 2;
+
+// Taps gathered over the circle of confusion of every out of focus pixel
+const DOF_TAPS: u32 = 32u;
+const GOLDEN_ANGLE: f32 = 2.39996323;
+
+// Longest blur in texture coordinates, fast motion is clamped to it
+const MAX_MOTION: f32 = 0.1;
+const MAX_MOTION_SAMPLES: u32 = 32u;
+
+@group(LENS_BIND_GROUP) @binding(0)
+var source_texture: texture_2d<f32>;
+
+@group(LENS_BIND_GROUP) @binding(1)
+var motion_texture: texture_2d<f32>;
+
+@group(LENS_BIND_GROUP) @binding(2)
+var source_sampler: sampler;
+
+@group(LENS_BIND_GROUP) @binding(3)
+var<uniform> lens: Lens;
+
+@group(G_BUFFER_BIND_GROUP) @binding(1)
+var position_texture: texture_2d<f32>;
+
+fn pixel_of(uv: vec2<f32>) -> vec2<i32> {
+    let dimensions = vec2<f32>(textureDimensions(position_texture));
+    return vec2<i32>(clamp(uv * dimensions, vec2<f32>(0.0), dimensions - vec2<f32>(1.0)));
+}
+
+// Radius in pixels a point spreads over for being out of focus, nothing drawn is as far as it gets
+fn circle_of_confusion(uv: vec2<f32>) -> f32 {
+    let surface = textureLoad(position_texture, pixel_of(uv), 0);
+    if (surface.w == 0.0) {
+        return lens.max_radius;
+    }
+
+    let distance_to_camera = distance(frame.view_position.xyz, surface.xyz);
+    let defocus = abs(distance_to_camera - lens.focus_distance) / max(lens.focus_range, 1e-4);
+
+    return clamp(defocus, 0.0, 1.0) * lens.max_radius;
+}
+
+// Movement of a pixel in texture coordinates since the last frame
+fn velocity(uv: vec2<f32>) -> vec2<f32> {
+    let pixel = pixel_of(uv);
+
+    // Objects that moved wrote their own motion
+    let motion = textureLoad(motion_texture, pixel, 0);
+    if (motion.w != 0.0) {
+        return motion.xy;
+    }
+
+    // Everything else only moved with the camera
+    let surface = textureLoad(position_texture, pixel, 0);
+    if (surface.w == 0.0) {
+        return vec2<f32>(0.0);
+    }
+
+    let previous = frame.previous_view_proj * vec4<f32>(surface.xyz, 1.0);
+    if (previous.w <= 0.0) {
+        return vec2<f32>(0.0);
+    }
+
+    let previous_ndc = previous.xy / previous.w;
+    let previous_uv = vec2<f32>(previous_ndc.x * 0.5 + 0.5, 0.5 - previous_ndc.y * 0.5);
+
+    return uv - previous_uv;
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var output: VertexOutput;
+
+    // Create a full-screen triangle
+    let x = f32((vertex_index << 1) & 2);
+    let y = f32(vertex_index & 2);
+    output.position = vec4<f32>(x * 2.0 - 1.0, y * 2.0 - 1.0, 0.0, 1.0);
+    output.uv = vec2<f32>(x, 1.0 - y);
+
+    return output;
+}
+
+@fragment
+fn fs_depth_of_field(input: VertexOutput) -> @location(0) vec4<f32> {
+    let center = textureSampleLevel(source_texture, source_sampler, input.uv, 0.0);
+    if (lens.depth_of_field == 0u) {
+        return center;
+    }
+
+    let coc = circle_of_confusion(input.uv);
+    if (coc < 0.5) {
+        return center;
+    }
+
+    let texel = 1.0 / vec2<f32>(textureDimensions(source_texture));
+
+    var color = center;
+    var total_weight = 1.0;
+    for (var i: u32 = 0u; i < DOF_TAPS; i++) {
+        // Vogel disk, spreads the taps evenly over the circle
+        let radius = sqrt((f32(i) + 0.5) / f32(DOF_TAPS)) * coc;
+        let theta = f32(i) * GOLDEN_ANGLE;
+        let tap_uv = input.uv + vec2<f32>(cos(theta), sin(theta)) * radius * texel;
+
+        // A tap only spreads over this pixel if it is blurred at least as far,
+        // which keeps sharp things from bleeding into the blur around them
+        let weight = clamp(circle_of_confusion(tap_uv) - radius + 1.0, 0.0, 1.0);
+
+        color += textureSampleLevel(source_texture, source_sampler, tap_uv, 0.0) * weight;
+        total_weight += weight;
+    }
+
+    return color / total_weight;
+}
+
+@fragment
+fn fs_motion_blur(input: VertexOutput) -> @location(0) vec4<f32> {
+    let center = textureSampleLevel(source_texture, source_sampler, input.uv, 0.0);
+    if (lens.motion_blur == 0u) {
+        return center;
+    }
+
+    var motion = velocity(input.uv) * lens.shutter;
+    let motion_length = length(motion);
+    if (motion_length > MAX_MOTION) {
+        motion *= MAX_MOTION / motion_length;
+    }
+
+    // Less than half a pixel of movement doesn't show
+    let dimensions = vec2<f32>(textureDimensions(source_texture));
+    if (length(motion * dimensions) < 0.5) {
+        return center;
+    }
+
+    let samples = clamp(lens.samples, 1u, MAX_MOTION_SAMPLES);
+
+    // Samples are spread along the motion, centered on the pixel
+    var color = vec4<f32>(0.0);
+    for (var i: u32 = 0u; i < samples; i++) {
+        let t = (f32(i) + 0.5) / f32(samples) - 0.5;
+        color += textureSampleLevel(source_texture, source_sampler, input.uv - motion * t, 0.0);
+    }
+
+    return color / f32(samples);
+}
@@ -0,0 +1,230 @@
+const SURFACES_BIND_GROUP: u32 = 1;
+const EMITTER_BIND_GROUP: u32 = 2;
+
+const COLLISION_NONE: u32 = 0;
+const COLLISION_DEPTH: u32 = 1;
+const COLLISION_COLLIDERS: u32 = 2;
+
+const COLLIDER_PLANE: u32 = 0;
+const COLLIDER_SPHERE: u32 = 1;
+
+struct Particle {
+    position: vec3<f32>,
+    age: f32,
+    velocity: vec3<f32>,
+    lifetime: f32,
+}
+
+struct Emitter {
+    origin: vec3<f32>,
+    spawn_count: u32,
+    direction: vec3<f32>,
+    spread: f32,
+    gravity: vec3<f32>,
+    drag: f32,
+    start_color: vec4<f32>,
+    end_color: vec4<f32>,
+    speed: vec2<f32>,
+    lifetime: vec2<f32>,
+    size: vec2<f32>,
+    delta_t: f32,
+    seed: u32,
+    collision: u32,
+    restitution: f32,
+    friction: f32,
+    kill_on_collision: u32,
+    collider_count: u32,
+    thickness: f32,
+    max_particles: u32,
+    radius: f32,
+    distortion: f32,
+}
+
+struct Collider {
+    // Planes hold their normal and distance from the origin, spheres their center and radius
+    shape: vec4<f32>,
+    kind: u32,
+}
+
+@group(SURFACES_BIND_GROUP) @binding(0)
+var position_texture: texture_2d<f32>;
+
+@group(SURFACES_BIND_GROUP) @binding(1)
+var normal_texture: texture_2d<f32>;
+
+@group(EMITTER_BIND_GROUP) @binding(0)
+var<uniform> emitter: Emitter;
+
+@group(EMITTER_BIND_GROUP) @binding(1)
+var<storage, read_write> particles: array<Particle>;
+
+@group(EMITTER_BIND_GROUP) @binding(2)
+var<storage, read_write> spawned: atomic<u32>;
+
+@group(EMITTER_BIND_GROUP) @binding(3)
+var<storage, read> colliders: array<Collider>;
+
+fn hash(value: u32) -> u32 {
+    var x = value;
+    x ^= x >> 16u;
+    x *= 0x7feb352du;
+    x ^= x >> 15u;
+    x *= 0x846ca68bu;
+    x ^= x >> 16u;
+    return x;
+}
+
+// Random number from 0 to 1, advancing the state
+fn random(state: ptr<function, u32>) -> f32 {
+    *state = hash(*state);
+    return f32(*state) / 4294967295.0;
+}
+
+fn spawn(index: u32) -> Particle {
+    var state = hash(index ^ hash(emitter.seed));
+
+    // Direction within a cone around the emitter's direction
+    let forward = normalize(emitter.direction);
+    var helper = vec3<f32>(0.0, 1.0, 0.0);
+    if abs(forward.y) > 0.99 {
+        helper = vec3<f32>(1.0, 0.0, 0.0);
+    }
+    let right = normalize(cross(helper, forward));
+    let up = cross(forward, right);
+
+    let cos_angle = mix(1.0, cos(emitter.spread), random(&state));
+    let sin_angle = sqrt(max(1.0 - cos_angle * cos_angle, 0.0));
+    let around = random(&state) * 6.2831853;
+    let direction = forward * cos_angle + (right * cos(around) + up * sin(around)) * sin_angle;
+
+    var particle: Particle;
+    particle.position = emitter.origin;
+    particle.velocity = direction * mix(emitter.speed.x, emitter.speed.y, random(&state));
+    particle.lifetime = mix(emitter.lifetime.x, emitter.lifetime.y, random(&state));
+    particle.age = 0.0;
+    return particle;
+}
+
+// Bounces a particle off a surface, returning false if it should die instead
+fn collide(particle: ptr<function, Particle>, point: vec3<f32>, normal: vec3<f32>) -> bool {
+    if emitter.kill_on_collision != 0u {
+        return false;
+    }
+
+    let into = dot((*particle).velocity, normal);
+    if into < 0.0 {
+        let normal_velocity = normal * into;
+        let tangent_velocity = (*particle).velocity - normal_velocity;
+        (*particle).velocity = tangent_velocity * (1.0 - emitter.friction)
+            - normal_velocity * emitter.restitution;
+    }
+    (*particle).position = point + normal * emitter.radius;
+
+    return true;
+}
+
+// Tests a particle against what the camera drew, read back from the G-buffer
+fn collide_depth(particle: ptr<function, Particle>) -> bool {
+    let clip = frame.view_proj * vec4<f32>((*particle).position, 1.0);
+    if clip.w <= 0.0 {
+        return true;
+    }
+
+    let ndc = clip.xyz / clip.w;
+    if any(abs(ndc.xy) > vec2<f32>(1.0)) {
+        return true;
+    }
+
+    let size = textureDimensions(position_texture);
+    let uv = vec2<f32>(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+    let texel = min(vec2<u32>(uv * vec2<f32>(size)), size - vec2<u32>(1u));
+
+    let surface = textureLoad(position_texture, texel, 0);
+    // Nothing was drawn here
+    if surface.w == 0.0 {
+        return true;
+    }
+
+    let eye = frame.view_position.xyz;
+    let behind = length((*particle).position - eye) - length(surface.xyz - eye);
+    if behind < -emitter.radius || behind > emitter.thickness {
+        return true;
+    }
+
+    let normal = normalize(textureLoad(normal_texture, texel, 0).xyz);
+    return collide(particle, surface.xyz, normal);
+}
+
+fn collide_colliders(particle: ptr<function, Particle>) -> bool {
+    for (var i = 0u; i < emitter.collider_count; i++) {
+        let collider = colliders[i];
+
+        switch collider.kind {
+            case COLLIDER_PLANE: {
+                let normal = collider.shape.xyz;
+                let distance = dot((*particle).position, normal) - collider.shape.w;
+                if distance < emitter.radius {
+                    let point = (*particle).position - normal * distance;
+                    if !collide(particle, point, normal) {
+                        return false;
+                    }
+                }
+            }
+            case COLLIDER_SPHERE: {
+                let offset = (*particle).position - collider.shape.xyz;
+                let distance = length(offset);
+                if distance < collider.shape.w + emitter.radius && distance > 0.0 {
+                    let normal = offset / distance;
+                    let point = collider.shape.xyz + normal * collider.shape.w;
+                    if !collide(particle, point, normal) {
+                        return false;
+                    }
+                }
+            }
+            default: {}
+        }
+    }
+
+    return true;
+}
+
+@compute @workgroup_size(64)
+fn cs_simulate(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if index >= emitter.max_particles {
+        return;
+    }
+
+    var particle = particles[index];
+
+    if particle.age >= particle.lifetime {
+        // Dead particles are reused for the ones spawned this frame
+        if atomicAdd(&spawned, 1u) >= emitter.spawn_count {
+            return;
+        }
+        particle = spawn(index);
+    } else {
+        let dt = emitter.delta_t;
+        particle.velocity += emitter.gravity * dt;
+        particle.velocity *= max(1.0 - emitter.drag * dt, 0.0);
+        particle.position += particle.velocity * dt;
+        particle.age += dt;
+
+        var alive = true;
+        switch emitter.collision {
+            case COLLISION_DEPTH: {
+                alive = collide_depth(&particle);
+            }
+            case COLLISION_COLLIDERS: {
+                alive = collide_colliders(&particle);
+            }
+            default: {}
+        }
+
+        if !alive {
+            particle.age = particle.lifetime;
+        }
+    }
+
+    particles[index] = particle;
+}
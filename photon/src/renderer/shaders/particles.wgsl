@@ -0,0 +1,164 @@
+struct Particle {
+    position: vec3<f32>,
+    age: f32,
+    velocity: vec3<f32>,
+    lifetime: f32,
+}
+
+struct Emitter {
+    origin: vec3<f32>,
+    spawn_count: u32,
+    direction: vec3<f32>,
+    spread: f32,
+    gravity: vec3<f32>,
+    drag: f32,
+    start_color: vec4<f32>,
+    end_color: vec4<f32>,
+    speed: vec2<f32>,
+    lifetime: vec2<f32>,
+    size: vec2<f32>,
+    delta_t: f32,
+    seed: u32,
+    collision: u32,
+    restitution: f32,
+    friction: f32,
+    kill_on_collision: u32,
+    collider_count: u32,
+    thickness: f32,
+    max_particles: u32,
+    radius: f32,
+    distortion: f32,
+}
+
+struct ParticleOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) corner: vec2<f32>,
+}
+
+@group(1) @binding(0)
+var<uniform> emitter: Emitter;
+
+@group(1) @binding(1)
+var<storage, read> particles: array<Particle>;
+
+// Camera facing quads, two triangles per particle
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) instance_index: u32,
+) -> ParticleOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+
+    var output: ParticleOutput;
+    let particle = particles[instance_index];
+
+    if particle.age >= particle.lifetime {
+        // Dead particles are collapsed out of view
+        output.clip_position = vec4<f32>(0.0, 0.0, -1.0, 1.0);
+        return output;
+    }
+
+    let life = clamp(particle.age / max(particle.lifetime, 0.0001), 0.0, 1.0);
+    let corner = corners[vertex_index];
+
+    let to_camera = normalize(frame.view_position.xyz - particle.position);
+    var helper = vec3<f32>(0.0, 1.0, 0.0);
+    if abs(to_camera.y) > 0.99 {
+        helper = vec3<f32>(1.0, 0.0, 0.0);
+    }
+    let right = normalize(cross(helper, to_camera));
+    let up = cross(to_camera, right);
+
+    let size = mix(emitter.size.x, emitter.size.y, life) * 0.5;
+    let position = particle.position + (right * corner.x + up * corner.y) * size;
+
+    output.clip_position = frame.view_proj * vec4<f32>(position, 1.0);
+    output.color = mix(emitter.start_color, emitter.end_color, life);
+    output.corner = corner;
+    return output;
+}
+
+@fragment
+fn fs_main(in: ParticleOutput) -> @location(0) vec4<f32> {
+    // Soft round particles
+    let falloff = 1.0 - smoothstep(0.5, 1.0, length(in.corner));
+    return vec4<f32>(in.color.rgb, in.color.a * falloff);
+}
+
+// Pushes the scene away from the center of the particle, as glass or a droplet would
+@fragment
+fn fs_distortion(in: ParticleOutput) -> @location(0) vec4<f32> {
+    let distance = length(in.corner);
+    let falloff = 1.0 - smoothstep(0.5, 1.0, distance);
+    let offset = vec2<f32>(in.corner.x, -in.corner.y) * falloff * in.color.a * emitter.distortion;
+    return vec4<f32>(offset, 0.0, 0.0);
+}
+
+struct MeshVertex {
+    @location(0) position: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+}
+
+struct MeshParticleOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) to_camera: vec3<f32>,
+}
+
+// One instance of the mesh per particle, its Y axis turned along the velocity
+@vertex
+fn vs_mesh(
+    vertex: MeshVertex,
+    @builtin(instance_index) instance_index: u32,
+) -> MeshParticleOutput {
+    var output: MeshParticleOutput;
+    let particle = particles[instance_index];
+
+    if particle.age >= particle.lifetime {
+        output.clip_position = vec4<f32>(0.0, 0.0, -1.0, 1.0);
+        return output;
+    }
+
+    let life = clamp(particle.age / max(particle.lifetime, 0.0001), 0.0, 1.0);
+
+    var up = vec3<f32>(0.0, 1.0, 0.0);
+    if dot(particle.velocity, particle.velocity) > 0.000001 {
+        up = normalize(particle.velocity);
+    }
+    var helper = vec3<f32>(0.0, 0.0, 1.0);
+    if abs(up.z) > 0.99 {
+        helper = vec3<f32>(1.0, 0.0, 0.0);
+    }
+    let right = normalize(cross(up, helper));
+    let forward = cross(right, up);
+    let rotation = mat3x3<f32>(right, up, forward);
+
+    let size = mix(emitter.size.x, emitter.size.y, life);
+    let position = particle.position + rotation * vertex.position * size;
+
+    output.clip_position = frame.view_proj * vec4<f32>(position, 1.0);
+    output.color = mix(emitter.start_color, emitter.end_color, life);
+    output.normal = rotation * vertex.normal;
+    output.to_camera = frame.view_position.xyz - position;
+    return output;
+}
+
+@fragment
+fn fs_mesh(in: MeshParticleOutput) -> @location(0) vec4<f32> {
+    // Particles are not lit by the scene, shade them by how much they face the camera
+    var facing = 1.0;
+    if dot(in.normal, in.normal) > 0.000001 {
+        facing = max(dot(normalize(in.normal), normalize(in.to_camera)), 0.0);
+    }
+    return vec4<f32>(in.color.rgb * (0.4 + 0.6 * facing), in.color.a);
+}
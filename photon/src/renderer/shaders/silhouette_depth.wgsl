@@ -0,0 +1,17 @@
+// Copies the depth of the scene into the depth stencil buffer of the silhouette pass
+
+@group(0) @binding(0)
+var scene_depth: texture_depth_2d;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    // Create a full-screen triangle
+    let x = f32((vertex_index << 1) & 2);
+    let y = f32(vertex_index & 2);
+    return vec4<f32>(x * 2.0 - 1.0, y * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @builtin(frag_depth) f32 {
+    return textureLoad(scene_depth, vec2<i32>(position.xy), 0);
+}
@@ -0,0 +1,42 @@
+// Helpers for sampling a texture array built with `TextureArrayBuilder`,
+// bound as `texture_2d_array<f32>` at binding 0 and its sampler at binding 1.
+// Fragment stages only, the other stages have to pick a mip level themselves.
+
+// Samples one layer of the array
+fn sample_layer(layers: texture_2d_array<f32>, layer_sampler: sampler, uv: vec2<f32>, layer: u32) -> vec4<f32> {
+    return textureSample(layers, layer_sampler, uv, layer);
+}
+
+// Blends four layers by their weights, the weights don't need to add up to one
+fn splat_layers(
+    layers: texture_2d_array<f32>,
+    layer_sampler: sampler,
+    uv: vec2<f32>,
+    indices: vec4<u32>,
+    weights: vec4<f32>,
+) -> vec4<f32> {
+    let total = max(dot(weights, vec4<f32>(1.0)), 1e-5);
+    let normalized = weights / total;
+
+    // Every layer is sampled, branching on the weights would break the mip level derivatives
+    return textureSample(layers, layer_sampler, uv, indices.x) * normalized.x
+        + textureSample(layers, layer_sampler, uv, indices.y) * normalized.y
+        + textureSample(layers, layer_sampler, uv, indices.z) * normalized.z
+        + textureSample(layers, layer_sampler, uv, indices.w) * normalized.w;
+}
+
+// Blends the first four layers by the channels of a splat map
+fn splat_map(
+    layers: texture_2d_array<f32>,
+    layer_sampler: sampler,
+    uv: vec2<f32>,
+    splat: vec4<f32>,
+) -> vec4<f32> {
+    return splat_layers(layers, layer_sampler, uv, vec4<u32>(0u, 1u, 2u, 3u), splat);
+}
+
+// Samples a tile of a tilemap, `tile_uv` counts in tiles so each whole unit is one tile.
+// The mip level comes from the unwrapped coordinates, so there are no seams between tiles.
+fn sample_tile(layers: texture_2d_array<f32>, layer_sampler: sampler, tile_uv: vec2<f32>, tile: u32) -> vec4<f32> {
+    return textureSampleGrad(layers, layer_sampler, fract(tile_uv), tile, dpdx(tile_uv), dpdy(tile_uv));
+}
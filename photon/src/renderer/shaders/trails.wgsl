@@ -0,0 +1,87 @@
+struct TrailPoint {
+    position: vec3<f32>,
+    age: f32,
+}
+
+struct Trail {
+    start_color: vec4<f32>,
+    end_color: vec4<f32>,
+    width: vec2<f32>,
+    lifetime: f32,
+    point_count: u32,
+}
+
+@group(1) @binding(0)
+var<uniform> trail: Trail;
+
+@group(1) @binding(1)
+var<storage, read> points: array<TrailPoint>;
+
+struct TrailOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) side: f32,
+}
+
+// Direction the ribbon runs in at a point, smoothed over its neighbours
+fn tangent(index: u32) -> vec3<f32> {
+    let last = trail.point_count - 1u;
+    let before = points[select(index - 1u, 0u, index == 0u)].position;
+    let after = points[min(index + 1u, last)].position;
+    let direction = after - before;
+
+    if dot(direction, direction) < 0.000001 {
+        return vec3<f32>(0.0, 1.0, 0.0);
+    }
+    return normalize(direction);
+}
+
+// Two triangles per segment between recorded points, widened towards the camera
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> TrailOutput {
+    // Which end of the segment and which side of the ribbon each corner is on
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+    );
+
+    var output: TrailOutput;
+    let segment = vertex_index / 6u;
+
+    if trail.point_count < 2u || segment + 1u >= trail.point_count {
+        output.clip_position = vec4<f32>(0.0, 0.0, -1.0, 1.0);
+        return output;
+    }
+
+    let corner = corners[vertex_index % 6u];
+    let index = segment + u32(corner.x);
+    let point = points[index];
+
+    let life = clamp(point.age / max(trail.lifetime, 0.0001), 0.0, 1.0);
+
+    let to_camera = normalize(frame.view_position.xyz - point.position);
+    var side = cross(tangent(index), to_camera);
+    if dot(side, side) < 0.000001 {
+        side = vec3<f32>(1.0, 0.0, 0.0);
+    }
+    side = normalize(side);
+
+    let width = mix(trail.width.x, trail.width.y, life) * 0.5;
+    let position = point.position + side * corner.y * width;
+
+    output.clip_position = frame.view_proj * vec4<f32>(position, 1.0);
+    output.color = mix(trail.start_color, trail.end_color, life);
+    output.side = corner.y;
+    return output;
+}
+
+@fragment
+fn fs_main(in: TrailOutput) -> @location(0) vec4<f32> {
+    // Soften the edges of the ribbon
+    let falloff = 1.0 - smoothstep(0.7, 1.0, abs(in.side));
+    return vec4<f32>(in.color.rgb, in.color.a * falloff);
+}
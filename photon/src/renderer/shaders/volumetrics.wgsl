@@ -0,0 +1,160 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+struct Light {
+    position: vec3<f32>,
+    normal: vec3<f32>,
+    color: vec3<f32>,
+    intensity: f32,
+}
+
+struct Fog {
+    color: vec3<f32>,
+    density: f32,
+    anisotropy: f32,
+    max_distance: f32,
+    steps: u32,
+    shadow_steps: u32,
+    max_lights: u32,
+}
+
+const LIGHT_BIND_GROUP: u32 = 1;
+const G_BUFFER_BIND_GROUP: u32 = 2;
+const FOG_BIND_GROUP: u32 = 3;
+
+const PI: f32 = 3.14159265;
+
+// Distance a surface must be in front of a sample to shadow it, hides self shadowing
+const SHADOW_BIAS: f32 = 0.05;
+
+@group(LIGHT_BIND_GROUP) @binding(0)
+var<storage, read> lights: array<Light>;
+
+@group(LIGHT_BIND_GROUP) @binding(1)
+var<uniform> lights_len: u32;
+
+@group(G_BUFFER_BIND_GROUP) @binding(1)
+var position_texture: texture_2d<f32>;
+
+@group(FOG_BIND_GROUP) @binding(0)
+var<uniform> fog: Fog;
+
+// Henyey-Greenstein phase function, how much light scatters towards the camera
+fn phase(cos_theta: f32, g: f32) -> f32 {
+    let g2 = g * g;
+    return (1.0 - g2) / (4.0 * PI * pow(1.0 + g2 - 2.0 * g * cos_theta, 1.5));
+}
+
+// Noise that offsets the samples of neighbouring pixels, trading banding for grain
+fn interleaved_gradient_noise(pixel: vec2<f32>) -> f32 {
+    return fract(52.9829189 * fract(dot(pixel, vec2<f32>(0.06711056, 0.00583715))));
+}
+
+// Marches from a sample towards a light through the G-buffer, anything drawn
+// in front of a step hides it from the camera and is taken to block the light
+fn light_visibility(sample_position: vec3<f32>, light_position: vec3<f32>) -> f32 {
+    let dimensions = vec2<f32>(textureDimensions(position_texture));
+
+    for (var step: u32 = 1u; step <= fog.shadow_steps; step++) {
+        let point = mix(
+            sample_position,
+            light_position,
+            f32(step) / f32(fog.shadow_steps + 1u),
+        );
+
+        let clip = frame.view_proj * vec4<f32>(point, 1.0);
+        if (clip.w <= 0.0) {
+            continue;
+        }
+
+        let ndc = clip.xyz / clip.w;
+        if (any(abs(ndc.xy) > vec2<f32>(1.0))) {
+            continue;
+        }
+
+        let uv = vec2<f32>(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+        let pixel = vec2<i32>(min(uv * dimensions, dimensions - vec2<f32>(1.0)));
+        let surface = textureLoad(position_texture, pixel, 0);
+
+        let point_distance = distance(frame.view_position.xyz, point);
+        let surface_distance = distance(frame.view_position.xyz, surface.xyz);
+        if (surface.w != 0.0 && surface_distance < point_distance - SHADOW_BIAS) {
+            return 0.0;
+        }
+    }
+
+    return 1.0;
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var output: VertexOutput;
+
+    // Create a full-screen triangle
+    let x = f32((vertex_index << 1) & 2);
+    let y = f32(vertex_index & 2);
+    output.position = vec4<f32>(x * 2.0 - 1.0, y * 2.0 - 1.0, 0.0, 1.0);
+    output.uv = vec2<f32>(x, 1.0 - y);
+
+    return output;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let dimensions = vec2<f32>(textureDimensions(position_texture));
+    let pixel = vec2<i32>(min(input.uv * dimensions, dimensions - vec2<f32>(1.0)));
+    let surface = textureLoad(position_texture, pixel, 0);
+
+    let origin = frame.view_position.xyz;
+
+    // March to the surface drawn here, or into the distance where nothing was drawn
+    var ray_length = fog.max_distance;
+    var direction: vec3<f32>;
+    if (surface.w != 0.0) {
+        direction = normalize(surface.xyz - origin);
+        ray_length = min(distance(surface.xyz, origin), fog.max_distance);
+    } else {
+        let ndc = vec2<f32>(input.uv.x * 2.0 - 1.0, 1.0 - input.uv.y * 2.0);
+        let far = frame.inverse_view_proj * vec4<f32>(ndc, 1.0, 1.0);
+        direction = normalize(far.xyz / far.w - origin);
+    }
+
+    let steps = max(fog.steps, 1u);
+    let step_length = ray_length / f32(steps);
+    let step_transmittance = exp(-fog.density * step_length);
+    let jitter = interleaved_gradient_noise(input.position.xy);
+    let num_lights = min(lights_len, fog.max_lights);
+
+    var transmittance = 1.0;
+    var scattered = vec3<f32>(0.0);
+
+    for (var step: u32 = 0u; step < steps; step++) {
+        let sample_position = origin + direction * (f32(step) + jitter) * step_length;
+
+        var in_scattered = vec3<f32>(0.0);
+        for (var i: u32 = 0u; i < num_lights; i++) {
+            let light = lights[i];
+
+            let to_light = light.position - sample_position;
+            let light_distance_squared = dot(to_light, to_light);
+            let light_direction = to_light * inverseSqrt(max(light_distance_squared, 1e-6));
+
+            let visibility = light_visibility(sample_position, light.position);
+            let falloff = light.intensity / (1.0 + light_distance_squared);
+
+            in_scattered += light.color
+                * falloff
+                * visibility
+                * phase(dot(-direction, -light_direction), fog.anisotropy);
+        }
+
+        // Light scattered towards the camera at this step, dimmed by the fog in front of it
+        scattered += in_scattered * fog.color * fog.density * step_length * transmittance;
+        transmittance *= step_transmittance;
+    }
+
+    // Blended over the lit scene, which the fog dims by its transmittance
+    return vec4<f32>(scattered, 1.0 - transmittance);
+}
@@ -0,0 +1,419 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use gpu_controller::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BlendState, Buffered, ColorTargetState,
+    ColorWrites, CommandEncoder, CompareFunction, DepthBiasState, DepthStencilState, Extent3d,
+    Face, FragmentState, FrontFace, GpuController, Instance, LoadOp, MultisampleState, Operations,
+    PipelineCache, PipelineCompilationOptions, PipelineLayout, PipelineLayoutDescriptor,
+    PolygonMode, PrimitiveState, PrimitiveTopology, QuantizedVertex, RenderPass,
+    RenderPassColorAttachment, RenderPassDepthStencilAttachment, RenderPassDescriptor,
+    RenderPipeline, RenderPipelineDescriptor, ShaderModule, ShaderStages, StencilFaceState,
+    StencilOperation, StencilState, StoreOp, Texture, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor,
+    TextureViewDimension, Vertex, VertexBufferLayout, VertexEncoding, VertexState,
+};
+use log::error;
+
+use crate::camera::PhotonCamera;
+
+use super::{CAMERA_BIND_GROUP, GeometryPass};
+
+const SCENE_DEPTH_BIND_GROUP: u32 = 0;
+
+/// Format of the depth stencil buffer of the silhouette pass
+const SILHOUETTE_DEPTH_FORMAT: TextureFormat = TextureFormat::Depth24PlusStencil8;
+
+// Stencil value of the pixels a silhouetted object is in front at
+const VISIBLE_STENCIL: u32 = 1;
+
+// Stencil value of the pixels nothing was tinted at yet
+const UNTINTED_STENCIL: u32 = 0;
+
+/// The draws of the silhouetted objects within the silhouette pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum SilhouetteStage {
+    /// Marks the pixels a silhouetted object is in front at, so an object
+    /// is never tinted where it only hides behind itself
+    Mask,
+    /// Tints the fragments hidden behind the scene, once per pixel
+    Tint,
+}
+
+/// Tinted silhouettes of objects hidden behind other geometry, for players
+/// and selected units seen through walls.
+///
+/// The depth of the scene is copied into a depth stencil buffer of its own.
+/// The objects drawn in `GeometryPass::Silhouettes` are then drawn twice over
+/// the lit scene, first marking the stencil where they are in front of the
+/// scene, then tinting wherever they are behind it and unmarked.
+pub(crate) struct SilhouetteRenderer {
+    gpu_controller: Arc<GpuController>,
+    pipelines: PipelineCache<(SilhouetteStage, VertexEncoding)>,
+    depth_pipeline: RenderPipeline,
+    depth_layout: BindGroupLayout,
+    depth_bind_group: BindGroup,
+    depth_stencil_texture: Texture,
+    enabled: bool,
+}
+
+impl SilhouetteRenderer {
+    /// Creates the silhouette renderer
+    ///
+    /// # Arguments
+    /// * `gpu_controller` - The gpu controller of the renderer
+    /// * `geometry_shader_module` - The shader the geometry is drawn with
+    /// * `geometry_pipeline_layouts` - The layouts of full precision and quantized geometry
+    /// * `color_format` - The format of the scene the silhouettes are drawn over
+    /// * `scene_depth` - The depth of the geometry, as `Depth32Float`
+    pub(crate) fn new(
+        gpu_controller: Arc<GpuController>,
+        geometry_shader_module: &ShaderModule,
+        (geometry_pipeline_layout, quantized_pipeline_layout): (&PipelineLayout, &PipelineLayout),
+        color_format: TextureFormat,
+        scene_depth: &Texture,
+    ) -> Result<Self> {
+        let depth_layout = gpu_controller.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Silhouette Depth Bind Group Layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Depth,
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+
+        let depth_pipeline_layout =
+            gpu_controller.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("Silhouette Depth Pipeline Layout"),
+                bind_group_layouts: &[&depth_layout],
+                push_constant_ranges: &[],
+            });
+
+        let depth_module =
+            gpu_controller.create_shader(include_str!("shaders/silhouette_depth.wgsl"));
+
+        // Overwrites the whole buffer, so the stencil starts out cleared everywhere
+        let depth_pipeline = gpu_controller.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Silhouette Depth Pipeline"),
+            cache: None,
+            multiview: None,
+            layout: Some(&depth_pipeline_layout),
+            vertex: VertexState {
+                module: &depth_module,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &depth_module,
+                entry_point: Some("fs_main"),
+                targets: &[],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(DepthStencilState {
+                format: SILHOUETTE_DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: CompareFunction::Always,
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default(),
+            }),
+            multisample: MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        });
+
+        // Silhouettes are rare, so the variants compile the first time an object draws one
+        let pipelines = {
+            let gpu_controller = gpu_controller.clone();
+            let geometry_shader_module = geometry_shader_module.clone();
+            let geometry_pipeline_layout = geometry_pipeline_layout.clone();
+            let quantized_pipeline_layout = quantized_pipeline_layout.clone();
+
+            PipelineCache::new(
+                "Silhouette Renderer",
+                move |&(stage, encoding): &(SilhouetteStage, VertexEncoding)| {
+                    let (layout, vertex_buffer, entry_point) = match encoding {
+                        VertexEncoding::Full => {
+                            (&geometry_pipeline_layout, Vertex::desc(), "vs_main")
+                        }
+                        VertexEncoding::Quantized => (
+                            &quantized_pipeline_layout,
+                            QuantizedVertex::desc(),
+                            "vs_quantized",
+                        ),
+                    };
+
+                    Ok(create_silhouette_pipeline(
+                        &gpu_controller,
+                        &geometry_shader_module,
+                        &format!("Silhouette {:?} {:?} Pipeline", encoding, stage),
+                        layout,
+                        (entry_point, stage),
+                        vertex_buffer,
+                        color_format,
+                    ))
+                },
+            )
+        };
+
+        let size = gpu_controller.read_surface_config(|config| Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        })?;
+        let depth_stencil_texture = Self::create_depth_stencil_texture(&gpu_controller, size);
+        let depth_bind_group =
+            Self::create_depth_bind_group(&gpu_controller, &depth_layout, scene_depth);
+
+        Ok(Self {
+            gpu_controller,
+            pipelines,
+            depth_pipeline,
+            depth_layout,
+            depth_bind_group,
+            depth_stencil_texture,
+            enabled: false,
+        })
+    }
+
+    fn create_depth_stencil_texture(gpu_controller: &GpuController, size: Extent3d) -> Texture {
+        gpu_controller.create_texture(&TextureDescriptor {
+            label: Some("Silhouette Depth Stencil"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: SILHOUETTE_DEPTH_FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        })
+    }
+
+    fn create_depth_bind_group(
+        gpu_controller: &GpuController,
+        layout: &BindGroupLayout,
+        scene_depth: &Texture,
+    ) -> BindGroup {
+        gpu_controller.create_bind_group(&BindGroupDescriptor {
+            label: Some("Silhouette Depth Bind Group"),
+            layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(
+                    &scene_depth.create_view(&TextureViewDescriptor::default()),
+                ),
+            }],
+        })
+    }
+
+    /// Sets whether the pass runs, it is skipped while no object draws a silhouette
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Draws the silhouettes of the hidden parts of objects over the scene
+    ///
+    /// # Arguments
+    /// * `encoder` - The encoder of the camera's frame
+    /// * `scene_view` - The lit scene the silhouettes are blended over
+    /// * `camera` - The camera the scene is seen through
+    /// * `geometry_callback` - Draws the objects with silhouettes in `GeometryPass::Silhouettes`
+    pub(crate) fn render<C, F>(
+        &self,
+        encoder: &mut CommandEncoder,
+        scene_view: &TextureView,
+        camera: &C,
+        geometry_callback: &mut F,
+    ) where
+        C: PhotonCamera,
+        F: FnMut(&mut RenderPass, GeometryPass, VertexEncoding),
+    {
+        if !self.enabled {
+            return;
+        }
+
+        let depth_stencil_view = self
+            .depth_stencil_texture
+            .create_view(&TextureViewDescriptor::default());
+
+        // Depth Copy Pass
+        {
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("Silhouette Depth Pass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                    view: &depth_stencil_view,
+                    depth_ops: Some(Operations {
+                        load: LoadOp::Clear(1.0),
+                        store: StoreOp::Store,
+                    }),
+                    stencil_ops: Some(Operations {
+                        load: LoadOp::Clear(UNTINTED_STENCIL),
+                        store: StoreOp::Store,
+                    }),
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+            render_pass.set_pipeline(&self.depth_pipeline);
+            render_pass.set_bind_group(SCENE_DEPTH_BIND_GROUP, &self.depth_bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+
+        // Silhouette Pass
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Silhouette Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: scene_view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: &depth_stencil_view,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Discard,
+                }),
+                stencil_ops: Some(Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Discard,
+                }),
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_bind_group(CAMERA_BIND_GROUP, camera.bind_group(), &[]);
+
+        for (stage, stencil_reference) in [
+            (SilhouetteStage::Mask, VISIBLE_STENCIL),
+            (SilhouetteStage::Tint, UNTINTED_STENCIL),
+        ] {
+            render_pass.set_stencil_reference(stencil_reference);
+
+            for encoding in [VertexEncoding::Full, VertexEncoding::Quantized] {
+                match self.pipelines.get(&(stage, encoding)) {
+                    Ok(pipeline) => {
+                        render_pass.set_pipeline(&pipeline);
+                        geometry_callback(&mut render_pass, GeometryPass::Silhouettes, encoding);
+                    }
+                    Err(err) => error!(
+                        "Failed to compile the {:?} {:?} silhouette pipeline: {}",
+                        encoding, stage, err
+                    ),
+                }
+            }
+        }
+    }
+
+    pub(crate) fn resize(&mut self, size: Extent3d, scene_depth: &Texture) {
+        self.depth_stencil_texture = Self::create_depth_stencil_texture(&self.gpu_controller, size);
+        self.depth_bind_group =
+            Self::create_depth_bind_group(&self.gpu_controller, &self.depth_layout, scene_depth);
+    }
+}
+
+fn create_silhouette_pipeline(
+    gpu_controller: &GpuController,
+    shader_module: &ShaderModule,
+    label: &str,
+    layout: &PipelineLayout,
+    (vertex_entry_point, stage): (&str, SilhouetteStage),
+    vertex_buffer: VertexBufferLayout,
+    color_format: TextureFormat,
+) -> RenderPipeline {
+    let (depth_compare, stencil_face, write_mask) = match stage {
+        // Surfaces in front of the scene match its depth exactly, the vertex math is the same
+        SilhouetteStage::Mask => (
+            CompareFunction::LessEqual,
+            StencilFaceState {
+                compare: CompareFunction::Always,
+                fail_op: StencilOperation::Keep,
+                depth_fail_op: StencilOperation::Keep,
+                pass_op: StencilOperation::Replace,
+            },
+            ColorWrites::empty(),
+        ),
+        // Incrementing keeps overlapping fragments from blending over the same pixel twice
+        SilhouetteStage::Tint => (
+            CompareFunction::Greater,
+            StencilFaceState {
+                compare: CompareFunction::Equal,
+                fail_op: StencilOperation::Keep,
+                depth_fail_op: StencilOperation::Keep,
+                pass_op: StencilOperation::IncrementClamp,
+            },
+            ColorWrites::ALL,
+        ),
+    };
+
+    gpu_controller.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some(label),
+        cache: None,
+        multiview: None,
+        layout: Some(layout),
+        vertex: VertexState {
+            module: shader_module,
+            entry_point: Some(vertex_entry_point),
+            buffers: &[vertex_buffer, Instance::desc()],
+            compilation_options: PipelineCompilationOptions::default(),
+        },
+        fragment: Some(FragmentState {
+            module: shader_module,
+            entry_point: Some("fs_silhouette"),
+            targets: &[Some(ColorTargetState {
+                format: color_format,
+                blend: Some(BlendState::ALPHA_BLENDING),
+                write_mask,
+            })],
+            compilation_options: PipelineCompilationOptions::default(),
+        }),
+        primitive: PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: FrontFace::Ccw,
+            cull_mode: Some(Face::Back),
+            polygon_mode: PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(DepthStencilState {
+            format: SILHOUETTE_DEPTH_FORMAT,
+            depth_write_enabled: false,
+            depth_compare,
+            stencil: StencilState {
+                front: stencil_face,
+                back: stencil_face,
+                read_mask: !0,
+                write_mask: !0,
+            },
+            bias: DepthBiasState::default(),
+        }),
+        multisample: MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+    })
+}
@@ -0,0 +1,349 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Result;
+use gpu_controller::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, BlendComponent, BlendFactor, BlendOperation, BlendState,
+    Buffer, BufferBindingType, BufferDescriptor, BufferInitDescriptor, BufferUsages,
+    ColorTargetState, ColorWrites, CommandEncoder, CompareFunction, DepthBiasState,
+    DepthStencilState, FragmentState, FrontFace, GpuController, LoadOp, MultisampleState,
+    Operations, PipelineCompilationOptions, PipelineLayoutDescriptor, PolygonMode, PrimitiveState,
+    PrimitiveTopology, RenderPassColorAttachment, RenderPassDepthStencilAttachment,
+    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, ShaderStages, StencilState,
+    StoreOp, TextureFormat, TextureView, VertexState,
+};
+
+use crate::camera::PhotonCamera;
+
+use super::CAMERA_BIND_GROUP;
+use super::draw_order::{DrawOrder, sort_draws};
+use super::frame::create_frame_shader;
+
+const TRAIL_BIND_GROUP: u32 = 1;
+
+/// How a trail looks over the life of its points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrailSettings {
+    /// Most points recorded at once, older points are dropped past this
+    pub max_points: u32,
+    /// Seconds a point stays in the trail
+    pub lifetime: f32,
+    /// Width of the ribbon at a new point and at a point about to be dropped
+    pub width: (f32, f32),
+    /// Color of the ribbon at a new point, blended to the end color over its life
+    pub start_color: [f32; 4],
+    pub end_color: [f32; 4],
+}
+
+impl Default for TrailSettings {
+    fn default() -> Self {
+        Self {
+            max_points: 64,
+            lifetime: 0.5,
+            width: (0.2, 0.0),
+            start_color: [1.0; 4],
+            end_color: [1.0, 1.0, 1.0, 0.0],
+        }
+    }
+}
+
+/// A recorded point of a trail, as laid out in the trails shader
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TrailPoint {
+    pub position: [f32; 3],
+    /// Seconds since the point was recorded
+    pub age: f32,
+}
+
+// As laid out in the trails shader
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct TrailUniform {
+    start_color: [f32; 4],
+    end_color: [f32; 4],
+    width: [f32; 2],
+    lifetime: f32,
+    point_count: u32,
+}
+
+struct GpuTrail {
+    max_points: u32,
+    point_count: u32,
+    order: DrawOrder,
+    // Newest point of the trail, trails are sorted by it
+    head: [f32; 3],
+    trail_buffer: Buffer,
+    point_buffer: Buffer,
+    bind_group: BindGroup,
+}
+
+/// Draws trails as camera facing ribbons through their recorded points.
+///
+/// Only the points are uploaded, the vertex shader builds two triangles for
+/// every segment between them, turning each towards the camera.
+pub(crate) struct TrailRenderer {
+    gpu_controller: Arc<GpuController>,
+    pipeline: RenderPipeline,
+    trail_layout: BindGroupLayout,
+    trails: HashMap<u64, GpuTrail>,
+}
+
+impl TrailRenderer {
+    pub(crate) fn new(gpu_controller: Arc<GpuController>) -> Result<Self> {
+        let trail_layout = gpu_controller.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Trail Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::VERTEX,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::VERTEX,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = gpu_controller.read_layouts(|layouts| {
+            gpu_controller.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("Trail Pipeline Layout"),
+                bind_group_layouts: &[&layouts["Camera"], &trail_layout],
+                push_constant_ranges: &[],
+            })
+        })?;
+
+        let shader = create_frame_shader(&gpu_controller, include_str!("shaders/trails.wgsl"));
+
+        let pipeline = gpu_controller.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Trail Pipeline"),
+            cache: None,
+            multiview: None,
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(ColorTargetState {
+                    format: gpu_controller.read_surface_config(|config| config.format)?,
+                    blend: Some(BlendState {
+                        color: BlendComponent {
+                            src_factor: BlendFactor::SrcAlpha,
+                            dst_factor: BlendFactor::OneMinusSrcAlpha,
+                            operation: BlendOperation::Add,
+                        },
+                        alpha: BlendComponent::OVER,
+                    }),
+                    write_mask: ColorWrites::ALL,
+                })],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            // Ribbons twist, so both of their faces are drawn
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(DepthStencilState {
+                format: TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                depth_compare: CompareFunction::Less,
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default(),
+            }),
+            multisample: MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        });
+
+        Ok(Self {
+            gpu_controller,
+            pipeline,
+            trail_layout,
+            trails: HashMap::new(),
+        })
+    }
+
+    /// Adds a trail or replaces the points of an existing one, recreating its
+    /// buffers if it records a different number of points
+    ///
+    /// # Arguments
+    /// * `id` - Identifies the trail between frames
+    /// * `settings` - How the trail looks
+    /// * `points` - The points of the trail from oldest to newest, only the newest `max_points` are drawn
+    pub(crate) fn update_trail(
+        &mut self,
+        id: u64,
+        settings: &TrailSettings,
+        points: &[TrailPoint],
+    ) {
+        let recreate = self
+            .trails
+            .get(&id)
+            .is_none_or(|trail| trail.max_points != settings.max_points);
+        if recreate {
+            let mut trail = self.create_trail(settings.max_points);
+            if let Some(previous) = self.trails.get(&id) {
+                trail.order = previous.order;
+            }
+            self.trails.insert(id, trail);
+        }
+
+        let Some(trail) = self.trails.get_mut(&id) else {
+            return;
+        };
+
+        let points = &points[points.len().saturating_sub(settings.max_points as usize)..];
+        trail.point_count = points.len() as u32;
+        if let Some(head) = points.last() {
+            trail.head = head.position;
+        }
+
+        let uniform = TrailUniform {
+            start_color: settings.start_color,
+            end_color: settings.end_color,
+            width: [settings.width.0, settings.width.1],
+            lifetime: settings.lifetime,
+            point_count: trail.point_count,
+        };
+
+        self.gpu_controller
+            .write_buffer(&trail.trail_buffer, 0, bytemuck::cast_slice(&[uniform]));
+        if !points.is_empty() {
+            self.gpu_controller
+                .write_buffer(&trail.point_buffer, 0, bytemuck::cast_slice(points));
+        }
+    }
+
+    /// Sets where a trail is drawn among the other trails, see `DrawOrder`
+    pub(crate) fn set_order(&mut self, id: u64, order: DrawOrder) {
+        if let Some(trail) = self.trails.get_mut(&id) {
+            trail.order = order;
+        }
+    }
+
+    /// Removes the trails whose id the closure returns false for
+    pub(crate) fn retain_trails<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(u64) -> bool,
+    {
+        self.trails.retain(|id, _| keep(*id));
+    }
+
+    fn create_trail(&self, max_points: u32) -> GpuTrail {
+        let uniform: TrailUniform = bytemuck::Zeroable::zeroed();
+
+        let trail_buffer = self
+            .gpu_controller
+            .create_buffer_init(&BufferInitDescriptor {
+                label: Some("Trail Buffer"),
+                contents: bytemuck::cast_slice(&[uniform]),
+                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            });
+
+        let point_buffer = self.gpu_controller.create_buffer(&BufferDescriptor {
+            label: Some("Trail Point Buffer"),
+            size: (max_points.max(1) as usize * size_of::<TrailPoint>()) as u64,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = self.gpu_controller.create_bind_group(&BindGroupDescriptor {
+            label: Some("Trail Bind Group"),
+            layout: &self.trail_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: trail_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: point_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        GpuTrail {
+            max_points,
+            point_count: 0,
+            order: DrawOrder::default(),
+            head: [0.0; 3],
+            trail_buffer,
+            point_buffer,
+            bind_group,
+        }
+    }
+
+    /// Draws every trail over the color in their `DrawOrder`, hidden by the geometry
+    pub(crate) fn render<C>(
+        &self,
+        encoder: &mut CommandEncoder,
+        view: &TextureView,
+        depth_view: &TextureView,
+        camera: &C,
+    ) where
+        C: PhotonCamera,
+    {
+        if self.trails.values().all(|trail| trail.point_count < 2) {
+            return;
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Trail Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(CAMERA_BIND_GROUP, camera.bind_group(), &[]);
+
+        let trails = self
+            .trails
+            .values()
+            .filter(|trail| trail.point_count >= 2)
+            .map(|trail| (trail.order, trail.head, trail));
+        for trail in sort_draws(trails, camera.position()) {
+            render_pass.set_bind_group(TRAIL_BIND_GROUP, &trail.bind_group, &[]);
+            render_pass.draw(0..(trail.point_count - 1) * 6, 0..1);
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use gpu_controller::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, BlendComponent, BlendFactor, BlendOperation, BlendState,
+    Buffer, BufferBindingType, BufferInitDescriptor, BufferUsages, ColorTargetState, ColorWrites,
+    CommandEncoder, FragmentState, FrontFace, GpuController, LoadOp, MultisampleState, Operations,
+    PipelineCompilationOptions, PipelineLayoutDescriptor, PolygonMode, PrimitiveState,
+    PrimitiveTopology, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
+    RenderPipelineDescriptor, ShaderStages, StoreOp, TextureView, VertexState,
+};
+
+use crate::camera::PhotonCamera;
+
+use super::frame::create_frame_shader;
+use super::{CAMERA_BIND_GROUP, LIGHTS_BIND_GROUP};
+
+const G_BUFFER_BIND_GROUP: u32 = 2;
+const FOG_BIND_GROUP: u32 = 3;
+
+/// Participating media lights scatter through, as laid out in the volumetrics shader.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct FogSettings {
+    pub(crate) color: [f32; 3],
+    pub(crate) density: f32,
+    pub(crate) anisotropy: f32,
+    pub(crate) max_distance: f32,
+    steps: u32,
+    shadow_steps: u32,
+    max_lights: u32,
+    _padding: [u32; 3],
+}
+
+impl FogSettings {
+    /// # Arguments
+    /// * `color` - How much of each color the fog scatters
+    /// * `density` - How much light the fog scatters and absorbs per unit
+    /// * `anisotropy` - From -1 to 1, how much light scatters forward, making shafts brighter looking towards a light
+    /// * `max_distance` - How far from the camera the fog is marched
+    /// * `steps` - Samples taken along every view ray
+    /// * `shadow_steps` - Samples taken towards every light to find what blocks it
+    /// * `max_lights` - The number of lights that scatter through the fog
+    pub fn new(
+        color: [f32; 3],
+        density: f32,
+        anisotropy: f32,
+        max_distance: f32,
+        steps: u32,
+        shadow_steps: u32,
+        max_lights: u32,
+    ) -> Self {
+        Self {
+            color,
+            density,
+            anisotropy: anisotropy.clamp(-0.99, 0.99),
+            max_distance,
+            steps,
+            shadow_steps,
+            max_lights,
+            _padding: [0; 3],
+        }
+    }
+}
+
+/// Scatters the lights through fog after the lighting pass.
+///
+/// Every pixel marches along its view ray through the fog, adding the light
+/// scattered towards the camera and dimming the scene behind it. Whether a
+/// light reaches a sample is found by marching towards the light through
+/// the G-buffer, so geometry on screen casts light shafts.
+pub(crate) struct VolumetricRenderer {
+    gpu_controller: Arc<GpuController>,
+    pipeline: RenderPipeline,
+    fog_buffer: Buffer,
+    fog_bind_group: BindGroup,
+    enabled: bool,
+}
+
+impl VolumetricRenderer {
+    pub(crate) fn new(gpu_controller: Arc<GpuController>) -> Result<Self> {
+        let fog_bind_group_layout =
+            gpu_controller.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("Fog Bind Group Layout"),
+                entries: &[BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+
+        let fog_buffer = gpu_controller.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Fog Buffer"),
+            contents: bytemuck::cast_slice(&[FogSettings::new([1.0; 3], 0.0, 0.0, 0.0, 0, 0, 0)]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let fog_bind_group = gpu_controller.create_bind_group(&BindGroupDescriptor {
+            label: Some("Fog Bind Group"),
+            layout: &fog_bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: fog_buffer.as_entire_binding(),
+            }],
+        });
+
+        let pipeline_layout = gpu_controller.read_layouts(|layouts| {
+            gpu_controller.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("Volumetrics Pipeline Layout"),
+                bind_group_layouts: &[
+                    &layouts["Camera"],
+                    &layouts["Lights"],
+                    &layouts["G-Buffer"],
+                    &fog_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            })
+        })?;
+
+        let shader_module =
+            create_frame_shader(&gpu_controller, include_str!("shaders/volumetrics.wgsl"));
+
+        let pipeline = gpu_controller.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Volumetrics Pipeline"),
+            cache: None,
+            multiview: None,
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader_module,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &shader_module,
+                entry_point: Some("fs_main"),
+                targets: &[Some(ColorTargetState {
+                    format: gpu_controller.read_surface_config(|config| config.format)?,
+                    // Adds the scattered light and dims the scene by the fog's transmittance
+                    blend: Some(BlendState {
+                        color: BlendComponent {
+                            src_factor: BlendFactor::One,
+                            dst_factor: BlendFactor::OneMinusSrcAlpha,
+                            operation: BlendOperation::Add,
+                        },
+                        alpha: BlendComponent::OVER,
+                    }),
+                    write_mask: ColorWrites::ALL,
+                })],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        });
+
+        Ok(Self {
+            gpu_controller,
+            pipeline,
+            fog_buffer,
+            fog_bind_group,
+            enabled: false,
+        })
+    }
+
+    /// Sets the fog the lights scatter through, `None` turns the pass off
+    pub(crate) fn set_fog(&mut self, fog: Option<FogSettings>) {
+        self.enabled = fog.is_some();
+
+        if let Some(fog) = fog {
+            self.gpu_controller
+                .write_buffer(&self.fog_buffer, 0, bytemuck::cast_slice(&[fog]));
+        }
+    }
+
+    pub(crate) fn render<C>(
+        &self,
+        encoder: &mut CommandEncoder,
+        view: &TextureView,
+        camera: &C,
+        lights_bind_group: &BindGroup,
+        g_buffer_bind_group: &BindGroup,
+    ) where
+        C: PhotonCamera,
+    {
+        if !self.enabled {
+            return;
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Volumetrics Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(CAMERA_BIND_GROUP, camera.bind_group(), &[]);
+        render_pass.set_bind_group(LIGHTS_BIND_GROUP, lights_bind_group, &[]);
+        render_pass.set_bind_group(G_BUFFER_BIND_GROUP, g_buffer_bind_group, &[]);
+        render_pass.set_bind_group(FOG_BIND_GROUP, &self.fog_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}